@@ -15,7 +15,7 @@ def outer(x):
 
     print(inner.__name__)
     print(inner.__qualname__)
-    print(inner.__closure__)
+    print(len(inner.__closure__))
 
     return inner(5)
 
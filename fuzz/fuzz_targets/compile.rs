@@ -0,0 +1,12 @@
+//! Feeds arbitrary text to the compiler, which must report syntax errors
+//! rather than panic.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxython::compiler::Compiler;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = std::str::from_utf8(data) {
+        let _ = Compiler::compile_with_errors(source, "<fuzz>");
+    }
+});
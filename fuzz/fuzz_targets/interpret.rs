@@ -0,0 +1,38 @@
+//! Runs arbitrary bytes as bytecode, which the VM must reject with a runtime
+//! error rather than panic.
+//!
+//! The first byte picks how many constants of a few fixed kinds the chunk's
+//! pool holds; the rest is the code. Inputs containing `OpLoop` are skipped
+//! since a backward jump can loop forever.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxython::bytecode::{Chunk, OpCode};
+use oxython::object::ObjectType;
+use oxython::vm::VM;
+use std::rc::Rc;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&constant_count, code)) = data.split_first() else {
+        return;
+    };
+    if code.contains(&(OpCode::OpLoop as u8)) {
+        return;
+    }
+
+    let mut chunk = Chunk::new();
+    chunk.code = code.to_vec();
+    for i in 0..constant_count {
+        let value = match i % 5 {
+            0 => ObjectType::Integer(i64::from(i) - 128),
+            1 => ObjectType::Float(f64::from(i) / 7.0),
+            2 => ObjectType::String(format!("c{}", i)),
+            3 => ObjectType::Boolean(i % 2 == 0),
+            _ => ObjectType::Nil,
+        };
+        chunk.constants.push(Rc::new(value));
+    }
+
+    let mut vm = VM::new();
+    let _ = vm.interpret(chunk);
+});
@@ -0,0 +1,20 @@
+//! Compiles arbitrary text and runs whatever compiles, which must end in a
+//! result or an error rather than panic or overflow the stack. The VM is
+//! given budgets so that loops and deep recursion stop.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use oxython::compiler::Compiler;
+use oxython::vm::VM;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Some(chunk) = Compiler::compile(source) else {
+        return;
+    };
+    let mut vm = VM::with_limits(5_000_000, 100_000, 200);
+    vm.capture_output();
+    let _ = vm.interpret(chunk);
+});
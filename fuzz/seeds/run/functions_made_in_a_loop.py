@@ -0,0 +1,10 @@
+def mk():
+    def inner():
+        return 1
+    return inner
+
+
+i = 0
+while i < 200000:
+    h = mk()
+    i = i + 1
@@ -0,0 +1,9 @@
+fn main() {
+    match oxython::lsp::run() {
+        Ok(code) => std::process::exit(code),
+        Err(error) => {
+            eprintln!("oxython-lsp: {}", error);
+            std::process::exit(74); // Standard exit code for I/O error
+        }
+    }
+}
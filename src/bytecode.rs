@@ -1,4 +1,6 @@
-use crate::object::Object;
+use crate::object::{Object, ObjectType};
+use std::collections::HashMap;
+use std::fmt;
 
 /// Represents the instructions that our Virtual Machine will execute.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -56,7 +58,7 @@ pub enum OpCode {
     OpMultiply,
     /// Builds a list representing a numeric range.
     OpRange,
-    /// Compares two numeric values and pushes a boolean indicating if left < right.
+    /// Compares two values and pushes a boolean indicating if left < right.
     OpLess,
     /// Extracts a slice from a list or string.
     OpSlice,
@@ -66,8 +68,6 @@ pub enum OpCode {
     OpEqual,
     /// Converts a value into a list.
     OpToList,
-    /// Zips multiple iterables together.
-    OpZip,
     /// Converts a string to lowercase.
     OpStrLower,
     /// Returns whether a string is alphanumeric.
@@ -96,11 +96,134 @@ pub enum OpCode {
     OpInherit,
     /// Returns the type of an object as a string.
     OpType,
+    /// Pops an iterable and a list, and pushes the list extended with the iterable's items.
+    OpListExtend,
+    /// Pops a list of arguments and calls the callee beneath it with them (used for `f(*args)`).
+    OpCallEx,
+    /// Removes a global variable binding, unshadowing any builtin of the same name.
+    OpDeleteGlobal,
+    /// Pops two values and floor-divides the first by the second.
+    OpFloorDivide,
+    /// Converts a string to uppercase.
+    OpStrUpper,
+    /// Compares two values and pushes a boolean indicating if left <= right.
+    OpLessEqual,
+    /// Compares two values and pushes a boolean indicating if left > right.
+    OpGreater,
+    /// Compares two values and pushes a boolean indicating if left >= right.
+    OpGreaterEqual,
+    /// Pops `reverse`, `key` and a list, and pushes the list sorted (used for `list.sort()`).
+    OpListSort,
+    /// Pops a tuple of keyword names and calls the callee beneath the given number of
+    /// arguments, the last of which were passed by those names.
+    OpCallKw,
+    /// Pops the given number of values and pushes a list of them, first pushed first.
+    OpBuildList,
+    /// Pops the given number of key/value pairs and pushes a dict of them.
+    OpBuildDict,
+    /// Pops an iterable and pushes its items for the given number of targets, the
+    /// first target's value on top. A nonzero second operand is one more than the
+    /// position of a starred target, which receives a list of the leftover items.
+    OpUnpack,
+    /// Pops a value and pushes whether it is falsy (used for `not`).
+    OpNot,
+    /// Pops two values and pushes whether they are the same object (used for `is`).
+    OpIs,
+    /// Pushes the module named by the given constant, loading it on first use.
+    OpImport,
+    /// Pops a module and pushes its attribute named by the given constant.
+    OpImportFrom,
+    /// Installs an exception handler at the given forward offset. An exception
+    /// raised before the matching OpPopTry restores the stack height, becomes
+    /// the exception being handled and jumps there.
+    OpSetupExcept,
+    /// Installs a finally handler at the given forward offset. An exception
+    /// raised before the matching OpPopTry restores the stack height, is held
+    /// until OpEndFinally and jumps there.
+    OpSetupFinally,
+    /// Removes the most recently installed handler.
+    OpPopTry,
+    /// Pops an exception instance or class and raises it.
+    OpRaise,
+    /// Raises the exception being handled again.
+    OpReraise,
+    /// Pops an exception class (or a tuple of them) and pushes whether the
+    /// exception being handled is an instance of it.
+    OpExceptMatch,
+    /// Pushes the exception being handled (used for `except ... as name`).
+    OpGetCaught,
+    /// Finishes handling the current exception.
+    OpEndExcept,
+    /// Enters a finally block normally, with no exception held.
+    OpEnterFinally,
+    /// Leaves a finally block, raising the exception held on entry, if any.
+    OpEndFinally,
+    /// Leaves a finally block early through break, continue or return,
+    /// discarding any exception held on entry.
+    OpPopFinally,
+    /// Formats a value for an f-string replacement field. The operand's
+    /// `FORMAT_CONVERSION` bits pick `!s`, `!r` or `!a`, and
+    /// `FORMAT_WITH_SPEC` says a format spec string is on the stack above
+    /// the value.
+    OpFormatValue,
+    /// Pops a generator expression's `<genexpr>` function and the iterable
+    /// it loops over, and pushes a generator object that runs it.
+    OpMakeGenerator,
+    /// Suspends the generator running in the current frame, handing the
+    /// value on top of the stack to whatever resumed it.
+    OpYield,
+    /// Pops a slice's step, end and start, a value and a list, and pushes the
+    /// list with that slice replaced by the value's items (used for
+    /// `name[start:end] = value`).
+    OpSetSlice,
+    /// Pops an index/key and a collection, and pushes the collection without
+    /// that item (used for `del name[index]`).
+    OpDeleteIndex,
+    /// Pops a slice's step, end and start and a list, and pushes the list
+    /// without that slice (used for `del name[start:end]`).
+    OpDeleteSlice,
+    /// `OpGetLocal` with a two-byte slot, for locals past the 256th.
+    OpGetLocalLong,
+    /// `OpSetLocal` with a two-byte slot.
+    OpSetLocalLong,
+    /// `OpGetUpvalue` with a two-byte index, for upvalues past the 256th.
+    OpGetUpvalueLong,
+    /// `OpSetUpvalue` with a two-byte index.
+    OpSetUpvalueLong,
+    /// Pops a list of keyword mappings and a list of arguments and calls the
+    /// callee beneath them with both (used for `f(*args, **kwargs)`).
+    OpCallExKw,
+    /// `OpConstant` with a two-byte index, for constants past the 256th.
+    OpConstantLong,
+    /// `OpDefineGlobal` with a two-byte constant index.
+    OpDefineGlobalLong,
+    /// `OpGetGlobal` with a two-byte constant index.
+    OpGetGlobalLong,
+    /// `OpSetGlobal` with a two-byte constant index.
+    OpSetGlobalLong,
+    /// `OpDeleteGlobal` with a two-byte constant index.
+    OpDeleteGlobalLong,
+    /// `OpMakeFunction` with a two-byte constant index.
+    OpMakeFunctionLong,
+    /// `OpGetAttr` with a two-byte constant index.
+    OpGetAttrLong,
+    /// `OpSetAttr` with a two-byte constant index.
+    OpSetAttrLong,
+    /// `OpImport` with a two-byte constant index.
+    OpImportLong,
+    /// `OpImportFrom` with a two-byte constant index.
+    OpImportFromLong,
+    /// Pops a dict passed to `eval()` or `exec()` and pushes it with the
+    /// names the call just bound in it (used for `exec(source, namespace)`).
+    OpEvaluatedNamespace,
+    /// Pops a number and pushes it negated (used for unary minus).
+    OpNegate,
 }
 
-impl From<u8> for OpCode {
-    fn from(byte: u8) -> Self {
-        match byte {
+impl OpCode {
+    /// Decodes an opcode byte, returning `None` for a byte that names no opcode.
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        let opcode = match byte {
             0 => OpCode::OpConstant,
             1 => OpCode::OpAdd,
             2 => OpCode::OpDivide,
@@ -132,30 +255,234 @@ impl From<u8> for OpCode {
             28 => OpCode::OpModulo,
             29 => OpCode::OpEqual,
             30 => OpCode::OpToList,
-            31 => OpCode::OpZip,
-            32 => OpCode::OpStrLower,
-            33 => OpCode::OpStrIsAlnum,
-            34 => OpCode::OpStrJoin,
-            35 => OpCode::OpCall,
-            36 => OpCode::OpGetLocal,
-            37 => OpCode::OpSetLocal,
-            38 => OpCode::OpGetUpvalue,
-            39 => OpCode::OpSetUpvalue,
-            40 => OpCode::OpMakeFunction,
-            41 => OpCode::OpMakeClass,
-            42 => OpCode::OpGetAttr,
-            43 => OpCode::OpSetAttr,
-            44 => OpCode::OpInherit,
-            45 => OpCode::OpType,
-            _ => panic!("Invalid opcode: {}", byte),
+            31 => OpCode::OpStrLower,
+            32 => OpCode::OpStrIsAlnum,
+            33 => OpCode::OpStrJoin,
+            34 => OpCode::OpCall,
+            35 => OpCode::OpGetLocal,
+            36 => OpCode::OpSetLocal,
+            37 => OpCode::OpGetUpvalue,
+            38 => OpCode::OpSetUpvalue,
+            39 => OpCode::OpMakeFunction,
+            40 => OpCode::OpMakeClass,
+            41 => OpCode::OpGetAttr,
+            42 => OpCode::OpSetAttr,
+            43 => OpCode::OpInherit,
+            44 => OpCode::OpType,
+            45 => OpCode::OpListExtend,
+            46 => OpCode::OpCallEx,
+            47 => OpCode::OpDeleteGlobal,
+            48 => OpCode::OpFloorDivide,
+            49 => OpCode::OpStrUpper,
+            50 => OpCode::OpLessEqual,
+            51 => OpCode::OpGreater,
+            52 => OpCode::OpGreaterEqual,
+            53 => OpCode::OpListSort,
+            54 => OpCode::OpCallKw,
+            55 => OpCode::OpBuildList,
+            56 => OpCode::OpBuildDict,
+            57 => OpCode::OpUnpack,
+            58 => OpCode::OpNot,
+            59 => OpCode::OpIs,
+            60 => OpCode::OpImport,
+            61 => OpCode::OpImportFrom,
+            62 => OpCode::OpSetupExcept,
+            63 => OpCode::OpSetupFinally,
+            64 => OpCode::OpPopTry,
+            65 => OpCode::OpRaise,
+            66 => OpCode::OpReraise,
+            67 => OpCode::OpExceptMatch,
+            68 => OpCode::OpGetCaught,
+            69 => OpCode::OpEndExcept,
+            70 => OpCode::OpEnterFinally,
+            71 => OpCode::OpEndFinally,
+            72 => OpCode::OpPopFinally,
+            73 => OpCode::OpFormatValue,
+            74 => OpCode::OpMakeGenerator,
+            75 => OpCode::OpYield,
+            76 => OpCode::OpSetSlice,
+            77 => OpCode::OpDeleteIndex,
+            78 => OpCode::OpDeleteSlice,
+            79 => OpCode::OpGetLocalLong,
+            80 => OpCode::OpSetLocalLong,
+            81 => OpCode::OpGetUpvalueLong,
+            82 => OpCode::OpSetUpvalueLong,
+            83 => OpCode::OpCallExKw,
+            84 => OpCode::OpConstantLong,
+            85 => OpCode::OpDefineGlobalLong,
+            86 => OpCode::OpGetGlobalLong,
+            87 => OpCode::OpSetGlobalLong,
+            88 => OpCode::OpDeleteGlobalLong,
+            89 => OpCode::OpMakeFunctionLong,
+            90 => OpCode::OpGetAttrLong,
+            91 => OpCode::OpSetAttrLong,
+            92 => OpCode::OpImportLong,
+            93 => OpCode::OpImportFromLong,
+            94 => OpCode::OpEvaluatedNamespace,
+            95 => OpCode::OpNegate,
+            _ => return None,
+        };
+        Some(opcode)
+    }
+
+    /// Returns the number of operand bytes that follow this opcode.
+    pub fn operand_width(self) -> usize {
+        match self {
+            OpCode::OpConstant
+            | OpCode::OpDefineGlobal
+            | OpCode::OpGetGlobal
+            | OpCode::OpSetGlobal
+            | OpCode::OpDeleteGlobal
+            | OpCode::OpCall
+            | OpCode::OpCallKw
+            | OpCode::OpBuildList
+            | OpCode::OpBuildDict
+            | OpCode::OpGetLocal
+            | OpCode::OpSetLocal
+            | OpCode::OpGetUpvalue
+            | OpCode::OpSetUpvalue
+            | OpCode::OpMakeFunction
+            | OpCode::OpMakeClass
+            | OpCode::OpGetAttr
+            | OpCode::OpSetAttr
+            | OpCode::OpImport
+            | OpCode::OpImportFrom
+            | OpCode::OpFormatValue => 1,
+            OpCode::OpUnpack
+            | OpCode::OpGetLocalLong
+            | OpCode::OpSetLocalLong
+            | OpCode::OpGetUpvalueLong
+            | OpCode::OpSetUpvalueLong => 2,
+            _ if self.takes_long_constant() => 2,
+            _ if self.is_jump() => JUMP_WIDTH,
+            _ => 0,
         }
     }
+
+    /// Whether this opcode's operand is a jump distance, counted in bytes
+    /// from the end of the instruction: backwards for `OpLoop`, forwards
+    /// for the rest.
+    pub fn is_jump(self) -> bool {
+        matches!(
+            self,
+            OpCode::OpIterNext
+                | OpCode::OpLoop
+                | OpCode::OpJumpIfFalse
+                | OpCode::OpJump
+                | OpCode::OpSetupExcept
+                | OpCode::OpSetupFinally
+        )
+    }
+
+    /// The form of a local, upvalue or constant opcode whose operand is two
+    /// bytes wide, or the opcode itself for any other.
+    pub fn long_form(self) -> Self {
+        match self {
+            OpCode::OpGetLocal => OpCode::OpGetLocalLong,
+            OpCode::OpSetLocal => OpCode::OpSetLocalLong,
+            OpCode::OpGetUpvalue => OpCode::OpGetUpvalueLong,
+            OpCode::OpSetUpvalue => OpCode::OpSetUpvalueLong,
+            OpCode::OpConstant => OpCode::OpConstantLong,
+            OpCode::OpDefineGlobal => OpCode::OpDefineGlobalLong,
+            OpCode::OpGetGlobal => OpCode::OpGetGlobalLong,
+            OpCode::OpSetGlobal => OpCode::OpSetGlobalLong,
+            OpCode::OpDeleteGlobal => OpCode::OpDeleteGlobalLong,
+            OpCode::OpMakeFunction => OpCode::OpMakeFunctionLong,
+            OpCode::OpGetAttr => OpCode::OpGetAttrLong,
+            OpCode::OpSetAttr => OpCode::OpSetAttrLong,
+            OpCode::OpImport => OpCode::OpImportLong,
+            OpCode::OpImportFrom => OpCode::OpImportFromLong,
+            other => other,
+        }
+    }
+
+    /// Whether this opcode's operand is an index into the constant pool.
+    pub fn takes_constant(self) -> bool {
+        matches!(
+            self,
+            OpCode::OpConstant
+                | OpCode::OpDefineGlobal
+                | OpCode::OpGetGlobal
+                | OpCode::OpSetGlobal
+                | OpCode::OpDeleteGlobal
+                | OpCode::OpMakeFunction
+                | OpCode::OpGetAttr
+                | OpCode::OpSetAttr
+                | OpCode::OpImport
+                | OpCode::OpImportFrom
+        ) || self.takes_long_constant()
+    }
+
+    /// Whether this opcode is the long form of one taking a constant, its
+    /// index two bytes wide.
+    pub fn takes_long_constant(self) -> bool {
+        matches!(
+            self,
+            OpCode::OpConstantLong
+                | OpCode::OpDefineGlobalLong
+                | OpCode::OpGetGlobalLong
+                | OpCode::OpSetGlobalLong
+                | OpCode::OpDeleteGlobalLong
+                | OpCode::OpMakeFunctionLong
+                | OpCode::OpGetAttrLong
+                | OpCode::OpSetAttrLong
+                | OpCode::OpImportLong
+                | OpCode::OpImportFromLong
+        )
+    }
 }
+
+impl From<u8> for OpCode {
+    /// Decodes an opcode byte the compiler emitted.
+    ///
+    /// Panics on an invalid byte; use `OpCode::from_byte` for untrusted bytecode.
+    fn from(byte: u8) -> Self {
+        Self::from_byte(byte).unwrap_or_else(|| panic!("Invalid opcode: {}", byte))
+    }
+}
+
+/// Bytes in a jump's operand, which holds its distance big-endian.
+pub const JUMP_WIDTH: usize = 4;
+
+/// Decodes a jump's operand bytes into its distance.
+pub fn jump_distance(operands: &[u8]) -> usize {
+    operands
+        .iter()
+        .fold(0, |distance, &byte| distance << 8 | usize::from(byte))
+}
+
+/// The highest local slot or upvalue index an instruction can address,
+/// which the long forms of the local and upvalue opcodes hold in two bytes.
+pub const MAX_SLOT: usize = u16::MAX as usize;
+
+/// The highest constant index an instruction can address, which the long
+/// forms of the constant opcodes hold in two bytes.
+pub const MAX_CONSTANT: usize = u16::MAX as usize;
+
+/// Decodes a local, upvalue or constant instruction's operand bytes, one or
+/// two of them big-endian, into its slot or constant index.
+pub fn slot_index(operands: &[u8]) -> usize {
+    jump_distance(operands)
+}
+
+/// `OpFormatValue` operand bits holding the conversion applied before
+/// formatting: 0 for none, then `!s`, `!r` and `!a`.
+pub const FORMAT_CONVERSION: u8 = 0b11;
+/// `OpFormatValue` operand bit set when the field has a format spec.
+pub const FORMAT_WITH_SPEC: u8 = 0b100;
+
 /// A chunk of bytecode representing a compiled script or function.
 #[derive(Clone, Debug)]
 pub struct Chunk {
     pub code: Vec<u8>,
     pub constants: Vec<Object>,
+    /// (offset, line) pairs in offset order: the code from each offset up to
+    /// the next pair's was compiled from that source line
+    pub lines: Vec<(usize, usize)>,
+    /// (offset, depth) pairs in offset order: a statement starts at each
+    /// offset, with `depth` values that enclosing for loops keep on the stack
+    /// above the frame's locals
+    pub statements: Vec<(usize, usize)>,
 }
 
 impl Chunk {
@@ -163,8 +490,194 @@ impl Chunk {
         Chunk {
             code: Vec::new(),
             constants: Vec::new(),
+            lines: Vec::new(),
+            statements: Vec::new(),
+        }
+    }
+
+    /// Records that the code emitted from now on comes from `line`.
+    pub fn add_line(&mut self, line: usize) {
+        if self.lines.last().is_some_and(|&(_, last)| last == line) {
+            return;
+        }
+        self.restart_line(line);
+    }
+
+    /// Like `add_line`, but starts a new entry even when the current one is
+    /// for the same line, so that a loop jumping back here reaches the line
+    /// again.
+    pub fn restart_line(&mut self, line: usize) {
+        let offset = self.code.len();
+        match self.lines.last_mut() {
+            Some(last) if last.0 == offset => last.1 = line,
+            _ => self.lines.push((offset, line)),
+        }
+    }
+
+    /// Records that a statement starts here, with `depth` loop values on the
+    /// stack. A statement that emitted no code is replaced by the next one.
+    pub fn add_statement(&mut self, depth: usize) {
+        let offset = self.code.len();
+        match self.statements.last_mut() {
+            Some(last) if last.0 == offset => last.1 = depth,
+            _ => self.statements.push((offset, depth)),
+        }
+    }
+
+    /// The loop values on the stack when the statement starting at `offset`
+    /// runs, if one starts there.
+    pub fn statement_depth_at(&self, offset: usize) -> Option<usize> {
+        self.statements
+            .binary_search_by_key(&offset, |&(start, _)| start)
+            .ok()
+            .map(|index| self.statements[index].1)
+    }
+
+    /// The source line the instruction at `offset` was compiled from.
+    pub fn line_at(&self, offset: usize) -> Option<usize> {
+        let index = self.lines.partition_point(|&(start, _)| start <= offset);
+        index.checked_sub(1).map(|index| self.lines[index].1)
+    }
+
+    /// Iterates over the chunk's instructions in order.
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions {
+            code: &self.code,
+            offset: 0,
         }
     }
+
+    /// The line whose entry in the line table starts at `offset`, if any.
+    pub fn line_starting_at(&self, offset: usize) -> Option<usize> {
+        self.lines
+            .binary_search_by_key(&offset, |&(start, _)| start)
+            .ok()
+            .map(|index| self.lines[index].1)
+    }
+
+    /// Iterates over the instructions that refer to a constant, yielding
+    /// each one's offset and the index of the constant.
+    pub fn constant_references(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.instructions()
+            .filter(|&(_, opcode, _)| opcode.takes_constant())
+            .map(|(offset, _, operands)| (offset, slot_index(operands)))
+    }
+
+    /// Whether some instruction refers to each constant, by index.
+    pub fn referenced_constants(&self) -> Vec<bool> {
+        let mut referenced = vec![false; self.constants.len()];
+        for (_, index) in self.constant_references() {
+            if let Some(slot) = referenced.get_mut(index) {
+                *slot = true;
+            }
+        }
+        referenced
+    }
+
+    /// Points the instructions referring to a constant equal to an earlier
+    /// one at the earlier one instead, returning how many constants that
+    /// left unreferenced. Only immutable values are merged: numbers,
+    /// strings, booleans and nil, with floats merged only when their bits
+    /// match, so that `0.0` and `-0.0` stay apart. The pool itself is left
+    /// as it is; `remove_unused_constants` drops what this frees.
+    pub fn deduplicate_constants(&mut self) -> usize {
+        let mut first_of = HashMap::new();
+        let remap: Vec<usize> = self
+            .constants
+            .iter()
+            .enumerate()
+            .map(|(index, constant)| match ConstantKey::of(constant) {
+                Some(key) => *first_of.entry(key).or_insert(index),
+                None => index,
+            })
+            .collect();
+        let merged = remap
+            .iter()
+            .enumerate()
+            .filter(|&(index, &target)| index != target)
+            .count();
+        if merged > 0 {
+            self.remap_constants(&remap);
+        }
+        merged
+    }
+
+    /// Drops the constants no instruction refers to, renumbering the rest
+    /// in their original order, and returns how many were dropped.
+    ///
+    /// Nothing is dropped from a chunk whose code does not decode to the
+    /// end, as the references past the bad byte cannot be seen.
+    pub fn remove_unused_constants(&mut self) -> usize {
+        let decoded = self
+            .instructions()
+            .last()
+            .map_or(0, |(offset, opcode, _)| offset + 1 + opcode.operand_width());
+        if decoded != self.code.len() {
+            return 0;
+        }
+        let referenced = self.referenced_constants();
+        let mut remap = Vec::with_capacity(referenced.len());
+        let mut kept = 0;
+        for &used in &referenced {
+            remap.push(kept);
+            kept += usize::from(used);
+        }
+        let removed = self.constants.len() - kept;
+        if removed > 0 {
+            self.remap_constants(&remap);
+            let mut used = referenced.into_iter();
+            self.constants.retain(|_| used.next().unwrap_or(true));
+        }
+        removed
+    }
+
+    /// Merges duplicate constants and drops unreferenced ones, returning
+    /// how many constants the pool lost.
+    pub fn compact_constants(&mut self) -> usize {
+        self.deduplicate_constants();
+        self.remove_unused_constants()
+    }
+
+    /// Rewrites every constant operand `index` to `remap[index]`, leaving
+    /// operands past the end of the pool alone.
+    fn remap_constants(&mut self, remap: &[usize]) {
+        let references: Vec<(usize, usize)> = self.constant_references().collect();
+        for (offset, index) in references {
+            let Some(&target) = remap.get(index) else {
+                continue;
+            };
+            // Indices only shrink, so the operand keeps its width
+            if OpCode::from(self.code[offset]).takes_long_constant() {
+                self.code[offset + 1..offset + 3].copy_from_slice(&(target as u16).to_be_bytes());
+            } else {
+                self.code[offset + 1] = target as u8;
+            }
+        }
+    }
+}
+
+/// What makes two constants interchangeable: the same immutable value of
+/// the same type.
+#[derive(PartialEq, Eq, Hash)]
+enum ConstantKey<'a> {
+    Integer(i64),
+    Float(u64),
+    String(&'a str),
+    Boolean(bool),
+    Nil,
+}
+
+impl<'a> ConstantKey<'a> {
+    fn of(constant: &'a ObjectType) -> Option<Self> {
+        Some(match constant {
+            ObjectType::Integer(number) => ConstantKey::Integer(*number),
+            ObjectType::Float(number) => ConstantKey::Float(number.to_bits()),
+            ObjectType::String(text) => ConstantKey::String(text),
+            ObjectType::Boolean(flag) => ConstantKey::Boolean(*flag),
+            ObjectType::Nil => ConstantKey::Nil,
+            _ => return None,
+        })
+    }
 }
 
 impl Default for Chunk {
@@ -172,3 +685,89 @@ impl Default for Chunk {
         Self::new()
     }
 }
+
+/// Iterator over a chunk's instructions, yielding each one's offset, opcode
+/// and operand bytes. It stops early at a byte that names no opcode or at
+/// operands cut off by the end of the code.
+#[derive(Clone, Debug)]
+pub struct Instructions<'a> {
+    code: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = (usize, OpCode, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let opcode = OpCode::from_byte(*self.code.get(offset)?)?;
+        let operands = self
+            .code
+            .get(offset + 1..offset + 1 + opcode.operand_width())?;
+        self.offset += 1 + operands.len();
+        Some((offset, opcode, operands))
+    }
+}
+
+/// Disassembles the chunk one instruction per line: the offset, the source
+/// line (`|` when unchanged), the opcode and its decoded operands. Functions
+/// in the constant pool follow, each under its own header.
+impl fmt::Display for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut last_line = None;
+        for (offset, opcode, operands) in self.instructions() {
+            match self.line_at(offset) {
+                Some(line) if last_line != Some(line) => {
+                    write!(f, "{:04} {:4} ", offset, line)?;
+                    last_line = Some(line);
+                }
+                _ => write!(f, "{:04}    | ", offset)?,
+            }
+            writeln!(f, "{}", self.describe(offset, opcode, operands).trim_end())?;
+        }
+        for constant in &self.constants {
+            if let ObjectType::FunctionPrototype(prototype) = &**constant {
+                write!(f, "\n{}", prototype)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Chunk {
+    /// Formats one instruction's opcode and operands for `Display`.
+    fn describe(&self, offset: usize, opcode: OpCode, operands: &[u8]) -> String {
+        let name = format!("{:?}", opcode);
+        match (opcode, operands) {
+            _ if opcode.takes_constant() => {
+                let index = slot_index(operands);
+                match self.constants.get(index).map(|value| &**value) {
+                    Some(ObjectType::String(text)) => {
+                        format!("{:<16} {:3} '{}'", name, index, text)
+                    }
+                    Some(value) => format!("{:<16} {:3} {}", name, index, value),
+                    None => format!("{:<16} {:3} <missing>", name, index),
+                }
+            }
+            _ if opcode.is_jump() => {
+                let jump = jump_distance(operands);
+                let end = offset + 1 + JUMP_WIDTH;
+                let target = match opcode {
+                    OpCode::OpLoop => end.wrapping_sub(jump),
+                    _ => end + jump,
+                };
+                format!("{:<16} {:3} -> {:04}", name, jump, target)
+            }
+            (
+                OpCode::OpGetLocalLong
+                | OpCode::OpSetLocalLong
+                | OpCode::OpGetUpvalueLong
+                | OpCode::OpSetUpvalueLong,
+                _,
+            ) => format!("{:<16} {:3}", name, slot_index(operands)),
+            (_, [operand]) => format!("{:<16} {:3}", name, operand),
+            (_, [first, second]) => format!("{:<16} {:3} {}", name, first, second),
+            _ => name,
+        }
+    }
+}
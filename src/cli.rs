@@ -1,15 +1,42 @@
-use crate::bytecode::OpCode;
+use crate::bytecode::{Chunk, OpCode};
+use crate::compiler::syntax_error::SyntaxError;
 use crate::compiler::Compiler;
+use crate::diagnostic::Diagnostic;
+use crate::formatter;
+use crate::marshal;
 use crate::object::ObjectType;
+use crate::vm::coverage::Coverage;
+use crate::vm::modules::pprint::Layout;
+use crate::vm::modules::warnings::WarningAction;
+use crate::vm::runtime_error::RuntimeError;
 use crate::vm::{InterpretResult, VM};
+use std::collections::hash_map::DefaultHasher;
 use std::env;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io::{self, BufRead, Read, Write};
 #[cfg(unix)]
 use std::mem::MaybeUninit;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 const BANNER: &str = include_str!("banner.txt");
 
+/// Module the script run and the REPL's lines are compiled and run as, as
+/// `__module__` and `__name__` name it.
+const MAIN_MODULE: &str = "__main__";
+
+/// How the REPL shows the value of an expression: its repr, spread over
+/// several lines when wider than the terminal's usual 80 columns.
+const REPL_LAYOUT: Layout = Layout {
+    indent: 1,
+    width: 80,
+    depth: None,
+    sort_dicts: false,
+};
+
 pub fn run_main() -> Result<(), i32> {
     let args: Vec<String> = env::args().skip(1).collect();
     run_main_with_args(&args)
@@ -27,29 +54,303 @@ pub fn handle_args_with_prompt<F>(args: &[String], prompt: F) -> Result<(), i32>
 where
     F: FnOnce(),
 {
-    match args.len() {
-        0 => {
+    if let Some((command, rest)) = args.split_first() {
+        match command.as_str() {
+            "fmt" => return format_files(rest),
+            "check" => return check_files(rest),
+            _ => {}
+        }
+    }
+    let mut options = RunOptions::default();
+    let mut rest = args;
+    while let Some((flag, after)) = rest.split_first().filter(|(arg, _)| arg.starts_with("--")) {
+        rest = after;
+        match flag.as_str() {
+            "--profile" => options.profile = true,
+            "--coverage" => options.coverage = true,
+            "--tail-calls" => options.tail_calls = true,
+            "--cache" => options.cache = true,
+            // The flags taking a value, as `--timeout 5s`
+            "--timeout" => {
+                let Some((timeout, after)) = rest.split_first() else {
+                    return usage_error();
+                };
+                let Some(timeout) = parse_duration(timeout) else {
+                    return usage_error();
+                };
+                options.timeout = Some(timeout);
+                rest = after;
+            }
+            "--path" => {
+                let Some((directory, after)) = rest.split_first() else {
+                    return usage_error();
+                };
+                options.search_path.push(directory.clone());
+                rest = after;
+            }
+            _ => match (error_format(flag), warning_action(flag)) {
+                (Some(json), _) => options.json_errors = json,
+                (_, Some(action)) => options.warnings = action,
+                _ => return usage_error(),
+            },
+        }
+    }
+    match rest {
+        [] if args.is_empty() => {
             prompt();
             Ok(())
         }
-        1 => run_file(&args[0]),
-        _ => {
-            eprintln!("Usage: oxython [script]");
-            Err(64) // Standard exit code for command-line usage error
+        [path] => run_file_with_options(path, options),
+        _ => usage_error(),
+    }
+}
+
+fn usage_error() -> Result<(), i32> {
+    eprintln!(
+        "Usage: oxython [--profile] [--coverage] [--tail-calls] [--cache] [--error-format=json] [--warnings=ignore|error] [--timeout 5s] [--path dir]... [script]"
+    );
+    eprintln!("       oxython fmt [--check] file...");
+    eprintln!("       oxython check [--lint] [--error-format=json] file...");
+    Err(64) // Standard exit code for command-line usage error
+}
+
+/// `oxython fmt [--check] file...`: rewrites each file formatted, or with
+/// `--check` only lists those that are not and fails if there are any.
+fn format_files(args: &[String]) -> Result<(), i32> {
+    let check = args.first().is_some_and(|arg| arg == "--check");
+    let paths = &args[usize::from(check)..];
+    if paths.is_empty() || paths.iter().any(|path| path.starts_with("--")) {
+        return usage_error();
+    }
+    let mut status = Ok(());
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Error reading file '{}': {}", path, e);
+                status = Err(74);
+                continue;
+            }
+        };
+        let formatted = match formatter::format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}: {}", path, error);
+                }
+                status = Err(65);
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("would reformat {}", path);
+            status = status.and(Err(1));
+        } else if let Err(e) = fs::write(path, formatted) {
+            eprintln!("Error writing file '{}': {}", path, e);
+            status = Err(74);
+        } else {
+            println!("reformatted {}", path);
+        }
+    }
+    status
+}
+
+/// Reads an `--error-format=` flag: whether errors are to be reported as
+/// JSON lines, or None if `flag` is not one.
+fn error_format(flag: &str) -> Option<bool> {
+    match flag.strip_prefix("--error-format=")? {
+        "json" => Some(true),
+        "human" => Some(false),
+        _ => None,
+    }
+}
+
+/// Reads a `--warnings=` flag: what to do with the warnings a script raises,
+/// or None if `flag` is not one.
+fn warning_action(flag: &str) -> Option<WarningAction> {
+    WarningAction::from_name(flag.strip_prefix("--warnings=")?)
+}
+
+/// Reads a `--timeout` duration: a number of seconds, or of hours, minutes,
+/// seconds or milliseconds with an `h`, `m`, `s` or `ms` suffix, such as
+/// `5s` or `1.5m`.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text
+        .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let seconds_per_unit = match unit {
+        "" | "s" => 1.0,
+        "ms" => 0.001,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return None,
+    };
+    let seconds = number.parse::<f64>().ok()? * seconds_per_unit;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// `oxython check [--lint] [--error-format=json] file...`: reports the
+/// syntax errors in each file, and with `--lint` the lints too, failing if
+/// there are any. As JSON, every diagnostic goes to stderr.
+fn check_files(args: &[String]) -> Result<(), i32> {
+    let flags = args.iter().take_while(|arg| arg.starts_with("--"));
+    let (mut lint, mut json) = (false, false);
+    for flag in flags.clone() {
+        match (flag.as_str(), error_format(flag)) {
+            ("--lint", _) => lint = true,
+            (_, Some(format)) => json = format,
+            _ => return usage_error(),
+        }
+    }
+    let paths = &args[flags.count()..];
+    if paths.is_empty() || paths.iter().any(|path| path.starts_with("--")) {
+        return usage_error();
+    }
+    let mut status = Ok(());
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Error reading file '{}': {}", path, e);
+                status = Err(74);
+                continue;
+            }
+        };
+        let result = if lint {
+            Compiler::lint(&source, "<script>")
+        } else {
+            Compiler::compile_with_errors(&source, "<script>").map(|_| Vec::new())
+        };
+        match result {
+            Ok(lints) => {
+                for lint in &lints {
+                    if json {
+                        eprintln!("{}", Diagnostic::from_lint(path, lint).to_json());
+                    } else {
+                        println!("{}: {}", path, lint);
+                    }
+                }
+                if !lints.is_empty() {
+                    status = status.and(Err(1));
+                }
+            }
+            Err(errors) => {
+                for error in &errors {
+                    if json {
+                        eprintln!("{}", Diagnostic::from_syntax_error(path, error).to_json());
+                    } else {
+                        eprintln!("{}: {}", path, error);
+                    }
+                }
+                status = Err(65);
+            }
         }
     }
+    status
+}
+
+/// What to report about a script besides its own output.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunOptions {
+    /// Print the instructions and time spent per opcode and per function
+    /// to stderr
+    pub profile: bool,
+    /// Write the lines executed to `lcov.info` beside the script
+    pub coverage: bool,
+    /// Run a function's calls to itself in tail position in its own frame
+    pub tail_calls: bool,
+    /// Reuse the bytecode saved by an earlier run of the same source, and
+    /// save it otherwise
+    pub cache: bool,
+    /// Report syntax and runtime errors as JSON lines rather than text
+    pub json_errors: bool,
+    /// What to do with the warnings the script raises
+    pub warnings: WarningAction,
+    /// Stop the script once it has run this long
+    pub timeout: Option<Duration>,
+    /// Directories searched for modules after the script's own and before
+    /// those in `OXYTHONPATH`
+    pub search_path: Vec<String>,
 }
 
 pub fn run_file(path: &str) -> Result<(), i32> {
+    run_file_with_options(path, RunOptions::default())
+}
+
+/// Runs the script at `path` like `run_file`, then prints a table of the
+/// instructions and time spent per opcode and per function to stderr.
+pub fn run_file_with_profile(path: &str) -> Result<(), i32> {
+    run_file_with_options(
+        path,
+        RunOptions {
+            profile: true,
+            ..RunOptions::default()
+        },
+    )
+}
+
+/// Runs the script at `path` like `run_file`, reporting on it as `options`
+/// asks.
+pub fn run_file_with_options(path: &str, options: RunOptions) -> Result<(), i32> {
     match fs::read_to_string(path) {
         Ok(contents) => {
-            if let Some(chunk) = Compiler::compile(&contents) {
-                let mut vm = VM::new();
-                vm.interpret(chunk);
-                Ok(())
-            } else {
-                eprintln!("Compilation failed.");
-                Err(65) // Standard exit code for data format error
+            match compile_script(&contents, options.cache) {
+                Ok(chunk) => {
+                    let mut vm = VM::new();
+                    vm.set_script_file(path);
+                    vm.set_profiling(options.profile);
+                    vm.set_tail_calls(options.tail_calls);
+                    vm.set_warning_action(options.warnings);
+                    interrupt_on_ctrl_c(&mut vm);
+                    vm.set_timeout(options.timeout);
+                    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+                    vm.set_search_path(search_path(directory, &options.search_path));
+                    let coverage = options.coverage.then(|| Coverage::attach(&mut vm, &chunk));
+                    let result = vm.interpret_module(chunk, MAIN_MODULE);
+                    if let Some(profile) = vm.profile() {
+                        eprint!("{}", profile);
+                    }
+                    if let Some(coverage) = coverage {
+                        write_coverage(path, &coverage)?;
+                    }
+                    match result {
+                        InterpretResult::RuntimeError => {
+                            report_runtime_error(path, vm.last_error(), options.json_errors);
+                            let interrupted = vm
+                                .last_error()
+                                .is_some_and(|error| error.kind == "KeyboardInterrupt");
+                            if interrupted {
+                                Err(130) // What shells report for a process stopped by Ctrl-C
+                            } else {
+                                Err(70) // Standard exit code for internal software error
+                            }
+                        }
+                        InterpretResult::LimitExceeded => {
+                            if let Some(limit) = vm.exceeded_limit() {
+                                eprintln!("{}.", limit);
+                            }
+                            Err(124) // What the timeout command exits with
+                        }
+                        _ => Ok(()),
+                    }
+                }
+                Err(errors) if options.json_errors => {
+                    for error in &errors {
+                        eprintln!("{}", Diagnostic::from_syntax_error(path, error).to_json());
+                    }
+                    Err(65)
+                }
+                Err(errors) => {
+                    for error in errors {
+                        eprintln!("{}", error);
+                    }
+                    eprintln!("Compilation failed.");
+                    Err(65) // Standard exit code for data format error
+                }
             }
         }
         Err(e) => {
@@ -59,6 +360,125 @@ pub fn run_file(path: &str) -> Result<(), i32> {
     }
 }
 
+/// The module search path for a script in `directory`: that directory, then
+/// `extra`, then the directories listed in `OXYTHONPATH`.
+fn search_path(directory: &Path, extra: &[String]) -> Vec<String> {
+    let mut path = vec![directory.display().to_string()];
+    path.extend(extra.iter().cloned());
+    if let Some(listed) = env::var_os("OXYTHONPATH") {
+        path.extend(
+            env::split_paths(&listed)
+                .filter(|directory| !directory.as_os_str().is_empty())
+                .map(|directory| directory.display().to_string()),
+        );
+    }
+    path
+}
+
+/// Set by the SIGINT handler, and read by the VM running a script.
+static INTERRUPT: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Makes Ctrl-C raise KeyboardInterrupt in the script `vm` runs, so its
+/// handlers and finally blocks run, rather than kill the process.
+fn interrupt_on_ctrl_c(vm: &mut VM) {
+    let flag = INTERRUPT.get_or_init(|| {
+        #[cfg(unix)]
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+        Arc::new(AtomicBool::new(false))
+    });
+    flag.store(false, Ordering::Relaxed);
+    vm.set_interrupt_flag(flag.clone());
+}
+
+/// Sets the interrupt flag, or, if the VM has not yet seen the last Ctrl-C,
+/// as when a native function runs long, kills the process as Ctrl-C
+/// normally does.
+#[cfg(unix)]
+extern "C" fn on_sigint(_signal: libc::c_int) {
+    let Some(flag) = INTERRUPT.get() else {
+        return;
+    };
+    if flag.swap(true, Ordering::Relaxed) {
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            libc::raise(libc::SIGINT);
+        }
+    }
+}
+
+/// Prints why the script at `path` stopped, as a traceback or as a JSON
+/// diagnostic.
+fn report_runtime_error(path: &str, error: Option<&RuntimeError>, json: bool) {
+    match (error, json) {
+        (Some(error), false) => eprintln!("{}", error),
+        (None, false) => eprintln!("Runtime error."),
+        (error, true) => {
+            let unknown = RuntimeError::new("RuntimeError", "Runtime error.");
+            let error = error.unwrap_or(&unknown);
+            eprintln!("{}", Diagnostic::from_runtime_error(path, error).to_json());
+        }
+    }
+}
+
+/// Compiles a script, first looking for its bytecode in the cache when
+/// `cached` is set. A cache that cannot be read or written is passed over.
+fn compile_script(source: &str, cached: bool) -> Result<Chunk, Vec<SyntaxError>> {
+    let Some(path) = cached.then(|| cache_path(source)).flatten() else {
+        return Compiler::compile_with_errors(source, MAIN_MODULE);
+    };
+    if let Some(chunk) = fs::read(&path).ok().and_then(|bytes| marshal::load(&bytes)) {
+        return Ok(chunk);
+    }
+    let chunk = Compiler::compile_with_errors(source, MAIN_MODULE)?;
+    if let (Some(bytes), Some(dir)) = (marshal::dump(&chunk), path.parent()) {
+        let _ = fs::create_dir_all(dir).and_then(|_| fs::write(&path, bytes));
+    }
+    Ok(chunk)
+}
+
+/// Where the bytecode for `source` is cached: a file named by a hash of the
+/// source and of the interpreter build, so that rebuilding the interpreter
+/// never reuses bytecode an older compiler wrote. The directory is
+/// `$OXYTHON_CACHE_DIR`, else `oxython` in the user's cache directory.
+fn cache_path(source: &str) -> Option<PathBuf> {
+    let dir = match env::var_os("OXYTHON_CACHE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?
+            .join("oxython"),
+    };
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    let build = env::current_exe().and_then(fs::metadata).ok()?;
+    build.len().hash(&mut hasher);
+    build.modified().ok()?.hash(&mut hasher);
+    source.hash(&mut hasher);
+    Some(dir.join(format!("{:016x}.oxyc", hasher.finish())))
+}
+
+/// Writes the lcov report for the script at `path` to `lcov.info` in the
+/// script's directory.
+fn write_coverage(path: &str, coverage: &Coverage) -> Result<(), i32> {
+    let source = fs::canonicalize(path).unwrap_or_else(|_| Path::new(path).to_path_buf());
+    let report_path = source.with_file_name("lcov.info");
+    let report = coverage.lcov(&source.to_string_lossy());
+    if let Err(e) = fs::write(&report_path, report) {
+        eprintln!(
+            "Error writing coverage report '{}': {}",
+            report_path.display(),
+            e
+        );
+        return Err(74); // Standard exit code for I/O error
+    }
+    Ok(())
+}
+
 pub fn run_prompt() {
     #[cfg(unix)]
     {
@@ -92,6 +512,7 @@ where
     W: Write,
 {
     let mut vm = VM::new();
+    vm.set_search_path(search_path(Path::new(""), &[]));
     writeln!(writer, "{}", BANNER.trim_end())?;
     writeln!(writer)?;
     writeln!(writer, "Welcome to the oxython REPL! (Ctrl+D to exit)")?;
@@ -114,31 +535,40 @@ fn execute_line<W: Write>(vm: &mut VM, line: &str, writer: &mut W) -> io::Result
         return Ok(());
     }
 
-    match Compiler::compile(trimmed) {
-        Some(chunk) => {
+    match Compiler::compile_with_errors(trimmed, MAIN_MODULE) {
+        Ok(chunk) => {
             let has_expression_result = chunk.code.contains(&(OpCode::OpPop as u8));
-            match vm.interpret(chunk) {
+            match vm.interpret_module(chunk, MAIN_MODULE) {
                 InterpretResult::Ok => {
-                    if has_expression_result {
-                        let value = vm.last_popped_stack_elem();
-                        if !matches!(&*value, ObjectType::Nil) {
-                            writeln!(writer, "{}", &*value)?;
-                        }
-                    } else if let Some(value) = vm.peek_stack() {
-                        if !matches!(&*value, ObjectType::Nil) {
-                            writeln!(writer, "{}", &*value)?;
-                        }
+                    let value = if has_expression_result {
+                        Some(vm.last_popped_stack_elem())
+                    } else {
+                        vm.peek_stack()
+                    };
+                    if let Some(value) = value.filter(|value| !matches!(**value, ObjectType::Nil)) {
+                        writeln!(writer, "{}", vm.pformat(&value, &REPL_LAYOUT))?;
                     }
                 }
                 InterpretResult::RuntimeError => {
                     writeln!(writer, "Runtime error.")?;
+                    if let Some(error) = vm.last_error() {
+                        writeln!(writer, "{}", error)?;
+                    }
                 }
                 InterpretResult::CompileError => {
                     writeln!(writer, "Compilation error.")?;
                 }
+                InterpretResult::LimitExceeded => {
+                    if let Some(limit) = vm.exceeded_limit() {
+                        writeln!(writer, "{}.", limit)?;
+                    }
+                }
             }
         }
-        None => {
+        Err(errors) => {
+            for error in errors {
+                writeln!(writer, "{}", error)?;
+            }
             writeln!(writer, "Compilation failed.")?;
         }
     }
@@ -160,6 +590,7 @@ fn run_prompt_interactive() -> io::Result<()> {
     writeln!(output, "Welcome to the oxython REPL! (Ctrl+D to exit)")?;
 
     let mut vm = VM::new();
+    vm.set_search_path(search_path(Path::new(""), &[]));
     let mut history: Vec<String> = Vec::new();
     let mut history_pos: Option<usize> = None;
     let mut saved_input = String::new();
@@ -240,6 +671,24 @@ fn run_prompt_interactive() -> io::Result<()> {
                 writeln!(output)?;
                 break;
             }
+            b'\t' => {
+                let completions = vm.complete(&current_input);
+                let typed = current_input.len() - completions.start;
+                let common = completions.common_prefix();
+                if common.len() > typed {
+                    current_input.truncate(completions.start);
+                    current_input.push_str(common);
+                } else if completions.candidates.len() > 1 {
+                    // Nothing more can be filled in, so list the choices
+                    writeln!(output)?;
+                    writeln!(output, "{}", completions.candidates.join("  "))?;
+                } else {
+                    continue;
+                }
+                history_pos = None;
+                saved_input.clear();
+                redraw_prompt(&mut output, &current_input)?;
+            }
             byte if byte.is_ascii_control() => {}
             byte => {
                 current_input.push(byte as char);
@@ -0,0 +1,83 @@
+//! Compiling several modules at once.
+//!
+//! Compilation is a pure function of the source text, so independent modules
+//! can be compiled side by side and only run one after another. A chunk holds
+//! its constants as `Rc`s unless the `sync` feature is on, so only then is
+//! the work spread over threads; otherwise the modules are compiled in turn.
+//!
+//! Imports use this to compile the source modules a module imports before
+//! its body runs them (see `VM::precompile_imports`).
+
+use super::syntax_error::SyntaxError;
+use super::Compiler;
+use crate::bytecode::Chunk;
+use std::io;
+
+/// The result of compiling one module.
+pub type CompiledModule = Result<Chunk, Vec<SyntaxError>>;
+
+/// Stack given to each compiling thread, matching the main thread's so that
+/// the nesting limit holds just as well there.
+#[cfg(feature = "sync")]
+const THREAD_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+impl Compiler<'_> {
+    /// Compiles each `(module name, source)` pair, returning the results in
+    /// the same order.
+    ///
+    /// # Errors
+    /// Returns the error that stopped a compiling thread from starting.
+    pub fn compile_modules(modules: &[(&str, &str)]) -> io::Result<Vec<CompiledModule>> {
+        #[cfg(feature = "sync")]
+        if modules.len() > 1 {
+            return compile_in_parallel(modules);
+        }
+        Ok(modules
+            .iter()
+            .map(|(name, source)| Compiler::compile_with_errors(source, name))
+            .collect())
+    }
+}
+
+/// Compiles `modules` on a pool of threads, each taking the next module not
+/// yet started until none are left. The threads started are joined before a
+/// failure to start another is returned.
+#[cfg(feature = "sync")]
+fn compile_in_parallel(modules: &[(&str, &str)]) -> io::Result<Vec<CompiledModule>> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    let workers = thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(modules.len());
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<CompiledModule>> = modules.iter().map(|_| None).collect();
+    thread::scope(|scope| -> io::Result<()> {
+        let mut handles = Vec::with_capacity(workers);
+        for _ in 0..workers {
+            let handle = thread::Builder::new()
+                .stack_size(THREAD_STACK_SIZE)
+                .spawn_scoped(scope, || {
+                    let mut compiled = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((name, source)) = modules.get(index) else {
+                            return compiled;
+                        };
+                        compiled.push((index, Compiler::compile_with_errors(source, name)));
+                    }
+                })?;
+            handles.push(handle);
+        }
+        for handle in handles {
+            let compiled = handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, result) in compiled {
+                results[index] = Some(result);
+            }
+        }
+        Ok(())
+    })?;
+    Ok(results.into_iter().flatten().collect())
+}
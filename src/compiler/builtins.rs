@@ -1,261 +1,522 @@
 //! Built-in function call parsing for the compiler.
 //!
-//! This module contains functions for parsing built-in Python functions
-//! like join(), zip(), list(), and f-string literals.
+//! This module contains functions for parsing f-string literals, plus the
+//! peephole fast paths for calls to builtins such as len() and round() that
+//! have a dedicated opcode, and the calls to eval() and exec() that pass
+//! them the caller's locals and store back the dicts they bind names in.
 
-use crate::bytecode::OpCode;
+use crate::bytecode::{OpCode, FORMAT_WITH_SPEC};
 use crate::object::ObjectType;
+use crate::sync::Rc;
 use crate::token::Token;
-use std::rc::Rc;
+use std::collections::HashSet;
+use std::ops::Range;
 
-use super::types::{ComprehensionEnd, FStringSegment};
+/// Builtins with a dedicated opcode, paired with the argument count that opcode expects.
+const FAST_PATH_BUILTINS: [(&str, OpCode, usize); 4] = [
+    ("len", OpCode::OpLen, 1),
+    ("round", OpCode::OpRound, 2),
+    ("range", OpCode::OpRange, 2),
+    ("type", OpCode::OpType, 1),
+];
+
+/// Builtins that run source text, which the compiler passes the locals of
+/// the function calling them.
+const EVALUATION_BUILTINS: [&str; 2] = ["eval", "exec"];
+
+use super::token_stream::TokenStream;
+use super::types::{FStringSegment, VariableTarget};
 
 impl super::Compiler<'_> {
-    /// Parses a str.join() call or join(iterable) comprehension.
-    /// Supports both simple calls and comprehension expressions.
-    #[allow(dead_code)]
-    pub(super) fn parse_join_call(&mut self) -> bool {
-        if self.lexer.next() != Some(Ok(Token::LParen)) {
-            self.had_error = true;
-            return false;
+    /// Returns the fast-path opcode and arity for a call to a builtin that can skip
+    /// the generic OpCall, if the identifier just consumed is one of them.
+    ///
+    /// The fast path is only taken when the source never rebinds the name and the
+    /// call passes exactly the arguments the opcode expects; anything else (e.g.
+    /// `round(x)` or `range(0, 10, 2)`) goes through the native function instead.
+    pub(super) fn fast_builtin_call(&self, name: &str) -> Option<(OpCode, usize)> {
+        let (_, opcode, arity) = FAST_PATH_BUILTINS
+            .iter()
+            .find(|(builtin, _, _)| *builtin == name)?;
+
+        if self.rebound_builtins.contains(name) {
+            return None;
         }
 
-        let arg_start = self.chunk.code.len();
-        if !self.parse_expression() {
-            self.had_error = true;
-            return false;
+        if self.call_argument_count() == Some(*arity) {
+            Some((*opcode, *arity))
+        } else {
+            None
         }
-        let arg_end = self.chunk.code.len();
-        let element_code = self.chunk.code[arg_start..arg_end].to_vec();
-        self.chunk.code.truncate(arg_start);
+    }
 
-        if self.lexer.clone().next() == Some(Ok(Token::For)) {
-            if !self.compile_comprehension(element_code, ComprehensionEnd::RParen) {
+    /// Parses `(arg, ...)` for a fast-path builtin and emits its opcode.
+    pub(super) fn parse_fast_builtin_call(&mut self, opcode: OpCode, arity: usize) -> bool {
+        self.tokens.next(); // consume '('
+        for index in 0..arity {
+            if index > 0 && self.tokens.next() != Some(Ok(Token::Comma)) {
+                self.had_error = true;
                 return false;
             }
-        } else {
-            self.chunk.code.extend_from_slice(&element_code);
-            if self.lexer.next() != Some(Ok(Token::RParen)) {
+            if !self.parse_expression() {
                 self.had_error = true;
                 return false;
             }
         }
+        self.skip_trailing_comma(Token::RParen);
+        if self.tokens.next() != Some(Ok(Token::RParen)) {
+            self.had_error = true;
+            return false;
+        }
+        self.chunk.code.push(opcode as u8);
+        self.parse_postfix(None)
+    }
 
-        self.chunk.code.push(OpCode::OpStrJoin as u8);
-        true
+    /// Returns the number of arguments of a call to `eval()` or `exec()`, if
+    /// the identifier just consumed is one of them, the source never rebinds
+    /// it, no local or enclosing variable shadows it and the call passes one
+    /// to three arguments by position.
+    pub(super) fn evaluation_call(&mut self, name: &str) -> Option<usize> {
+        if !EVALUATION_BUILTINS.contains(&name)
+            || self.rebound_builtins.contains(name)
+            || self.resolve_variable(name) != VariableTarget::Global
+        {
+            return None;
+        }
+        self.call_argument_count()
+            .filter(|count| (1..=3).contains(count))
     }
 
-    /// Parses an f-string literal: f"Hello {name}!"
-    /// Interpolates variables into the string by converting {name} to variable lookups.
-    pub(super) fn parse_f_string_literal(&mut self) -> bool {
-        let template = match self.lexer.next() {
-            Some(Ok(Token::String(template))) => template,
-            _ => {
+    /// Parses `(source, ...)` for `eval()` or `exec()` and emits the call.
+    ///
+    /// Given the source alone inside a function, the call is passed None for
+    /// `globals` and the function's locals for `locals`. A variable passed
+    /// last as `globals` or `locals` is given the dict back with the names
+    /// the code bound in it.
+    pub(super) fn parse_evaluation_call(&mut self, name: &str, arg_count: usize) -> bool {
+        let name_idx = self.name_constant(name);
+        self.emit_constant_operand(OpCode::OpGetGlobal, name_idx);
+        self.tokens.next(); // consume '('
+        let mut namespace = None;
+        for index in 0..arg_count {
+            if index > 0 && self.tokens.next() != Some(Ok(Token::Comma)) {
                 self.had_error = true;
                 return false;
             }
-        };
-
-        let segments = match Self::f_string_segments(&template) {
-            Ok(segments) => segments,
-            Err(_) => {
+            if index > 0 && index == arg_count - 1 {
+                namespace = self.variable_argument();
+            }
+            if !self.parse_expression() {
                 self.had_error = true;
                 return false;
             }
-        };
-
-        if segments.is_empty() {
-            let const_idx = self.add_constant(Rc::new(ObjectType::String(String::new())));
-            self.chunk.code.push(OpCode::OpConstant as u8);
-            self.chunk.code.push(const_idx as u8);
-            return true;
+        }
+        self.skip_trailing_comma(Token::RParen);
+        if self.tokens.next() != Some(Ok(Token::RParen)) {
+            self.had_error = true;
+            return false;
         }
 
-        for (index, segment) in segments.into_iter().enumerate() {
-            match segment {
-                FStringSegment::Literal(text) => {
-                    let const_idx = self.add_constant(Rc::new(ObjectType::String(text)));
-                    self.chunk.code.push(OpCode::OpConstant as u8);
-                    self.chunk.code.push(const_idx as u8);
-                }
-                FStringSegment::Identifier(identifier) => {
-                    let name_idx = self.add_constant(Rc::new(ObjectType::String(identifier)));
-                    self.chunk.code.push(OpCode::OpGetGlobal as u8);
-                    self.chunk.code.push(name_idx as u8);
-                }
-            }
-
-            if index > 0 {
-                self.chunk.code.push(OpCode::OpAdd as u8);
+        let mut passed = arg_count;
+        if arg_count == 1 && !self.function_scopes.is_empty() {
+            self.emit_nil();
+            if !self.emit_locals_dict() {
+                return false;
             }
+            passed = 3;
         }
-
-        true
+        self.chunk.code.push(OpCode::OpCall as u8);
+        self.chunk.code.push(passed as u8);
+
+        if let Some((name_idx, target)) = namespace {
+            // Stack: [result] -> [result, dict] -> [result]
+            self.emit_get_variable(name_idx, target);
+            self.chunk.code.push(OpCode::OpEvaluatedNamespace as u8);
+            self.emit_set_variable(name_idx, target);
+            self.chunk.code.push(OpCode::OpPop as u8);
+        }
+        self.parse_postfix(None)
     }
 
-    /// Parses a list() constructor call: list(iterable)
-    /// Converts an iterable to a list.
-    pub(super) fn parse_list_call(&mut self) -> bool {
-        self.lexer.next(); // consume '('
-
-        if self.lexer.clone().next() == Some(Ok(Token::RParen)) {
-            self.lexer.next(); // consume ')'
-            let empty_list_idx = self.add_constant(Rc::new(ObjectType::List(Vec::new())));
-            self.chunk.code.push(OpCode::OpConstant as u8);
-            self.chunk.code.push(empty_list_idx as u8);
-            return true;
+    /// Resolves the next call argument if it is a variable alone, ending the
+    /// call, returning its name constant and where it is stored.
+    fn variable_argument(&mut self) -> Option<(usize, VariableTarget)> {
+        let Some(Ok(Token::Identifier(name))) = self.tokens.peek() else {
+            return None;
+        };
+        let ends_call = match self.tokens.peek_nth(1) {
+            Some(Ok(Token::RParen)) => true,
+            Some(Ok(Token::Comma)) => self.tokens.peek_nth(2) == Some(Ok(Token::RParen)),
+            _ => false,
+        };
+        if name == "None" || !ends_call {
+            return None;
         }
+        let target = self.resolve_variable(&name);
+        Some((self.variable_name_constant(&name, target), target))
+    }
 
-        if !self.parse_expression() {
-            self.had_error = true;
+    /// Emits a dict of the parameters and locals of the function being
+    /// compiled, by name, as far as an OpBuildDict can gather them.
+    fn emit_locals_dict(&mut self) -> bool {
+        let Some(scope) = self.function_scopes.last() else {
             return false;
+        };
+        let locals: Vec<(String, usize)> = scope
+            .parameters
+            .iter()
+            .map(|parameter| &parameter.name)
+            .chain(scope.locals.iter().map(|local| &local.name))
+            .filter_map(|name| Some((name.clone(), scope.resolve(name)?)))
+            .take(u8::MAX as usize)
+            .collect();
+        for (name, slot) in &locals {
+            let name_idx = self.name_constant(name);
+            self.emit_constant_operand(OpCode::OpConstant, name_idx);
+            self.emit_get_variable(0, VariableTarget::Local(*slot));
         }
+        self.emit_build(OpCode::OpBuildDict, locals.len())
+    }
 
-        if self.lexer.clone().next() == Some(Ok(Token::Comma)) {
-            self.had_error = true;
-            return false;
+    /// Counts the top-level arguments of the call whose `(` is the next token.
+    /// Returns None if the next token is not `(`, the call is unterminated, or it
+    /// spreads a `*iterable` argument or passes a keyword argument.
+    fn call_argument_count(&self) -> Option<usize> {
+        let mut lookahead = self.tokens.ahead();
+        if lookahead.next() != Some(Ok(Token::LParen)) {
+            return None;
         }
 
-        if self.lexer.next() != Some(Ok(Token::RParen)) {
-            self.had_error = true;
-            return false;
+        let mut depth = 0usize;
+        let mut count = 0usize;
+        let mut at_argument_start = true;
+
+        while let Some(Ok(token)) = lookahead.next() {
+            match token {
+                Token::RParen if depth == 0 => return Some(count),
+                Token::Star if depth == 0 && at_argument_start => return None,
+                Token::Assign if depth == 0 => return None,
+                Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+                Token::RParen | Token::RBracket | Token::RBrace => {
+                    depth = depth.saturating_sub(1);
+                }
+                _ => {}
+            }
+            if at_argument_start && token != Token::Comma {
+                count += 1;
+            }
+            at_argument_start = depth == 0 && token == Token::Comma;
         }
 
-        self.chunk.code.push(OpCode::OpToList as u8);
-        true
+        None
     }
 
-    /// Parses a zip() call: zip(iter1, iter2, *iter3)
-    /// Supports unpacking iterables with the * operator.
-    pub(super) fn parse_zip_call(&mut self) -> bool {
-        self.lexer.next(); // consume '('
+    /// Collects the names of the builtins compiled above that the source may
+    /// rebind.
+    ///
+    /// Any use of one of those names other than a plain call (an assignment target,
+    /// a parameter, a `def`/`class` name, or passing the builtin around as a value)
+    /// counts as a rebinding, so the compiler falls back to a normal global lookup.
+    /// Uses of `eval()` and `exec()` as a value, as in `e = eval`, or as an
+    /// argument do not count: their calls look for a local of the same name
+    /// first, so only the module's globals need checking.
+    pub(super) fn rebound_builtin_names(source: &str) -> HashSet<String> {
+        let mut rebound = HashSet::new();
+        let mut tokens = TokenStream::new(source);
+        let mut previous: Option<Token> = None;
+
+        while let Some(token) = tokens.next() {
+            let Ok(token) = token else {
+                previous = None;
+                continue;
+            };
+
+            if let Token::Identifier(name) = &token {
+                let is_builtin = FAST_PATH_BUILTINS
+                    .iter()
+                    .any(|(builtin, _, _)| builtin == name)
+                    || EVALUATION_BUILTINS.contains(&name.as_str());
+                let is_attribute = previous == Some(Token::Dot);
+                if is_builtin && !is_attribute {
+                    let defines_name = matches!(previous, Some(Token::Def | Token::Class));
+                    let next = tokens.peek();
+                    let is_call = next == Some(Ok(Token::LParen));
+                    let is_read = EVALUATION_BUILTINS.contains(&name.as_str())
+                        && match previous {
+                            Some(Token::Assign) => next != Some(Ok(Token::Assign)),
+                            Some(Token::LParen | Token::Comma) => {
+                                matches!(next, Some(Ok(Token::RParen | Token::Comma)))
+                            }
+                            _ => false,
+                        };
+                    if defines_name || !(is_call || is_read) {
+                        rebound.insert(name.clone());
+                    }
+                }
+            }
 
-        if self.lexer.clone().next() == Some(Ok(Token::RParen)) {
-            self.lexer.next(); // consume ')'
-            let empty_list_idx = self.add_constant(Rc::new(ObjectType::List(Vec::new())));
-            self.chunk.code.push(OpCode::OpConstant as u8);
-            self.chunk.code.push(empty_list_idx as u8);
-            return true;
+            previous = Some(token);
         }
 
-        let mut arg_count: u16 = 0;
-        let mut star_mask: u16 = 0;
+        rebound
+    }
 
+    /// Parses a run of adjacent string literals, which Python joins into one
+    /// string: `"a" "b"` is `"ab"`, and f-strings mix with plain strings as in
+    /// `"total: " f"{count}"`. The run may continue onto later lines inside
+    /// brackets, but a string starting a new statement begins a new run.
+    ///
+    /// `first` is the text of the first literal, already consumed, or None if
+    /// the `f` of an f-string was consumed and its template comes next.
+    /// Replacement fields compile their expressions where they appear.
+    pub(super) fn parse_string_literals(&mut self, first: Option<String>) -> bool {
+        let mut segments = Vec::new();
+        let mut literal = first;
         loop {
-            let is_star = matches!(self.lexer.clone().next(), Some(Ok(Token::Star)));
-            if is_star {
-                self.lexer.next(); // consume '*'
-            }
-
-            if !self.parse_expression() {
+            let template_segments = match literal.take() {
+                Some(text) => Ok(vec![FStringSegment::Literal(text)]),
+                None => match self.tokens.next() {
+                    Some(Ok(Token::String(template))) => {
+                        // The template is the source text between the quotes
+                        let offset = self.tokens.span().start + 1;
+                        Self::f_string_segments(&template, offset)
+                    }
+                    _ => Err(()),
+                },
+            };
+            let Ok(template_segments) = template_segments else {
                 self.had_error = true;
                 return false;
+            };
+            for segment in template_segments {
+                match (segments.last_mut(), segment) {
+                    (Some(FStringSegment::Literal(text)), FStringSegment::Literal(more)) => {
+                        text.push_str(&more)
+                    }
+                    (_, segment) => segments.push(segment),
+                }
             }
 
-            if arg_count >= 16 {
-                self.had_error = true;
-                return false;
+            if !self.continues_string_run() {
+                break;
             }
-
-            if is_star {
-                star_mask |= 1 << arg_count;
+            // Otherwise this consumes the `f` of an f-string
+            if let Some(Ok(Token::String(text))) = self.tokens.next() {
+                literal = Some(text);
             }
+        }
 
-            arg_count += 1;
+        self.emit_segments(segments)
+    }
 
-            if self.lexer.clone().next() == Some(Ok(Token::Comma)) {
-                self.lexer.next(); // consume ','
-                continue;
+    /// Emits code concatenating `segments` into one string.
+    fn emit_segments(&mut self, segments: Vec<FStringSegment>) -> bool {
+        if segments.is_empty() {
+            let const_idx = self.add_constant(Rc::new(ObjectType::String(String::new())));
+            self.emit_constant_operand(OpCode::OpConstant, const_idx);
+            return true;
+        }
+
+        for (index, segment) in segments.into_iter().enumerate() {
+            match segment {
+                FStringSegment::Literal(text) => {
+                    let const_idx = self.add_constant(Rc::new(ObjectType::String(text)));
+                    self.emit_constant_operand(OpCode::OpConstant, const_idx);
+                }
+                FStringSegment::Field {
+                    expression,
+                    conversion,
+                    spec,
+                } => {
+                    if !self.parse_replacement_field(expression, conversion, spec) {
+                        return false;
+                    }
+                }
+            }
+
+            if index > 0 {
+                self.chunk.code.push(OpCode::OpAdd as u8);
             }
-            break;
         }
 
-        if self.lexer.next() != Some(Ok(Token::RParen)) {
+        true
+    }
+
+    /// Compiles a replacement field: its expression, then its format spec,
+    /// which may hold replacement fields of its own, as in `{x:>{width}}`.
+    fn parse_replacement_field(
+        &mut self,
+        expression: Range<usize>,
+        conversion: u8,
+        spec: Option<Range<usize>>,
+    ) -> bool {
+        let tokens = TokenStream::within(self.source, expression);
+        let outer = std::mem::replace(&mut self.tokens, tokens);
+        let parsed = self.parse_expression() && self.tokens.next().is_none();
+        self.tokens = outer;
+        if !parsed {
             self.had_error = true;
             return false;
         }
 
-        self.chunk.code.push(OpCode::OpZip as u8);
-        self.chunk.code.push(arg_count as u8);
-        self.chunk.code.push(((star_mask >> 8) & 0xff) as u8);
-        self.chunk.code.push((star_mask & 0xff) as u8);
+        let mut flags = conversion;
+        if let Some(spec) = spec {
+            let segments = Self::f_string_segments(&self.source[spec.clone()], spec.start);
+            let Ok(segments) = segments else {
+                self.had_error = true;
+                return false;
+            };
+            if !self.nested(|compiler| compiler.emit_segments(segments)) {
+                return false;
+            }
+            flags |= FORMAT_WITH_SPEC;
+        }
+        self.chunk.code.push(OpCode::OpFormatValue as u8);
+        self.chunk.code.push(flags);
         true
     }
 
-    /// Parses an f-string template into segments of literals and identifiers.
-    /// Handles escape sequences like {{ and }}.
-    pub(super) fn f_string_segments(template: &str) -> Result<Vec<FStringSegment>, ()> {
+    /// Whether the next token is another string literal, plain or f-string,
+    /// continuing the current statement.
+    fn continues_string_run(&self) -> bool {
+        let is_string = match self.tokens.peek() {
+            Some(Ok(Token::String(_))) => true,
+            Some(Ok(Token::Identifier(name))) => {
+                name == "f" && matches!(self.tokens.peek_nth(1), Some(Ok(Token::String(_))))
+            }
+            _ => false,
+        };
+        is_string && self.continues_line()
+    }
+
+    /// Parses an f-string template into segments of literal text and
+    /// replacement fields, the fields' ranges counted from `offset`. Doubled
+    /// braces `{{` and `}}` stand for literal braces.
+    pub(super) fn f_string_segments(
+        template: &str,
+        offset: usize,
+    ) -> Result<Vec<FStringSegment>, ()> {
+        let bytes = template.as_bytes();
         let mut segments = Vec::new();
-        let mut chars = template.chars().peekable();
-        let mut current_literal = String::new();
+        let mut literal = String::new();
+        let mut index = 0;
 
-        while let Some(ch) = chars.next() {
+        while let Some(ch) = template[index..].chars().next() {
             match ch {
+                '{' | '}' if bytes.get(index + 1) == Some(&bytes[index]) => {
+                    literal.push(ch);
+                    index += 2;
+                }
                 '{' => {
-                    if chars.peek() == Some(&'{') {
-                        chars.next();
-                        current_literal.push('{');
-                        continue;
-                    }
-
-                    if !current_literal.is_empty() {
-                        segments.push(FStringSegment::Literal(std::mem::take(
-                            &mut current_literal,
-                        )));
-                    }
-
-                    let mut expr = String::new();
-                    let mut found_closing = false;
-                    for next_ch in chars.by_ref() {
-                        if next_ch == '}' {
-                            found_closing = true;
-                            break;
-                        }
-                        if next_ch == '{' {
-                            return Err(());
-                        }
-                        expr.push(next_ch);
-                    }
-
-                    if !found_closing {
-                        return Err(());
+                    if !literal.is_empty() {
+                        segments.push(FStringSegment::Literal(std::mem::take(&mut literal)));
                     }
-
-                    let trimmed = expr.trim();
-                    if trimmed.is_empty() || !Self::is_valid_identifier(trimmed) {
-                        return Err(());
-                    }
-
-                    segments.push(FStringSegment::Identifier(trimmed.to_string()));
+                    let (field, end) = Self::replacement_field(template, index + 1, offset)?;
+                    segments.push(field);
+                    index = end;
                 }
-                '}' => {
-                    if chars.peek() == Some(&'}') {
-                        chars.next();
-                        current_literal.push('}');
-                    } else {
-                        return Err(());
-                    }
+                '}' => return Err(()),
+                _ => {
+                    literal.push(ch);
+                    index += ch.len_utf8();
                 }
-                _ => current_literal.push(ch),
             }
         }
 
-        if !current_literal.is_empty() {
-            segments.push(FStringSegment::Literal(current_literal));
+        if !literal.is_empty() {
+            segments.push(FStringSegment::Literal(literal));
         }
 
         Ok(segments)
     }
 
-    /// Checks if a string is a valid Python identifier.
-    /// Must start with a letter or underscore, followed by letters, digits, or underscores.
-    pub(super) fn is_valid_identifier(name: &str) -> bool {
-        let mut chars = name.chars();
-        match chars.next() {
-            Some(ch) if ch == '_' || ch.is_ascii_alphabetic() => {}
-            _ => return false,
+    /// The byte ranges, counted from `offset`, of the expressions in an
+    /// f-string template's replacement fields, or None if the template is
+    /// malformed.
+    pub(crate) fn f_string_expressions(template: &str, offset: usize) -> Option<Vec<Range<usize>>> {
+        let segments = Self::f_string_segments(template, offset).ok()?;
+        Some(
+            segments
+                .into_iter()
+                .filter_map(|segment| match segment {
+                    FStringSegment::Field { expression, .. } => Some(expression),
+                    FStringSegment::Literal(_) => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// Parses the replacement field starting at `start`, just after its `{`,
+    /// returning it and the index just past its `}`.
+    ///
+    /// The expression ends at the first `!`, `:` or `}` outside any brackets
+    /// or string literals it contains, so `{d['}']}`, `{ {'a': 1}['a'] }` and
+    /// `{items[1:]}` work. The format spec after `:` runs to the matching `}`.
+    fn replacement_field(
+        template: &str,
+        start: usize,
+        offset: usize,
+    ) -> Result<(FStringSegment, usize), ()> {
+        let bytes = template.as_bytes();
+        let mut index = start;
+        let mut depth = 0usize;
+        let mut quote = None;
+        loop {
+            let byte = *bytes.get(index).ok_or(())?;
+            match (quote, byte) {
+                (Some(open), _) if byte == open => quote = None,
+                (Some(_), _) => {}
+                (None, b'\'' | b'"') => quote = Some(byte),
+                (None, b'(' | b'[' | b'{') => depth += 1,
+                (None, b')' | b']' | b'}') if depth > 0 => depth -= 1,
+                (None, b')' | b']') => return Err(()),
+                (None, b'}') => break,
+                (None, b':') if depth == 0 => break,
+                (None, b'!') if depth == 0 && bytes.get(index + 1) != Some(&b'=') => break,
+                _ => {}
+            }
+            index += 1;
+        }
+        if template[start..index].trim().is_empty() {
+            return Err(());
+        }
+        let expression = start + offset..index + offset;
+
+        let mut conversion = 0;
+        if bytes[index] == b'!' {
+            conversion = match bytes.get(index + 1) {
+                Some(b's') => 1,
+                Some(b'r') => 2,
+                Some(b'a') => 3,
+                _ => return Err(()),
+            };
+            index += 2;
+        }
+
+        let mut spec = None;
+        if bytes.get(index) == Some(&b':') {
+            let spec_start = index + 1;
+            let mut depth = 0usize;
+            loop {
+                index += 1;
+                match bytes.get(index).ok_or(())? {
+                    b'{' => depth += 1,
+                    b'}' if depth == 0 => break,
+                    b'}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            spec = Some(spec_start + offset..index + offset);
         }
 
-        chars.all(|ch| ch == '_' || ch.is_ascii_alphanumeric())
+        if bytes.get(index) != Some(&b'}') {
+            return Err(());
+        }
+        let field = FStringSegment::Field {
+            expression,
+            conversion,
+            spec,
+        };
+        Ok((field, index + 1))
     }
 }
@@ -1,36 +1,34 @@
 //! Code generation utilities for the compiler.
 //!
 //! This module contains functions for emitting bytecode instructions,
-//! managing jumps, and manipulating the constant pool.
+//! managing jumps, folding constant expressions, and manipulating the
+//! constant pool.
 
-use crate::bytecode::OpCode;
+use crate::bytecode::{jump_distance, slot_index, OpCode, JUMP_WIDTH, MAX_CONSTANT};
 use crate::object::{Object, ObjectType};
-use std::rc::Rc;
+use crate::sync::Rc;
+use crate::vm::opcodes::{arithmetic, comparison};
 
-use super::types::VariableTarget;
+use super::types::{AssignmentTarget, SubscriptKind, VariableTarget};
 
 impl super::Compiler<'_> {
     /// Emits bytecode to push nil onto the stack.
     pub(super) fn emit_nil(&mut self) {
         let nil_idx = self.add_constant(Rc::new(ObjectType::Nil));
-        self.chunk.code.push(OpCode::OpConstant as u8);
-        self.chunk.code.push(nil_idx as u8);
+        self.emit_constant_operand(OpCode::OpConstant, nil_idx);
     }
 
     /// Emits bytecode to get a variable value.
     pub(super) fn emit_get_variable(&mut self, name_idx: usize, target: VariableTarget) {
         match target {
             VariableTarget::Local(local) => {
-                self.chunk.code.push(OpCode::OpGetLocal as u8);
-                self.chunk.code.push(local as u8);
+                self.emit_slot(OpCode::OpGetLocal, local);
             }
             VariableTarget::Upvalue(upvalue) => {
-                self.chunk.code.push(OpCode::OpGetUpvalue as u8);
-                self.chunk.code.push(upvalue as u8);
+                self.emit_slot(OpCode::OpGetUpvalue, upvalue);
             }
             VariableTarget::Global => {
-                self.chunk.code.push(OpCode::OpGetGlobal as u8);
-                self.chunk.code.push(name_idx as u8);
+                self.emit_constant_operand(OpCode::OpGetGlobal, name_idx);
             }
         }
     }
@@ -39,16 +37,13 @@ impl super::Compiler<'_> {
     pub(super) fn emit_set_variable(&mut self, name_idx: usize, target: VariableTarget) {
         match target {
             VariableTarget::Local(local) => {
-                self.chunk.code.push(OpCode::OpSetLocal as u8);
-                self.chunk.code.push(local as u8);
+                self.emit_slot(OpCode::OpSetLocal, local);
             }
             VariableTarget::Upvalue(upvalue) => {
-                self.chunk.code.push(OpCode::OpSetUpvalue as u8);
-                self.chunk.code.push(upvalue as u8);
+                self.emit_slot(OpCode::OpSetUpvalue, upvalue);
             }
             VariableTarget::Global => {
-                self.chunk.code.push(OpCode::OpSetGlobal as u8);
-                self.chunk.code.push(name_idx as u8);
+                self.emit_constant_operand(OpCode::OpSetGlobal, name_idx);
             }
         }
     }
@@ -57,18 +52,56 @@ impl super::Compiler<'_> {
     pub(super) fn emit_define_variable(&mut self, name_idx: usize, target: VariableTarget) {
         match target {
             VariableTarget::Local(local) => {
-                self.chunk.code.push(OpCode::OpSetLocal as u8);
-                self.chunk.code.push(local as u8);
+                self.emit_slot(OpCode::OpSetLocal, local);
                 self.chunk.code.push(OpCode::OpPop as u8);
             }
             VariableTarget::Upvalue(upvalue) => {
-                self.chunk.code.push(OpCode::OpSetUpvalue as u8);
-                self.chunk.code.push(upvalue as u8);
+                self.emit_slot(OpCode::OpSetUpvalue, upvalue);
                 self.chunk.code.push(OpCode::OpPop as u8);
             }
             VariableTarget::Global => {
-                self.chunk.code.push(OpCode::OpDefineGlobal as u8);
-                self.chunk.code.push(name_idx as u8);
+                self.emit_constant_operand(OpCode::OpDefineGlobal, name_idx);
+            }
+        }
+    }
+
+    /// Emits the code storing the value on top of the stack into `target`,
+    /// consuming the value.
+    pub(super) fn emit_store_target(&mut self, target: AssignmentTarget) {
+        match target {
+            AssignmentTarget::Variable { name_idx, target } => {
+                self.emit_define_variable(name_idx, target);
+            }
+            AssignmentTarget::Attribute {
+                name_idx,
+                target,
+                attr_idx,
+            } => {
+                // Stack: [value] -> [object, value]
+                self.emit_get_variable(name_idx, target);
+                self.chunk.code.push(OpCode::OpSwap as u8);
+                self.emit_constant_operand(OpCode::OpSetAttr, attr_idx);
+            }
+            AssignmentTarget::Subscript {
+                name_idx,
+                target,
+                index_code,
+                kind,
+            } => {
+                self.emit_get_variable(name_idx, target);
+                self.chunk.code.push(OpCode::OpSwap as u8);
+                self.chunk.code.extend_from_slice(&index_code);
+                match kind {
+                    // Stack: [value] -> [collection, index, value]
+                    SubscriptKind::Index => {
+                        self.chunk.code.push(OpCode::OpSwap as u8);
+                        self.chunk.code.push(OpCode::OpSetIndex as u8);
+                    }
+                    // Stack: [value] -> [collection, value, start, end, step]
+                    SubscriptKind::Slice => self.chunk.code.push(OpCode::OpSetSlice as u8),
+                }
+                self.emit_set_variable(name_idx, target);
+                self.chunk.code.push(OpCode::OpPop as u8);
             }
         }
     }
@@ -77,27 +110,31 @@ impl super::Compiler<'_> {
     pub(super) fn emit_jump(&mut self, instruction: OpCode) -> usize {
         self.chunk.code.push(instruction as u8);
         let operand_index = self.chunk.code.len();
-        self.chunk.code.push(0);
-        self.chunk.code.push(0);
+        self.chunk.code.extend([0; JUMP_WIDTH]);
         operand_index
     }
 
     /// Emits a loop instruction that jumps backward to the given position.
     pub(super) fn emit_loop(&mut self, loop_start: usize) {
-        self.chunk.code.push(OpCode::OpLoop as u8);
-        let operand_index = self.chunk.code.len();
-        self.chunk.code.push(0);
-        self.chunk.code.push(0);
+        let operand_index = self.emit_jump(OpCode::OpLoop);
         let offset = self.chunk.code.len() - loop_start;
-        self.chunk.code[operand_index] = ((offset >> 8) & 0xff) as u8;
-        self.chunk.code[operand_index + 1] = (offset & 0xff) as u8;
+        self.write_jump(operand_index, offset);
     }
 
     /// Patches a previously emitted jump instruction with the correct offset.
     pub(super) fn patch_jump(&mut self, operand_index: usize) {
-        let jump = self.chunk.code.len() - (operand_index + 2);
-        self.chunk.code[operand_index] = ((jump >> 8) & 0xff) as u8;
-        self.chunk.code[operand_index + 1] = (jump & 0xff) as u8;
+        let jump = self.chunk.code.len() - (operand_index + JUMP_WIDTH);
+        self.write_jump(operand_index, jump);
+    }
+
+    /// Stores a jump's distance in the operand at `operand_index`.
+    fn write_jump(&mut self, operand_index: usize, distance: usize) {
+        let Ok(distance) = u32::try_from(distance) else {
+            self.fail("too much code to jump over");
+            return;
+        };
+        self.chunk.code[operand_index..operand_index + JUMP_WIDTH]
+            .copy_from_slice(&distance.to_be_bytes());
     }
 
     /// Returns all constant indices that contain the given string.
@@ -113,71 +150,234 @@ impl super::Compiler<'_> {
             .collect()
     }
 
+    /// Emits `opcode`, a local or upvalue instruction, addressing `slot`,
+    /// in its long form when the slot does not fit in a byte.
+    pub(super) fn emit_slot(&mut self, opcode: OpCode, slot: usize) {
+        push_slot(&mut self.chunk.code, opcode, slot);
+    }
+
+    /// Emits `opcode`, an instruction taking a constant, addressing the
+    /// constant `index`, in its long form when the index does not fit in a
+    /// byte.
+    pub(super) fn emit_constant_operand(&mut self, opcode: OpCode, index: usize) {
+        push_slot(&mut self.chunk.code, opcode, index);
+    }
+
     /// Rewrites OpGetGlobal and OpSetGlobal instructions to use local variables instead.
     /// This is used for list comprehensions where globals need to become locals.
+    ///
+    /// A slot needing the long form lengthens the rewritten instructions, so
+    /// the code is returned anew with its jumps re-aimed at where their
+    /// targets moved.
     pub(super) fn rewrite_globals_to_local(
         &self,
-        code: &mut [u8],
+        code: &[u8],
         target_indices: &[usize],
         local_slot: usize,
-    ) {
+    ) -> Vec<u8> {
         if target_indices.is_empty() {
-            return;
+            return code.to_vec();
         }
 
+        let mut rewritten = Vec::with_capacity(code.len());
+        // Where each instruction, and the end of the code, moved to
+        let mut moved = vec![0; code.len() + 1];
+        let mut jumps = Vec::new();
         let mut i = 0;
         while i < code.len() {
             let opcode = OpCode::from(code[i]);
-            match opcode {
-                OpCode::OpGetGlobal => {
-                    if i + 1 < code.len() {
-                        let idx = code[i + 1] as usize;
-                        if target_indices.contains(&idx) {
-                            code[i] = OpCode::OpGetLocal as u8;
-                            code[i + 1] = local_slot as u8;
-                        }
-                    }
-                    i += 1 + 1;
-                }
-                OpCode::OpSetGlobal => {
-                    if i + 1 < code.len() {
-                        let idx = code[i + 1] as usize;
-                        if target_indices.contains(&idx) {
-                            code[i] = OpCode::OpSetLocal as u8;
-                            code[i + 1] = local_slot as u8;
-                        }
+            let end = (i + 1 + opcode.operand_width()).min(code.len());
+            moved[i] = rewritten.len();
+            let targeted = || target_indices.contains(&slot_index(&code[i + 1..end]));
+            let local = match opcode.long_form() {
+                OpCode::OpGetGlobalLong if targeted() => Some(OpCode::OpGetLocal),
+                OpCode::OpSetGlobalLong if targeted() => Some(OpCode::OpSetLocal),
+                _ => None,
+            };
+            match local {
+                Some(local) => push_slot(&mut rewritten, local, local_slot),
+                None => {
+                    if opcode.is_jump() && end - i == 1 + JUMP_WIDTH {
+                        jumps.push((i, rewritten.len()));
                     }
-                    i += 1 + 1;
-                }
-                _ => {
-                    i += 1 + Self::opcode_operand_width(opcode);
+                    rewritten.extend_from_slice(&code[i..end]);
                 }
             }
+            i = end;
         }
+        moved[code.len()] = rewritten.len();
+
+        for (old_start, new_start) in jumps {
+            let old_end = old_start + 1 + JUMP_WIDTH;
+            let new_end = new_start + 1 + JUMP_WIDTH;
+            let distance = jump_distance(&code[old_start + 1..old_end]);
+            let target = match OpCode::from(code[old_start]) {
+                OpCode::OpLoop => old_end.checked_sub(distance),
+                _ => Some(old_end + distance),
+            };
+            let Some(&new_target) = target.and_then(|target| moved.get(target)) else {
+                continue;
+            };
+            let distance = new_target.abs_diff(new_end) as u32;
+            rewritten[new_start + 1..new_end].copy_from_slice(&distance.to_be_bytes());
+        }
+        rewritten
     }
 
-    /// Returns the number of operand bytes for a given opcode.
-    pub(super) fn opcode_operand_width(opcode: OpCode) -> usize {
-        match opcode {
-            OpCode::OpConstant
-            | OpCode::OpDefineGlobal
-            | OpCode::OpGetGlobal
-            | OpCode::OpSetGlobal
-            | OpCode::OpCall
-            | OpCode::OpGetLocal
-            | OpCode::OpSetLocal
-            | OpCode::OpGetUpvalue
-            | OpCode::OpSetUpvalue
-            | OpCode::OpMakeFunction => 1,
-            OpCode::OpIterNext | OpCode::OpLoop | OpCode::OpJumpIfFalse | OpCode::OpJump => 2,
-            OpCode::OpZip => 3,
-            _ => 0,
+    /// Folds the `OpConstant a, OpConstant b, <op>` sequence emitted since
+    /// `start` into a single constant, so `2 * 3600 + 30` compiles to `7230`.
+    ///
+    /// Only number, string and bool literals are folded, using the VM's own
+    /// operator implementations. Anything that would fail at runtime (such as
+    /// division by zero) is left alone so the error still happens when the
+    /// code runs.
+    pub(super) fn fold_constant_operation(&mut self, start: usize) {
+        let [first, lhs_idx, second, rhs_idx, operator] = self.chunk.code[start..] else {
+            return;
+        };
+        if first != OpCode::OpConstant as u8 || second != OpCode::OpConstant as u8 {
+            return;
+        }
+        let (lhs_idx, rhs_idx) = (lhs_idx as usize, rhs_idx as usize);
+        let lhs = self.chunk.constants[lhs_idx].clone();
+        let rhs = self.chunk.constants[rhs_idx].clone();
+        let foldable = |value: &Object| {
+            matches!(
+                **value,
+                ObjectType::Integer(_)
+                    | ObjectType::Float(_)
+                    | ObjectType::String(_)
+                    | ObjectType::Boolean(_)
+            )
+        };
+        if !foldable(&lhs) || !foldable(&rhs) {
+            return;
+        }
+
+        let boolean = |result: Result<bool, _>| result.map(|b| Rc::new(ObjectType::Boolean(b)));
+        let folded = match OpCode::from(operator) {
+            OpCode::OpAdd => arithmetic::op_add(lhs, rhs),
+            OpCode::OpSubtract => arithmetic::op_subtract(lhs, rhs),
+            OpCode::OpMultiply => arithmetic::op_multiply(lhs, rhs),
+            OpCode::OpDivide => arithmetic::op_divide(lhs, rhs),
+            OpCode::OpFloorDivide => arithmetic::op_floor_divide(lhs, rhs),
+            OpCode::OpModulo => arithmetic::op_modulo(lhs, rhs),
+            OpCode::OpLess => boolean(comparison::op_less(&lhs, &rhs)),
+            OpCode::OpLessEqual => boolean(comparison::op_less_equal(&lhs, &rhs)),
+            OpCode::OpGreater => boolean(comparison::op_greater(&lhs, &rhs)),
+            OpCode::OpGreaterEqual => boolean(comparison::op_greater_equal(&lhs, &rhs)),
+            OpCode::OpEqual => Ok(Rc::new(ObjectType::Boolean(comparison::op_equal(lhs, rhs)))),
+            _ => return,
+        };
+        if let Ok(value) = folded {
+            self.replace_with_constant(start, &[lhs_idx, rhs_idx], value);
+        }
+    }
+
+    /// Folds the `OpConstant a, OpNegate` sequence emitted since `start` into
+    /// a single constant, so `-5` compiles to the constant `-5`.
+    pub(super) fn fold_constant_negation(&mut self, start: usize) {
+        let [first, idx, operator] = self.chunk.code[start..] else {
+            return;
+        };
+        if first != OpCode::OpConstant as u8 || operator != OpCode::OpNegate as u8 {
+            return;
+        }
+        let idx = idx as usize;
+        let value = self.chunk.constants[idx].clone();
+        if !matches!(
+            *value,
+            ObjectType::Integer(_) | ObjectType::Float(_) | ObjectType::Boolean(_)
+        ) {
+            return;
+        }
+        if let Ok(negated) = arithmetic::op_negate(value) {
+            self.replace_with_constant(start, &[idx], negated);
+        }
+    }
+
+    /// Returns the constant indices loaded by the code emitted since `start`
+    /// if that code is nothing but `OpConstant` instructions.
+    pub(super) fn constant_run(&self, start: usize) -> Option<Vec<usize>> {
+        let code = &self.chunk.code[start..];
+        if !code.len().is_multiple_of(2) {
+            return None;
+        }
+        code.chunks(2)
+            .map(|pair| (pair[0] == OpCode::OpConstant as u8).then_some(pair[1] as usize))
+            .collect()
+    }
+
+    /// Replaces the code emitted since `start` with a single load of `value`.
+    ///
+    /// `operands` are the constants that code loaded; when they are the
+    /// newest entries of the pool nothing else can refer to them yet, so
+    /// they are dropped to keep folded expressions from using up slots.
+    pub(super) fn replace_with_constant(
+        &mut self,
+        start: usize,
+        operands: &[usize],
+        value: Object,
+    ) {
+        self.chunk.code.truncate(start);
+        let constant_count = self.chunk.constants.len();
+        if let Some(first) = constant_count.checked_sub(operands.len()) {
+            if operands.iter().copied().eq(first..constant_count) {
+                self.chunk.constants.truncate(first);
+            }
+        }
+        let idx = self.add_constant(value);
+        self.emit_constant_operand(OpCode::OpConstant, idx);
+    }
+
+    /// Returns the index of a string constant holding `name`, the operand of
+    /// instructions addressing a variable, attribute or module by name.
+    ///
+    /// A name already in the pool is reused, so that a long body naming the
+    /// same variables over and over does not run out of slots. Strings that
+    /// `OpConstant` loads are added afresh instead, as folding a literal
+    /// drops the constants its elements added.
+    pub(super) fn name_constant(&mut self, name: &str) -> usize {
+        match self.constant_indices_for_string(name).first() {
+            Some(&index) => index,
+            None => self.add_constant(Rc::new(ObjectType::String(name.to_string()))),
+        }
+    }
+
+    /// The operand naming `name` for instructions on a variable resolved to
+    /// `target`. Only globals are addressed by name; locals and upvalues use
+    /// slots, so they add no constant and get 0.
+    pub(super) fn variable_name_constant(&mut self, name: &str, target: VariableTarget) -> usize {
+        match target {
+            VariableTarget::Global => self.name_constant(name),
+            VariableTarget::Local(_) | VariableTarget::Upvalue(_) => 0,
         }
     }
 
     /// Adds a constant to the constant pool and returns its index.
+    ///
+    /// Only the constant that overflows the pool fails, so that the
+    /// statements after it do not each report the same error.
     pub(super) fn add_constant(&mut self, value: Object) -> usize {
+        // Constant operands are at most two bytes, so a larger pool cannot be addressed
+        if self.chunk.constants.len() == MAX_CONSTANT + 1 {
+            self.fail("too many constants in one chunk");
+        }
         self.chunk.constants.push(value);
         self.chunk.constants.len() - 1
     }
 }
+
+/// Appends `opcode`, a local, upvalue or constant instruction, addressing
+/// `slot` to `code`, in its long form when the slot does not fit in a byte.
+/// Slots past `MAX_SLOT` are refused when declared, and constants past
+/// `MAX_CONSTANT` fail the compile, so neither is ever run.
+fn push_slot(code: &mut Vec<u8>, opcode: OpCode, slot: usize) {
+    match u8::try_from(slot) {
+        Ok(slot) => code.extend([opcode as u8, slot]),
+        Err(_) => {
+            code.push(opcode.long_form() as u8);
+            code.extend_from_slice(&(slot as u16).to_be_bytes());
+        }
+    }
+}
@@ -4,59 +4,162 @@
 //! operations including function calls, indexing, slicing, and method calls.
 
 use crate::bytecode::OpCode;
-use crate::object::ObjectType;
+use crate::object::{Object, ObjectType};
+use crate::sync::Rc;
 use crate::token::Token;
-use std::rc::Rc;
 
-use super::types::VariableTarget;
+use super::types::{AssignmentTarget, PathStep, StorePath, SubscriptKind};
 
 impl super::Compiler<'_> {
-    /// Parses a simple binary expression: term (operator term)*
-    /// Supports operators: +, -, *, /, %, <, ==, in
+    /// Parses an expression, which may be conditional: `value if condition
+    /// else other`, where `other` may itself be conditional.
     pub(super) fn parse_expression(&mut self) -> bool {
-        // Extremely simplified expression parser for "value" or "value + value"
-        let mut operands = 0;
+        let start = self.chunk.code.len();
+        if !self.parse_disjunction() {
+            return false;
+        }
+        if self.tokens.peek() != Some(Ok(Token::If)) || !self.continues_line() {
+            return true;
+        }
+        self.tokens.next(); // consume 'if'
 
-        if let Some(Ok(token)) = self.lexer.next() {
-            if self.parse_term(token) {
-                operands += 1;
-            } else {
+        // The condition is evaluated first, so the value's code moves after it
+        let value_code = self.chunk.code.split_off(start);
+        if !self.parse_disjunction() || self.tokens.next() != Some(Ok(Token::Else)) {
+            self.had_error = true;
+            return false;
+        }
+
+        let else_jump = self.emit_jump(OpCode::OpJumpIfFalse);
+        self.chunk.code.push(OpCode::OpPop as u8);
+        self.chunk.code.extend_from_slice(&value_code);
+        let end_jump = self.emit_jump(OpCode::OpJump);
+        self.patch_jump(else_jump);
+        self.chunk.code.push(OpCode::OpPop as u8);
+        if !self.nested(Self::parse_expression) {
+            self.had_error = true;
+            return false;
+        }
+        self.patch_jump(end_jump);
+        true
+    }
+
+    /// Parses an optional `not`, then arithmetic operands joined by
+    /// comparisons (`<`, `<=`, `>`, `>=`, `==`, `in`, `not in`, `is`,
+    /// `is not`). Comparisons bind more loosely than arithmetic, and `not`
+    /// more loosely than comparisons.
+    pub(super) fn parse_disjunction(&mut self) -> bool {
+        if matches!(self.tokens.peek(), Some(Ok(Token::Not))) {
+            self.tokens.next(); // consume 'not'
+            if !self.nested(Self::parse_disjunction) {
                 self.had_error = true;
                 return false;
             }
-        } else {
+            self.chunk.code.push(OpCode::OpNot as u8);
+            return true;
+        }
+
+        let start = self.chunk.code.len();
+        if !self.parse_arithmetic() {
             return false;
         }
 
-        while let Some(Ok(next_token)) = self.lexer.clone().next() {
+        while let Some((opcode, negated)) = self.comparison_operator() {
+            if !self.parse_arithmetic() {
+                self.had_error = true;
+                return false;
+            }
+            self.chunk.code.push(opcode as u8);
+            self.fold_constant_operation(start);
+            if negated {
+                self.chunk.code.push(OpCode::OpNot as u8);
+            }
+        }
+        true
+    }
+
+    /// Consumes a comparison operator, returning its opcode and whether the
+    /// result is negated, as for the two-word `not in` and `is not`.
+    fn comparison_operator(&mut self) -> Option<(OpCode, bool)> {
+        let (operator, length) = match self.tokens.peek()? {
+            Ok(Token::Less) => ((OpCode::OpLess, false), 1),
+            Ok(Token::LessEqual) => ((OpCode::OpLessEqual, false), 1),
+            Ok(Token::Greater) => ((OpCode::OpGreater, false), 1),
+            Ok(Token::GreaterEqual) => ((OpCode::OpGreaterEqual, false), 1),
+            Ok(Token::EqualEqual) => ((OpCode::OpEqual, false), 1),
+            Ok(Token::In) => ((OpCode::OpContains, false), 1),
+            Ok(Token::Not) if self.tokens.peek_nth(1) == Some(Ok(Token::In)) => {
+                ((OpCode::OpContains, true), 2)
+            }
+            Ok(Token::Is) if self.tokens.peek_nth(1) == Some(Ok(Token::Not)) => {
+                ((OpCode::OpIs, true), 2)
+            }
+            Ok(Token::Is) => ((OpCode::OpIs, false), 1),
+            _ => return None,
+        };
+        self.tokens.advance(length);
+        Some(operator)
+    }
+
+    /// Parses arithmetic: term (operator term)*, evaluated left to right.
+    /// Supports operators: +, -, *, /, //, %
+    fn parse_arithmetic(&mut self) -> bool {
+        // Extremely simplified expression parser for "value" or "value + value"
+        let mut operands = 0;
+        let start = self.chunk.code.len();
+
+        match self.tokens.next() {
+            Some(Ok(token)) => {
+                if self.parse_term(token) {
+                    operands += 1;
+                } else {
+                    self.had_error = true;
+                    return false;
+                }
+            }
+            Some(Err(())) => {
+                self.fail_on_invalid_token();
+                return false;
+            }
+            None => return false,
+        }
+
+        while let Some(Ok(next_token)) = self.tokens.peek() {
+            // A minus starting the next line negates, it does not subtract
+            if !self.continues_line() {
+                break;
+            }
             let opcode = match next_token {
                 Token::Plus => Some(OpCode::OpAdd),
                 Token::Slash => Some(OpCode::OpDivide),
+                Token::SlashSlash => Some(OpCode::OpFloorDivide),
                 Token::Star => Some(OpCode::OpMultiply),
                 Token::Minus => Some(OpCode::OpSubtract),
-                Token::Less => Some(OpCode::OpLess),
-                Token::In => Some(OpCode::OpContains),
                 Token::Percent => Some(OpCode::OpModulo),
-                Token::EqualEqual => Some(OpCode::OpEqual),
                 _ => None,
             };
 
             let Some(opcode) = opcode else { break };
 
-            self.lexer.next(); // Consume the operator
+            self.tokens.next(); // Consume the operator
             let mut term_produced = false;
-            if let Some(Ok(token)) = self.lexer.next() {
-                term_produced = self.parse_term(token); // Parse the next term
-                if term_produced {
-                    operands += 1;
+            match self.tokens.next() {
+                Some(Ok(token)) => {
+                    term_produced = self.parse_term(token); // Parse the next term
+                    if term_produced {
+                        operands += 1;
+                    }
                 }
+                Some(Err(())) => self.fail_on_invalid_token(),
+                None => {}
             }
             if term_produced && operands >= 2 {
                 self.chunk.code.push(opcode as u8);
+                self.fold_constant_operation(start);
                 operands -= 1; // Two operands consumed, one result pushed.
             } else {
                 self.had_error = true;
-                break;
+                return false;
             }
         }
 
@@ -70,20 +173,25 @@ impl super::Compiler<'_> {
     /// Parses a term (primary expression) including literals, identifiers,
     /// unary minus, built-in functions, and collection literals.
     pub(super) fn parse_term(&mut self, token: Token) -> bool {
+        self.nested(|compiler| compiler.parse_term_at_depth(token))
+    }
+
+    fn parse_term_at_depth(&mut self, token: Token) -> bool {
         match token {
             Token::Minus => {
-                let zero_idx = self.add_constant(Rc::new(ObjectType::Integer(0)));
-                self.chunk.code.push(OpCode::OpConstant as u8);
-                self.chunk.code.push(zero_idx as u8);
-
-                if let Some(Ok(next)) = self.lexer.next() {
-                    if self.parse_term(next) {
-                        self.chunk.code.push(OpCode::OpSubtract as u8);
-                        true
-                    } else {
-                        self.had_error = true;
-                        false
+                let start = self.chunk.code.len();
+                let negated = match self.tokens.next() {
+                    Some(Ok(next)) => self.parse_term(next),
+                    Some(Err(())) => {
+                        self.fail_on_invalid_token();
+                        return false;
                     }
+                    None => false,
+                };
+                if negated {
+                    self.chunk.code.push(OpCode::OpNegate as u8);
+                    self.fold_constant_negation(start);
+                    true
                 } else {
                     self.had_error = true;
                     false
@@ -91,170 +199,131 @@ impl super::Compiler<'_> {
             }
             Token::Integer(val) => {
                 let const_idx = self.add_constant(Rc::new(ObjectType::Integer(val)));
-                self.chunk.code.push(OpCode::OpConstant as u8);
-                self.chunk.code.push(const_idx as u8);
-                true
+                self.emit_constant_operand(OpCode::OpConstant, const_idx);
+                self.parse_postfix(None)
             }
             Token::True => {
                 let const_idx = self.add_constant(Rc::new(ObjectType::Boolean(true)));
-                self.chunk.code.push(OpCode::OpConstant as u8);
-                self.chunk.code.push(const_idx as u8);
-                true
+                self.emit_constant_operand(OpCode::OpConstant, const_idx);
+                self.parse_postfix(None)
             }
             Token::False => {
                 let const_idx = self.add_constant(Rc::new(ObjectType::Boolean(false)));
-                self.chunk.code.push(OpCode::OpConstant as u8);
-                self.chunk.code.push(const_idx as u8);
-                true
+                self.emit_constant_operand(OpCode::OpConstant, const_idx);
+                self.parse_postfix(None)
+            }
+            Token::Identifier(name) if name == "None" => {
+                self.emit_nil();
+                self.parse_postfix(None)
             }
             Token::Identifier(name) => {
-                if name == "f" && matches!(self.lexer.clone().next(), Some(Ok(Token::String(_)))) {
-                    return self.parse_f_string_literal();
-                } else if name == "list"
-                    && matches!(self.lexer.clone().next(), Some(Ok(Token::LParen)))
-                {
-                    return self.parse_list_call();
-                } else if name == "zip"
-                    && matches!(self.lexer.clone().next(), Some(Ok(Token::LParen)))
-                {
-                    return self.parse_zip_call();
+                if name == "f" && matches!(self.tokens.peek(), Some(Ok(Token::String(_)))) {
+                    return self.parse_string_literals(None) && self.parse_postfix(None);
                 }
 
-                if name == "type" && self.lexer.clone().next() == Some(Ok(Token::LParen)) {
-                    self.lexer.next(); // consume '('
-                    if !self.parse_expression() {
-                        self.had_error = true;
-                        return false;
-                    }
-                    if self.lexer.next() != Some(Ok(Token::RParen)) {
-                        self.had_error = true;
-                        return false;
-                    }
-                    self.chunk.code.push(OpCode::OpType as u8);
-                    true
-                } else if name == "len" && self.lexer.clone().next() == Some(Ok(Token::LParen)) {
-                    self.lexer.next(); // consume '('
-                    if !self.parse_expression() {
-                        self.had_error = true;
-                        return false;
-                    }
-                    if self.lexer.next() != Some(Ok(Token::RParen)) {
-                        self.had_error = true;
-                        return false;
-                    }
-                    self.chunk.code.push(OpCode::OpLen as u8);
-                    true
-                } else if name == "round" && self.lexer.clone().next() == Some(Ok(Token::LParen)) {
-                    self.lexer.next(); // consume '('
-                    if !self.parse_expression() {
-                        self.had_error = true;
-                        return false;
-                    }
-                    if self.lexer.next() != Some(Ok(Token::Comma)) {
-                        self.had_error = true;
-                        return false;
-                    }
-                    if !self.parse_expression() {
-                        self.had_error = true;
-                        return false;
-                    }
-                    if self.lexer.next() != Some(Ok(Token::RParen)) {
-                        self.had_error = true;
-                        return false;
-                    }
-                    self.chunk.code.push(OpCode::OpRound as u8);
-                    true
-                } else if name == "range" && self.lexer.clone().next() == Some(Ok(Token::LParen)) {
-                    self.lexer.next(); // consume '('
-                    if !self.parse_expression() {
-                        self.had_error = true;
-                        return false;
-                    }
-                    if self.lexer.next() != Some(Ok(Token::Comma)) {
-                        self.had_error = true;
-                        return false;
-                    }
-                    if !self.parse_expression() {
-                        self.had_error = true;
-                        return false;
-                    }
-                    if self.lexer.next() != Some(Ok(Token::RParen)) {
-                        self.had_error = true;
-                        return false;
-                    }
-                    self.chunk.code.push(OpCode::OpRange as u8);
-                    true
+                if let Some((opcode, arity)) = self.fast_builtin_call(&name) {
+                    self.parse_fast_builtin_call(opcode, arity)
+                } else if let Some(arg_count) = self.evaluation_call(&name) {
+                    self.parse_evaluation_call(&name, arg_count)
                 } else {
-                    match self.resolve_variable(&name) {
-                        VariableTarget::Local(local_index) => {
-                            self.chunk.code.push(OpCode::OpGetLocal as u8);
-                            self.chunk.code.push(local_index as u8);
-                            self.parse_postfix(None)
-                        }
-                        VariableTarget::Upvalue(upvalue_index) => {
-                            self.chunk.code.push(OpCode::OpGetUpvalue as u8);
-                            self.chunk.code.push(upvalue_index as u8);
-                            self.parse_postfix(None)
-                        }
-                        VariableTarget::Global => {
-                            let const_idx =
-                                self.add_constant(Rc::new(ObjectType::String(name.clone())));
-                            self.chunk.code.push(OpCode::OpGetGlobal as u8);
-                            self.chunk.code.push(const_idx as u8);
-                            self.parse_postfix(Some(const_idx))
-                        }
-                    }
+                    let target = self.resolve_variable(&name);
+                    let name_idx = self.variable_name_constant(&name, target);
+                    self.emit_get_variable(name_idx, target);
+                    self.parse_postfix(Some(StorePath {
+                        name_idx,
+                        target,
+                        steps: Vec::new(),
+                    }))
                 }
             }
-            Token::String(val) => {
-                let const_idx = self.add_constant(Rc::new(ObjectType::String(val)));
-                self.chunk.code.push(OpCode::OpConstant as u8);
-                self.chunk.code.push(const_idx as u8);
-                self.parse_postfix(None)
-            }
+            Token::String(val) => self.parse_string_literals(Some(val)) && self.parse_postfix(None),
             Token::Float(val) => {
                 let const_idx = self.add_constant(Rc::new(ObjectType::Float(val)));
-                self.chunk.code.push(OpCode::OpConstant as u8);
-                self.chunk.code.push(const_idx as u8);
-                true
-            }
-            Token::LBrace => {
-                if let Some(dict_constant) = self.parse_dict_literal() {
-                    let const_idx = self.add_constant(dict_constant);
-                    self.chunk.code.push(OpCode::OpConstant as u8);
-                    self.chunk.code.push(const_idx as u8);
-                    true
-                } else {
-                    false
+                self.emit_constant_operand(OpCode::OpConstant, const_idx);
+                self.parse_postfix(None)
+            }
+            Token::LParen => {
+                if self.is_generator_expression() {
+                    return self.parse_generator_expression() && self.parse_postfix(None);
+                }
+                if !self.parse_expression() {
+                    self.had_error = true;
+                    return false;
+                }
+
+                if self.tokens.next() != Some(Ok(Token::RParen)) {
+                    self.had_error = true;
+                    return false;
                 }
+                self.parse_postfix(None)
             }
+            Token::LBrace => self.parse_dict_literal() && self.parse_postfix(None),
             Token::LBracket => {
-                if self.is_list_comprehension() {
+                let built = if self.is_list_comprehension() {
                     self.parse_list_comprehension()
-                } else if let Some(list_constant) = self.parse_list_literal() {
-                    let const_idx = self.add_constant(list_constant);
-                    self.chunk.code.push(OpCode::OpConstant as u8);
-                    self.chunk.code.push(const_idx as u8);
-                    true
                 } else {
-                    false
-                }
+                    self.parse_list_literal()
+                };
+                built && self.parse_postfix(None)
             }
             _ => false, // Not a term, do nothing
         }
     }
 
     /// Parses postfix operations: function calls, indexing, slicing, and attribute access.
-    /// Handles chained operations like `obj.method()[0].attr`.
-    pub(super) fn parse_postfix(&mut self, mut base_name_idx: Option<usize>) -> bool {
+    /// Handles chained operations like `obj.method()[0].attr`. A `(` or `[` at the
+    /// start of a line begins a new statement rather than continuing the chain.
+    ///
+    /// `base` is where the value the chain has reached is stored, if anywhere:
+    /// the variable it starts from, followed by the attributes and indexes
+    /// taken since. `list.append()` and `list.sort()` store the list they
+    /// build back there.
+    pub(super) fn parse_postfix(&mut self, mut base: Option<StorePath>) -> bool {
         loop {
-            match self.lexer.clone().next() {
+            let next = self.tokens.peek();
+            if matches!(next, Some(Ok(Token::LParen | Token::LBracket)))
+                && self.tokens.peek_span().is_some_and(|span| {
+                    self.has_newline_between(self.tokens.span().end, span.start)
+                })
+            {
+                break;
+            }
+            match next {
                 Some(Ok(Token::LParen)) => {
-                    self.lexer.next(); // consume '('
+                    self.tokens.next(); // consume '('
+                    if self.call_has_star_argument() {
+                        if !self.parse_star_call_arguments() {
+                            return false;
+                        }
+                        base = None;
+                        continue;
+                    }
+
                     let mut arg_count: u8 = 0;
+                    let mut keyword_names: Vec<Object> = Vec::new();
+                    // Whether a generator expression argument already
+                    // consumed the closing parenthesis
+                    let mut closed = false;
 
-                    if self.lexer.clone().next() != Some(Ok(Token::RParen)) {
+                    if self.tokens.peek() != Some(Ok(Token::RParen)) {
                         loop {
+                            // A generator expression can be the only argument,
+                            // without parentheses of its own
+                            if arg_count == 0 && self.is_generator_expression() {
+                                if !self.parse_generator_expression() {
+                                    return false;
+                                }
+                                arg_count = 1;
+                                closed = true;
+                                break;
+                            }
+                            if let Some(name) = self.keyword_argument_name() {
+                                keyword_names.push(Rc::new(ObjectType::String(name)));
+                            } else if !keyword_names.is_empty() {
+                                // Positional argument follows keyword argument
+                                self.had_error = true;
+                                return false;
+                            }
                             if !self.parse_expression() {
                                 self.had_error = true;
                                 return false;
@@ -264,10 +333,11 @@ impl super::Compiler<'_> {
                                 return false;
                             }
                             arg_count += 1;
+                            self.skip_trailing_comma(Token::RParen);
 
-                            match self.lexer.clone().next() {
+                            match self.tokens.peek() {
                                 Some(Ok(Token::Comma)) => {
-                                    self.lexer.next();
+                                    self.tokens.next();
                                 }
                                 Some(Ok(Token::RParen)) => break,
                                 _ => {
@@ -278,94 +348,48 @@ impl super::Compiler<'_> {
                         }
                     }
 
-                    if self.lexer.next() != Some(Ok(Token::RParen)) {
+                    if !closed && self.tokens.next() != Some(Ok(Token::RParen)) {
                         self.had_error = true;
                         return false;
                     }
 
-                    self.chunk.code.push(OpCode::OpCall as u8);
+                    if keyword_names.is_empty() {
+                        self.chunk.code.push(OpCode::OpCall as u8);
+                    } else {
+                        let names_idx =
+                            self.add_constant(Rc::new(ObjectType::Tuple(keyword_names)));
+                        self.emit_constant_operand(OpCode::OpConstant, names_idx);
+                        self.chunk.code.push(OpCode::OpCallKw as u8);
+                    }
                     self.chunk.code.push(arg_count);
-                    base_name_idx = None;
+                    base = None;
                 }
                 Some(Ok(Token::LBracket)) => {
-                    self.lexer.next(); // consume '['
-                    base_name_idx = None;
-
-                    let mut start_pushed = false;
-                    let next_token = self.lexer.clone().next();
-                    if next_token != Some(Ok(Token::Colon))
-                        && next_token != Some(Ok(Token::RBracket))
-                    {
-                        if !self.parse_expression() {
-                            self.had_error = true;
-                            return false;
-                        }
-                        start_pushed = true;
-                    }
-
-                    if self.lexer.clone().next() == Some(Ok(Token::Colon)) {
-                        self.lexer.next(); // consume ':'
-
-                        if !start_pushed {
-                            let nil_idx = self.add_constant(Rc::new(ObjectType::Nil));
-                            self.chunk.code.push(OpCode::OpConstant as u8);
-                            self.chunk.code.push(nil_idx as u8);
-                        }
-
-                        let mut end_pushed = false;
-                        match self.lexer.clone().next() {
-                            Some(Ok(Token::RBracket)) | Some(Ok(Token::Colon)) => {}
-                            _ => {
-                                if !self.parse_expression() {
-                                    self.had_error = true;
-                                    return false;
-                                }
-                                end_pushed = true;
+                    self.tokens.next(); // consume '['
+                    let start = self.chunk.code.len();
+                    let opcode = match self.parse_subscript() {
+                        Some(SubscriptKind::Index) => {
+                            if let Some(base) = base.as_mut() {
+                                let code = self.chunk.code[start..].to_vec();
+                                base.steps.push(PathStep::Index(code));
                             }
+                            OpCode::OpIndex
                         }
-
-                        if !end_pushed {
-                            let nil_idx = self.add_constant(Rc::new(ObjectType::Nil));
-                            self.chunk.code.push(OpCode::OpConstant as u8);
-                            self.chunk.code.push(nil_idx as u8);
+                        // A slice is a new list, stored nowhere
+                        Some(SubscriptKind::Slice) => {
+                            base = None;
+                            OpCode::OpSlice
                         }
-
-                        let mut step_pushed = false;
-                        if self.lexer.clone().next() == Some(Ok(Token::Colon)) {
-                            self.lexer.next(); // consume second ':'
-                            if self.lexer.clone().next() != Some(Ok(Token::RBracket)) {
-                                if !self.parse_expression() {
-                                    self.had_error = true;
-                                    return false;
-                                }
-                                step_pushed = true;
-                            }
-                        }
-
-                        if !step_pushed {
-                            let nil_idx = self.add_constant(Rc::new(ObjectType::Nil));
-                            self.chunk.code.push(OpCode::OpConstant as u8);
-                            self.chunk.code.push(nil_idx as u8);
-                        }
-
-                        if self.lexer.next() != Some(Ok(Token::RBracket)) {
-                            self.had_error = true;
-                            return false;
-                        }
-
-                        self.chunk.code.push(OpCode::OpSlice as u8);
-                    } else {
-                        if self.lexer.next() != Some(Ok(Token::RBracket)) {
+                        None => {
                             self.had_error = true;
                             return false;
                         }
-
-                        self.chunk.code.push(OpCode::OpIndex as u8);
-                    }
+                    };
+                    self.chunk.code.push(opcode as u8);
                 }
                 Some(Ok(Token::Dot)) => {
-                    self.lexer.next(); // consume '.'
-                    let attr_name = match self.lexer.next() {
+                    self.tokens.next(); // consume '.'
+                    let attr_name = match self.tokens.next() {
                         Some(Ok(Token::Identifier(name))) => name,
                         _ => {
                             self.had_error = true;
@@ -376,49 +400,63 @@ impl super::Compiler<'_> {
                     // Check for built-in methods first (for lists, strings, etc.)
                     match attr_name.as_str() {
                         "append" => {
-                            // Handle list.append(value) -> OpAppend + OpSetGlobal
-                            if base_name_idx.is_none() {
+                            // Handle list.append(value) -> OpAppend + rebind
+                            let base = base.take();
+                            if self.tokens.next() != Some(Ok(Token::LParen)) {
                                 self.had_error = true;
                                 return false;
                             }
-                            if self.lexer.next() != Some(Ok(Token::LParen)) {
+                            if !self.parse_expression() {
                                 self.had_error = true;
                                 return false;
                             }
-                            if !self.parse_expression() {
+                            if self.tokens.next() != Some(Ok(Token::RParen)) {
                                 self.had_error = true;
                                 return false;
                             }
-                            if self.lexer.next() != Some(Ok(Token::RParen)) {
+                            self.chunk.code.push(OpCode::OpAppend as u8);
+                            // A list no variable holds is built and dropped
+                            if let Some(base) = base {
+                                self.emit_store_back(base);
+                            }
+                        }
+                        "sort" if base.is_some() => {
+                            // Handle list.sort(key=..., reverse=...) -> OpListSort + rebind
+                            let Some(base) = base.take() else {
+                                unreachable!("guarded by is_some");
+                            };
+                            if !self.parse_sort_arguments() {
                                 self.had_error = true;
                                 return false;
                             }
-                            self.chunk.code.push(OpCode::OpAppend as u8);
-                            self.chunk.code.push(OpCode::OpSetGlobal as u8);
-                            self.chunk
-                                .code
-                                .push(base_name_idx.expect("base variable index") as u8);
+                            self.chunk.code.push(OpCode::OpListSort as u8);
+                            self.emit_store_back(base);
                         }
-                        "lower" => {
-                            // Handle string.lower() -> OpStrLower
-                            if self.lexer.next() != Some(Ok(Token::LParen)) {
+                        "lower" | "upper" => {
+                            // Handle string.lower() / string.upper() -> OpStrLower / OpStrUpper
+                            if self.tokens.next() != Some(Ok(Token::LParen)) {
                                 self.had_error = true;
                                 return false;
                             }
-                            if self.lexer.next() != Some(Ok(Token::RParen)) {
+                            if self.tokens.next() != Some(Ok(Token::RParen)) {
                                 self.had_error = true;
                                 return false;
                             }
-                            self.chunk.code.push(OpCode::OpStrLower as u8);
-                            base_name_idx = None;
+                            let opcode = if attr_name == "lower" {
+                                OpCode::OpStrLower
+                            } else {
+                                OpCode::OpStrUpper
+                            };
+                            self.chunk.code.push(opcode as u8);
+                            base = None;
                         }
                         _ => {
                             // General attribute access for user-defined classes
-                            let attr_idx =
-                                self.add_constant(Rc::new(ObjectType::String(attr_name)));
-                            self.chunk.code.push(OpCode::OpGetAttr as u8);
-                            self.chunk.code.push(attr_idx as u8);
-                            base_name_idx = None;
+                            let attr_idx = self.name_constant(&attr_name);
+                            self.emit_constant_operand(OpCode::OpGetAttr, attr_idx);
+                            if let Some(base) = base.as_mut() {
+                                base.steps.push(PathStep::Attribute(attr_idx));
+                            }
                         }
                     }
                 }
@@ -428,4 +466,285 @@ impl super::Compiler<'_> {
 
         true
     }
+
+    /// Parses the inside of a subscript after its `[`, through the closing
+    /// `]`: an index, or a slice's start, end and step with nil for any that
+    /// are left out.
+    pub(super) fn parse_subscript(&mut self) -> Option<SubscriptKind> {
+        let next_token = self.tokens.peek();
+        let has_start =
+            next_token != Some(Ok(Token::Colon)) && next_token != Some(Ok(Token::RBracket));
+        if has_start && !self.parse_expression() {
+            return None;
+        }
+        if self.tokens.peek() != Some(Ok(Token::Colon)) {
+            return (self.tokens.next() == Some(Ok(Token::RBracket)) && has_start)
+                .then_some(SubscriptKind::Index);
+        }
+        self.tokens.next(); // consume ':'
+        if !has_start {
+            self.emit_nil();
+        }
+
+        match self.tokens.peek() {
+            Some(Ok(Token::RBracket)) | Some(Ok(Token::Colon)) => self.emit_nil(),
+            _ => {
+                if !self.parse_expression() {
+                    return None;
+                }
+            }
+        }
+
+        let has_step = self.tokens.peek() == Some(Ok(Token::Colon));
+        if has_step {
+            self.tokens.next(); // consume second ':'
+        }
+        if !has_step || self.tokens.peek() == Some(Ok(Token::RBracket)) {
+            self.emit_nil();
+        } else if !self.parse_expression() {
+            return None;
+        }
+
+        (self.tokens.next() == Some(Ok(Token::RBracket))).then_some(SubscriptKind::Slice)
+    }
+
+    /// Stores the value on top of the stack back into `base`, leaving it on
+    /// the stack as the value of the expression.
+    fn emit_store_back(&mut self, base: StorePath) {
+        if base.steps.is_empty() {
+            self.emit_set_variable(base.name_idx, base.target);
+        } else {
+            self.chunk.code.push(OpCode::OpDup as u8);
+            self.emit_store_along(&base, &base.steps);
+        }
+    }
+
+    /// Stores the value on top of the stack, consuming it, at the end of
+    /// `steps` taken from the variable of `base`. Lists and dicts are
+    /// values, so storing into an element builds a new container, which is
+    /// stored back in turn; an instance whose attribute is set is shared and
+    /// needs no more.
+    fn emit_store_along(&mut self, base: &StorePath, steps: &[PathStep]) {
+        match steps.split_last() {
+            None => self.emit_store_target(AssignmentTarget::Variable {
+                name_idx: base.name_idx,
+                target: base.target,
+            }),
+            Some((PathStep::Attribute(attr_idx), container)) => {
+                // Stack: [value] -> [object, value]
+                self.emit_value_along(base, container);
+                self.chunk.code.push(OpCode::OpSwap as u8);
+                self.emit_constant_operand(OpCode::OpSetAttr, *attr_idx);
+            }
+            Some((PathStep::Index(code), container)) => {
+                // Stack: [value] -> [collection, index, value]
+                self.emit_value_along(base, container);
+                self.chunk.code.push(OpCode::OpSwap as u8);
+                self.chunk.code.extend_from_slice(code);
+                self.chunk.code.push(OpCode::OpSwap as u8);
+                self.chunk.code.push(OpCode::OpSetIndex as u8);
+                self.emit_store_along(base, container);
+            }
+        }
+    }
+
+    /// Pushes the value reached by taking `steps` from the variable of `base`.
+    fn emit_value_along(&mut self, base: &StorePath, steps: &[PathStep]) {
+        self.emit_get_variable(base.name_idx, base.target);
+        for step in steps {
+            match step {
+                PathStep::Attribute(attr_idx) => {
+                    self.emit_constant_operand(OpCode::OpGetAttr, *attr_idx);
+                }
+                PathStep::Index(code) => {
+                    self.chunk.code.extend_from_slice(code);
+                    self.chunk.code.push(OpCode::OpIndex as u8);
+                }
+            }
+        }
+    }
+
+    /// Consumes a comma directly before `closing`, which Python allows after
+    /// the last element of a bracketed list.
+    pub(super) fn skip_trailing_comma(&mut self, closing: Token) {
+        if self.tokens.peek() == Some(Ok(Token::Comma))
+            && self.tokens.peek_nth(1) == Some(Ok(closing))
+        {
+            self.tokens.next(); // consume ','
+        }
+    }
+
+    /// Consumes `name=` if the next call argument is passed by keyword and
+    /// returns the name.
+    fn keyword_argument_name(&mut self) -> Option<String> {
+        let Some(Ok(Token::Identifier(name))) = self.tokens.peek() else {
+            return None;
+        };
+        if self.tokens.peek_nth(1) != Some(Ok(Token::Assign)) {
+            return None;
+        }
+        self.tokens.advance(2);
+        Some(name)
+    }
+
+    /// Parses `(key=..., reverse=...)` for `list.sort()`, pushing the key (or
+    /// None) and the reverse flag (or False) in that order.
+    fn parse_sort_arguments(&mut self) -> bool {
+        if self.tokens.next() != Some(Ok(Token::LParen)) {
+            return false;
+        }
+
+        let mut key_code = None;
+        let mut reverse_code = None;
+        while self.tokens.peek() != Some(Ok(Token::RParen)) {
+            let Some(name) = self.keyword_argument_name() else {
+                return false; // sort() takes keyword arguments only
+            };
+            let start = self.chunk.code.len();
+            if !self.parse_expression() {
+                return false;
+            }
+            let code = self.chunk.code.split_off(start);
+            let slot = match name.as_str() {
+                "key" => &mut key_code,
+                "reverse" => &mut reverse_code,
+                _ => return false,
+            };
+            if slot.replace(code).is_some() {
+                return false;
+            }
+            if self.tokens.peek() == Some(Ok(Token::Comma)) {
+                self.tokens.next();
+            }
+        }
+        self.tokens.next(); // consume ')'
+
+        for (code, fallback) in [
+            (key_code, ObjectType::Nil),
+            (reverse_code, ObjectType::Boolean(false)),
+        ] {
+            match code {
+                Some(code) => self.chunk.code.extend_from_slice(&code),
+                None => {
+                    let idx = self.add_constant(Rc::new(fallback));
+                    self.emit_constant_operand(OpCode::OpConstant, idx);
+                }
+            }
+        }
+        true
+    }
+
+    /// Checks whether the argument list that follows an already-consumed `(`
+    /// contains a `*iterable` argument at the top level.
+    fn call_has_star_argument(&self) -> bool {
+        let mut lookahead = self.tokens.ahead();
+        let mut depth = 0usize;
+        let mut at_argument_start = true;
+
+        while let Some(Ok(token)) = lookahead.next() {
+            match token {
+                Token::Star if depth == 0 && at_argument_start => return true,
+                Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+                Token::RParen if depth == 0 => return false,
+                Token::RParen | Token::RBracket | Token::RBrace => {
+                    depth = depth.saturating_sub(1);
+                }
+                _ => {}
+            }
+            at_argument_start = depth == 0 && token == Token::Comma;
+        }
+
+        false
+    }
+
+    /// Parses call arguments containing `*iterable` or `**mapping` entries
+    /// into a list of the positional arguments built at runtime, then emits
+    /// OpCallEx to spread it into the call. Once keywords begin, they are
+    /// gathered into a second list of mappings, one per `**mapping` and one
+    /// per `name=value`, and OpCallExKw passes both.
+    fn parse_star_call_arguments(&mut self) -> bool {
+        self.emit_empty_list();
+        let mut keywords = false;
+        let mut unpacked_mapping = false;
+
+        loop {
+            let is_double_star = self.tokens.peek() == Some(Ok(Token::Star))
+                && self.tokens.peek_nth(1) == Some(Ok(Token::Star));
+            let keyword = if is_double_star {
+                self.tokens.advance(2); // consume '**'
+                None
+            } else {
+                self.keyword_argument_name()
+            };
+            let is_star =
+                !is_double_star && keyword.is_none() && self.tokens.peek() == Some(Ok(Token::Star));
+            if is_star {
+                self.tokens.next(); // consume '*'
+            }
+
+            if is_double_star || keyword.is_some() {
+                if !keywords {
+                    self.emit_empty_list();
+                    keywords = true;
+                }
+                unpacked_mapping |= is_double_star;
+            } else if keywords && (!is_star || unpacked_mapping) {
+                // Positional arguments cannot follow keywords, nor `*` follow `**`
+                self.had_error = true;
+                return false;
+            }
+            let behind_keywords = is_star && keywords;
+            if behind_keywords {
+                // `*iterable` after `name=value` extends the arguments beneath
+                self.chunk.code.push(OpCode::OpSwap as u8);
+            }
+            if let Some(name) = keyword {
+                let name_idx = self.add_constant(Rc::new(ObjectType::String(name)));
+                self.emit_constant_operand(OpCode::OpConstant, name_idx);
+            }
+
+            if !self.parse_expression() {
+                self.had_error = true;
+                return false;
+            }
+
+            if is_star {
+                self.chunk.code.push(OpCode::OpListExtend as u8);
+                if behind_keywords {
+                    self.chunk.code.push(OpCode::OpSwap as u8);
+                }
+            } else if keywords && !is_double_star {
+                // `name=value` is a mapping of its own
+                self.chunk.code.push(OpCode::OpBuildDict as u8);
+                self.chunk.code.push(1);
+                self.chunk.code.push(OpCode::OpAppend as u8);
+            } else {
+                self.chunk.code.push(OpCode::OpAppend as u8);
+            }
+
+            self.skip_trailing_comma(Token::RParen);
+            match self.tokens.next() {
+                Some(Ok(Token::Comma)) => {}
+                Some(Ok(Token::RParen)) => break,
+                _ => {
+                    self.had_error = true;
+                    return false;
+                }
+            }
+        }
+
+        let opcode = if keywords {
+            OpCode::OpCallExKw
+        } else {
+            OpCode::OpCallEx
+        };
+        self.chunk.code.push(opcode as u8);
+        true
+    }
+
+    /// Pushes a new empty list.
+    fn emit_empty_list(&mut self) {
+        let empty_list_idx = self.add_constant(Rc::new(ObjectType::List(Vec::new())));
+        self.emit_constant_operand(OpCode::OpConstant, empty_list_idx);
+    }
 }
@@ -0,0 +1,145 @@
+//! Indentation checks matching CPython's tokenizer.
+//!
+//! The parser compares indentation widths directly, so before parsing, the
+//! source is walked one logical line at a time, keeping the stack of open
+//! indentation levels the way CPython does when it emits INDENT and DEDENT
+//! tokens. Lines that indent where no block opens, dedent to a level no
+//! enclosing block uses, or only line up with their block for some tab
+//! sizes are recorded by the position of their first token. A backslash at
+//! the end of a line joins the next line to it.
+
+use super::token_stream::TokenStream;
+use crate::token::Token;
+use std::collections::{HashMap, HashSet};
+
+pub(super) const UNEXPECTED_INDENT: &str = "unexpected indent";
+pub(super) const UNINDENT_MISMATCH: &str = "unindent does not match any outer indentation level";
+pub(super) const EXPECTED_INDENTED_BLOCK: &str = "expected an indented block";
+pub(super) const INCONSISTENT_TABS: &str = "inconsistent use of tabs and spaces in indentation";
+
+/// Tab stops CPython uses to measure indentation.
+const TAB_SIZE: usize = 8;
+
+/// The exception class CPython raises for a syntax error with `message`.
+pub(super) fn error_kind(message: &str) -> &'static str {
+    match message {
+        INCONSISTENT_TABS => "TabError",
+        UNEXPECTED_INDENT | UNINDENT_MISMATCH | EXPECTED_INDENTED_BLOCK => "IndentationError",
+        _ => "SyntaxError",
+    }
+}
+
+/// Indentation of the line containing `position`, with tabs advancing to
+/// the next multiple of eight columns.
+pub(super) fn indent_at(source: &str, position: usize) -> usize {
+    width_at(source, position, TAB_SIZE)
+}
+
+/// The logical lines of a source: lines that start outside any brackets, so
+/// begin a statement rather than continue one.
+#[derive(Debug, Default)]
+pub(super) struct LogicalLines {
+    /// Position of the first token of each logical line
+    pub(super) starts: HashSet<usize>,
+    /// Messages for the logical lines whose indentation is invalid, keyed by
+    /// the position of their first token
+    pub(super) errors: HashMap<usize, &'static str>,
+}
+
+/// Finds where the logical lines of `source` start and which of them are
+/// misindented.
+pub(super) fn check(source: &str) -> LogicalLines {
+    let mut lines = LogicalLines::default();
+    // Open levels as (width, width counting each tab as one column)
+    let mut levels = vec![(0, 0)];
+    let mut tokens = TokenStream::new(source);
+    let mut bracket_depth = 0usize;
+    let mut previous_end = None;
+    let mut opens_block = false;
+
+    while let Some(token) = tokens.next() {
+        let start = tokens.span().start;
+        let starts_line =
+            previous_end.is_none_or(|end| source.get(end..start).is_some_and(breaks_line));
+        if starts_line && bracket_depth == 0 {
+            let width = (indent_at(source, start), width_at(source, start, 1));
+            lines.starts.insert(start);
+            if let Some(message) = check_line(&mut levels, width, opens_block) {
+                lines.errors.insert(start, message);
+            }
+        }
+
+        match token {
+            Ok(Token::LParen | Token::LBracket | Token::LBrace) => bracket_depth += 1,
+            Ok(Token::RParen | Token::RBracket | Token::RBrace) => {
+                bracket_depth = bracket_depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+        opens_block = bracket_depth == 0 && token == Ok(Token::Colon);
+        previous_end = Some(tokens.span().end);
+    }
+
+    lines
+}
+
+/// Whether `gap`, the text between two tokens, ends a physical line that
+/// a backslash does not join to the next one.
+pub(super) fn breaks_line(gap: &str) -> bool {
+    let mut lines = gap.split('\n');
+    lines.next_back();
+    lines.any(|line| {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        // A backslash ending a comment is part of the comment
+        !line.ends_with('\\') || line.contains('#')
+    })
+}
+
+/// Checks a logical line indented `width` against the open `levels`,
+/// updating them. A line in error still opens or closes levels as best it
+/// can, so the lines after it are not reported again.
+fn check_line(
+    levels: &mut Vec<(usize, usize)>,
+    width: (usize, usize),
+    opens_block: bool,
+) -> Option<&'static str> {
+    let (column, alt_column) = width;
+    let &(top, alt_top) = levels.last()?;
+
+    if column > top {
+        levels.push(width);
+        if !opens_block {
+            return Some(UNEXPECTED_INDENT);
+        }
+        return (alt_column <= alt_top).then_some(INCONSISTENT_TABS);
+    }
+
+    while levels.last().is_some_and(|&(level, _)| column < level) {
+        levels.pop();
+    }
+    match levels.last() {
+        Some(&(level, alt_level)) if level == column => {
+            (alt_level != alt_column).then_some(INCONSISTENT_TABS)
+        }
+        _ => {
+            levels.push(width);
+            Some(UNINDENT_MISMATCH)
+        }
+    }
+}
+
+/// Width of the indentation of the line containing `position`, with tabs
+/// advancing to the next multiple of `tab_size`.
+fn width_at(source: &str, position: usize, tab_size: usize) -> usize {
+    let before = source.get(..position).unwrap_or(source);
+    let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+    let mut width = 0;
+    for ch in before[line_start..].chars() {
+        match ch {
+            ' ' => width += 1,
+            '\t' => width = (width / tab_size + 1) * tab_size,
+            _ => break,
+        }
+    }
+    width
+}
@@ -0,0 +1,323 @@
+//! Warnings about code that compiles but is likely a mistake.
+//!
+//! `Compiler::lint` compiles the source with the compiler noting, as it
+//! goes, the locals each function assigns but never reads, names that
+//! shadow a builtin and statements that follow a `return`, `raise`, `break`
+//! or `continue` in the same block. Once the whole module is compiled, the
+//! names the functions read as globals are checked against those the module
+//! defines and the builtins, since a function may use a global defined
+//! after it.
+
+use super::types::FunctionScope;
+use super::Compiler;
+use crate::bytecode::{slot_index, Chunk, OpCode};
+use crate::compiler::syntax_error::SyntaxError;
+use crate::object::ObjectType;
+use crate::token::Token;
+use crate::vm::exceptions::register_exceptions;
+use crate::vm::namespace::Namespace;
+use crate::vm::native::register_builtins;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// What a lint warns about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// A local assigned a value that is never read
+    UnusedVariable,
+    /// A variable or parameter named like a builtin, hiding it
+    ShadowedBuiltin,
+    /// A name a function reads that nothing defines
+    UndefinedName,
+    /// A statement no path through its block reaches
+    UnreachableCode,
+}
+
+impl LintKind {
+    /// The short name the lint is reported under.
+    pub fn code(self) -> &'static str {
+        match self {
+            LintKind::UnusedVariable => "unused-variable",
+            LintKind::ShadowedBuiltin => "shadowed-builtin",
+            LintKind::UndefinedName => "undefined-name",
+            LintKind::UnreachableCode => "unreachable-code",
+        }
+    }
+}
+
+/// A warning found while linting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    /// 1-based line of the offending name or statement
+    pub line: usize,
+    /// 1-based column, counted in characters
+    pub column: usize,
+    /// Human readable description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (line {}, column {})",
+            self.kind.code(),
+            self.message,
+            self.line,
+            self.column
+        )
+    }
+}
+
+/// Whether the statements read so far in a block left it unconditionally,
+/// so that the next one cannot run.
+#[derive(Debug, Default)]
+pub(super) struct BlockExit {
+    /// The keyword of the statement that left the block
+    keyword: Option<&'static str>,
+    /// Whether the unreachable statement was reported, which is done once
+    /// per block
+    reported: bool,
+}
+
+/// Names of the builtins, which functions may read without defining.
+fn builtin_names() -> &'static HashSet<String> {
+    static NAMES: OnceLock<HashSet<String>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let mut builtins = Namespace::default();
+        register_builtins(&mut builtins);
+        register_exceptions(&mut builtins);
+        builtins.into_iter().map(|(name, _)| name).collect()
+    })
+}
+
+impl<'a> Compiler<'a> {
+    /// Compiles `source` and returns the lints found in it, sorted by
+    /// position, or its syntax errors should it not compile.
+    pub fn lint(source: &'a str, module: &str) -> Result<Vec<Lint>, Vec<SyntaxError>> {
+        let mut compiler = Self::new(source, module);
+        compiler.lints = Some(Vec::new());
+        compiler.compile_statements()?;
+        let chunk = std::mem::take(&mut compiler.chunk);
+        compiler.lint_module(&chunk);
+        let mut lints = compiler.lints.unwrap_or_default();
+        lints.sort_by_key(|lint| (lint.line, lint.column));
+        Ok(lints)
+    }
+
+    /// Reports the statement starting at `start`, read as `token`, if an
+    /// earlier statement of its block already left it, and notes whether
+    /// this one leaves it.
+    pub(super) fn check_reachable(&mut self, exit: &mut BlockExit, token: &Token, start: usize) {
+        if let Some(keyword) = exit.keyword {
+            if !exit.reported {
+                exit.reported = true;
+                self.record_lint(
+                    LintKind::UnreachableCode,
+                    start,
+                    format!("code after '{}' is unreachable", keyword),
+                );
+            }
+        }
+        exit.keyword = exit.keyword.or(match token {
+            Token::Return => Some("return"),
+            Token::Raise => Some("raise"),
+            Token::Break => Some("break"),
+            Token::Continue => Some("continue"),
+            _ => None,
+        });
+    }
+
+    /// Notes the line of the assignment declaring the newest local, which
+    /// is reported should the local never be read.
+    pub(super) fn note_assigned_local(&mut self) {
+        let line = self.line_at(self.tokens.span().start);
+        if let Some(local) = self
+            .function_scopes
+            .last_mut()
+            .and_then(|scope| scope.locals.last_mut())
+        {
+            local.line = Some(line);
+        }
+    }
+
+    /// Reports the parameters and locals of a function, compiled to
+    /// `chunk` from a definition whose name starts at `name_start`, that
+    /// shadow builtins or are assigned but never read.
+    pub(super) fn lint_function_scope(
+        &mut self,
+        scope: &FunctionScope,
+        chunk: &Chunk,
+        name_start: usize,
+    ) {
+        if self.lints.is_none() {
+            return;
+        }
+        for parameter in &scope.parameters {
+            let position = self.name_position(&parameter.name, name_start);
+            self.lint_shadowing(&parameter.name, position);
+        }
+
+        let read = read_slots(chunk);
+        for (index, local) in scope.locals.iter().enumerate() {
+            let Some(line) = local.line else {
+                continue;
+            };
+            let position = self.name_position(&local.name, self.line_starts[line - 1]);
+            self.lint_shadowing(&local.name, position);
+            let slot = scope.parameters.len() + 1 + index;
+            if !read.contains(&slot) && !local.name.starts_with('_') {
+                self.record_lint(
+                    LintKind::UnusedVariable,
+                    position,
+                    format!(
+                        "local variable '{}' is assigned to but never used",
+                        local.name
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Reports the module's globals that shadow builtins, and the names
+    /// the functions in `chunk` read as globals that neither the module
+    /// nor the builtins define.
+    fn lint_module(&mut self, chunk: &Chunk) {
+        // The VM binds `__name__` before the module runs
+        let mut defined = HashSet::from(["__name__".to_string()]);
+        let mut reads = Vec::new();
+        collect_globals(chunk, 0, &mut defined, &mut reads);
+
+        let mut shadowed = HashSet::new();
+        for (offset, opcode, operands) in chunk.instructions() {
+            if opcode.long_form() != OpCode::OpDefineGlobalLong {
+                continue;
+            }
+            if let Some(name) = constant_name(chunk, opcode, operands) {
+                if builtin_names().contains(name) && shadowed.insert(name) {
+                    let line = chunk.line_at(offset).unwrap_or(1);
+                    let position = self.name_position(name, self.line_starts[line - 1]);
+                    self.lint_shadowing(name, position);
+                }
+            }
+        }
+
+        let mut reported = HashSet::new();
+        for (name, line) in reads {
+            if defined.contains(&name)
+                || builtin_names().contains(&name)
+                || !reported.insert((name.clone(), line))
+            {
+                continue;
+            }
+            let position = self.name_position(&name, self.line_starts[line - 1]);
+            self.record_lint(
+                LintKind::UndefinedName,
+                position,
+                format!("name '{}' is not defined", name),
+            );
+        }
+    }
+
+    fn lint_shadowing(&mut self, name: &str, position: usize) {
+        if builtin_names().contains(name) {
+            self.record_lint(
+                LintKind::ShadowedBuiltin,
+                position,
+                format!("'{}' shadows the builtin of the same name", name),
+            );
+        }
+    }
+
+    /// Where `name` first appears as a whole word at or after `from`, or
+    /// `from` itself if it does not.
+    fn name_position(&self, name: &str, from: usize) -> usize {
+        let is_name_char = |ch: char| ch.is_alphanumeric() || ch == '_';
+        let rest = self.source.get(from..).unwrap_or_default();
+        rest.match_indices(name)
+            .find(|&(index, _)| {
+                let before = rest[..index].chars().next_back();
+                let after = rest[index + name.len()..].chars().next();
+                !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+            })
+            .map_or(from, |(index, _)| from + index)
+    }
+
+    fn record_lint(&mut self, kind: LintKind, position: usize, message: String) {
+        let (line, column) = self.line_and_column(position);
+        if let Some(lints) = &mut self.lints {
+            lints.push(Lint {
+                kind,
+                line,
+                column,
+                message,
+            });
+        }
+    }
+}
+
+/// The local slots `chunk` reads, directly or by a function defined in it
+/// capturing them.
+fn read_slots(chunk: &Chunk) -> HashSet<usize> {
+    let mut read: HashSet<usize> = chunk
+        .instructions()
+        .filter(|&(_, opcode, _)| opcode.long_form() == OpCode::OpGetLocalLong)
+        .map(|(_, _, operands)| slot_index(operands))
+        .collect();
+    for constant in &chunk.constants {
+        if let ObjectType::FunctionPrototype(prototype) = &**constant {
+            read.extend(
+                prototype
+                    .upvalues
+                    .iter()
+                    .filter(|upvalue| upvalue.is_local)
+                    .map(|upvalue| upvalue.index),
+            );
+        }
+    }
+    read
+}
+
+/// Collects the global names `chunk` and the functions in it define, and
+/// the names functions read as globals along with the line reading them.
+/// `depth` counts the functions `chunk` is nested in.
+fn collect_globals(
+    chunk: &Chunk,
+    depth: usize,
+    defined: &mut HashSet<String>,
+    reads: &mut Vec<(String, usize)>,
+) {
+    for (offset, opcode, operands) in chunk.instructions() {
+        let Some(name) = constant_name(chunk, opcode, operands) else {
+            continue;
+        };
+        match opcode.long_form() {
+            OpCode::OpDefineGlobalLong | OpCode::OpSetGlobalLong => {
+                defined.insert(name.to_string());
+            }
+            OpCode::OpGetGlobalLong if depth > 0 => {
+                reads.push((name.to_string(), chunk.line_at(offset).unwrap_or(1)));
+            }
+            _ => {}
+        }
+    }
+    for constant in &chunk.constants {
+        if let ObjectType::FunctionPrototype(prototype) = &**constant {
+            collect_globals(&prototype.chunk, depth + 1, defined, reads);
+        }
+    }
+}
+
+/// The string constant an instruction taking a constant names, if it
+/// names one.
+fn constant_name<'c>(chunk: &'c Chunk, opcode: OpCode, operands: &[u8]) -> Option<&'c str> {
+    if !opcode.takes_constant() {
+        return None;
+    }
+    match &**chunk.constants.get(slot_index(operands))? {
+        ObjectType::String(name) => Some(name),
+        _ => None,
+    }
+}
@@ -1,32 +1,35 @@
 //! Literal and collection parsing for the compiler.
 //!
 //! This module contains functions for parsing literal values including
-//! lists, dictionaries, and list comprehensions.
+//! lists, dictionaries, list comprehensions and generator expressions.
 
 use crate::bytecode::OpCode;
-use crate::object::{Object, ObjectType};
+use crate::object::{FunctionPrototype, ObjectType};
+use crate::sync::Rc;
 use crate::token::Token;
-use std::rc::Rc;
+use crate::vm::opcodes;
 
-use super::types::ComprehensionEnd;
+use super::token_stream::TokenStream;
+use super::types::{FunctionScope, Parameter};
 
-impl super::Compiler<'_> {
+impl<'a> super::Compiler<'a> {
     /// Checks if the current position in the token stream is a list comprehension.
     /// A list comprehension contains a 'for' keyword at the top level before the closing bracket.
     pub(super) fn is_list_comprehension(&self) -> bool {
-        let lookahead = self.lexer.clone();
-        let mut bracket_depth = 0;
+        let lookahead = self.tokens.ahead();
+        let mut depth = 0;
 
         for token_result in lookahead {
             match token_result {
-                Ok(Token::For) if bracket_depth == 0 => return true,
-                Ok(Token::RBracket) if bracket_depth == 0 => return false,
-                Ok(Token::LBracket) => bracket_depth += 1,
-                Ok(Token::RBracket) => {
-                    if bracket_depth == 0 {
+                Ok(Token::For) if depth == 0 => return true,
+                Ok(Token::RBracket) if depth == 0 => return false,
+                // A `for` nested in brackets belongs to a generator expression
+                Ok(Token::LParen | Token::LBracket | Token::LBrace) => depth += 1,
+                Ok(Token::RParen | Token::RBracket | Token::RBrace) => {
+                    if depth == 0 {
                         return false;
                     }
-                    bracket_depth -= 1;
+                    depth -= 1;
                 }
                 Err(_) => return false,
                 _ => {}
@@ -48,22 +51,176 @@ impl super::Compiler<'_> {
         let element_code = self.chunk.code[element_start..element_end].to_vec();
         self.chunk.code.truncate(element_start);
 
-        self.compile_comprehension(element_code, ComprehensionEnd::RBracket)
+        self.compile_comprehension(element_code)
     }
 
-    /// Compiles a comprehension (list or generator) into bytecode.
-    /// Handles the iteration, optional filtering, and result accumulation.
-    pub(super) fn compile_comprehension(
+    /// Checks if the tokens ahead begin a generator expression: an
+    /// expression followed by `for` at the top level, before the `)` or `,`
+    /// that would end it.
+    pub(super) fn is_generator_expression(&self) -> bool {
+        let mut depth = 0;
+        for token_result in self.tokens.ahead() {
+            match token_result {
+                Ok(Token::For) if depth == 0 => return true,
+                Ok(Token::RParen | Token::Comma) if depth == 0 => return false,
+                Ok(Token::LParen | Token::LBracket | Token::LBrace) => depth += 1,
+                Ok(Token::RParen | Token::RBracket | Token::RBrace) => depth -= 1,
+                Err(_) => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Parses a generator expression, `expr for var in iterable if condition`,
+    /// through its closing parenthesis.
+    ///
+    /// The loop runs as a `<genexpr>` function of its own, taking the
+    /// iterable (evaluated here, where the generator is created) as its
+    /// argument and yielding each element; OpMakeGenerator pairs the two
+    /// into a generator object that runs the loop as items are requested.
+    pub(super) fn parse_generator_expression(&mut self) -> bool {
+        // The element is compiled inside the loop, once its variable is known
+        let element_tokens = self.tokens.clone();
+        let mut depth = 0;
+        loop {
+            match self.tokens.next() {
+                Some(Ok(Token::For)) if depth == 0 => break,
+                Some(Ok(Token::LParen | Token::LBracket | Token::LBrace)) => depth += 1,
+                Some(Ok(Token::RParen | Token::RBracket | Token::RBrace)) => depth -= 1,
+                Some(Ok(_)) => {}
+                _ => {
+                    self.had_error = true;
+                    return false;
+                }
+            }
+        }
+        let loop_var = match self.tokens.next() {
+            Some(Ok(Token::Identifier(name))) => name,
+            _ => {
+                self.had_error = true;
+                return false;
+            }
+        };
+        if self.tokens.next() != Some(Ok(Token::In)) || !self.parse_disjunction() {
+            self.had_error = true;
+            return false;
+        }
+
+        let outer_chunk = std::mem::take(&mut self.chunk);
+        let outer_loop_stack = std::mem::take(&mut self.loop_stack);
+        let outer_stack_temporaries = std::mem::take(&mut self.stack_temporaries);
+        let outer_unwind_stack = std::mem::take(&mut self.unwind_stack);
+        let qualname = self.qualified_name("<genexpr>");
+        self.function_name_stack
+            .push("<genexpr>.<locals>".to_string());
+        self.function_scopes
+            .push(FunctionScope::new_with_params(vec![Parameter::new(
+                ".0".to_string(),
+                None,
+            )]));
+        self.function_depth += 1;
+
+        let compiled = self.compile_generator_body(loop_var, element_tokens);
+
+        self.function_depth -= 1;
+        self.function_name_stack.pop();
+        let scope = self
+            .function_scopes
+            .pop()
+            .expect("generator expression scope should exist");
+        self.chunk.compact_constants();
+        let body_chunk = std::mem::replace(&mut self.chunk, outer_chunk);
+        self.loop_stack = outer_loop_stack;
+        self.stack_temporaries = outer_stack_temporaries;
+        self.unwind_stack = outer_unwind_stack;
+        if !compiled || self.had_error {
+            self.had_error = true;
+            return false;
+        }
+
+        let mut prototype = FunctionPrototype::new(
+            "<genexpr>".to_string(),
+            1,
+            body_chunk,
+            scope.upvalues,
+            self.module.clone(),
+        );
+        prototype.qualname = qualname;
+        prototype.parameter_names = vec![".0".to_string()];
+        prototype.local_count = scope.max_locals;
+        let prototype_idx =
+            self.add_constant(Rc::new(ObjectType::FunctionPrototype(Rc::new(prototype))));
+        self.emit_constant_operand(OpCode::OpMakeFunction, prototype_idx);
+        self.chunk.code.push(OpCode::OpMakeGenerator as u8);
+        true
+    }
+
+    /// Compiles the loop of a generator expression whose tokens are just
+    /// past its iterable, reading the element from `element_tokens`.
+    fn compile_generator_body(
         &mut self,
-        mut element_code: Vec<u8>,
-        terminator: ComprehensionEnd,
+        loop_var: String,
+        element_tokens: TokenStream<'a>,
     ) -> bool {
-        if self.lexer.next() != Some(Ok(Token::For)) {
+        let Some((var_slot, _)) = self.declare_local(loop_var) else {
+            return false;
+        };
+
+        self.emit_slot(OpCode::OpGetLocal, 1);
+        let zero_idx = self.add_constant(Rc::new(ObjectType::Integer(0)));
+        self.emit_constant_operand(OpCode::OpConstant, zero_idx);
+
+        let loop_start = self.chunk.code.len();
+        let exit_jump = self.emit_jump(OpCode::OpIterNext);
+        self.emit_slot(OpCode::OpSetLocal, var_slot);
+        self.chunk.code.push(OpCode::OpPop as u8);
+
+        let skip_jump = if self.tokens.peek() == Some(Ok(Token::If)) {
+            self.tokens.next(); // consume 'if'
+            if !self.parse_disjunction() {
+                return false;
+            }
+            let jump = self.emit_jump(OpCode::OpJumpIfFalse);
+            self.chunk.code.push(OpCode::OpPop as u8);
+            Some(jump)
+        } else {
+            None
+        };
+
+        let end_tokens = std::mem::replace(&mut self.tokens, element_tokens);
+        let element_compiled = self.parse_expression();
+        self.tokens = end_tokens;
+        if !element_compiled {
+            return false;
+        }
+        self.chunk.code.push(OpCode::OpYield as u8);
+
+        if let Some(skip_jump) = skip_jump {
+            let after_skip = self.emit_jump(OpCode::OpJump);
+            self.patch_jump(skip_jump);
+            self.chunk.code.push(OpCode::OpPop as u8);
+            self.patch_jump(after_skip);
+        }
+        self.emit_loop(loop_start);
+        self.patch_jump(exit_jump);
+
+        let nil_idx = self.add_constant(Rc::new(ObjectType::Nil));
+        self.emit_constant_operand(OpCode::OpConstant, nil_idx);
+        self.chunk.code.push(OpCode::OpReturn as u8);
+
+        self.tokens.next() == Some(Ok(Token::RParen))
+    }
+
+    /// Compiles a list comprehension into bytecode, through its closing bracket.
+    /// Handles the iteration, optional filtering, and result accumulation.
+    pub(super) fn compile_comprehension(&mut self, mut element_code: Vec<u8>) -> bool {
+        if self.tokens.next() != Some(Ok(Token::For)) {
             self.had_error = true;
             return false;
         }
 
-        let loop_var = match self.lexer.next() {
+        let loop_var = match self.tokens.next() {
             Some(Ok(Token::Identifier(name))) => name,
             _ => {
                 self.had_error = true;
@@ -71,19 +228,16 @@ impl super::Compiler<'_> {
             }
         };
 
-        if self.lexer.next() != Some(Ok(Token::In)) {
+        if self.tokens.next() != Some(Ok(Token::In)) {
             self.had_error = true;
             return false;
         }
 
-        let loop_var_idx = self.add_constant(Rc::new(ObjectType::String(loop_var.clone())));
+        let loop_var_idx = self.name_constant(&loop_var);
         let mut loop_var_local = None;
         if self.function_depth > 0 {
-            if let Some((idx, is_new)) = self.declare_local(loop_var.clone()) {
+            if let Some((idx, _)) = self.declare_local(loop_var.clone()) {
                 loop_var_local = Some(idx);
-                if is_new {
-                    self.emit_nil();
-                }
             }
             if self.had_error {
                 return false;
@@ -91,97 +245,78 @@ impl super::Compiler<'_> {
         }
         let target_indices = self.constant_indices_for_string(&loop_var);
         if let Some(local_idx) = loop_var_local {
-            self.rewrite_globals_to_local(&mut element_code, &target_indices, local_idx);
+            element_code = self.rewrite_globals_to_local(&element_code, &target_indices, local_idx);
         }
 
         let nil_idx = self.add_constant(Rc::new(ObjectType::Nil));
-        self.chunk.code.push(OpCode::OpConstant as u8);
-        self.chunk.code.push(nil_idx as u8);
+        self.emit_constant_operand(OpCode::OpConstant, nil_idx);
         if let Some(local_idx) = loop_var_local {
-            self.chunk.code.push(OpCode::OpSetLocal as u8);
-            self.chunk.code.push(local_idx as u8);
+            self.emit_slot(OpCode::OpSetLocal, local_idx);
             self.chunk.code.push(OpCode::OpPop as u8);
         } else {
-            self.chunk.code.push(OpCode::OpDefineGlobal as u8);
-            self.chunk.code.push(loop_var_idx as u8);
+            self.emit_constant_operand(OpCode::OpDefineGlobal, loop_var_idx);
         }
 
         let result_name = self.next_list_comp_result_name();
-        let result_name_idx = self.add_constant(Rc::new(ObjectType::String(result_name.clone())));
+        let result_name_idx = self.name_constant(&result_name);
         let mut result_local = None;
         if self.function_depth > 0 {
-            if let Some((idx, is_new)) = self.declare_local(result_name.clone()) {
+            if let Some((idx, _)) = self.declare_local(result_name.clone()) {
                 result_local = Some(idx);
-                if is_new {
-                    self.emit_nil();
-                }
             }
             if self.had_error {
                 return false;
             }
         }
         let empty_list_idx = self.add_constant(Rc::new(ObjectType::List(Vec::new())));
-        self.chunk.code.push(OpCode::OpConstant as u8);
-        self.chunk.code.push(empty_list_idx as u8);
+        self.emit_constant_operand(OpCode::OpConstant, empty_list_idx);
         if let Some(local_idx) = result_local {
-            self.chunk.code.push(OpCode::OpSetLocal as u8);
-            self.chunk.code.push(local_idx as u8);
+            self.emit_slot(OpCode::OpSetLocal, local_idx);
             self.chunk.code.push(OpCode::OpPop as u8);
         } else {
-            self.chunk.code.push(OpCode::OpDefineGlobal as u8);
-            self.chunk.code.push(result_name_idx as u8);
+            self.emit_constant_operand(OpCode::OpDefineGlobal, result_name_idx);
         }
 
-        if !self.parse_expression() {
+        // The iterable and filter cannot be conditional expressions, which
+        // would take the filter's `if` as their own
+        if !self.parse_disjunction() {
             self.had_error = true;
             return false;
         }
 
-        let filter_code = if self.lexer.clone().next() == Some(Ok(Token::If)) {
-            self.lexer.next(); // consume 'if'
+        let filter_code = if self.tokens.peek() == Some(Ok(Token::If)) {
+            self.tokens.next(); // consume 'if'
             let filter_start = self.chunk.code.len();
-            if !self.parse_expression() {
+            if !self.parse_disjunction() {
                 self.had_error = true;
                 return false;
             }
             let filter_end = self.chunk.code.len();
-            let code = self.chunk.code[filter_start..filter_end].to_vec();
+            let mut code = self.chunk.code[filter_start..filter_end].to_vec();
             self.chunk.code.truncate(filter_start);
-            let mut code = code;
             if let Some(local_idx) = loop_var_local {
-                self.rewrite_globals_to_local(&mut code, &target_indices, local_idx);
+                code = self.rewrite_globals_to_local(&code, &target_indices, local_idx);
             }
             Some(code)
         } else {
             None
         };
 
-        let expected_terminator = match terminator {
-            ComprehensionEnd::RBracket => Token::RBracket,
-            ComprehensionEnd::RParen => Token::RParen,
-        };
-
-        if self.lexer.next() != Some(Ok(expected_terminator)) {
+        if self.tokens.next() != Some(Ok(Token::RBracket)) {
             self.had_error = true;
             return false;
         }
 
         let zero_idx = self.add_constant(Rc::new(ObjectType::Integer(0)));
-        self.chunk.code.push(OpCode::OpConstant as u8);
-        self.chunk.code.push(zero_idx as u8);
+        self.emit_constant_operand(OpCode::OpConstant, zero_idx);
 
         let loop_start = self.chunk.code.len();
-        self.chunk.code.push(OpCode::OpIterNext as u8);
-        let iter_jump_pos = self.chunk.code.len();
-        self.chunk.code.push(0);
-        self.chunk.code.push(0);
+        let iter_jump_pos = self.emit_jump(OpCode::OpIterNext);
 
         if let Some(local_idx) = loop_var_local {
-            self.chunk.code.push(OpCode::OpSetLocal as u8);
-            self.chunk.code.push(local_idx as u8);
+            self.emit_slot(OpCode::OpSetLocal, local_idx);
         } else {
-            self.chunk.code.push(OpCode::OpSetGlobal as u8);
-            self.chunk.code.push(loop_var_idx as u8);
+            self.emit_constant_operand(OpCode::OpSetGlobal, loop_var_idx);
         }
         self.chunk.code.push(OpCode::OpPop as u8);
 
@@ -191,20 +326,16 @@ impl super::Compiler<'_> {
             self.chunk.code.push(OpCode::OpPop as u8);
 
             if let Some(local_idx) = result_local {
-                self.chunk.code.push(OpCode::OpGetLocal as u8);
-                self.chunk.code.push(local_idx as u8);
+                self.emit_slot(OpCode::OpGetLocal, local_idx);
             } else {
-                self.chunk.code.push(OpCode::OpGetGlobal as u8);
-                self.chunk.code.push(result_name_idx as u8);
+                self.emit_constant_operand(OpCode::OpGetGlobal, result_name_idx);
             }
             self.chunk.code.extend_from_slice(&element_code);
             self.chunk.code.push(OpCode::OpAppend as u8);
             if let Some(local_idx) = result_local {
-                self.chunk.code.push(OpCode::OpSetLocal as u8);
-                self.chunk.code.push(local_idx as u8);
+                self.emit_slot(OpCode::OpSetLocal, local_idx);
             } else {
-                self.chunk.code.push(OpCode::OpSetGlobal as u8);
-                self.chunk.code.push(result_name_idx as u8);
+                self.emit_constant_operand(OpCode::OpSetGlobal, result_name_idx);
             }
             self.chunk.code.push(OpCode::OpPop as u8);
 
@@ -214,20 +345,16 @@ impl super::Compiler<'_> {
             self.patch_jump(after_append);
         } else {
             if let Some(local_idx) = result_local {
-                self.chunk.code.push(OpCode::OpGetLocal as u8);
-                self.chunk.code.push(local_idx as u8);
+                self.emit_slot(OpCode::OpGetLocal, local_idx);
             } else {
-                self.chunk.code.push(OpCode::OpGetGlobal as u8);
-                self.chunk.code.push(result_name_idx as u8);
+                self.emit_constant_operand(OpCode::OpGetGlobal, result_name_idx);
             }
             self.chunk.code.extend_from_slice(&element_code);
             self.chunk.code.push(OpCode::OpAppend as u8);
             if let Some(local_idx) = result_local {
-                self.chunk.code.push(OpCode::OpSetLocal as u8);
-                self.chunk.code.push(local_idx as u8);
+                self.emit_slot(OpCode::OpSetLocal, local_idx);
             } else {
-                self.chunk.code.push(OpCode::OpSetGlobal as u8);
-                self.chunk.code.push(result_name_idx as u8);
+                self.emit_constant_operand(OpCode::OpSetGlobal, result_name_idx);
             }
             self.chunk.code.push(OpCode::OpPop as u8);
         }
@@ -236,122 +363,112 @@ impl super::Compiler<'_> {
         self.patch_jump(iter_jump_pos);
 
         if let Some(local_idx) = result_local {
-            self.chunk.code.push(OpCode::OpGetLocal as u8);
-            self.chunk.code.push(local_idx as u8);
+            self.emit_slot(OpCode::OpGetLocal, local_idx);
         } else {
-            self.chunk.code.push(OpCode::OpGetGlobal as u8);
-            self.chunk.code.push(result_name_idx as u8);
+            self.emit_constant_operand(OpCode::OpGetGlobal, result_name_idx);
         }
 
         true
     }
 
-    /// Parses a list literal: [1, 2, 3] or ["a", "b", "c"]
-    /// Supports nested lists and dictionaries as elements.
-    pub(super) fn parse_list_literal(&mut self) -> Option<Object> {
-        let mut elements: Vec<Object> = Vec::new();
+    /// Parses a list literal: `[1, -2, [x, "y"]]`.
+    /// Elements are arbitrary expressions. A list of constants is folded into
+    /// a single constant; otherwise the elements are gathered by OpBuildList.
+    pub(super) fn parse_list_literal(&mut self) -> bool {
+        let start = self.chunk.code.len();
+        let mut count = 0;
 
-        loop {
-            match self.lexer.clone().next() {
-                Some(Ok(Token::RBracket)) => {
-                    self.lexer.next(); // consume closing bracket
-                    break;
-                }
-                Some(Ok(Token::Comma)) => {
-                    self.lexer.next(); // consume comma between elements
-                }
-                Some(Ok(Token::Integer(value))) => {
-                    self.lexer.next(); // consume integer
-                    elements.push(Rc::new(ObjectType::Integer(value)));
-                }
-                Some(Ok(Token::Float(value))) => {
-                    self.lexer.next(); // consume float
-                    elements.push(Rc::new(ObjectType::Float(value)));
-                }
-                Some(Ok(Token::String(value))) => {
-                    self.lexer.next(); // consume string
-                    elements.push(Rc::new(ObjectType::String(value)));
-                }
-                Some(Ok(Token::LBracket)) => {
-                    self.lexer.next(); // consume '['
-                    if let Some(nested) = self.parse_list_literal() {
-                        elements.push(nested);
-                    } else {
-                        return None;
-                    }
-                }
-                Some(Ok(Token::LBrace)) => {
-                    self.lexer.next(); // consume '{'
-                    if let Some(dict) = self.parse_dict_literal() {
-                        elements.push(dict);
-                    } else {
-                        return None;
-                    }
-                }
-                Some(Ok(Token::Identifier(_))) => {
-                    self.had_error = true;
-                    return None;
-                }
-                _ => {
-                    self.had_error = true;
-                    return None;
-                }
+        while self.tokens.peek() != Some(Ok(Token::RBracket)) {
+            if !self.parse_expression() {
+                self.had_error = true;
+                return false;
+            }
+            count += 1;
+            if !self.consume_element_separator(Token::RBracket) {
+                return false;
             }
         }
-
-        Some(Rc::new(ObjectType::List(elements)))
+        self.tokens.next(); // consume closing bracket
+
+        if let Some(operands) = self.constant_run(start) {
+            let elements = operands
+                .iter()
+                .map(|&idx| self.chunk.constants[idx].clone())
+                .collect();
+            let list = opcodes::collections::op_build_list(elements);
+            self.replace_with_constant(start, &operands, list);
+            return true;
+        }
+        self.emit_build(OpCode::OpBuildList, count)
     }
 
-    /// Parses a dictionary literal: {"key1": value1, "key2": value2}
-    /// Keys must be strings, values can be integers, floats, or strings.
-    /// Duplicate keys are handled by keeping the last value.
-    pub(super) fn parse_dict_literal(&mut self) -> Option<Object> {
-        let mut entries: Vec<(String, Object)> = Vec::new();
-
-        loop {
-            match self.lexer.clone().next() {
-                Some(Ok(Token::RBrace)) => {
-                    self.lexer.next(); // consume closing brace
-                    break;
-                }
-                Some(Ok(Token::Comma)) => {
-                    self.lexer.next(); // consume comma between entries
-                }
-                Some(Ok(Token::String(key))) => {
-                    self.lexer.next(); // consume key
+    /// Parses a dictionary literal: `{"key": value, "other": [1, 2]}`.
+    /// Keys and values are arbitrary expressions; keys must evaluate to
+    /// hashable values. Duplicate keys, including equal numbers such as `1`
+    /// and `1.0`, are handled by keeping the last value.
+    pub(super) fn parse_dict_literal(&mut self) -> bool {
+        let start = self.chunk.code.len();
+        let mut count = 0;
 
-                    if self.lexer.next() != Some(Ok(Token::Colon)) {
-                        self.had_error = true;
-                        return None;
-                    }
+        while self.tokens.peek() != Some(Ok(Token::RBrace)) {
+            if !self.parse_expression() {
+                self.had_error = true;
+                return false;
+            }
+            if self.tokens.next() != Some(Ok(Token::Colon)) {
+                self.had_error = true;
+                return false;
+            }
+            if !self.parse_expression() {
+                self.had_error = true;
+                return false;
+            }
+            count += 1;
+            if !self.consume_element_separator(Token::RBrace) {
+                return false;
+            }
+        }
+        self.tokens.next(); // consume closing brace
+
+        if let Some(operands) = self.constant_run(start) {
+            let items = operands
+                .iter()
+                .map(|&idx| self.chunk.constants[idx].clone())
+                .collect();
+            // An unhashable key is left for OpBuildDict to report at runtime
+            if let Ok(dict) = opcodes::collections::op_build_dict(items) {
+                self.replace_with_constant(start, &operands, dict);
+                return true;
+            }
+        }
+        self.emit_build(OpCode::OpBuildDict, count)
+    }
 
-                    let value = match self.lexer.next() {
-                        Some(Ok(Token::Integer(value))) => Rc::new(ObjectType::Integer(value)),
-                        Some(Ok(Token::Float(value))) => Rc::new(ObjectType::Float(value)),
-                        Some(Ok(Token::String(value))) => Rc::new(ObjectType::String(value)),
-                        _ => {
-                            self.had_error = true;
-                            return None;
-                        }
-                    };
-
-                    if let Some(position) = entries
-                        .iter()
-                        .position(|(existing_key, _)| existing_key == &key)
-                    {
-                        entries[position].1 = value;
-                    } else {
-                        entries.push((key, value));
-                    }
-                }
-                _ => {
-                    self.had_error = true;
-                    return None;
-                }
+    /// Consumes the comma after a container element, leaving `closing` for
+    /// the caller. Anything else is a syntax error.
+    fn consume_element_separator(&mut self, closing: Token) -> bool {
+        match self.tokens.peek() {
+            Some(Ok(Token::Comma)) => {
+                self.tokens.next();
+                true
+            }
+            Some(Ok(token)) if token == closing => true,
+            _ => {
+                self.had_error = true;
+                false
             }
         }
+    }
 
-        Some(Rc::new(ObjectType::Dict(entries)))
+    /// Emits an OpBuildList or OpBuildDict gathering `count` elements.
+    pub(super) fn emit_build(&mut self, opcode: OpCode, count: usize) -> bool {
+        let Ok(count) = u8::try_from(count) else {
+            self.had_error = true;
+            return false;
+        };
+        self.chunk.code.push(opcode as u8);
+        self.chunk.code.push(count);
+        true
     }
 
     /// Generates a unique name for a list comprehension result variable.
@@ -361,3 +478,29 @@ impl super::Compiler<'_> {
         name
     }
 }
+
+/// Describes what is wrong with a number literal the lexer rejected, or
+/// returns `None` if `text` is not a number at all.
+pub(super) fn invalid_number_message(text: &str) -> Option<&'static str> {
+    if !text.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let lower = text.to_ascii_lowercase();
+    let (digits, radix, malformed) = match lower.get(..2) {
+        Some("0x") => (&lower[2..], 16, "invalid hexadecimal literal"),
+        Some("0o") => (&lower[2..], 8, "invalid octal literal"),
+        Some("0b") => (&lower[2..], 2, "invalid binary literal"),
+        _ => (&lower[..], 10, "invalid decimal literal"),
+    };
+    let well_formed = !digits.is_empty()
+        && !digits.ends_with('_')
+        && !digits.contains("__")
+        && digits.chars().all(|c| c == '_' || c.is_digit(radix));
+    if !well_formed {
+        return Some(malformed);
+    }
+    if radix == 10 && digits.starts_with('0') {
+        return Some("leading zeros in decimal integer literals are not permitted");
+    }
+    Some("integer literal too large")
+}
@@ -3,114 +3,284 @@
 //! This module implements a single-pass compiler that parses Python syntax
 //! and generates bytecode instructions for the virtual machine.
 
+mod batch;
 mod builtins;
 mod codegen;
 mod expressions;
+mod indentation;
+pub mod lint;
 mod literals;
 mod scope;
 mod statements;
+pub mod syntax_error;
+mod token_stream;
 mod types;
 
+pub use batch::CompiledModule;
+
 use crate::bytecode::{Chunk, OpCode};
 use crate::object::Type;
 use crate::token::Token;
-use logos::{Lexer, Logos};
-use std::collections::HashMap;
+use indentation::LogicalLines;
+use lint::Lint;
+use std::collections::{HashMap, HashSet};
+use syntax_error::SyntaxError;
+use token_stream::TokenStream;
 use types::*;
 
 pub struct Compiler<'a> {
-    lexer: Lexer<'a, Token>,
+    tokens: TokenStream<'a>,
     source: &'a str,
+    line_starts: Vec<usize>, // Offset of the start of each source line
     chunk: Chunk,
     had_error: bool,
     list_comp_counter: usize,
     loop_stack: Vec<LoopContext>,
+    stack_temporaries: usize, // Values enclosing for loops keep on the stack
+    unwind_stack: Vec<Unwind<'a>>, // Enclosing try statement parts, innermost last
     current_indent: usize,
     function_depth: usize,
     function_scopes: Vec<FunctionScope>,
     module: String,
-    function_name_stack: Vec<String>, // Stack of function names for building qualified names
+    function_name_stack: Vec<String>, // Enclosing classes and functions, for qualified names
     global_type_annotations: HashMap<String, Type>, // Type annotations for global variables
+    rebound_builtins: HashSet<String>, // Special-cased builtins the source shadows or aliases
+    errors: Vec<SyntaxError>,         // Syntax errors recovered from so far
+    error_message: Option<&'static str>, // Specific message for the failing statement
+    nesting_depth: usize,             // Terms and statements currently being parsed
+    logical_lines: LogicalLines,      // Where statements may start, and misindented ones
+    lints: Option<Vec<Lint>>,         // Warnings found so far, when linting
 }
 
+/// How deeply terms and statements may nest before compilation fails,
+/// keeping the recursive descent well within the thread's stack.
+const MAX_NESTING_DEPTH: usize = 200;
+
 impl<'a> Compiler<'a> {
     pub fn compile(source: &'a str) -> Option<Chunk> {
         Self::compile_with_module(source, "<script>")
     }
 
     pub fn compile_with_module(source: &'a str, module: &str) -> Option<Chunk> {
-        let mut compiler = Compiler {
-            lexer: Token::lexer(source),
+        Self::compile_with_errors(source, module).ok()
+    }
+
+    /// Compiles `source`, returning every syntax error found instead of
+    /// stopping at the first one.
+    ///
+    /// Malformed input never panics: anything the compiler cannot handle,
+    /// including nesting too deep or more constants than an operand can
+    /// address, is reported as a syntax error.
+    pub fn compile_with_errors(source: &'a str, module: &str) -> Result<Chunk, Vec<SyntaxError>> {
+        let mut compiler = Self::new(source, module);
+        compiler.compile_statements()?;
+        Ok(compiler.chunk)
+    }
+
+    /// Compiles `source` as a single expression, as `eval()` takes it,
+    /// into a chunk that returns the expression's value.
+    pub fn compile_expression(source: &'a str, module: &str) -> Result<Chunk, Vec<SyntaxError>> {
+        let mut compiler = Self::new(source, module);
+        let parsed = compiler.parse_expression() && !compiler.had_error;
+        let rest = compiler.tokens.peek_span();
+        if !parsed || rest.is_some() || !compiler.errors.is_empty() {
+            let error_at = match rest {
+                Some(rest) if parsed => rest.start,
+                _ => compiler.tokens.span().start,
+            };
+            let message = compiler.error_message.take().unwrap_or("invalid syntax");
+            compiler.record_error(error_at, message);
+            return Err(compiler.errors);
+        }
+        compiler.chunk.code.push(OpCode::OpReturn as u8);
+        compiler.chunk.compact_constants();
+        Ok(compiler.chunk)
+    }
+
+    fn new(source: &'a str, module: &str) -> Self {
+        Compiler {
+            tokens: TokenStream::new(source),
             source,
+            line_starts: std::iter::once(0)
+                .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+                .collect(),
             chunk: Chunk::new(),
             had_error: false,
             list_comp_counter: 0,
             loop_stack: Vec::new(),
+            stack_temporaries: 0,
+            unwind_stack: Vec::new(),
             current_indent: 0,
             function_depth: 0,
             function_scopes: Vec::new(),
             module: module.to_string(),
             function_name_stack: Vec::new(),
             global_type_annotations: HashMap::new(),
-        };
+            rebound_builtins: Self::rebound_builtin_names(source),
+            errors: Vec::new(),
+            error_message: None,
+            nesting_depth: 0,
+            logical_lines: indentation::check(source),
+            lints: None,
+        }
+    }
 
+    /// Compiles every statement of the source into `self.chunk`.
+    fn compile_statements(&mut self) -> Result<(), Vec<SyntaxError>> {
+        let mut exit = lint::BlockExit::default();
         // Loop until we run out of tokens
-        while let Some(next_token) = compiler.lexer.clone().next() {
+        while let Some((next_token, info)) = self.peek_token_with_indent() {
             match next_token {
-                Ok(_) => {
-                    compiler.parse_statement();
+                Ok(token) => {
+                    self.check_reachable(&mut exit, &token, info.start);
+                    self.parse_statement();
+                    if self.had_error {
+                        self.recover_from_error(info);
+                    }
 
                     // Consume any trailing semicolons after a statement
-                    while compiler.lexer.clone().next() == Some(Ok(Token::Semicolon)) {
-                        compiler.lexer.next();
+                    while self.tokens.peek() == Some(Ok(Token::Semicolon)) {
+                        self.tokens.next();
                     }
                 }
-                Err(_) => {
-                    // Advance past unrecognized input to avoid infinite loops.
-                    compiler.lexer.next();
-                    compiler.had_error = true;
-                }
+                Err(_) => self.skip_invalid_character(),
             }
         }
-        if compiler.had_error {
-            return None;
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
         }
 
-        compiler.chunk.code.push(OpCode::OpReturn as u8);
+        self.chunk.code.push(OpCode::OpReturn as u8);
+        self.chunk.compact_constants();
+        Ok(())
+    }
+
+    /// Records a syntax error for the statement starting at `statement`,
+    /// then skips to the next statement boundary so compilation can go on.
+    ///
+    /// The boundary is the first token that begins a line indented no deeper
+    /// than the failed statement (which also covers dedents out of its
+    /// block), or the token after a `;` on such a line.
+    #[allow(private_interfaces)]
+    pub(super) fn recover_from_error(&mut self, statement: TokenInfo) {
+        self.had_error = false;
+        let error_at = self.tokens.span().start.max(statement.start);
+        let message = self.error_message.take().unwrap_or("invalid syntax");
+        self.record_error(error_at, message);
 
-        Some(compiler.chunk)
+        // Always make progress, even if the statement consumed nothing
+        if self.tokens.span().end <= statement.start {
+            self.tokens.next();
+        }
+
+        loop {
+            let consumed_end = self.tokens.span().end;
+            let Some((token, next)) = self.peek_token_with_indent() else {
+                return;
+            };
+            if self.has_newline_between(consumed_end, next.start) && next.indent <= statement.indent
+            {
+                return;
+            }
+            self.tokens.next();
+            if token == Ok(Token::Semicolon) && next.indent <= statement.indent {
+                return;
+            }
+        }
+    }
+
+    /// Fails the current statement with a more specific message than
+    /// "invalid syntax". The first message given for a statement wins.
+    pub(super) fn fail(&mut self, message: &'static str) {
+        self.had_error = true;
+        self.error_message.get_or_insert(message);
+    }
+
+    /// Runs `parse` one nesting level deeper, failing instead once the
+    /// input nests more than `MAX_NESTING_DEPTH` levels.
+    pub(super) fn nested<T: Default>(&mut self, parse: impl FnOnce(&mut Self) -> T) -> T {
+        if self.nesting_depth >= MAX_NESTING_DEPTH {
+            self.fail("too many nested expressions or blocks");
+            return T::default();
+        }
+        self.nesting_depth += 1;
+        let result = parse(self);
+        self.nesting_depth -= 1;
+        result
+    }
+
+    /// Whether the next token continues the current logical line rather
+    /// than starting a new statement.
+    pub(super) fn continues_line(&self) -> bool {
+        self.tokens
+            .peek_span()
+            .is_some_and(|span| !self.logical_lines.starts.contains(&span.start))
+    }
+
+    /// Consumes a character the lexer does not recognize and records it.
+    pub(super) fn skip_invalid_character(&mut self) {
+        self.tokens.next();
+        let message = match literals::invalid_number_message(self.tokens.slice()) {
+            Some(message) => message.to_string(),
+            None => format!("invalid character '{}'", self.tokens.slice()),
+        };
+        self.record_error(self.tokens.span().start, message);
+    }
+
+    /// Fails the current statement on the unrecognized token just consumed,
+    /// saying what is wrong when it is a malformed number.
+    pub(super) fn fail_on_invalid_token(&mut self) {
+        match literals::invalid_number_message(self.tokens.slice()) {
+            Some(message) => self.fail(message),
+            None => self.had_error = true,
+        }
+    }
+
+    fn record_error(&mut self, position: usize, message: impl Into<String>) {
+        let (line, column) = self.line_and_column(position);
+        let message = message.into();
+        let kind = indentation::error_kind(&message);
+        self.errors
+            .push(SyntaxError::new(line, column, message).with_kind(kind));
+    }
+
+    /// The 1-based line and column, counted in characters, of `position`.
+    pub(super) fn line_and_column(&self, position: usize) -> (usize, usize) {
+        let before = self.source_before(position);
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        let line = before.matches('\n').count() + 1;
+        let column = before[line_start..].chars().count() + 1;
+        (line, column)
     }
 
     /// Peeks at the next token along with its indentation information.
     #[allow(private_interfaces)]
     pub(super) fn peek_token_with_indent(&self) -> Option<(Result<Token, ()>, TokenInfo)> {
-        let mut lookahead = self.lexer.clone();
-        let token = lookahead.next()?;
-        let span = lookahead.span();
-        let indent = self.indent_at(span.start);
-        Some((
-            token,
-            TokenInfo {
-                indent,
-                start: span.start,
-            },
-        ))
+        self.tokens.peek_with_indent()
     }
 
     /// Calculates the indentation level at a given position in the source code.
     pub(super) fn indent_at(&self, position: usize) -> usize {
-        let line_start = self.source[..position]
-            .rfind('\n')
-            .map(|idx| idx + 1)
-            .unwrap_or(0);
-        let mut indent = 0;
-        for ch in self.source[line_start..position].chars() {
-            match ch {
-                ' ' => indent += 1,
-                '\t' => indent += 4,
-                _ => break,
-            }
-        }
-        indent
+        indentation::indent_at(self.source, position)
+    }
+
+    /// Returns the source text before `position`, or all of it if `position`
+    /// is past the end or not on a character boundary.
+    /// The 1-based source line containing `position`.
+    pub(super) fn line_at(&self, position: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= position)
+    }
+
+    pub(super) fn source_before(&self, position: usize) -> &'a str {
+        self.source.get(..position).unwrap_or(self.source)
+    }
+
+    /// The qualified name of `name` defined in the current scope: the
+    /// enclosing classes and functions joined by dots, each function
+    /// followed by `<locals>` as in CPython.
+    pub(super) fn qualified_name(&self, name: &str) -> String {
+        let mut parts = self.function_name_stack.clone();
+        parts.push(name.to_string());
+        parts.join(".")
     }
 }
@@ -4,7 +4,9 @@
 //! resolving variable names to their storage locations (local, upvalue, or global),
 //! and handling type annotations.
 
-use crate::object::Type;
+use crate::bytecode::MAX_SLOT;
+use crate::object::{Object, ObjectType, Type};
+use crate::sync::Rc;
 use crate::token::Token;
 
 use super::types::VariableTarget;
@@ -13,7 +15,7 @@ impl super::Compiler<'_> {
     /// Parses a type annotation from the token stream.
     /// Returns None if the next token is not a valid type identifier.
     pub(super) fn parse_type_annotation(&mut self) -> Option<Type> {
-        match self.lexer.next() {
+        match self.tokens.next() {
             Some(Ok(Token::Identifier(name))) => match name.as_str() {
                 "int" => Some(Type::Int),
                 "float" => Some(Type::Float),
@@ -22,6 +24,7 @@ impl super::Compiler<'_> {
                 "list" => Some(Type::List),
                 "dict" => Some(Type::Dict),
                 "tuple" => Some(Type::Tuple),
+                "set" => Some(Type::Set),
                 _ => Some(Type::Class(name)),
             },
             _ => None,
@@ -76,9 +79,15 @@ impl super::Compiler<'_> {
         }
 
         let parent_index = scope_index - 1;
+        // A class body's locals are its attributes, which the functions
+        // defined in it reach through the class rather than by name
         let parent_local = {
             let parent_scope = &self.function_scopes[parent_index];
-            parent_scope.resolve(name)
+            if parent_scope.is_class_body {
+                None
+            } else {
+                parent_scope.resolve(name)
+            }
         };
 
         if let Some(local_index) = parent_local {
@@ -87,7 +96,8 @@ impl super::Compiler<'_> {
                 .function_scopes
                 .get_mut(scope_index)
                 .expect("scope should exist");
-            return Some(scope.add_upvalue(name_owned, true, local_index));
+            let index = scope.add_upvalue(name_owned, true, local_index);
+            return self.check_upvalue_index(index);
         }
 
         let parent_upvalue_index = self.resolve_upvalue_recursive(parent_index, name)?;
@@ -97,17 +107,30 @@ impl super::Compiler<'_> {
             .function_scopes
             .get_mut(scope_index)
             .expect("scope should exist");
-        Some(scope.add_upvalue(name_owned, false, parent_upvalue_index))
+        let index = scope.add_upvalue(name_owned, false, parent_upvalue_index);
+        self.check_upvalue_index(index)
+    }
+
+    /// Passes on a newly resolved upvalue index, failing instead if it is
+    /// past `MAX_SLOT`.
+    fn check_upvalue_index(&mut self, index: usize) -> Option<usize> {
+        if index > MAX_SLOT {
+            self.fail("too many variables captured by one function");
+            return None;
+        }
+        Some(index)
     }
 
     /// Declares a local variable in the current scope without a type annotation.
     /// Returns the slot index and whether it was newly created.
-    /// Returns None if we're not in a function scope or if we've exceeded the max local count.
+    /// Returns None if we're not in a function scope or if a new local would pass `MAX_SLOT`.
     pub(super) fn declare_local(&mut self, name: String) -> Option<(usize, bool)> {
         let scope = self.function_scopes.last_mut()?;
 
-        if scope.parameters.len() + scope.locals.len() + 1 >= u8::MAX as usize {
-            self.had_error = true;
+        if scope.resolve(&name).is_none()
+            && scope.parameters.len() + scope.locals.len() + 1 > MAX_SLOT
+        {
+            self.fail("too many local variables in one function");
             return None;
         }
 
@@ -116,7 +139,7 @@ impl super::Compiler<'_> {
 
     /// Declares a local variable in the current scope with an optional type annotation.
     /// Returns the slot index and whether it was newly created.
-    /// Returns None if we're not in a function scope or if we've exceeded the max local count.
+    /// Returns None if we're not in a function scope or if a new local would pass `MAX_SLOT`.
     pub(super) fn declare_local_with_type(
         &mut self,
         name: String,
@@ -124,8 +147,10 @@ impl super::Compiler<'_> {
     ) -> Option<(usize, bool)> {
         let scope = self.function_scopes.last_mut()?;
 
-        if scope.parameters.len() + scope.locals.len() + 1 >= u8::MAX as usize {
-            self.had_error = true;
+        if scope.resolve(&name).is_none()
+            && scope.parameters.len() + scope.locals.len() + 1 > MAX_SLOT
+        {
+            self.fail("too many local variables in one function");
             return None;
         }
 
@@ -138,6 +163,26 @@ impl super::Compiler<'_> {
         self.global_type_annotations.insert(name, type_annotation);
     }
 
+    /// Records the annotation of a field `name` in the class body being
+    /// compiled, for its `__annotations__`. Does nothing outside class bodies.
+    pub(super) fn record_class_annotation(&mut self, name: &str, type_annotation: &Type) {
+        let Some(scope) = self.function_scopes.last_mut() else {
+            return;
+        };
+        if !scope.is_class_body {
+            return;
+        }
+        let type_name: Object = Rc::new(ObjectType::String(type_annotation.name().to_string()));
+        match scope
+            .annotations
+            .iter_mut()
+            .find(|(field, _)| field == name)
+        {
+            Some((_, existing)) => *existing = type_name,
+            None => scope.annotations.push((name.to_string(), type_name)),
+        }
+    }
+
     /// Retrieves a type annotation for a global variable, if one exists.
     #[allow(dead_code)]
     pub(super) fn get_global_type_annotation(&self, name: &str) -> Option<&Type> {
@@ -1,22 +1,21 @@
 //! Assignment statement parsing.
 
 use crate::bytecode::OpCode;
-use crate::object::ObjectType;
+use crate::object::Type;
 use crate::token::Token;
-use std::rc::Rc;
 
+use super::super::token_stream::Lookahead;
 use super::super::types::*;
 
 impl super::super::Compiler<'_> {
     pub(super) fn detect_assignment_kind(&self) -> Option<AssignmentKind> {
-        let mut lookahead = self.lexer.clone();
+        let mut lookahead = self.tokens.ahead();
         match lookahead.next()? {
             Ok(Token::Identifier(_)) => {}
             _ => return None,
         }
 
         let mut bracket_depth = 0;
-        let mut has_dot = false;
 
         while let Some(token_result) = lookahead.next() {
             match token_result {
@@ -27,20 +26,26 @@ impl super::super::Compiler<'_> {
                     }
                     bracket_depth -= 1;
                 }
-                Ok(Token::Dot) if bracket_depth == 0 => {
-                    has_dot = true;
-                    // Continue to see if there's an assignment after the dot and identifier
+                Ok(Token::Assign) if bracket_depth == 0 => {
+                    let chained = self.has_further_assign(lookahead);
+                    return Some(AssignmentKind::Simple { chained });
                 }
-                Ok(Token::Assign) if bracket_depth == 0 => return Some(AssignmentKind::Simple),
                 Ok(Token::PlusEqual) if bracket_depth == 0 => {
                     return Some(AssignmentKind::AddAssign)
                 }
                 Ok(Token::StarEqual) if bracket_depth == 0 => {
                     return Some(AssignmentKind::MultiplyAssign)
                 }
-                Ok(Token::LParen) if bracket_depth == 0 && !has_dot => return None,
+                // A call at the top level means an expression statement; this also keeps
+                // keyword arguments such as `items.sort(reverse=True)` from looking like
+                // an assignment
+                Ok(Token::LParen) if bracket_depth == 0 => return None,
                 Ok(Token::Comma) | Ok(Token::Semicolon) if bracket_depth == 0 => return None,
-                Ok(Token::Plus) | Ok(Token::Slash) | Ok(Token::Star) | Ok(Token::Minus)
+                Ok(Token::Plus)
+                | Ok(Token::Slash)
+                | Ok(Token::SlashSlash)
+                | Ok(Token::Star)
+                | Ok(Token::Minus)
                 | Ok(Token::In)
                     if bracket_depth == 0 =>
                 {
@@ -65,23 +70,107 @@ impl super::super::Compiler<'_> {
         None
     }
 
+    /// Returns whether the statement is an annotation without a value, such
+    /// as `name: int`.
+    pub(super) fn is_annotation_statement(&self) -> bool {
+        let mut lookahead = self.tokens.ahead();
+        let annotated = matches!(lookahead.next(), Some(Ok(Token::Identifier(_))))
+            && matches!(lookahead.next(), Some(Ok(Token::Colon)))
+            && matches!(lookahead.next(), Some(Ok(Token::Identifier(_))));
+        if !annotated {
+            return false;
+        }
+        let type_end = lookahead.span().end;
+        match lookahead.next() {
+            None | Some(Ok(Token::Semicolon)) => true,
+            Some(_) => self.has_newline_between(type_end, lookahead.span().start),
+        }
+    }
+
+    /// Parses `name: type`, which declares the name's type without binding
+    /// it: a local inside a function, and a field in a class body.
+    pub(super) fn parse_annotation_statement(&mut self) {
+        let Some(Ok(Token::Identifier(name))) = self.tokens.next() else {
+            return; // Checked by is_annotation_statement
+        };
+        self.tokens.next(); // consume ':'
+        let Some(type_annotation) = self.parse_type_annotation() else {
+            self.had_error = true;
+            return;
+        };
+        self.record_class_annotation(&name, &type_annotation);
+        if self.function_depth == 0 {
+            self.store_global_type_annotation(name, type_annotation);
+        } else if !self
+            .function_scopes
+            .last()
+            .is_some_and(|scope| scope.is_class_body)
+        {
+            self.declare_local_with_type(name, Some(type_annotation));
+        }
+    }
+
+    /// Returns whether another top-level `=` follows before the statement
+    /// ends, given a lookahead positioned just after the first `=`.
+    fn has_further_assign(&self, mut lookahead: Lookahead<'_, '_>) -> bool {
+        let mut depth = 0usize;
+        let mut previous_end = lookahead.span().end;
+        while let Some(token) = lookahead.next() {
+            let span = lookahead.span();
+            if depth == 0 && self.has_newline_between(previous_end, span.start) {
+                return false;
+            }
+            previous_end = span.end;
+            match token {
+                Ok(Token::LParen | Token::LBracket | Token::LBrace) => depth += 1,
+                Ok(Token::RParen | Token::RBracket | Token::RBrace) => {
+                    let Some(outer) = depth.checked_sub(1) else {
+                        return false;
+                    };
+                    depth = outer;
+                }
+                Ok(Token::Assign) if depth == 0 => return true,
+                Ok(Token::Semicolon | Token::Colon | Token::Else) if depth == 0 => return false,
+                Err(_) => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
     pub(super) fn parse_assignment_statement(&mut self, kind: AssignmentKind) {
-        let name = if let Some(Ok(Token::Identifier(s))) = self.lexer.next() {
+        if kind == (AssignmentKind::Simple { chained: true }) {
+            self.parse_chained_assignment();
+            return;
+        }
+
+        let name = if let Some(Ok(Token::Identifier(s))) = self.tokens.next() {
             s
         } else {
             return; // Should not happen
         };
 
         // Check for type annotation: name: type
-        let type_annotation = if matches!(self.lexer.clone().next(), Some(Ok(Token::Colon))) {
-            self.lexer.next(); // consume ':'
+        let type_annotation = if matches!(self.tokens.peek(), Some(Ok(Token::Colon))) {
+            self.tokens.next(); // consume ':'
             self.parse_type_annotation()
         } else {
             None
         };
+        if let Some(type_annotation) = &type_annotation {
+            self.record_class_annotation(&name, type_annotation);
+        }
 
         let mut target = self.resolve_variable(&name);
-        let name_idx = self.add_constant(Rc::new(ObjectType::String(name.clone())));
+        // A plain `name = value` may yet declare a new local, so its name
+        // constant waits until the binding is settled
+        let plain = matches!(kind, AssignmentKind::Simple { .. })
+            && self.tokens.peek() == Some(Ok(Token::Assign));
+        let mut name_idx = if plain {
+            0
+        } else {
+            self.variable_name_constant(&name, target)
+        };
 
         let mut has_subscript = false;
         let mut has_attribute = false;
@@ -89,9 +178,9 @@ impl super::super::Compiler<'_> {
         let mut index_expression_code: Option<Vec<u8>> = None;
 
         // Check for attribute assignment (obj.attr = value)
-        if self.lexer.clone().next() == Some(Ok(Token::Dot)) {
-            self.lexer.next(); // consume '.'
-            attr_name = match self.lexer.next() {
+        if self.tokens.peek() == Some(Ok(Token::Dot)) {
+            self.tokens.next(); // consume '.'
+            attr_name = match self.tokens.next() {
                 Some(Ok(Token::Identifier(attr))) => Some(attr),
                 _ => {
                     self.had_error = true;
@@ -102,12 +191,13 @@ impl super::super::Compiler<'_> {
 
             // Load the object onto the stack
             self.emit_get_variable(name_idx, target);
-        } else if self.lexer.clone().next() == Some(Ok(Token::LBracket)) {
+        } else if self.tokens.peek() == Some(Ok(Token::LBracket)) {
             has_subscript = true;
-            self.lexer.next(); // Consume '['
+            self.tokens.next(); // Consume '['
 
+            let target_start = self.chunk.code.len();
             match kind {
-                AssignmentKind::Simple => {
+                AssignmentKind::Simple { .. } => {
                     self.emit_get_variable(name_idx, target);
                 }
                 AssignmentKind::AddAssign | AssignmentKind::MultiplyAssign => {
@@ -117,22 +207,38 @@ impl super::super::Compiler<'_> {
             }
 
             let expr_start = self.chunk.code.len();
-            if !self.parse_expression() {
+            let Some(subscript) = self.parse_subscript() else {
                 self.had_error = true;
                 return;
-            }
+            };
             let expr_end = self.chunk.code.len();
             index_expression_code = Some(self.chunk.code[expr_start..expr_end].to_vec());
 
-            if self.lexer.next() != Some(Ok(Token::RBracket)) {
-                self.had_error = true;
+            if subscript == SubscriptKind::Slice {
+                if !matches!(kind, AssignmentKind::Simple { .. }) {
+                    self.fail("augmented assignment to a slice is not supported");
+                    return;
+                }
+                // The bounds are evaluated after the value, so set their code aside
+                let index_code = self.chunk.code.split_off(expr_start);
+                self.chunk.code.truncate(target_start);
+                if self.tokens.next() != Some(Ok(Token::Assign)) || !self.parse_expression() {
+                    self.had_error = true;
+                    return;
+                }
+                self.emit_store_target(AssignmentTarget::Subscript {
+                    name_idx,
+                    target,
+                    index_code,
+                    kind: subscript,
+                });
                 return;
             }
         }
 
         match kind {
-            AssignmentKind::Simple => {
-                if self.lexer.next() != Some(Ok(Token::Assign)) {
+            AssignmentKind::Simple { .. } => {
+                if self.tokens.next() != Some(Ok(Token::Assign)) {
                     self.had_error = true;
                     return;
                 }
@@ -141,7 +247,7 @@ impl super::super::Compiler<'_> {
                     // Handle attribute assignment: obj.attr = value
                     // Stack already has the object loaded
                     let attr_name_str = attr_name.expect("attribute name");
-                    let attr_idx = self.add_constant(Rc::new(ObjectType::String(attr_name_str)));
+                    let attr_idx = self.name_constant(&attr_name_str);
 
                     if !self.parse_expression() {
                         self.had_error = true;
@@ -149,8 +255,7 @@ impl super::super::Compiler<'_> {
                     }
 
                     // Stack: [object, value]
-                    self.chunk.code.push(OpCode::OpSetAttr as u8);
-                    self.chunk.code.push(attr_idx as u8);
+                    self.emit_constant_operand(OpCode::OpSetAttr, attr_idx);
                 } else if has_subscript {
                     if !self.parse_expression() {
                         self.had_error = true;
@@ -161,37 +266,11 @@ impl super::super::Compiler<'_> {
                     self.emit_set_variable(name_idx, target);
                     self.chunk.code.push(OpCode::OpPop as u8);
                 } else {
-                    if self.function_depth > 0 && matches!(target, VariableTarget::Global) {
-                        // Check if variable is declared as nonlocal
-                        let is_nonlocal = self
-                            .function_scopes
-                            .last()
-                            .map(|scope| scope.nonlocals.contains(&name))
-                            .unwrap_or(false);
-
-                        if !is_nonlocal {
-                            // Not nonlocal, so create as local variable
-                            if let Some((idx, is_new)) =
-                                self.declare_local_with_type(name.clone(), type_annotation)
-                            {
-                                target = VariableTarget::Local(idx);
-                                if is_new {
-                                    self.emit_nil();
-                                }
-                            }
-                            if self.had_error {
-                                return;
-                            }
-                        } else {
-                            // Variable is nonlocal, re-resolve to get upvalue target
-                            target = self.resolve_variable(&name);
-                        }
-                    } else if self.function_depth == 0 && matches!(target, VariableTarget::Global) {
-                        // Store type annotation for global variable at module scope
-                        if let Some(ty) = type_annotation {
-                            self.store_global_type_annotation(name.clone(), ty);
-                        }
+                    target = self.binding_target(&name, target, type_annotation);
+                    if self.had_error {
+                        return;
                     }
+                    name_idx = self.variable_name_constant(&name, target);
 
                     if !self.parse_expression() {
                         self.had_error = true;
@@ -205,6 +284,7 @@ impl super::super::Compiler<'_> {
                 if self.function_depth > 0
                     && matches!(target, VariableTarget::Global)
                     && !has_subscript
+                    && !has_attribute
                 {
                     self.had_error = true;
                     return;
@@ -216,7 +296,7 @@ impl super::super::Compiler<'_> {
                     Token::StarEqual
                 };
 
-                if self.lexer.next() != Some(Ok(expected_token)) {
+                if self.tokens.next() != Some(Ok(expected_token)) {
                     self.had_error = true;
                     return;
                 }
@@ -227,7 +307,22 @@ impl super::super::Compiler<'_> {
                     OpCode::OpMultiply
                 };
 
-                if has_subscript {
+                if has_attribute {
+                    // Stack: [object] -> [object, object] -> [object, current]; the
+                    // receiver is evaluated once and used for both the get and the set
+                    let attr_name_str = attr_name.expect("attribute name");
+                    let attr_idx = self.name_constant(&attr_name_str);
+                    self.chunk.code.push(OpCode::OpDup as u8);
+                    self.emit_constant_operand(OpCode::OpGetAttr, attr_idx);
+
+                    if !self.parse_expression() {
+                        self.had_error = true;
+                        return;
+                    }
+
+                    self.chunk.code.push(arithmetic_opcode as u8);
+                    self.emit_constant_operand(OpCode::OpSetAttr, attr_idx);
+                } else if has_subscript {
                     self.chunk.code.push(OpCode::OpIndex as u8);
 
                     if !self.parse_expression() {
@@ -263,4 +358,227 @@ impl super::super::Compiler<'_> {
             }
         }
     }
+
+    /// Resolves where a plain `name = ...` stores its value. Inside a function
+    /// a name that is not nonlocal becomes a local.
+    pub(super) fn binding_target(
+        &mut self,
+        name: &str,
+        target: VariableTarget,
+        type_annotation: Option<Type>,
+    ) -> VariableTarget {
+        if !matches!(target, VariableTarget::Global) {
+            return target;
+        }
+        if self.function_depth == 0 {
+            // Store type annotation for global variable at module scope
+            if let Some(ty) = type_annotation {
+                self.store_global_type_annotation(name.to_string(), ty);
+            }
+            return target;
+        }
+
+        // Check if variable is declared as nonlocal
+        let is_nonlocal = self
+            .function_scopes
+            .last()
+            .map(|scope| scope.nonlocals.contains(name))
+            .unwrap_or(false);
+        if is_nonlocal {
+            // Variable is nonlocal, re-resolve to get upvalue target
+            return self.resolve_variable(name);
+        }
+
+        // Not nonlocal, so create as local variable
+        match self.declare_local_with_type(name.to_string(), type_annotation) {
+            Some((idx, created)) => {
+                if created {
+                    self.note_assigned_local();
+                }
+                VariableTarget::Local(idx)
+            }
+            None => target,
+        }
+    }
+
+    /// Parses `a = b.c = d[i] = value`: the value is computed once, then
+    /// stored to each target from left to right.
+    fn parse_chained_assignment(&mut self) {
+        let mut targets = Vec::new();
+        loop {
+            let Some(target) = self.parse_assignment_target() else {
+                self.had_error = true;
+                return;
+            };
+            targets.push(target);
+            if self.tokens.next() != Some(Ok(Token::Assign)) {
+                self.had_error = true;
+                return;
+            }
+            if !self.has_further_assign(self.tokens.ahead()) {
+                break;
+            }
+        }
+
+        if !self.parse_expression() {
+            self.had_error = true;
+            return;
+        }
+
+        let last = targets.len() - 1;
+        for (position, target) in targets.into_iter().enumerate() {
+            if position < last {
+                self.chunk.code.push(OpCode::OpDup as u8);
+            }
+            self.emit_store_target(target);
+        }
+    }
+
+    /// Returns whether the statement is a comma-separated or starred target
+    /// list followed by `=`, such as `first, *rest = items`.
+    pub(super) fn is_unpack_assignment(&self) -> bool {
+        let mut lookahead = self.tokens.ahead();
+        let mut depth = 0usize;
+        let mut is_list = false;
+        let mut previous_end = None;
+        while let Some(token) = lookahead.next() {
+            let span = lookahead.span();
+            let after_newline =
+                previous_end.is_some_and(|end| self.has_newline_between(end, span.start));
+            if depth == 0 && after_newline {
+                return false;
+            }
+            previous_end = Some(span.end);
+            match token {
+                Ok(Token::LBracket) => depth += 1,
+                Ok(Token::RBracket) => {
+                    let Some(outer) = depth.checked_sub(1) else {
+                        return false;
+                    };
+                    depth = outer;
+                }
+                _ if depth > 0 => {}
+                Ok(Token::Comma | Token::Star) => is_list = true,
+                Ok(Token::Identifier(_) | Token::Dot) => {}
+                Ok(Token::Assign) => return is_list,
+                _ => return false,
+            }
+        }
+        false
+    }
+
+    /// Parses `a, *b, c.d = value`: the value is computed, split by OpUnpack,
+    /// and its items are stored to the targets from left to right.
+    pub(super) fn parse_unpack_assignment(&mut self) {
+        let mut targets = Vec::new();
+        let mut starred = None;
+        loop {
+            if matches!(self.tokens.peek(), Some(Ok(Token::Star))) {
+                self.tokens.next(); // consume '*'
+                if starred.is_some() {
+                    self.fail("multiple starred expressions in assignment");
+                    return;
+                }
+                starred = Some(targets.len());
+            }
+            match self.parse_assignment_target() {
+                Some(target) => targets.push(target),
+                None => {
+                    self.had_error = true;
+                    return;
+                }
+            }
+            match self.tokens.next() {
+                Some(Ok(Token::Comma)) => {
+                    if matches!(self.tokens.peek(), Some(Ok(Token::Assign))) {
+                        self.tokens.next(); // consume '='
+                        break;
+                    }
+                }
+                Some(Ok(Token::Assign)) => break,
+                _ => {
+                    self.had_error = true;
+                    return;
+                }
+            }
+        }
+
+        if targets.len() > u8::MAX as usize - 1 {
+            self.fail("too many targets in assignment");
+            return;
+        }
+        if starred.is_some() && targets.len() == 1 {
+            self.fail("starred assignment target must be in a list or tuple");
+            return;
+        }
+
+        // `a, b = b, a` packs the values into a list before unpacking them
+        let mut value_count = 0;
+        loop {
+            if !self.parse_expression() {
+                self.had_error = true;
+                return;
+            }
+            value_count += 1;
+            if !matches!(self.tokens.peek(), Some(Ok(Token::Comma))) {
+                break;
+            }
+            self.tokens.next(); // consume ','
+        }
+        if value_count > 1 && !self.emit_build(OpCode::OpBuildList, value_count) {
+            return;
+        }
+
+        self.chunk.code.push(OpCode::OpUnpack as u8);
+        self.chunk.code.push(targets.len() as u8);
+        self.chunk
+            .code
+            .push(starred.map_or(0, |position| position as u8 + 1));
+        for target in targets {
+            self.emit_store_target(target);
+        }
+    }
+
+    /// Parses one assignment target for a chained or unpacking assignment.
+    fn parse_assignment_target(&mut self) -> Option<AssignmentTarget> {
+        let Some(Ok(Token::Identifier(name))) = self.tokens.next() else {
+            return None;
+        };
+        let target = self.resolve_variable(&name);
+
+        match self.tokens.peek() {
+            Some(Ok(Token::Dot)) => {
+                let name_idx = self.variable_name_constant(&name, target);
+                self.tokens.next(); // consume '.'
+                let Some(Ok(Token::Identifier(attr))) = self.tokens.next() else {
+                    return None;
+                };
+                let attr_idx = self.name_constant(&attr);
+                Some(AssignmentTarget::Attribute {
+                    name_idx,
+                    target,
+                    attr_idx,
+                })
+            }
+            Some(Ok(Token::LBracket)) => {
+                let name_idx = self.variable_name_constant(&name, target);
+                self.tokens.next(); // consume '['
+                let index_start = self.chunk.code.len();
+                let kind = self.parse_subscript()?;
+                // The index is evaluated after the value, so set its code aside
+                let index_code = self.chunk.code.split_off(index_start);
+                Some(AssignmentTarget::Subscript {
+                    name_idx,
+                    target,
+                    index_code,
+                    kind,
+                })
+            }
+            _ => {
+                let target = self.binding_target(&name, target, None);
+                let name_idx = self.variable_name_constant(&name, target);
+                (!self.had_error).then_some(AssignmentTarget::Variable { name_idx, target })
+            }
+        }
+    }
 }
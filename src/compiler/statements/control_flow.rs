@@ -1,15 +1,15 @@
-//! Control flow statement parsing (if, while, for, break).
+//! Control flow statement parsing (if, while, for, break, continue).
 
 use crate::bytecode::OpCode;
 use crate::object::ObjectType;
+use crate::sync::Rc;
 use crate::token::Token;
-use std::rc::Rc;
 
 use super::super::types::*;
 
 impl super::super::Compiler<'_> {
     pub(super) fn parse_if_statement(&mut self) {
-        self.lexer.next(); // Consume 'if'
+        self.tokens.next(); // Consume 'if'
 
         let if_indent = self.current_indent;
 
@@ -18,8 +18,8 @@ impl super::super::Compiler<'_> {
             return;
         }
 
-        let colon_end = if let Some(Ok(Token::Colon)) = self.lexer.next() {
-            self.lexer.span().end
+        let colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+            self.tokens.span().end
         } else {
             self.had_error = true;
             return;
@@ -44,9 +44,9 @@ impl super::super::Compiler<'_> {
             self.patch_jump(then_jump);
             self.chunk.code.push(OpCode::OpPop as u8);
 
-            self.lexer.next(); // Consume 'else'
-            let else_colon_end = if let Some(Ok(Token::Colon)) = self.lexer.next() {
-                self.lexer.span().end
+            self.tokens.next(); // Consume 'else'
+            let else_colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+                self.tokens.span().end
             } else {
                 self.had_error = true;
                 return;
@@ -67,7 +67,7 @@ impl super::super::Compiler<'_> {
     }
 
     pub(super) fn parse_while_statement(&mut self) {
-        self.lexer.next(); // Consume 'while'
+        self.tokens.next(); // Consume 'while'
 
         let loop_indent = self.current_indent;
 
@@ -78,8 +78,8 @@ impl super::super::Compiler<'_> {
             return;
         }
 
-        let colon_end = if let Some(Ok(Token::Colon)) = self.lexer.next() {
-            self.lexer.span().end
+        let colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+            self.tokens.span().end
         } else {
             self.had_error = true;
             return;
@@ -88,9 +88,15 @@ impl super::super::Compiler<'_> {
         let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse);
         self.chunk.code.push(OpCode::OpPop as u8);
 
-        self.loop_stack.push(LoopContext::new(0));
+        let unwind_depth = self.unwind_stack.len();
+        let stack_depth = self.stack_temporaries;
+        self.loop_stack
+            .push(LoopContext::new(stack_depth, loop_start, unwind_depth));
         let body_had_statement = self.parse_suite(loop_indent, colon_end);
-        let context = self.loop_stack.pop().unwrap_or_else(|| LoopContext::new(0));
+        let context = self
+            .loop_stack
+            .pop()
+            .unwrap_or_else(|| LoopContext::new(stack_depth, loop_start, unwind_depth));
 
         if !body_had_statement && !self.had_error {
             self.had_error = true;
@@ -106,18 +112,19 @@ impl super::super::Compiler<'_> {
     }
 
     pub(super) fn parse_for_statement(&mut self) {
-        self.lexer.next(); // Consume 'for'
+        self.tokens.next(); // Consume 'for'
+        let line = self.line_at(self.tokens.span().start);
 
         let loop_indent = self.current_indent;
 
-        let loop_var = if let Some(Ok(Token::Identifier(name))) = self.lexer.next() {
+        let loop_var = if let Some(Ok(Token::Identifier(name))) = self.tokens.next() {
             name
         } else {
             self.had_error = true;
             return;
         };
 
-        if self.lexer.next() != Some(Ok(Token::In)) {
+        if self.tokens.next() != Some(Ok(Token::In)) {
             self.had_error = true;
             return;
         }
@@ -126,11 +133,8 @@ impl super::super::Compiler<'_> {
         let mut loop_var_const_idx = None;
 
         if self.function_depth > 0 {
-            if let Some((idx, is_new)) = self.declare_local(loop_var.clone()) {
+            if let Some((idx, _)) = self.declare_local(loop_var.clone()) {
                 loop_var_local = Some(idx);
-                if is_new {
-                    self.emit_nil();
-                }
             }
             if self.had_error {
                 return;
@@ -138,71 +142,75 @@ impl super::super::Compiler<'_> {
         }
 
         if loop_var_local.is_none() {
-            loop_var_const_idx =
-                Some(self.add_constant(Rc::new(ObjectType::String(loop_var.clone()))));
+            loop_var_const_idx = Some(self.name_constant(&loop_var));
         }
 
         let nil_idx = self.add_constant(Rc::new(ObjectType::Nil));
-        self.chunk.code.push(OpCode::OpConstant as u8);
-        self.chunk.code.push(nil_idx as u8);
+        self.emit_constant_operand(OpCode::OpConstant, nil_idx);
         match loop_var_local {
             Some(local_idx) => {
-                self.chunk.code.push(OpCode::OpSetLocal as u8);
-                self.chunk.code.push(local_idx as u8);
+                self.emit_slot(OpCode::OpSetLocal, local_idx);
                 self.chunk.code.push(OpCode::OpPop as u8);
             }
             None => {
                 let name_idx = loop_var_const_idx.expect("global loop variable must have name");
-                self.chunk.code.push(OpCode::OpDefineGlobal as u8);
-                self.chunk.code.push(name_idx as u8);
+                self.emit_constant_operand(OpCode::OpDefineGlobal, name_idx);
             }
         }
 
+        // The iterable and the index of its next item stay on the stack
+        // until the loop finishes or breaks
+        let stack_depth = self.stack_temporaries;
         if !self.parse_expression() {
             self.had_error = true;
             return;
         }
+        self.stack_temporaries += 1;
 
-        let colon_end = if let Some(Ok(Token::Colon)) = self.lexer.next() {
-            self.lexer.span().end
+        let colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+            self.tokens.span().end
         } else {
+            self.stack_temporaries = stack_depth;
             self.had_error = true;
             return;
         };
 
         let zero_idx = self.add_constant(Rc::new(ObjectType::Integer(0)));
-        self.chunk.code.push(OpCode::OpConstant as u8);
-        self.chunk.code.push(zero_idx as u8);
+        self.emit_constant_operand(OpCode::OpConstant, zero_idx);
+        self.stack_temporaries += 1;
 
+        self.chunk.restart_line(line);
         let loop_start = self.chunk.code.len();
-        self.chunk.code.push(OpCode::OpIterNext as u8);
-        let iter_jump_pos = self.chunk.code.len();
-        self.chunk.code.push(0);
-        self.chunk.code.push(0);
+        let iter_jump_pos = self.emit_jump(OpCode::OpIterNext);
 
         match loop_var_local {
             Some(local_idx) => {
-                self.chunk.code.push(OpCode::OpSetLocal as u8);
-                self.chunk.code.push(local_idx as u8);
+                self.emit_slot(OpCode::OpSetLocal, local_idx);
             }
             None => {
                 let name_idx = loop_var_const_idx.expect("global loop variable must have name");
-                self.chunk.code.push(OpCode::OpSetGlobal as u8);
-                self.chunk.code.push(name_idx as u8);
+                self.emit_constant_operand(OpCode::OpSetGlobal, name_idx);
             }
         }
         self.chunk.code.push(OpCode::OpPop as u8);
 
-        self.loop_stack.push(LoopContext::new(2));
+        let unwind_depth = self.unwind_stack.len();
+        self.loop_stack
+            .push(LoopContext::new(stack_depth, loop_start, unwind_depth));
         let body_had_statement = self.parse_suite(loop_indent, colon_end);
-        let context = self.loop_stack.pop().unwrap_or_else(|| LoopContext::new(2));
+        let context = self
+            .loop_stack
+            .pop()
+            .unwrap_or_else(|| LoopContext::new(stack_depth, loop_start, unwind_depth));
 
         if !body_had_statement && !self.had_error {
             self.had_error = true;
         }
 
+        // OpIterNext pops the iterable and index once they are exhausted
         self.emit_loop(loop_start);
         self.patch_jump(iter_jump_pos);
+        self.stack_temporaries = stack_depth;
 
         for jump in context.break_jumps {
             self.patch_jump(jump);
@@ -210,16 +218,17 @@ impl super::super::Compiler<'_> {
     }
 
     pub(super) fn parse_break_statement(&mut self) {
-        self.lexer.next(); // Consume 'break'
+        self.tokens.next(); // Consume 'break'
 
-        let cleanup_depth = if let Some(context) = self.loop_stack.last() {
-            context.cleanup_depth
+        let (stack_depth, unwind_depth) = if let Some(context) = self.loop_stack.last() {
+            (context.stack_depth, context.unwind_depth)
         } else {
             self.had_error = true;
             return;
         };
 
-        for _ in 0..cleanup_depth {
+        self.emit_unwind(unwind_depth);
+        for _ in stack_depth..self.stack_temporaries {
             self.chunk.code.push(OpCode::OpPop as u8);
         }
 
@@ -229,4 +238,17 @@ impl super::super::Compiler<'_> {
             context.break_jumps.push(jump_pos);
         }
     }
+
+    pub(super) fn parse_continue_statement(&mut self) {
+        self.tokens.next(); // Consume 'continue'
+
+        let Some(context) = self.loop_stack.last() else {
+            self.fail("'continue' not properly in loop");
+            return;
+        };
+        let (continue_target, unwind_depth) = (context.continue_target, context.unwind_depth);
+
+        self.emit_unwind(unwind_depth);
+        self.emit_loop(continue_target);
+    }
 }
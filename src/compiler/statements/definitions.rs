@@ -1,17 +1,17 @@
 //! Function and class definition parsing.
 
-use crate::bytecode::OpCode;
+use crate::bytecode::{OpCode, MAX_SLOT};
 use crate::object::{FunctionPrototype, ObjectType, Type, UpvalueDescriptor};
+use crate::sync::Rc;
 use crate::token::Token;
-use std::rc::Rc;
 
 use super::super::types::*;
 
 impl super::super::Compiler<'_> {
     pub(super) fn parse_function_statement(&mut self) {
-        self.lexer.next(); // consume 'def'
+        self.tokens.next(); // consume 'def'
 
-        let name = match self.lexer.next() {
+        let name = match self.tokens.next() {
             Some(Ok(Token::Identifier(identifier))) => identifier,
             _ => {
                 self.had_error = true;
@@ -19,14 +19,21 @@ impl super::super::Compiler<'_> {
             }
         };
 
+        // Inside a function the name is a local, usually declared up front by
+        // `nested_function_names`
+        let target = self.resolve_variable(&name);
+        let target = self.binding_target(&name, target, None);
+
         let (
             parameters,
             return_type,
             _body_had_statement,
             function_chunk,
             captured_upvalues,
+            local_count,
             qualname,
-        ) = match self.parse_function_definition(name.clone(), None, None) {
+            doc,
+        ) = match self.parse_function_definition(name.clone()) {
             Some(result) => result,
             None => return,
         };
@@ -56,20 +63,76 @@ impl super::super::Compiler<'_> {
             self.module.clone(),
         );
         prototype.qualname = qualname;
+        prototype.local_count = local_count;
+        prototype.doc = doc;
         let prototype_value = Rc::new(ObjectType::FunctionPrototype(Rc::new(prototype)));
         let prototype_const_idx = self.add_constant(prototype_value);
-        self.chunk.code.push(OpCode::OpMakeFunction as u8);
-        self.chunk.code.push(prototype_const_idx as u8);
+        self.emit_constant_operand(OpCode::OpMakeFunction, prototype_const_idx);
 
-        let name_idx = self.add_constant(Rc::new(ObjectType::String(name)));
-        self.chunk.code.push(OpCode::OpDefineGlobal as u8);
-        self.chunk.code.push(name_idx as u8);
+        let name_idx = self.variable_name_constant(&name, target);
+        self.emit_define_variable(name_idx, target);
     }
 
+    /// Parses `@decorator` lines and the `def` or `class` they decorate. The
+    /// name is bound to the result of calling each decorator on the
+    /// definition, the one nearest the definition first. Decorator
+    /// expressions are evaluated after the definition rather than before it.
+    pub(super) fn parse_decorated_statement(&mut self) {
+        let indent = self.current_indent;
+        let mut decorators = Vec::new();
+        while let Some((Ok(Token::At), info)) = self.peek_token_with_indent() {
+            if info.indent != indent {
+                self.had_error = true;
+                return;
+            }
+            self.tokens.next(); // consume '@'
+            let start = self.chunk.code.len();
+            if !self.parse_expression() {
+                self.had_error = true;
+                return;
+            }
+            decorators.push(self.chunk.code.split_off(start));
+        }
+
+        let mut lookahead = self.tokens.ahead();
+        let definition = lookahead.next();
+        let definition_start = lookahead.span().start;
+        let Some(Ok(Token::Identifier(name))) = lookahead.next() else {
+            self.fail("expected a function or class definition after a decorator");
+            return;
+        };
+        self.chunk.add_line(self.line_at(definition_start));
+        match definition {
+            Some(Ok(Token::Def)) => self.parse_function_statement(),
+            Some(Ok(Token::Class)) => self.parse_class_statement(),
+            _ => {
+                self.fail("expected a function or class definition after a decorator");
+                return;
+            }
+        }
+        if self.had_error {
+            return;
+        }
+
+        let target = self.resolve_variable(&name);
+        let name_idx = self.variable_name_constant(&name, target);
+        for decorator in decorators.into_iter().rev() {
+            self.chunk.code.extend_from_slice(&decorator);
+            self.emit_get_variable(name_idx, target);
+            self.chunk.code.push(OpCode::OpCall as u8);
+            self.chunk.code.push(1);
+            self.emit_define_variable(name_idx, target);
+        }
+    }
+
+    /// Parses a class definition. The body runs as a function of its own,
+    /// called once where the class is defined: its statements can compute
+    /// attributes from enclosing names and from earlier attributes, and the
+    /// locals it leaves become the class's methods and attributes.
     pub(super) fn parse_class_statement(&mut self) {
-        self.lexer.next(); // consume 'class'
+        self.tokens.next(); // consume 'class'
 
-        let class_name = match self.lexer.next() {
+        let class_name = match self.tokens.next() {
             Some(Ok(Token::Identifier(identifier))) => identifier,
             _ => {
                 self.had_error = true;
@@ -78,17 +141,18 @@ impl super::super::Compiler<'_> {
         };
 
         // Check for inheritance: class Child(Parent):
-        let has_parent = matches!(self.lexer.clone().next(), Some(Ok(Token::LParen)));
+        let has_parent = matches!(self.tokens.peek(), Some(Ok(Token::LParen)));
         let parent_name = if has_parent {
-            self.lexer.next(); // consume '('
-            let parent = match self.lexer.next() {
+            self.tokens.next(); // consume '('
+            let parent = match self.tokens.next() {
                 Some(Ok(Token::Identifier(identifier))) => identifier,
                 _ => {
                     self.had_error = true;
                     return;
                 }
             };
-            if self.lexer.next() != Some(Ok(Token::RParen)) {
+            self.skip_trailing_comma(Token::RParen);
+            if self.tokens.next() != Some(Ok(Token::RParen)) {
                 self.had_error = true;
                 return;
             }
@@ -97,173 +161,144 @@ impl super::super::Compiler<'_> {
             None
         };
 
-        let colon_end = if let Some(Ok(Token::Colon)) = self.lexer.next() {
-            self.lexer.span().end
+        let colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+            self.tokens.span().end
         } else {
             self.had_error = true;
             return;
         };
 
+        let outer_chunk = std::mem::take(&mut self.chunk);
+        let outer_loop_stack = std::mem::take(&mut self.loop_stack);
+        let outer_stack_temporaries = std::mem::take(&mut self.stack_temporaries);
+        let outer_unwind_stack = std::mem::take(&mut self.unwind_stack);
         let parent_indent = self.current_indent;
-        let class_body_indent = parent_indent + 4; // Methods are indented 4 spaces from class
-
-        // Parse class body - collect methods
-        let mut method_names: Vec<String> = Vec::new();
 
-        loop {
-            let Some((token_result, info)) = self.peek_token_with_indent() else {
-                break;
-            };
-
-            if info.indent <= parent_indent {
-                break;
-            }
-
-            if self.has_newline_between(colon_end, info.start) && info.indent != class_body_indent {
-                self.had_error = true;
-                return;
-            }
-
-            let Ok(token) = token_result else {
-                self.lexer.next();
-                self.had_error = true;
-                return;
-            };
+        let qualname = self.qualified_name(&class_name);
+        self.function_name_stack.push(class_name.clone());
+        self.function_scopes.push(FunctionScope::new_class_body());
+        self.function_depth += 1;
 
-            // Only methods (def statements) are allowed in class body
-            if matches!(token, Token::Def) {
-                // Parse the method as a function
-                self.lexer.next(); // consume 'def'
+        let doc = self.docstring();
+        let body_had_statement = self.parse_suite(parent_indent, colon_end);
 
-                let method_name = match self.lexer.next() {
-                    Some(Ok(Token::Identifier(identifier))) => identifier,
-                    _ => {
-                        self.had_error = true;
-                        return;
-                    }
-                };
+        self.function_depth -= 1;
+        self.function_name_stack.pop();
+        let scope = self
+            .function_scopes
+            .pop()
+            .expect("class body scope should exist");
 
-                // Parse the method using shared function parsing helper
-                let (
-                    parameters,
-                    return_type,
-                    _body_had_statement,
-                    function_chunk,
-                    captured_upvalues,
-                    qualname,
-                ) = match self.parse_function_definition(
-                    method_name.clone(),
-                    Some(class_body_indent),
-                    Some(&class_name),
-                ) {
-                    Some(result) => result,
-                    None => return,
-                };
+        if !body_had_statement {
+            self.had_error = true;
+        }
 
-                // Extract parameter names, types, and default values for the prototype
-                let parameter_names: Vec<String> =
-                    parameters.iter().map(|p| p.name.clone()).collect();
-                let parameter_types: Vec<Option<Type>> = parameters
-                    .iter()
-                    .map(|p| p.type_annotation.clone())
-                    .collect();
-                let default_values: Vec<Option<crate::object::Object>> =
-                    parameters.iter().map(|p| p.default_value.clone()).collect();
-
-                let type_info = crate::object::TypeInfo {
-                    parameter_names,
-                    parameter_types,
-                    return_type,
-                    default_values,
-                };
+        // The body ends by making the class from its locals, in the order
+        // they were first assigned, and returning it
+        let mut attribute_names: Vec<String> = Vec::new();
+        for (idx, local) in scope.locals.iter().enumerate() {
+            self.emit_get_variable(0, VariableTarget::Local(idx + 1));
+            attribute_names.push(local.name.clone());
+        }
 
-                let mut prototype = FunctionPrototype::new_with_types(
-                    method_name.clone(),
-                    parameters.len(),
-                    function_chunk,
-                    captured_upvalues,
-                    type_info,
-                    self.module.clone(),
-                );
-                prototype.qualname = qualname;
-                let prototype_value = Rc::new(ObjectType::FunctionPrototype(Rc::new(prototype)));
-                let prototype_const_idx = self.add_constant(prototype_value);
-                self.chunk.code.push(OpCode::OpMakeFunction as u8);
-                self.chunk.code.push(prototype_const_idx as u8);
-
-                method_names.push(method_name);
-            } else {
-                // Skip non-def tokens in class body
-                self.had_error = true;
-                return;
-            }
+        // The field annotations, in declaration order, become `__annotations__`
+        if !scope.annotations.is_empty() {
+            let annotations_idx =
+                self.add_constant(Rc::new(ObjectType::dict_of_names(scope.annotations)));
+            self.emit_constant_operand(OpCode::OpConstant, annotations_idx);
+            attribute_names.push("__annotations__".to_string());
         }
 
-        if self.had_error {
-            return;
+        if let Some(doc) = &doc {
+            let doc_idx = self.add_constant(Rc::new(ObjectType::String(doc.clone())));
+            self.emit_constant_operand(OpCode::OpConstant, doc_idx);
+            attribute_names.push("__doc__".to_string());
         }
 
-        // Methods are already on the stack as functions
-        // Emit name constants after all functions
-        for method_name in method_names.iter() {
-            let name_idx = self.add_constant(Rc::new(ObjectType::String(method_name.clone())));
-            self.chunk.code.push(OpCode::OpConstant as u8);
-            self.chunk.code.push(name_idx as u8);
+        for attribute_name in attribute_names.iter() {
+            let name_idx = self.add_constant(Rc::new(ObjectType::String(attribute_name.clone())));
+            self.emit_constant_operand(OpCode::OpConstant, name_idx);
         }
 
         // Emit class name constant
         let class_name_idx = self.add_constant(Rc::new(ObjectType::String(class_name.clone())));
-        self.chunk.code.push(OpCode::OpConstant as u8);
-        self.chunk.code.push(class_name_idx as u8);
+        self.emit_constant_operand(OpCode::OpConstant, class_name_idx);
 
-        // Emit OpMakeClass with method count
+        // Emit OpMakeClass with attribute count
         self.chunk.code.push(OpCode::OpMakeClass as u8);
-        self.chunk.code.push(method_names.len() as u8);
+        self.chunk.code.push(attribute_names.len() as u8);
+        self.chunk.code.push(OpCode::OpReturn as u8);
+        self.chunk.compact_constants();
+
+        let body_chunk = std::mem::replace(&mut self.chunk, outer_chunk);
+        self.loop_stack = outer_loop_stack;
+        self.stack_temporaries = outer_stack_temporaries;
+        self.unwind_stack = outer_unwind_stack;
+        self.current_indent = parent_indent;
+
+        if self.had_error {
+            return;
+        }
+
+        // Run the body right away, leaving the class on the stack
+        let mut prototype = FunctionPrototype::new(
+            class_name.clone(),
+            0,
+            body_chunk,
+            scope.upvalues,
+            self.module.clone(),
+        );
+        prototype.qualname = qualname;
+        prototype.local_count = scope.max_locals;
+        prototype.doc = doc;
+        let prototype_idx =
+            self.add_constant(Rc::new(ObjectType::FunctionPrototype(Rc::new(prototype))));
+        self.emit_constant_operand(OpCode::OpMakeFunction, prototype_idx);
+        self.chunk.code.push(OpCode::OpCall as u8);
+        self.chunk.code.push(0);
 
         // If there's a parent class, emit OpInherit
         if let Some(parent_name) = parent_name {
             // Get the parent class from globals
-            let parent_idx = self.add_constant(Rc::new(ObjectType::String(parent_name)));
-            self.chunk.code.push(OpCode::OpGetGlobal as u8);
-            self.chunk.code.push(parent_idx as u8);
+            let parent_idx = self.name_constant(&parent_name);
+            self.emit_constant_operand(OpCode::OpGetGlobal, parent_idx);
 
             // Now we have [class, parent] on stack
             self.chunk.code.push(OpCode::OpInherit as u8);
         }
 
         // Define class as global
-        let define_name_idx = self.add_constant(Rc::new(ObjectType::String(class_name)));
-        self.chunk.code.push(OpCode::OpDefineGlobal as u8);
-        self.chunk.code.push(define_name_idx as u8);
+        let define_name_idx = self.name_constant(&class_name);
+        self.emit_constant_operand(OpCode::OpDefineGlobal, define_name_idx);
     }
 
-    /// Parses a function or method definition, extracting the shared logic.
-    /// Returns: (parameters, return_type, body_had_statement, function_chunk, captured_upvalues, qualname)
-    /// If method_indent is Some, this is a method and the body should be parsed at that indent level.
-    /// If class_name is Some, builds qualname as ClassName.method_name instead of using function_name_stack.
+    /// Parses a function or method definition after its name.
+    /// Returns: (parameters, return_type, body_had_statement, function_chunk, captured_upvalues, local_count, qualname, doc)
+    /// The qualname joins the names of the enclosing functions and classes.
     #[allow(clippy::type_complexity)]
     fn parse_function_definition(
         &mut self,
         name: String,
-        method_indent: Option<usize>,
-        class_name: Option<&str>,
     ) -> Option<(
         Vec<Parameter>,
         Option<Type>,
         bool,
         crate::bytecode::Chunk,
         Vec<UpvalueDescriptor>,
+        usize,
         String,
+        Option<String>,
     )> {
-        if self.lexer.next() != Some(Ok(Token::LParen)) {
+        let name_start = self.tokens.span().start;
+        if self.tokens.next() != Some(Ok(Token::LParen)) {
             self.had_error = true;
             return None;
         }
 
         let mut parameters: Vec<Parameter> = Vec::new();
-        if self.lexer.clone().next() != Some(Ok(Token::RParen)) {
+        if self.tokens.peek() != Some(Ok(Token::RParen)) {
             loop {
-                let param_name = match self.lexer.next() {
+                let param_name = match self.tokens.next() {
                     Some(Ok(Token::Identifier(param))) => param,
                     _ => {
                         self.had_error = true;
@@ -272,38 +307,25 @@ impl super::super::Compiler<'_> {
                 };
 
                 // Check for type annotation: param: type
-                let type_annotation = if matches!(self.lexer.clone().next(), Some(Ok(Token::Colon)))
-                {
-                    // For methods, need to differentiate between parameter type annotation and method end colon
-                    if method_indent.is_some() {
-                        let mut lookahead = self.lexer.clone();
-                        lookahead.next(); // consume ':'
-                        match lookahead.next() {
-                            Some(Ok(Token::Identifier(_))) => {
-                                self.lexer.next(); // consume ':'
-                                self.parse_type_annotation()
-                            }
-                            _ => None,
-                        }
-                    } else {
-                        self.lexer.next(); // consume ':'
-                        self.parse_type_annotation()
-                    }
+                let type_annotation = if matches!(self.tokens.peek(), Some(Ok(Token::Colon))) {
+                    self.tokens.next(); // consume ':'
+                    self.parse_type_annotation()
                 } else {
                     None
                 };
 
                 // Check for default value: param = value
-                let default_value = if matches!(self.lexer.clone().next(), Some(Ok(Token::Assign)))
-                {
-                    self.lexer.next(); // consume '='
+                let default_value = if matches!(self.tokens.peek(), Some(Ok(Token::Assign))) {
+                    self.tokens.next(); // consume '='
                     self.parse_constant_default_value()
                 } else {
                     None
                 };
 
-                // Validate: non-default parameters cannot follow default parameters
-                let has_default_params = parameters.iter().any(|p| p.default_value.is_some());
+                // Validate: non-default parameters cannot follow default parameters,
+                // so only the last one need be checked
+                let has_default_params =
+                    parameters.last().is_some_and(|p| p.default_value.is_some());
                 if has_default_params && default_value.is_none() {
                     // Error: non-default parameter after default parameter
                     self.had_error = true;
@@ -320,9 +342,10 @@ impl super::super::Compiler<'_> {
                     parameters.push(Parameter::new(param_name, type_annotation));
                 }
 
-                match self.lexer.clone().next() {
+                self.skip_trailing_comma(Token::RParen);
+                match self.tokens.peek() {
                     Some(Ok(Token::Comma)) => {
-                        self.lexer.next();
+                        self.tokens.next();
                     }
                     Some(Ok(Token::RParen)) => break,
                     _ => {
@@ -333,26 +356,26 @@ impl super::super::Compiler<'_> {
             }
         }
 
-        if self.lexer.next() != Some(Ok(Token::RParen)) {
+        if self.tokens.next() != Some(Ok(Token::RParen)) {
             self.had_error = true;
             return None;
         }
 
-        if parameters.len() > u8::MAX as usize {
-            self.had_error = true;
+        if parameters.len() > MAX_SLOT {
+            self.fail("too many parameters");
             return None;
         }
 
         // Check for return type annotation: -> type
-        let return_type = if matches!(self.lexer.clone().next(), Some(Ok(Token::Arrow))) {
-            self.lexer.next(); // consume '->'
+        let return_type = if matches!(self.tokens.peek(), Some(Ok(Token::Arrow))) {
+            self.tokens.next(); // consume '->'
             self.parse_type_annotation()
         } else {
             None
         };
 
-        let colon_end = if let Some(Ok(Token::Colon)) = self.lexer.next() {
-            self.lexer.span().end
+        let colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+            self.tokens.span().end
         } else {
             self.had_error = true;
             return None;
@@ -360,32 +383,28 @@ impl super::super::Compiler<'_> {
 
         let outer_chunk = std::mem::take(&mut self.chunk);
         let outer_loop_stack = std::mem::take(&mut self.loop_stack);
-        let parent_indent = method_indent.unwrap_or(self.current_indent);
+        let outer_stack_temporaries = std::mem::take(&mut self.stack_temporaries);
+        let outer_unwind_stack = std::mem::take(&mut self.unwind_stack);
+        let parent_indent = self.current_indent;
 
-        // Build qualified name by pushing function name
-        self.function_name_stack.push(name.clone());
+        let qualname = self.qualified_name(&name);
+        self.function_name_stack.push(format!("{}.<locals>", name));
 
         self.function_scopes
             .push(FunctionScope::new_with_params(parameters.clone()));
         self.function_depth += 1;
 
+        for nested in self.nested_function_names(parent_indent) {
+            self.declare_local(nested);
+        }
+
+        let doc = self.docstring();
         let body_had_statement = self.parse_suite(parent_indent, colon_end);
 
         self.function_depth -= 1;
 
-        // Build qualified name BEFORE popping from the stack
-        let qualname = if let Some(class_name) = class_name {
-            format!("{}.{}", class_name, name)
-        } else {
-            self.function_name_stack.join(".")
-        };
-
         self.function_name_stack.pop();
-        let captured_upvalues = self
-            .function_scopes
-            .pop()
-            .map(|scope| scope.upvalues)
-            .unwrap_or_default();
+        let scope = self.function_scopes.pop();
 
         if !body_had_statement && !self.had_error {
             self.had_error = true;
@@ -395,9 +414,18 @@ impl super::super::Compiler<'_> {
             self.chunk.code.push(OpCode::OpReturn as u8);
         }
 
+        self.chunk.compact_constants();
         let function_chunk = std::mem::replace(&mut self.chunk, outer_chunk);
+        if let Some(scope) = &scope {
+            self.lint_function_scope(scope, &function_chunk, name_start);
+        }
+        let (captured_upvalues, local_count) = scope
+            .map(|scope| (scope.upvalues, scope.max_locals))
+            .unwrap_or_default();
         self.loop_stack = outer_loop_stack;
-        self.current_indent = method_indent.unwrap_or(parent_indent);
+        self.stack_temporaries = outer_stack_temporaries;
+        self.unwind_stack = outer_unwind_stack;
+        self.current_indent = parent_indent;
 
         if self.had_error {
             return None;
@@ -409,15 +437,78 @@ impl super::super::Compiler<'_> {
             body_had_statement,
             function_chunk,
             captured_upvalues,
+            local_count,
             qualname,
+            doc,
         ))
     }
 
+    /// The docstring of the body about to be compiled: a string literal, or
+    /// a run of adjacent ones, standing alone as its first statement. The
+    /// statement is still compiled as usual.
+    fn docstring(&self) -> Option<String> {
+        let mut ahead = self.tokens.ahead();
+        let mut doc = String::new();
+        let mut end = None;
+        loop {
+            let next = ahead.next();
+            let on_new_line =
+                end.is_some_and(|end| self.has_newline_between(end, ahead.span().start));
+            match next {
+                Some(Ok(Token::String(text))) if !on_new_line => {
+                    doc.push_str(&text);
+                    end = Some(ahead.span().end);
+                }
+                None | Some(Ok(Token::Semicolon)) => return end.map(|_| doc),
+                _ => return on_new_line.then_some(doc),
+            }
+        }
+    }
+
+    /// Names bound by `def` statements in the function body about to be
+    /// compiled, leaving out those inside nested function and class bodies.
+    /// Declaring them as locals before the body lets a nested function call a
+    /// sibling defined after it: the call goes through the captured slot,
+    /// which holds the sibling by the time the call runs.
+    fn nested_function_names(&self, parent_indent: usize) -> Vec<String> {
+        let mut lookahead = self.tokens.ahead();
+        let mut names = Vec::new();
+        // Indent of the nested `def` or `class` whose body is being skipped
+        let mut skipping_deeper_than = None;
+        while let Some(token) = lookahead.next() {
+            let start = lookahead.span().start;
+            let before = self.source_before(start);
+            let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+            if !before[line_start..].trim().is_empty() {
+                continue;
+            }
+            let indent = self.indent_at(start);
+            if indent <= parent_indent {
+                break;
+            }
+            match skipping_deeper_than {
+                Some(body_indent) if indent > body_indent => continue,
+                _ => skipping_deeper_than = None,
+            }
+            match token {
+                Ok(Token::Def) => {
+                    if let Some(Ok(Token::Identifier(name))) = lookahead.next() {
+                        names.push(name);
+                    }
+                    skipping_deeper_than = Some(indent);
+                }
+                Ok(Token::Class) => skipping_deeper_than = Some(indent),
+                _ => {}
+            }
+        }
+        names
+    }
+
     /// Parses a constant default value for a function parameter.
     /// Only supports literal values: integers, floats, strings, True, False, None.
     /// Returns None if the token is not a valid constant.
     fn parse_constant_default_value(&mut self) -> Option<crate::object::Object> {
-        match self.lexer.next() {
+        match self.tokens.next() {
             Some(Ok(Token::Integer(val))) => Some(Rc::new(ObjectType::Integer(val))),
             Some(Ok(Token::Float(val))) => Some(Rc::new(ObjectType::Float(val))),
             Some(Ok(Token::String(val))) => Some(Rc::new(ObjectType::String(val))),
@@ -0,0 +1,368 @@
+//! Exception statement parsing (try, raise) and the unwinding that break,
+//! continue and return emit when they leave a `try` statement early.
+//!
+//! A `try` statement compiles to:
+//!
+//! ```text
+//!     [SETUP_FINALLY F]           only with a finally clause
+//!     [SETUP_EXCEPT H]            only with except clauses
+//!     body
+//!     [POP_TRY]
+//!     JUMP else
+//! H:  per except clause: match it, run it, END_EXCEPT, JUMP end
+//!     RERAISE                     unless the last clause is a bare except
+//! else:
+//!     else body
+//! end:
+//!     [POP_TRY; ENTER_FINALLY; JUMP body
+//! F:  body: finally suite
+//!     END_FINALLY]
+//! ```
+//!
+//! A handler runs with the stack cut back to its height when installed,
+//! which leaves every local in place: their slots are reserved when the
+//! function is called. Exceptions never reach break, continue or return: they jump
+//! straight out, so before jumping they remove the handlers and finish the
+//! except clauses they leave, and compile a copy of each finally suite they
+//! pass through.
+
+use crate::bytecode::OpCode;
+use crate::token::Token;
+
+use super::super::types::*;
+
+/// Name of the hidden local holding a return value while finally suites
+/// run; no identifier can clash with it.
+const RETURN_VALUE: &str = " return";
+
+impl<'a> super::super::Compiler<'a> {
+    pub(super) fn parse_raise_statement(&mut self) {
+        self.tokens.next(); // Consume 'raise'
+
+        let raise_end = self.tokens.span().end;
+        let has_expression = match self.peek_token_with_indent() {
+            Some((Ok(Token::Semicolon), _)) | None => false,
+            Some((_, info)) => !self.has_newline_between(raise_end, info.start),
+        };
+
+        if !has_expression {
+            self.chunk.code.push(OpCode::OpReraise as u8);
+            return;
+        }
+        if !self.parse_expression() {
+            self.had_error = true;
+            return;
+        }
+        self.chunk.code.push(OpCode::OpRaise as u8);
+    }
+
+    pub(super) fn parse_try_statement(&mut self) {
+        self.tokens.next(); // Consume 'try'
+
+        let try_indent = self.current_indent;
+
+        let colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+            self.tokens.span().end
+        } else {
+            self.had_error = true;
+            return;
+        };
+
+        let (has_except, finally) = self.scan_try_clauses(try_indent);
+        if !has_except && finally.is_none() {
+            self.fail("expected 'except' or 'finally' block");
+            return;
+        }
+
+        let setup_finally = finally.map(|clause| {
+            self.unwind_stack.push(Unwind::Finally(clause));
+            self.emit_jump(OpCode::OpSetupFinally)
+        });
+
+        if has_except {
+            self.parse_try_except(try_indent, colon_end);
+        } else if !self.parse_suite(try_indent, colon_end) && !self.had_error {
+            self.had_error = true;
+        }
+        if self.had_error {
+            return;
+        }
+
+        let Some(setup_finally) = setup_finally else {
+            return;
+        };
+        self.unwind_stack.pop();
+        self.chunk.code.push(OpCode::OpPopTry as u8);
+        self.chunk.code.push(OpCode::OpEnterFinally as u8);
+        let body_jump = self.emit_jump(OpCode::OpJump);
+
+        self.patch_jump(setup_finally);
+        self.patch_jump(body_jump);
+
+        self.tokens.next(); // Consume 'finally'
+        let finally_colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+            self.tokens.span().end
+        } else {
+            self.had_error = true;
+            return;
+        };
+        self.unwind_stack.push(Unwind::FinallyBody);
+        let finally_had_statement = self.parse_suite(try_indent, finally_colon_end);
+        self.unwind_stack.pop();
+        if !finally_had_statement && !self.had_error {
+            self.had_error = true;
+            return;
+        }
+        self.chunk.code.push(OpCode::OpEndFinally as u8);
+    }
+
+    /// Compiles the body of a `try` statement with its except clauses and
+    /// optional else clause.
+    fn parse_try_except(&mut self, try_indent: usize, colon_end: usize) {
+        let setup = self.emit_jump(OpCode::OpSetupExcept);
+
+        self.unwind_stack.push(Unwind::Handler);
+        let body_had_statement = self.parse_suite(try_indent, colon_end);
+        self.unwind_stack.pop();
+        if !body_had_statement && !self.had_error {
+            self.had_error = true;
+        }
+        if self.had_error {
+            return;
+        }
+        self.chunk.code.push(OpCode::OpPopTry as u8);
+        let else_jump = self.emit_jump(OpCode::OpJump);
+
+        self.patch_jump(setup);
+
+        let mut end_jumps = Vec::new();
+        let mut caught_all = false;
+        while matches!(
+            self.peek_token_with_indent(),
+            Some((Ok(Token::Except), info)) if info.indent == try_indent
+        ) {
+            if caught_all {
+                self.fail("default 'except:' must be last");
+                return;
+            }
+            self.tokens.next(); // Consume 'except'
+
+            let mut next_clause = None;
+            if self.tokens.peek() != Some(Ok(Token::Colon)) {
+                if !self.parse_exception_classes() {
+                    self.had_error = true;
+                    return;
+                }
+                self.chunk.code.push(OpCode::OpExceptMatch as u8);
+                next_clause = Some(self.emit_jump(OpCode::OpJumpIfFalse));
+                self.chunk.code.push(OpCode::OpPop as u8);
+
+                if self.tokens.peek() == Some(Ok(Token::As)) {
+                    self.tokens.next(); // Consume 'as'
+                    let Some(Ok(Token::Identifier(name))) = self.tokens.next() else {
+                        self.fail("expected a name after 'as'");
+                        return;
+                    };
+                    let target = self.resolve_variable(&name);
+                    let target = self.binding_target(&name, target, None);
+                    let name_idx = self.variable_name_constant(&name, target);
+                    self.chunk.code.push(OpCode::OpGetCaught as u8);
+                    self.emit_define_variable(name_idx, target);
+                }
+            } else {
+                caught_all = true;
+            }
+
+            let clause_colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+                self.tokens.span().end
+            } else {
+                self.had_error = true;
+                return;
+            };
+            self.unwind_stack.push(Unwind::Except);
+            let clause_had_statement = self.parse_suite(try_indent, clause_colon_end);
+            self.unwind_stack.pop();
+            if !clause_had_statement && !self.had_error {
+                self.had_error = true;
+            }
+            if self.had_error {
+                return;
+            }
+            self.chunk.code.push(OpCode::OpEndExcept as u8);
+            end_jumps.push(self.emit_jump(OpCode::OpJump));
+
+            if let Some(next_clause) = next_clause {
+                self.patch_jump(next_clause);
+                self.chunk.code.push(OpCode::OpPop as u8);
+            }
+        }
+        if !caught_all {
+            self.chunk.code.push(OpCode::OpReraise as u8);
+        }
+
+        self.patch_jump(else_jump);
+        let else_is_next = matches!(
+            self.peek_token_with_indent(),
+            Some((Ok(Token::Else), info)) if info.indent == try_indent
+        );
+        if else_is_next {
+            self.tokens.next(); // Consume 'else'
+            let else_colon_end = if let Some(Ok(Token::Colon)) = self.tokens.next() {
+                self.tokens.span().end
+            } else {
+                self.had_error = true;
+                return;
+            };
+            if !self.parse_suite(try_indent, else_colon_end) && !self.had_error {
+                self.had_error = true;
+            }
+        }
+
+        for jump in end_jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    /// Compiles the classes an except clause catches: one expression, or a
+    /// parenthesized list of them built as a list.
+    fn parse_exception_classes(&mut self) -> bool {
+        if self.tokens.peek() != Some(Ok(Token::LParen)) {
+            return self.parse_expression();
+        }
+        self.tokens.next(); // Consume '('
+        let mut count = 0;
+        while self.tokens.peek() != Some(Ok(Token::RParen)) {
+            if !self.parse_expression() {
+                return false;
+            }
+            count += 1;
+            if self.tokens.peek() == Some(Ok(Token::Comma)) {
+                self.tokens.next();
+            } else {
+                break;
+            }
+        }
+        if self.tokens.next() != Some(Ok(Token::RParen)) || count > u8::MAX as usize {
+            return false;
+        }
+        if count != 1 {
+            self.chunk.code.push(OpCode::OpBuildList as u8);
+            self.chunk.code.push(count as u8);
+        }
+        true
+    }
+
+    /// Looks ahead over the clauses of the `try` statement at `try_indent`,
+    /// returning whether it has except clauses and its finally clause.
+    fn scan_try_clauses(&self, try_indent: usize) -> (bool, Option<FinallyClause<'a>>) {
+        let mut lookahead = self.tokens.ahead();
+        let mut has_except = false;
+        loop {
+            let before = lookahead.position();
+            let consumed_end = lookahead.span().end;
+            let Some(token) = lookahead.next() else {
+                return (has_except, None);
+            };
+            let start = lookahead.span().start;
+            if !self.has_newline_between(consumed_end, start) {
+                continue;
+            }
+            let indent = self.indent_at(start);
+            if indent > try_indent {
+                continue;
+            }
+            match token {
+                Ok(Token::Except) if indent == try_indent => has_except = true,
+                Ok(Token::Else) if indent == try_indent => {}
+                Ok(Token::Finally) if indent == try_indent => {
+                    let clause = FinallyClause {
+                        tokens: self.tokens.fork(before),
+                        indent: try_indent,
+                    };
+                    return (has_except, Some(clause));
+                }
+                _ => return (has_except, None),
+            }
+        }
+    }
+
+    /// Number of locals declared so far in the current function.
+    fn local_count(&self) -> usize {
+        self.function_scopes
+            .last()
+            .map_or(0, |scope| scope.locals.len())
+    }
+
+    /// Emits what break, continue or return must do to leave the parts of
+    /// try statements above `depth` in the unwind stack, innermost first.
+    pub(super) fn emit_unwind(&mut self, depth: usize) {
+        for index in (depth..self.unwind_stack.len()).rev() {
+            match self.unwind_stack[index].clone() {
+                Unwind::Handler => self.chunk.code.push(OpCode::OpPopTry as u8),
+                Unwind::Except => self.chunk.code.push(OpCode::OpEndExcept as u8),
+                Unwind::FinallyBody => self.chunk.code.push(OpCode::OpPopFinally as u8),
+                Unwind::Finally(clause) => {
+                    self.chunk.code.push(OpCode::OpPopTry as u8);
+                    self.emit_finally_copy(clause, index);
+                }
+            }
+        }
+    }
+
+    /// Emits the unwinding for a return whose value is on top of the stack.
+    /// The value is held in a hidden local while finally suites run, so
+    /// their statements find the stack as they expect it.
+    pub(super) fn emit_return_unwind(&mut self) {
+        let runs_finally = self
+            .unwind_stack
+            .iter()
+            .any(|unwind| matches!(unwind, Unwind::Finally(_)));
+        let locals = self.local_count();
+        let held = if runs_finally {
+            self.declare_local(RETURN_VALUE.to_string())
+        } else {
+            None
+        };
+        let Some((slot, _)) = held else {
+            self.emit_unwind(0);
+            return;
+        };
+        self.emit_slot(OpCode::OpSetLocal, slot);
+        self.chunk.code.push(OpCode::OpPop as u8);
+        self.emit_unwind(0);
+        self.emit_slot(OpCode::OpGetLocal, slot);
+        if let Some(scope) = self.function_scopes.last_mut() {
+            scope.truncate_locals(locals);
+        }
+    }
+
+    /// Compiles a copy of the finally suite `clause` in place, as part of the
+    /// try statement at `index` in the unwind stack. The suite is compiled
+    /// again with its statement, which reports any syntax errors in it, and
+    /// locals the copy declares go out of scope at its end.
+    fn emit_finally_copy(&mut self, clause: FinallyClause<'a>, index: usize) {
+        let tokens = std::mem::replace(&mut self.tokens, clause.tokens);
+        let unwind_stack = self.unwind_stack.split_off(index);
+        let indent = self.current_indent;
+        let errors = self.errors.len();
+        let had_error = self.had_error;
+        let error_message = self.error_message;
+        let locals = self.local_count();
+
+        self.tokens.next(); // Consume 'finally'
+        if let Some(Ok(Token::Colon)) = self.tokens.next() {
+            let colon_end = self.tokens.span().end;
+            self.parse_suite(clause.indent, colon_end);
+        }
+        if let Some(scope) = self.function_scopes.last_mut() {
+            scope.truncate_locals(locals);
+        }
+
+        self.tokens = tokens;
+        self.unwind_stack.extend(unwind_stack);
+        self.current_indent = indent;
+        self.errors.truncate(errors);
+        self.had_error = had_error;
+        self.error_message = error_message;
+    }
+}
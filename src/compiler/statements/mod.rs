@@ -2,29 +2,43 @@
 //!
 //! This module contains functions for parsing Python statements including
 //! function definitions, class definitions, control flow statements (if, while, for),
-//! assignments, and expression statements.
+//! exception handling (try, raise), assignments, and expression statements.
 
 mod assignments;
 mod control_flow;
 mod definitions;
+mod exceptions;
 mod simple;
 
 use crate::token::Token;
 
+use super::indentation::{breaks_line, EXPECTED_INDENTED_BLOCK};
+use super::lint::BlockExit;
+
 impl super::Compiler<'_> {
     /// Main statement dispatcher. Parses a single statement based on the current token.
     pub(super) fn parse_statement(&mut self) {
+        self.nested(Self::parse_statement_at_depth);
+    }
+
+    fn parse_statement_at_depth(&mut self) {
         let (token_result, info) = match self.peek_token_with_indent() {
             Some(value) => value,
             None => return,
         };
 
         self.current_indent = info.indent;
+        self.chunk.add_line(self.line_at(info.start));
+        self.chunk.add_statement(self.stack_temporaries);
+        if let Some(&message) = self.logical_lines.errors.get(&info.start) {
+            self.fail(message);
+            return;
+        }
 
         let token = match token_result {
             Ok(token) => token,
             Err(_) => {
-                self.lexer.next(); // Consume the unknown token.
+                self.tokens.next(); // Consume the unknown token.
                 self.had_error = true;
                 return;
             }
@@ -37,18 +51,29 @@ impl super::Compiler<'_> {
             Token::If => self.parse_if_statement(),
             Token::Def => self.parse_function_statement(),
             Token::Class => self.parse_class_statement(),
+            Token::At => self.parse_decorated_statement(),
             Token::Return => self.parse_return_statement(),
             Token::Break => self.parse_break_statement(),
+            Token::Continue => self.parse_continue_statement(),
+            Token::Try => self.parse_try_statement(),
+            Token::Raise => self.parse_raise_statement(),
             Token::Nonlocal => self.parse_nonlocal_statement(),
+            Token::Del => self.parse_del_statement(),
+            Token::Import => self.parse_import_statement(),
+            Token::From => self.parse_from_import_statement(),
             Token::Semicolon => {
-                self.lexer.next(); // Consume stray semicolons.
+                self.tokens.next(); // Consume stray semicolons.
             }
             Token::Unknown => {
-                self.lexer.next(); // Skip unrecognized tokens to prevent infinite loops.
+                self.tokens.next(); // Skip unrecognized tokens to prevent infinite loops.
                 self.had_error = true;
             }
-            Token::Identifier(_) => {
-                if let Some(kind) = self.detect_assignment_kind() {
+            Token::Identifier(_) | Token::Star => {
+                if self.is_unpack_assignment() {
+                    self.parse_unpack_assignment();
+                } else if self.is_annotation_statement() {
+                    self.parse_annotation_statement();
+                } else if let Some(kind) = self.detect_assignment_kind() {
                     self.parse_assignment_statement(kind);
                 } else {
                     self.parse_expression_statement(true);
@@ -59,35 +84,50 @@ impl super::Compiler<'_> {
     }
 
     pub(super) fn has_newline_between(&self, start: usize, end: usize) -> bool {
-        self.source[start..end].chars().any(|ch| ch == '\n')
+        self.source.get(start..end).is_some_and(breaks_line)
     }
 
     pub(super) fn parse_suite(&mut self, parent_indent: usize, colon_end: usize) -> bool {
         let Some((token_result, info)) = self.peek_token_with_indent() else {
+            self.fail(EXPECTED_INDENTED_BLOCK);
             return false;
         };
 
         let inline = !self.has_newline_between(colon_end, info.start);
 
         if inline {
-            match token_result {
-                Ok(_) => {
-                    self.parse_statement();
-                    !self.had_error
-                }
-                Err(_) => {
-                    self.lexer.next();
+            // Every `;`-separated statement on the header's line is in the suite
+            let mut token_result = token_result;
+            loop {
+                if token_result.is_err() {
+                    self.tokens.next();
                     self.had_error = true;
-                    false
+                    return false;
+                }
+                self.parse_statement();
+                if self.had_error {
+                    return false;
+                }
+                if self.tokens.peek() != Some(Ok(Token::Semicolon)) {
+                    return true;
+                }
+                self.tokens.next(); // consume ';'
+                let semicolon_end = self.tokens.span().end;
+                match self.peek_token_with_indent() {
+                    Some((next, info)) if !self.has_newline_between(semicolon_end, info.start) => {
+                        token_result = next;
+                    }
+                    _ => return true,
                 }
             }
         } else {
             if info.indent <= parent_indent {
-                self.had_error = true;
+                self.fail(EXPECTED_INDENTED_BLOCK);
                 return false;
             }
 
             let mut had_statement = false;
+            let mut exit = BlockExit::default();
 
             while let Some((token_result, next_info)) = self.peek_token_with_indent() {
                 if next_info.indent <= parent_indent {
@@ -95,22 +135,15 @@ impl super::Compiler<'_> {
                 }
 
                 match token_result {
-                    Ok(_) => {
+                    Ok(token) => {
+                        self.check_reachable(&mut exit, &token, next_info.start);
                         self.parse_statement();
                         had_statement = true;
                         if self.had_error {
-                            break;
+                            self.recover_from_error(next_info);
                         }
                     }
-                    Err(_) => {
-                        self.lexer.next();
-                        self.had_error = true;
-                        break;
-                    }
-                }
-
-                if self.had_error {
-                    break;
+                    Err(_) => self.skip_invalid_character(),
                 }
             }
 
@@ -1,19 +1,21 @@
-//! Simple statement parsing (print, return, nonlocal, expression).
+//! Simple statement parsing (print, return, nonlocal, del, import, expression).
 
 use crate::bytecode::OpCode;
 use crate::token::Token;
 
+use super::super::types::{SubscriptKind, VariableTarget};
+
 impl super::super::Compiler<'_> {
     pub(super) fn parse_print_statement(&mut self) {
-        self.lexer.next(); // Consume 'print'
-        self.lexer.next(); // Consume '('
+        self.tokens.next(); // Consume 'print'
+        self.tokens.next(); // Consume '('
 
         let mut first = true;
-        while self.lexer.clone().next() != Some(Ok(Token::RParen)) {
+        while self.tokens.peek() != Some(Ok(Token::RParen)) {
             if !first {
                 // Consume the comma separating arguments.
-                if self.lexer.clone().next() == Some(Ok(Token::Comma)) {
-                    self.lexer.next();
+                if self.tokens.peek() == Some(Ok(Token::Comma)) {
+                    self.tokens.next();
                 } else {
                     break;
                 }
@@ -23,8 +25,9 @@ impl super::super::Compiler<'_> {
                 self.had_error = true;
                 return;
             }
+            self.skip_trailing_comma(Token::RParen);
 
-            if self.lexer.clone().next() == Some(Ok(Token::Comma)) {
+            if self.tokens.peek() == Some(Ok(Token::Comma)) {
                 self.chunk.code.push(OpCode::OpPrintSpaced as u8);
             } else {
                 self.chunk.code.push(OpCode::OpPrint as u8);
@@ -34,19 +37,19 @@ impl super::super::Compiler<'_> {
             first = false;
         }
 
-        self.lexer.next(); // Consume ')'
+        self.tokens.next(); // Consume ')'
         self.chunk.code.push(OpCode::OpPrintln as u8);
     }
 
     pub(super) fn parse_return_statement(&mut self) {
-        self.lexer.next(); // consume 'return'
+        self.tokens.next(); // consume 'return'
 
         if self.function_depth == 0 {
             self.had_error = true;
             return;
         }
 
-        let return_end = self.lexer.span().end;
+        let return_end = self.tokens.span().end;
 
         let mut has_expression = false;
         if let Some((token_result, info)) = self.peek_token_with_indent() {
@@ -68,11 +71,12 @@ impl super::super::Compiler<'_> {
             self.emit_nil();
         }
 
+        self.emit_return_unwind();
         self.chunk.code.push(OpCode::OpReturn as u8);
     }
 
     pub(super) fn parse_nonlocal_statement(&mut self) {
-        self.lexer.next(); // consume 'nonlocal'
+        self.tokens.next(); // consume 'nonlocal'
 
         if self.function_depth == 0 {
             self.had_error = true;
@@ -81,7 +85,7 @@ impl super::super::Compiler<'_> {
 
         // Parse comma-separated list of identifiers
         loop {
-            match self.lexer.next() {
+            match self.tokens.next() {
                 Some(Ok(Token::Identifier(name))) => {
                     // Add to nonlocals set in current function scope
                     // The variable doesn't need to exist yet - it will be resolved
@@ -97,10 +101,9 @@ impl super::super::Compiler<'_> {
             }
 
             // Check for comma (more variables) or end of statement
-            let mut lookahead = self.lexer.clone();
-            match lookahead.next() {
+            match self.tokens.peek() {
                 Some(Ok(Token::Comma)) => {
-                    self.lexer.next(); // consume comma
+                    self.tokens.next(); // consume comma
                     continue;
                 }
                 _ => break,
@@ -108,6 +111,182 @@ impl super::super::Compiler<'_> {
         }
     }
 
+    /// Parses `del target[, target]*`, where a target is a global name, whose
+    /// binding is removed, or `name[index]` / `name[start:end]`, whose item or
+    /// slice is removed from the collection. Deleting a global that shadows a
+    /// builtin makes the builtin visible again.
+    pub(super) fn parse_del_statement(&mut self) {
+        self.tokens.next(); // consume 'del'
+
+        loop {
+            let name = match self.tokens.next() {
+                Some(Ok(Token::Identifier(name))) => name,
+                _ => {
+                    self.had_error = true;
+                    return;
+                }
+            };
+
+            if self.tokens.peek() == Some(Ok(Token::LBracket)) {
+                self.tokens.next(); // consume '['
+                let target = self.resolve_variable(&name);
+                let name_idx = self.name_constant(&name);
+                self.emit_get_variable(name_idx, target);
+                let opcode = match self.parse_subscript() {
+                    Some(SubscriptKind::Index) => OpCode::OpDeleteIndex,
+                    Some(SubscriptKind::Slice) => OpCode::OpDeleteSlice,
+                    None => {
+                        self.had_error = true;
+                        return;
+                    }
+                };
+                self.chunk.code.push(opcode as u8);
+                self.emit_set_variable(name_idx, target);
+                self.chunk.code.push(OpCode::OpPop as u8);
+            } else {
+                // Locals live in fixed stack slots and cannot be unbound yet.
+                if self.resolve_variable(&name) != VariableTarget::Global {
+                    self.had_error = true;
+                    return;
+                }
+
+                let name_idx = self.name_constant(&name);
+                self.emit_constant_operand(OpCode::OpDeleteGlobal, name_idx);
+            }
+
+            if self.tokens.peek() == Some(Ok(Token::Comma)) {
+                self.tokens.next(); // consume comma
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parses `import module [as alias][, ...]`, binding each module to its
+    /// alias or its own name. `import package.module` binds the package,
+    /// after importing the module into it.
+    pub(super) fn parse_import_statement(&mut self) {
+        self.tokens.next(); // consume 'import'
+
+        loop {
+            let Some(module) = self.parse_module_name("expected a module name after 'import'")
+            else {
+                return;
+            };
+            let aliased = self.tokens.peek() == Some(Ok(Token::As));
+            let package = module.split('.').next().unwrap_or_default().to_string();
+            let Some(binding) = self.parse_import_alias(&package) else {
+                return;
+            };
+            if aliased || package == module {
+                self.emit_import(&module, None, binding);
+            } else {
+                let module_idx = self.name_constant(&module);
+                self.emit_constant_operand(OpCode::OpImport, module_idx);
+                self.chunk.code.push(OpCode::OpPop as u8);
+                self.emit_import(&package, None, binding);
+            }
+
+            if self.tokens.peek() == Some(Ok(Token::Comma)) {
+                self.tokens.next(); // consume comma
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parses `from module import name [as alias][, ...]`, binding each
+    /// attribute of the module to its alias or its own name. The module may
+    /// be relative, as in `from .sibling import name` or `from .. import
+    /// module`, its leading dots kept in the name the VM resolves.
+    pub(super) fn parse_from_import_statement(&mut self) {
+        self.tokens.next(); // consume 'from'
+
+        let mut dots = String::new();
+        while self.tokens.peek() == Some(Ok(Token::Dot)) {
+            self.tokens.next();
+            dots.push('.');
+        }
+        let module = if dots.is_empty() || self.tokens.peek() != Some(Ok(Token::Import)) {
+            let Some(module) = self.parse_module_name("expected a module name after 'from'") else {
+                return;
+            };
+            dots + &module
+        } else {
+            dots
+        };
+        if self.tokens.next() != Some(Ok(Token::Import)) {
+            self.fail("expected 'import'");
+            return;
+        }
+
+        loop {
+            let Some(Ok(Token::Identifier(name))) = self.tokens.next() else {
+                self.fail("expected a name to import");
+                return;
+            };
+            let Some(binding) = self.parse_import_alias(&name) else {
+                return;
+            };
+            self.emit_import(&module, Some(&name), binding);
+
+            if self.tokens.peek() == Some(Ok(Token::Comma)) {
+                self.tokens.next(); // consume comma
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parses a module name, dotted as in `package.module`, failing with
+    /// `message` if there is none.
+    fn parse_module_name(&mut self, message: &'static str) -> Option<String> {
+        let Some(Ok(Token::Identifier(mut module))) = self.tokens.next() else {
+            self.fail(message);
+            return None;
+        };
+        while self.tokens.peek() == Some(Ok(Token::Dot)) {
+            self.tokens.next(); // consume '.'
+            let Some(Ok(Token::Identifier(name))) = self.tokens.next() else {
+                self.fail("expected a module name after '.'");
+                return None;
+            };
+            module.push('.');
+            module.push_str(&name);
+        }
+        Some(module)
+    }
+
+    /// Parses an optional `as alias`, returning the name to bind.
+    fn parse_import_alias(&mut self, name: &str) -> Option<String> {
+        if self.tokens.peek() != Some(Ok(Token::As)) {
+            return Some(name.to_string());
+        }
+        self.tokens.next(); // consume 'as'
+        match self.tokens.next() {
+            Some(Ok(Token::Identifier(alias))) => Some(alias),
+            _ => {
+                self.fail("expected a name after 'as'");
+                None
+            }
+        }
+    }
+
+    /// Imports `module`, or its attribute `name`, and binds it to `binding`.
+    fn emit_import(&mut self, module: &str, name: Option<&str>, binding: String) {
+        let target = self.resolve_variable(&binding);
+        let target = self.binding_target(&binding, target, None);
+        let binding_idx = self.variable_name_constant(&binding, target);
+
+        let module_idx = self.name_constant(module);
+        self.emit_constant_operand(OpCode::OpImport, module_idx);
+        if let Some(name) = name {
+            let name_idx = self.name_constant(name);
+            self.emit_constant_operand(OpCode::OpImportFrom, name_idx);
+        }
+        self.emit_define_variable(binding_idx, target);
+    }
+
     pub(super) fn parse_expression_statement(&mut self, discard_result: bool) {
         let produced = self.parse_expression();
         if discard_result && produced {
@@ -0,0 +1,49 @@
+//! Syntax error reporting for the compiler.
+//!
+//! When a statement fails to compile the compiler records a `SyntaxError` at
+//! the point where parsing stopped, then skips ahead to the next statement
+//! boundary and carries on, so a single run can report several independent
+//! errors.
+
+use std::fmt;
+
+/// A syntax error found while compiling.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    /// Python exception class: SyntaxError, or IndentationError and TabError
+    /// for misindented lines
+    pub kind: &'static str,
+    /// 1-based line of the offending token
+    pub line: usize,
+    /// 1-based column, counted in characters, of the offending token
+    pub column: usize,
+    /// Human readable description of the failure
+    pub message: String,
+}
+
+impl SyntaxError {
+    pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        SyntaxError {
+            kind: "SyntaxError",
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /// Reports the error as the exception class `kind` instead.
+    pub fn with_kind(mut self, kind: &'static str) -> Self {
+        self.kind = kind;
+        self
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (line {}, column {})",
+            self.kind, self.message, self.line, self.column
+        )
+    }
+}
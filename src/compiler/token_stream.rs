@@ -0,0 +1,195 @@
+//! Token stream the parser reads from.
+//!
+//! Wraps the logos lexer with a buffer of tokens lexed ahead of the parser,
+//! so peeking any number of tokens ahead lexes each token only once instead
+//! of cloning and re-running the lexer for every lookahead.
+
+use super::indentation::indent_at;
+use super::types::TokenInfo;
+use crate::token::Token;
+use logos::{Lexer, Logos};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// A token read from the source with the byte range it covers.
+type Spanned = (Result<Token, ()>, Range<usize>);
+
+#[derive(Debug, Clone)]
+pub(super) struct TokenStream<'a> {
+    source: &'a str,
+    lexer: RefCell<Lexer<'a, Token>>,
+    /// Tokens lexed but not consumed yet, next token first
+    peeked: RefCell<VecDeque<Spanned>>,
+    /// Span the lexer reported once the source ran out, if it has
+    end: RefCell<Option<Range<usize>>>,
+    /// Span of the token consumed last
+    span: Range<usize>,
+    /// Where in `source` the lexed text starts, added to every span
+    offset: usize,
+}
+
+impl<'a> TokenStream<'a> {
+    pub(super) fn new(source: &'a str) -> Self {
+        Self::within(source, 0..source.len())
+    }
+
+    /// A stream over the tokens in `range` of `source` alone, such as an
+    /// expression interpolated into an f-string. Spans stay positions in
+    /// the whole source.
+    pub(super) fn within(source: &'a str, range: Range<usize>) -> Self {
+        TokenStream {
+            source,
+            lexer: RefCell::new(Token::lexer(&source[range.clone()])),
+            peeked: RefCell::new(VecDeque::new()),
+            end: RefCell::new(None),
+            span: range.start..range.start,
+            offset: range.start,
+        }
+    }
+
+    /// Span of the token consumed last.
+    pub(super) fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Source text of the token consumed last.
+    pub(super) fn slice(&self) -> &'a str {
+        &self.source[self.span.clone()]
+    }
+
+    /// The next token, without consuming it.
+    pub(super) fn peek(&self) -> Option<Result<Token, ()>> {
+        self.peek_nth(0)
+    }
+
+    /// The token `n` places after the next one, without consuming anything.
+    pub(super) fn peek_nth(&self, n: usize) -> Option<Result<Token, ()>> {
+        self.spanned(n).map(|(token, _)| token)
+    }
+
+    /// Span of the next token, or None at the end of the source.
+    pub(super) fn peek_span(&self) -> Option<Range<usize>> {
+        self.spanned(0).map(|(_, span)| span)
+    }
+
+    /// The next token along with its indentation information.
+    pub(super) fn peek_with_indent(&self) -> Option<(Result<Token, ()>, TokenInfo)> {
+        let (token, span) = self.spanned(0)?;
+        let info = TokenInfo {
+            indent: indent_at(self.source, span.start),
+            start: span.start,
+        };
+        Some((token, info))
+    }
+
+    /// Iterates over the tokens after the last consumed one without
+    /// consuming them. Tokens it lexes stay buffered for the parser.
+    pub(super) fn ahead(&self) -> Lookahead<'_, 'a> {
+        Lookahead {
+            stream: self,
+            position: 0,
+            span: self.span(),
+        }
+    }
+
+    /// Consumes the next `count` tokens.
+    pub(super) fn advance(&mut self, count: usize) {
+        for _ in 0..count {
+            self.next();
+        }
+    }
+
+    /// A copy of this stream with the next `count` tokens already consumed.
+    pub(super) fn fork(&self, count: usize) -> TokenStream<'a> {
+        let mut fork = self.clone();
+        fork.advance(count);
+        fork
+    }
+
+    /// The `n`th token not consumed yet with its span, lexing up to it.
+    fn spanned(&self, n: usize) -> Option<Spanned> {
+        let mut peeked = self.peeked.borrow_mut();
+        while peeked.len() <= n {
+            peeked.push_back(self.lex()?);
+        }
+        Some(peeked[n].clone())
+    }
+
+    /// Lexes the token after those already buffered.
+    fn lex(&self) -> Option<Spanned> {
+        let mut lexer = self.lexer.borrow_mut();
+        let span = |span: Range<usize>| span.start + self.offset..span.end + self.offset;
+        match lexer.next() {
+            Some(token) => Some((token, span(lexer.span()))),
+            None => {
+                self.end.borrow_mut().get_or_insert(span(lexer.span()));
+                None
+            }
+        }
+    }
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = Result<Token, ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = match self.peeked.get_mut().pop_front() {
+            Some(spanned) => Some(spanned),
+            None => self.lex(),
+        };
+        match next {
+            Some((token, span)) => {
+                self.span = span;
+                Some(token)
+            }
+            None => {
+                if let Some(end) = self.end.get_mut() {
+                    self.span = end.clone();
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Iterator over the tokens ahead of a [`TokenStream`], tracking the span
+/// of the token it yielded last the way a cloned lexer would.
+#[derive(Clone)]
+pub(super) struct Lookahead<'s, 'a> {
+    stream: &'s TokenStream<'a>,
+    position: usize,
+    span: Range<usize>,
+}
+
+impl Lookahead<'_, '_> {
+    /// Span of the token yielded last.
+    pub(super) fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Number of tokens yielded so far.
+    pub(super) fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Iterator for Lookahead<'_, '_> {
+    type Item = Result<Token, ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.stream.spanned(self.position) {
+            Some((token, span)) => {
+                self.position += 1;
+                self.span = span;
+                Some(token)
+            }
+            None => {
+                if let Some(end) = &*self.stream.end.borrow() {
+                    self.span = end.clone();
+                }
+                None
+            }
+        }
+    }
+}
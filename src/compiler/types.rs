@@ -3,37 +3,86 @@
 //! This module contains all the supporting types used throughout the compiler,
 //! including assignment kinds, variable targets, function scopes, and more.
 
+use super::token_stream::TokenStream;
 use crate::object::{Type, UpvalueDescriptor};
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 
 /// Represents the kind of assignment operation being performed.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(super) enum AssignmentKind {
-    /// Simple assignment: `x = value`
-    Simple,
+    /// Simple assignment: `x = value`, or `a = b = value` when `chained`
+    Simple { chained: bool },
     /// Add-assign: `x += value`
     AddAssign,
     /// Multiply-assign: `x *= value`
     MultiplyAssign,
 }
 
+/// One target of a chained or unpacking assignment, with what is needed to store into it.
+#[derive(Debug, Clone)]
+pub(super) enum AssignmentTarget {
+    /// `name = ...`
+    Variable {
+        name_idx: usize,
+        target: VariableTarget,
+    },
+    /// `name.attr = ...`
+    Attribute {
+        name_idx: usize,
+        target: VariableTarget,
+        attr_idx: usize,
+    },
+    /// `name[index] = ...` or `name[start:end] = ...`, holding the compiled
+    /// index expression or slice bounds
+    Subscript {
+        name_idx: usize,
+        target: VariableTarget,
+        index_code: Vec<u8>,
+        kind: SubscriptKind,
+    },
+}
+
+/// Where the value a postfix chain has reached is stored: the variable the
+/// chain starts from and the attributes and indexes it has taken since.
+/// `list.append()` and `list.sort()` store the list they build back along it.
+#[derive(Debug, Clone)]
+pub(super) struct StorePath {
+    pub name_idx: usize,
+    pub target: VariableTarget,
+    pub steps: Vec<PathStep>,
+}
+
+/// One attribute or index taken along a `StorePath`.
+#[derive(Debug, Clone)]
+pub(super) enum PathStep {
+    /// `.attr`, holding the attribute name's constant
+    Attribute(usize),
+    /// `[index]`, holding the compiled index expression
+    Index(Vec<u8>),
+}
+
+/// Whether a subscript is a single index/key or a slice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SubscriptKind {
+    /// `[index]`, compiled to the index expression
+    Index,
+    /// `[start:end:step]`, compiled to the three bounds with nil for any left out
+    Slice,
+}
+
 /// Represents segments of an f-string literal.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) enum FStringSegment {
     /// A literal string segment
     Literal(String),
-    /// An identifier to be interpolated
-    Identifier(String),
-}
-
-/// Represents the ending token for list/generator comprehensions.
-#[allow(dead_code)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(super) enum ComprehensionEnd {
-    /// Ends with `]` (list comprehension)
-    RBracket,
-    /// Ends with `)` (generator expression)
-    RParen,
+    /// A replacement field: the byte ranges of its expression and format
+    /// spec, and its conversion as `OpFormatValue`'s conversion bits
+    Field {
+        expression: Range<usize>,
+        conversion: u8,
+        spec: Option<Range<usize>>,
+    },
 }
 
 /// Represents where a variable is stored (local, upvalue, or global).
@@ -47,25 +96,59 @@ pub(super) enum VariableTarget {
     Global,
 }
 
-/// Context for tracking loop state (for break statements).
+/// Context for tracking loop state (for break and continue statements).
 #[derive(Debug)]
 pub(super) struct LoopContext {
     /// Positions in bytecode where break jumps need to be patched
     pub(super) break_jumps: Vec<usize>,
-    /// Stack depth to clean up to when breaking
-    pub(super) cleanup_depth: usize,
+    /// Loop temporaries on the stack outside the loop, which break pops
+    /// back down to
+    pub(super) stack_depth: usize,
+    /// Where continue jumps back to
+    pub(super) continue_target: usize,
+    /// Length of the unwind stack outside the loop
+    pub(super) unwind_depth: usize,
 }
 
 impl LoopContext {
-    /// Creates a new loop context with the specified cleanup depth.
-    pub(super) fn new(cleanup_depth: usize) -> Self {
+    /// Creates a new loop context entered with `stack_depth` loop
+    /// temporaries on the stack, whose continue statements jump back to
+    /// `continue_target`.
+    pub(super) fn new(stack_depth: usize, continue_target: usize, unwind_depth: usize) -> Self {
         LoopContext {
             break_jumps: Vec::new(),
-            cleanup_depth,
+            stack_depth,
+            continue_target,
+            unwind_depth,
         }
     }
 }
 
+/// A `finally` clause, kept so break, continue and return can compile its
+/// suite again wherever they leave the `try` body.
+#[derive(Debug, Clone)]
+pub(super) struct FinallyClause<'a> {
+    /// Tokens positioned just before the `finally` keyword
+    pub(super) tokens: TokenStream<'a>,
+    /// Indentation of the `try` statement
+    pub(super) indent: usize,
+}
+
+/// What break, continue and return must undo when they jump out of part of
+/// a `try` statement.
+#[derive(Debug, Clone)]
+pub(super) enum Unwind<'a> {
+    /// Inside a body guarded by except clauses: remove the handler
+    Handler,
+    /// Inside an except clause: finish handling the exception
+    Except,
+    /// Inside a body guarded by a finally clause: remove the handler and run
+    /// the finally suite
+    Finally(FinallyClause<'a>),
+    /// Inside a finally suite: drop the exception it holds
+    FinallyBody,
+}
+
 /// Information about a token's position in the source.
 #[derive(Debug, Clone, Copy)]
 pub(super) struct TokenInfo {
@@ -118,6 +201,10 @@ pub(super) struct Local {
     /// Optional type annotation
     #[allow(dead_code)]
     pub(super) type_annotation: Option<Type>,
+    /// Line of the assignment that declared it, for the linter to point at
+    /// should it never be read; None for loop variables and other bindings
+    /// a value is often left unused in
+    pub(super) line: Option<usize>,
 }
 
 impl Local {
@@ -126,6 +213,7 @@ impl Local {
         Local {
             name,
             type_annotation,
+            line: None,
         }
     }
 }
@@ -137,12 +225,22 @@ pub(super) struct FunctionScope {
     pub(super) parameters: Vec<Parameter>,
     /// Local variables declared in this scope
     pub(super) locals: Vec<Local>,
+    /// Map from parameter or local name to its slot for fast lookup
+    slot_map: HashMap<String, usize>,
     /// Upvalues (captured variables from outer scopes)
     pub(super) upvalues: Vec<UpvalueDescriptor>,
     /// Map from variable name to upvalue index for fast lookup
     pub(super) upvalue_map: HashMap<String, usize>,
     /// Set of variables declared as nonlocal
     pub(super) nonlocals: HashSet<String>,
+    /// Most locals declared at once: the slots a call reserves above the
+    /// parameters
+    pub(super) max_locals: usize,
+    /// Whether this is a class body, whose locals become class attributes
+    /// and are not visible to the functions defined in it
+    pub(super) is_class_body: bool,
+    /// Type names of a class body's annotated fields, in declaration order
+    pub(super) annotations: Vec<(String, crate::object::Object)>,
 }
 
 impl FunctionScope {
@@ -153,54 +251,46 @@ impl FunctionScope {
             .into_iter()
             .map(|name| Parameter::new(name, None))
             .collect();
-        FunctionScope {
-            parameters: params,
-            locals: Vec::new(),
-            upvalues: Vec::new(),
-            upvalue_map: HashMap::new(),
-            nonlocals: HashSet::new(),
-        }
+        FunctionScope::new_with_params(params)
     }
 
     /// Creates a new function scope with typed parameters.
     pub(super) fn new_with_params(parameters: Vec<Parameter>) -> Self {
+        let mut slot_map = HashMap::new();
+        for (idx, param) in parameters.iter().enumerate() {
+            slot_map.entry(param.name.clone()).or_insert(idx + 1);
+        }
         FunctionScope {
             parameters,
             locals: Vec::new(),
+            slot_map,
             upvalues: Vec::new(),
             upvalue_map: HashMap::new(),
             nonlocals: HashSet::new(),
+            max_locals: 0,
+            is_class_body: false,
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Creates the scope of a class body.
+    pub(super) fn new_class_body() -> Self {
+        FunctionScope {
+            is_class_body: true,
+            ..FunctionScope::new_with_params(Vec::new())
         }
     }
 
     /// Resolves a variable name to its slot index in this scope.
     /// Returns None if the variable is not found in parameters or locals.
     pub(super) fn resolve(&self, name: &str) -> Option<usize> {
-        if let Some(idx) = self.parameters.iter().position(|param| param.name == name) {
-            return Some(idx + 1);
-        }
-
-        if let Some(idx) = self.locals.iter().position(|local| local.name == name) {
-            return Some(self.parameters.len() + 1 + idx);
-        }
-
-        None
+        self.slot_map.get(name).copied()
     }
 
     /// Declares a variable in this scope without type annotation.
     /// Returns the slot index and whether it was newly created (true) or already existed (false).
     pub(super) fn declare(&mut self, name: String) -> (usize, bool) {
-        if let Some(idx) = self.parameters.iter().position(|param| param.name == name) {
-            return (idx + 1, false);
-        }
-
-        if let Some(idx) = self.locals.iter().position(|local| local.name == name) {
-            return (self.parameters.len() + 1 + idx, false);
-        }
-
-        self.locals.push(Local::new(name, None));
-        let idx = self.locals.len() - 1;
-        (self.parameters.len() + 1 + idx, true)
+        self.declare_with_type(name, None)
     }
 
     /// Declares a variable in this scope with an optional type annotation.
@@ -210,17 +300,22 @@ impl FunctionScope {
         name: String,
         type_annotation: Option<Type>,
     ) -> (usize, bool) {
-        if let Some(idx) = self.parameters.iter().position(|param| param.name == name) {
-            return (idx + 1, false);
-        }
-
-        if let Some(idx) = self.locals.iter().position(|local| local.name == name) {
-            return (self.parameters.len() + 1 + idx, false);
+        if let Some(&slot) = self.slot_map.get(&name) {
+            return (slot, false);
         }
 
+        let slot = self.parameters.len() + 1 + self.locals.len();
+        self.slot_map.insert(name.clone(), slot);
         self.locals.push(Local::new(name, type_annotation));
-        let idx = self.locals.len() - 1;
-        (self.parameters.len() + 1 + idx, true)
+        self.max_locals = self.max_locals.max(self.locals.len());
+        (slot, true)
+    }
+
+    /// Takes the locals declared after the first `len` out of scope.
+    pub(super) fn truncate_locals(&mut self, len: usize) {
+        for local in self.locals.drain(len.min(self.locals.len())..) {
+            self.slot_map.remove(&local.name);
+        }
     }
 
     /// Adds an upvalue to this scope, returning its index.
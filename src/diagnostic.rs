@@ -0,0 +1,163 @@
+//! Diagnostics in a shape editors and language servers can consume.
+//!
+//! Syntax errors, lints and runtime errors each become a `Diagnostic`
+//! naming the file, a severity, the exception class or lint code, the
+//! message and the source range it concerns. `to_json` writes one as a
+//! single line of JSON:
+//!
+//! ```text
+//! {"severity":"error","code":"SyntaxError","message":"invalid syntax","file":"a.py","range":{"start":{"line":1,"column":7},"end":{"line":1,"column":7}}}
+//! ```
+//!
+//! Lines and columns are 1-based, columns counted in characters. Syntax
+//! errors and lints point at a single position, so their range starts and
+//! ends there. A runtime error covers the whole line the innermost frame was
+//! running, from its first column to the start of the next line, and has no
+//! range when the bytecode records no line.
+
+use crate::compiler::lint::Lint;
+use crate::compiler::syntax_error::SyntaxError;
+use crate::vm::runtime_error::RuntimeError;
+use std::fmt::Write;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A 1-based line and column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The source a diagnostic concerns, from `start` up to `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    fn at(line: usize, column: usize) -> Self {
+        let position = Position { line, column };
+        Range {
+            start: position,
+            end: position,
+        }
+    }
+}
+
+/// A problem found in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Exception class for errors, such as `SyntaxError`, or lint code
+    pub code: String,
+    pub message: String,
+    pub file: String,
+    pub range: Option<Range>,
+}
+
+impl Diagnostic {
+    pub fn from_syntax_error(file: &str, error: &SyntaxError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: error.kind.to_string(),
+            message: error.message.clone(),
+            file: file.to_string(),
+            range: Some(Range::at(error.line, error.column)),
+        }
+    }
+
+    pub fn from_lint(file: &str, lint: &Lint) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: lint.kind.code().to_string(),
+            message: lint.message.clone(),
+            file: file.to_string(),
+            range: Some(Range::at(lint.line, lint.column)),
+        }
+    }
+
+    pub fn from_runtime_error(file: &str, error: &RuntimeError) -> Self {
+        let line = error.traceback.last().and_then(|frame| frame.line);
+        Diagnostic {
+            severity: Severity::Error,
+            code: error.kind.clone(),
+            message: error.message.clone(),
+            file: file.to_string(),
+            range: line.map(|line| Range {
+                start: Position { line, column: 1 },
+                end: Position {
+                    line: line + 1,
+                    column: 1,
+                },
+            }),
+        }
+    }
+
+    /// The diagnostic as one line of JSON, without a trailing newline.
+    pub fn to_json(&self) -> String {
+        let mut json = format!(
+            "{{\"severity\":\"{}\",\"code\":{},\"message\":{},\"file\":{},\"range\":",
+            self.severity.name(),
+            json_string(&self.code),
+            json_string(&self.message),
+            json_string(&self.file)
+        );
+        match self.range {
+            Some(range) => {
+                let _ = write!(
+                    json,
+                    "{{\"start\":{},\"end\":{}}}",
+                    json_position(range.start),
+                    json_position(range.end)
+                );
+            }
+            None => json.push_str("null"),
+        }
+        json.push('}');
+        json
+    }
+}
+
+fn json_position(position: Position) -> String {
+    format!(
+        "{{\"line\":{},\"column\":{}}}",
+        position.line, position.column
+    )
+}
+
+/// `text` as a JSON string literal.
+pub fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            ch if ch < ' ' => {
+                let _ = write!(json, "\\u{:04x}", ch as u32);
+            }
+            ch => json.push(ch),
+        }
+    }
+    json.push('"');
+    json
+}
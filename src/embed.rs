@@ -0,0 +1,69 @@
+//! A string-in, string-out surface for running scripts where there is no
+//! terminal or file system, such as a browser through wasm-bindgen.
+//!
+//! `run` compiles and runs a script on a fresh VM, capturing what it prints,
+//! and reports a failure as the text the command line would show on stderr.
+//! With the `wasm` feature, `run` and `RunOutput` are exported through
+//! wasm-bindgen, so JavaScript calls `run(source)` and reads `stdout` and
+//! `error` off what it returns.
+
+use crate::bytecode::Chunk;
+use crate::compiler::Compiler;
+use crate::vm::{InterpretResult, VM};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// What running a script produced.
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOutput {
+    /// Everything the script printed
+    pub stdout: String,
+    /// Why the script failed to compile or stopped, one line per error, or
+    /// None if it ran to the end
+    pub error: Option<String>,
+}
+
+/// Compiles `source`, returning its syntax errors one per line on failure.
+pub fn compile(source: &str) -> Result<Chunk, String> {
+    Compiler::compile_with_errors(source, "<script>").map_err(|errors| {
+        errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+/// Compiles and runs `source` on a fresh VM, capturing what it prints.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn run(source: &str) -> RunOutput {
+    let chunk = match compile(source) {
+        Ok(chunk) => chunk,
+        Err(error) => {
+            return RunOutput {
+                stdout: String::new(),
+                error: Some(error),
+            }
+        }
+    };
+    let mut vm = VM::new();
+    vm.capture_output();
+    let result = vm.interpret(chunk);
+    let error = match result {
+        InterpretResult::Ok => None,
+        InterpretResult::RuntimeError => Some(match vm.last_error() {
+            Some(error) => error.to_string(),
+            None => "Runtime error.".to_string(),
+        }),
+        InterpretResult::CompileError => Some("Compilation error.".to_string()),
+        InterpretResult::LimitExceeded => Some(match vm.exceeded_limit() {
+            Some(limit) => format!("{}.", limit),
+            None => "Limit exceeded.".to_string(),
+        }),
+    };
+    RunOutput {
+        stdout: vm.take_output(),
+        error,
+    }
+}
@@ -0,0 +1,352 @@
+//! The source formatter behind `oxython fmt`.
+//!
+//! The compiler parses straight to bytecode without building a syntax tree,
+//! so the formatter re-prints the token stream instead: each logical line is
+//! indented four spaces per block, tokens are spaced as PEP 8 spaces them,
+//! single-quoted strings become double-quoted where that needs no escaping,
+//! runs of blank lines are cut to two and comments are kept where they were.
+//! Line breaks inside brackets are kept, with the continued lines indented
+//! one step per open bracket.
+//!
+//! Only source that compiles is formatted, and the result must lex to the
+//! same tokens as the source; should it not, the source is returned as it
+//! was, so formatting never changes what a script does.
+
+use crate::compiler::syntax_error::SyntaxError;
+use crate::compiler::Compiler;
+use crate::token::Token;
+use logos::Logos;
+use std::ops::Range;
+
+/// Spaces per level of indentation.
+const INDENT: usize = 4;
+/// Most blank lines kept in a row.
+const MAX_BLANK_LINES: usize = 2;
+
+/// `source` formatted, or the syntax errors that keep it from being.
+pub fn format_source(source: &str) -> Result<String, Vec<SyntaxError>> {
+    Compiler::compile_with_errors(source, "<script>")?;
+    let Some(tokens) = lex(source) else {
+        return Ok(source.to_string());
+    };
+    let formatted = Formatter::new(source).format(&tokens);
+    let unchanged = lex(&formatted).is_some_and(|formatted_tokens| {
+        formatted_tokens
+            .iter()
+            .map(|(token, _)| token)
+            .eq(tokens.iter().map(|(token, _)| token))
+    });
+    Ok(if unchanged {
+        formatted
+    } else {
+        source.to_string()
+    })
+}
+
+fn lex(source: &str) -> Option<Vec<(Token, Range<usize>)>> {
+    Token::lexer(source)
+        .spanned()
+        .map(|(token, span)| token.ok().map(|token| (token, span)))
+        .collect()
+}
+
+/// A bracket not closed yet.
+struct Bracket {
+    kind: Token,
+    /// Whether the item being read has an annotation, so its `=` is spaced
+    annotated: bool,
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    out: String,
+    /// Widths of the open blocks' indentation in the source, outermost first
+    widths: Vec<usize>,
+    brackets: Vec<Bracket>,
+    /// Indentation level of the logical line being written
+    level: usize,
+    /// Whether the last logical line ended with the `:` opening a block
+    opens_block: bool,
+    /// Blank lines seen since the last line written
+    blank_lines: usize,
+    /// Whether the next token is the first of a logical line
+    at_line_start: bool,
+    /// Whether the last token written was a sign, or a `*` unpacking
+    after_unary: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: &'a str) -> Self {
+        Formatter {
+            source,
+            out: String::new(),
+            widths: vec![0],
+            brackets: Vec::new(),
+            level: 0,
+            opens_block: false,
+            blank_lines: 0,
+            at_line_start: false,
+            after_unary: false,
+        }
+    }
+
+    fn format(mut self, tokens: &[(Token, Range<usize>)]) -> String {
+        let mut previous: Option<(&Token, &Range<usize>)> = None;
+        for (index, (token, span)) in tokens.iter().enumerate() {
+            let gap_start = previous.map_or(0, |(_, span)| span.end);
+            let gap = &self.source[gap_start..span.start];
+            let closes = matches!(token, Token::RParen | Token::RBracket | Token::RBrace);
+            let starts_line = self.write_gap(gap, previous.is_none(), closes);
+
+            if !starts_line {
+                if let Some((previous_token, previous_span)) = previous {
+                    let adjacent = previous_span.end == span.start;
+                    if self.wants_space(previous_token, token, adjacent) {
+                        self.out.push(' ');
+                    }
+                }
+            }
+
+            self.after_unary = matches!(token, Token::Plus | Token::Minus | Token::Star)
+                && (self.at_line_start || previous.is_none_or(|(token, _)| !is_operand_end(token)));
+            self.at_line_start = false;
+            // Adjacent literals make one string, such as a triple-quoted one
+            let touches_string = |other: Option<&(Token, Range<usize>)>| {
+                other.is_some_and(|(other, other_span)| {
+                    matches!(other, Token::String(_))
+                        && (other_span.end == span.start || other_span.start == span.end)
+                })
+            };
+            let joined = touches_string(index.checked_sub(1).and_then(|index| tokens.get(index)))
+                || touches_string(tokens.get(index + 1));
+            self.write_token(token, span, joined);
+            self.track_brackets(token);
+            previous = Some((token, span));
+        }
+        // Comments after the last token, read as if a line followed them
+        let source = self.source;
+        let tail = format!("{}\n", &source[previous.map_or(0, |(_, span)| span.end)..]);
+        self.write_gap(&tail, previous.is_none(), false);
+        self.finish_line();
+        let trimmed = self.out.trim_end().len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out
+    }
+
+    /// Writes what `gap`, the text before the next token, holds: comments,
+    /// blank lines and the start of the next token's line, which `closes` a
+    /// bracket when set. Returns whether the next token starts a line.
+    fn write_gap(&mut self, gap: &str, at_start: bool, closes: bool) -> bool {
+        let mut lines: Vec<&str> = gap.split('\n').collect();
+        let Some(prefix) = lines.pop() else {
+            return at_start;
+        };
+        if lines.is_empty() {
+            if at_start {
+                self.start_logical_line(prefix);
+            }
+            return at_start;
+        }
+
+        let mut lines = lines.into_iter();
+        if !at_start {
+            // The rest of the line the last token was on
+            let rest = lines.next().unwrap_or_default();
+            if let Some(comment) = comment_in(rest) {
+                self.out.push_str("  ");
+                self.out.push_str(comment);
+            } else if rest.trim_end_matches('\r').ends_with('\\') {
+                // A backslash joins the next line to this one
+                self.out.push_str(" \\");
+                self.finish_line();
+                let level = self.level + self.brackets.len().max(1);
+                self.out.push_str(&" ".repeat(level * INDENT));
+                return true;
+            }
+            self.finish_line();
+        }
+
+        for line in lines {
+            match comment_in(line) {
+                Some(comment) => {
+                    self.write_blank_lines();
+                    let level = self.comment_level(width(line));
+                    self.out.push_str(&" ".repeat(level * INDENT));
+                    self.out.push_str(comment);
+                    self.finish_line();
+                }
+                None if self.out.is_empty() => {}
+                None => self.blank_lines += 1,
+            }
+        }
+
+        if self.brackets.is_empty() {
+            self.write_blank_lines();
+            self.start_logical_line(prefix);
+        } else {
+            // A closing bracket lines up with the line that opened it
+            self.blank_lines = 0;
+            let level = self.level + self.brackets.len() - usize::from(closes);
+            self.out.push_str(&" ".repeat(level * INDENT));
+        }
+        true
+    }
+
+    /// Indents a logical line whose indentation in the source is `prefix`.
+    fn start_logical_line(&mut self, prefix: &str) {
+        let width = width(prefix);
+        while self.widths.last().is_some_and(|&top| width < top) {
+            self.widths.pop();
+        }
+        if self.widths.last().is_none_or(|&top| width > top) {
+            self.widths.push(width);
+        }
+        self.level = self.widths.len() - 1;
+        self.opens_block = false;
+        self.at_line_start = true;
+        self.out.push_str(&" ".repeat(self.level * INDENT));
+    }
+
+    /// The level of a comment on a line of its own indented `width`: that
+    /// of the block it lines up with, or of the block about to open.
+    fn comment_level(&self, width: usize) -> usize {
+        if !self.brackets.is_empty() {
+            return self.level + self.brackets.len();
+        }
+        let top = self.widths.last().copied().unwrap_or(0);
+        if width > top && self.opens_block {
+            return self.widths.len();
+        }
+        self.widths
+            .iter()
+            .filter(|&&level_width| level_width <= width)
+            .count()
+            .saturating_sub(1)
+    }
+
+    fn write_blank_lines(&mut self) {
+        if !self.out.is_empty() {
+            for _ in 0..self.blank_lines.min(MAX_BLANK_LINES) {
+                self.out.push('\n');
+            }
+        }
+        self.blank_lines = 0;
+    }
+
+    /// Ends the line being written, dropping trailing spaces.
+    fn finish_line(&mut self) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    /// Writes a token, double-quoting a string unless it is `joined` to
+    /// another or would need escaping.
+    fn write_token(&mut self, token: &Token, span: &Range<usize>, joined: bool) {
+        let text = &self.source[span.clone()];
+        match token {
+            Token::String(content) if text.starts_with('\'') && !joined => {
+                if content.contains(['"', '\\', '\n']) {
+                    self.out.push_str(text);
+                } else {
+                    self.out.push('"');
+                    self.out.push_str(content);
+                    self.out.push('"');
+                }
+            }
+            _ => self.out.push_str(text),
+        }
+        if self.brackets.is_empty() {
+            self.opens_block = *token == Token::Colon;
+        }
+    }
+
+    fn track_brackets(&mut self, token: &Token) {
+        match token {
+            Token::LParen | Token::LBracket | Token::LBrace => self.brackets.push(Bracket {
+                kind: token.clone(),
+                annotated: false,
+            }),
+            Token::RParen | Token::RBracket | Token::RBrace => {
+                self.brackets.pop();
+            }
+            Token::Comma => {
+                if let Some(bracket) = self.brackets.last_mut() {
+                    bracket.annotated = false;
+                }
+            }
+            Token::Colon => {
+                if let Some(bracket) = self.brackets.last_mut() {
+                    bracket.annotated = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a space goes between `previous` and `next` on one line.
+    /// `adjacent` tells whether the source had them touching.
+    fn wants_space(&self, previous: &Token, next: &Token, adjacent: bool) -> bool {
+        let inner = self.brackets.last();
+        let in_parens = inner.is_some_and(|bracket| bracket.kind == Token::LParen);
+        let in_brackets = inner.is_some_and(|bracket| bracket.kind == Token::LBracket);
+        // `=` giving a keyword argument or a default with no annotation
+        let bare_keyword = in_parens && !inner.is_some_and(|bracket| bracket.annotated);
+        match (previous, next) {
+            (Token::String(_), Token::String(_)) if adjacent => false,
+            _ if self.after_unary => false,
+            (Token::Identifier(name), Token::String(_)) if name == "f" => false,
+            (_, Token::RParen | Token::RBracket | Token::RBrace) => false,
+            (_, Token::Comma | Token::Semicolon | Token::Colon) => false,
+            (Token::LParen | Token::LBracket | Token::LBrace, _) => false,
+            // Around the dots of a relative import, `from .. import name`
+            (Token::From, Token::Dot) | (Token::Dot, Token::Import) => true,
+            (Token::Dot, _) | (_, Token::Dot) => false,
+            (Token::Colon, _) => !in_brackets,
+            (Token::Assign, _) | (_, Token::Assign) => !bare_keyword,
+            (Token::At, _) => false,
+            (_, Token::LParen | Token::LBracket) => !is_operand_end(previous),
+            _ => true,
+        }
+    }
+}
+
+/// Whether `token` ends an operand, so a bracket after it calls or indexes
+/// it rather than starting a new expression.
+fn is_operand_end(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Identifier(_)
+            | Token::String(_)
+            | Token::Integer(_)
+            | Token::Float(_)
+            | Token::True
+            | Token::False
+            | Token::Print
+            | Token::RParen
+            | Token::RBracket
+            | Token::RBrace
+    )
+}
+
+/// The comment in `line`, the text between tokens, without trailing space.
+fn comment_in(line: &str) -> Option<&str> {
+    line.find('#').map(|start| line[start..].trim_end())
+}
+
+/// Indentation width of `prefix`, tabs advancing to the next multiple of
+/// eight as the compiler counts them.
+fn width(prefix: &str) -> usize {
+    prefix
+        .chars()
+        .take_while(|ch| matches!(ch, ' ' | '\t'))
+        .fold(0, |width, ch| match ch {
+            '\t' => (width / 8 + 1) * 8,
+            _ => width + 1,
+        })
+}
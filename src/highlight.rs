@@ -0,0 +1,158 @@
+//! Tokens with their source spans and a highlighting category, so editor
+//! plugins can color oxython source exactly as the interpreter reads it.
+//!
+//! Whitespace, comments and line continuations are skipped, as the lexer
+//! skips them. An f-string is split into its replacement fields' expressions,
+//! lexed like any other code, and the text around them. Text the lexer
+//! cannot read, and malformed number literals, come back as errors.
+
+use crate::compiler::Compiler;
+use crate::token::Token;
+use logos::Logos;
+use std::ops::Range;
+
+/// A byte range of the source.
+pub type Span = Range<usize>;
+
+/// How a token is highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// `def`, `class`, `True`, `None` and the other reserved words
+    Keyword,
+    /// Names of variables, functions, classes and attributes
+    Name,
+    Number,
+    String,
+    /// The `f` prefix of an f-string and its text outside replacement fields
+    FString,
+    /// Arithmetic, comparison and assignment operators, `->` and `@`
+    Operator,
+    /// Brackets, commas, dots, colons and semicolons
+    Punctuation,
+    /// Text that is not a token, or a literal that cannot be read
+    Error,
+}
+
+/// Lexes `source` into tokens, each with its span and category.
+pub fn lex_with_spans(source: &str) -> Vec<(Result<Token, ()>, Span, Category)> {
+    let mut tokens = Vec::new();
+    lex_range(source, 0..source.len(), &mut tokens);
+    tokens
+}
+
+/// Lexes the text `range` covers, with spans counted in the whole source.
+fn lex_range(source: &str, range: Span, tokens: &mut Vec<(Result<Token, ()>, Span, Category)>) {
+    let offset = range.start;
+    let mut lexer = Token::lexer(&source[range]).spanned().peekable();
+    while let Some((token, span)) = lexer.next() {
+        let span = span.start + offset..span.end + offset;
+        // The compiler reads an `f` name followed by a string as an f-string
+        if token == Ok(Token::Identifier("f".to_string())) {
+            if let Some((Ok(Token::String(template)), string_span)) = lexer.peek() {
+                let template = template.clone();
+                let string_span = string_span.start + offset..string_span.end + offset;
+                lexer.next();
+                tokens.push((token, span, Category::FString));
+                push_f_string(source, &template, string_span, tokens);
+                continue;
+            }
+        }
+        let category = match &token {
+            Ok(token) => category(token),
+            Err(()) => Category::Error,
+        };
+        tokens.push((token, span, category));
+    }
+}
+
+/// Pushes the text of the f-string at `span` around its replacement fields,
+/// and the tokens of the fields' expressions.
+fn push_f_string(
+    source: &str,
+    template: &str,
+    span: Span,
+    tokens: &mut Vec<(Result<Token, ()>, Span, Category)>,
+) {
+    // The template starts after the opening quote
+    let Some(expressions) = Compiler::f_string_expressions(template, span.start + 1) else {
+        tokens.push((
+            Ok(Token::String(template.to_string())),
+            span,
+            Category::Error,
+        ));
+        return;
+    };
+    let text = |piece: Span, tokens: &mut Vec<_>| {
+        if !piece.is_empty() {
+            let token = Token::String(source[piece.clone()].to_string());
+            tokens.push((Ok(token), piece, Category::FString));
+        }
+    };
+    let mut position = span.start;
+    for expression in expressions {
+        text(position..expression.start, tokens);
+        position = expression.end;
+        lex_range(source, expression, tokens);
+    }
+    text(position..span.end, tokens);
+}
+
+fn category(token: &Token) -> Category {
+    match token {
+        Token::Integer(_) | Token::Float(_) => Category::Number,
+        Token::String(_) => Category::String,
+        Token::Identifier(name) if name == "None" => Category::Keyword,
+        Token::Identifier(_) => Category::Name,
+        Token::Print
+        | Token::For
+        | Token::While
+        | Token::If
+        | Token::Else
+        | Token::Def
+        | Token::Class
+        | Token::Return
+        | Token::Break
+        | Token::Continue
+        | Token::Try
+        | Token::Except
+        | Token::Finally
+        | Token::Raise
+        | Token::Nonlocal
+        | Token::Del
+        | Token::Import
+        | Token::From
+        | Token::As
+        | Token::True
+        | Token::False
+        | Token::In
+        | Token::Not
+        | Token::Is => Category::Keyword,
+        Token::EqualEqual
+        | Token::Assign
+        | Token::PlusEqual
+        | Token::StarEqual
+        | Token::Plus
+        | Token::Minus
+        | Token::Star
+        | Token::Slash
+        | Token::SlashSlash
+        | Token::Percent
+        | Token::Less
+        | Token::LessEqual
+        | Token::Greater
+        | Token::GreaterEqual
+        | Token::Arrow
+        | Token::At => Category::Operator,
+        Token::Semicolon
+        | Token::Comma
+        | Token::Dot
+        | Token::LParen
+        | Token::RParen
+        | Token::LBracket
+        | Token::RBracket
+        | Token::LBrace
+        | Token::RBrace
+        | Token::Colon => Category::Punctuation,
+        Token::Unknown => Category::Error,
+    }
+}
@@ -1,6 +1,16 @@
 pub mod bytecode;
+#[cfg(all(feature = "cli", not(target_arch = "wasm32")))]
 pub mod cli;
 pub mod compiler;
+pub mod diagnostic;
+pub mod embed;
+pub mod formatter;
+pub mod highlight;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod marshal;
 pub mod object;
+pub mod platform;
+pub mod sync;
 pub mod token;
 pub mod vm;
@@ -0,0 +1,418 @@
+//! What the server knows about one open file: its text, its tokens, the
+//! functions and classes it defines and their docstrings.
+//!
+//! Names resolve the way the compiler resolves them: to a definition in the
+//! innermost function around the use, then in the functions enclosing that
+//! one, then in the module. A class body's names are visible inside the body
+//! itself but not to the methods defined in it. A name after a dot is taken
+//! to be a method, looked for first in the class around the use.
+
+use crate::compiler::Compiler;
+use crate::object::ObjectType;
+use crate::token::Token;
+use crate::vm::help::clean_doc;
+use logos::Logos;
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Function,
+    Class,
+}
+
+/// A `def` or `class` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub kind: DefinitionKind,
+    pub name: String,
+    /// Names of the enclosing definitions and this one, joined by dots, with
+    /// `<locals>` after each function as CPython's `__qualname__` has
+    pub qualname: String,
+    /// Where the defined name is written
+    pub name_span: Range<usize>,
+    /// The statement up to its colon, such as `def f(a, b=2) -> int`, with
+    /// runs of whitespace made single spaces
+    pub header: String,
+    /// From the colon to the end of the block
+    pub body: Range<usize>,
+    /// The definition this one is nested in
+    pub parent: Option<usize>,
+}
+
+pub struct Document {
+    pub text: String,
+    tokens: Vec<(Token, Range<usize>)>,
+    pub definitions: Vec<Definition>,
+    /// Cleaned docstrings by qualname, known once the file compiles
+    docs: HashMap<String, String>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let tokens: Vec<_> = Token::lexer(&text)
+            .spanned()
+            .filter_map(|(token, span)| token.ok().map(|token| (token, span)))
+            .collect();
+        let definitions = definitions(&text, &tokens);
+        let mut docs = HashMap::new();
+        if let Ok(chunk) = Compiler::compile_with_errors(&text, "<script>") {
+            collect_docs(&chunk, &mut docs);
+        }
+        Document {
+            text,
+            tokens,
+            definitions,
+            docs,
+        }
+    }
+
+    /// The end of the token starting at `offset`, for widening a diagnostic
+    /// reported at a single position to the token there.
+    pub fn token_end(&self, offset: usize) -> Option<usize> {
+        let index = self.tokens.partition_point(|(_, span)| span.start < offset);
+        self.tokens
+            .get(index)
+            .filter(|(_, span)| span.start == offset)
+            .map(|(_, span)| span.end)
+    }
+
+    /// The index of the name token at `offset`, the cursor touching it on
+    /// either side.
+    fn name_at(&self, offset: usize) -> Option<usize> {
+        let index = self.tokens.partition_point(|(_, span)| span.end < offset);
+        (index..self.tokens.len().min(index + 2)).find(|&index| {
+            let (token, span) = &self.tokens[index];
+            matches!(token, Token::Identifier(_)) && span.start <= offset && offset <= span.end
+        })
+    }
+
+    /// The name at `offset` and where it is written.
+    pub fn word_at(&self, offset: usize) -> Option<(&str, Range<usize>)> {
+        let index = self.name_at(offset)?;
+        match &self.tokens[index] {
+            (Token::Identifier(name), span) => Some((name, span.clone())),
+            _ => None,
+        }
+    }
+
+    /// The function or class the name at `offset` refers to.
+    pub fn definition_at(&self, offset: usize) -> Option<&Definition> {
+        let index = self.name_at(offset)?;
+        let (Token::Identifier(name), span) = &self.tokens[index] else {
+            return None;
+        };
+        if let Some(definition) = self
+            .definitions
+            .iter()
+            .find(|definition| definition.name_span == *span)
+        {
+            return Some(definition);
+        }
+
+        let scopes = self.scopes_at(offset);
+        let after_dot = index
+            .checked_sub(1)
+            .is_some_and(|previous| self.tokens[previous].0 == Token::Dot);
+        if after_dot {
+            let class = scopes
+                .iter()
+                .rev()
+                .find(|&&scope| self.definitions[scope].kind == DefinitionKind::Class);
+            let methods = self.definitions.iter().enumerate().filter(|(_, method)| {
+                method.name == *name
+                    && method.parent.is_some_and(|parent| {
+                        self.definitions[parent].kind == DefinitionKind::Class
+                    })
+            });
+            return methods
+                .clone()
+                .find(|(_, method)| class.is_some() && method.parent == class.copied())
+                .or_else(|| methods.clone().next())
+                .map(|(_, method)| method);
+        }
+
+        for scope in self.visible_scopes(offset) {
+            let mut candidates = self
+                .definitions
+                .iter()
+                .filter(|definition| definition.parent == scope && definition.name == *name);
+            let first = candidates.clone().next();
+            // A name bound twice means the definition last run before the use
+            let before = candidates.rfind(|definition| definition.name_span.start <= offset);
+            if let Some(definition) = before.or(first) {
+                return Some(definition);
+            }
+        }
+        None
+    }
+
+    /// Markdown describing the name at `offset`: a definition's header and
+    /// docstring, or the type of a variable as far as its assignment shows.
+    pub fn hover_at(&self, offset: usize) -> Option<String> {
+        if let Some(definition) = self.definition_at(offset) {
+            let mut hover = format!("```python\n{}\n```", definition.header);
+            if let Some(doc) = self.docs.get(&definition.qualname) {
+                hover.push_str("\n\n");
+                hover.push_str(doc);
+            }
+            return Some(hover);
+        }
+        let (name, _) = self.word_at(offset)?;
+        let inferred = self.inferred_type(name, offset)?;
+        Some(format!("```python\n{}: {}\n```", name, inferred))
+    }
+
+    /// The scopes whose names are visible at `offset`, innermost first and
+    /// ending with the module as None.
+    fn visible_scopes(&self, offset: usize) -> Vec<Option<usize>> {
+        let scopes = self.scopes_at(offset);
+        let innermost = scopes.last().copied();
+        scopes
+            .into_iter()
+            .rev()
+            .filter(|&scope| {
+                self.definitions[scope].kind == DefinitionKind::Function || Some(scope) == innermost
+            })
+            .map(Some)
+            .chain([None])
+            .collect()
+    }
+
+    /// The definitions whose bodies hold `offset`, outermost first.
+    fn scopes_at(&self, offset: usize) -> Vec<usize> {
+        let mut scopes = Vec::new();
+        let mut parent = self.innermost_at(offset);
+        while let Some(scope) = parent {
+            scopes.push(scope);
+            parent = self.definitions[scope].parent;
+        }
+        scopes.reverse();
+        scopes
+    }
+
+    /// The innermost definition whose body holds `offset`.
+    fn innermost_at(&self, offset: usize) -> Option<usize> {
+        self.definitions
+            .iter()
+            .enumerate()
+            .filter(|(_, definition)| definition.body.contains(&offset))
+            .max_by_key(|(_, definition)| definition.body.start)
+            .map(|(index, _)| index)
+    }
+
+    /// The type of the variable `name` used at `offset`, read from an
+    /// annotation on it or from the value first assigned to it in its scope.
+    fn inferred_type(&self, name: &str, offset: usize) -> Option<String> {
+        // A parameter in the header of its function
+        if let Some(header) = self.definitions.iter().position(|definition| {
+            (definition.name_span.end..definition.body.start).contains(&offset)
+        }) {
+            return self.parameter_type(header, name);
+        }
+        for scope in self.visible_scopes(offset) {
+            if let Some(inferred) = scope.and_then(|scope| self.parameter_type(scope, name)) {
+                return Some(inferred);
+            }
+            if let Some(inferred) = self.assigned_type(scope, name) {
+                return Some(inferred);
+            }
+        }
+        None
+    }
+
+    /// The annotation of the parameter `name` of the function `scope`.
+    fn parameter_type(&self, scope: usize, name: &str) -> Option<String> {
+        let definition = &self.definitions[scope];
+        let parameters = definition.header.split_once('(')?.1.rsplit_once(')')?.0;
+        parameters.split(',').find_map(|parameter| {
+            let (parameter, annotation) = parameter.split_once(':')?;
+            let annotation = annotation.split('=').next()?.trim();
+            (parameter.trim() == name).then(|| annotation.to_string())
+        })
+    }
+
+    /// The type of the first value assigned to `name` directly in `scope`,
+    /// the module when None.
+    fn assigned_type(&self, scope: Option<usize>, name: &str) -> Option<String> {
+        let range = scope.map_or(0..self.text.len(), |scope| {
+            self.definitions[scope].body.clone()
+        });
+        let first = self
+            .tokens
+            .partition_point(|(_, span)| span.start < range.start);
+        let tokens = &self.tokens[first..];
+        for (index, (token, span)) in tokens.iter().enumerate() {
+            if span.start >= range.end {
+                break;
+            }
+            if *token != Token::Identifier(name.to_string())
+                || self.innermost_at(span.start) != scope
+                || !self.starts_line(span.start)
+            {
+                continue;
+            }
+            let next = |ahead: usize| tokens.get(index + ahead).map(|(token, _)| token);
+            match next(1) {
+                Some(Token::Colon) => match next(2) {
+                    Some(Token::Identifier(annotation)) => return Some(annotation.clone()),
+                    _ => continue,
+                },
+                Some(Token::Assign) => {}
+                _ => continue,
+            }
+            let inferred = match (next(2), next(3)) {
+                (Some(Token::Integer(_)), _) => "int",
+                (Some(Token::Float(_)), _) => "float",
+                (Some(Token::String(_)), _) => "str",
+                (Some(Token::Identifier(prefix)), Some(Token::String(_))) if prefix == "f" => "str",
+                (Some(Token::True | Token::False), _) => "bool",
+                (Some(Token::LBracket), _) => "list",
+                (Some(Token::LBrace), _) => "dict",
+                (Some(Token::Identifier(none)), _) if none == "None" => "None",
+                (Some(Token::Identifier(callee)), Some(Token::LParen)) => {
+                    let callee_span = &tokens[index + 2].1;
+                    match self.definition_at(callee_span.start) {
+                        Some(class) if class.kind == DefinitionKind::Class => {
+                            return Some(class.name.clone())
+                        }
+                        Some(function) => {
+                            let (_, returned) = function.header.rsplit_once("->")?;
+                            return Some(returned.trim().to_string());
+                        }
+                        None => return builtin_result(callee).map(str::to_string),
+                    }
+                }
+                _ => return None,
+            };
+            return Some(inferred.to_string());
+        }
+        None
+    }
+
+    /// Whether only indentation comes before `offset` on its line.
+    fn starts_line(&self, offset: usize) -> bool {
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        before[line_start..].trim().is_empty()
+    }
+}
+
+/// The type a call to the builtin `name` returns.
+fn builtin_result(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "len" | "int" | "ord" | "hash" | "id" | "sum" => "int",
+        "list" | "sorted" => "list",
+        "dict" | "vars" => "dict",
+        "tuple" | "divmod" => "tuple",
+        "str" | "chr" | "hex" | "oct" | "bin" | "repr" | "format" => "str",
+        "any" | "all" | "callable" | "isinstance" => "bool",
+        _ => return None,
+    })
+}
+
+/// Finds the `def` and `class` statements among `tokens`.
+fn definitions(text: &str, tokens: &[(Token, Range<usize>)]) -> Vec<Definition> {
+    let mut definitions: Vec<Definition> = Vec::new();
+    // Definitions whose blocks are open, with the indentation of their lines
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut depth = 0usize;
+    let mut previous_end = None;
+    for (index, (token, span)) in tokens.iter().enumerate() {
+        let starts_line =
+            depth == 0 && previous_end.is_none_or(|end| text[end..span.start].contains('\n'));
+        previous_end = Some(span.end);
+        match token {
+            Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+            Token::RParen | Token::RBracket | Token::RBrace => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if !starts_line {
+            continue;
+        }
+        let indent = indentation(text, span.start);
+        while open
+            .last()
+            .is_some_and(|&(_, open_indent)| indent <= open_indent)
+        {
+            let (closed, _) = open.pop().expect("a definition should be open");
+            definitions[closed].body.end = span.start;
+        }
+        let kind = match token {
+            Token::Def => DefinitionKind::Function,
+            Token::Class => DefinitionKind::Class,
+            _ => continue,
+        };
+        let Some((Token::Identifier(name), name_span)) = tokens.get(index + 1) else {
+            continue;
+        };
+        // The header ends at the first colon outside brackets
+        let mut header_depth = 0usize;
+        let colon = tokens[index..].iter().find(|(token, _)| {
+            match token {
+                Token::LParen | Token::LBracket | Token::LBrace => header_depth += 1,
+                Token::RParen | Token::RBracket | Token::RBrace => {
+                    header_depth = header_depth.saturating_sub(1)
+                }
+                Token::Colon => return header_depth == 0,
+                _ => {}
+            }
+            false
+        });
+        let Some((_, colon)) = colon else {
+            continue;
+        };
+        let parent = open.last().map(|&(parent, _)| parent);
+        let qualname = match parent {
+            Some(parent) => match definitions[parent].kind {
+                DefinitionKind::Function => {
+                    format!("{}.<locals>.{}", definitions[parent].qualname, name)
+                }
+                DefinitionKind::Class => format!("{}.{}", definitions[parent].qualname, name),
+            },
+            None => name.clone(),
+        };
+        let header = text[span.start..colon.start]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .replace("( ", "(")
+            .replace(" )", ")");
+        open.push((definitions.len(), indent));
+        definitions.push(Definition {
+            kind,
+            name: name.clone(),
+            qualname,
+            name_span: name_span.clone(),
+            header,
+            body: colon.end..text.len(),
+            parent,
+        });
+    }
+    definitions
+}
+
+/// Indentation width of the line holding `offset`, tabs advancing to the
+/// next multiple of eight as the compiler counts them.
+fn indentation(text: &str, offset: usize) -> usize {
+    let line_start = text[..offset].rfind('\n').map_or(0, |index| index + 1);
+    text[line_start..offset]
+        .chars()
+        .fold(0, |width, ch| match ch {
+            '\t' => (width / 8 + 1) * 8,
+            _ => width + 1,
+        })
+}
+
+/// Collects the docstrings of the functions and classes compiled into
+/// `chunk`, by qualname.
+fn collect_docs(chunk: &crate::bytecode::Chunk, docs: &mut HashMap<String, String>) {
+    for constant in &chunk.constants {
+        if let ObjectType::FunctionPrototype(prototype) = &**constant {
+            if let Some(doc) = &prototype.doc {
+                docs.insert(prototype.qualname.clone(), clean_doc(doc));
+            }
+            collect_docs(&prototype.chunk, docs);
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! A language server for editors, run by the `oxython-lsp` binary.
+//!
+//! The server speaks the Language Server Protocol over stdin and stdout.
+//! Opening or saving a file publishes its syntax errors and lints; hovering
+//! a name shows a function's or class's header and docstring, or the type a
+//! variable's assignment gives it; and go-to-definition jumps to the `def`
+//! or `class` statement a name refers to within the same file. Files are
+//! synced whole on every change.
+
+pub mod document;
+pub mod json;
+
+use crate::compiler::Compiler;
+use crate::diagnostic::{Diagnostic, Severity};
+use document::Document;
+use json::Value;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+/// JSON-RPC error code for a request naming no method the server has.
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+/// The state of a session: the files the client has open.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutting_down: bool,
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The code to exit with once the client has sent `exit`: 0 if it asked
+    /// for a shutdown first, as the protocol requires, and 1 otherwise.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Handles one message from the client, returning the messages to send
+    /// back: the response to a request and any notifications it causes.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message.get("method").and_then(Value::as_str);
+        let params = message.get("params").unwrap_or(&Value::Null);
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method.unwrap_or_default(), params);
+        };
+        let result = match method {
+            Some("initialize") => Ok(capabilities()),
+            Some("shutdown") => {
+                self.shutting_down = true;
+                Ok(Value::Null)
+            }
+            Some("textDocument/hover") => Ok(self.hover(params)),
+            Some("textDocument/definition") => Ok(self.definition(params)),
+            _ => Err(Value::object([
+                ("code", Value::Number(METHOD_NOT_FOUND)),
+                ("message", "method not found".into()),
+            ])),
+        };
+        let (key, value) = match result {
+            Ok(result) => ("result", result),
+            Err(error) => ("error", error),
+        };
+        vec![Value::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id),
+            (key, value),
+        ])]
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("text"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                self.documents
+                    .insert(uri.clone(), Document::new(text.to_string()));
+                vec![self.diagnostics(&uri)]
+            }
+            "textDocument/didChange" => {
+                let changes = match params.get("contentChanges") {
+                    Some(Value::Array(changes)) => changes.as_slice(),
+                    _ => &[],
+                };
+                if let Some(text) = changes
+                    .last()
+                    .and_then(|change| change.get("text"))
+                    .and_then(Value::as_str)
+                {
+                    self.documents.insert(uri, Document::new(text.to_string()));
+                }
+                Vec::new()
+            }
+            "textDocument/didSave" => vec![self.diagnostics(&uri)],
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, Vec::new())]
+            }
+            "exit" => {
+                self.exit_code = Some(if self.shutting_down { 0 } else { 1 });
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The document a request's params name and the byte offset of their
+    /// position in it.
+    fn position<'a>(&'a self, params: &Value) -> Option<(&'a str, &'a Document, usize)> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let (uri, document) = self.documents.get_key_value(uri)?;
+        let position = params.get("position")?;
+        let line = position.get("line")?.as_usize()?;
+        let character = position.get("character")?.as_usize()?;
+        Some((uri, document, offset_at(&document.text, line, character)))
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((_, document, offset)) = self.position(params) else {
+            return Value::Null;
+        };
+        let Some(contents) = document.hover_at(offset) else {
+            return Value::Null;
+        };
+        let mut hover = vec![(
+            "contents".to_string(),
+            Value::object([("kind", "markdown".into()), ("value", contents.into())]),
+        )];
+        if let Some((_, span)) = document.word_at(offset) {
+            hover.push((
+                "range".to_string(),
+                range(&document.text, span.start, span.end),
+            ));
+        }
+        Value::Object(hover)
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let Some((uri, document, offset)) = self.position(params) else {
+            return Value::Null;
+        };
+        match document.definition_at(offset) {
+            Some(definition) => Value::object([
+                ("uri", uri.into()),
+                (
+                    "range",
+                    range(
+                        &document.text,
+                        definition.name_span.start,
+                        definition.name_span.end,
+                    ),
+                ),
+            ]),
+            None => Value::Null,
+        }
+    }
+
+    /// The `publishDiagnostics` notification for the open file `uri`: its
+    /// syntax errors, or its lints when it compiles.
+    fn diagnostics(&self, uri: &str) -> Value {
+        let Some(document) = self.documents.get(uri) else {
+            return publish_diagnostics(uri, Vec::new());
+        };
+        let diagnostics = match Compiler::lint(&document.text, "<script>") {
+            Ok(lints) => lints
+                .iter()
+                .map(|lint| Diagnostic::from_lint(uri, lint))
+                .collect(),
+            Err(errors) => errors
+                .iter()
+                .map(|error| Diagnostic::from_syntax_error(uri, error))
+                .collect::<Vec<_>>(),
+        };
+        let diagnostics = diagnostics
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(document, diagnostic))
+            .collect();
+        publish_diagnostics(uri, diagnostics)
+    }
+}
+
+fn capabilities() -> Value {
+    Value::object([
+        (
+            "capabilities",
+            Value::object([
+                (
+                    "textDocumentSync",
+                    Value::object([
+                        ("openClose", true.into()),
+                        // Every change sends the whole text
+                        ("change", 1usize.into()),
+                        ("save", Value::object([("includeText", false.into())])),
+                    ]),
+                ),
+                ("hoverProvider", true.into()),
+                ("definitionProvider", true.into()),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Value::object([
+                ("name", "oxython-lsp".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    Value::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Value::object([
+                ("uri", uri.into()),
+                ("diagnostics", Value::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+/// `diagnostic` in the protocol's shape, its position widened to the token
+/// found there.
+fn lsp_diagnostic(document: &Document, diagnostic: &Diagnostic) -> Value {
+    let text = &document.text;
+    let start = diagnostic.range.map_or(0, |range| {
+        offset_of_column(text, range.start.line, range.start.column)
+    });
+    let end = document.token_end(start).unwrap_or(start);
+    let severity: usize = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+    Value::object([
+        ("range", range(text, start, end)),
+        ("severity", severity.into()),
+        ("source", "oxython".into()),
+        ("code", diagnostic.code.clone().into()),
+        ("message", diagnostic.message.clone().into()),
+    ])
+}
+
+/// The protocol's range for the bytes `start..end` of `text`.
+fn range(text: &str, start: usize, end: usize) -> Value {
+    Value::object([
+        ("start", position_at(text, start)),
+        ("end", position_at(text, end)),
+    ])
+}
+
+/// The protocol's position of byte `offset`: a 0-based line and a column
+/// counted in UTF-16 code units.
+fn position_at(text: &str, offset: usize) -> Value {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    Value::object([
+        ("line", before.matches('\n').count().into()),
+        ("character", character.into()),
+    ])
+}
+
+/// The byte offset of a protocol position, clamped to the end of its line.
+pub fn offset_at(text: &str, line: usize, character: usize) -> usize {
+    let Some(line_start) = line_start(text, line) else {
+        return text.len();
+    };
+    let mut units = 0;
+    for (index, ch) in text[line_start..].char_indices() {
+        if units >= character || ch == '\n' {
+            return line_start + index;
+        }
+        units += ch.len_utf16();
+    }
+    text.len()
+}
+
+/// The byte offset of a 1-based line and character column, as diagnostics
+/// count them.
+fn offset_of_column(text: &str, line: usize, column: usize) -> usize {
+    let Some(line_start) = line_start(text, line.saturating_sub(1)) else {
+        return text.len();
+    };
+    text[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1))
+        .map_or(text.len(), |(index, _)| line_start + index)
+}
+
+/// The byte offset where 0-based `line` starts.
+fn line_start(text: &str, line: usize) -> Option<usize> {
+    if line == 0 {
+        return Some(0);
+    }
+    text.match_indices('\n')
+        .nth(line - 1)
+        .map(|(index, _)| index + 1)
+}
+
+/// Serves the client on stdin and stdout until it exits, returning the
+/// process's exit code.
+pub fn run() -> io::Result<i32> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    serve(&mut stdin.lock(), &mut stdout.lock())
+}
+
+/// Serves messages read from `reader`, writing replies to `writer`.
+pub fn serve<R: BufRead, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<i32> {
+    let mut server = Server::new();
+    while let Some(body) = read_message(reader)? {
+        // A message that is not JSON has no id to answer, so it is dropped
+        let Some(message) = Value::parse(&body) else {
+            continue;
+        };
+        for reply in server.handle(&message) {
+            write_message(writer, &reply)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+    Ok(1)
+}
+
+/// Reads one message's body, or None at the end of input.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
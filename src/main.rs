@@ -1,8 +1,10 @@
-use oxython::cli;
-use std::process;
-
+#[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    if let Err(code) = cli::run_main() {
-        process::exit(code);
+    if let Err(code) = oxython::cli::run_main() {
+        std::process::exit(code);
     }
 }
+
+// On wasm32 the interpreter is driven through `oxython::embed` instead
+#[cfg(target_arch = "wasm32")]
+fn main() {}
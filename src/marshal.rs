@@ -0,0 +1,398 @@
+//! A binary encoding of compiled chunks, so that compiling a script can be
+//! skipped when its bytecode was saved earlier.
+//!
+//! A chunk is written as its code, line and statement tables and constants,
+//! recursing into the chunks of function prototypes and code objects. Only
+//! the constants the compiler emits can be written: numbers, strings,
+//! booleans, None, lists, tuples and dicts of those, and prototypes. Loading
+//! checks every length and tag and gives up on anything malformed, so a
+//! damaged or truncated file reads as missing rather than as bad bytecode.
+//!
+//! The encoding changes whenever `FORMAT_VERSION` does; files written with
+//! another version do not load.
+
+use crate::bytecode::Chunk;
+use crate::object::{FunctionPrototype, Object, ObjectType, Type, UpvalueDescriptor};
+use crate::sync::Rc;
+
+/// Bytes every file starts with.
+const MAGIC: &[u8; 4] = b"OXYC";
+/// Version of the encoding, bumped whenever it changes.
+const FORMAT_VERSION: u8 = 2;
+/// How deeply constants may nest inside one another, well past anything the
+/// compiler produces, so that a corrupt file cannot exhaust the stack.
+const MAX_DEPTH: usize = 256;
+
+/// Encodes `chunk`, or returns None if one of its constants cannot be
+/// written.
+pub fn dump(chunk: &Chunk) -> Option<Vec<u8>> {
+    let mut writer = Writer(MAGIC.to_vec());
+    writer.0.push(FORMAT_VERSION);
+    writer.chunk(chunk)?;
+    Some(writer.0)
+}
+
+/// Decodes a chunk written by `dump`, or returns None if `bytes` is not one.
+pub fn load(bytes: &[u8]) -> Option<Chunk> {
+    let mut reader = Reader {
+        bytes,
+        position: 0,
+        depth: 0,
+    };
+    if reader.take(MAGIC.len())? != MAGIC || reader.byte()? != FORMAT_VERSION {
+        return None;
+    }
+    let chunk = reader.chunk()?;
+    (reader.position == bytes.len()).then_some(chunk)
+}
+
+// Constant tags
+const NIL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const INTEGER: u8 = 3;
+const FLOAT: u8 = 4;
+const STRING: u8 = 5;
+const LIST: u8 = 6;
+const TUPLE: u8 = 7;
+const DICT: u8 = 8;
+const PROTOTYPE: u8 = 9;
+const CODE: u8 = 10;
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    /// An unsigned LEB128 number.
+    fn size(&mut self, mut value: usize) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.0.push(byte);
+                return;
+            }
+            self.0.push(byte | 0x80);
+        }
+    }
+
+    fn string(&mut self, text: &str) {
+        self.size(text.len());
+        self.0.extend_from_slice(text.as_bytes());
+    }
+
+    fn pairs(&mut self, pairs: &[(usize, usize)]) {
+        self.size(pairs.len());
+        for &(first, second) in pairs {
+            self.size(first);
+            self.size(second);
+        }
+    }
+
+    fn chunk(&mut self, chunk: &Chunk) -> Option<()> {
+        self.size(chunk.code.len());
+        self.0.extend_from_slice(&chunk.code);
+        self.pairs(&chunk.lines);
+        self.pairs(&chunk.statements);
+        self.size(chunk.constants.len());
+        for constant in &chunk.constants {
+            self.object(constant)?;
+        }
+        Some(())
+    }
+
+    fn objects(&mut self, tag: u8, items: &[Object]) -> Option<()> {
+        self.0.push(tag);
+        self.size(items.len());
+        items.iter().try_for_each(|item| self.object(item))
+    }
+
+    fn object(&mut self, value: &ObjectType) -> Option<()> {
+        match value {
+            ObjectType::Nil => self.0.push(NIL),
+            ObjectType::Boolean(false) => self.0.push(FALSE),
+            ObjectType::Boolean(true) => self.0.push(TRUE),
+            ObjectType::Integer(number) => {
+                self.0.push(INTEGER);
+                self.0.extend_from_slice(&number.to_le_bytes());
+            }
+            ObjectType::Float(number) => {
+                self.0.push(FLOAT);
+                self.0.extend_from_slice(&number.to_bits().to_le_bytes());
+            }
+            ObjectType::String(text) => {
+                self.0.push(STRING);
+                self.string(text);
+            }
+            ObjectType::List(items) => self.objects(LIST, items)?,
+            ObjectType::Tuple(items) => self.objects(TUPLE, items)?,
+            ObjectType::Dict(entries) => {
+                self.0.push(DICT);
+                self.size(entries.len());
+                for (key, value) in entries {
+                    self.object(key)?;
+                    self.object(value)?;
+                }
+            }
+            ObjectType::FunctionPrototype(prototype) => {
+                self.0.push(PROTOTYPE);
+                self.prototype(prototype)?;
+            }
+            ObjectType::CodeObject(chunk) => {
+                self.0.push(CODE);
+                self.chunk(chunk)?;
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn optional<T>(
+        &mut self,
+        value: &Option<T>,
+        write: impl FnOnce(&mut Self, &T) -> Option<()>,
+    ) -> Option<()> {
+        match value {
+            None => {
+                self.0.push(0);
+                Some(())
+            }
+            Some(value) => {
+                self.0.push(1);
+                write(self, value)
+            }
+        }
+    }
+
+    fn annotation(&mut self, annotation: &Type) -> Option<()> {
+        let tag = match annotation {
+            Type::Int => 0,
+            Type::Float => 1,
+            Type::Str => 2,
+            Type::Bool => 3,
+            Type::List => 4,
+            Type::Dict => 5,
+            Type::Tuple => 6,
+            Type::Any => 7,
+            Type::None => 8,
+            Type::Set => 10,
+            Type::Class(name) => {
+                self.0.push(9);
+                self.string(name);
+                return Some(());
+            }
+        };
+        self.0.push(tag);
+        Some(())
+    }
+
+    fn prototype(&mut self, prototype: &FunctionPrototype) -> Option<()> {
+        self.string(&prototype.name);
+        self.string(&prototype.qualname);
+        self.string(&prototype.module);
+        self.optional(&prototype.doc, |writer, doc| {
+            writer.string(doc);
+            Some(())
+        })?;
+        self.size(prototype.arity);
+        self.size(prototype.required_args);
+        self.size(prototype.local_count);
+        self.size(prototype.upvalues.len());
+        for upvalue in &prototype.upvalues {
+            self.0.push(upvalue.is_local as u8);
+            self.size(upvalue.index);
+        }
+        self.size(prototype.parameter_names.len());
+        for name in &prototype.parameter_names {
+            self.string(name);
+        }
+        self.size(prototype.parameter_types.len());
+        for annotation in &prototype.parameter_types {
+            self.optional(annotation, Self::annotation)?;
+        }
+        self.optional(&prototype.return_type, Self::annotation)?;
+        self.size(prototype.default_values.len());
+        for default in &prototype.default_values {
+            self.optional(default, |writer, value| writer.object(value))?;
+        }
+        self.chunk(&prototype.chunk)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// Constants being read that contain the current one
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Option<&[u8]> {
+        let end = self.position.checked_add(count)?;
+        let bytes = self.bytes.get(self.position..end)?;
+        self.position = end;
+        Some(bytes)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn size(&mut self) -> Option<usize> {
+        let mut value = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            value |= usize::from(byte & 0x7f).checked_shl(shift)?;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    /// A count of items still to read, each taking at least one byte, so a
+    /// corrupt count cannot ask for a huge allocation.
+    fn count(&mut self) -> Option<usize> {
+        let count = self.size()?;
+        (count <= self.bytes.len() - self.position).then_some(count)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let length = self.size()?;
+        String::from_utf8(self.take(length)?.to_vec()).ok()
+    }
+
+    fn word(&mut self) -> Option<[u8; 8]> {
+        self.take(8)?.try_into().ok()
+    }
+
+    fn pairs(&mut self) -> Option<Vec<(usize, usize)>> {
+        (0..self.count()?)
+            .map(|_| Some((self.size()?, self.size()?)))
+            .collect()
+    }
+
+    fn chunk(&mut self) -> Option<Chunk> {
+        let length = self.size()?;
+        let code = self.take(length)?.to_vec();
+        let lines = self.pairs()?;
+        let statements = self.pairs()?;
+        let constants = (0..self.count()?)
+            .map(|_| self.object())
+            .collect::<Option<_>>()?;
+        Some(Chunk {
+            code,
+            constants,
+            lines,
+            statements,
+        })
+    }
+
+    fn objects(&mut self) -> Option<Vec<Object>> {
+        (0..self.count()?).map(|_| self.object()).collect()
+    }
+
+    fn object(&mut self) -> Option<Object> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = self.value();
+        self.depth -= 1;
+        value.map(Rc::new)
+    }
+
+    fn value(&mut self) -> Option<ObjectType> {
+        Some(match self.byte()? {
+            NIL => ObjectType::Nil,
+            FALSE => ObjectType::Boolean(false),
+            TRUE => ObjectType::Boolean(true),
+            INTEGER => ObjectType::Integer(i64::from_le_bytes(self.word()?)),
+            FLOAT => ObjectType::Float(f64::from_bits(u64::from_le_bytes(self.word()?))),
+            STRING => ObjectType::String(self.string()?),
+            LIST => ObjectType::List(self.objects()?),
+            TUPLE => ObjectType::Tuple(self.objects()?),
+            DICT => ObjectType::Dict(
+                (0..self.count()?)
+                    .map(|_| Some((self.object()?, self.object()?)))
+                    .collect::<Option<_>>()?,
+            ),
+            PROTOTYPE => ObjectType::FunctionPrototype(Rc::new(self.prototype()?)),
+            CODE => ObjectType::CodeObject(Rc::new(self.chunk()?)),
+            _ => return None,
+        })
+    }
+
+    fn optional<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        match self.byte()? {
+            0 => Some(None),
+            1 => read(self).map(Some),
+            _ => None,
+        }
+    }
+
+    fn annotation(&mut self) -> Option<Type> {
+        Some(match self.byte()? {
+            0 => Type::Int,
+            1 => Type::Float,
+            2 => Type::Str,
+            3 => Type::Bool,
+            4 => Type::List,
+            5 => Type::Dict,
+            6 => Type::Tuple,
+            7 => Type::Any,
+            8 => Type::None,
+            9 => Type::Class(self.string()?),
+            10 => Type::Set,
+            _ => return None,
+        })
+    }
+
+    fn prototype(&mut self) -> Option<FunctionPrototype> {
+        let name = self.string()?;
+        let qualname = self.string()?;
+        let module = self.string()?;
+        let doc = self.optional(Self::string)?;
+        let arity = self.size()?;
+        let required_args = self.size()?;
+        let local_count = self.size()?;
+        let upvalues = (0..self.count()?)
+            .map(|_| {
+                let is_local = match self.byte()? {
+                    0 => false,
+                    1 => true,
+                    _ => return None,
+                };
+                Some(UpvalueDescriptor {
+                    is_local,
+                    index: self.size()?,
+                })
+            })
+            .collect::<Option<_>>()?;
+        let parameter_names = (0..self.count()?)
+            .map(|_| self.string())
+            .collect::<Option<_>>()?;
+        let parameter_types = (0..self.count()?)
+            .map(|_| self.optional(Self::annotation))
+            .collect::<Option<_>>()?;
+        let return_type = self.optional(Self::annotation)?;
+        let default_values = (0..self.count()?)
+            .map(|_| self.optional(Self::object))
+            .collect::<Option<_>>()?;
+        let chunk = self.chunk()?;
+        Some(FunctionPrototype {
+            name,
+            arity,
+            chunk: Rc::new(chunk),
+            upvalues,
+            parameter_names,
+            parameter_types,
+            return_type,
+            doc,
+            module,
+            qualname,
+            default_values,
+            required_args,
+            local_count,
+        })
+    }
+}
@@ -1,8 +1,10 @@
 use crate::bytecode::Chunk;
-use std::cell::RefCell;
+use crate::sync::Rc;
+use crate::sync::RefCell;
+use crate::vm::hashing::static_hash;
+use crate::vm::{InterpretResult, VM};
 use std::collections::HashMap;
 use std::fmt;
-use std::rc::Rc;
 
 /// Type information for function parameters and return types
 #[derive(Clone, Debug, Default)]
@@ -31,6 +33,8 @@ pub enum Type {
     Dict,
     /// Tuple type (tuple)
     Tuple,
+    /// Set type (set)
+    Set,
     /// Class type with the class name
     Class(String),
     /// Any type (no type constraint)
@@ -50,6 +54,7 @@ impl Type {
             Type::List => "list",
             Type::Dict => "dict",
             Type::Tuple => "tuple",
+            Type::Set => "set",
             Type::Class(name) => name,
             Type::Any => "Any",
             Type::None => "None",
@@ -100,7 +105,7 @@ pub type UpvalueRef = Rc<RefCell<Upvalue>>;
 pub struct FunctionObject {
     pub name: String,
     pub arity: usize,
-    pub chunk: Chunk,
+    pub chunk: Rc<Chunk>, // Shared with the prototype, so creating a closure never copies bytecode
     pub upvalues: Vec<UpvalueRef>,
     pub parameter_names: Vec<String>,
     pub parameter_types: Vec<Option<Type>>,
@@ -108,16 +113,16 @@ pub struct FunctionObject {
     pub doc: Option<String>,
     pub module: String,
     pub qualname: String, // Qualified name (e.g., "outer.inner" for nested functions)
-    pub globals: HashMap<String, Object>, // Reference to global namespace at function definition time
     pub default_values: Vec<Option<Object>>, // Default values for parameters
-    pub required_args: usize,             // Number of required (non-default) parameters
+    pub required_args: usize, // Number of required (non-default) parameters
+    pub local_count: usize, // Slots a call reserves for locals above the parameters
 }
 
 impl FunctionObject {
     pub fn new(
         name: String,
         arity: usize,
-        chunk: Chunk,
+        chunk: impl Into<Rc<Chunk>>,
         upvalues: Vec<UpvalueRef>,
         module: String,
     ) -> Self {
@@ -125,23 +130,23 @@ impl FunctionObject {
             qualname: name.clone(),
             name,
             arity,
-            chunk,
+            chunk: chunk.into(),
             upvalues,
             parameter_names: Vec::new(),
             parameter_types: Vec::new(),
             return_type: None,
             doc: None,
             module,
-            globals: HashMap::new(),
             default_values: Vec::new(),
             required_args: arity,
+            local_count: 0,
         }
     }
 
     pub fn new_with_types(
         name: String,
         arity: usize,
-        chunk: Chunk,
+        chunk: impl Into<Rc<Chunk>>,
         upvalues: Vec<UpvalueRef>,
         type_info: TypeInfo,
         module: String,
@@ -157,20 +162,28 @@ impl FunctionObject {
             qualname: name.clone(),
             name,
             arity,
-            chunk,
+            chunk: chunk.into(),
             upvalues,
             parameter_names: type_info.parameter_names,
             parameter_types: type_info.parameter_types,
             return_type: type_info.return_type,
             doc: None,
             module,
-            globals: HashMap::new(),
             default_values: type_info.default_values,
             required_args,
+            local_count: 0,
         }
     }
 }
 
+/// Disassembles the function under a `== qualname ==` header.
+impl fmt::Display for FunctionObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "== {} ==", self.qualname)?;
+        write!(f, "{}", self.chunk)
+    }
+}
+
 impl PartialEq for FunctionObject {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name && self.arity == other.arity
@@ -182,7 +195,7 @@ impl PartialEq for FunctionObject {
 pub struct FunctionPrototype {
     pub name: String,
     pub arity: usize,
-    pub chunk: Chunk,
+    pub chunk: Rc<Chunk>,
     pub upvalues: Vec<UpvalueDescriptor>,
     pub parameter_names: Vec<String>,
     pub parameter_types: Vec<Option<Type>>,
@@ -192,13 +205,22 @@ pub struct FunctionPrototype {
     pub qualname: String, // Qualified name (e.g., "outer.inner" for nested functions)
     pub default_values: Vec<Option<Object>>, // Default values for parameters
     pub required_args: usize, // Number of required (non-default) parameters
+    pub local_count: usize, // Slots a call reserves for locals above the parameters
+}
+
+/// Disassembles the prototype under a `== qualname ==` header.
+impl fmt::Display for FunctionPrototype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "== {} ==", self.qualname)?;
+        write!(f, "{}", self.chunk)
+    }
 }
 
 impl FunctionPrototype {
     pub fn new(
         name: String,
         arity: usize,
-        chunk: Chunk,
+        chunk: impl Into<Rc<Chunk>>,
         upvalues: Vec<UpvalueDescriptor>,
         module: String,
     ) -> Self {
@@ -206,7 +228,7 @@ impl FunctionPrototype {
             qualname: name.clone(),
             name,
             arity,
-            chunk,
+            chunk: chunk.into(),
             upvalues,
             parameter_names: Vec::new(),
             parameter_types: Vec::new(),
@@ -215,13 +237,14 @@ impl FunctionPrototype {
             module,
             default_values: Vec::new(),
             required_args: arity,
+            local_count: 0,
         }
     }
 
     pub fn new_with_types(
         name: String,
         arity: usize,
-        chunk: Chunk,
+        chunk: impl Into<Rc<Chunk>>,
         upvalues: Vec<UpvalueDescriptor>,
         type_info: TypeInfo,
         module: String,
@@ -237,7 +260,7 @@ impl FunctionPrototype {
             qualname: name.clone(),
             name,
             arity,
-            chunk,
+            chunk: chunk.into(),
             upvalues,
             parameter_names: type_info.parameter_names,
             parameter_types: type_info.parameter_types,
@@ -246,6 +269,7 @@ impl FunctionPrototype {
             module,
             default_values: type_info.default_values,
             required_args,
+            local_count: 0,
         }
     }
 }
@@ -301,6 +325,26 @@ impl ClassObject {
     }
 }
 
+impl ClassObject {
+    /// Finds the class, `class` or one of its ancestors, whose own methods
+    /// include `method`, as a plain function or wrapped by `@classmethod`.
+    /// A `super()` call inside the method looks past this class.
+    pub fn defining_class(class: &Rc<ClassObject>, method: &Object) -> Option<Rc<ClassObject>> {
+        let mut current = Some(class.clone());
+        while let Some(candidate) = current {
+            let defines = candidate.methods.values().any(|value| match &**value {
+                ObjectType::ClassMethod(function) => Rc::ptr_eq(function, method),
+                _ => Rc::ptr_eq(value, method),
+            });
+            if defines {
+                return Some(candidate);
+            }
+            current = candidate.parent.clone();
+        }
+        None
+    }
+}
+
 impl PartialEq for ClassObject {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name
@@ -344,12 +388,274 @@ impl PartialEq for InstanceObject {
     }
 }
 
+/// A module bound by `import`, holding its attributes by name.
+#[derive(Clone, Debug)]
+pub struct ModuleObject {
+    pub name: String,
+    pub attributes: HashMap<String, Object>,
+}
+
+impl PartialEq for ModuleObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+/// A lazily-advanced iterator, such as the one returned by `zip()`.
+#[derive(Debug)]
+pub enum IteratorObject {
+    /// Walks a list, tuple, set, string, or the keys of a dict one element at a
+    /// time. `index` is the position of the next element, a byte offset into a
+    /// string.
+    Sequence { source: Object, index: usize },
+    /// Advances each inner iterator in lockstep, yielding tuples until any is exhausted.
+    Zip(Vec<IteratorRef>),
+    /// A generator expression, whose loop the VM runs as items are requested.
+    Generator(GeneratorFrame),
+}
+
+/// The frame of a generator expression's `<genexpr>` function between the
+/// items it yields.
+#[derive(Debug)]
+pub struct GeneratorFrame {
+    /// The `<genexpr>` function
+    pub function: Object,
+    /// The frame's stack slots from its callee slot up, while suspended
+    pub slots: Vec<Object>,
+    /// Where the frame resumes
+    pub ip: usize,
+    pub state: GeneratorState,
+}
+
+/// Where a generator is in its run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorState {
+    /// Not running, with its frame saved in `slots`
+    Suspended,
+    /// Resumed and not yet yielding or finished
+    Running,
+    /// Done, by returning or raising
+    Finished,
+}
+
+pub type IteratorRef = Rc<RefCell<IteratorObject>>;
+
+impl IteratorObject {
+    /// Returns an iterator over the given value, or None if it is not iterable.
+    /// Iterators are returned as-is so that advancing one is visible to all holders.
+    pub fn from_iterable(value: &Object) -> Option<IteratorRef> {
+        match &**value {
+            ObjectType::List(_)
+            | ObjectType::Tuple(_)
+            | ObjectType::Set(_)
+            | ObjectType::Dict(_)
+            | ObjectType::String(_) => Some(Rc::new(RefCell::new(IteratorObject::Sequence {
+                source: value.clone(),
+                index: 0,
+            }))),
+            ObjectType::Iterator(iterator) => Some(iterator.clone()),
+            _ => None,
+        }
+    }
+
+    /// Advances the iterator, returning None once it is exhausted.
+    pub fn next_item(&mut self) -> Option<Object> {
+        match self {
+            IteratorObject::Sequence { source, index } => match &**source {
+                ObjectType::List(values) | ObjectType::Tuple(values) | ObjectType::Set(values) => {
+                    let item = values.get(*index).cloned();
+                    if item.is_some() {
+                        *index += 1;
+                    }
+                    item
+                }
+                ObjectType::Dict(entries) => {
+                    let (key, _) = entries.get(*index)?;
+                    *index += 1;
+                    Some(key.clone())
+                }
+                ObjectType::String(text) => {
+                    let ch = text.get(*index..)?.chars().next()?;
+                    *index += ch.len_utf8();
+                    Some(Rc::new(ObjectType::String(ch.to_string())))
+                }
+                _ => None,
+            },
+            IteratorObject::Zip(iterators) => {
+                if iterators.is_empty() {
+                    return None;
+                }
+                let mut row = Vec::with_capacity(iterators.len());
+                for iterator in iterators.iter() {
+                    row.push(iterator.borrow_mut().next_item()?);
+                }
+                Some(Rc::new(ObjectType::Tuple(row)))
+            }
+            // Only the VM can run a generator's bytecode (see
+            // `VM::resume_generator`)
+            IteratorObject::Generator(_) => None,
+        }
+    }
+
+    /// Returns the Python-visible type name of the iterator.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            IteratorObject::Sequence { source, .. } => match &**source {
+                ObjectType::Tuple(_) => "tuple_iterator",
+                ObjectType::Set(_) => "set_iterator",
+                ObjectType::String(_) => "str_iterator",
+                _ => "list_iterator",
+            },
+            IteratorObject::Zip(_) => "zip",
+            IteratorObject::Generator(_) => "generator",
+        }
+    }
+}
+
+/// An exception raised by a native function: the name of the builtin
+/// exception class to raise and its message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeError {
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl NativeError {
+    pub fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        NativeError {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn type_error(message: impl Into<String>) -> Self {
+        NativeError::new("TypeError", message)
+    }
+
+    pub fn value_error(message: impl Into<String>) -> Self {
+        NativeError::new("ValueError", message)
+    }
+}
+
+/// A bare message raises a TypeError, the usual complaint about arguments.
+impl From<String> for NativeError {
+    fn from(message: String) -> Self {
+        NativeError::type_error(message)
+    }
+}
+
+/// What a native function returns: a value, or the exception to raise.
+pub type NativeResult = Result<Object, NativeError>;
+
 /// Type alias for native (Rust-implemented) functions
 /// The function receives:
 /// - args: The arguments passed to the function
 /// - class_context: The class context from the current call frame (for super())
-pub type NativeFn =
-    fn(args: &[Object], class_context: Option<Rc<ClassObject>>) -> Result<Object, String>;
+pub type NativeFn = fn(args: &[Object], class_context: Option<Rc<ClassObject>>) -> NativeResult;
+
+/// Type alias for native functions that need the interpreter itself: those
+/// calling back into bytecode, like `eval()` or `sorted(key=...)`, or reading
+/// its state, like `sys.getrecursionlimit()`.
+/// The function receives:
+/// - vm: The running interpreter
+/// - args: The positional arguments passed to the function
+/// - keywords: The arguments passed by keyword, in call order
+///
+/// It raises any error on the VM before returning it.
+pub type VmNativeFn = fn(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult>;
+
+/// The entries of a dict, `(key, value)` in insertion order, each kept with
+/// the hash of its key so finding a key compares hashes before running any
+/// `__eq__`.
+///
+/// A hash is `None` when the dict was built without a VM to run the key's
+/// `__hash__`, as `copy.deepcopy()` builds one; the VM then hashes that key
+/// whenever it looks for another.
+#[derive(Clone, Debug, Default)]
+pub struct DictEntries {
+    entries: Vec<(Object, Object)>,
+    hashes: Vec<Option<i64>>,
+}
+
+impl DictEntries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        DictEntries {
+            entries: Vec::with_capacity(capacity),
+            hashes: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// The hash of the key at `position`, when it is known.
+    pub fn hash(&self, position: usize) -> Option<i64> {
+        self.hashes[position]
+    }
+
+    /// Adds an entry for a key not yet in the dict, whose hash is `hash`.
+    pub fn push(&mut self, key: Object, value: Object, hash: Option<i64>) {
+        self.entries.push((key, value));
+        self.hashes.push(hash);
+    }
+
+    /// Replaces the value of the entry at `position`, keeping its key.
+    pub fn set_value(&mut self, position: usize, value: Object) {
+        self.entries[position].1 = value;
+    }
+
+    /// Removes the entry at `position`, returning it.
+    pub fn remove(&mut self, position: usize) -> (Object, Object) {
+        self.hashes.remove(position);
+        self.entries.remove(position)
+    }
+}
+
+impl std::ops::Deref for DictEntries {
+    type Target = [(Object, Object)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl PartialEq for DictEntries {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl<'a> IntoIterator for &'a DictEntries {
+    type Item = &'a (Object, Object);
+    type IntoIter = std::slice::Iter<'a, (Object, Object)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// Entries with distinct keys, hashed by the rules of builtin values.
+impl FromIterator<(Object, Object)> for DictEntries {
+    fn from_iter<I: IntoIterator<Item = (Object, Object)>>(entries: I) -> Self {
+        let mut dict = DictEntries::new();
+        for (key, value) in entries {
+            let hash = static_hash(&key);
+            dict.push(key, value, hash);
+        }
+        dict
+    }
+}
+
+impl From<Vec<(Object, Object)>> for DictEntries {
+    fn from(entries: Vec<(Object, Object)>) -> Self {
+        entries.into_iter().collect()
+    }
+}
 
 /// Represents all possible data types that can exist in the oxython language.
 /// By wrapping primitive Rust types, we create a unified object model.
@@ -361,15 +667,20 @@ pub enum ObjectType {
     Boolean(bool),
     List(Vec<Object>),
     Tuple(Vec<Object>),
-    Dict(Vec<(String, Object)>),
+    Dict(DictEntries),
+    Set(Vec<Object>), // Distinct items in insertion order
     FunctionPrototype(Rc<FunctionPrototype>),
     Function(Rc<FunctionObject>),
-    NativeFunction(String, NativeFn), // (name, function pointer)
+    NativeFunction(String, NativeFn),     // (name, function pointer)
+    VmNativeFunction(String, VmNativeFn), // (name, function pointer)
     Class(Rc<ClassObject>),
     Instance(Rc<RefCell<InstanceObject>>),
     BoundMethod(Object, Object),         // (instance, method function)
     SuperProxy(Object, Rc<ClassObject>), // (instance, parent class to lookup methods in)
-    CodeObject(Chunk),                   // Bytecode chunk representing compiled code
+    ClassMethod(Object),                 // Function wrapped by @classmethod, bound to the class
+    CodeObject(Rc<Chunk>),               // Bytecode chunk representing compiled code
+    Iterator(IteratorRef),               // Lazily-advanced iterator (e.g. from zip())
+    Module(Rc<ModuleObject>),            // Imported module
     Nil,
 }
 
@@ -380,12 +691,29 @@ impl PartialEq for ObjectType {
             (ObjectType::Float(a), ObjectType::Float(b)) => a == b,
             (ObjectType::String(a), ObjectType::String(b)) => a == b,
             (ObjectType::Boolean(a), ObjectType::Boolean(b)) => a == b,
+            // Booleans equal the integers 0 and 1, and hash like them
+            (ObjectType::Boolean(flag), ObjectType::Integer(number))
+            | (ObjectType::Integer(number), ObjectType::Boolean(flag)) => *flag as i64 == *number,
+            // Integers equal the floats of the same value, as they hash alike
+            (ObjectType::Integer(number), ObjectType::Float(float))
+            | (ObjectType::Float(float), ObjectType::Integer(number)) => {
+                integer_equals_float(*number, *float)
+            }
+            (ObjectType::Boolean(flag), ObjectType::Float(float))
+            | (ObjectType::Float(float), ObjectType::Boolean(flag)) => {
+                integer_equals_float(*flag as i64, *float)
+            }
             (ObjectType::List(a), ObjectType::List(b)) => a == b,
             (ObjectType::Tuple(a), ObjectType::Tuple(b)) => a == b,
             (ObjectType::Dict(a), ObjectType::Dict(b)) => a == b,
+            // Sets are equal when they hold the same items in any order
+            (ObjectType::Set(a), ObjectType::Set(b)) => {
+                a.len() == b.len() && a.iter().all(|item| b.contains(item))
+            }
             (ObjectType::FunctionPrototype(a), ObjectType::FunctionPrototype(b)) => a == b,
             (ObjectType::Function(a), ObjectType::Function(b)) => a == b,
-            (ObjectType::NativeFunction(name_a, _), ObjectType::NativeFunction(name_b, _)) => {
+            (ObjectType::NativeFunction(name_a, _), ObjectType::NativeFunction(name_b, _))
+            | (ObjectType::VmNativeFunction(name_a, _), ObjectType::VmNativeFunction(name_b, _)) => {
                 name_a == name_b
             }
             (ObjectType::Class(a), ObjectType::Class(b)) => a == b,
@@ -396,17 +724,39 @@ impl PartialEq for ObjectType {
             (ObjectType::SuperProxy(inst_a, class_a), ObjectType::SuperProxy(inst_b, class_b)) => {
                 inst_a == inst_b && class_a == class_b
             }
+            (ObjectType::ClassMethod(a), ObjectType::ClassMethod(b)) => a == b,
             (ObjectType::CodeObject(_), ObjectType::CodeObject(_)) => {
                 // Code objects are compared by reference, not by value
                 false
             }
+            (ObjectType::Iterator(a), ObjectType::Iterator(b)) => Rc::ptr_eq(a, b),
+            (ObjectType::Module(a), ObjectType::Module(b)) => Rc::ptr_eq(a, b),
             (ObjectType::Nil, ObjectType::Nil) => true,
             _ => false,
         }
     }
 }
 
+/// Whether `float` is exactly the integer `number`, without the rounding
+/// converting `number` to a float could introduce.
+fn integer_equals_float(number: i64, float: f64) -> bool {
+    // 2**63, the first float past the i64 range
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    float.fract() == 0.0 && (-LIMIT..LIMIT).contains(&float) && float as i64 == number
+}
+
 impl ObjectType {
+    /// A dict keyed by the strings in `entries`, as namespaces such as
+    /// `__globals__` and `vars()` are exposed.
+    pub fn dict_of_names(entries: impl IntoIterator<Item = (String, Object)>) -> ObjectType {
+        ObjectType::Dict(
+            entries
+                .into_iter()
+                .map(|(name, value)| (Rc::new(ObjectType::String(name)), value))
+                .collect(),
+        )
+    }
+
     /// Returns the Type corresponding to this ObjectType.
     /// This is used for runtime type introspection and type checking.
     pub fn get_type(&self) -> Type {
@@ -418,6 +768,7 @@ impl ObjectType {
             ObjectType::List(_) => Type::List,
             ObjectType::Tuple(_) => Type::Tuple,
             ObjectType::Dict(_) => Type::Dict,
+            ObjectType::Set(_) => Type::Set,
             ObjectType::Class(class) => Type::Class(class.name.clone()),
             ObjectType::Instance(instance) => Type::Class(instance.borrow().class.name.clone()),
             ObjectType::Nil => Type::None,
@@ -425,9 +776,13 @@ impl ObjectType {
             ObjectType::Function(_)
             | ObjectType::FunctionPrototype(_)
             | ObjectType::NativeFunction(_, _)
+            | ObjectType::VmNativeFunction(_, _)
             | ObjectType::BoundMethod(_, _)
             | ObjectType::SuperProxy(_, _)
-            | ObjectType::CodeObject(_) => Type::Any,
+            | ObjectType::ClassMethod(_)
+            | ObjectType::CodeObject(_)
+            | ObjectType::Iterator(_)
+            | ObjectType::Module(_) => Type::Any,
         }
     }
 
@@ -441,24 +796,133 @@ impl ObjectType {
             ObjectType::List(_) => "list".to_string(),
             ObjectType::Tuple(_) => "tuple".to_string(),
             ObjectType::Dict(_) => "dict".to_string(),
+            ObjectType::Set(_) => "set".to_string(),
             ObjectType::FunctionPrototype(_) => "function".to_string(),
             ObjectType::Function(_) => "function".to_string(),
-            ObjectType::NativeFunction(_, _) => "builtin_function_or_method".to_string(),
+            ObjectType::NativeFunction(_, _) | ObjectType::VmNativeFunction(_, _) => {
+                "builtin_function_or_method".to_string()
+            }
             ObjectType::Class(_) => "type".to_string(),
             ObjectType::Instance(instance) => instance.borrow().class.name.clone(),
             ObjectType::BoundMethod(_, _) => "method".to_string(),
             ObjectType::SuperProxy(_, _) => "super".to_string(),
+            ObjectType::ClassMethod(_) => "classmethod".to_string(),
             ObjectType::CodeObject(_) => "code".to_string(),
+            ObjectType::Iterator(iterator) => iterator.borrow().type_name().to_string(),
+            ObjectType::Module(_) => "module".to_string(),
             ObjectType::Nil => "NoneType".to_string(),
         }
     }
 }
 
+/// A string literal reading back as `text`: single-quoted unless only
+/// double quotes avoid escaping, with backslashes, the quote and control
+/// characters escaped.
+pub fn quote(text: &str) -> String {
+    let quote = if text.contains('\'') && !text.contains('"') {
+        '"'
+    } else {
+        '\''
+    };
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push(quote);
+    for ch in text.chars() {
+        match ch {
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ if ch == quote => {
+                quoted.push('\\');
+                quoted.push(ch);
+            }
+            _ if ch.is_control() => quoted.push_str(&escape(ch)),
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push(quote);
+    quoted
+}
+
+/// The shortest backslash escape for `ch`: `\xhh`, `\uhhhh` or `\Uhhhhhhhh`.
+pub(crate) fn escape(ch: char) -> String {
+    match ch as u32 {
+        code @ 0..=0xff => format!("\\x{:02x}", code),
+        code @ 0x100..=0xffff => format!("\\u{:04x}", code),
+        code => format!("\\U{:08x}", code),
+    }
+}
+
+/// Writes `value` as it appears inside a container: strings quoted as
+/// `repr()` gives them, everything else as it displays.
+fn write_element(f: &mut fmt::Formatter, value: &ObjectType) -> fmt::Result {
+    match value {
+        ObjectType::String(text) => f.write_str(&quote(text)),
+        _ => write!(f, "{}", value),
+    }
+}
+
+/// Formats a float the way Python's `repr()` does: the shortest digits
+/// that read back as the same value, in positional notation with at least
+/// one fractional digit when the decimal exponent is from -4 to 15, and in
+/// scientific notation with a signed, two-digit exponent otherwise.
+pub fn float_repr(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+
+    // `{:e}` gives the shortest round-tripping digits, as in "-1.25e-7"
+    let scientific = format!("{:e}", value);
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("scientific notation has an exponent");
+    let exponent: i32 = exponent.parse().expect("exponent is an integer");
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+
+    if !(-4..16).contains(&exponent) {
+        let (first, rest) = digits.split_at(1);
+        let fraction = if rest.is_empty() {
+            String::new()
+        } else {
+            format!(".{}", rest)
+        };
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        return format!(
+            "{}{}{}e{}{:02}",
+            sign,
+            first,
+            fraction,
+            exponent_sign,
+            exponent.abs()
+        );
+    }
+
+    if exponent < 0 {
+        let zeros = "0".repeat((-exponent - 1) as usize);
+        return format!("{}0.{}{}", sign, zeros, digits);
+    }
+    let integer_len = exponent as usize + 1;
+    if digits.len() <= integer_len {
+        let zeros = "0".repeat(integer_len - digits.len());
+        format!("{}{}{}.0", sign, digits, zeros)
+    } else {
+        let (integer, fraction) = digits.split_at(integer_len);
+        format!("{}{}.{}", sign, integer, fraction)
+    }
+}
+
 impl fmt::Display for ObjectType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ObjectType::Integer(val) => write!(f, "{}", val),
-            ObjectType::Float(val) => write!(f, "{}", val),
+            ObjectType::Float(val) => f.write_str(&float_repr(*val)),
             ObjectType::String(val) => write!(f, "{}", val),
             ObjectType::Boolean(val) => {
                 if *val {
@@ -473,10 +937,7 @@ impl fmt::Display for ObjectType {
                     if index > 0 {
                         write!(f, ", ")?;
                     }
-                    match &**value {
-                        ObjectType::String(text) => write!(f, "'{}'", text)?,
-                        _ => write!(f, "{}", value)?,
-                    }
+                    write_element(f, value)?
                 }
                 write!(f, "]")
             }
@@ -486,40 +947,109 @@ impl fmt::Display for ObjectType {
                     if index > 0 {
                         write!(f, ", ")?;
                     }
-                    match &**value {
-                        ObjectType::String(text) => write!(f, "'{}'", text)?,
-                        _ => write!(f, "{}", value)?,
-                    }
+                    write_element(f, value)?
                 }
                 if values.len() == 1 {
                     write!(f, ",")?;
                 }
                 write!(f, ")")
             }
+            ObjectType::Set(items) if items.is_empty() => write!(f, "set()"),
+            ObjectType::Set(items) => {
+                write!(f, "{{")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_element(f, item)?
+                }
+                write!(f, "}}")
+            }
             ObjectType::Dict(entries) => {
                 write!(f, "{{")?;
                 for (idx, (key, value)) in entries.iter().enumerate() {
                     if idx > 0 {
                         write!(f, ", ")?;
                     }
-                    write!(f, "'{}': {}", key, value)?;
+                    write_element(f, key)?;
+                    write!(f, ": ")?;
+                    write_element(f, value)?;
                 }
                 write!(f, "}}")
             }
             ObjectType::FunctionPrototype(proto) => write!(f, "<fn {}>", proto.name),
-            ObjectType::Function(func) => write!(f, "<function {}>", func.name),
-            ObjectType::NativeFunction(name, _) => write!(f, "<built-in function {}>", name),
-            ObjectType::Class(class) => write!(f, "<class '{}'>", class.name),
-            ObjectType::Instance(instance) => {
-                write!(f, "<{} instance>", instance.borrow().class.name)
+            ObjectType::Function(func) => {
+                let address = Address(Rc::as_ptr(func) as *const ());
+                write!(f, "<function {} at {}>", func.qualname, address)
             }
-            ObjectType::BoundMethod(_, method) => match &**method {
-                ObjectType::Function(func) => write!(f, "<bound method {}>", func.name),
-                _ => write!(f, "<bound method>"),
+            ObjectType::NativeFunction(name, _) | ObjectType::VmNativeFunction(name, _) => {
+                write!(f, "<built-in function {}>", name)
+            }
+            ObjectType::Class(class) => write!(f, "<class '{}'>", class.name),
+            ObjectType::Instance(instance) => write!(
+                f,
+                "<{} object at {}>",
+                instance.borrow().class.name,
+                Address(Rc::as_ptr(instance) as *const ())
+            ),
+            ObjectType::BoundMethod(receiver, method) => match &**method {
+                ObjectType::Function(func) => {
+                    write!(f, "<bound method {} of {}>", func.qualname, receiver)
+                }
+                ObjectType::NativeFunction(name, _) | ObjectType::VmNativeFunction(name, _) => {
+                    write!(f, "<built-in method {} of {}>", name, receiver)
+                }
+                _ => write!(f, "<bound method of {}>", receiver),
             },
             ObjectType::SuperProxy(_, _) => write!(f, "<super>"),
+            ObjectType::ClassMethod(function) => write!(f, "<classmethod({})>", function),
             ObjectType::CodeObject(_) => write!(f, "<code object>"),
-            ObjectType::Nil => write!(f, "nil"),
+            ObjectType::Iterator(iterator) => write!(
+                f,
+                "<{} object at {}>",
+                iterator.borrow().type_name(),
+                Address(Rc::as_ptr(iterator) as *const ())
+            ),
+            ObjectType::Module(module) => write!(f, "<module '{}' (built-in)>", module.name),
+            ObjectType::Nil => write!(f, "None"),
         }
     }
 }
+
+thread_local! {
+    /// Ids standing in for object addresses while deterministic formatting is on.
+    static STABLE_ADDRESSES: std::cell::RefCell<Option<HashMap<usize, usize>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Turns deterministic address formatting on or off for the current thread.
+///
+/// While on, the address shown in reprs such as `<function f at 0x10>` is an
+/// id numbered in order of first appearance instead of where the allocator
+/// put the object, so the same program always prints the same text. Turning
+/// it on again restarts the numbering.
+pub fn set_deterministic_addresses(enabled: bool) {
+    STABLE_ADDRESSES.with(|ids| *ids.borrow_mut() = enabled.then(HashMap::new));
+}
+
+/// The address reprs show for the object at `pointer`: the pointer itself,
+/// or its stable id while deterministic formatting is on.
+pub fn display_address(pointer: *const ()) -> usize {
+    let address = pointer as usize;
+    let stable = STABLE_ADDRESSES.with(|ids| {
+        let mut ids = ids.borrow_mut();
+        let ids = ids.as_mut()?;
+        let next = ids.len() + 1;
+        Some(*ids.entry(address).or_insert(next))
+    });
+    stable.map_or(address, |id| id * 0x10)
+}
+
+/// An object address as shown in reprs.
+struct Address(*const ());
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", display_address(self.0))
+    }
+}
@@ -0,0 +1,81 @@
+//! What the interpreter needs from its host beyond computation: somewhere for
+//! `print`, and for warnings, to write.
+//!
+//! Everything touching files or the terminal lives in `cli`, apart from the
+//! source modules `import` reads from directories an embedder puts on the
+//! search path. `cli` is left out of wasm32 builds and of builds without the
+//! `cli` feature, so the library compiles to `wasm32-unknown-unknown`. There output is captured by
+//! default, for the embedder to read back with `VM::take_output` (see
+//! `embed`). Hosts with their own console hand `VM::set_output` a
+//! `Output::Writer` instead.
+
+use std::fmt;
+
+/// Where `print` or warnings write.
+pub enum Output {
+    /// The process's standard output
+    Stdout,
+    /// The process's standard error
+    Stderr,
+    /// A buffer the embedder reads back with `VM::take_output`
+    Captured(String),
+    /// A function the embedder passes each piece of printed text
+    Writer(Writer),
+}
+
+#[cfg(not(feature = "sync"))]
+type Writer = Box<dyn FnMut(&str)>;
+#[cfg(feature = "sync")]
+type Writer = Box<dyn FnMut(&str) + Send>;
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::Stdout => write!(f, "Stdout"),
+            Output::Stderr => write!(f, "Stderr"),
+            Output::Captured(buffer) => f.debug_tuple("Captured").field(buffer).finish(),
+            Output::Writer(_) => write!(f, "Writer"),
+        }
+    }
+}
+
+impl Default for Output {
+    /// Standard output, except on wasm32, which has none to write to.
+    fn default() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            Output::Captured(String::new())
+        } else {
+            Output::Stdout
+        }
+    }
+}
+
+impl Output {
+    /// Standard error, except on wasm32, where it is captured as output is.
+    pub fn standard_error() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            Output::Captured(String::new())
+        } else {
+            Output::Stderr
+        }
+    }
+
+    /// Writes `text` without a newline.
+    pub fn write(&mut self, text: &str) {
+        match self {
+            Output::Stdout => print!("{}", text),
+            Output::Stderr => eprint!("{}", text),
+            Output::Captured(buffer) => buffer.push_str(text),
+            Output::Writer(write) => write(text),
+        }
+    }
+
+    /// Takes what has been captured so far, which is nothing unless output
+    /// is being captured.
+    pub fn take(&mut self) -> String {
+        match self {
+            Output::Captured(buffer) => std::mem::take(buffer),
+            Output::Stdout | Output::Stderr | Output::Writer(_) => String::new(),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! The shared pointer and interior mutability types every value is built on.
+//!
+//! By default these are `std::rc::Rc` and `std::cell::RefCell`, which keep
+//! the single-threaded interpreter free of atomic reference counts and locks.
+//! The `sync` feature swaps in `Arc` and a `RefCell` backed by a read-write
+//! lock, with the same `borrow` and `borrow_mut` methods, so that a VM, its
+//! compiled chunks and its values are `Send` and can be handed between the
+//! threads of an async server. A VM is still run by one thread at a time.
+//!
+//! Per-thread state such as the small-int cache and the cycle collector's
+//! list of tracked containers stays per thread under `sync`: `gc.collect()`
+//! only looks at containers created on the thread calling it.
+
+#[cfg(not(feature = "sync"))]
+pub use std::cell::RefCell;
+#[cfg(not(feature = "sync"))]
+pub use std::rc::{Rc, Weak};
+
+#[cfg(feature = "sync")]
+pub use locked::RefCell;
+#[cfg(feature = "sync")]
+pub use std::sync::{Arc as Rc, Weak};
+
+/// Bounds closures the VM holds on to, such as hooks and output writers:
+/// `Send` under the `sync` feature and nothing otherwise.
+#[cfg(feature = "sync")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "sync")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// Bounds closures the VM holds on to, such as hooks and output writers:
+/// `Send` under the `sync` feature and nothing otherwise.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+#[cfg(feature = "sync")]
+mod locked {
+    use std::fmt;
+    use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+    /// A `RefCell` lookalike that can be shared between threads. Where a
+    /// `RefCell` panics on a conflicting borrow this blocks, which only the
+    /// same misuse could cause.
+    #[derive(Default)]
+    pub struct RefCell<T: ?Sized>(RwLock<T>);
+
+    /// The value was already borrowed in a way that conflicts.
+    #[derive(Debug)]
+    pub struct BorrowError;
+
+    impl<T> RefCell<T> {
+        pub const fn new(value: T) -> Self {
+            RefCell(RwLock::new(value))
+        }
+
+        pub fn into_inner(self) -> T {
+            self.0.into_inner().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    impl<T: ?Sized> RefCell<T> {
+        pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+            self.0.read().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+            self.0.write().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub fn try_borrow(&self) -> Result<RwLockReadGuard<'_, T>, BorrowError> {
+            match self.0.try_read() {
+                Ok(value) => Ok(value),
+                Err(TryLockError::Poisoned(poisoned)) => Ok(poisoned.into_inner()),
+                Err(TryLockError::WouldBlock) => Err(BorrowError),
+            }
+        }
+
+        pub fn try_borrow_mut(&self) -> Result<RwLockWriteGuard<'_, T>, BorrowError> {
+            match self.0.try_write() {
+                Ok(value) => Ok(value),
+                Err(TryLockError::Poisoned(poisoned)) => Ok(poisoned.into_inner()),
+                Err(TryLockError::WouldBlock) => Err(BorrowError),
+            }
+        }
+    }
+
+    impl<T: PartialEq> PartialEq for RefCell<T> {
+        fn eq(&self, other: &Self) -> bool {
+            *self.borrow() == *other.borrow()
+        }
+    }
+
+    impl<T: fmt::Debug> fmt::Debug for RefCell<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RefCell")
+                .field("value", &*self.borrow())
+                .finish()
+        }
+    }
+}
@@ -3,13 +3,26 @@ use logos::Logos;
 /// Defines the set of recognizable tokens in the oxython language.
 /// The `#[derive(Logos)]` macro from the `logos` crate generates the lexer implementation.
 #[derive(Logos, Debug, Clone, PartialEq, Default)]
-#[logos(skip r"[ \t\n\f]+")] // Ignore whitespace
+#[logos(skip r"[ \t\r\n\f]+")] // Ignore whitespace
+#[logos(skip r"#[^\r\n]*")] // Ignore comments
+#[logos(skip r"\\\r?\n")] // Ignore backslash line continuations
 pub enum Token {
-    // Literals
-    #[regex("[0-9]+\\.[0-9]+", |lex| lex.slice().parse::<f64>().ok())]
+    // Literals. Digits may be grouped with single underscores, as in `1_000`.
+    #[regex(
+        r"[0-9](_?[0-9])*\.([0-9](_?[0-9])*)?([eE][+-]?[0-9](_?[0-9])*)?",
+        parse_float
+    )]
+    #[regex(r"\.[0-9](_?[0-9])*([eE][+-]?[0-9](_?[0-9])*)?", parse_float)]
+    #[regex(r"[0-9](_?[0-9])*[eE][+-]?[0-9](_?[0-9])*", parse_float)]
     Float(f64),
 
-    #[regex("[0-9]+", |lex| lex.slice().parse::<i64>().ok())]
+    #[regex(r"[0-9](_?[0-9])*", |lex| parse_integer(lex.slice(), 10))]
+    #[regex(r"0[xX](_?[0-9a-fA-F])+", |lex| parse_integer(lex.slice(), 16))]
+    #[regex(r"0[oO](_?[0-7])+", |lex| parse_integer(lex.slice(), 8))]
+    #[regex(r"0[bB](_?[01])+", |lex| parse_integer(lex.slice(), 2))]
+    // A number running into letters or stray underscores, such as `0b102` or
+    // `1__0`, is one malformed literal rather than a number and a name
+    #[regex(r"[0-9][0-9a-zA-Z_]*", |_| None::<i64>, priority = 1)]
     Integer(i64),
 
     // Handles both single and double-quoted strings.
@@ -49,9 +62,36 @@ pub enum Token {
     #[token("break")]
     Break,
 
+    #[token("continue")]
+    Continue,
+
+    #[token("try")]
+    Try,
+
+    #[token("except")]
+    Except,
+
+    #[token("finally")]
+    Finally,
+
+    #[token("raise")]
+    Raise,
+
     #[token("nonlocal")]
     Nonlocal,
 
+    #[token("del")]
+    Del,
+
+    #[token("import")]
+    Import,
+
+    #[token("from")]
+    From,
+
+    #[token("as")]
+    As,
+
     #[token("True")]
     True,
 
@@ -61,6 +101,12 @@ pub enum Token {
     #[token("in")]
     In,
 
+    #[token("not")]
+    Not,
+
+    #[token("is")]
+    Is,
+
     #[token("==")]
     EqualEqual,
 
@@ -89,12 +135,24 @@ pub enum Token {
     #[token("/")]
     Slash,
 
+    #[token("//")]
+    SlashSlash,
+
     #[token("%")]
     Percent,
 
     #[token("<")]
     Less,
 
+    #[token("<=")]
+    LessEqual,
+
+    #[token(">")]
+    Greater,
+
+    #[token(">=")]
+    GreaterEqual,
+
     // Punctuation
     #[token(";")]
     Semicolon,
@@ -129,6 +187,29 @@ pub enum Token {
     #[token("->")]
     Arrow,
 
+    #[token("@")]
+    At,
+
     #[default]
     Unknown,
 }
+
+fn parse_float(lex: &mut logos::Lexer<Token>) -> Option<f64> {
+    lex.slice().replace('_', "").parse().ok()
+}
+
+/// Parses an integer literal in the given radix, skipping its `0x`-style
+/// prefix and underscores. Fails on overflow and, as in Python, on decimal
+/// literals with leading zeros such as `007`.
+fn parse_integer(text: &str, radix: u32) -> Option<i64> {
+    let digits = text.replace('_', "");
+    let digits = if radix == 10 {
+        &digits[..]
+    } else {
+        &digits[2..]
+    };
+    if radix == 10 && digits.starts_with('0') && digits.bytes().any(|b| b != b'0') {
+        return None;
+    }
+    i64::from_str_radix(digits, radix).ok()
+}
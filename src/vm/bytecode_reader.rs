@@ -4,31 +4,187 @@
 //! and operands from the current execution frame.
 
 use super::VM;
-use crate::bytecode::Chunk;
+use crate::bytecode::{Chunk, OpCode, JUMP_WIDTH};
+use crate::object::{Object, ObjectType};
 
 impl VM {
     /// Get a reference to the current call frame's bytecode chunk.
+    #[inline(always)]
     pub(super) fn current_chunk(&self) -> &Chunk {
         &self
             .frames
             .last()
             .expect("expected active call frame")
-            .function
             .chunk
     }
 
     /// Read a single byte from the current instruction pointer and advance it.
+    ///
+    /// Reading past the end of the code yields `OpReturn`, so a chunk that
+    /// does not end in one returns instead of running off its end.
+    #[inline(always)]
     pub(super) fn read_byte(&mut self) -> u8 {
         let frame = self.frames.last_mut().expect("expected active call frame");
-        let byte = frame.function.chunk.code[frame.ip];
+        let byte = frame
+            .chunk
+            .code
+            .get(frame.ip)
+            .copied()
+            .unwrap_or(OpCode::OpReturn as u8);
         frame.ip += 1;
         byte
     }
 
-    /// Read a 16-bit unsigned integer (big-endian) from the instruction stream.
-    pub(super) fn read_u16(&mut self) -> usize {
-        let high = self.read_byte() as usize;
-        let low = self.read_byte() as usize;
-        (high << 8) | low
+    /// Runs the `OpPop` that `if` and `while` put on both paths of their
+    /// conditional jump as part of the jump, saving it a dispatch of its own.
+    /// Hooked runs step through it as usual, so hooks still see every
+    /// instruction.
+    #[inline(always)]
+    pub(super) fn fold_condition_pop(&mut self) {
+        if self.instrumented {
+            return;
+        }
+        let frame = self.frames.last_mut().expect("expected active call frame");
+        if frame.chunk.code.get(frame.ip) == Some(&(OpCode::OpPop as u8)) {
+            frame.ip += 1;
+            let value = self.pop();
+            self.stack.set_last_popped(value);
+        }
+    }
+
+    /// Pushes the outcome of a comparison, unless an `if` or `while` tests it
+    /// straight away: their conditional jump is then taken here, with the
+    /// `OpPop` behind it, and the outcome never goes through the stack.
+    #[inline(always)]
+    pub(super) fn push_condition(&mut self, result: bool) {
+        if !self.instrumented {
+            let frame = self.frames.last_mut().expect("expected active call frame");
+            if frame.chunk.code.get(frame.ip) == Some(&(OpCode::OpJumpIfFalse as u8)) {
+                frame.ip += 1;
+                let offset = self.read_jump();
+                let frame = self.frames.last_mut().expect("expected active call frame");
+                if !result {
+                    frame.ip += offset;
+                }
+                if frame.chunk.code.get(frame.ip) == Some(&(OpCode::OpPop as u8)) {
+                    frame.ip += 1;
+                    let result = self.shared.boolean(result);
+                    self.stack.set_last_popped(result);
+                    return;
+                }
+            }
+        }
+        let result = self.shared.boolean(result);
+        self.push(result);
+    }
+
+    /// Pushes constant `index` of the current chunk, returning false if
+    /// there is none. An integer that an arithmetic or comparison instruction
+    /// takes next with another integer beneath it, as in `n - 1` or `n < 2`,
+    /// is not pushed: the instruction is run here instead, saving it a
+    /// dispatch and the constant a trip through the stack.
+    #[inline(always)]
+    pub(super) fn push_constant(&mut self, index: usize) -> bool {
+        let integer = match self.current_chunk().constants.get(index).map(|c| &**c) {
+            None => return false,
+            Some(ObjectType::Integer(value)) => Some(*value),
+            Some(_) => None,
+        };
+        if let (false, Some(b)) = (self.instrumented, integer) {
+            if self.fold_integer_operand(b) {
+                return true;
+            }
+        }
+        let constant = self.current_chunk().constants[index].clone();
+        self.push(constant);
+        true
+    }
+
+    /// Runs the arithmetic or comparison that comes next on the integer on
+    /// top of the stack and `b`, reporting whether there was one to run.
+    #[inline(always)]
+    fn fold_integer_operand(&mut self, b: i64) -> bool {
+        let (1.., ObjectType::Integer(a)) = (self.stack.top(), &**self.stack.peek(0)) else {
+            return false;
+        };
+        let a = *a;
+        let frame = self.frames.last_mut().expect("expected active call frame");
+        let next = frame.chunk.code.get(frame.ip).copied();
+        match next.and_then(OpCode::from_byte) {
+            Some(operation @ (OpCode::OpAdd | OpCode::OpSubtract)) => {
+                let result = match operation {
+                    OpCode::OpAdd => a.checked_add(b),
+                    _ => a.checked_sub(b),
+                };
+                let Some(result) = result else {
+                    return false;
+                };
+                frame.ip += 1;
+                let result = self.shared.integer(result);
+                self.pop();
+                self.push(result);
+            }
+            Some(
+                comparison @ (OpCode::OpLess
+                | OpCode::OpLessEqual
+                | OpCode::OpGreater
+                | OpCode::OpGreaterEqual
+                | OpCode::OpEqual),
+            ) => {
+                frame.ip += 1;
+                self.pop();
+                self.push_condition(match comparison {
+                    OpCode::OpLess => a < b,
+                    OpCode::OpLessEqual => a <= b,
+                    OpCode::OpGreater => a > b,
+                    OpCode::OpGreaterEqual => a >= b,
+                    _ => a == b,
+                });
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Runs an `OpConstant` that comes next as part of the instruction before
+    /// it: a local is mostly followed by one, as in `n - 1` or `i < 10`, so
+    /// with what `push_constant` folds in, the three take a single dispatch.
+    #[inline(always)]
+    pub(super) fn run_next_constant(&mut self) {
+        if self.instrumented {
+            return;
+        }
+        let frame = self.frames.last_mut().expect("expected active call frame");
+        let chunk = &frame.chunk;
+        let index = match chunk.code.get(frame.ip..frame.ip + 2) {
+            Some(&[opcode, index]) if opcode == OpCode::OpConstant as u8 => index as usize,
+            _ => return,
+        };
+        if index < chunk.constants.len() {
+            frame.ip += 2;
+            self.push_constant(index);
+        }
+    }
+
+    /// Read the constant index operand of `opcode`, one byte or two for its
+    /// long form, and return that entry of the current chunk's constant
+    /// pool, or `None` if the index is out of range.
+    #[inline(always)]
+    pub(super) fn read_constant(&mut self, opcode: OpCode) -> Option<Object> {
+        let idx = self.read_slot(opcode);
+        self.current_chunk().constants.get(idx).cloned()
+    }
+
+    /// Read the slot operand of `opcode`, a local or upvalue instruction:
+    /// one byte, or two big-endian for its long form.
+    #[inline(always)]
+    pub(super) fn read_slot(&mut self, opcode: OpCode) -> usize {
+        (0..opcode.operand_width()).fold(0, |slot, _| slot << 8 | self.read_byte() as usize)
+    }
+
+    /// Read a jump's distance: a 32-bit unsigned integer (big-endian).
+    #[inline(always)]
+    pub(super) fn read_jump(&mut self) -> usize {
+        (0..JUMP_WIDTH).fold(0, |distance, _| distance << 8 | self.read_byte() as usize)
     }
 }
@@ -1,29 +1,39 @@
-use crate::object::{ClassObject, FunctionObject};
-use std::rc::Rc;
+use crate::bytecode::Chunk;
+use crate::object::{ClassObject, FunctionObject, IteratorRef};
+use crate::sync::Rc;
 
+/// The default recursion limit: the most frames active at once, counting the
+/// script itself.
 pub const FRAMES_MAX: usize = 64;
 
+/// The highest recursion limit `sys.setrecursionlimit` accepts.
+pub const MAX_RECURSION_LIMIT: usize = 4096;
+
 pub struct CallFrame {
     pub function: Rc<FunctionObject>,
+    pub chunk: Rc<Chunk>, // Cached from `function` so instruction fetches skip one indirection
     pub ip: usize,
     pub slot: usize,
-    pub instance_slot: Option<usize>, // For __init__ calls, where to find the instance to return
+    pub is_initializer: bool, // __init__ frames return the instance in their callee slot
     pub class_context: Option<Rc<ClassObject>>, // For tracking which class a method belongs to (for super())
+    pub generator: Option<IteratorRef>, // The generator whose `<genexpr>` loop this frame runs
 }
 
 impl CallFrame {
     pub fn new(
         function: Rc<FunctionObject>,
         slot: usize,
-        instance_slot: Option<usize>,
+        is_initializer: bool,
         class_context: Option<Rc<ClassObject>>,
     ) -> Self {
         Self {
+            chunk: function.chunk.clone(),
             function,
             ip: 0,
             slot,
-            instance_slot,
+            is_initializer,
             class_context,
+            generator: None,
         }
     }
 }
@@ -0,0 +1,33 @@
+//! Native `list` and `dict` methods found by attribute access, alongside the
+//! ones the compiler turns into opcodes (`append`, `sort`).
+//!
+//! `op_get_attr` binds these to the receiving collection, so each native gets
+//! the collection as its first argument followed by the call's own arguments.
+
+use crate::object::{ClassObject, NativeError, NativeFn, NativeResult, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::modules::copy::shallow_copy;
+
+/// Names of the list and dict methods `collection_method` provides.
+pub const COLLECTION_METHODS: [&str; 1] = ["copy"];
+
+/// Returns the native implementing the list or dict method `name`, if there
+/// is one.
+pub fn collection_method(name: &str) -> Option<Object> {
+    let func: NativeFn = match name {
+        "copy" => collection_copy,
+        _ => return None,
+    };
+    Some(Rc::new(ObjectType::NativeFunction(name.to_string(), func)))
+}
+
+/// `list.copy()` and `dict.copy()`: a shallow copy.
+fn collection_copy(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    match args {
+        [collection] => shallow_copy(collection),
+        _ => Err(NativeError::type_error(format!(
+            "copy() takes no arguments ({} given)",
+            args.len().saturating_sub(1)
+        ))),
+    }
+}
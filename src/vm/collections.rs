@@ -1,20 +1,111 @@
 use crate::object::{Object, ObjectType};
-use std::rc::Rc;
+use crate::sync::Rc;
+use crate::vm::opcodes::comparison::{compare, comparison_error};
+use std::cmp::Ordering;
 
-/// Collects elements from an iterable object into a Vec.
+/// Collects elements from an iterable object into a Vec. A dict yields its
+/// keys in insertion order.
 pub fn collect_iterable(value: &Object) -> Option<Vec<Object>> {
     match &**value {
         ObjectType::List(elements) => Some(elements.clone()),
         ObjectType::Tuple(elements) => Some(elements.clone()),
+        ObjectType::Set(elements) => Some(elements.clone()),
+        ObjectType::Dict(entries) => Some(entries.iter().map(|(key, _)| key.clone()).collect()),
         ObjectType::String(text) => Some(
             text.chars()
                 .map(|ch| Rc::new(ObjectType::String(ch.to_string())))
                 .collect(),
         ),
+        ObjectType::Iterator(iterator) => {
+            let mut iterator = iterator.borrow_mut();
+            Some(std::iter::from_fn(|| iterator.next_item()).collect())
+        }
         _ => None,
     }
 }
 
+/// The TypeError message for iterating over `value`, which is not iterable.
+pub fn not_iterable_message(value: &ObjectType) -> String {
+    format!("'{}' object is not iterable", value.type_name())
+}
+
+/// Stable-sorts `values` by the parallel `keys`, as `sorted()` and
+/// `list.sort()` do.
+///
+/// `reverse` flips the comparison rather than the result, so equal keys keep
+/// their original order either way. Keys are compared with `<` only, and an
+/// unorderable pair makes the whole sort fail with its TypeError message.
+pub fn sort_by_keys(
+    values: Vec<Object>,
+    keys: Vec<Object>,
+    reverse: bool,
+) -> Result<Vec<Object>, String> {
+    let mut error = None;
+    let less = |a: &Object, b: &Object, error: &mut Option<String>| match compare(a, b) {
+        Ok(ordering) => ordering == Some(Ordering::Less),
+        Err(_) => {
+            error.get_or_insert_with(|| comparison_error("<", a, b));
+            false
+        }
+    };
+
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&i, &j| {
+        let (a, b) = if reverse {
+            (&keys[j], &keys[i])
+        } else {
+            (&keys[i], &keys[j])
+        };
+        if less(a, b, &mut error) {
+            Ordering::Less
+        } else if less(b, a, &mut error) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    });
+
+    match error {
+        Some(message) => Err(message),
+        None => Ok(order
+            .into_iter()
+            .map(|index| values[index].clone())
+            .collect()),
+    }
+}
+
+/// The first of `values` whose parallel key is the least, or with `largest`
+/// the greatest, as `min()` and `max()` pick it; None when there are none.
+///
+/// Keys are compared as CPython compares them, with `<` for the least and
+/// `>` for the greatest, and an unorderable pair fails with its TypeError
+/// message.
+pub fn extremum(
+    values: Vec<Object>,
+    keys: Vec<Object>,
+    largest: bool,
+) -> Result<Option<Object>, String> {
+    let (wanted, operator) = if largest {
+        (Ordering::Greater, ">")
+    } else {
+        (Ordering::Less, "<")
+    };
+    let mut best: Option<usize> = None;
+    for index in 0..values.len() {
+        let better = match best {
+            None => true,
+            Some(best) => match compare(&keys[index], &keys[best]) {
+                Ok(ordering) => ordering == Some(wanted),
+                Err(_) => return Err(comparison_error(operator, &keys[index], &keys[best])),
+            },
+        };
+        if better {
+            best = Some(index);
+        }
+    }
+    Ok(best.map(|index| values[index].clone()))
+}
+
 /// Computes the indices for a slice operation with support for step values.
 pub fn slice_indices(
     len: usize,
@@ -60,6 +151,16 @@ pub fn slice_indices(
     Some(indices)
 }
 
+/// Computes the range a slice with a step of 1 covers. An end before the
+/// start gives the empty range at the start, which is where assigning to the
+/// slice inserts.
+pub fn slice_range(len: usize, start: Option<i64>, end: Option<i64>) -> (usize, usize) {
+    let len_isize = len as isize;
+    let start_idx = adjust_index(start, len_isize, false, true) as usize;
+    let end_idx = adjust_index(end, len_isize, true, true) as usize;
+    (start_idx, end_idx.max(start_idx))
+}
+
 /// Adjusts a slice index according to Python's slicing rules.
 fn adjust_index(index: Option<i64>, len: isize, is_end: bool, step_positive: bool) -> isize {
     let len_i64 = len as i64;
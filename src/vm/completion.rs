@@ -0,0 +1,110 @@
+//! Completions for the REPL and editors: the names that could finish the
+//! identifier or attribute at the end of a line.
+//!
+//! A bare name is completed from the globals, the builtins and the keywords.
+//! After a dot, the dotted path before it is looked up the way `a.b` would
+//! be, without running any code, and the last part is completed from the
+//! attributes `dir()` lists for that value: an instance's fields and its
+//! class's methods, a module's members and so on. Names starting with an
+//! underscore are only offered once the typed part starts with one too.
+
+use crate::object::Object;
+use crate::vm::opcodes::attributes::{attribute_names, op_get_attr};
+use crate::vm::VM;
+use std::collections::BTreeSet;
+
+/// Words the compiler reads as keywords rather than names.
+const KEYWORDS: [&str; 24] = [
+    "False", "None", "True", "as", "break", "class", "continue", "def", "del", "else", "except",
+    "finally", "for", "from", "if", "import", "in", "is", "nonlocal", "not", "print", "raise",
+    "return", "try",
+];
+
+/// The names that could finish a line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completions {
+    /// Byte offset in the line where the name being completed starts; a
+    /// candidate replaces the text from there to the end of the line
+    pub start: usize,
+    /// The names starting with what has been typed, sorted
+    pub candidates: Vec<String>,
+}
+
+impl Completions {
+    /// The longest text every candidate starts with, which can be filled in
+    /// even while several candidates remain.
+    pub fn common_prefix(&self) -> &str {
+        let Some((first, rest)) = self.candidates.split_first() else {
+            return "";
+        };
+        let mut prefix = first.as_str();
+        for candidate in rest {
+            let shared = prefix
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(prefix.len().min(candidate.len()), |((index, _), _)| index);
+            prefix = &prefix[..shared];
+        }
+        prefix
+    }
+}
+
+impl VM {
+    /// Completes the identifier or dotted attribute path at the end of
+    /// `line` against the VM's current globals.
+    pub fn complete(&self, line: &str) -> Completions {
+        let path_start = line
+            .char_indices()
+            .rev()
+            .take_while(|&(_, ch)| ch.is_alphanumeric() || ch == '_' || ch == '.')
+            .last()
+            .map_or(line.len(), |(index, _)| index);
+        let path = &line[path_start..];
+        let (names, prefix) = match path.rsplit_once('.') {
+            Some((base, prefix)) => match self.resolve_path(base) {
+                Some(value) => (attribute_names(&value), prefix),
+                None => (Vec::new(), prefix),
+            },
+            None => {
+                let mut names: BTreeSet<String> = self.globals.keys().cloned().collect();
+                names.extend(self.builtins.keys().cloned());
+                names.extend(KEYWORDS.iter().map(|keyword| keyword.to_string()));
+                (names.into_iter().collect(), path)
+            }
+        };
+        let mut candidates: Vec<String> = names
+            .into_iter()
+            .filter(|name| name.starts_with(prefix))
+            .filter(|name| prefix.starts_with('_') || !name.starts_with('_'))
+            .collect();
+        candidates.sort();
+        Completions {
+            start: line.len() - prefix.len(),
+            candidates,
+        }
+    }
+
+    /// The value a dotted path such as `point.x` names, looking the first
+    /// part up in the globals and builtins and the rest as attributes.
+    fn resolve_path(&self, path: &str) -> Option<Object> {
+        let mut parts = path.split('.');
+        let first = parts.next().filter(|name| is_identifier(name))?;
+        let mut value = self
+            .globals
+            .get(first)
+            .or_else(|| self.builtins.get(first))?
+            .clone();
+        for part in parts {
+            if !is_identifier(part) {
+                return None;
+            }
+            value = op_get_attr(value, part, &self.stack).ok()?;
+        }
+        Some(value)
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars().next().is_some_and(|ch| !ch.is_ascii_digit())
+}
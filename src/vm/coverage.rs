@@ -0,0 +1,76 @@
+//! Line coverage of a script, reported in the lcov format.
+//!
+//! The lines a script can execute are the lines in the line tables of its
+//! chunk and of every function compiled into it. `Coverage::attach` collects
+//! them and installs an `on_line` hook counting how often each one is reached.
+
+use super::VM;
+use crate::bytecode::Chunk;
+use crate::object::ObjectType;
+use crate::sync::Rc;
+use crate::sync::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Execution counts for the lines of one script.
+#[derive(Debug)]
+pub struct Coverage {
+    /// Every executable line, with how often it has been reached
+    hits: Rc<RefCell<BTreeMap<usize, u64>>>,
+}
+
+impl Coverage {
+    /// Starts counting the lines of `chunk` that `vm` executes, replacing any
+    /// line hook it had.
+    pub fn attach(vm: &mut VM, chunk: &Chunk) -> Self {
+        let mut lines = BTreeMap::new();
+        executable_lines(chunk, &mut lines);
+        let hits = Rc::new(RefCell::new(lines));
+        let counts = hits.clone();
+        vm.on_line(move |frame| {
+            let Some(line) = frame.line else {
+                return;
+            };
+            if let Some(count) = counts.borrow_mut().get_mut(&line) {
+                *count += 1;
+            }
+        });
+        Coverage { hits }
+    }
+
+    /// Each executable line with how often it was reached, in line order.
+    pub fn lines(&self) -> Vec<(usize, u64)> {
+        self.hits
+            .borrow()
+            .iter()
+            .map(|(line, count)| (*line, *count))
+            .collect()
+    }
+
+    /// An lcov tracefile for the script at `source_path`.
+    pub fn lcov(&self, source_path: &str) -> String {
+        let lines = self.lines();
+        let mut report = format!("TN:\nSF:{}\n", source_path);
+        for (line, count) in &lines {
+            let _ = writeln!(report, "DA:{},{}", line, count);
+        }
+        let hit = lines.iter().filter(|(_, count)| *count > 0).count();
+        let _ = write!(report, "LF:{}\nLH:{}\nend_of_record\n", lines.len(), hit);
+        report
+    }
+}
+
+/// Adds the lines in the line tables of `chunk` and the functions compiled
+/// into it to `lines`, each with a count of zero.
+fn executable_lines(chunk: &Chunk, lines: &mut BTreeMap<usize, u64>) {
+    for (_, line) in &chunk.lines {
+        lines.insert(*line, 0);
+    }
+    for constant in &chunk.constants {
+        match &**constant {
+            ObjectType::FunctionPrototype(prototype) => executable_lines(&prototype.chunk, lines),
+            ObjectType::CodeObject(chunk) => executable_lines(chunk, lines),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! The `eval()` and `exec()` builtins.
+//!
+//! Both compile source text when they are called and run it as the body of
+//! a module named `<string>`: `eval` a single expression, returning its
+//! value, and `exec` any statements, returning None. Running bytecode needs
+//! the VM, so both are natives that run on it.
+//!
+//! The code reads and binds the VM's globals. Passing a `globals` dict, and
+//! a `locals` dict laid over it, runs it against those names instead; a
+//! `globals` of None stands for the running module's globals. Called by name
+//! inside a function, with the source alone, it is passed the function's
+//! locals that way, so it sees them but binds no names of the caller's.
+//!
+//! Dicts are values, so the names the code binds in one passed to it would
+//! go no further than the call. The VM keeps the last of the dicts with
+//! those names bound, and the compiler, for a call naming `eval` or `exec`
+//! whose last argument is a variable, stores it back there with
+//! `OpEvaluatedNamespace`.
+
+use crate::bytecode::Chunk;
+use crate::compiler::Compiler;
+use crate::object::{FunctionObject, NativeError, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::namespace::Namespace;
+use crate::vm::{InterpretResult, VM};
+
+/// Module name the compiled code runs under, as tracebacks show it.
+const MODULE: &str = "<string>";
+
+/// `eval(source[, globals[, locals]])`.
+pub(in crate::vm) fn eval(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("eval", keywords)?;
+    vm.evaluate("eval", args)
+}
+
+/// `exec(source[, globals[, locals]])`.
+pub(in crate::vm) fn exec(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("exec", keywords)?;
+    vm.evaluate("exec", args)?;
+    Ok(Rc::new(ObjectType::Nil))
+}
+
+impl VM {
+    /// Compiles and runs the source `args` passes to `name`, `eval` or
+    /// `exec`, returning the value it leaves.
+    fn evaluate(&mut self, name: &str, args: &[Object]) -> Result<Object, InterpretResult> {
+        self.evaluated_namespace = None;
+        let compiled = compile_call(name, args)
+            .and_then(|(chunk, dicts)| Ok((chunk, dicts, self.evaluation_namespace(dicts)?)));
+        let (chunk, dicts, namespace) = match compiled {
+            Ok(compiled) => compiled,
+            Err(error) => return Err(self.raise_error(error.kind, error.message)),
+        };
+        let before = namespace.clone();
+
+        let function = Rc::new(FunctionObject::new(
+            "<script>".to_string(),
+            0,
+            chunk,
+            Vec::new(),
+            MODULE.to_string(),
+        ));
+        // The code runs as a module of its own that shares the caller's
+        // globals, so they are swapped back in after it calls into another
+        let caller = std::mem::replace(&mut self.active_module, MODULE.to_string());
+        let saved = namespace.map(|namespace| std::mem::replace(&mut self.globals, namespace));
+        let result = self.call_callback(Rc::new(ObjectType::Function(function)), Vec::new());
+        self.switch_globals(MODULE);
+        if let Some(saved) = saved {
+            let after = std::mem::replace(&mut self.globals, saved);
+            if result.is_ok() {
+                self.evaluated_namespace =
+                    before.and_then(|before| bound_names(dicts, &before, after));
+            }
+        }
+        self.active_module = caller;
+        result
+    }
+
+    /// Builds the namespace the code runs against from the `globals` and
+    /// `locals` dicts passed, or returns None if there are none. A `globals`
+    /// of None starts from the running module's globals.
+    fn evaluation_namespace(&mut self, dicts: &[Object]) -> Result<Option<Namespace>, NativeError> {
+        let Some(globals) = dicts.first() else {
+            return Ok(None);
+        };
+        let mut namespace = if matches!(**globals, ObjectType::Nil) {
+            self.sync_globals();
+            self.globals.clone()
+        } else {
+            Namespace::default()
+        };
+        for (dict, role) in dicts.iter().zip(["globals", "locals"]) {
+            add_names(&mut namespace, dict, role)?;
+        }
+        Ok(Some(namespace))
+    }
+
+    /// Pops a dict and pushes the one the last `eval()` or `exec()` left
+    /// with the names its code bound, or the dict popped if there is none.
+    pub(in crate::vm) fn op_evaluated_namespace(&mut self) {
+        let passed = self.pop();
+        let namespace = self.evaluated_namespace.take().unwrap_or(passed);
+        self.push(namespace);
+    }
+}
+
+/// Compiles the source passed to the builtin `name`, returning its chunk and
+/// the `globals` and `locals` dicts passed after it, if any.
+fn compile_call<'a>(name: &str, args: &'a [Object]) -> Result<(Chunk, &'a [Object]), NativeError> {
+    let (source, dicts) = match args {
+        [] => {
+            return Err(NativeError::type_error(format!(
+                "{} expected at least 1 argument, got 0",
+                name
+            )))
+        }
+        [source, dicts @ ..] if dicts.len() <= 2 => (source, dicts),
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "{} expected at most 3 arguments, got {}",
+                name,
+                args.len()
+            )))
+        }
+    };
+    let ObjectType::String(source) = &**source else {
+        return Err(NativeError::type_error(format!(
+            "{}() arg 1 must be a string, not '{}'",
+            name,
+            source.type_name()
+        )));
+    };
+
+    // Like CPython, leading spaces and tabs do not count as indentation
+    let source = source.trim_start_matches([' ', '\t']);
+    let compiled = if name == "eval" {
+        Compiler::compile_expression(source, MODULE)
+    } else {
+        Compiler::compile_with_errors(source, MODULE)
+    };
+    let chunk = compiled.map_err(|errors| {
+        let error = &errors[0];
+        NativeError::new(
+            error.kind,
+            format!("{} ({}, line {})", error.message, MODULE, error.line),
+        )
+    })?;
+    Ok((chunk, dicts))
+}
+
+/// The last of `dicts` with the names the code bound in it: its entries
+/// with their values `after` the run, less those the code deleted, then the
+/// names the code bound anew, in sorted order. Names it read from the other
+/// dicts but left alone stay out. None if the last is not a dict.
+fn bound_names(dicts: &[Object], before: &Namespace, mut after: Namespace) -> Option<Object> {
+    let ObjectType::Dict(entries) = &**dicts.last()? else {
+        return None;
+    };
+    let mut bound: Vec<(Object, Object)> = entries
+        .iter()
+        .filter_map(|(key, _)| match &**key {
+            ObjectType::String(name) => after.remove(name).map(|value| (key.clone(), value)),
+            _ => None,
+        })
+        .collect();
+    let mut added: Vec<(String, Object)> = after
+        .into_iter()
+        .filter(|(name, value)| before.get(name).is_none_or(|old| !Rc::ptr_eq(old, value)))
+        .collect();
+    added.sort_by(|(a, _), (b, _)| a.cmp(b));
+    bound.extend(
+        added
+            .into_iter()
+            .map(|(name, value)| (Rc::new(ObjectType::String(name)) as Object, value)),
+    );
+    Some(Rc::new(ObjectType::Dict(bound.into())))
+}
+
+/// Adds the names in `dict`, the `role` argument, to `namespace`, replacing
+/// those already there. None adds nothing, as when `locals` is left out.
+fn add_names(namespace: &mut Namespace, dict: &Object, role: &str) -> Result<(), NativeError> {
+    let entries = match &**dict {
+        ObjectType::Dict(entries) => entries,
+        ObjectType::Nil => return Ok(()),
+        other => {
+            return Err(NativeError::type_error(format!(
+                "{} must be a dict, not {}",
+                role,
+                other.type_name()
+            )))
+        }
+    };
+    for (key, value) in entries {
+        let ObjectType::String(key) = &**key else {
+            return Err(NativeError::type_error(format!(
+                "{} keys must be strings, not '{}'",
+                role,
+                key.type_name()
+            )));
+        };
+        namespace.insert(key.clone(), value.clone());
+    }
+    Ok(())
+}
@@ -0,0 +1,363 @@
+//! Exceptions: the builtin exception classes and the handlers that catch them.
+//!
+//! An exception is an instance of a class deriving from `BaseException`,
+//! whose `args` field holds the arguments it was created with. Errors the VM
+//! raises itself are recorded as a `RuntimeError` first and only become an
+//! exception object when a handler catches them.
+//!
+//! `try` statements install handlers with OpSetupExcept and OpSetupFinally.
+//! When an instruction fails, `catch` unwinds the frames and stack to the
+//! innermost handler and jumps there. An except handler makes the exception
+//! the one being handled, which OpExceptMatch, OpGetCaught and OpReraise
+//! read; a finally handler holds it until OpEndFinally raises it again. The
+//! compiler has already emitted the bookkeeping for break, continue and return
+//! leaving a handler, so normal control flow never consults these stacks.
+
+use super::modules::traceback;
+use super::runtime_error::RuntimeError;
+use super::{gc, upvalues, InterpretResult, VM};
+use crate::object::{ClassObject, InstanceObject, NativeResult, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::namespace::Namespace;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// The builtin exception classes, each listed after its parent.
+const EXCEPTION_CLASSES: [(&str, &str); 32] = [
+    ("Exception", "BaseException"),
+    ("ArithmeticError", "Exception"),
+    ("ZeroDivisionError", "ArithmeticError"),
+    ("OverflowError", "ArithmeticError"),
+    ("AssertionError", "Exception"),
+    ("AttributeError", "Exception"),
+    ("ImportError", "Exception"),
+    ("ModuleNotFoundError", "ImportError"),
+    ("LookupError", "Exception"),
+    ("IndexError", "LookupError"),
+    ("KeyError", "LookupError"),
+    ("MemoryError", "Exception"),
+    ("NameError", "Exception"),
+    ("UnboundLocalError", "NameError"),
+    ("RuntimeError", "Exception"),
+    ("NotImplementedError", "RuntimeError"),
+    ("RecursionError", "RuntimeError"),
+    ("StopIteration", "Exception"),
+    ("SyntaxError", "Exception"),
+    ("IndentationError", "SyntaxError"),
+    ("TabError", "IndentationError"),
+    ("SystemError", "Exception"),
+    ("TypeError", "Exception"),
+    ("ValueError", "Exception"),
+    ("Warning", "Exception"),
+    ("UserWarning", "Warning"),
+    ("DeprecationWarning", "Warning"),
+    ("PendingDeprecationWarning", "Warning"),
+    ("SyntaxWarning", "Warning"),
+    ("RuntimeWarning", "Warning"),
+    ("FutureWarning", "Warning"),
+    ("KeyboardInterrupt", "BaseException"),
+];
+
+thread_local! {
+    static CLASSES: RefCell<HashMap<String, Rc<ClassObject>>> = RefCell::new(builtin_classes());
+}
+
+fn builtin_classes() -> HashMap<String, Rc<ClassObject>> {
+    let mut classes = HashMap::new();
+    let init = Rc::new(ObjectType::NativeFunction(
+        "__init__".to_string(),
+        exception_init,
+    ));
+    let methods = HashMap::from([("__init__".to_string(), init)]);
+    let base = Rc::new(ClassObject::new("BaseException".to_string(), methods));
+    classes.insert(base.name.clone(), base);
+    for (name, parent) in EXCEPTION_CLASSES {
+        let parent = classes[parent].clone();
+        let class = ClassObject::new_with_parent(name.to_string(), HashMap::new(), parent);
+        classes.insert(name.to_string(), Rc::new(class));
+    }
+    classes
+}
+
+/// `BaseException.__init__(self, *args)`, so subclasses can pass their
+/// arguments on with `super().__init__(...)`.
+fn exception_init(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    if let Some((receiver, args)) = args.split_first() {
+        if let ObjectType::Instance(instance) = &**receiver {
+            let args = Rc::new(ObjectType::Tuple(args.to_vec()));
+            instance.borrow_mut().set_field("args".to_string(), args);
+        }
+    }
+    Ok(Rc::new(ObjectType::Nil))
+}
+
+/// Returns the builtin exception class called `name`. An error kind with no
+/// builtin class gets one deriving from `Exception`, so every error the VM
+/// raises can be caught.
+pub fn exception_class(name: &str) -> Rc<ClassObject> {
+    CLASSES.with(|classes| {
+        let mut classes = classes.borrow_mut();
+        if let Some(class) = classes.get(name) {
+            return class.clone();
+        }
+        let parent = classes["Exception"].clone();
+        let class = Rc::new(ClassObject::new_with_parent(
+            name.to_string(),
+            HashMap::new(),
+            parent,
+        ));
+        classes.insert(name.to_string(), class.clone());
+        class
+    })
+}
+
+/// Adds `BaseException` and the builtin exception classes to the builtins.
+pub fn register_exceptions(builtins: &mut Namespace) {
+    let names = std::iter::once("BaseException").chain(EXCEPTION_CLASSES.map(|(name, _)| name));
+    for name in names {
+        builtins.insert(
+            name.to_string(),
+            Rc::new(ObjectType::Class(exception_class(name))),
+        );
+    }
+}
+
+/// Whether `class` is `ancestor` or inherits from it.
+pub fn is_subclass(class: &ClassObject, ancestor: &ClassObject) -> bool {
+    let mut current = Some(class);
+    while let Some(class) = current {
+        if std::ptr::eq(class, ancestor) {
+            return true;
+        }
+        current = class.parent.as_deref();
+    }
+    false
+}
+
+/// Whether instances of `class` can be raised.
+pub fn is_exception_class(class: &ClassObject) -> bool {
+    is_subclass(class, &exception_class("BaseException"))
+}
+
+/// Whether an exception of class `class` is caught by an except clause naming
+/// `expected`: a class, or the list the compiler builds for `(A, B)`.
+/// Returns None if `expected` names anything that is not an exception class.
+pub fn matches(class: &ClassObject, expected: &Object) -> Option<bool> {
+    match &**expected {
+        ObjectType::Class(expected) if is_exception_class(expected) => {
+            Some(is_subclass(class, expected))
+        }
+        ObjectType::List(classes) => classes.iter().try_fold(false, |matched, expected| {
+            Some(matches(class, expected)? || matched)
+        }),
+        _ => None,
+    }
+}
+
+/// Creates an instance of the exception class `class` holding `args`.
+pub fn new_exception(class: Rc<ClassObject>, args: Vec<Object>) -> Object {
+    let mut instance = InstanceObject::new(class);
+    instance.set_field("args".to_string(), Rc::new(ObjectType::Tuple(args)));
+    Rc::new(ObjectType::Instance(gc::new_instance(instance)))
+}
+
+/// The text `str()` gives for an exception: its only argument, nothing for
+/// no arguments or the tuple of several. Returns None for other instances.
+pub fn exception_str(instance: &InstanceObject) -> Option<String> {
+    if !is_exception_class(&instance.class) {
+        return None;
+    }
+    Some(match instance.get_field("args").as_deref() {
+        Some(ObjectType::Tuple(args)) => match args.as_slice() {
+            [] => String::new(),
+            [arg] => match &**arg {
+                ObjectType::String(text) => text.clone(),
+                other => other.to_string(),
+            },
+            _ => ObjectType::Tuple(args.clone()).to_string(),
+        },
+        Some(other) => other.to_string(),
+        None => String::new(),
+    })
+}
+
+/// Returns the exception object `value` raises, or None if it cannot be
+/// raised. Raising a class raises a new instance of it with no arguments.
+pub fn raisable(value: &Object) -> Option<Object> {
+    match &**value {
+        ObjectType::Class(class) if is_exception_class(class) => {
+            Some(new_exception(class.clone(), Vec::new()))
+        }
+        ObjectType::Instance(instance) if is_exception_class(&instance.borrow().class) => {
+            Some(value.clone())
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum HandlerKind {
+    Except,
+    Finally,
+}
+
+/// A handler installed by OpSetupExcept or OpSetupFinally.
+#[derive(Debug)]
+pub(super) struct Handler {
+    kind: HandlerKind,
+    /// Frames active when it was installed; its code runs in the last one
+    frame_count: usize,
+    /// Stack height to restore before running it
+    stack_depth: usize,
+    /// Where its code starts in the chunk of its frame
+    target: usize,
+    /// Lengths of the caught and pending stacks to restore
+    caught_len: usize,
+    pending_len: usize,
+}
+
+/// An exception together with the error recorded when it was raised, so
+/// raising it again reports the original traceback.
+pub(super) type Raised = (Object, RuntimeError);
+
+impl VM {
+    /// Installs a handler of `kind` whose code starts `offset` bytes on.
+    pub(super) fn setup_handler(&mut self, kind: HandlerKind, offset: usize) {
+        let frame_count = self.frames.len();
+        let Some(frame) = self.frames.last() else {
+            return;
+        };
+        self.handlers.push(Handler {
+            kind,
+            frame_count,
+            stack_depth: self.stack.top(),
+            target: frame.ip + offset,
+            caught_len: self.caught.len(),
+            pending_len: self.pending.len(),
+        });
+    }
+
+    /// Whether the innermost handler was installed in the frame `frame_count`
+    /// frames deep or in one above it.
+    pub(super) fn has_handler_from(&self, frame_count: usize) -> bool {
+        self.handlers
+            .last()
+            .is_some_and(|handler| handler.frame_count >= frame_count)
+    }
+
+    /// Raises the exception object `exception`, recording it as the error.
+    pub(super) fn raise_exception(&mut self, exception: Object) -> InterpretResult {
+        let (kind, message) = match &*exception {
+            ObjectType::Instance(instance) => {
+                let instance = instance.borrow();
+                let message = exception_str(&instance).unwrap_or_default();
+                (instance.class.name.clone(), message)
+            }
+            other => ("TypeError".to_string(), other.type_name()),
+        };
+        let result = self.raise_error(&kind, message);
+        self.raised = Some(exception);
+        result
+    }
+
+    /// Raises an exception that was caught earlier, keeping its traceback.
+    pub(super) fn reraise(&mut self, (exception, error): Raised) -> InterpretResult {
+        self.last_error = Some(error);
+        self.raised = Some(exception);
+        InterpretResult::RuntimeError
+    }
+
+    /// The exception being handled by the innermost except clause.
+    pub(super) fn caught_exception(&self) -> Option<&Object> {
+        self.caught.last().map(|(exception, _)| exception)
+    }
+
+    /// Catches a StopIteration just raised by code that started with
+    /// `base_frames` frames and a stack `stack_depth` high, unwinding to that
+    /// point as a builtin driving an iterator does. Returns false, leaving the
+    /// error in place, for any other error.
+    pub(super) fn catch_stop_iteration(&mut self, base_frames: usize, stack_depth: usize) -> bool {
+        let class = match (&self.raised, &self.last_error) {
+            (Some(exception), _) => match &**exception {
+                ObjectType::Instance(instance) => instance.borrow().class.clone(),
+                _ => return false,
+            },
+            (None, Some(error)) => exception_class(&error.kind),
+            (None, None) => return false,
+        };
+        if !is_subclass(&class, &exception_class("StopIteration")) {
+            return false;
+        }
+        self.last_error = None;
+        self.raised = None;
+        while self
+            .handlers
+            .last()
+            .is_some_and(|handler| handler.frame_count > base_frames)
+        {
+            self.handlers.pop();
+        }
+        upvalues::close_upvalues(&mut self.open_upvalues, &self.stack, stack_depth);
+        self.frames.truncate(base_frames);
+        self.stack.set_top(stack_depth);
+        true
+    }
+
+    /// Hands the error just raised to the innermost handler installed by one
+    /// of the frames above the first `base_frames`, unwinding to it and
+    /// jumping to its code. Returns false, leaving the error in place, if
+    /// there is no such handler or the failure recorded no error.
+    pub(super) fn catch(&mut self, base_frames: usize) -> bool {
+        // A handler outlives its frame only in malformed bytecode
+        while self
+            .handlers
+            .last()
+            .is_some_and(|handler| handler.frame_count > self.frames.len())
+        {
+            self.handlers.pop();
+        }
+        match self.handlers.last() {
+            Some(handler) if handler.frame_count > base_frames => {}
+            _ => return false,
+        }
+        let Some(error) = self.last_error.take() else {
+            return false;
+        };
+        let Some(handler) = self.handlers.pop() else {
+            return false;
+        };
+        let exception = self.raised.take().unwrap_or_else(|| {
+            // An error raised without a message, like StopIteration, has no args
+            let args = match error.message.as_str() {
+                "" => Vec::new(),
+                message => vec![Rc::new(ObjectType::String(message.to_string()))],
+            };
+            new_exception(exception_class(&error.kind), args)
+        });
+        // Its traceback runs from the handler's frame down to where it was raised
+        if let ObjectType::Instance(instance) = &*exception {
+            let frames = error
+                .traceback
+                .get(handler.frame_count.saturating_sub(1)..)
+                .unwrap_or_default();
+            instance.borrow_mut().set_field(
+                "__traceback__".to_string(),
+                traceback::traceback_object(frames),
+            );
+        }
+
+        upvalues::close_upvalues(&mut self.open_upvalues, &self.stack, handler.stack_depth);
+        self.frames.truncate(handler.frame_count);
+        self.stack.set_top(handler.stack_depth);
+        self.caught.truncate(handler.caught_len);
+        self.pending.truncate(handler.pending_len);
+        match handler.kind {
+            HandlerKind::Except => self.caught.push((exception, error)),
+            HandlerKind::Finally => self.pending.push(Some((exception, error))),
+        }
+        if let Some(frame) = self.frames.last_mut() {
+            frame.ip = handler.target;
+        }
+        true
+    }
+}
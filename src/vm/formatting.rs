@@ -0,0 +1,804 @@
+//! The format mini-language behind f-strings, `str.format()` and `format()`.
+//!
+//! A replacement field converts its value with `!s`, `!r` or `!a` when asked
+//! to, then formats it by its spec,
+//! `[[fill]align][sign][#][0][width][grouping][.precision][type]`, which
+//! integers, floats and strings read as CPython's do. Other values take only
+//! an empty spec, formatting as `str()` does.
+//!
+//! Formatting an instance runs its `__str__` or `__repr__`, which needs the
+//! VM stack, so `format()`, `str()`, `repr()` and `str.format()` are natives
+//! that run on the VM.
+
+use crate::bytecode::FORMAT_CONVERSION;
+use crate::object::{escape, float_repr, quote, NativeError, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::call_frame::CallFrame;
+use crate::vm::native::{native_repr, native_str};
+use crate::vm::opcodes::attributes::{attr_error, op_get_attr};
+use crate::vm::opcodes::collections::{index_error, op_index};
+use crate::vm::stack_ops::Stack;
+use crate::vm::{string_repr, InterpretResult, VM};
+
+/// `format(value[, spec])`.
+pub(in crate::vm) fn format(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("format", keywords)?;
+    let (value, spec) = match args {
+        [value] => (value, ""),
+        [value, spec] => match &**spec {
+            ObjectType::String(spec) => (value, spec.as_str()),
+            other => {
+                let message = format!("format() argument 2 must be str, not {}", other.type_name());
+                return Err(vm.raise_error("TypeError", message));
+            }
+        },
+        _ => {
+            let message = format!("format expected at most 2 arguments, got {}", args.len());
+            return Err(vm.raise_error("TypeError", message));
+        }
+    };
+    vm.format_field(value.clone(), 0, spec).map(string)
+}
+
+/// `str()`, running an instance's `__str__` (or else `__repr__`).
+pub(in crate::vm) fn str(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("str", keywords)?;
+    match args {
+        [value] => vm.text(value, false),
+        _ => vm.native_result(native_str(args, None)),
+    }
+}
+
+/// `repr()`, running an instance's `__repr__`.
+pub(in crate::vm) fn repr(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("repr", keywords)?;
+    match args {
+        [value] => vm.text(value, true),
+        _ => vm.native_result(native_repr(args, None)),
+    }
+}
+
+/// `str.format()`: `args` starts with the template, and `keywords` fill the
+/// fields it names.
+pub(in crate::vm) fn str_format(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.format_template(args, keywords)
+}
+
+impl VM {
+    /// `str(value)`, or `repr(value)` when `repr` is set.
+    fn text(&mut self, value: &Object, repr: bool) -> Result<Object, InterpretResult> {
+        if let Some(text) = self.instance_text(value, repr) {
+            return text;
+        }
+        if let Some(text) = self.container_repr(value) {
+            return text.map(string);
+        }
+        let text = if repr {
+            repr_of(value.clone(), &mut self.stack, &mut self.frames)
+        } else {
+            str_of(value.clone(), &mut self.stack, &mut self.frames)
+        };
+        Ok(string(text))
+    }
+
+    /// Runs the `__str__` (or else `__repr__`) of an instance, or its
+    /// `__repr__` when `repr` is set, returning the string it gives. Returns
+    /// None unless `value` is an instance whose class defines the method.
+    pub(in crate::vm) fn instance_text(
+        &mut self,
+        value: &Object,
+        repr: bool,
+    ) -> Option<Result<Object, InterpretResult>> {
+        let ObjectType::Instance(instance) = &**value else {
+            return None;
+        };
+        let class = instance.borrow().class.clone();
+        let (method_name, method) = match class.get_method("__str__") {
+            Some(method) if !repr => ("__str__", method),
+            _ => ("__repr__", class.get_method("__repr__")?),
+        };
+        let bound = Rc::new(ObjectType::BoundMethod(value.clone(), method));
+        let text = match self.call_callback(bound, Vec::new()) {
+            Ok(text) => text,
+            Err(error) => return Some(Err(error)),
+        };
+        if !matches!(*text, ObjectType::String(_)) {
+            let message = format!(
+                "{}() returned non-string (type {})",
+                method_name,
+                text.type_name()
+            );
+            return Some(Err(self.raise_error("TypeError", message)));
+        }
+        Some(Ok(text))
+    }
+
+    /// Fills the template `args` starts with from the rest of `args` and
+    /// from `keywords`.
+    fn format_template(
+        &mut self,
+        args: &[Object],
+        keywords: &[(String, Object)],
+    ) -> Result<Object, InterpretResult> {
+        let Some(ObjectType::String(template)) = args.first().map(|arg| &**arg) else {
+            return Err(
+                self.raise_error("TypeError", "descriptor 'format' requires a 'str' object")
+            );
+        };
+        let mut formatter = TemplateFormatter {
+            args: &args[1..],
+            keywords,
+            next_index: Some(0),
+            vm: self,
+        };
+        formatter.format(template).map(string)
+    }
+
+    /// Formats `value` for a replacement field with the given `OpFormatValue`
+    /// conversion bits and format spec. An instance, or a container holding
+    /// one, shows the text its methods give.
+    pub(in crate::vm) fn format_field(
+        &mut self,
+        value: Object,
+        conversion: u8,
+        spec: &str,
+    ) -> Result<String, InterpretResult> {
+        let value = match conversion & FORMAT_CONVERSION {
+            0 => value,
+            1 => self.text(&value, false)?,
+            2 => self.text(&value, true)?,
+            _ => string(ascii(&self.text(&value, true)?.to_string())),
+        };
+        match &*value {
+            ObjectType::Instance(_)
+            | ObjectType::List(_)
+            | ObjectType::Tuple(_)
+            | ObjectType::Set(_)
+            | ObjectType::Dict(_)
+                if spec.is_empty() =>
+            {
+                Ok(self.text(&value, false)?.to_string())
+            }
+            _ => format_value(&value, spec)
+                .map_err(|error| self.raise_error(error.kind, error.message)),
+        }
+    }
+}
+
+fn string(text: String) -> Object {
+    Rc::new(ObjectType::String(text))
+}
+
+/// Formats a replacement field as `VM::format_field` does, for code that
+/// has only the stack and frames, such as a `__str__` run by `string_repr`'s
+/// own loop.
+pub fn format_field_on_stack(
+    value: Object,
+    conversion: u8,
+    spec: &str,
+    stack: &mut Stack,
+    frames: &mut Vec<CallFrame>,
+) -> Result<String, NativeError> {
+    let value = match conversion & FORMAT_CONVERSION {
+        0 => value,
+        1 => string(str_of(value, stack, frames)),
+        2 => string(repr_of(value, stack, frames)),
+        _ => string(ascii(&repr_of(value, stack, frames))),
+    };
+    match &*value {
+        ObjectType::Instance(_) if spec.is_empty() => Ok(str_of(value, stack, frames)),
+        _ => format_value(&value, spec),
+    }
+}
+
+/// The text `str()` gives for `value`.
+pub fn str_of(value: Object, stack: &mut Stack, frames: &mut Vec<CallFrame>) -> String {
+    match &*value {
+        ObjectType::String(text) => text.clone(),
+        ObjectType::Nil => "None".to_string(),
+        _ => string_repr::get_string_representation(value.clone(), stack, frames)
+            .unwrap_or_else(|| value.to_string()),
+    }
+}
+
+/// The text `repr()` gives for `value`.
+pub fn repr_of(value: Object, stack: &mut Stack, frames: &mut Vec<CallFrame>) -> String {
+    match &*value {
+        ObjectType::String(text) => quote(text),
+        ObjectType::Nil => "None".to_string(),
+        _ => string_repr::get_repr_representation(value.clone(), stack, frames)
+            .unwrap_or_else(|| value.to_string()),
+    }
+}
+
+/// `text` with every non-ASCII character escaped, as `ascii()` gives it.
+fn ascii(text: &str) -> String {
+    text.chars()
+        .map(|ch| {
+            if ch.is_ascii() {
+                ch.to_string()
+            } else {
+                escape(ch)
+            }
+        })
+        .collect()
+}
+
+/// Formats a value other than an instance by `spec`.
+pub fn format_value(value: &ObjectType, spec: &str) -> Result<String, NativeError> {
+    if spec.is_empty() {
+        return Ok(match value {
+            ObjectType::String(text) => text.clone(),
+            ObjectType::Nil => "None".to_string(),
+            _ => value.to_string(),
+        });
+    }
+    match value {
+        ObjectType::String(text) => FormatSpec::parse(spec, "str")?.format_str(text),
+        ObjectType::Integer(number) => FormatSpec::parse(spec, "int")?.format_int(*number, "int"),
+        ObjectType::Boolean(flag) => {
+            FormatSpec::parse(spec, "bool")?.format_int(*flag as i64, "bool")
+        }
+        ObjectType::Float(number) => {
+            FormatSpec::parse(spec, "float")?.format_float(*number, "float")
+        }
+        other => Err(NativeError::type_error(format!(
+            "unsupported format string passed to {}.__format__",
+            other.type_name()
+        ))),
+    }
+}
+
+/// A parsed format spec.
+#[derive(Debug, Default)]
+struct FormatSpec {
+    fill: Option<char>,
+    align: Option<char>,
+    sign: Option<char>,
+    alternate: bool,
+    zero_pad: bool,
+    width: usize,
+    grouping: Option<char>,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+impl FormatSpec {
+    /// Parses `spec` for a value of type `type_name`.
+    fn parse(spec: &str, type_name: &str) -> Result<Self, NativeError> {
+        let chars: Vec<char> = spec.chars().collect();
+        let is_align = |ch: &char| matches!(ch, '<' | '>' | '=' | '^');
+        let mut parsed = FormatSpec::default();
+        let mut index = 0;
+
+        if chars.get(1).is_some_and(is_align) {
+            parsed.fill = Some(chars[0]);
+            parsed.align = Some(chars[1]);
+            index = 2;
+        } else if chars.first().is_some_and(is_align) {
+            parsed.align = Some(chars[0]);
+            index = 1;
+        }
+        if let Some(&sign @ ('+' | '-' | ' ')) = chars.get(index) {
+            parsed.sign = Some(sign);
+            index += 1;
+        }
+        if chars.get(index) == Some(&'#') {
+            parsed.alternate = true;
+            index += 1;
+        }
+        if chars.get(index) == Some(&'0') {
+            parsed.zero_pad = true;
+            index += 1;
+        }
+        parsed.width = digits(&chars, &mut index)?.unwrap_or(0);
+        if let Some(&grouping @ (',' | '_')) = chars.get(index) {
+            parsed.grouping = Some(grouping);
+            index += 1;
+            if let Some(&other @ (',' | '_')) = chars.get(index) {
+                return Err(NativeError::value_error(if other == grouping {
+                    format!("Cannot specify '{}' with '{}'.", grouping, other)
+                } else {
+                    "Cannot specify both ',' and '_'.".to_string()
+                }));
+            }
+        }
+        if chars.get(index) == Some(&'.') {
+            index += 1;
+            parsed.precision =
+                Some(digits(&chars, &mut index)?.ok_or_else(|| {
+                    NativeError::value_error("Format specifier missing precision")
+                })?);
+        }
+        match &chars[index..] {
+            [] => {}
+            [kind] => parsed.kind = Some(*kind),
+            _ => {
+                return Err(NativeError::value_error(format!(
+                    "Invalid format specifier '{}' for object of type '{}'",
+                    spec, type_name
+                )))
+            }
+        }
+
+        if let Some(grouping) = parsed.grouping {
+            let allowed = match parsed.kind {
+                None | Some('d' | 'e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%') => true,
+                Some('b' | 'o' | 'x' | 'X') => grouping == '_',
+                Some(_) => false,
+            };
+            if !allowed {
+                return Err(NativeError::value_error(format!(
+                    "Cannot specify '{}' with '{}'.",
+                    grouping,
+                    parsed.kind.unwrap_or_default()
+                )));
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Formats a string: only a precision, truncating it, and padding apply.
+    fn format_str(&self, text: &str) -> Result<String, NativeError> {
+        if let Some(kind) = self.kind.filter(|kind| *kind != 's') {
+            return Err(unknown_code(kind, "str"));
+        }
+        if self.sign.is_some() {
+            return Err(NativeError::value_error(
+                "Sign not allowed in string format specifier",
+            ));
+        }
+        if self.alternate {
+            return Err(NativeError::value_error(
+                "Alternate form (#) not allowed in string format specifier",
+            ));
+        }
+        if self.align == Some('=') {
+            return Err(NativeError::value_error(
+                "'=' alignment not allowed in string format specifier",
+            ));
+        }
+        let text = match self.precision {
+            Some(precision) => text.chars().take(precision).collect(),
+            None => text.to_string(),
+        };
+        Ok(self.pad("", &text, false))
+    }
+
+    /// Formats an integer in the base its type picks, or as a float for the
+    /// float types.
+    fn format_int(&self, number: i64, type_name: &str) -> Result<String, NativeError> {
+        let kind = self.kind.unwrap_or('d');
+        if matches!(kind, 'e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%') {
+            return self.format_float(number as f64, type_name);
+        }
+        if self.precision.is_some() {
+            return Err(NativeError::value_error(
+                "Precision not allowed in integer format specifier",
+            ));
+        }
+
+        let magnitude = number.unsigned_abs();
+        let (prefix, digits, group_size) = match kind {
+            'd' | 'n' => ("", magnitude.to_string(), 3),
+            'b' => ("0b", format!("{:b}", magnitude), 4),
+            'o' => ("0o", format!("{:o}", magnitude), 4),
+            'x' => ("0x", format!("{:x}", magnitude), 4),
+            'X' => ("0X", format!("{:X}", magnitude), 4),
+            'c' => {
+                if self.sign.is_some() {
+                    return Err(NativeError::value_error(
+                        "Sign not allowed with integer format specifier 'c'",
+                    ));
+                }
+                if self.alternate {
+                    return Err(NativeError::value_error(
+                        "Alternate form (#) not allowed with integer format specifier 'c'",
+                    ));
+                }
+                let ch = u32::try_from(number)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        NativeError::new("OverflowError", "%c arg not in range(0x110000)")
+                    })?;
+                return Ok(self.pad("", &ch.to_string(), true));
+            }
+            other => return Err(unknown_code(other, type_name)),
+        };
+        let prefix = if self.alternate { prefix } else { "" };
+        let sign = format!("{}{}", self.sign_of(number < 0), prefix);
+        Ok(self.number(&sign, digits, "", group_size))
+    }
+
+    /// Formats a float in fixed-point, scientific or general notation.
+    fn format_float(&self, number: f64, type_name: &str) -> Result<String, NativeError> {
+        let kind = self.kind;
+        if let Some(kind) = kind.filter(|kind| !"eEfFgGn%".contains(*kind)) {
+            return Err(unknown_code(kind, type_name));
+        }
+        let sign = self.sign_of(number.is_sign_negative() && !number.is_nan());
+        let magnitude = number.abs();
+        let upper = matches!(kind, Some('E' | 'F' | 'G'));
+        let percent = if kind == Some('%') { "%" } else { "" };
+
+        if !magnitude.is_finite() {
+            let text = if number.is_nan() { "nan" } else { "inf" };
+            let text = if upper {
+                text.to_uppercase()
+            } else {
+                text.to_string()
+            };
+            return Ok(self.pad(&sign, &format!("{}{}", text, percent), true));
+        }
+
+        let body = match kind {
+            Some('f' | 'F') => format!("{:.*}", self.precision.unwrap_or(6), magnitude),
+            Some('%') => format!("{:.*}", self.precision.unwrap_or(6), magnitude * 100.0),
+            Some('e' | 'E') => scientific(magnitude, self.precision.unwrap_or(6)),
+            Some(_) => general(magnitude, self.precision.unwrap_or(6), self.alternate),
+            None => match self.precision {
+                Some(precision) => {
+                    let text = general(magnitude, precision, self.alternate);
+                    if text.contains(['.', 'e']) {
+                        text
+                    } else {
+                        format!("{}.0", text)
+                    }
+                }
+                None => float_repr(magnitude),
+            },
+        };
+        let mut body = if upper { body.to_uppercase() } else { body };
+        if self.alternate && !body.contains('.') {
+            // The alternate form always shows the decimal point
+            let point = body.find(['e', 'E']).unwrap_or(body.len());
+            body.insert(point, '.');
+        }
+        let split = body
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(body.len());
+        let (digits, rest) = body.split_at(split);
+        Ok(self.number(
+            &sign,
+            digits.to_string(),
+            &format!("{}{}", rest, percent),
+            3,
+        ))
+    }
+
+    /// The sign shown before a number.
+    fn sign_of(&self, negative: bool) -> String {
+        match (negative, self.sign) {
+            (true, _) => "-",
+            (false, Some('+')) => "+",
+            (false, Some(' ')) => " ",
+            _ => "",
+        }
+        .to_string()
+    }
+
+    /// Lays out a number: its sign and base prefix, its integer `digits`
+    /// grouped every `group_size` digits when asked to, and the `rest` of it.
+    /// Zero padding is grouped along with the digits, as in `0,001,234`.
+    fn number(&self, sign: &str, mut digits: String, rest: &str, group_size: usize) -> String {
+        let Some(separator) = self.grouping else {
+            return self.pad(sign, &format!("{}{}", digits, rest), true);
+        };
+        let zero_filled =
+            self.fill.unwrap_or('0') == '0' && self.zero_pad && self.align.unwrap_or('=') == '=';
+        let mut grouped = group(&digits, separator, group_size);
+        while zero_filled
+            && sign.chars().count() + grouped.chars().count() + rest.chars().count() < self.width
+        {
+            digits.insert(0, '0');
+            grouped = group(&digits, separator, group_size);
+        }
+        self.pad(sign, &format!("{}{}", grouped, rest), true)
+    }
+
+    /// Pads `prefix` and `body` to the spec's width. Numbers align right by
+    /// default and strings left; the zero flag pads numbers after their sign.
+    fn pad(&self, prefix: &str, body: &str, numeric: bool) -> String {
+        let fill = self.fill.unwrap_or(if self.zero_pad { '0' } else { ' ' });
+        let align = self.align.unwrap_or(match (numeric, self.zero_pad) {
+            (false, _) => '<',
+            (true, true) => '=',
+            (true, false) => '>',
+        });
+        let length = prefix.chars().count() + body.chars().count();
+        let padding = self.width.saturating_sub(length);
+        let fill = |count: usize| fill.to_string().repeat(count);
+        match align {
+            '<' => format!("{}{}{}", prefix, body, fill(padding)),
+            '^' => format!(
+                "{}{}{}{}",
+                fill(padding / 2),
+                prefix,
+                body,
+                fill(padding - padding / 2)
+            ),
+            '=' => format!("{}{}{}", prefix, fill(padding), body),
+            _ => format!("{}{}{}", fill(padding), prefix, body),
+        }
+    }
+}
+
+/// Reads a run of decimal digits at `index`, if there is one.
+fn digits(chars: &[char], index: &mut usize) -> Result<Option<usize>, NativeError> {
+    let start = *index;
+    while chars.get(*index).is_some_and(char::is_ascii_digit) {
+        *index += 1;
+    }
+    if *index == start {
+        return Ok(None);
+    }
+    let text: String = chars[start..*index].iter().collect();
+    text.parse()
+        .map(Some)
+        .map_err(|_| NativeError::value_error("Too many decimal digits in format string"))
+}
+
+fn unknown_code(kind: char, type_name: &str) -> NativeError {
+    NativeError::value_error(format!(
+        "Unknown format code '{}' for object of type '{}'",
+        kind, type_name
+    ))
+}
+
+/// `digits` with `separator` between every `size` digits from the right.
+fn group(digits: &str, separator: char, size: usize) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / size);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(size) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// `number` in scientific notation with `precision` fractional digits and
+/// an exponent of at least two digits, as in `1.500000e+03`.
+fn scientific(number: f64, precision: usize) -> String {
+    let text = format!("{:.*e}", precision, number);
+    let (mantissa, exponent) = text.split_once('e').expect("scientific notation");
+    let exponent: i32 = exponent.parse().expect("exponent is an integer");
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+}
+
+/// `number` to `precision` significant digits, in fixed-point notation
+/// unless its exponent is below -4 or not below the precision, with
+/// trailing zeros dropped unless `alternate`.
+fn general(number: f64, precision: usize, alternate: bool) -> String {
+    let precision = precision.max(1);
+    let rounded = format!("{:.*e}", precision - 1, number);
+    let exponent: i32 = rounded
+        .split_once('e')
+        .and_then(|(_, exponent)| exponent.parse().ok())
+        .unwrap_or(0);
+
+    let text = if (-4..precision as i32).contains(&exponent) {
+        let decimals = (precision as i32 - 1 - exponent) as usize;
+        format!("{:.*}", decimals, number)
+    } else {
+        scientific(number, precision - 1)
+    };
+    if alternate {
+        return text;
+    }
+    let (mantissa, exponent) = match text.find('e') {
+        Some(at) => text.split_at(at),
+        None => (text.as_str(), ""),
+    };
+    let mantissa = if mantissa.contains('.') {
+        mantissa.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mantissa
+    };
+    format!("{}{}", mantissa, exponent)
+}
+
+/// Formats a `str.format()` template, numbering fields without an explicit
+/// index automatically.
+struct TemplateFormatter<'a> {
+    args: &'a [Object],
+    keywords: &'a [(String, Object)],
+    /// The next automatic field number, or None once an explicit one is used
+    next_index: Option<usize>,
+    vm: &'a mut VM,
+}
+
+impl TemplateFormatter<'_> {
+    fn format(&mut self, template: &str) -> Result<String, InterpretResult> {
+        let mut text = String::new();
+        let mut chars = template.char_indices().peekable();
+        while let Some((start, ch)) = chars.next() {
+            match ch {
+                '{' | '}' if chars.next_if(|&(_, next)| next == ch).is_some() => text.push(ch),
+                '{' if chars.peek().is_none() => {
+                    return Err(self.fail(NativeError::value_error(
+                        "Single '{' encountered in format string",
+                    )))
+                }
+                '{' => {
+                    // The field runs to the matching brace, past any in its spec
+                    let mut depth = 0;
+                    let end = loop {
+                        match chars.next() {
+                            Some((_, '{')) => depth += 1,
+                            Some((end, '}')) if depth == 0 => break end,
+                            Some((_, '}')) => depth -= 1,
+                            Some(_) => {}
+                            None => {
+                                return Err(self.fail(NativeError::value_error(
+                                    "expected '}' before end of string",
+                                )))
+                            }
+                        }
+                    };
+                    text.push_str(&self.field(&template[start + 1..end])?);
+                }
+                '}' => {
+                    return Err(self.fail(NativeError::value_error(
+                        "Single '}' encountered in format string",
+                    )))
+                }
+                _ => text.push(ch),
+            }
+        }
+        Ok(text)
+    }
+
+    /// Formats the replacement field `field`: `name[!conversion][:spec]`.
+    fn field(&mut self, field: &str) -> Result<String, InterpretResult> {
+        let name_end = field.find(['!', ':']).unwrap_or(field.len());
+        let (name, rest) = field.split_at(name_end);
+        let (conversion, spec) = match rest.strip_prefix('!') {
+            Some(rest) => {
+                let mut chars = rest.chars();
+                let conversion = match chars.next() {
+                    Some('s') => 1,
+                    Some('r') => 2,
+                    Some('a') => 3,
+                    Some(other) => {
+                        return Err(self.fail(NativeError::value_error(format!(
+                            "Unknown conversion specifier {}",
+                            other
+                        ))))
+                    }
+                    None => {
+                        return Err(self.fail(NativeError::value_error(
+                            "end of string while looking for conversion specifier",
+                        )))
+                    }
+                };
+                let rest = chars.as_str();
+                match rest.strip_prefix(':') {
+                    Some(spec) => (conversion, spec),
+                    None if rest.is_empty() => (conversion, ""),
+                    None => {
+                        return Err(self.fail(NativeError::value_error(
+                            "expected ':' after conversion specifier",
+                        )))
+                    }
+                }
+            }
+            None => (0, rest.strip_prefix(':').unwrap_or(rest)),
+        };
+
+        let value = self.lookup(name)?;
+        let spec = if spec.contains('{') {
+            self.format(spec)?
+        } else {
+            spec.to_string()
+        };
+        self.vm.format_field(value, conversion, &spec)
+    }
+
+    /// The value a field name refers to: a positional argument, by number or
+    /// in turn, or a keyword argument by name, then any `.attribute` and
+    /// `[index]` parts.
+    fn lookup(&mut self, name: &str) -> Result<Object, InterpretResult> {
+        let first_end = name.find(['.', '[']).unwrap_or(name.len());
+        let (first, mut rest) = name.split_at(first_end);
+
+        let mut value = match first.parse::<usize>() {
+            Ok(index) => self.positional(Some(index))?,
+            Err(_) if first.is_empty() => self.positional(None)?,
+            Err(_) => self
+                .keywords
+                .iter()
+                .find(|(keyword, _)| keyword == first)
+                .map(|(_, value)| value.clone())
+                .ok_or_else(|| self.fail(NativeError::new("KeyError", format!("'{}'", first))))?,
+        };
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                let attribute = &after[..end];
+                value = op_get_attr(value.clone(), attribute, &self.vm.stack).map_err(|_| {
+                    self.fail(NativeError::new(
+                        "AttributeError",
+                        attr_error(&value, attribute),
+                    ))
+                })?;
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| {
+                    self.fail(NativeError::value_error("Missing ']' in format string"))
+                })?;
+                let key = &after[..end];
+                let key = match key.parse::<i64>() {
+                    Ok(number) => Rc::new(ObjectType::Integer(number)),
+                    Err(_) => string(key.to_string()),
+                };
+                value = op_index(value.clone(), key.clone()).map_err(|_| {
+                    let (kind, message) = index_error(&value, &key);
+                    self.vm.raise_error(kind, message)
+                })?;
+                rest = &after[end + 1..];
+            } else {
+                return Err(self.fail(NativeError::value_error(
+                    "Only '.' or '[' may follow ']' in format field specifier",
+                )));
+            }
+        }
+        Ok(value)
+    }
+
+    /// The positional argument a field names by number, or the next one in
+    /// turn when it names none.
+    fn positional(&mut self, number: Option<usize>) -> Result<Object, InterpretResult> {
+        let index = match number {
+            Some(index) => {
+                if self.next_index.is_some_and(|next| next > 0) {
+                    return Err(self.fail(NativeError::value_error(
+                        "cannot switch from automatic field numbering to manual field specification",
+                    )));
+                }
+                self.next_index = None;
+                index
+            }
+            None => {
+                let index = self.next_index.ok_or_else(|| {
+                    self.fail(NativeError::value_error(
+                        "cannot switch from manual field specification to automatic field numbering",
+                    ))
+                })?;
+                self.next_index = Some(index + 1);
+                index
+            }
+        };
+        self.args.get(index).cloned().ok_or_else(|| {
+            let message = format!(
+                "Replacement index {} out of range for positional args tuple",
+                index
+            );
+            self.vm.raise_error("IndexError", message)
+        })
+    }
+
+    /// Raises `error` on the VM.
+    fn fail(&mut self, error: NativeError) -> InterpretResult {
+        self.vm.raise_error(error.kind, error.message)
+    }
+}
@@ -1,21 +1,35 @@
-use crate::object::{ClassObject, FunctionObject, InstanceObject, Object, ObjectType};
-use crate::vm::call_frame::{CallFrame, FRAMES_MAX};
-use crate::vm::VM;
-use std::cell::RefCell;
-use std::rc::Rc;
+use crate::object::{
+    ClassObject, FunctionObject, InstanceObject, NativeResult, Object, ObjectType, VmNativeFn,
+};
+use crate::sync::Rc;
+use crate::vm::call_frame::CallFrame;
+use crate::vm::{exceptions, gc, values};
+use crate::vm::{InterpretResult, VM};
 
 impl VM {
     pub(super) fn call_value(&mut self, arg_count: usize) -> bool {
         if self.stack.top() < arg_count + 1 {
+            self.bad_operand();
             return false;
         }
         let callee_index = self.stack.top() - arg_count - 1;
+
+        // Plain function calls dominate recursive code, so take them before
+        // cloning the callee for the general dispatch below.
+        if let ObjectType::Function(function) = &**self.stack.get(callee_index) {
+            let function = function.clone();
+            return self.call_function(function, callee_index, arg_count, false, None);
+        }
+
         let callee = self.stack.get(callee_index).clone();
         match &*callee {
-            ObjectType::Function(function) => {
-                self.call_function(function.clone(), callee_index, arg_count, None, None)
-            }
             ObjectType::NativeFunction(name, func) => {
+                let mut args: Vec<Object> = (0..arg_count)
+                    .map(|i| self.stack.get(callee_index + 1 + i).clone())
+                    .collect();
+                if self.gather_generator_arguments(name, &mut args).is_err() {
+                    return false;
+                }
                 // Special handling for super() - it needs access to self
                 if name == "super" {
                     // Get the class context from the current frame
@@ -25,6 +39,7 @@ impl VM {
                     let self_instance = if let Some(frame) = self.frames.last() {
                         self.stack.get(frame.slot + 1).clone()
                     } else {
+                        self.raise_error("RuntimeError", "super(): no arguments");
                         return false;
                     };
 
@@ -36,74 +51,99 @@ impl VM {
                             self.push(result);
                             true
                         }
-                        Err(_) => false,
+                        Err(error) => self.call_error(error.kind, error.message),
                     }
                 } else {
                     // General native function call
                     let class_context = self.frames.last().and_then(|f| f.class_context.clone());
-                    let args: Vec<Object> = (0..arg_count)
-                        .map(|i| self.stack.get(callee_index + 1 + i).clone())
-                        .collect();
                     match func(&args, class_context) {
                         Ok(result) => {
                             self.stack.set_top(callee_index);
                             self.push(result);
                             true
                         }
-                        Err(_) => false,
+                        Err(error) => self.call_error(error.kind, error.message),
                     }
                 }
             }
+            ObjectType::VmNativeFunction(name, func) => {
+                let mut args: Vec<Object> = (0..arg_count)
+                    .map(|i| self.stack.get(callee_index + 1 + i).clone())
+                    .collect();
+                if self.gather_generator_arguments(name, &mut args).is_err() {
+                    return false;
+                }
+                self.call_vm_native(*func, callee_index, &args, &[])
+            }
             ObjectType::Class(class) => {
                 // Create instance
-                let instance = Rc::new(RefCell::new(InstanceObject::new(class.clone())));
+                let instance = gc::new_instance(InstanceObject::new(class.clone()));
                 let instance_obj = Rc::new(ObjectType::Instance(instance.clone()));
 
+                let is_exception = exceptions::is_exception_class(class);
+                if is_exception {
+                    // Exceptions keep the arguments they were created with
+                    let args = (0..arg_count)
+                        .map(|i| self.stack.get(callee_index + 1 + i).clone())
+                        .collect();
+                    instance
+                        .borrow_mut()
+                        .set_field("args".to_string(), Rc::new(ObjectType::Tuple(args)));
+                }
+
+                // The instance replaces the class in the callee slot, where
+                // handle_return picks it up once __init__ finishes.
+                self.stack.set(callee_index, instance_obj.clone());
+
                 // Look for __init__ method (traverses inheritance chain)
                 if let Some(init_method) = class.get_method("__init__") {
                     if let ObjectType::Function(init_func) = &*init_method {
-                        // Stack layout: [class, arg1, arg2, ...]
-                        // We want: [instance, instance, arg1, arg2, ...] so that after __init__ returns,
-                        // one instance remains
-
-                        // Push instance at the callee position
-                        self.stack.set(callee_index, instance_obj.clone());
-
-                        // Insert instance as self parameter
-                        // Shift arguments up by one to make room for self
+                        // Stack layout: [instance, arg1, arg2, ...]
+                        // Shift arguments up by one to pass the instance as self
                         let stack_top = self.stack.top();
                         for i in (callee_index + 1..stack_top).rev() {
                             let value = self.stack.get(i).clone();
                             self.stack.set(i + 1, value);
                         }
-                        self.stack.set(callee_index + 1, instance_obj.clone());
+                        self.stack.set(callee_index + 1, instance_obj);
                         self.stack.set_top(stack_top + 1);
 
-                        // Save instance beyond the call frame so it doesn't get overwritten
-                        let new_top = self.stack.top();
-                        self.stack.set(new_top, instance_obj.clone());
-                        let saved_instance_slot = new_top;
-                        self.stack.set_top(new_top + 1);
-
-                        // Now call __init__: [instance, self(instance), arg1, arg2, ..., saved_instance]
-                        // Pass the saved_instance_slot to call_function so handle_return can restore it
-                        // Pass the class as context for super() to work in __init__
+                        // Pass the class defining __init__ as context for
+                        // super() to work in it
+                        let class_context = ClassObject::defining_class(class, &init_method)
+                            .unwrap_or_else(|| class.clone());
                         return self.call_function(
                             init_func.clone(),
                             callee_index,
                             arg_count + 1,
-                            Some(saved_instance_slot),
-                            Some(class.clone()),
+                            true,
+                            Some(class_context),
                         );
                     }
                 }
 
+                if arg_count > 0 && !is_exception {
+                    return self
+                        .call_error("TypeError", format!("{}() takes no arguments", class.name));
+                }
+
                 // No __init__, just return the instance
-                self.stack.set(callee_index, instance_obj);
                 self.stack.set_top(callee_index + 1);
                 true
             }
             ObjectType::BoundMethod(instance, method) => {
+                // Builtin functions are not methods: one kept on a class, as
+                // `ev = eval` in its body, is called without the receiver
+                if let ObjectType::NativeFunction(name, _) | ObjectType::VmNativeFunction(name, _) =
+                    &**method
+                {
+                    let builtin = self.builtins.get(name.as_str());
+                    if builtin.is_some_and(|builtin| Rc::ptr_eq(builtin, method)) {
+                        self.stack.set(callee_index, method.clone());
+                        return self.call_value(arg_count);
+                    }
+                }
+
                 // Insert the instance as first parameter
                 // Stack layout: [bound_method, arg1, arg2, ...]
                 // Need: [bound_method, instance(self), arg1, arg2, ...]
@@ -118,12 +158,15 @@ impl VM {
                 self.stack.set(callee_index + 1, instance.clone());
                 self.stack.set_top(stack_top + 1);
 
-                // Get the class context from the instance for super() support
-                let class_context = if let ObjectType::Instance(inst_ref) = &**instance {
-                    Some(inst_ref.borrow().class.clone())
-                } else {
-                    None
+                // The class defining the method is the context super() starts
+                // after; the receiver is an instance, or a class for class methods
+                let receiver_class = match &**instance {
+                    ObjectType::Instance(inst_ref) => Some(inst_ref.borrow().class.clone()),
+                    ObjectType::Class(class) => Some(class.clone()),
+                    _ => None,
                 };
+                let class_context = receiver_class
+                    .map(|class| ClassObject::defining_class(&class, method).unwrap_or(class));
 
                 match &**method {
                     ObjectType::Function(function) => {
@@ -133,32 +176,69 @@ impl VM {
                             function.clone(),
                             callee_index,
                             arg_count + 1,
-                            None,
+                            false,
                             class_context,
                         )
                     }
-                    _ => false,
+                    ObjectType::NativeFunction(name, func) => {
+                        // Native methods take the receiver as their first argument
+                        let mut args: Vec<Object> = (0..=arg_count)
+                            .map(|i| self.stack.get(callee_index + 1 + i).clone())
+                            .collect();
+                        if self.gather_generator_arguments(name, &mut args).is_err() {
+                            return false;
+                        }
+                        match func(&args, class_context) {
+                            Ok(result) => {
+                                self.stack.set_top(callee_index);
+                                self.push(result);
+                                true
+                            }
+                            Err(error) => self.call_error(error.kind, error.message),
+                        }
+                    }
+                    ObjectType::VmNativeFunction(name, func) => {
+                        let mut args: Vec<Object> = (0..=arg_count)
+                            .map(|i| self.stack.get(callee_index + 1 + i).clone())
+                            .collect();
+                        if self.gather_generator_arguments(name, &mut args).is_err() {
+                            return false;
+                        }
+                        self.call_vm_native(*func, callee_index, &args, &[])
+                    }
+                    other => self.call_error(
+                        "TypeError",
+                        format!("'{}' object is not callable", other.type_name()),
+                    ),
                 }
             }
-            _ => false,
+            ObjectType::Instance(_) if self.bind_call_method(callee_index, &callee) => {
+                self.call_value(arg_count)
+            }
+            other => self.call_error(
+                "TypeError",
+                format!("'{}' object is not callable", other.type_name()),
+            ),
         }
     }
 
+    #[inline]
     pub(super) fn call_function(
         &mut self,
         function: Rc<FunctionObject>,
         callee_index: usize,
         arg_count: usize,
-        instance_slot: Option<usize>,
+        is_initializer: bool,
         class_context: Option<Rc<ClassObject>>,
     ) -> bool {
         // Validate argument count with default parameters support
         // arg_count must be between required_args and arity (inclusive)
         if arg_count < function.required_args || arg_count > function.arity {
-            return false;
+            return self.call_error("TypeError", arity_error_message(&function, arg_count));
         }
 
-        if self.frames.len() >= FRAMES_MAX {
+        if self.frames.len() >= self.frame_limit() {
+            self.frame_limit_reached();
             return false;
         }
 
@@ -170,17 +250,427 @@ impl VM {
                     self.push(default_value.clone());
                 } else {
                     // This should not happen if required_args is calculated correctly
+                    self.bad_operand();
                     return false;
                 }
             }
         }
 
-        self.frames.push(CallFrame::new(
-            function,
-            callee_index,
-            instance_slot,
-            class_context,
-        ));
+        // Reserve the slots of the function's locals, so temporaries never
+        // sit between them
+        for _ in 0..function.local_count {
+            self.push(values::none());
+        }
+
+        // Built where it is stored, once there is room for it, rather than
+        // moved there field by field from a copy built first
+        self.frames.extend(std::iter::once_with(|| {
+            CallFrame::new(function, callee_index, is_initializer, class_context)
+        }));
+        true
+    }
+
+    /// Calls the callee beneath `arg_count` arguments, the last `names.len()` of
+    /// which were passed by keyword (in `names` order).
+    ///
+    /// Keywords are matched to parameter names and the call is rewritten into a
+    /// positional one, with defaults filling any gaps, before going through
+    /// `call_value`. Natives take no keywords, except those running on the VM,
+    /// which are passed them to read themselves.
+    pub(super) fn call_value_with_keywords(
+        &mut self,
+        arg_count: usize,
+        names: Vec<String>,
+    ) -> bool {
+        if self.stack.top() < arg_count + 1 || names.len() > arg_count {
+            self.bad_operand();
+            return false;
+        }
+        let callee_index = self.stack.top() - arg_count - 1;
+        let positional = arg_count - names.len();
+
+        let callee = self.stack.get(callee_index).clone();
+        let (function, receiver_slots) = match &*callee {
+            ObjectType::Function(function) => (function.clone(), 0),
+            ObjectType::BoundMethod(receiver, method) => match &**method {
+                ObjectType::Function(function) => (function.clone(), 1),
+                ObjectType::VmNativeFunction(name, func) => {
+                    let (mut args, keywords) =
+                        self.keyword_call_arguments(callee_index, positional, names);
+                    args.insert(0, receiver.clone());
+                    if self.gather_generator_arguments(name, &mut args).is_err() {
+                        return false;
+                    }
+                    return self.call_vm_native(*func, callee_index, &args, &keywords);
+                }
+                other => {
+                    return self.call_error(
+                        "TypeError",
+                        format!("'{}' object is not callable", other.type_name()),
+                    )
+                }
+            },
+            ObjectType::Class(class) => match class.get_method("__init__").as_deref() {
+                Some(ObjectType::Function(init)) => (init.clone(), 1),
+                _ => {
+                    return self
+                        .call_error("TypeError", format!("{}() takes no arguments", class.name))
+                }
+            },
+            ObjectType::Instance(_) if self.bind_call_method(callee_index, &callee) => {
+                return self.call_value_with_keywords(arg_count, names);
+            }
+            ObjectType::VmNativeFunction(name, func) => {
+                let (mut args, keywords) =
+                    self.keyword_call_arguments(callee_index, positional, names);
+                if self.gather_generator_arguments(name, &mut args).is_err() {
+                    return false;
+                }
+                return self.call_vm_native(*func, callee_index, &args, &keywords);
+            }
+            ObjectType::NativeFunction(name, _) => {
+                return self.call_error(
+                    "TypeError",
+                    format!("{}() takes no keyword arguments", name),
+                )
+            }
+            other => {
+                return self.call_error(
+                    "TypeError",
+                    format!("'{}' object is not callable", other.type_name()),
+                )
+            }
+        };
+
+        let keywords: Vec<(String, Object)> = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| {
+                (
+                    name,
+                    self.stack.get(callee_index + 1 + positional + i).clone(),
+                )
+            })
+            .collect();
+
+        match bind_arguments(&function, receiver_slots, positional, keywords, |i| {
+            self.stack.get(callee_index + 1 + i).clone()
+        }) {
+            Ok(arguments) => {
+                let count = arguments.len();
+                self.stack.set_top(callee_index + 1);
+                for argument in arguments {
+                    self.push(argument);
+                }
+                self.call_value(count)
+            }
+            Err(message) => self.call_error("TypeError", message),
+        }
+    }
+
+    /// Calls the callee on top of the stack with the positional `args` and
+    /// the keywords of each of `mappings` in turn (OpCallExKw), raising
+    /// TypeError for a mapping that is not a dict, a key that is not a
+    /// string, or a keyword given twice.
+    pub(super) fn call_with_mappings(&mut self, args: &Object, mappings: &Object) -> bool {
+        let (ObjectType::List(args), ObjectType::List(mappings)) = (&**args, &**mappings) else {
+            self.bad_operand();
+            return false;
+        };
+        let callee_name = match &**self.peek(0) {
+            ObjectType::Function(function) => function.name.clone(),
+            ObjectType::NativeFunction(name, _) | ObjectType::VmNativeFunction(name, _) => {
+                name.clone()
+            }
+            ObjectType::Class(class) => class.name.clone(),
+            ObjectType::BoundMethod(_, method) => match &**method {
+                ObjectType::Function(function) => function.name.clone(),
+                other => other.type_name(),
+            },
+            other => other.type_name(),
+        };
+
+        let mut names: Vec<String> = Vec::new();
+        let mut values = Vec::new();
+        for mapping in mappings {
+            let ObjectType::Dict(entries) = &**mapping else {
+                return self.call_error(
+                    "TypeError",
+                    format!(
+                        "{}() argument after ** must be a mapping, not {}",
+                        callee_name,
+                        mapping.type_name()
+                    ),
+                );
+            };
+            for (key, value) in entries {
+                let ObjectType::String(name) = &**key else {
+                    return self.call_error("TypeError", "keywords must be strings");
+                };
+                if names.contains(name) {
+                    return self.call_error(
+                        "TypeError",
+                        format!(
+                            "{}() got multiple values for keyword argument '{}'",
+                            callee_name, name
+                        ),
+                    );
+                }
+                names.push(name.clone());
+                values.push(value.clone());
+            }
+        }
+
+        let arg_count = args.len() + values.len();
+        for value in args.iter().chain(&values) {
+            self.push(value.clone());
+        }
+        if names.is_empty() {
+            self.call_value(arg_count)
+        } else {
+            self.call_value_with_keywords(arg_count, names)
+        }
+    }
+
+    /// Calls `callee` with `args` from native code and runs it to completion,
+    /// returning its result. This is how builtins such as `sorted()` call back
+    /// into bytecode (e.g. a `key=` function).
+    pub(super) fn call_callback(
+        &mut self,
+        callee: Object,
+        args: Vec<Object>,
+    ) -> Result<Object, InterpretResult> {
+        let depth = self.frames.len();
+        let arg_count = args.len();
+        self.push(callee);
+        for arg in args {
+            self.push(arg);
+        }
+        if !self.call_value(arg_count) {
+            return Err(InterpretResult::RuntimeError);
+        }
+        self.run_frames_above(depth)?;
+        Ok(self.pop())
+    }
+
+    /// Runs the frames above the first `depth` until they have all returned,
+    /// letting their own handlers catch what they raise.
+    pub(super) fn run_frames_above(&mut self, depth: usize) -> Result<(), InterpretResult> {
+        while self.frames.len() > depth {
+            if let Some(result) = self.step(depth) {
+                return Err(result);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces an instance in the callee slot with its `__call__` method
+    /// bound to it, reporting whether its class defines one.
+    fn bind_call_method(&mut self, callee_index: usize, instance: &Object) -> bool {
+        let ObjectType::Instance(inst_ref) = &**instance else {
+            return false;
+        };
+        let Some(method) = inst_ref.borrow().class.get_method("__call__") else {
+            return false;
+        };
+        let bound = Rc::new(ObjectType::BoundMethod(instance.clone(), method));
+        self.stack.set(callee_index, bound);
         true
     }
+
+    /// The positional arguments and the named values of a call whose callee
+    /// sits at `callee_index`, followed by `positional` arguments and then
+    /// one value per keyword in `names`.
+    fn keyword_call_arguments(
+        &self,
+        callee_index: usize,
+        positional: usize,
+        names: Vec<String>,
+    ) -> (Vec<Object>, Vec<(String, Object)>) {
+        let args: Vec<Object> = (0..positional)
+            .map(|i| self.stack.get(callee_index + 1 + i).clone())
+            .collect();
+        let keywords = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let value = self.stack.get(callee_index + 1 + positional + i).clone();
+                (name, value)
+            })
+            .collect();
+        (args, keywords)
+    }
+
+    /// Runs a native that works on the VM for a call whose callee sits at
+    /// `callee_index`, leaving its result in the callee's place.
+    fn call_vm_native(
+        &mut self,
+        func: VmNativeFn,
+        callee_index: usize,
+        args: &[Object],
+        keywords: &[(String, Object)],
+    ) -> bool {
+        match func(self, args, keywords) {
+            Ok(result) => {
+                self.stack.set_top(callee_index);
+                self.push(result);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Raises TypeError when keywords were passed to `name`, a native that
+    /// takes none.
+    pub(in crate::vm) fn no_keywords(
+        &mut self,
+        name: &str,
+        keywords: &[(String, Object)],
+    ) -> Result<(), InterpretResult> {
+        if keywords.is_empty() {
+            return Ok(());
+        }
+        Err(self.raise_error(
+            "TypeError",
+            format!("{}() takes no keyword arguments", name),
+        ))
+    }
+
+    /// Raises the error a native returned, or passes its value through.
+    pub(in crate::vm) fn native_result(
+        &mut self,
+        result: NativeResult,
+    ) -> Result<Object, InterpretResult> {
+        result.map_err(|error| self.raise_error(error.kind, error.message))
+    }
+
+    /// Records a runtime error for a failed call and reports the failure.
+    pub(super) fn call_error(&mut self, kind: &str, message: impl Into<String>) -> bool {
+        self.raise_error(kind, message);
+        false
+    }
+}
+
+/// Lays out the positional arguments for a call to `function` that also
+/// passes `keywords`, skipping the first `skip` parameters (the receiver of a
+/// bound method). `positional_arg(i)` reads the i-th positional argument.
+///
+/// Returns the full argument list, defaults included, or a TypeError message.
+fn bind_arguments(
+    function: &FunctionObject,
+    skip: usize,
+    positional: usize,
+    keywords: Vec<(String, Object)>,
+    positional_arg: impl Fn(usize) -> Object,
+) -> Result<Vec<Object>, String> {
+    let parameter_count = function.arity - skip;
+    if positional > parameter_count {
+        return Err(arity_error_message(function, positional + skip));
+    }
+
+    let mut slots: Vec<Option<Object>> = (0..parameter_count)
+        .map(|i| (i < positional).then(|| positional_arg(i)))
+        .collect();
+    for (name, value) in keywords {
+        let index = function
+            .parameter_names
+            .iter()
+            .skip(skip)
+            .position(|parameter| *parameter == name)
+            .ok_or_else(|| {
+                format!(
+                    "{}() got an unexpected keyword argument '{}'",
+                    function.qualname, name
+                )
+            })?;
+        if slots[index].is_some() {
+            return Err(format!(
+                "{}() got multiple values for argument '{}'",
+                function.qualname, name
+            ));
+        }
+        slots[index] = Some(value);
+    }
+
+    let mut missing = Vec::new();
+    let mut arguments = Vec::with_capacity(parameter_count);
+    for (index, slot) in slots.into_iter().enumerate() {
+        let default = || function.default_values.get(index + skip).cloned().flatten();
+        match slot.or_else(default) {
+            Some(value) => arguments.push(value),
+            None => missing.push(
+                function
+                    .parameter_names
+                    .get(index + skip)
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(arguments)
+    } else {
+        Err(missing_arguments_message(function, missing.len(), &missing))
+    }
+}
+
+/// Builds the Python-style message for calling `function` with the wrong
+/// number of positional arguments.
+fn arity_error_message(function: &FunctionObject, arg_count: usize) -> String {
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+
+    if arg_count > function.arity {
+        let expected = if function.required_args == function.arity {
+            format!(
+                "{} positional argument{}",
+                function.arity,
+                plural(function.arity)
+            )
+        } else {
+            format!(
+                "from {} to {} positional arguments",
+                function.required_args, function.arity
+            )
+        };
+        let verb = if arg_count == 1 { "was" } else { "were" };
+        return format!(
+            "{}() takes {} but {} {} given",
+            function.qualname, expected, arg_count, verb
+        );
+    }
+
+    let missing_count = function.required_args - arg_count;
+    let missing: Vec<String> = function
+        .parameter_names
+        .iter()
+        .skip(arg_count)
+        .take(missing_count)
+        .cloned()
+        .collect();
+    missing_arguments_message(function, missing_count, &missing)
+}
+
+/// Builds the "missing N required positional arguments" message, naming the
+/// missing parameters when all of their names are known.
+fn missing_arguments_message(function: &FunctionObject, count: usize, names: &[String]) -> String {
+    let plural = if count == 1 { "" } else { "s" };
+    let mut message = format!(
+        "{}() missing {} required positional argument{}",
+        function.qualname, count, plural
+    );
+
+    if names.len() == count {
+        let quoted: Vec<String> = names.iter().map(|name| format!("'{}'", name)).collect();
+        let names = match quoted.as_slice() {
+            [only] => only.clone(),
+            [first, second] => format!("{} and {}", first, second),
+            [rest @ .., last] => format!("{}, and {}", rest.join(", "), last),
+            [] => String::new(),
+        };
+        message.push_str(": ");
+        message.push_str(&names);
+    }
+
+    message
 }
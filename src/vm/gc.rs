@@ -0,0 +1,345 @@
+//! Cycle collection.
+//!
+//! Objects are reference counted, so a cycle such as `obj.me = obj` or a
+//! closure capturing itself keeps itself alive after the last outside
+//! reference is gone. Every cycle passes through something mutable, and the
+//! only mutable containers are instances and upvalues, so those are tracked
+//! (weakly) as they are created.
+//!
+//! `collect` finds garbage by trial deletion rather than by tracing from the
+//! VM's roots: it walks everything reachable from the tracked containers and
+//! counts the references each object receives from within that graph. An
+//! object with more strong references than that is held from outside, by the
+//! VM or by native code, and keeps alive everything it reaches. The rest can
+//! only be reached from each other, so clearing the fields of the garbage
+//! instances and the values of the garbage upvalues frees them all. Objects
+//! that are borrowed while the collector runs count as held from outside.
+//!
+//! A collection also runs automatically whenever the number of tracked
+//! containers doubles, starting at `THRESHOLD`, unless it has been disabled.
+
+use crate::object::{
+    ClassObject, FunctionObject, InstanceObject, IteratorObject, IteratorRef, ModuleObject, Object,
+    ObjectType, Upvalue, UpvalueRef,
+};
+use crate::sync::RefCell;
+use crate::sync::{Rc, Weak};
+use std::collections::HashMap;
+
+/// Tracked containers that trigger the first automatic collection.
+const THRESHOLD: usize = 1000;
+
+enum Tracked {
+    Instance(Weak<RefCell<InstanceObject>>),
+    Upvalue(Weak<RefCell<Upvalue>>),
+}
+
+impl Tracked {
+    fn is_alive(&self) -> bool {
+        match self {
+            Tracked::Instance(instance) => instance.strong_count() > 0,
+            Tracked::Upvalue(upvalue) => upvalue.strong_count() > 0,
+        }
+    }
+
+    fn upgrade(&self) -> Option<Node> {
+        match self {
+            Tracked::Instance(instance) => instance.upgrade().map(Node::Instance),
+            Tracked::Upvalue(upvalue) => upvalue.upgrade().map(Node::Upvalue),
+        }
+    }
+}
+
+struct Collector {
+    tracked: Vec<Tracked>,
+    enabled: bool,
+    next_collection: usize,
+}
+
+thread_local! {
+    static COLLECTOR: std::cell::RefCell<Collector> = const {
+        std::cell::RefCell::new(Collector {
+            tracked: Vec::new(),
+            enabled: true,
+            next_collection: THRESHOLD,
+        })
+    };
+}
+
+/// Wraps a new instance, tracking it for cycle collection.
+pub fn new_instance(instance: InstanceObject) -> Rc<RefCell<InstanceObject>> {
+    let instance = Rc::new(RefCell::new(instance));
+    track(Tracked::Instance(Rc::downgrade(&instance)));
+    instance
+}
+
+/// Tracks a new upvalue for cycle collection.
+pub fn track_upvalue(upvalue: &UpvalueRef) {
+    track(Tracked::Upvalue(Rc::downgrade(upvalue)));
+}
+
+fn track(container: Tracked) {
+    let due = COLLECTOR.with(|collector| {
+        let mut collector = collector.borrow_mut();
+        collector.tracked.push(container);
+        collector.enabled && collector.tracked.len() >= collector.next_collection
+    });
+    if due {
+        collect();
+    }
+}
+
+/// Enables or disables automatic collection. `collect` still works while it
+/// is disabled.
+pub fn set_enabled(enabled: bool) {
+    COLLECTOR.with(|collector| collector.borrow_mut().enabled = enabled);
+}
+
+/// Whether automatic collection is enabled.
+pub fn is_enabled() -> bool {
+    COLLECTOR.with(|collector| collector.borrow().enabled)
+}
+
+/// Frees the unreachable cycles among the objects created on this thread,
+/// returning how many containers (instances, functions, lists and so on)
+/// were found unreachable.
+pub fn collect() -> usize {
+    let candidates: Vec<Node> = COLLECTOR.with(|collector| {
+        let mut collector = collector.borrow_mut();
+        collector.tracked.retain(Tracked::is_alive);
+        collector
+            .tracked
+            .iter()
+            .filter_map(Tracked::upgrade)
+            .collect()
+    });
+    let collected = collect_cycles(candidates);
+    COLLECTOR.with(|collector| {
+        let mut collector = collector.borrow_mut();
+        collector.tracked.retain(Tracked::is_alive);
+        collector.next_collection = (collector.tracked.len() * 2).max(THRESHOLD);
+    });
+    collected
+}
+
+/// Something reference counted that may hold references to other objects.
+enum Node {
+    Value(Object),
+    Function(Rc<FunctionObject>),
+    Class(Rc<ClassObject>),
+    Instance(Rc<RefCell<InstanceObject>>),
+    Upvalue(UpvalueRef),
+    Iterator(IteratorRef),
+    Module(Rc<ModuleObject>),
+}
+
+impl Node {
+    fn address(&self) -> usize {
+        match self {
+            Node::Value(value) => Rc::as_ptr(value) as *const () as usize,
+            Node::Function(function) => Rc::as_ptr(function) as *const () as usize,
+            Node::Class(class) => Rc::as_ptr(class) as *const () as usize,
+            Node::Instance(instance) => Rc::as_ptr(instance) as *const () as usize,
+            Node::Upvalue(upvalue) => Rc::as_ptr(upvalue) as *const () as usize,
+            Node::Iterator(iterator) => Rc::as_ptr(iterator) as *const () as usize,
+            Node::Module(module) => Rc::as_ptr(module) as *const () as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Value(value) => Rc::strong_count(value),
+            Node::Function(function) => Rc::strong_count(function),
+            Node::Class(class) => Rc::strong_count(class),
+            Node::Instance(instance) => Rc::strong_count(instance),
+            Node::Upvalue(upvalue) => Rc::strong_count(upvalue),
+            Node::Iterator(iterator) => Rc::strong_count(iterator),
+            Node::Module(module) => Rc::strong_count(module),
+        }
+    }
+
+    /// Every reference this node holds to another node, once per reference,
+    /// or None if it is borrowed and cannot be inspected.
+    fn references(&self) -> Option<Vec<Node>> {
+        let values = |values: &mut dyn Iterator<Item = &Object>| {
+            values.map(|value| Node::Value(value.clone())).collect()
+        };
+        Some(match self {
+            Node::Value(value) => match &**value {
+                ObjectType::List(items) | ObjectType::Tuple(items) | ObjectType::Set(items) => {
+                    values(&mut items.iter())
+                }
+                ObjectType::Dict(entries) => values(
+                    &mut entries
+                        .iter()
+                        .flat_map(|(key, value)| [key, value].into_iter()),
+                ),
+                ObjectType::Function(function) => vec![Node::Function(function.clone())],
+                ObjectType::Class(class) => vec![Node::Class(class.clone())],
+                ObjectType::Instance(instance) => vec![Node::Instance(instance.clone())],
+                ObjectType::BoundMethod(receiver, method) => {
+                    vec![Node::Value(receiver.clone()), Node::Value(method.clone())]
+                }
+                ObjectType::SuperProxy(receiver, class) => {
+                    vec![Node::Value(receiver.clone()), Node::Class(class.clone())]
+                }
+                ObjectType::ClassMethod(function) => vec![Node::Value(function.clone())],
+                ObjectType::Iterator(iterator) => vec![Node::Iterator(iterator.clone())],
+                ObjectType::Module(module) => vec![Node::Module(module.clone())],
+                _ => Vec::new(),
+            },
+            Node::Function(function) => {
+                let mut references: Vec<Node> = function
+                    .upvalues
+                    .iter()
+                    .map(|upvalue| Node::Upvalue(upvalue.clone()))
+                    .collect();
+                references.extend(
+                    function
+                        .default_values
+                        .iter()
+                        .flatten()
+                        .map(|value| Node::Value(value.clone())),
+                );
+                references
+            }
+            Node::Class(class) => {
+                let mut references: Vec<Node> = values(&mut class.methods.values());
+                references.extend(class.parent.clone().map(Node::Class));
+                references
+            }
+            Node::Instance(instance) => {
+                let instance = instance.try_borrow().ok()?;
+                let mut references = vec![Node::Class(instance.class.clone())];
+                references.extend(
+                    instance
+                        .fields
+                        .iter()
+                        .map(|(_, value)| Node::Value(value.clone())),
+                );
+                references
+            }
+            Node::Upvalue(upvalue) => {
+                let upvalue = upvalue.try_borrow().ok()?;
+                vec![Node::Value(upvalue.closed.clone())]
+            }
+            Node::Iterator(iterator) => match &*iterator.try_borrow().ok()? {
+                IteratorObject::Sequence { source, .. } => vec![Node::Value(source.clone())],
+                IteratorObject::Generator(frame) => std::iter::once(&frame.function)
+                    .chain(frame.slots.iter())
+                    .map(|value| Node::Value(value.clone()))
+                    .collect(),
+                IteratorObject::Zip(iterators) => iterators
+                    .iter()
+                    .map(|iterator| Node::Iterator(iterator.clone()))
+                    .collect(),
+            },
+            Node::Module(module) => values(&mut module.attributes.values()),
+        })
+    }
+}
+
+/// Whether `value` is a container rather than a scalar such as an int or
+/// string, which `collect` does not count.
+fn is_container(value: &Object) -> bool {
+    !matches!(
+        **value,
+        ObjectType::Integer(_)
+            | ObjectType::Float(_)
+            | ObjectType::String(_)
+            | ObjectType::Boolean(_)
+            | ObjectType::NativeFunction(..)
+            | ObjectType::VmNativeFunction(..)
+            | ObjectType::Nil
+    )
+}
+
+/// A node found while walking the graph, which holds the walk's only clone
+/// of it.
+struct Entry {
+    node: Node,
+    references: Vec<usize>,
+    internal: usize,
+    inspected: bool,
+}
+
+/// Finds and breaks the garbage cycles among everything reachable from
+/// `candidates`, returning the number of garbage containers.
+fn collect_cycles(candidates: Vec<Node>) -> usize {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut index: HashMap<usize, usize> = HashMap::new();
+    let mut add = |node: Node, entries: &mut Vec<Entry>| -> usize {
+        *index.entry(node.address()).or_insert_with(|| {
+            entries.push(Entry {
+                node,
+                references: Vec::new(),
+                internal: 0,
+                inspected: false,
+            });
+            entries.len() - 1
+        })
+    };
+    for candidate in candidates {
+        add(candidate, &mut entries);
+    }
+
+    let mut next = 0;
+    while next < entries.len() {
+        if let Some(references) = entries[next].node.references() {
+            let references: Vec<usize> = references
+                .into_iter()
+                .map(|node| add(node, &mut entries))
+                .collect();
+            for &reference in &references {
+                entries[reference].internal += 1;
+            }
+            entries[next].references = references;
+            entries[next].inspected = true;
+        }
+        next += 1;
+    }
+
+    // The walk's own clone accounts for one strong reference to each node
+    let mut live: Vec<bool> = entries
+        .iter()
+        .map(|entry| !entry.inspected || entry.node.strong_count() - 1 > entry.internal)
+        .collect();
+    let mut pending: Vec<usize> = (0..entries.len()).filter(|&i| live[i]).collect();
+    while let Some(i) = pending.pop() {
+        for &reference in &entries[i].references {
+            if !live[reference] {
+                live[reference] = true;
+                pending.push(reference);
+            }
+        }
+    }
+
+    // Values released by breaking the cycles are dropped once the walk's
+    // clones are gone, so nothing is freed while it is still being inspected
+    let mut released: Vec<Object> = Vec::new();
+    let mut collected = 0;
+    for (entry, live) in entries.iter().zip(&live) {
+        if *live {
+            continue;
+        }
+        match &entry.node {
+            Node::Value(value) if is_container(value) => collected += 1,
+            Node::Instance(instance) => {
+                if let Ok(mut instance) = instance.try_borrow_mut() {
+                    let fields = std::mem::take(&mut instance.fields);
+                    released.extend(fields.into_iter().map(|(_, value)| value));
+                }
+            }
+            Node::Upvalue(upvalue) => {
+                if let Ok(mut upvalue) = upvalue.try_borrow_mut() {
+                    let nil = Rc::new(ObjectType::Nil);
+                    released.push(std::mem::replace(&mut upvalue.closed, nil));
+                }
+            }
+            _ => {}
+        }
+    }
+    drop(entries);
+    drop(released);
+    collected
+}
@@ -0,0 +1,202 @@
+//! Generator expressions.
+//!
+//! A generator expression compiles to a `<genexpr>` function looping over
+//! the iterable it is created with, which OpMakeGenerator pairs into a
+//! generator. Each request for an item resumes that function's frame on top
+//! of the stack until its next OpYield, which saves the frame back into the
+//! generator; returning finishes it.
+//!
+//! For loops resume generators one item at a time. Natives cannot run
+//! bytecode, so the builtins that consume an iterable get a generator's
+//! remaining items, gathered up front, instead.
+
+use crate::object::{
+    GeneratorFrame, GeneratorState, IteratorObject, IteratorRef, Object, ObjectType,
+};
+use crate::sync::Rc;
+use crate::sync::RefCell;
+use crate::vm::call_frame::CallFrame;
+use crate::vm::collections::not_iterable_message;
+use crate::vm::upvalues;
+use crate::vm::{InterpretResult, VM};
+
+/// Builtins that iterate over their arguments, which are handed a
+/// generator's items rather than the generator.
+const CONSUMERS: [&str; 12] = [
+    "list", "tuple", "dict", "set", "sorted", "min", "max", "zip", "sum", "any", "all", "join",
+];
+
+impl VM {
+    /// Creates the generator for a `<genexpr>` function looping over
+    /// `iterable` (OpMakeGenerator).
+    pub(super) fn make_generator(
+        &mut self,
+        function: Object,
+        iterable: Object,
+    ) -> Result<Object, InterpretResult> {
+        let ObjectType::Function(genexpr) = &*function else {
+            return Err(self.bad_operand());
+        };
+        if !matches!(
+            *iterable,
+            ObjectType::List(_)
+                | ObjectType::Tuple(_)
+                | ObjectType::Set(_)
+                | ObjectType::String(_)
+                | ObjectType::Iterator(_)
+        ) {
+            return Err(self.raise_error("TypeError", not_iterable_message(&iterable)));
+        }
+
+        // The frame a call would set up: callee, the iterable, then locals
+        let mut slots = vec![function.clone(), iterable];
+        slots.resize(2 + genexpr.local_count, Rc::new(ObjectType::Nil));
+        let generator = IteratorObject::Generator(GeneratorFrame {
+            function,
+            slots,
+            ip: 0,
+            state: GeneratorState::Suspended,
+        });
+        Ok(Rc::new(ObjectType::Iterator(Rc::new(RefCell::new(
+            generator,
+        )))))
+    }
+
+    /// Runs `generator` to its next item, returning None once it is finished.
+    pub(super) fn resume_generator(
+        &mut self,
+        generator: &IteratorRef,
+    ) -> Result<Option<Object>, InterpretResult> {
+        let (function, slots, ip) = {
+            let mut iterator = generator.borrow_mut();
+            let IteratorObject::Generator(frame) = &mut *iterator else {
+                return Ok(iterator.next_item());
+            };
+            match frame.state {
+                GeneratorState::Finished => return Ok(None),
+                GeneratorState::Running => {
+                    drop(iterator);
+                    return Err(self.raise_error("ValueError", "generator already executing"));
+                }
+                GeneratorState::Suspended => {}
+            }
+            frame.state = GeneratorState::Running;
+            (
+                frame.function.clone(),
+                std::mem::take(&mut frame.slots),
+                frame.ip,
+            )
+        };
+        let ObjectType::Function(function) = &*function else {
+            return Err(self.bad_operand());
+        };
+        if self.frames.len() >= self.frame_limit() {
+            self.finish_generator(generator);
+            return Err(self.frame_limit_reached());
+        }
+
+        let depth = self.frames.len();
+        let slot = self.stack.top();
+        for value in slots {
+            self.push(value);
+        }
+        let mut frame = CallFrame::new(function.clone(), slot, false, None);
+        frame.ip = ip;
+        frame.generator = Some(generator.clone());
+        self.frames.push(frame);
+
+        if let Err(error) = self.run_frames_above(depth) {
+            self.finish_generator(generator);
+            return Err(error);
+        }
+        let value = self.pop();
+        let IteratorObject::Generator(frame) = &mut *generator.borrow_mut() else {
+            return Ok(None);
+        };
+        // A frame that returned rather than yielded is still marked running
+        if frame.state == GeneratorState::Running {
+            frame.state = GeneratorState::Finished;
+            return Ok(None);
+        }
+        Ok(Some(value))
+    }
+
+    /// Suspends the generator running in the current frame, saving its frame
+    /// and handing `value` to whatever resumed it (OpYield).
+    pub(super) fn suspend_generator(&mut self, value: Object) -> Result<(), InterpretResult> {
+        let Some(frame) = self.frames.pop() else {
+            return Err(self.bad_operand());
+        };
+        let Some(generator) = frame.generator else {
+            return Err(self.raise_error("SystemError", "yield outside a generator"));
+        };
+        // Closures made in the frame keep the values they captured
+        upvalues::close_upvalues(&mut self.open_upvalues, &self.stack, frame.slot);
+        let slots = (frame.slot..self.stack.top())
+            .map(|index| self.stack.get(index).clone())
+            .collect();
+        self.stack.set_top(frame.slot);
+        if let IteratorObject::Generator(saved) = &mut *generator.borrow_mut() {
+            saved.slots = slots;
+            saved.ip = frame.ip;
+            saved.state = GeneratorState::Suspended;
+        }
+        self.push(value);
+        Ok(())
+    }
+
+    /// Replaces each generator among the arguments of the builtin `name`,
+    /// if it consumes its arguments, with an iterator over its remaining
+    /// items.
+    pub(super) fn gather_generator_arguments(
+        &mut self,
+        name: &str,
+        args: &mut [Object],
+    ) -> Result<(), InterpretResult> {
+        if !CONSUMERS.contains(&name) {
+            return Ok(());
+        }
+        for arg in args.iter_mut() {
+            *arg = self.gather_generator(arg)?;
+        }
+        Ok(())
+    }
+
+    /// An iterator over the remaining items of `value` if it is a generator,
+    /// running it to the end, or `value` itself otherwise.
+    pub(super) fn gather_generator(&mut self, value: &Object) -> Result<Object, InterpretResult> {
+        let Some(generator) = generator_of(value) else {
+            return Ok(value.clone());
+        };
+        let mut items = Vec::new();
+        while let Some(item) = self.resume_generator(&generator)? {
+            items.push(item);
+        }
+        let remaining = IteratorObject::Sequence {
+            source: Rc::new(ObjectType::List(items)),
+            index: 0,
+        };
+        Ok(Rc::new(ObjectType::Iterator(Rc::new(RefCell::new(
+            remaining,
+        )))))
+    }
+
+    /// Marks `generator` finished after it raised.
+    fn finish_generator(&self, generator: &IteratorRef) {
+        if let IteratorObject::Generator(frame) = &mut *generator.borrow_mut() {
+            frame.state = GeneratorState::Finished;
+        }
+    }
+}
+
+/// The generator `value` is, if it is one.
+pub(super) fn generator_of(value: &Object) -> Option<IteratorRef> {
+    match &**value {
+        ObjectType::Iterator(iterator)
+            if matches!(*iterator.borrow(), IteratorObject::Generator(_)) =>
+        {
+            Some(iterator.clone())
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,427 @@
+//! Object identity and hashing behind `id()`, `hash()` and dict keys.
+//!
+//! Hashes follow CPython's numeric scheme, so `hash(1) == hash(1.0) ==
+//! hash(True)` and equal numbers of any type hash alike, and tuples combine
+//! their items the way CPython's tuple hash does. Strings use a fixed-key
+//! hash, so they are stable between runs where CPython's are randomized.
+//! Lists, dicts and sets are unhashable, as are instances whose class defines
+//! `__eq__` without `__hash__`; every other object hashes by identity, a
+//! user-defined `__hash__` being run by the VM before these are consulted.
+//!
+//! Dict keys and set items are matched as CPython matches them: two keys are
+//! the same when they hash alike and are identical or equal, so `d[1]`,
+//! `d[1.0]` and `d[True]` all reach one entry, which keeps the key it was
+//! created with. The VM finds keys with `VM::find_key`, which runs the
+//! `__hash__` and `__eq__` of instances as `hash()` and `==` do;
+//! `key_position` matches by the rules of builtin values alone, for code
+//! without a VM to run them, such as the compiler folding a dict display.
+//! Either way a dict keeps the hash of each key with its entry, so a lookup
+//! hashes only the key it looks for and runs `__eq__` only on a hash match.
+
+use crate::object::{display_address, DictEntries, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::native::native_hash;
+use crate::vm::{values, InterpretResult, VM};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Modulus of CPython's numeric hash, the Mersenne prime 2**61 - 1.
+const MODULUS: u64 = (1 << 61) - 1;
+/// Bits of the modulus.
+const MODULUS_BITS: u32 = 61;
+/// CPython's hash of positive infinity.
+const INFINITY_HASH: i64 = 314_159;
+
+/// Python's `id()` of `value`: the address its repr shows.
+///
+/// `None`, booleans and small integers report the address of their shared
+/// copy, since `is` treats any two equal ones as the same object.
+pub fn identity(value: &ObjectType) -> i64 {
+    let pointer = match value {
+        ObjectType::Nil => Rc::as_ptr(&values::none()) as *const (),
+        ObjectType::Boolean(flag) => Rc::as_ptr(&values::boolean(*flag)) as *const (),
+        ObjectType::Integer(number) if values::is_small_int(*number) => {
+            Rc::as_ptr(&values::integer(*number)) as *const ()
+        }
+        ObjectType::Function(function) => Rc::as_ptr(function) as *const (),
+        ObjectType::FunctionPrototype(prototype) => Rc::as_ptr(prototype) as *const (),
+        ObjectType::Class(class) => Rc::as_ptr(class) as *const (),
+        ObjectType::Instance(instance) => Rc::as_ptr(instance) as *const (),
+        ObjectType::CodeObject(chunk) => Rc::as_ptr(chunk) as *const (),
+        ObjectType::Iterator(iterator) => Rc::as_ptr(iterator) as *const (),
+        ObjectType::Module(module) => Rc::as_ptr(module) as *const (),
+        ObjectType::NativeFunction(_, function) => *function as *const (),
+        ObjectType::VmNativeFunction(_, function) => *function as *const (),
+        _ => value as *const ObjectType as *const (),
+    };
+    display_address(pointer) as i64
+}
+
+/// Python's `hash()` of `value`, or the type name of the unhashable value
+/// found in it.
+pub fn hash_value(value: &ObjectType) -> Result<i64, String> {
+    let hash = match value {
+        ObjectType::Integer(number) => integer_hash(*number),
+        ObjectType::Boolean(flag) => *flag as i64,
+        ObjectType::Float(number) => match float_hash(*number) {
+            Some(hash) => hash,
+            None => identity_hash(value),
+        },
+        ObjectType::String(text) if text.is_empty() => 0,
+        ObjectType::String(text) => {
+            let mut hasher = DefaultHasher::new();
+            text.hash(&mut hasher);
+            hasher.finish() as i64
+        }
+        ObjectType::Tuple(items) => tuple_hash(items)?,
+        ObjectType::List(_) | ObjectType::Dict(_) | ObjectType::Set(_) => {
+            return Err(value.type_name())
+        }
+        ObjectType::Instance(instance) => {
+            let instance = instance.borrow();
+            let class = &instance.class;
+            // Setting `__hash__ = None`, or defining `__eq__` alone, opts out
+            let unhashable = match class.get_method("__hash__") {
+                Some(method) => matches!(*method, ObjectType::Nil),
+                None => class.get_method("__eq__").is_some(),
+            };
+            if unhashable {
+                return Err(value.type_name());
+            }
+            identity_hash(value)
+        }
+        ObjectType::BoundMethod(receiver, method) => identity_hash(receiver) ^ hash_value(method)?,
+        _ => identity_hash(value),
+    };
+    Ok(if hash == -1 { -2 } else { hash })
+}
+
+/// `hash()`, running an instance's `__hash__`.
+pub(in crate::vm) fn hash(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("hash", keywords)?;
+    let [value] = args else {
+        return vm.native_result(native_hash(args, None));
+    };
+    vm.key_hash(value).map(values::integer)
+}
+
+impl VM {
+    /// The hash of `key` as `hash()` gives it, running the `__hash__` of an
+    /// instance, also inside a tuple. Raises TypeError for an unhashable key.
+    pub(in crate::vm) fn key_hash(&mut self, key: &Object) -> Result<i64, InterpretResult> {
+        let hash = match &**key {
+            ObjectType::Tuple(items) => {
+                let mut lanes = Vec::with_capacity(items.len());
+                for item in items {
+                    lanes.push(self.key_hash(item)?);
+                }
+                combine_tuple_hash(&lanes)
+            }
+            ObjectType::Instance(_) => match self.call_hash_method(key) {
+                Some(hash) => hash?,
+                None => self.builtin_hash(key)?,
+            },
+            _ => self.builtin_hash(key)?,
+        };
+        Ok(if hash == -1 { -2 } else { hash })
+    }
+
+    /// `hash_value` of `key`, raising TypeError for an unhashable key.
+    fn builtin_hash(&mut self, key: &Object) -> Result<i64, InterpretResult> {
+        hash_value(key).map_err(|type_name| {
+            self.raise_error("TypeError", format!("unhashable type: '{}'", type_name))
+        })
+    }
+
+    /// Runs the `__hash__` method of an instance, returning `None` when its
+    /// class defines none to run.
+    fn call_hash_method(&mut self, value: &Object) -> Option<Result<i64, InterpretResult>> {
+        let ObjectType::Instance(instance) = &**value else {
+            return None;
+        };
+        let method = instance.borrow().class.get_method("__hash__")?;
+        if matches!(*method, ObjectType::Nil) {
+            return None;
+        }
+        let bound = Rc::new(ObjectType::BoundMethod(value.clone(), method));
+        let result = match self.call_callback(bound, Vec::new()) {
+            Ok(result) => result,
+            Err(error) => return Some(Err(error)),
+        };
+        Some(match &*result {
+            ObjectType::Integer(hash) => Ok(*hash),
+            ObjectType::Boolean(flag) => Ok(*flag as i64),
+            _ => Err(self.raise_error("TypeError", "__hash__ method should return an integer")),
+        })
+    }
+
+    /// Position of the entry for `key` among a dict's `entries`, hashing
+    /// and comparing keys as `hash()` and `==` do.
+    pub(in crate::vm) fn find_key(
+        &mut self,
+        entries: &DictEntries,
+        key: &Object,
+    ) -> Result<Option<usize>, InterpretResult> {
+        Ok(self.entry_position(entries, key)?.0)
+    }
+
+    /// The position of the entry for `key` among a dict's `entries`, if
+    /// there is one, and the hash of `key`.
+    pub(in crate::vm) fn entry_position(
+        &mut self,
+        entries: &DictEntries,
+        key: &Object,
+    ) -> Result<(Option<usize>, i64), InterpretResult> {
+        let hash = self.key_hash(key)?;
+        Ok((self.find_hashed_key(entries, key, hash)?, hash))
+    }
+
+    /// Position of the entry for `key`, whose hash is `hash`, comparing the
+    /// hash kept with each entry before running `__eq__`.
+    fn find_hashed_key(
+        &mut self,
+        entries: &DictEntries,
+        key: &Object,
+        hash: i64,
+    ) -> Result<Option<usize>, InterpretResult> {
+        for (position, (existing, _)) in entries.iter().enumerate() {
+            let existing_hash = match entries.hash(position) {
+                Some(existing_hash) => existing_hash,
+                None => self.key_hash(existing)?,
+            };
+            if existing_hash == hash && self.is_same_key(existing, key)? {
+                return Ok(Some(position));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Position of `item` among a set's `items`, found as `find_key` finds
+    /// a dict key.
+    pub(in crate::vm) fn find_item(
+        &mut self,
+        items: &[Object],
+        item: &Object,
+    ) -> Result<Option<usize>, InterpretResult> {
+        let hash = self.key_hash(item)?;
+        for (position, existing) in items.iter().enumerate() {
+            if self.key_hash(existing)? == hash && self.is_same_key(existing, item)? {
+                return Ok(Some(position));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The distinct items of `items` in the order they first appear, as
+    /// `set()` keeps them. Each item is hashed once.
+    pub(in crate::vm) fn distinct_items(
+        &mut self,
+        items: Vec<Object>,
+    ) -> Result<Vec<Object>, InterpretResult> {
+        let mut distinct = DictEntries::with_capacity(items.len());
+        for item in items {
+            let hash = self.key_hash(&item)?;
+            if self.find_hashed_key(&distinct, &item, hash)?.is_none() {
+                distinct.push(item, values::none(), Some(hash));
+            }
+        }
+        Ok(distinct.iter().map(|(item, _)| item.clone()).collect())
+    }
+
+    /// Sets the value of `key` among a dict's `entries`, adding an entry
+    /// after the others for a new key. A key already there keeps the object
+    /// it was first stored with.
+    pub(in crate::vm) fn insert_entry(
+        &mut self,
+        entries: &mut DictEntries,
+        key: Object,
+        value: Object,
+    ) -> Result<(), InterpretResult> {
+        let (position, hash) = self.entry_position(entries, &key)?;
+        set_entry(entries, position, key, value, hash);
+        Ok(())
+    }
+
+    /// Whether `existing` is the same key as `key`, which hashes alike: the
+    /// same object or an equal one, `existing.__eq__` deciding for an
+    /// instance.
+    fn is_same_key(&mut self, existing: &Object, key: &Object) -> Result<bool, InterpretResult> {
+        if Rc::ptr_eq(existing, key) {
+            return Ok(true);
+        }
+        self.equals(existing, key)
+    }
+}
+
+/// Sets the value of the entry at `position`, or adds one for `key`, whose
+/// hash is `hash`, when there is none.
+pub fn set_entry(
+    entries: &mut DictEntries,
+    position: Option<usize>,
+    key: Object,
+    value: Object,
+    hash: i64,
+) {
+    match position {
+        Some(position) => entries.set_value(position, value),
+        None => entries.push(key, value, Some(hash)),
+    }
+}
+
+/// The hash `hash()` gives `key` without running a `__hash__` method, or
+/// `None` when one would be run or `key` is unhashable. Dicts built without a
+/// VM keep these hashes with their entries.
+pub fn static_hash(key: &ObjectType) -> Option<i64> {
+    match key {
+        ObjectType::Tuple(items) => {
+            let lanes = items
+                .iter()
+                .map(|item| static_hash(item))
+                .collect::<Option<Vec<_>>>()?;
+            let hash = combine_tuple_hash(&lanes);
+            Some(if hash == -1 { -2 } else { hash })
+        }
+        ObjectType::Instance(instance)
+            if instance
+                .borrow()
+                .class
+                .get_method("__hash__")
+                .is_some_and(|method| !matches!(*method, ObjectType::Nil)) =>
+        {
+            None
+        }
+        _ => hash_value(key).ok(),
+    }
+}
+
+/// Position of the entry for `key` among a dict's `entries`, or the type
+/// name of the unhashable value found in `key`.
+pub fn key_position(entries: &DictEntries, key: &ObjectType) -> Result<Option<usize>, String> {
+    let hash = hash_value(key)?;
+    Ok(entries
+        .iter()
+        .enumerate()
+        .position(|(position, (existing, _))| {
+            let existing_hash = match entries.hash(position) {
+                Some(existing_hash) => Ok(existing_hash),
+                None => hash_value(existing),
+            };
+            existing_hash == Ok(hash) && (std::ptr::eq(&**existing, key) || **existing == *key)
+        }))
+}
+
+/// Hash of an object compared by identity: its id rotated so the low bits,
+/// which are always zero for aligned addresses, vary.
+fn identity_hash(value: &ObjectType) -> i64 {
+    (identity(value) as u64).rotate_right(4) as i64
+}
+
+/// Hash of an integer: its value reduced modulo 2**61 - 1, keeping its sign.
+fn integer_hash(number: i64) -> i64 {
+    let hash = (number.unsigned_abs() % MODULUS) as i64;
+    if number < 0 {
+        -hash
+    } else {
+        hash
+    }
+}
+
+/// Hash of a float, equal to the integer hash of any integral value and to
+/// the hash of the exact fraction otherwise. NaN has none, since it hashes
+/// by identity.
+fn float_hash(number: f64) -> Option<i64> {
+    if number.is_nan() {
+        return None;
+    }
+    if number.is_infinite() {
+        return Some(if number > 0.0 {
+            INFINITY_HASH
+        } else {
+            -INFINITY_HASH
+        });
+    }
+
+    let (mut mantissa, mut exponent) = frexp(number);
+    let sign = if mantissa < 0.0 {
+        mantissa = -mantissa;
+        -1
+    } else {
+        1
+    };
+
+    // Fold in 28 bits of the mantissa at a time, multiplying by 2**28
+    // modulo 2**61 - 1 (a rotation) before each
+    let mut hash = 0u64;
+    while mantissa != 0.0 {
+        hash = ((hash << 28) & MODULUS) | (hash >> (MODULUS_BITS - 28));
+        mantissa *= 268_435_456.0;
+        exponent -= 28;
+        let digits = mantissa as u64;
+        mantissa -= digits as f64;
+        hash += digits;
+        if hash >= MODULUS {
+            hash -= MODULUS;
+        }
+    }
+
+    // Multiply by 2**exponent modulo 2**61 - 1, again a rotation
+    let bits = MODULUS_BITS as i32;
+    let shift = if exponent >= 0 {
+        exponent % bits
+    } else {
+        bits - 1 - ((-1 - exponent) % bits)
+    } as u32;
+    hash = ((hash << shift) & MODULUS) | (hash >> (MODULUS_BITS - shift));
+    Some(hash as i64 * sign)
+}
+
+/// Splits a finite `number` into a mantissa in [0.5, 1) and a power of two,
+/// as C's `frexp` does.
+fn frexp(number: f64) -> (f64, i32) {
+    if number == 0.0 {
+        return (number, 0);
+    }
+    let bits = number.to_bits();
+    let biased = ((bits >> 52) & 0x7ff) as i32;
+    if biased == 0 {
+        // Subnormal: scale into the normal range first
+        let (mantissa, exponent) = frexp(number * 2f64.powi(54));
+        return (mantissa, exponent - 54);
+    }
+    let mantissa = f64::from_bits((bits & !(0x7ff << 52)) | (1022 << 52));
+    (mantissa, biased - 1022)
+}
+
+/// Hash of a tuple, or the type name of the unhashable value found in it.
+fn tuple_hash(items: &[Object]) -> Result<i64, String> {
+    let mut lanes = Vec::with_capacity(items.len());
+    for item in items {
+        lanes.push(hash_value(item)?);
+    }
+    Ok(combine_tuple_hash(&lanes))
+}
+
+/// Combines the hashes of a tuple's items as CPython's xxHash-based tuple
+/// hash does.
+fn combine_tuple_hash(lanes: &[i64]) -> i64 {
+    const PRIME_1: u64 = 11_400_714_785_074_694_791;
+    const PRIME_2: u64 = 14_029_467_366_897_019_727;
+    const PRIME_5: u64 = 2_870_177_450_012_600_261;
+
+    let mut accumulator = PRIME_5;
+    for &lane in lanes {
+        let lane = lane as u64;
+        accumulator = accumulator.wrapping_add(lane.wrapping_mul(PRIME_2));
+        accumulator = accumulator.rotate_left(31);
+        accumulator = accumulator.wrapping_mul(PRIME_1);
+    }
+    accumulator = accumulator.wrapping_add(lanes.len() as u64 ^ (PRIME_5 ^ 3_527_539));
+    if accumulator == u64::MAX {
+        return 1_546_275_796;
+    }
+    accumulator as i64
+}
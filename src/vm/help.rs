@@ -0,0 +1,547 @@
+//! The `help()` builtin: a readable summary of a function, method, class,
+//! module or builtin, laid out as CPython's `pydoc` lays it out.
+//!
+//! A function is shown as its signature, built from its parameter names,
+//! annotations and defaults, followed by its docstring. A class is shown as
+//! its constructor signature, docstring and method resolution order, then
+//! its methods, class methods and other attributes, those it defines ahead
+//! of those it inherits. Builtins have no parameter names or docstrings of
+//! their own, so theirs are kept in a table here.
+//!
+//! The summary is written to the VM's output, and defaults are shown by
+//! their repr, so `help` is a native that runs on the VM.
+
+use crate::object::{ClassObject, FunctionObject, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::formatting::repr_of;
+use crate::vm::{InterpretResult, VM};
+
+/// `help(value)`.
+pub(in crate::vm) fn help(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("help", keywords)?;
+    let value = match args {
+        [value] => value,
+        [] => return Err(vm.raise_error("TypeError", "help() without arguments is not supported")),
+        _ => {
+            let message = format!("help expected at most 1 argument, got {}", args.len());
+            return Err(vm.raise_error("TypeError", message));
+        }
+    };
+    let text = vm.help_text(value);
+    vm.output.write(&text);
+    Ok(Rc::new(ObjectType::Nil))
+}
+
+/// Signatures and docstrings of the builtins.
+const BUILTINS: &[(&str, &str, &str)] = &[
+    ("abs", "abs(x, /)", "Return the absolute value of the argument."),
+    (
+        "all",
+        "all(iterable, /)",
+        "Return True if bool(x) is True for all values x in the iterable.\n\nIf the iterable is empty, return True.",
+    ),
+    (
+        "any",
+        "any(iterable, /)",
+        "Return True if bool(x) is True for any x in the iterable.\n\nIf the iterable is empty, return False.",
+    ),
+    (
+        "bin",
+        "bin(number, /)",
+        "Return the binary representation of an integer.",
+    ),
+    (
+        "callable",
+        "callable(obj, /)",
+        "Return whether the object is callable (i.e., some kind of function).",
+    ),
+    (
+        "chr",
+        "chr(i, /)",
+        "Return a Unicode string of one character with ordinal i; 0 <= i <= 0x10ffff.",
+    ),
+    (
+        "classmethod",
+        "classmethod(function, /)",
+        "Convert a function to be a class method.",
+    ),
+    (
+        "dict",
+        "dict(iterable=(), /, **kwargs)",
+        "Create a new dictionary from an iterable of key/value pairs and keyword arguments.",
+    ),
+    (
+        "dir",
+        "dir(object, /)",
+        "Return the sorted names of the attributes of the object.",
+    ),
+    (
+        "divmod",
+        "divmod(x, y, /)",
+        "Return the tuple (x//y, x%y).  Invariant: div*y + mod == x.",
+    ),
+    (
+        "eval",
+        "eval(source, globals=None, locals=None, /)",
+        "Evaluate the expression in source and return its value.",
+    ),
+    (
+        "exec",
+        "exec(source, globals=None, locals=None, /)",
+        "Execute the statements in source; return None.",
+    ),
+    (
+        "format",
+        "format(value, format_spec='', /)",
+        "Return value formatted by format_spec, as an f-string field would be.",
+    ),
+    (
+        "getattr",
+        "getattr(object, name, default=None, /)",
+        "Get a named attribute from an object, or default if it has none.",
+    ),
+    (
+        "hasattr",
+        "hasattr(obj, name, /)",
+        "Return whether the object has an attribute with the given name.",
+    ),
+    (
+        "hash",
+        "hash(obj, /)",
+        "Return the hash value for the given object.",
+    ),
+    (
+        "help",
+        "help(object, /)",
+        "Show the signature and docstring of an object.",
+    ),
+    (
+        "hex",
+        "hex(number, /)",
+        "Return the hexadecimal representation of an integer.",
+    ),
+    ("id", "id(obj, /)", "Return the identity of an object."),
+    (
+        "int",
+        "int(x=0, /, base=10)",
+        "Convert a number or string to an integer.",
+    ),
+    ("iter", "iter(iterable, /)", "Get an iterator from an object."),
+    (
+        "len",
+        "len(obj, /)",
+        "Return the number of items in a container.",
+    ),
+    (
+        "list",
+        "list(iterable=(), /)",
+        "Create a new list from the items of an iterable.",
+    ),
+    (
+        "max",
+        "max(iterable, *, key=None, default=None)",
+        "With a single iterable argument, return its biggest item; with two or more arguments, return the largest.",
+    ),
+    (
+        "min",
+        "min(iterable, *, key=None, default=None)",
+        "With a single iterable argument, return its smallest item; with two or more arguments, return the smallest.",
+    ),
+    (
+        "next",
+        "next(iterator, default=None, /)",
+        "Return the next item from the iterator, or default once it is exhausted.",
+    ),
+    (
+        "oct",
+        "oct(number, /)",
+        "Return the octal representation of an integer.",
+    ),
+    (
+        "ord",
+        "ord(c, /)",
+        "Return the Unicode code point for a one-character string.",
+    ),
+    (
+        "pow",
+        "pow(base, exp, mod=None)",
+        "Equivalent to base**exp with 2 arguments or base**exp % mod with 3 arguments.",
+    ),
+    (
+        "range",
+        "range(start, stop=None, step=1, /)",
+        "Return the integers from start (or 0) up to stop, counting by step.",
+    ),
+    (
+        "repr",
+        "repr(obj, /)",
+        "Return the canonical string representation of the object.",
+    ),
+    (
+        "round",
+        "round(number, ndigits=None)",
+        "Round a number to a given precision in decimal digits.",
+    ),
+    (
+        "set",
+        "set(iterable=(), /)",
+        "Build an unordered collection of unique elements.",
+    ),
+    (
+        "setattr",
+        "setattr(obj, name, value, /)",
+        "Sets the named attribute on the given object to the specified value.",
+    ),
+    (
+        "sorted",
+        "sorted(iterable, /, *, key=None, reverse=False)",
+        "Return a new list containing all items from the iterable in ascending order.",
+    ),
+    (
+        "str",
+        "str(object='')",
+        "Return the string version of object, as print() shows it.",
+    ),
+    (
+        "sum",
+        "sum(iterable, /, start=0)",
+        "Return the sum of a 'start' value (default: 0) plus an iterable of numbers.",
+    ),
+    (
+        "super",
+        "super()",
+        "Look methods up in the parent of the class the calling method is defined in.",
+    ),
+    (
+        "tuple",
+        "tuple(iterable=(), /)",
+        "Create a new tuple from the items of an iterable.",
+    ),
+    (
+        "type",
+        "type(object, /)",
+        "Return the type of an object.",
+    ),
+    (
+        "vars",
+        "vars(object, /)",
+        "Return the attributes of an object as a dict.",
+    ),
+    (
+        "zip",
+        "zip(*iterables)",
+        "Iterate over several iterables in parallel, yielding tuples of their items.",
+    ),
+];
+
+/// The rule between the sections of a class's summary.
+const SECTION_RULE: &str = "----------------------------------------------------------------------";
+
+impl VM {
+    /// The summary `help(value)` writes. A string is taken as the name of a
+    /// global or builtin to describe.
+    pub fn help_text(&mut self, value: &Object) -> String {
+        match &**value {
+            ObjectType::String(topic) => {
+                let found = self
+                    .globals
+                    .get(topic)
+                    .or_else(|| self.builtins.get(topic))
+                    .cloned();
+                match found {
+                    Some(found) => self.help_text(&found),
+                    None => format!(
+                        "No Python documentation found for {}.\n\
+                         Use help() to get the interactive help utility.\n\
+                         Use help(str) for help on the str class.\n\n",
+                        repr_of(value.clone(), &mut self.stack, &mut self.frames)
+                    ),
+                }
+            }
+            ObjectType::Function(function) => format!(
+                "Help on function {} in module {}:\n\n{}\n",
+                function.name,
+                function.module,
+                self.function_entry(function, false, "")
+            ),
+            ObjectType::BoundMethod(receiver, method) => {
+                let owner = match &**receiver {
+                    ObjectType::Class(_) => "builtins.type".to_string(),
+                    ObjectType::Instance(instance) => qualified_name(&instance.borrow().class),
+                    other => format!("builtins.{}", other.type_name()),
+                };
+                let suffix = format!(" method of {} instance", owner);
+                match &**method {
+                    ObjectType::Function(function) => format!(
+                        "Help on method {} in module {}:\n\n{}\n",
+                        function.name,
+                        function.module,
+                        self.function_entry(function, true, &suffix)
+                    ),
+                    ObjectType::NativeFunction(name, _) | ObjectType::VmNativeFunction(name, _) => {
+                        format!(
+                            "Help on built-in function {}:\n\n{}(...){}\n\n",
+                            name, name, suffix
+                        )
+                    }
+                    _ => self.help_text(method),
+                }
+            }
+            ObjectType::NativeFunction(name, _) | ObjectType::VmNativeFunction(name, _) => {
+                match BUILTINS.iter().find(|(builtin, _, _)| builtin == name) {
+                    Some((_, signature, doc)) => format!(
+                        "Help on built-in function {} in module builtins:\n\n{}\n{}\n",
+                        name,
+                        signature,
+                        indent(doc, "    ")
+                    ),
+                    None => format!("Help on built-in function {}:\n\n{}(...)\n\n", name, name),
+                }
+            }
+            ObjectType::Class(class) => {
+                let heading = match class_module(class) {
+                    Some(module) => format!("Help on class {} in module {}", class.name, module),
+                    None => format!("Help on class {}", class.name),
+                };
+                format!("{}:\n\n{}\n", heading, self.class_summary(class))
+            }
+            ObjectType::Instance(instance) => {
+                let class = instance.borrow().class.clone();
+                let heading = match class_module(&class) {
+                    Some(module) => format!("Help on {} in module {} object", class.name, module),
+                    None => format!("Help on {} object", class.name),
+                };
+                format!("{}:\n\n{}\n", heading, self.class_summary(&class))
+            }
+            ObjectType::Module(module) => {
+                let mut names: Vec<&String> = module.attributes.keys().collect();
+                names.sort();
+                let mut functions = String::new();
+                let mut data = String::new();
+                for name in names {
+                    let attribute = module.attributes[name].clone();
+                    match &*attribute {
+                        ObjectType::NativeFunction(..)
+                        | ObjectType::VmNativeFunction(..)
+                        | ObjectType::Function(_) => {
+                            functions.push_str(&format!("    {}(...)\n\n", name));
+                        }
+                        _ => {
+                            let repr = repr_of(attribute, &mut self.stack, &mut self.frames);
+                            data.push_str(&format!("    {} = {}\n", name, repr));
+                        }
+                    }
+                }
+                let mut text = format!(
+                    "Help on module {}:\n\nNAME\n    {}\n\n",
+                    module.name, module.name
+                );
+                if !functions.is_empty() {
+                    text.push_str("FUNCTIONS\n");
+                    text.push_str(&functions);
+                }
+                if !data.is_empty() {
+                    text.push_str("DATA\n");
+                    text.push_str(&data);
+                    text.push('\n');
+                }
+                text
+            }
+            other => {
+                let type_name = other.type_name();
+                format!(
+                    "Help on {} object:\n\nclass {}(object)\n\n",
+                    type_name, type_name
+                )
+            }
+        }
+    }
+
+    /// A function's signature line followed by its docstring indented under
+    /// it. A bound method leaves out its first parameter.
+    fn function_entry(&mut self, function: &FunctionObject, bound: bool, suffix: &str) -> String {
+        let skip = usize::from(bound && !function.parameter_names.is_empty());
+        let mut entry = format!("{}{}\n", self.signature(function, skip), suffix);
+        if let Some(doc) = &function.doc {
+            entry.push_str(&indent(&clean_doc(doc), "    "));
+        }
+        entry
+    }
+
+    /// `name(parameters) -> return type`, leaving out the first `skip`
+    /// parameters.
+    fn signature(&mut self, function: &FunctionObject, skip: usize) -> String {
+        let mut parameters = Vec::new();
+        for (index, name) in function.parameter_names.iter().enumerate().skip(skip) {
+            let annotation = function.parameter_types.get(index).cloned().flatten();
+            let default = function.default_values.get(index).cloned().flatten();
+            let default = default.map(|value| repr_of(value, &mut self.stack, &mut self.frames));
+            parameters.push(match (annotation, default) {
+                (Some(annotation), Some(default)) => {
+                    format!("{}: {} = {}", name, annotation, default)
+                }
+                (Some(annotation), None) => format!("{}: {}", name, annotation),
+                (None, Some(default)) => format!("{}={}", name, default),
+                (None, None) => name.clone(),
+            });
+        }
+        let mut signature = format!("{}({})", function.name, parameters.join(", "));
+        if let Some(return_type) = &function.return_type {
+            signature.push_str(&format!(" -> {}", return_type));
+        }
+        signature
+    }
+
+    /// The `class Name(Parent)` block of a class's summary, each line under
+    /// the first set off by ` |  `.
+    fn class_summary(&mut self, class: &Rc<ClassObject>) -> String {
+        let parent = match &class.parent {
+            Some(parent) => parent.name.clone(),
+            None => "builtins.object".to_string(),
+        };
+        let mut intro = Vec::new();
+        if let Some(ObjectType::Function(init)) = class.get_method("__init__").as_deref() {
+            let constructor = self.signature(init, usize::from(!init.parameter_names.is_empty()));
+            intro.push(format!("{}{}", class.name, &constructor[init.name.len()..]));
+        }
+        if let Some(ObjectType::String(doc)) = class.methods.get("__doc__").map(|doc| &**doc) {
+            intro.push(clean_doc(doc).trim_end().to_string());
+        }
+        if class.parent.is_some() {
+            let mut order = String::from("Method resolution order:\n");
+            let mut current = Some(class.clone());
+            while let Some(ancestor) = current {
+                order.push_str(&format!("    {}\n", ancestor.name));
+                current = ancestor.parent.clone();
+            }
+            order.push_str("    builtins.object");
+            intro.push(order);
+        }
+
+        let mut sections = Vec::new();
+        let mut owner = Some(class.clone());
+        while let Some(current) = owner {
+            let whose = if Rc::ptr_eq(&current, class) {
+                "defined here".to_string()
+            } else {
+                format!("inherited from {}", current.name)
+            };
+            let mut names: Vec<&String> = current.methods.keys().collect();
+            names.sort();
+            let (mut methods, mut class_methods, mut data) = (Vec::new(), Vec::new(), Vec::new());
+            for name in names {
+                // Attributes a subclass overrides are listed with the subclass
+                let shadowed = !Rc::ptr_eq(&current, class)
+                    && class.get_method(name).as_ref() != current.methods.get(name);
+                if name == "__doc__" || shadowed {
+                    continue;
+                }
+                let attribute = current.methods[name].clone();
+                match &*attribute {
+                    ObjectType::Function(function) => {
+                        methods.push(self.function_entry(function, false, ""));
+                    }
+                    ObjectType::ClassMethod(function) => {
+                        match &**function {
+                            ObjectType::Function(function) => class_methods
+                                .push(self.function_entry(function, true, " from builtins.type")),
+                            _ => class_methods.push(format!("{}(...)\n", name)),
+                        }
+                    }
+                    _ => {
+                        let repr = repr_of(attribute, &mut self.stack, &mut self.frames);
+                        data.push(format!("{} = {}\n", name, repr));
+                    }
+                }
+            }
+            for (title, entries) in [
+                ("Methods", methods),
+                ("Class methods", class_methods),
+                ("Data and other attributes", data),
+            ] {
+                if !entries.is_empty() {
+                    sections.push(format!("{} {}:\n\n{}", title, whose, entries.join("\n")));
+                }
+            }
+            owner = current.parent.clone();
+        }
+
+        let mut body = String::new();
+        for part in intro {
+            body.push_str(&part);
+            body.push_str("\n\n");
+        }
+        body.push_str(&sections.join(&format!("\n{}\n", SECTION_RULE)));
+        let mut summary = format!("class {}({})\n", class.name, parent);
+        for line in body.trim_end_matches('\n').lines() {
+            summary.push_str(" |  ");
+            summary.push_str(line);
+            summary.push('\n');
+        }
+        summary
+    }
+}
+
+/// `module.Name` for a class, as `help()` names the owner of a method.
+fn qualified_name(class: &ClassObject) -> String {
+    match class_module(class) {
+        Some(module) => format!("{}.{}", module, class.name),
+        None => class.name.clone(),
+    }
+}
+
+/// The module a class was defined in, taken from its methods since classes
+/// do not record it themselves.
+fn class_module(class: &ClassObject) -> Option<String> {
+    class
+        .methods
+        .values()
+        .find_map(|attribute| match &**attribute {
+            ObjectType::Function(function) => Some(function.module.clone()),
+            ObjectType::ClassMethod(function) => match &**function {
+                ObjectType::Function(function) => Some(function.module.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+/// A docstring with the indentation its lines after the first share
+/// removed, along with blank lines at either end, as `inspect.cleandoc`
+/// gives it.
+pub(crate) fn clean_doc(doc: &str) -> String {
+    let lines: Vec<&str> = doc.lines().collect();
+    let margin = lines
+        .iter()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut cleaned: Vec<String> = Vec::with_capacity(lines.len());
+    if let Some(first) = lines.first() {
+        cleaned.push(first.trim_start().to_string());
+    }
+    for line in lines.iter().skip(1) {
+        cleaned.push(line.get(margin..).unwrap_or("").trim_end().to_string());
+    }
+    while cleaned.last().is_some_and(|line| line.trim().is_empty()) {
+        cleaned.pop();
+    }
+    let start = cleaned
+        .iter()
+        .position(|line| !line.trim().is_empty())
+        .unwrap_or(cleaned.len());
+    cleaned[start..].join("\n")
+}
+
+/// Each line of `text` with `prefix` in front of it, ending in a newline.
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}{}\n", prefix, line))
+        .collect()
+}
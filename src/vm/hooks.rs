@@ -0,0 +1,177 @@
+//! Hooks for embedders: callbacks the VM runs as a script executes, for
+//! building debuggers, tracers and coverage tools.
+//!
+//! Hooks are registered with `VM::on_instruction`, `VM::on_call`,
+//! `VM::on_return` and `VM::on_line`. A VM with no hooks and no profile runs
+//! each instruction through `dispatch_opcode` directly, so the only cost of
+//! the feature is one flag test per instruction; otherwise instructions go
+//! through `dispatch_instrumented`, which also keeps the profile.
+//!
+//! A line event fires before the first instruction of each entry in the
+//! chunk's line table, which the compiler starts at every statement and at
+//! the top of every loop, so a loop reports its header line each iteration.
+
+use super::profile::Sample;
+use super::VM;
+use crate::bytecode::OpCode;
+use crate::object::{FunctionObject, Object, ObjectType};
+use crate::sync::{MaybeSend, Rc};
+
+/// Where a hook event happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookFrame<'a> {
+    /// Qualified name of the running function, `<module>` for the script
+    pub function: &'a str,
+    pub module: &'a str,
+    /// Frames active, counting the script itself
+    pub depth: usize,
+    /// Offset of the instruction in its function's chunk
+    pub offset: usize,
+    /// Source line of the instruction, if the chunk has a line table
+    pub line: Option<usize>,
+}
+
+impl<'a> HookFrame<'a> {
+    fn new(function: &'a FunctionObject, depth: usize, offset: usize) -> Self {
+        HookFrame {
+            function: if function.name == "<script>" {
+                "<module>"
+            } else {
+                &function.qualname
+            },
+            module: &function.module,
+            depth,
+            offset,
+            line: function.chunk.line_at(offset),
+        }
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+type InstructionHook = Box<dyn FnMut(&HookFrame, OpCode)>;
+#[cfg(not(feature = "sync"))]
+type FrameHook = Box<dyn FnMut(&HookFrame)>;
+#[cfg(not(feature = "sync"))]
+type ReturnHook = Box<dyn FnMut(&HookFrame, &Object)>;
+
+#[cfg(feature = "sync")]
+type InstructionHook = Box<dyn FnMut(&HookFrame, OpCode) + Send>;
+#[cfg(feature = "sync")]
+type FrameHook = Box<dyn FnMut(&HookFrame) + Send>;
+#[cfg(feature = "sync")]
+type ReturnHook = Box<dyn FnMut(&HookFrame, &Object) + Send>;
+
+/// The hooks registered on a VM.
+#[derive(Default)]
+pub(super) struct Hooks {
+    instruction: Option<InstructionHook>,
+    call: Option<FrameHook>,
+    ret: Option<ReturnHook>,
+    line: Option<FrameHook>,
+}
+
+impl Hooks {
+    fn is_empty(&self) -> bool {
+        self.instruction.is_none()
+            && self.call.is_none()
+            && self.ret.is_none()
+            && self.line.is_none()
+    }
+}
+
+impl VM {
+    /// Runs `hook` before every instruction, with the opcode about to run.
+    pub fn on_instruction(&mut self, hook: impl FnMut(&HookFrame, OpCode) + MaybeSend + 'static) {
+        self.hooks.instruction = Some(Box::new(hook));
+        self.update_instrumented();
+    }
+
+    /// Runs `hook` when a function or the script is called, with the callee's
+    /// new frame.
+    pub fn on_call(&mut self, hook: impl FnMut(&HookFrame) + MaybeSend + 'static) {
+        self.hooks.call = Some(Box::new(hook));
+        self.update_instrumented();
+    }
+
+    /// Runs `hook` when a function or the script returns, with its frame at
+    /// the returning instruction and the value it returns. Frames unwound by
+    /// an exception report no return.
+    pub fn on_return(&mut self, hook: impl FnMut(&HookFrame, &Object) + MaybeSend + 'static) {
+        self.hooks.ret = Some(Box::new(hook));
+        self.update_instrumented();
+    }
+
+    /// Runs `hook` before the first instruction of each source line reached.
+    pub fn on_line(&mut self, hook: impl FnMut(&HookFrame) + MaybeSend + 'static) {
+        self.hooks.line = Some(Box::new(hook));
+        self.update_instrumented();
+    }
+
+    /// Removes every registered hook.
+    pub fn clear_hooks(&mut self) {
+        self.hooks = Hooks::default();
+        self.update_instrumented();
+    }
+
+    /// Reports the call to the script that `interpret` has just set up.
+    pub(super) fn script_started(&mut self) {
+        if let (Some(hook), Some(frame)) = (&mut self.hooks.call, self.frames.last()) {
+            hook(&HookFrame::new(&frame.function, 1, 0));
+        }
+    }
+
+    pub(super) fn update_instrumented(&mut self) {
+        self.instrumented = self.profile.is_some() || !self.hooks.is_empty();
+    }
+
+    /// Runs `instruction`, just read from the current frame, calling the
+    /// hooks and recording it in the profile.
+    pub(super) fn dispatch_instrumented(&mut self, instruction: OpCode) -> super::InterpretResult {
+        let depth = self.frames.len();
+        let Some((function, offset)) = self
+            .frames
+            .last()
+            .map(|frame| (frame.function.clone(), frame.ip.saturating_sub(1)))
+        else {
+            return self.dispatch_opcode(instruction);
+        };
+
+        if let Some(hook) = &mut self.hooks.line {
+            if function.chunk.line_starting_at(offset).is_some() {
+                hook(&HookFrame::new(&function, depth, offset));
+            }
+        }
+        if let Some(hook) = &mut self.hooks.instruction {
+            hook(&HookFrame::new(&function, depth, offset), instruction);
+        }
+        let sample = self
+            .profile
+            .as_ref()
+            .map(|profile| Sample::new(profile, function.clone(), depth));
+
+        let result = self.dispatch_opcode(instruction);
+
+        if let (Some(profile), Some(sample)) = (&mut self.profile, sample) {
+            profile.record(instruction, sample, &self.frames);
+        }
+        if self.frames.len() > depth {
+            if let (Some(hook), Some(frame)) = (&mut self.hooks.call, self.frames.last()) {
+                hook(&HookFrame::new(
+                    &frame.function,
+                    self.frames.len(),
+                    frame.ip,
+                ));
+            }
+        }
+        if instruction == OpCode::OpReturn && self.frames.len() < depth {
+            if let Some(hook) = &mut self.hooks.ret {
+                let value = self
+                    .stack
+                    .peek_top()
+                    .unwrap_or_else(|| Rc::new(ObjectType::Nil));
+                hook(&HookFrame::new(&function, depth, offset), &value);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,443 @@
+//! Finding and loading the modules `import` names.
+//!
+//! A name is looked up among the modules already imported, then among the
+//! native modules, then as a package directory `name/__init__.py` or a file
+//! `name.py` in each directory of the search path in turn, the first one
+//! found winning. The search path starts empty,
+//! so a VM reads no files unless its embedder calls `VM::set_search_path`;
+//! the CLI puts the script's own directory first, then the `--path`
+//! directories, then those listed in `OXYTHONPATH`. Scripts see it as
+//! `sys.path`, a list they can replace or append to, which later imports
+//! search.
+//!
+//! A dotted name, `package.module`, imports the package first and looks for
+//! the module in the package's directory, its `__path__`, binding it in the
+//! package once loaded. Names with leading dots, as `from .sibling import
+//! name` gives, are relative to the importing module's `__package__`.
+//!
+//! Importing a module whose body is still running is a circular import.
+//! Module objects are snapshots, so one handed out half-initialized would
+//! never gain the names its module goes on to define; instead the import
+//! raises an `ImportError` naming the cycle, as `circular import: a -> b ->
+//! a`. The exception is a package importing itself while its `__init__.py`
+//! runs, as its modules or `from . import module` do, which gives its
+//! globals as they are so far.
+//!
+//! A source module runs once, on its first import, as a module of its own:
+//! its globals are its own, and its functions go on using them wherever they
+//! are called from. The VM keeps the globals of the module running in
+//! `globals` and the others by module name, and swaps them when a frame of
+//! another module touches a global (see `VM::sync_globals`). The module
+//! object imports get is a snapshot of its globals once its body has run.
+//!
+//! Before a script or module body runs, the source modules it imports that
+//! are not loaded yet are compiled together (see `Compiler::compile_modules`)
+//! and kept until their import runs them. Only the top-level package of a
+//! dotted name is compiled ahead; a module whose file has changed place by
+//! then, as a new `sys.path` may make it, is compiled again.
+
+use crate::bytecode::{slot_index, Chunk, OpCode};
+use crate::compiler::Compiler;
+use crate::object::{FunctionObject, ModuleObject, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::modules;
+use crate::vm::namespace::Namespace;
+use crate::vm::{InterpretResult, VM};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+impl VM {
+    /// Sets the directories `import` searches for source modules, in the
+    /// order it searches them. An empty string stands for the current
+    /// directory.
+    pub fn set_search_path(&mut self, path: Vec<String>) {
+        self.search_path = path;
+    }
+
+    /// Returns the directories `import` searches, as `sys.path` lists them.
+    pub fn search_path(&self) -> &[String] {
+        &self.search_path
+    }
+
+    /// Returns the module called `name`, loading it on its first import, or
+    /// raises the error that stopped it loading. A name with leading dots is
+    /// relative to the package of the module importing it.
+    pub(super) fn import_module(&mut self, name: &str) -> Result<Object, InterpretResult> {
+        let name = self.absolute_module_name(name)?;
+        self.import_absolute(&name)
+    }
+
+    /// Resolves a relative module name such as `.sibling` or `..` against
+    /// the `__package__` of the running module.
+    fn absolute_module_name(&mut self, name: &str) -> Result<String, InterpretResult> {
+        let relative = name.trim_start_matches('.');
+        let level = name.len() - relative.len();
+        if level == 0 {
+            return Ok(name.to_string());
+        }
+        self.sync_globals();
+        let package = match self.globals.get("__package__").map(|package| &**package) {
+            Some(ObjectType::String(package)) if !package.is_empty() => package.clone(),
+            _ => {
+                let message = "attempted relative import with no known parent package";
+                return Err(self.raise_error("ImportError", message));
+            }
+        };
+        let mut base = package.as_str();
+        for _ in 1..level {
+            let Some((parent, _)) = base.rsplit_once('.') else {
+                let message = "attempted relative import beyond top-level package";
+                return Err(self.raise_error("ImportError", message));
+            };
+            base = parent;
+        }
+        Ok(match relative {
+            "" => base.to_string(),
+            _ => format!("{}.{}", base, relative),
+        })
+    }
+
+    /// Imports the module with the fully qualified name `name`, importing
+    /// the packages it is in first.
+    fn import_absolute(&mut self, name: &str) -> Result<Object, InterpretResult> {
+        if let Some(module) = self.modules.get(name) {
+            return Ok(module.clone());
+        }
+        if let Some(start) = self.loading.iter().position(|loading| loading == name) {
+            if self.inside_package(name) {
+                return Ok(self.module_snapshot(name));
+            }
+            let mut cycle = self.loading[start..].to_vec();
+            cycle.push(name.to_string());
+            let message = format!("circular import: {}", cycle.join(" -> "));
+            return Err(self.raise_error("ImportError", message));
+        }
+        if let Some(module) = modules::native_module(name) {
+            self.modules.insert(name.to_string(), module.clone());
+            return Ok(module);
+        }
+
+        let (directories, leaf) = match name.rsplit_once('.') {
+            Some((package, leaf)) => {
+                self.import_absolute(package)?;
+                let Some(directories) = self.package_path(package) else {
+                    let message =
+                        format!("No module named '{}'; '{}' is not a package", name, package);
+                    return Err(self.raise_error("ModuleNotFoundError", message));
+                };
+                (directories, leaf)
+            }
+            None => (self.search_path.clone(), name),
+        };
+        let Some((path, package)) = find_module(&directories, leaf) else {
+            let message = format!("No module named '{}'", name);
+            return Err(self.raise_error("ModuleNotFoundError", message));
+        };
+        let module = self.load_module(name, &path, package)?;
+        self.modules.insert(name.to_string(), module.clone());
+        if let Some((package, leaf)) = name.rsplit_once('.') {
+            self.set_module_attribute(package, leaf, module.clone());
+        }
+        Ok(module)
+    }
+
+    /// Whether the module being loaded is the package `name` or one of its
+    /// modules, which may import the package before it finishes loading.
+    fn inside_package(&self, name: &str) -> bool {
+        let Some(importer) = self.loading.last() else {
+            return false;
+        };
+        let inside = importer == name
+            || importer
+                .strip_prefix(name)
+                .is_some_and(|rest| rest.starts_with('.'));
+        inside && self.package_path(name).is_some()
+    }
+
+    /// Imports the submodule `name` of `package` for `from package import
+    /// name` when the package has no attribute `name`. Returns None if it
+    /// has no such submodule either.
+    pub(super) fn import_submodule(
+        &mut self,
+        package: &ModuleObject,
+        name: &str,
+    ) -> Option<Result<Object, InterpretResult>> {
+        let qualified = format!("{}.{}", package.name, name);
+        let found = self.modules.contains_key(&qualified)
+            || self
+                .package_path(&package.name)
+                .is_some_and(|directories| find_module(&directories, name).is_some());
+        found.then(|| self.import_absolute(&qualified))
+    }
+
+    /// The directories a source package's modules are found in, as its
+    /// `__path__` lists them, or None if `name` is not a package.
+    fn package_path(&self, name: &str) -> Option<Vec<String>> {
+        let path = self.module_namespace(name)?.get("__path__")?;
+        let ObjectType::List(directories) = &**path else {
+            return None;
+        };
+        directories
+            .iter()
+            .map(|directory| match &**directory {
+                ObjectType::String(directory) => Some(directory.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The globals of the source module `name`, wherever the VM holds them.
+    pub(super) fn module_namespace(&self, name: &str) -> Option<&Namespace> {
+        if name == self.active_module {
+            Some(&self.globals)
+        } else {
+            self.module_globals.get(name)
+        }
+    }
+
+    /// The file the source module `name` was read from, or `name` itself
+    /// if it was not read from one.
+    pub(super) fn module_file(&self, name: &str) -> String {
+        match self.module_files.get(name) {
+            Some(file) => file.clone(),
+            None => name.to_string(),
+        }
+    }
+
+    /// The module object for the globals of the source module `name` as
+    /// they are now.
+    fn module_snapshot(&self, name: &str) -> Object {
+        let attributes = self
+            .module_namespace(name)
+            .map(|globals| {
+                globals
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+            name: name.to_string(),
+            attributes,
+        })))
+    }
+
+    /// Binds `value` to `name` in the globals of the source module `module`,
+    /// as importing a submodule binds it in its package, refreshing the
+    /// module objects later imports get.
+    fn set_module_attribute(&mut self, module: &str, name: &str, value: Object) {
+        let globals = if module == self.active_module {
+            &mut self.globals
+        } else {
+            match self.module_globals.get_mut(module) {
+                Some(globals) => globals,
+                None => return,
+            }
+        };
+        globals.insert(name.to_string(), value);
+        if self.modules.contains_key(module) {
+            let snapshot = self.module_snapshot(module);
+            self.modules.insert(module.to_string(), snapshot.clone());
+            // The package holding this one holds its object too
+            if let Some((package, leaf)) = module.rsplit_once('.') {
+                self.set_module_attribute(package, leaf, snapshot);
+            }
+        }
+    }
+
+    /// Compiles the source modules `chunk` imports, by absolute name, that are
+    /// neither loaded nor compiled yet, keeping them for their imports.
+    pub(super) fn precompile_imports(&mut self, chunk: &Chunk) -> Result<(), InterpretResult> {
+        let mut names = Vec::new();
+        imported_names(chunk, &mut names);
+        let mut found: Vec<(String, PathBuf, String)> = Vec::new();
+        for name in names {
+            if name.starts_with('.') {
+                continue;
+            }
+            let top = name.split('.').next().unwrap_or(&name);
+            if self.modules.contains_key(top)
+                || self.precompiled.contains_key(top)
+                || self.loading.iter().any(|loading| loading == top)
+                || found.iter().any(|(name, _, _)| name == top)
+                || modules::native_module(top).is_some()
+            {
+                continue;
+            }
+            let Some((path, _)) = find_module(&self.search_path, top) else {
+                continue;
+            };
+            if let Ok(source) = fs::read_to_string(&path) {
+                found.push((top.to_string(), path, source));
+            }
+        }
+        if found.is_empty() {
+            return Ok(());
+        }
+        let sources: Vec<(&str, &str)> = found
+            .iter()
+            .map(|(name, _, source)| (name.as_str(), source.as_str()))
+            .collect();
+        let compiled = match Compiler::compile_modules(&sources) {
+            Ok(compiled) => compiled,
+            Err(error) => {
+                let message = format!("can't start new thread: {}", error);
+                return Err(self.raise_error("RuntimeError", message));
+            }
+        };
+        for ((name, path, _), result) in found.into_iter().zip(compiled) {
+            self.precompiled.insert(name, (path, result));
+        }
+        Ok(())
+    }
+
+    /// Compiles and runs the source module `name` read from `path`, the
+    /// `__init__.py` of a package when `package` is set, returning the
+    /// module it defines.
+    fn load_module(
+        &mut self,
+        name: &str,
+        path: &Path,
+        package: bool,
+    ) -> Result<Object, InterpretResult> {
+        let file = path.display().to_string();
+        let compiled = match self.precompiled.remove(name) {
+            Some((compiled_path, compiled)) if compiled_path == path => compiled,
+            _ => match fs::read_to_string(path) {
+                Ok(source) => Compiler::compile_with_errors(&source, name),
+                Err(error) => {
+                    let message = format!("cannot read '{}': {}", file, error);
+                    return Err(self.raise_error("ImportError", message));
+                }
+            },
+        };
+        let chunk = match compiled {
+            Ok(chunk) => chunk,
+            Err(errors) => {
+                let error = &errors[0];
+                let message = format!("{} ({}, line {})", error.message, file, error.line);
+                return Err(self.raise_error(error.kind, message));
+            }
+        };
+
+        let string = |value: &str| Rc::new(ObjectType::String(value.to_string()));
+        let mut namespace = Namespace::default();
+        namespace.insert("__name__".to_string(), string(name));
+        namespace.insert("__file__".to_string(), string(&file));
+        let parent = match name.rsplit_once('.') {
+            Some((parent, _)) => parent,
+            None => "",
+        };
+        if package {
+            let directory = path.parent().unwrap_or(Path::new(""));
+            let directories = vec![string(&directory.display().to_string())];
+            namespace.insert(
+                "__path__".to_string(),
+                Rc::new(ObjectType::List(directories)),
+            );
+            namespace.insert("__package__".to_string(), string(name));
+        } else {
+            namespace.insert("__package__".to_string(), string(parent));
+        }
+        self.module_globals.insert(name.to_string(), namespace);
+        self.module_files.insert(name.to_string(), file);
+        let body = Rc::new(FunctionObject::new(
+            "<script>".to_string(),
+            0,
+            chunk,
+            Vec::new(),
+            name.to_string(),
+        ));
+        if let Err(error) = self.precompile_imports(&body.chunk) {
+            self.module_globals.remove(name);
+            return Err(error);
+        }
+        let importer = self.active_module.clone();
+        self.loading.push(name.to_string());
+        let result = self.call_callback(Rc::new(ObjectType::Function(body)), Vec::new());
+        self.loading.pop();
+        self.switch_globals(&importer);
+        if let Err(error) = result {
+            self.module_globals.remove(name);
+            return Err(error);
+        }
+        Ok(self.module_snapshot(name))
+    }
+
+    /// Makes `globals` those of the module the running frame belongs to,
+    /// if that is a source module other than the one whose globals they are.
+    #[inline]
+    pub(super) fn sync_globals(&mut self) {
+        if self.module_globals.is_empty() {
+            return;
+        }
+        let Some(frame) = self.frames.last() else {
+            return;
+        };
+        if frame.function.module != self.active_module {
+            let module = frame.function.module.clone();
+            self.switch_globals(&module);
+        }
+    }
+
+    /// Swaps in the globals of `module`, putting away those in use, if the
+    /// VM holds globals for it.
+    pub(super) fn switch_globals(&mut self, module: &str) {
+        if module == self.active_module {
+            return;
+        }
+        let Some(globals) = self.module_globals.remove(module) else {
+            return;
+        };
+        let previous = std::mem::replace(&mut self.globals, globals);
+        let previous_module = std::mem::replace(&mut self.active_module, module.to_string());
+        self.module_globals.insert(previous_module, previous);
+    }
+
+    /// Makes `globals` those of `module`: its own if the VM holds them, and
+    /// otherwise the ones in use, which are then `module`'s.
+    pub(super) fn enter_module(&mut self, module: &str) {
+        self.switch_globals(module);
+        if self.active_module != module {
+            self.active_module = module.to_string();
+        }
+    }
+}
+
+/// Adds the module names `chunk` and the functions compiled in it import
+/// to `names`.
+fn imported_names(chunk: &Chunk, names: &mut Vec<String>) {
+    for (_, op, operands) in chunk.instructions() {
+        if !matches!(op, OpCode::OpImport | OpCode::OpImportLong) {
+            continue;
+        }
+        if let Some(ObjectType::String(name)) = chunk
+            .constants
+            .get(slot_index(operands))
+            .map(|name| &**name)
+        {
+            names.push(name.clone());
+        }
+    }
+    for constant in &chunk.constants {
+        if let ObjectType::FunctionPrototype(prototype) = &**constant {
+            imported_names(&prototype.chunk, names);
+        }
+    }
+}
+
+/// Finds the module `name` in the first of `directories` holding either a
+/// package directory `name/__init__.py` or a file `name.py`, the package
+/// winning within one directory. Returns its path and whether it is a
+/// package.
+fn find_module(directories: &[String], name: &str) -> Option<(PathBuf, bool)> {
+    directories.iter().find_map(|directory| {
+        let directory = Path::new(directory);
+        let package = directory.join(name).join("__init__.py");
+        if package.is_file() {
+            return Some((package, true));
+        }
+        let module = directory.join(format!("{}.py", name));
+        module.is_file().then_some((module, false))
+    })
+}
@@ -0,0 +1,33 @@
+//! Interrupting a running script from outside the VM, as Ctrl-C does.
+//!
+//! An embedder hands the VM a flag with `VM::set_interrupt_flag` and sets it
+//! from another thread or a signal handler. The run loop looks at the flag
+//! every `INTERRUPT_CHECK_INTERVAL` instructions, alongside its limit
+//! checks, so a VM with no flag pays nothing for it. Finding it set, the VM
+//! clears it and raises `KeyboardInterrupt` where the script is, which its
+//! handlers and finally blocks see like any other exception.
+
+use super::{InterpretResult, VM};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Instructions run between looks at the interrupt flag.
+pub(super) const INTERRUPT_CHECK_INTERVAL: usize = 1024;
+
+impl VM {
+    /// Interrupts the running script with `KeyboardInterrupt` soon after
+    /// `flag` is set.
+    pub fn set_interrupt_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.interrupt = Some(flag);
+    }
+
+    /// Raises `KeyboardInterrupt` if the interrupt flag has been set since
+    /// the last look, clearing it.
+    pub(super) fn check_interrupt(&mut self) -> Option<InterpretResult> {
+        let flag = self.interrupt.as_ref()?;
+        if !flag.swap(false, Ordering::Relaxed) {
+            return None;
+        }
+        Some(self.raise_error("KeyboardInterrupt", ""))
+    }
+}
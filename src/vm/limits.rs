@@ -0,0 +1,238 @@
+//! Resource limits for running untrusted scripts.
+//!
+//! A VM built with `VM::with_limits` stops with `InterpretResult::LimitExceeded`
+//! once a script runs too many instructions, keeps too many objects alive or
+//! nests calls too deeply. The instruction check is a comparison per
+//! instruction, and the call depth one a comparison per call. Counting live
+//! objects walks everything reachable from the stack and globals, so it only
+//! happens after as many instructions as objects were found last time, which
+//! keeps its cost proportional to the instructions run.
+//!
+//! A timeout set with `VM::set_timeout` stops a run that takes longer than
+//! it in wall-clock time. The clock is read every `CLOCK_CHECK_INTERVAL`
+//! instructions, and not at all without a timeout; wasm32 builds have no
+//! clock to read, so they cannot set one.
+
+use super::VM;
+use crate::object::{ClassObject, Object, ObjectType};
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Instructions run between live object counts when few objects are alive.
+const MIN_HEAP_CHECK_INTERVAL: usize = 1024;
+
+/// Instructions run between readings of the clock while a timeout is set.
+const CLOCK_CHECK_INTERVAL: usize = 1024;
+
+/// Budgets enforced by the VM during each call to `interpret`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Maximum number of instructions executed
+    pub max_instructions: usize,
+    /// Maximum number of objects alive at once
+    pub max_heap_objects: usize,
+    /// Maximum number of nested calls, counting the script itself
+    pub max_call_depth: usize,
+}
+
+impl Limits {
+    /// No limits beyond the VM's built-in recursion depth.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_instructions: usize::MAX,
+            max_heap_objects: usize::MAX,
+            max_call_depth: usize::MAX,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+/// The budget a script ran out of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Instructions,
+    HeapObjects,
+    CallDepth,
+    Time,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Limit::Instructions => "instruction",
+            Limit::HeapObjects => "heap object",
+            Limit::CallDepth => "call depth",
+            Limit::Time => "time",
+        };
+        write!(f, "{} limit exceeded", name)
+    }
+}
+
+impl VM {
+    /// Stops each `interpret` call with `InterpretResult::LimitExceeded`
+    /// once it has run for longer than `timeout`, or never for None.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Schedules the first limit check of a run.
+    pub(super) fn reset_limit_checks(&mut self) {
+        self.instructions_run = 0;
+        self.exceeded_limit = None;
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.schedule_limit_check(0);
+    }
+
+    /// Checks the instruction and heap budgets, returning the one exceeded.
+    /// Called from the run loop once `instructions_run` reaches `next_limit_check`.
+    pub(super) fn check_limits(&mut self) -> Option<Limit> {
+        if self.instructions_run > self.limits.max_instructions {
+            return Some(Limit::Instructions);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Some(Limit::Time);
+        }
+        let mut live_objects = 0;
+        if self.limits.max_heap_objects != usize::MAX {
+            live_objects = self.count_heap_objects();
+            if live_objects > self.limits.max_heap_objects {
+                return Some(Limit::HeapObjects);
+            }
+        }
+        self.schedule_limit_check(live_objects);
+        None
+    }
+
+    fn schedule_limit_check(&mut self, live_objects: usize) {
+        let mut next = self.limits.max_instructions.saturating_add(1);
+        if self.limits.max_heap_objects != usize::MAX {
+            let interval = live_objects.max(MIN_HEAP_CHECK_INTERVAL);
+            next = next.min(self.instructions_run.saturating_add(interval));
+        }
+        if self.interrupt.is_some() {
+            let interval = super::interrupt::INTERRUPT_CHECK_INTERVAL;
+            next = next.min(self.instructions_run.saturating_add(interval));
+        }
+        if self.deadline.is_some() {
+            next = next.min(self.instructions_run.saturating_add(CLOCK_CHECK_INTERVAL));
+        }
+        self.next_limit_check = next;
+    }
+
+    /// Stops the run because `limit` was exceeded. A run nested in an
+    /// instruction, as a sort key's is, stops the runs around it at their
+    /// next limit check, which is brought forward to the next instruction.
+    pub(super) fn limit_exceeded(&mut self, limit: Limit) -> super::InterpretResult {
+        self.exceeded_limit = Some(limit);
+        self.next_limit_check = 0;
+        super::InterpretResult::LimitExceeded
+    }
+
+    /// Refuses a call that would make one frame too many: it stops the run
+    /// if the frames would pass the call depth budget, and otherwise raises
+    /// RecursionError.
+    #[cold]
+    pub(super) fn frame_limit_reached(&mut self) -> super::InterpretResult {
+        if self.frames.len() >= self.limits.max_call_depth {
+            return self.limit_exceeded(Limit::CallDepth);
+        }
+        self.raise_error("RecursionError", "maximum recursion depth exceeded")
+    }
+
+    /// The number of active frames at which a call fails instead of pushing
+    /// its own: the recursion limit or the call depth limit, whichever is lower.
+    #[inline]
+    pub(super) fn frame_limit(&self) -> usize {
+        self.recursion_limit.min(self.limits.max_call_depth)
+    }
+
+    /// Counts the distinct objects reachable from the stack, the globals of
+    /// each module and the closures of active frames.
+    pub(super) fn count_heap_objects(&self) -> usize {
+        let mut classes_seen: HashSet<usize> = HashSet::new();
+        let mut pending: Vec<Object> = (0..self.stack.top())
+            .map(|slot| self.stack.get(slot).clone())
+            .collect();
+        pending.extend(self.globals.values().cloned());
+        for globals in self.module_globals.values() {
+            pending.extend(globals.values().cloned());
+        }
+        for frame in &self.frames {
+            for upvalue in &frame.function.upvalues {
+                let upvalue = upvalue.borrow();
+                if upvalue.is_closed {
+                    pending.push(upvalue.closed.clone());
+                }
+            }
+        }
+
+        let mut visit_class = |class: &ClassObject, pending: &mut Vec<Object>| {
+            let mut class = Some(class);
+            while let Some(current) = class {
+                if !classes_seen.insert(current as *const ClassObject as usize) {
+                    break;
+                }
+                pending.extend(current.methods.values().cloned());
+                class = current.parent.as_deref();
+            }
+        };
+
+        let mut count = 0;
+        let mut visited: HashSet<usize> = HashSet::new();
+        while let Some(object) = pending.pop() {
+            if !visited.insert(&*object as *const ObjectType as usize) {
+                continue;
+            }
+            count += 1;
+            match &*object {
+                ObjectType::List(values) | ObjectType::Tuple(values) | ObjectType::Set(values) => {
+                    pending.extend(values.iter().cloned());
+                }
+                ObjectType::Dict(entries) => {
+                    for (key, value) in entries {
+                        pending.push(key.clone());
+                        pending.push(value.clone());
+                    }
+                }
+                ObjectType::Function(function) => {
+                    for upvalue in &function.upvalues {
+                        let upvalue = upvalue.borrow();
+                        if upvalue.is_closed {
+                            pending.push(upvalue.closed.clone());
+                        }
+                    }
+                    pending.extend(function.default_values.iter().flatten().cloned());
+                }
+                ObjectType::Class(class) => visit_class(class, &mut pending),
+                ObjectType::Instance(instance) => {
+                    let instance = instance.borrow();
+                    pending.extend(instance.fields.iter().map(|(_, value)| value.clone()));
+                    visit_class(&instance.class, &mut pending);
+                }
+                ObjectType::BoundMethod(receiver, method) => {
+                    pending.push(receiver.clone());
+                    pending.push(method.clone());
+                }
+                ObjectType::SuperProxy(receiver, class) => {
+                    pending.push(receiver.clone());
+                    visit_class(class, &mut pending);
+                }
+                ObjectType::ClassMethod(function) => pending.push(function.clone()),
+                ObjectType::Module(module) => {
+                    pending.extend(module.attributes.values().cloned());
+                }
+                _ => {}
+            }
+        }
+        count
+    }
+}
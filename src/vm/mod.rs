@@ -1,28 +1,121 @@
 mod bytecode_reader;
 mod call_frame;
+mod collection_methods;
 pub mod collections;
+pub mod completion;
+pub mod coverage;
+mod evaluation;
+pub mod exceptions;
+mod formatting;
 mod function_calls;
+pub mod gc;
+mod generators;
+pub mod hashing;
+pub(crate) mod help;
+pub mod hooks;
+mod import;
+mod interrupt;
+pub mod limits;
+pub mod modules;
+pub mod namespace;
 pub mod native;
 mod opcode_dispatcher;
 pub mod opcodes;
+pub mod profile;
+mod protocols;
+mod reflection;
 mod return_handler;
+pub mod runtime_error;
+mod sorting;
 mod stack_ops;
+mod string_methods;
 mod string_repr;
+mod tail_calls;
 mod upvalues;
 pub mod values;
 
 use crate::bytecode::{Chunk, OpCode};
+use crate::compiler::CompiledModule;
 use crate::object::{FunctionObject, Object, ObjectType, UpvalueRef};
-use call_frame::CallFrame;
-use stack_ops::Stack;
+use crate::platform::Output;
+use crate::sync::Rc;
+use call_frame::{CallFrame, FRAMES_MAX};
+use limits::{Limit, Limits};
+use modules::warnings::WarningAction;
+use namespace::{GlobalCache, Namespace};
+use runtime_error::{RuntimeError, TraceFrame};
+use stack_ops::{Stack, StackFault};
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use values::SharedValues;
 
 pub struct VM {
     stack: Stack,
-    globals: HashMap<String, Object>,
+    globals: Namespace,
+    builtins: Namespace,
+    /// The values `OpGetGlobal` found last, while `globals` and `builtins`
+    /// stay as they were
+    global_cache: GlobalCache,
+    /// The small integers and booleans arithmetic and comparisons produce
+    shared: SharedValues,
+    /// Modules imported so far, so every import of a name shares one object
+    modules: Namespace,
+    /// Directories searched for source modules, as `sys.path` lists them
+    search_path: Vec<String>,
+    /// Globals of the source modules other than the one in `globals`
+    module_globals: HashMap<String, Namespace>,
+    /// The module whose globals are in `globals`
+    active_module: String,
+    /// Source modules whose bodies are running, outermost first
+    loading: Vec<String>,
+    /// Source modules compiled ahead of their import, with the file each
+    /// was read from
+    precompiled: HashMap<String, (std::path::PathBuf, CompiledModule)>,
     frames: Vec<CallFrame>,
     open_upvalues: Vec<UpvalueRef>,
+    last_error: Option<RuntimeError>,
+    /// The exception object behind `last_error`, when one was raised
+    raised: Option<Object>,
+    /// Handlers installed by the `try` statements being run, innermost last
+    handlers: Vec<exceptions::Handler>,
+    /// Exceptions being handled by except clauses, innermost last
+    caught: Vec<exceptions::Raised>,
+    /// Exceptions held by the finally blocks being run, innermost last
+    pending: Vec<Option<exceptions::Raised>>,
+    limits: Limits,
+    instructions_run: usize,
+    /// Most frames active at once, set by `sys.setrecursionlimit`
+    recursion_limit: usize,
+    /// Addresses of the containers whose repr is being rendered, outermost
+    /// first; see `container_repr`
+    repr_containers: Vec<usize>,
+    next_limit_check: usize,
+    exceeded_limit: Option<Limit>,
+    /// Wall-clock budget of each run, set by `set_timeout`
+    timeout: Option<Duration>,
+    /// When the current run's timeout runs out
+    deadline: Option<Instant>,
+    profile: Option<profile::Profile>,
+    hooks: hooks::Hooks,
+    /// Whether instructions go through `dispatch_instrumented`
+    instrumented: bool,
+    deterministic: bool,
+    /// Whether self tail calls reuse their caller's frame
+    tail_calls: bool,
+    /// Where `print` writes
+    output: Output,
+    warnings: modules::warnings::Warnings,
+    /// Set from outside to raise KeyboardInterrupt; see `set_interrupt_flag`
+    interrupt: Option<Arc<AtomicBool>>,
+    /// The dict the last `eval()` or `exec()` passed one bound its names in,
+    /// for `OpEvaluatedNamespace`
+    evaluated_namespace: Option<Object>,
+    /// Path of the script being run, set by `set_script_file`
+    script_file: Option<String>,
+    /// Files the source modules run so far were read from, by module name
+    module_files: HashMap<String, String>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -30,6 +123,9 @@ pub enum InterpretResult {
     Ok,
     CompileError,
     RuntimeError,
+    /// A budget set with `VM::with_limits` or `VM::set_timeout` ran out; see
+    /// `VM::exceeded_limit`.
+    LimitExceeded,
 }
 
 impl Default for VM {
@@ -42,49 +138,346 @@ impl VM {
     pub fn new() -> Self {
         let mut vm = VM {
             stack: Stack::new(),
-            globals: HashMap::new(),
-            frames: Vec::new(),
+            globals: Namespace::default(),
+            builtins: Namespace::default(),
+            global_cache: GlobalCache::new(),
+            shared: SharedValues::new(),
+            modules: Namespace::default(),
+            search_path: Vec::new(),
+            module_globals: HashMap::new(),
+            active_module: "<script>".to_string(),
+            loading: Vec::new(),
+            precompiled: HashMap::new(),
+            frames: Vec::with_capacity(FRAMES_MAX),
             open_upvalues: Vec::new(),
+            last_error: None,
+            raised: None,
+            handlers: Vec::new(),
+            caught: Vec::new(),
+            pending: Vec::new(),
+            limits: Limits::unlimited(),
+            instructions_run: 0,
+            recursion_limit: FRAMES_MAX,
+            repr_containers: Vec::new(),
+            next_limit_check: usize::MAX,
+            exceeded_limit: None,
+            timeout: None,
+            deadline: None,
+            profile: None,
+            hooks: hooks::Hooks::default(),
+            instrumented: false,
+            deterministic: false,
+            tail_calls: false,
+            output: Output::default(),
+            warnings: modules::warnings::Warnings::default(),
+            interrupt: None,
+            evaluated_namespace: None,
+            script_file: None,
+            module_files: HashMap::new(),
         };
         vm.register_builtins();
         vm
     }
 
+    /// Creates a VM that stops with `InterpretResult::LimitExceeded` once a
+    /// script runs more than `max_instructions` instructions, keeps more than
+    /// `max_heap_objects` objects alive or nests calls deeper than
+    /// `max_call_depth`. The budgets apply to each call to `interpret`.
+    pub fn with_limits(
+        max_instructions: usize,
+        max_heap_objects: usize,
+        max_call_depth: usize,
+    ) -> Self {
+        let mut vm = Self::new();
+        vm.limits = Limits {
+            max_instructions,
+            max_heap_objects,
+            max_call_depth,
+        };
+        vm
+    }
+
+    /// Makes every run of the same script print the same output.
+    ///
+    /// Object addresses in reprs become ids numbered from the start of each
+    /// `interpret` call, for values formatted on this thread until a VM that
+    /// is not deterministic runs. The builtins are all pure functions of
+    /// their arguments, with no access to the clock, the environment or
+    /// random seeds, so they need no further restriction.
+    pub fn set_deterministic(&mut self, enabled: bool) {
+        self.deterministic = enabled;
+    }
+
+    /// Keeps what scripts print for `take_output` instead of writing it to
+    /// standard output.
+    pub fn capture_output(&mut self) {
+        self.set_output(Output::Captured(String::new()));
+    }
+
+    /// Sends what scripts print to `output`.
+    pub fn set_output(&mut self, output: Output) {
+        self.output = output;
+    }
+
+    /// Returns what scripts have printed since output was captured or last
+    /// taken.
+    pub fn take_output(&mut self) -> String {
+        self.output.take()
+    }
+
+    /// Chooses what `warnings.warn` does with a warning: write it once for
+    /// each place it is raised from, as it does by default, write every
+    /// one, ignore them or raise them as exceptions.
+    pub fn set_warning_action(&mut self, action: WarningAction) {
+        self.warnings.action = action;
+    }
+
+    /// Sends the warnings scripts raise to `output` rather than to standard
+    /// error.
+    pub fn set_warning_output(&mut self, output: Output) {
+        self.warnings.output = output;
+    }
+
+    /// Returns the warnings written since warning output was captured or
+    /// last taken.
+    pub fn take_warnings(&mut self) -> String {
+        self.warnings.output.take()
+    }
+
+    /// Sets the path of the script the VM runs, which its `__file__` holds
+    /// and its tracebacks and warnings name in place of its module.
+    pub fn set_script_file(&mut self, path: &str) {
+        self.script_file = Some(path.to_string());
+    }
+
+    /// Turns profiling on or off. While it is on, each `interpret` call
+    /// records a fresh `Profile` of the script, at some cost in speed.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(profile::Profile::default);
+        self.update_instrumented();
+    }
+
+    /// Returns the profile of the most recent `interpret` call, if profiling
+    /// is on.
+    pub fn profile(&self) -> Option<&profile::Profile> {
+        self.profile.as_ref()
+    }
+
     fn register_builtins(&mut self) {
-        native::register_builtins(&mut self.globals);
+        native::register_builtins(&mut self.builtins);
+        exceptions::register_exceptions(&mut self.builtins);
     }
 
+    /// Runs `chunk` as a script, the program's entry point, whose
+    /// `__name__` is `"__main__"`.
+    ///
+    /// The bytecode need not come from the compiler: invalid opcodes, constant
+    /// indices, jumps and stack accesses are reported as a `SystemError` rather
+    /// than causing a panic.
     pub fn interpret(&mut self, chunk: Chunk) -> InterpretResult {
+        self.run_chunk(chunk, "<script>", "__main__")
+    }
+
+    /// Runs `chunk`, compiled by `Compiler::compile_with_module` under the
+    /// same `name`, as that module rather than as the entry point: its
+    /// `__name__` is `name`, so code under `if __name__ == '__main__':` is
+    /// skipped.
+    pub fn interpret_module(&mut self, chunk: Chunk, name: &str) -> InterpretResult {
+        self.run_chunk(chunk, name, name)
+    }
+
+    /// Runs `chunk` as the body of `module`, with `__name__` bound to `name`.
+    fn run_chunk(&mut self, chunk: Chunk, module: &str, name: &str) -> InterpretResult {
         self.stack.reset();
         self.frames.clear();
         self.open_upvalues.clear();
+        self.last_error = None;
+        self.raised = None;
+        self.handlers.clear();
+        self.caught.clear();
+        self.pending.clear();
+        self.reset_limit_checks();
+        crate::object::set_deterministic_addresses(self.deterministic);
 
+        self.enter_module(module);
+        self.globals.insert(
+            "__name__".to_string(),
+            Rc::new(ObjectType::String(name.to_string())),
+        );
+        if let Some(file) = &self.script_file {
+            self.module_files.insert(module.to_string(), file.clone());
+            let file = Rc::new(ObjectType::String(file.clone()));
+            self.globals.insert("__file__".to_string(), file);
+        }
         let script_function = Rc::new(FunctionObject::new(
             "<script>".to_string(),
             0,
             chunk,
             Vec::new(),
-            "<script>".to_string(),
+            module.to_string(),
         ));
         self.push(Rc::new(ObjectType::Function(script_function.clone())));
+        if let Some(profile) = &mut self.profile {
+            *profile = profile::Profile::default();
+            profile.enter(&script_function);
+        }
+        let chunk = script_function.chunk.clone();
         self.frames
-            .push(CallFrame::new(script_function, 0, None, None));
+            .push(CallFrame::new(script_function, 0, false, None));
+        self.script_started();
+        if let Err(error) = self.precompile_imports(&chunk) {
+            return error;
+        }
+
+        let result = self.run();
+        // An error may have stopped the run inside an imported module
+        self.switch_globals(module);
+        result
+    }
+
+    /// Returns the limit that stopped the most recent `interpret` call, if any.
+    pub fn exceeded_limit(&self) -> Option<Limit> {
+        self.exceeded_limit
+    }
+
+    /// Returns the error recorded by the most recent failed `interpret` call.
+    pub fn last_error(&self) -> Option<&RuntimeError> {
+        self.last_error.as_ref()
+    }
 
-        self.run()
+    /// Records a runtime error along with the current call stack.
+    pub(super) fn raise_error(
+        &mut self,
+        kind: &str,
+        message: impl Into<String>,
+    ) -> InterpretResult {
+        let mut error = RuntimeError::new(kind, message);
+        error.traceback = self
+            .frames
+            .iter()
+            .map(|frame| TraceFrame {
+                module: frame.function.module.clone(),
+                file: self.module_file(&frame.function.module),
+                function: if frame.function.name == "<script>" {
+                    "<module>".to_string()
+                } else {
+                    frame.function.qualname.clone()
+                },
+                line: frame.chunk.line_at(frame.ip.saturating_sub(1)),
+            })
+            .collect();
+        self.last_error = Some(error);
+        self.raised = None;
+        InterpretResult::RuntimeError
+    }
+
+    /// Reports an instruction whose constant operand is outside the pool.
+    pub(super) fn bad_constant(&mut self) -> InterpretResult {
+        self.raise_error("SystemError", "constant index out of range")
+    }
+
+    /// Reports an instruction whose operands or stack are not what the
+    /// compiler emits for it, which only hand-built bytecode runs into.
+    pub(super) fn bad_operand(&mut self) -> InterpretResult {
+        self.raise_error("SystemError", "bad operand for instruction")
     }
 
     fn run(&mut self) -> InterpretResult {
-        loop {
-            if self.frames.is_empty() {
-                return InterpretResult::Ok;
+        match self.run_frames_above(0) {
+            Ok(()) => InterpretResult::Ok,
+            Err(result) => result,
+        }
+    }
+
+    /// Runs the current frame's next instruction, with the budget, interrupt
+    /// and stack checks every instruction goes through, letting the handlers
+    /// of the frames above the first `base_frames` catch what it raises.
+    /// Returns the result stopping the run, or None to go on.
+    ///
+    /// Both the run loop and the nested loops running callbacks and
+    /// generator bodies step through here, so a script cannot escape its
+    /// limits by spinning inside a sort key. They share the one loop in
+    /// `run_frames_above`, the only place this is inlined into, so the
+    /// dispatch is compiled once and runs without a call per instruction.
+    #[inline(always)]
+    fn step(&mut self, base_frames: usize) -> Option<InterpretResult> {
+        self.instructions_run += 1;
+        // Limits, interrupts and the clock are all checked behind this one
+        // comparison, brought forward to the next instruction when a limit
+        // was exceeded in a nested run
+        if self.instructions_run >= self.next_limit_check {
+            if let Some(limit) = self.exceeded_limit.or_else(|| self.check_limits()) {
+                return Some(self.limit_exceeded(limit));
             }
+            if let Some(result) = self.check_interrupt() {
+                return (!self.catch(base_frames)).then_some(result);
+            }
+        }
 
-            let instruction = OpCode::from(self.read_byte());
-            let result = self.dispatch_opcode(instruction);
+        #[cfg(debug_assertions)]
+        self.check_statement_stack();
 
-            if result != InterpretResult::Ok {
-                return result;
-            }
+        let byte = self.read_byte();
+        let Some(instruction) = OpCode::from_byte(byte) else {
+            return Some(self.raise_error("SystemError", format!("invalid opcode {}", byte)));
+        };
+        let result = if self.instrumented {
+            self.dispatch_instrumented(instruction)
+        } else {
+            self.dispatch_opcode(instruction)
+        };
+        if result == InterpretResult::Ok && !self.stack.has_fault() {
+            return None;
+        }
+        self.instruction_failed(result, base_frames)
+    }
+
+    /// Finishes an instruction that failed or faulted the stack: reports the
+    /// fault, stops for an exceeded limit, or hands an error to the handler
+    /// that catches it. Returns None if one did.
+    #[cold]
+    fn instruction_failed(
+        &mut self,
+        result: InterpretResult,
+        base_frames: usize,
+    ) -> Option<InterpretResult> {
+        if let Some(fault) = self.stack.take_fault() {
+            return Some(match fault {
+                StackFault::Overflow => self.raise_error("MemoryError", "operand stack overflow"),
+                StackFault::OutOfRange => {
+                    self.raise_error("SystemError", "stack access out of range")
+                }
+            });
+        }
+        // A limit exceeded in a nested loop stops every loop around it
+        if self.exceeded_limit.is_some() {
+            return Some(InterpretResult::LimitExceeded);
         }
+        if result == InterpretResult::RuntimeError && self.catch(base_frames) {
+            return None;
+        }
+        Some(result)
+    }
+
+    /// Checks, in debug builds, that a statement about to start finds the
+    /// stack holding only its frame's slots and the values of the for loops
+    /// around it, so compiler bugs that leave values behind fail where they
+    /// happen instead of corrupting later statements.
+    #[cfg(debug_assertions)]
+    fn check_statement_stack(&self) {
+        let Some(frame) = self.frames.last() else {
+            return;
+        };
+        let Some(depth) = frame.chunk.statement_depth_at(frame.ip) else {
+            return;
+        };
+        let expected = frame.slot + 1 + frame.function.arity + frame.function.local_count + depth;
+        assert_eq!(
+            self.stack.top(),
+            expected,
+            "stack height at the statement starting at offset {} of {}",
+            frame.ip,
+            frame.function.name
+        );
     }
 }
@@ -0,0 +1,133 @@
+//! The native `copy` module: `copy` for shallow copies and `deepcopy` for
+//! recursive ones.
+//!
+//! Lists and dicts are copied into new containers and instances into new
+//! instances of the same class with their own fields. Strings, numbers,
+//! tuples of them, functions, classes and modules cannot be changed in place,
+//! so they are returned as they are. `deepcopy` remembers what it has already
+//! copied, so an object reached twice is copied once and a cycle through an
+//! instance is rebuilt as a cycle among the copies. Natives cannot run
+//! bytecode, so `__copy__` and `__deepcopy__` methods are not consulted.
+
+use crate::object::{
+    ClassObject, InstanceObject, ModuleObject, NativeError, NativeFn, NativeResult, Object,
+    ObjectType,
+};
+use crate::sync::Rc;
+use crate::vm::gc;
+use std::collections::HashMap;
+
+/// Builds the `copy` module object.
+pub fn module() -> Object {
+    let functions: [(&str, NativeFn); 2] = [("copy", copy_copy), ("deepcopy", copy_deepcopy)];
+    Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+        name: "copy".to_string(),
+        attributes: functions
+            .iter()
+            .map(|(name, func)| {
+                (
+                    name.to_string(),
+                    Rc::new(ObjectType::NativeFunction(name.to_string(), *func)),
+                )
+            })
+            .collect(),
+    })))
+}
+
+/// `copy.copy(x)`: a new container holding the same items, or a new instance
+/// holding the same field values.
+fn copy_copy(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "copy() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    shallow_copy(value)
+}
+
+/// `copy.deepcopy(x)`: copies `x` and, recursively, everything it holds.
+fn copy_deepcopy(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "deepcopy() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    deep_copy(value, &mut HashMap::new())
+}
+
+/// A shallow copy of `value`, as `copy.copy()`, `list.copy()` and
+/// `dict.copy()` make.
+pub fn shallow_copy(value: &Object) -> NativeResult {
+    match &**value {
+        ObjectType::List(items) => Ok(Rc::new(ObjectType::List(items.clone()))),
+        ObjectType::Dict(entries) => Ok(Rc::new(ObjectType::Dict(entries.clone()))),
+        ObjectType::Instance(instance) => {
+            let instance = instance.borrow();
+            let copy = InstanceObject {
+                class: instance.class.clone(),
+                fields: instance.fields.clone(),
+            };
+            Ok(Rc::new(ObjectType::Instance(gc::new_instance(copy))))
+        }
+        ObjectType::Iterator(_) => Err(uncopyable(value)),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// A deep copy of `value`. `memo` maps the address of each list, tuple,
+/// dict and instance already copied to its copy.
+fn deep_copy(value: &Object, memo: &mut HashMap<usize, Object>) -> NativeResult {
+    let address = match &**value {
+        ObjectType::Instance(instance) => Rc::as_ptr(instance) as usize,
+        _ => Rc::as_ptr(value) as usize,
+    };
+    if let Some(copy) = memo.get(&address) {
+        return Ok(copy.clone());
+    }
+
+    let copy = match &**value {
+        ObjectType::List(items) => Rc::new(ObjectType::List(deep_copy_all(items, memo)?)),
+        ObjectType::Tuple(items) => Rc::new(ObjectType::Tuple(deep_copy_all(items, memo)?)),
+        ObjectType::Set(items) => Rc::new(ObjectType::Set(deep_copy_all(items, memo)?)),
+        ObjectType::Dict(entries) => {
+            let mut copied = Vec::with_capacity(entries.len());
+            for (key, item) in entries {
+                copied.push((deep_copy(key, memo)?, deep_copy(item, memo)?));
+            }
+            Rc::new(ObjectType::Dict(copied.into()))
+        }
+        ObjectType::Instance(instance) => {
+            let (class, fields) = {
+                let instance = instance.borrow();
+                (instance.class.clone(), instance.fields.clone())
+            };
+            // Remembered before its fields are copied, so a field leading
+            // back to the instance finds this copy
+            let copy = gc::new_instance(InstanceObject::new(class));
+            let object = Rc::new(ObjectType::Instance(copy.clone()));
+            memo.insert(address, object.clone());
+            for (name, field) in fields {
+                let field = deep_copy(&field, memo)?;
+                copy.borrow_mut().set_field(name, field);
+            }
+            return Ok(object);
+        }
+        ObjectType::Iterator(_) => return Err(uncopyable(value)),
+        _ => return Ok(value.clone()),
+    };
+    memo.insert(address, copy.clone());
+    Ok(copy)
+}
+
+fn deep_copy_all(
+    items: &[Object],
+    memo: &mut HashMap<usize, Object>,
+) -> Result<Vec<Object>, NativeError> {
+    items.iter().map(|item| deep_copy(item, memo)).collect()
+}
+
+fn uncopyable(value: &Object) -> NativeError {
+    NativeError::type_error(format!("cannot pickle '{}' object", value.type_name()))
+}
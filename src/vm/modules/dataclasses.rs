@@ -0,0 +1,213 @@
+//! The native `dataclasses` module: a `dataclass` decorator that reads a
+//! class's annotated fields and fills in `__init__`, `__repr__` and `__eq__`.
+
+use crate::bytecode::{Chunk, OpCode};
+use crate::object::{
+    ClassObject, FunctionObject, ModuleObject, NativeError, NativeFn, NativeResult, Object,
+    ObjectType, TypeInfo,
+};
+use crate::sync::Rc;
+use crate::vm::opcodes::comparison::op_equal;
+use crate::vm::values;
+use std::collections::HashMap;
+
+/// Builds the `dataclasses` module object.
+pub fn module() -> Object {
+    Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+        name: "dataclasses".to_string(),
+        attributes: HashMap::from([(
+            "dataclass".to_string(),
+            Rc::new(ObjectType::NativeFunction(
+                "dataclass".to_string(),
+                dataclass as NativeFn,
+            )),
+        )]),
+    })))
+}
+
+/// `dataclass(cls)`: returns a copy of `cls` with the generated methods it
+/// does not define itself.
+fn dataclass(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [class] = args else {
+        return Err(NativeError::type_error(format!(
+            "dataclass() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    let ObjectType::Class(class) = &**class else {
+        return Err(NativeError::type_error(format!(
+            "dataclass() argument must be a class, not '{}'",
+            class.type_name()
+        )));
+    };
+
+    let fields = fields(class)?;
+    let mut methods = class.methods.clone();
+    if !methods.contains_key("__init__") {
+        methods.insert("__init__".to_string(), init_method(class, &fields));
+    }
+    for (name, func) in [
+        ("__repr__", dataclass_repr as NativeFn),
+        ("__eq__", dataclass_eq),
+    ] {
+        methods
+            .entry(name.to_string())
+            .or_insert_with(|| Rc::new(ObjectType::NativeFunction(name.to_string(), func)));
+    }
+
+    let generated = ClassObject {
+        name: class.name.clone(),
+        methods,
+        parent: class.parent.clone(),
+    };
+    Ok(Rc::new(ObjectType::Class(Rc::new(generated))))
+}
+
+/// The annotated fields of `class` and its ancestors, base classes first,
+/// paired with their defaults.
+fn fields(class: &ClassObject) -> Result<Vec<(String, Option<Object>)>, String> {
+    let mut chain = vec![class];
+    while let Some(parent) = &chain[chain.len() - 1].parent {
+        chain.push(parent);
+    }
+
+    let mut fields: Vec<(String, Option<Object>)> = Vec::new();
+    for ancestor in chain.into_iter().rev() {
+        let Some(ObjectType::Dict(annotations)) =
+            ancestor.methods.get("__annotations__").map(|a| &**a)
+        else {
+            continue;
+        };
+        for (name, _) in annotations {
+            let ObjectType::String(name) = &**name else {
+                continue;
+            };
+            let default = class.get_method(name);
+            match fields.iter_mut().find(|(field, _)| field == name) {
+                Some(field) => field.1 = default,
+                None => fields.push((name.clone(), default)),
+            }
+        }
+    }
+
+    let mut seen_default = false;
+    for (name, default) in &fields {
+        if default.is_some() {
+            seen_default = true;
+        } else if seen_default {
+            return Err(format!(
+                "non-default argument '{}' follows default argument",
+                name
+            ));
+        }
+    }
+    Ok(fields)
+}
+
+/// Builds `__init__(self, <fields>)`, storing each argument on `self`.
+fn init_method(class: &ClassObject, fields: &[(String, Option<Object>)]) -> Object {
+    let mut chunk = Chunk::new();
+    for (index, (name, _)) in fields.iter().enumerate() {
+        chunk.code.push(OpCode::OpGetLocal as u8);
+        chunk.code.push(1);
+        chunk.code.push(OpCode::OpGetLocal as u8);
+        chunk.code.push((index + 2) as u8);
+        chunk.code.push(OpCode::OpSetAttr as u8);
+        chunk.code.push(chunk.constants.len() as u8);
+        chunk
+            .constants
+            .push(Rc::new(ObjectType::String(name.clone())));
+    }
+    chunk.code.push(OpCode::OpConstant as u8);
+    chunk.code.push(chunk.constants.len() as u8);
+    chunk.constants.push(Rc::new(ObjectType::Nil));
+    chunk.code.push(OpCode::OpReturn as u8);
+
+    let mut parameter_names = vec!["self".to_string()];
+    parameter_names.extend(fields.iter().map(|(name, _)| name.clone()));
+    let mut default_values = vec![None];
+    default_values.extend(fields.iter().map(|(_, default)| default.clone()));
+    let type_info = TypeInfo {
+        parameter_types: vec![None; parameter_names.len()],
+        parameter_names,
+        return_type: None,
+        default_values,
+    };
+
+    // The generated method belongs to the module the class was defined in
+    let module = class
+        .methods
+        .values()
+        .find_map(|method| match &**method {
+            ObjectType::Function(function) => Some(function.module.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| "<script>".to_string());
+
+    let mut init = FunctionObject::new_with_types(
+        "__init__".to_string(),
+        fields.len() + 1,
+        chunk,
+        Vec::new(),
+        type_info,
+        module,
+    );
+    init.qualname = format!("{}.__init__", class.name);
+    Rc::new(ObjectType::Function(Rc::new(init)))
+}
+
+/// The generated `__repr__`: `Name(field=value, ...)`.
+fn dataclass_repr(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let Some(ObjectType::Instance(instance)) = args.first().map(|a| &**a) else {
+        return Err(NativeError::type_error(
+            "__repr__() requires a dataclass instance",
+        ));
+    };
+    let instance = instance.borrow();
+    let parts: Vec<String> = fields(&instance.class)?
+        .iter()
+        .map(|(name, _)| {
+            let value = instance
+                .get_field(name)
+                .or_else(|| instance.class.get_method(name))
+                .unwrap_or_else(|| Rc::new(ObjectType::Nil));
+            match &*value {
+                ObjectType::String(text) => format!("{}='{}'", name, text),
+                _ => format!("{}={}", name, value),
+            }
+        })
+        .collect();
+    Ok(Rc::new(ObjectType::String(format!(
+        "{}({})",
+        instance.class.name,
+        parts.join(", ")
+    ))))
+}
+
+/// The generated `__eq__`: instances of the same class with equal fields.
+fn dataclass_eq(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [left, right] = args else {
+        return Err(NativeError::type_error(format!(
+            "__eq__() takes exactly one argument ({} given)",
+            args.len().saturating_sub(1)
+        )));
+    };
+    let (ObjectType::Instance(left), ObjectType::Instance(right)) = (&**left, &**right) else {
+        return Ok(values::boolean(false));
+    };
+    if Rc::ptr_eq(left, right) {
+        return Ok(values::boolean(true));
+    }
+    let (left, right) = (left.borrow(), right.borrow());
+    if !Rc::ptr_eq(&left.class, &right.class) {
+        return Ok(values::boolean(false));
+    }
+    let equal = fields(&left.class)?.iter().all(|(name, _)| {
+        match (left.get_field(name), right.get_field(name)) {
+            (Some(a), Some(b)) => op_equal(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    });
+    Ok(values::boolean(equal))
+}
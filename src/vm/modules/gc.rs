@@ -0,0 +1,70 @@
+//! The native `gc` module: `collect`, `enable`, `disable` and `isenabled`,
+//! controlling the cycle collector in `vm::gc`.
+
+use crate::object::{ClassObject, ModuleObject, NativeFn, NativeResult, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::gc as collector;
+use crate::vm::values;
+
+/// Builds the `gc` module object.
+pub fn module() -> Object {
+    let functions: [(&str, NativeFn); 4] = [
+        ("collect", gc_collect),
+        ("enable", gc_enable),
+        ("disable", gc_disable),
+        ("isenabled", gc_isenabled),
+    ];
+    Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+        name: "gc".to_string(),
+        attributes: functions
+            .iter()
+            .map(|(name, func)| {
+                (
+                    name.to_string(),
+                    Rc::new(ObjectType::NativeFunction(name.to_string(), *func)),
+                )
+            })
+            .collect(),
+    })))
+}
+
+fn no_arguments(name: &str, args: &[Object]) -> Result<(), String> {
+    if args.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "{}() takes no arguments ({} given)",
+        name,
+        args.len()
+    ))
+}
+
+/// `gc.collect()`: frees unreachable cycles, returning how many containers
+/// were unreachable.
+fn gc_collect(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    no_arguments("collect", args)?;
+    let collected = collector::collect();
+    Ok(values::integer(
+        i64::try_from(collected).unwrap_or(i64::MAX),
+    ))
+}
+
+/// `gc.enable()`: turns automatic collection on.
+fn gc_enable(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    no_arguments("enable", args)?;
+    collector::set_enabled(true);
+    Ok(Rc::new(ObjectType::Nil))
+}
+
+/// `gc.disable()`: turns automatic collection off.
+fn gc_disable(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    no_arguments("disable", args)?;
+    collector::set_enabled(false);
+    Ok(Rc::new(ObjectType::Nil))
+}
+
+/// `gc.isenabled()`: whether automatic collection is on.
+fn gc_isenabled(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    no_arguments("isenabled", args)?;
+    Ok(Rc::new(ObjectType::Boolean(collector::is_enabled())))
+}
@@ -0,0 +1,28 @@
+//! Native modules made available to `import`.
+
+pub(crate) mod copy;
+mod dataclasses;
+mod gc;
+pub mod pprint;
+mod re;
+mod regex;
+mod sys;
+pub(crate) mod traceback;
+pub mod warnings;
+
+use crate::object::Object;
+
+/// Builds the native module called `name`, if there is one.
+pub fn native_module(name: &str) -> Option<Object> {
+    match name {
+        "copy" => Some(copy::module()),
+        "dataclasses" => Some(dataclasses::module()),
+        "gc" => Some(gc::module()),
+        "pprint" => Some(pprint::module()),
+        "re" => Some(re::module()),
+        "sys" => Some(sys::module()),
+        "traceback" => Some(traceback::module()),
+        "warnings" => Some(warnings::module()),
+        _ => None,
+    }
+}
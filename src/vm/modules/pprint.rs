@@ -0,0 +1,447 @@
+//! The native `pprint` module: `pformat`, `pprint` and `pp` lay out nested
+//! lists, tuples, dicts and long strings over several indented lines once
+//! their repr is wider than the line.
+//!
+//! The layout follows CPython's `PrettyPrinter`: a container that fits in
+//! what is left of the line is written as its repr, and one that does not is
+//! written one item per line, indented past its opening bracket. Items are
+//! written by the same repr machinery as `repr()`, so an instance shows its
+//! `__repr__`, and dicts are ordered by key unless `sort_dicts` is false.
+//!
+//! `pprint` writes to the VM's output, so these functions are natives that
+//! run on the VM, as the `sys` functions are.
+
+use super::super::formatting::repr_of;
+use super::super::opcodes::comparison::compare;
+use crate::object::{quote, ModuleObject, NativeError, Object, ObjectType, VmNativeFn};
+use crate::sync::Rc;
+use crate::vm::{InterpretResult, VM};
+use std::cmp::Ordering;
+
+/// The module's functions.
+const FUNCTIONS: [(&str, VmNativeFn); 3] = [("pformat", pformat), ("pp", pp), ("pprint", pprint)];
+
+/// Builds the `pprint` module object.
+pub fn module() -> Object {
+    Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+        name: "pprint".to_string(),
+        attributes: FUNCTIONS
+            .iter()
+            .map(|(name, func)| {
+                let function = ObjectType::VmNativeFunction(name.to_string(), *func);
+                (name.to_string(), Rc::new(function))
+            })
+            .collect(),
+    })))
+}
+
+/// `pformat(object, indent=1, width=80, depth=None)`.
+fn pformat(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.run_pprint_function("pformat", args, keywords)
+}
+
+/// `pp(object, stream=None, indent=1, width=80, depth=None)`, which leaves
+/// dicts in insertion order.
+fn pp(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.run_pprint_function("pp", args, keywords)
+}
+
+/// `pprint(object, stream=None, indent=1, width=80, depth=None)`.
+fn pprint(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.run_pprint_function("pprint", args, keywords)
+}
+
+/// How a value is laid out.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    /// Spaces added for each level of nesting
+    pub indent: usize,
+    /// Columns the output tries to stay within
+    pub width: usize,
+    /// Levels of nesting shown before containers are elided as `[...]`
+    pub depth: Option<usize>,
+    /// Whether dict items are ordered by key rather than by insertion
+    pub sort_dicts: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            indent: 1,
+            width: 80,
+            depth: None,
+            sort_dicts: true,
+        }
+    }
+}
+
+impl VM {
+    /// Runs the `pprint` function called `name`.
+    fn run_pprint_function(
+        &mut self,
+        name: &str,
+        args: &[Object],
+        keywords: &[(String, Object)],
+    ) -> Result<Object, InterpretResult> {
+        let (value, layout) = pprint_arguments(name, args, keywords)
+            .map_err(|error| self.raise_error(error.kind, error.message))?;
+        // Its repr raises RecursionError for a value nested too deep to lay out
+        if let Some(Err(error)) = self.container_repr(&value) {
+            return Err(error);
+        }
+        let text = self.pformat(&value, &layout);
+        if name == "pformat" {
+            return Ok(Rc::new(ObjectType::String(text)));
+        }
+        self.output.write(&text);
+        self.output.write("\n");
+        Ok(Rc::new(ObjectType::Nil))
+    }
+
+    /// `value` laid out as `pprint.pformat` would with `layout`.
+    pub fn pformat(&mut self, value: &Object, layout: &Layout) -> String {
+        let mut printer = Printer {
+            vm: self,
+            layout,
+            text: String::new(),
+        };
+        printer.format(value, 0, 0, 0);
+        printer.text
+    }
+}
+
+/// Reads the value to print and its layout from a call to `name`.
+fn pprint_arguments(
+    name: &str,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<(Object, Layout), NativeError> {
+    let parameters: &[&str] = if name == "pformat" {
+        &["indent", "width", "depth"]
+    } else {
+        &["stream", "indent", "width", "depth"]
+    };
+    let Some((value, positional)) = args.split_first() else {
+        return Err(NativeError::type_error(format!(
+            "{}() missing 1 required positional argument: 'object'",
+            name
+        )));
+    };
+    if positional.len() > parameters.len() {
+        return Err(NativeError::type_error(format!(
+            "{}() takes from 1 to {} positional arguments but {} were given",
+            name,
+            parameters.len() + 1,
+            args.len()
+        )));
+    }
+    let mut arguments: Vec<(String, Object)> = parameters
+        .iter()
+        .map(|parameter| parameter.to_string())
+        .zip(positional.iter().cloned())
+        .collect();
+    for (keyword, argument) in keywords {
+        if arguments.iter().any(|(name, _)| name == keyword) {
+            return Err(NativeError::type_error(format!(
+                "{}() got multiple values for argument '{}'",
+                name, keyword
+            )));
+        }
+        arguments.push((keyword.clone(), argument.clone()));
+    }
+
+    let mut layout = Layout {
+        sort_dicts: name != "pp",
+        ..Layout::default()
+    };
+    for (parameter, argument) in arguments {
+        match (parameter.as_str(), &*argument) {
+            ("stream", ObjectType::Nil) | ("depth", ObjectType::Nil) => {}
+            ("stream", _) => {
+                return Err(NativeError::type_error(format!(
+                    "{}() can only write to standard output",
+                    name
+                )))
+            }
+            ("indent", ObjectType::Integer(indent)) => {
+                layout.indent = usize::try_from(*indent)
+                    .map_err(|_| NativeError::value_error("indent must be >= 0"))?;
+            }
+            ("width", ObjectType::Integer(width)) => {
+                layout.width = match usize::try_from(*width) {
+                    Ok(0) => return Err(NativeError::value_error("width must be != 0")),
+                    Ok(width) => width,
+                    // Nothing fits a negative width
+                    Err(_) => 0,
+                };
+            }
+            ("depth", ObjectType::Integer(depth)) => {
+                layout.depth = match usize::try_from(*depth) {
+                    Ok(depth) if depth > 0 => Some(depth),
+                    _ => return Err(NativeError::value_error("depth must be > 0")),
+                };
+            }
+            ("sort_dicts", ObjectType::Boolean(flag)) => layout.sort_dicts = *flag,
+            ("indent" | "width" | "depth", other) => {
+                return Err(NativeError::type_error(format!(
+                    "'{}' object cannot be interpreted as an integer",
+                    other.type_name()
+                )))
+            }
+            ("sort_dicts", other) => {
+                return Err(NativeError::type_error(format!(
+                    "sort_dicts must be a bool, not {}",
+                    other.type_name()
+                )))
+            }
+            (other, _) => {
+                return Err(NativeError::type_error(format!(
+                    "{}() got an unexpected keyword argument '{}'",
+                    name, other
+                )))
+            }
+        }
+    }
+    Ok((value.clone(), layout))
+}
+
+/// Lays a value out into `text`.
+struct Printer<'a> {
+    vm: &'a mut VM,
+    layout: &'a Layout,
+    text: String,
+}
+
+impl Printer<'_> {
+    /// Writes `value`, starting `indent` columns in and leaving `allowance`
+    /// columns free after it for the brackets and commas that follow.
+    fn format(&mut self, value: &Object, indent: usize, allowance: usize, level: usize) {
+        let repr = self.repr(value, level);
+        let fits = repr.chars().count() + indent + allowance <= self.layout.width;
+        let level = level + 1;
+        match &**value {
+            _ if fits => self.text.push_str(&repr),
+            ObjectType::List(items) if !items.is_empty() => {
+                self.text.push('[');
+                self.format_items(items, indent, allowance + 1, level);
+                self.text.push(']');
+            }
+            ObjectType::Tuple(items) if !items.is_empty() => {
+                let end = if items.len() == 1 { ",)" } else { ")" };
+                self.text.push('(');
+                self.format_items(items, indent, allowance + end.len(), level);
+                self.text.push_str(end);
+            }
+            ObjectType::Dict(entries) if !entries.is_empty() => {
+                self.text.push('{');
+                self.pad();
+                let entries = self.ordered(entries);
+                self.format_dict_items(&entries, indent, allowance + 1, level);
+                self.text.push('}');
+            }
+            ObjectType::String(text) if !text.is_empty() => {
+                self.format_string(text, indent, allowance, level)
+            }
+            _ => self.text.push_str(&repr),
+        }
+    }
+
+    /// The spaces after an opening bracket that line the first item up with
+    /// the rest when each level indents by more than one.
+    fn pad(&mut self) {
+        let padding = self.layout.indent.saturating_sub(1);
+        self.text.extend(std::iter::repeat_n(' ', padding));
+    }
+
+    fn format_items(&mut self, items: &[Object], indent: usize, allowance: usize, level: usize) {
+        let indent = indent + self.layout.indent;
+        self.pad();
+        for (index, item) in items.iter().enumerate() {
+            let last = index + 1 == items.len();
+            if index > 0 {
+                self.newline(indent);
+            }
+            self.format(item, indent, if last { allowance } else { 1 }, level);
+        }
+    }
+
+    fn format_dict_items(
+        &mut self,
+        entries: &[(Object, Object)],
+        indent: usize,
+        allowance: usize,
+        level: usize,
+    ) {
+        let indent = indent + self.layout.indent;
+        for (index, (key, value)) in entries.iter().enumerate() {
+            let last = index + 1 == entries.len();
+            if index > 0 {
+                self.newline(indent);
+            }
+            let key = self.repr(key, level);
+            self.text.push_str(&key);
+            self.text.push_str(": ");
+            let indent = indent + key.chars().count() + 2;
+            self.format(value, indent, if last { allowance } else { 1 }, level);
+        }
+    }
+
+    /// Ends an item with a comma and starts the next line `indent` columns in.
+    fn newline(&mut self, indent: usize) {
+        self.text.push_str(",\n");
+        self.text.extend(std::iter::repeat_n(' ', indent));
+    }
+
+    /// Splits a long string into literals of whole lines and words that fit,
+    /// one per line, which Python joins back into the same string. At the top
+    /// level they are wrapped in parentheses.
+    fn format_string(&mut self, text: &str, mut indent: usize, mut allowance: usize, level: usize) {
+        if level == 1 {
+            indent += 1;
+            allowance += 1;
+        }
+        let width = self.layout.width.saturating_sub(indent);
+        let lines = split_lines(text);
+        let mut chunks = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            let last_line = index + 1 == lines.len();
+            let line_width = width.saturating_sub(if last_line { allowance } else { 0 });
+            let repr = quote(line);
+            if repr.chars().count() <= line_width {
+                chunks.push(repr);
+                continue;
+            }
+            let words = split_words(line);
+            let mut current = String::new();
+            for (word_index, word) in words.iter().enumerate() {
+                let last_word = last_line && word_index + 1 == words.len();
+                let word_width = width.saturating_sub(if last_word { allowance } else { 0 });
+                let candidate = format!("{}{}", current, word);
+                if quote(&candidate).chars().count() > word_width {
+                    if !current.is_empty() {
+                        chunks.push(quote(&current));
+                    }
+                    current = word.to_string();
+                } else {
+                    current = candidate;
+                }
+            }
+            if !current.is_empty() {
+                chunks.push(quote(&current));
+            }
+        }
+        if chunks.len() == 1 {
+            self.text.push_str(&quote(text));
+            return;
+        }
+        if level == 1 {
+            self.text.push('(');
+        }
+        for (index, chunk) in chunks.iter().enumerate() {
+            if index > 0 {
+                self.text.push('\n');
+                self.text.extend(std::iter::repeat_n(' ', indent));
+            }
+            self.text.push_str(chunk);
+        }
+        if level == 1 {
+            self.text.push(')');
+        }
+    }
+
+    /// The one-line repr of `value` at nesting `level`, with containers past
+    /// the depth limit elided and dicts in layout order.
+    fn repr(&mut self, value: &Object, level: usize) -> String {
+        let elided = self.layout.depth.is_some_and(|depth| level >= depth);
+        match &**value {
+            ObjectType::List(items) if items.is_empty() => "[]".to_string(),
+            ObjectType::Tuple(items) if items.is_empty() => "()".to_string(),
+            ObjectType::Dict(entries) if entries.is_empty() => "{}".to_string(),
+            ObjectType::List(_) if elided => "[...]".to_string(),
+            ObjectType::Tuple(_) if elided => "(...)".to_string(),
+            ObjectType::Dict(_) if elided => "{...}".to_string(),
+            ObjectType::List(items) => format!("[{}]", self.reprs(items, level)),
+            ObjectType::Tuple(items) if items.len() == 1 => {
+                format!("({},)", self.repr(&items[0], level + 1))
+            }
+            ObjectType::Tuple(items) => format!("({})", self.reprs(items, level)),
+            ObjectType::Dict(entries) => {
+                let entries = self.ordered(entries);
+                let items: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}: {}",
+                            self.repr(key, level + 1),
+                            self.repr(value, level + 1)
+                        )
+                    })
+                    .collect();
+                format!("{{{}}}", items.join(", "))
+            }
+            _ => repr_of(value.clone(), &mut self.vm.stack, &mut self.vm.frames),
+        }
+    }
+
+    fn reprs(&mut self, items: &[Object], level: usize) -> String {
+        let items: Vec<String> = items
+            .iter()
+            .map(|item| self.repr(item, level + 1))
+            .collect();
+        items.join(", ")
+    }
+
+    /// A dict's entries in the order they are shown.
+    fn ordered(&self, entries: &[(Object, Object)]) -> Vec<(Object, Object)> {
+        let mut entries = entries.to_vec();
+        if self.layout.sort_dicts {
+            entries.sort_by(|(a, _), (b, _)| key_order(a, b));
+        }
+        entries
+    }
+}
+
+/// Orders dict keys by value where they can be compared, and otherwise by
+/// type name, as CPython's pprint does.
+fn key_order(a: &Object, b: &Object) -> Ordering {
+    match compare(a, b) {
+        Ok(Some(order)) => order,
+        Ok(None) => Ordering::Equal,
+        Err(_) => a.type_name().cmp(&b.type_name()),
+    }
+}
+
+/// `text` split after each line break, which stays with its line.
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive(['\n', '\r']).collect()
+}
+
+/// `line` split into words, each keeping the whitespace that follows it.
+fn split_words(line: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut in_space = false;
+    for (index, ch) in line.char_indices() {
+        if ch.is_whitespace() {
+            in_space = true;
+        } else if in_space {
+            words.push(&line[start..index]);
+            start = index;
+            in_space = false;
+        }
+    }
+    words.push(&line[start..]);
+    words
+}
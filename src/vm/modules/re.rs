@@ -0,0 +1,411 @@
+//! The native `re` module: `match`, `search`, `findall` and `sub`.
+//!
+//! Patterns are compiled on every call by the engine in `regex.rs`. A
+//! successful `match` or `search` returns an instance of the built-in `Match`
+//! class, which keeps the searched string and the span of every group; its
+//! methods are native functions that read those fields back.
+
+use super::regex::{Captures, Regex};
+use crate::object::{
+    ClassObject, InstanceObject, ModuleObject, NativeError, NativeFn, NativeResult, Object,
+    ObjectType,
+};
+use crate::sync::Rc;
+use crate::vm::{exceptions, gc, values};
+use std::collections::HashMap;
+
+/// The exception raised for a bad pattern or replacement template, exposed
+/// as `re.error`.
+const PATTERN_ERROR: &str = "re.error";
+
+thread_local! {
+    static MATCH_CLASS: Rc<ClassObject> = Rc::new(ClassObject::new(
+        "Match".to_string(),
+        native_functions(&[
+            ("group", match_group),
+            ("groups", match_groups),
+            ("start", match_start),
+            ("end", match_end),
+            ("span", match_span),
+        ]),
+    ));
+}
+
+fn native_functions(functions: &[(&str, NativeFn)]) -> HashMap<String, Object> {
+    functions
+        .iter()
+        .map(|(name, func)| {
+            (
+                name.to_string(),
+                Rc::new(ObjectType::NativeFunction(name.to_string(), *func)),
+            )
+        })
+        .collect()
+}
+
+/// Builds the `re` module object.
+pub fn module() -> Object {
+    let mut attributes = native_functions(&[
+        ("match", re_match),
+        ("search", re_search),
+        ("findall", re_findall),
+        ("sub", re_sub),
+    ]);
+    attributes.insert(
+        "error".to_string(),
+        Rc::new(ObjectType::Class(exceptions::exception_class(
+            PATTERN_ERROR,
+        ))),
+    );
+    Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+        name: "re".to_string(),
+        attributes,
+    })))
+}
+
+fn pattern_error(message: String) -> NativeError {
+    NativeError::new(PATTERN_ERROR, message)
+}
+
+fn string(value: String) -> Object {
+    Rc::new(ObjectType::String(value))
+}
+
+/// Compiles the pattern argument.
+fn pattern_arg(value: &Object) -> Result<Regex, NativeError> {
+    match &**value {
+        ObjectType::String(pattern) => Regex::new(pattern).map_err(pattern_error),
+        _ => Err(NativeError::type_error(
+            "first argument must be string or compiled pattern",
+        )),
+    }
+}
+
+/// Reads the string searched by a function.
+fn text_arg(value: &Object) -> Result<Vec<char>, String> {
+    match &**value {
+        ObjectType::String(text) => Ok(text.chars().collect()),
+        other => Err(format!(
+            "expected string or bytes-like object, got '{}'",
+            other.type_name()
+        )),
+    }
+}
+
+fn pattern_and_text(name: &str, args: &[Object]) -> Result<(Regex, Vec<char>), NativeError> {
+    let [pattern, text] = args else {
+        return Err(NativeError::type_error(format!(
+            "{}() takes 2 arguments ({} given)",
+            name,
+            args.len()
+        )));
+    };
+    Ok((pattern_arg(pattern)?, text_arg(text)?))
+}
+
+fn slice(text: &[char], span: Option<(usize, usize)>) -> String {
+    span.map_or_else(String::new, |(start, end)| {
+        text[start..end].iter().collect()
+    })
+}
+
+/// Wraps a match of `text` in a `Match` instance, or returns None.
+fn match_object(text: &[char], captures: Option<Captures>) -> Object {
+    let Some(captures) = captures else {
+        return Rc::new(ObjectType::Nil);
+    };
+    let regs = captures
+        .iter()
+        .map(|span| {
+            let (start, end) = span.map_or((-1, -1), |(start, end)| (start as i64, end as i64));
+            Rc::new(ObjectType::Tuple(vec![
+                values::integer(start),
+                values::integer(end),
+            ]))
+        })
+        .collect();
+    let mut instance = InstanceObject::new(MATCH_CLASS.with(Rc::clone));
+    instance.set_field("string".to_string(), string(text.iter().collect()));
+    instance.set_field("regs".to_string(), Rc::new(ObjectType::Tuple(regs)));
+    Rc::new(ObjectType::Instance(gc::new_instance(instance)))
+}
+
+/// `re.match(pattern, string)`: a match at the start of the string, or None.
+fn re_match(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (regex, text) = pattern_and_text("match", args)?;
+    let captures = regex.find_at(&text, 0, true).map_err(pattern_error)?;
+    Ok(match_object(&text, captures))
+}
+
+/// `re.search(pattern, string)`: the first match anywhere, or None.
+fn re_search(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (regex, text) = pattern_and_text("search", args)?;
+    let captures = regex.find_at(&text, 0, false).map_err(pattern_error)?;
+    Ok(match_object(&text, captures))
+}
+
+/// `re.findall(pattern, string)`: every match as a string, the one group's
+/// text, or a tuple of all groups' text when there are several groups.
+fn re_findall(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (regex, text) = pattern_and_text("findall", args)?;
+    let found = regex
+        .find_all(&text)
+        .map_err(pattern_error)?
+        .into_iter()
+        .map(|captures| match regex.groups {
+            0 => string(slice(&text, captures[0])),
+            1 => string(slice(&text, captures[1])),
+            _ => Rc::new(ObjectType::Tuple(
+                captures[1..]
+                    .iter()
+                    .map(|span| string(slice(&text, *span)))
+                    .collect(),
+            )),
+        })
+        .collect();
+    Ok(Rc::new(ObjectType::List(found)))
+}
+
+/// A piece of a `sub` replacement template.
+enum Piece {
+    Literal(String),
+    Group(usize),
+}
+
+/// Splits a replacement template into literal text and `\1` / `\g<1>` group
+/// references.
+fn parse_template(template: &str, groups: usize) -> Result<Vec<Piece>, String> {
+    let chars: Vec<char> = template.chars().collect();
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut pos = 0;
+    let group_ref = |group: usize, literal: &mut String, pieces: &mut Vec<Piece>| {
+        if group > groups {
+            return Err(format!("invalid group reference {}", group));
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(std::mem::take(literal)));
+        }
+        pieces.push(Piece::Group(group));
+        Ok(())
+    };
+    while pos < chars.len() {
+        let c = chars[pos];
+        pos += 1;
+        if c != '\\' {
+            literal.push(c);
+            continue;
+        }
+        let Some(&escaped) = chars.get(pos) else {
+            return Err("bad escape (end of pattern)".to_string());
+        };
+        pos += 1;
+        match escaped {
+            '0' => literal.push('\0'),
+            '1'..='9' => {
+                let mut group = escaped.to_digit(10).unwrap_or(0) as usize;
+                if let Some(digit) = chars.get(pos).and_then(|c| c.to_digit(10)) {
+                    group = group * 10 + digit as usize;
+                    pos += 1;
+                }
+                group_ref(group, &mut literal, &mut pieces)?;
+            }
+            'g' => {
+                let close = chars[pos..].iter().position(|&c| c == '>');
+                let (Some('<'), Some(close)) = (chars.get(pos), close) else {
+                    return Err("missing group name".to_string());
+                };
+                let name: String = chars[pos + 1..pos + close].iter().collect();
+                let group = name
+                    .parse::<usize>()
+                    .map_err(|_| format!("bad character in group name '{}'", name))?;
+                pos += close + 1;
+                group_ref(group, &mut literal, &mut pieces)?;
+            }
+            'n' => literal.push('\n'),
+            't' => literal.push('\t'),
+            'r' => literal.push('\r'),
+            'f' => literal.push('\x0c'),
+            'v' => literal.push('\x0b'),
+            'a' => literal.push('\x07'),
+            '\\' => literal.push('\\'),
+            other if other.is_ascii_alphabetic() => {
+                return Err(format!("bad escape \\{}", other));
+            }
+            other => {
+                literal.push('\\');
+                literal.push(other);
+            }
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// `re.sub(pattern, repl, string[, count])`: the string with the first
+/// `count` matches (all when 0) replaced by the expanded template.
+fn re_sub(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (pattern, template, text, count) = match args {
+        [pattern, template, text] => (pattern, template, text, 0),
+        [pattern, template, text, count] => match &**count {
+            ObjectType::Integer(count) => (pattern, template, text, (*count).max(0) as usize),
+            other => {
+                return Err(NativeError::type_error(format!(
+                    "'{}' object cannot be interpreted as an integer",
+                    other.type_name()
+                )))
+            }
+        },
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "sub() takes from 3 to 4 arguments ({} given)",
+                args.len()
+            )))
+        }
+    };
+    let regex = pattern_arg(pattern)?;
+    let ObjectType::String(template) = &**template else {
+        return Err(NativeError::type_error(
+            "sub() replacement must be a string",
+        ));
+    };
+    let pieces = parse_template(template, regex.groups).map_err(pattern_error)?;
+    let text = text_arg(text)?;
+
+    let mut matches = regex.find_all(&text).map_err(pattern_error)?;
+    if count > 0 {
+        matches.truncate(count);
+    }
+    let mut result = String::new();
+    let mut copied = 0;
+    for captures in matches {
+        let Some((start, end)) = captures[0] else {
+            continue;
+        };
+        result.extend(&text[copied..start]);
+        for piece in &pieces {
+            match piece {
+                Piece::Literal(literal) => result.push_str(literal),
+                Piece::Group(group) => result.push_str(&slice(&text, captures[*group])),
+            }
+        }
+        copied = end;
+    }
+    result.extend(&text[copied..]);
+    Ok(string(result))
+}
+
+/// Start and end of the whole match and of each group, -1 when unmatched.
+type Spans = Vec<(i64, i64)>;
+
+/// Reads the searched string and group spans back out of a `Match` receiver.
+fn match_fields(args: &[Object]) -> Result<(Vec<char>, Spans), String> {
+    let instance = match args.first().map(|receiver| &**receiver) {
+        Some(ObjectType::Instance(instance)) => instance.borrow(),
+        _ => return Err("descriptor requires a 're.Match' object".to_string()),
+    };
+    let text = match instance.get_field("string").as_deref() {
+        Some(ObjectType::String(text)) => text.chars().collect(),
+        _ => return Err("descriptor requires a 're.Match' object".to_string()),
+    };
+    let regs = match instance.get_field("regs").as_deref() {
+        Some(ObjectType::Tuple(regs)) => regs
+            .iter()
+            .map(|span| match &**span {
+                ObjectType::Tuple(pair) => {
+                    match pair.iter().map(|value| &**value).collect::<Vec<_>>()[..] {
+                        [ObjectType::Integer(start), ObjectType::Integer(end)] => {
+                            Ok((*start, *end))
+                        }
+                        _ => Err("corrupt Match object".to_string()),
+                    }
+                }
+                _ => Err("corrupt Match object".to_string()),
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        _ => return Err("descriptor requires a 're.Match' object".to_string()),
+    };
+    Ok((text, regs))
+}
+
+/// Resolves a group index argument of a `Match` method.
+fn group_span(regs: &[(i64, i64)], group: Option<&Object>) -> Result<(i64, i64), NativeError> {
+    let no_such_group = || NativeError::new("IndexError", "no such group");
+    let index = match group.map(|group| &**group) {
+        None => 0,
+        Some(ObjectType::Integer(index)) => *index,
+        Some(_) => return Err(no_such_group()),
+    };
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| regs.get(index))
+        .copied()
+        .ok_or_else(no_such_group)
+}
+
+fn span_text(text: &[char], (start, end): (i64, i64)) -> Object {
+    if start < 0 {
+        return Rc::new(ObjectType::Nil);
+    }
+    string(text[start as usize..end as usize].iter().collect())
+}
+
+/// `Match.group([group, ...])`: the text of the whole match or of the given
+/// groups, None for a group that did not take part.
+fn match_group(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (text, regs) = match_fields(args)?;
+    match &args[1..] {
+        [] | [_] => Ok(span_text(&text, group_span(&regs, args.get(1))?)),
+        groups => Ok(Rc::new(ObjectType::Tuple(
+            groups
+                .iter()
+                .map(|group| group_span(&regs, Some(group)).map(|span| span_text(&text, span)))
+                .collect::<Result<_, _>>()?,
+        ))),
+    }
+}
+
+/// `Match.groups()`: a tuple of every group's text.
+fn match_groups(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (text, regs) = match_fields(args)?;
+    Ok(Rc::new(ObjectType::Tuple(
+        regs[1..]
+            .iter()
+            .map(|span| span_text(&text, *span))
+            .collect(),
+    )))
+}
+
+fn method_span(args: &[Object], name: &str) -> Result<(i64, i64), NativeError> {
+    if args.len() > 2 {
+        return Err(NativeError::type_error(format!(
+            "{}() takes at most 1 argument ({} given)",
+            name,
+            args.len() - 1
+        )));
+    }
+    let (_, regs) = match_fields(args)?;
+    group_span(&regs, args.get(1))
+}
+
+/// `Match.start([group])`: where the match or group begins, -1 if unmatched.
+fn match_start(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    method_span(args, "start").map(|(start, _)| values::integer(start))
+}
+
+/// `Match.end([group])`: where the match or group ends, -1 if unmatched.
+fn match_end(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    method_span(args, "end").map(|(_, end)| values::integer(end))
+}
+
+/// `Match.span([group])`: the `(start, end)` tuple of the match or group.
+fn match_span(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    method_span(args, "span").map(|(start, end)| {
+        Rc::new(ObjectType::Tuple(vec![
+            values::integer(start),
+            values::integer(end),
+        ]))
+    })
+}
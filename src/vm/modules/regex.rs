@@ -0,0 +1,713 @@
+//! A small backtracking regular expression engine for the `re` module.
+//!
+//! Patterns are parsed into a syntax tree and compiled to a program of
+//! instructions, which is run by a backtracking matcher with an explicit
+//! stack. The matcher remembers which (instruction, position) pairs it has
+//! already tried, so matching takes time proportional to the program size
+//! times the text length and never recurses, whatever the pattern.
+//!
+//! Supported syntax: literals, `.`, `[...]` classes with ranges and
+//! negation, the `\d \w \s` escapes and their negations, `^ $ \A \Z \b \B`,
+//! capturing and `(?:...)` groups, `|`, and the `* + ? {m,n}` quantifiers
+//! with their lazy forms. Flags, named groups, lookaround and
+//! backreferences are not supported and are reported as pattern errors.
+
+/// Deepest group nesting a pattern may use.
+const MAX_NESTING: usize = 100;
+/// Largest count allowed in a `{m,n}` quantifier.
+const MAX_REPEAT: u32 = 1000;
+/// Most instructions a compiled pattern may have.
+const MAX_PROGRAM: usize = 20_000;
+/// Most (instruction, position) pairs a single match may track.
+const MAX_STATES: usize = 1 << 28;
+
+/// A compiled regular expression.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+    classes: Vec<CharClass>,
+    /// Number of capturing groups, not counting the whole match
+    pub groups: usize,
+}
+
+/// Start and end character positions of the whole match (index 0) and of
+/// each group, `None` for a group that did not take part in the match.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+impl Regex {
+    /// Compiles `pattern`, returning a description of the problem if it is
+    /// malformed or uses unsupported syntax.
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            groups: 0,
+            depth: 0,
+        };
+        let node = parser.parse_alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err("unbalanced parenthesis".to_string());
+        }
+        let mut compiler = Compiler {
+            program: Vec::new(),
+            classes: Vec::new(),
+        };
+        compiler.emit(&node)?;
+        compiler.push(Inst::Match)?;
+        Ok(Regex {
+            program: compiler.program,
+            classes: compiler.classes,
+            groups: parser.groups,
+        })
+    }
+
+    /// Finds the first match starting at or after `start`, or exactly at
+    /// `start` when `anchored`.
+    pub fn find_at(
+        &self,
+        text: &[char],
+        start: usize,
+        anchored: bool,
+    ) -> Result<Option<Captures>, String> {
+        Ok(Matcher::new(self, text)?.find(start, anchored, true))
+    }
+
+    /// Returns every non-overlapping match from left to right. As in Python,
+    /// an empty match is allowed right after a non-empty one, but after an
+    /// empty match the next one must start later or be non-empty.
+    pub fn find_all(&self, text: &[char]) -> Result<Vec<Captures>, String> {
+        let mut matcher = Matcher::new(self, text)?;
+        let mut matches = Vec::new();
+        let mut position = 0;
+        let mut allow_empty = true;
+        while position <= text.len() {
+            let Some(captures) = matcher.find(position, false, allow_empty) else {
+                break;
+            };
+            let (start, end) = captures[0].unwrap_or((position, position));
+            matches.push(captures);
+            allow_empty = end != start;
+            position = end;
+        }
+        Ok(matches)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Assertion {
+    /// `^`: the start of the text
+    Start,
+    /// `$`: the end of the text, or just before a newline that ends it
+    End,
+    /// `\A`
+    TextStart,
+    /// `\Z`
+    TextEnd,
+    /// `\b`
+    WordBoundary,
+    /// `\B`
+    NotWordBoundary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(self, ch: char) -> bool {
+        match self {
+            ClassItem::Range(low, high) => low <= ch && ch <= high,
+            ClassItem::Digit(negated) => is_digit(ch) != negated,
+            ClassItem::Word(negated) => is_word(ch) != negated,
+            ClassItem::Space(negated) => ch.is_whitespace() != negated,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CharClass {
+    negated: bool,
+    items: Vec<ClassItem>,
+}
+
+impl CharClass {
+    fn matches(&self, ch: char) -> bool {
+        self.items.iter().any(|item| item.matches(ch)) != self.negated
+    }
+}
+
+fn is_digit(ch: char) -> bool {
+    ch.is_ascii_digit() || (!ch.is_ascii() && ch.is_numeric())
+}
+
+fn is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Empty,
+    Literal(char),
+    Any,
+    Class(CharClass),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat('|') {
+            branches.push(self.parse_concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap_or(Node::Empty)
+        } else {
+            Node::Alternate(branches)
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut items = Vec::new();
+        while let Some(ch) = self.peek() {
+            if ch == '|' || ch == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            items.push(self.parse_quantifier(atom)?);
+        }
+        Ok(match items.len() {
+            0 => Node::Empty,
+            1 => items.pop().unwrap_or(Node::Empty),
+            _ => Node::Concat(items),
+        })
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let Some(ch) = self.peek() else {
+            return Ok(Node::Empty);
+        };
+        self.pos += 1;
+        match ch {
+            '(' => self.parse_group(),
+            '[' => self.parse_class().map(Node::Class),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Assert(Assertion::Start)),
+            '$' => Ok(Node::Assert(Assertion::End)),
+            '\\' => self.parse_escape(),
+            '*' | '+' | '?' => Err(format!("nothing to repeat at position {}", self.pos - 1)),
+            '{' if self.repeat_bounds_ahead() => {
+                Err(format!("nothing to repeat at position {}", self.pos - 1))
+            }
+            _ => Ok(Node::Literal(ch)),
+        }
+    }
+
+    fn parse_group(&mut self) -> Result<Node, String> {
+        if self.depth >= MAX_NESTING {
+            return Err("pattern nests too deeply".to_string());
+        }
+        let index = if self.eat('?') {
+            if !self.eat(':') {
+                return Err(format!(
+                    "unsupported group syntax at position {}",
+                    self.pos - 2
+                ));
+            }
+            None
+        } else {
+            self.groups += 1;
+            Some(self.groups)
+        };
+        self.depth += 1;
+        let inner = self.parse_alternation()?;
+        self.depth -= 1;
+        if !self.eat(')') {
+            return Err("missing ), unterminated subpattern".to_string());
+        }
+        Ok(Node::Group(Box::new(inner), index))
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, String> {
+        let Some(ch) = self.peek() else {
+            return Err("bad escape (end of pattern)".to_string());
+        };
+        self.pos += 1;
+        let class = |item| {
+            Node::Class(CharClass {
+                negated: false,
+                items: vec![item],
+            })
+        };
+        Ok(match ch {
+            'd' => class(ClassItem::Digit(false)),
+            'D' => class(ClassItem::Digit(true)),
+            'w' => class(ClassItem::Word(false)),
+            'W' => class(ClassItem::Word(true)),
+            's' => class(ClassItem::Space(false)),
+            'S' => class(ClassItem::Space(true)),
+            'b' => Node::Assert(Assertion::WordBoundary),
+            'B' => Node::Assert(Assertion::NotWordBoundary),
+            'A' => Node::Assert(Assertion::TextStart),
+            'Z' => Node::Assert(Assertion::TextEnd),
+            _ => Node::Literal(self.escaped_char(ch)?),
+        })
+    }
+
+    /// Resolves an escape that stands for a single character.
+    fn escaped_char(&self, ch: char) -> Result<char, String> {
+        match ch {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            'f' => Ok('\x0c'),
+            'v' => Ok('\x0b'),
+            '0' => Ok('\0'),
+            '1'..='9' => Err("backreferences are not supported".to_string()),
+            _ if ch.is_ascii_alphanumeric() => Err(format!("bad escape \\{}", ch)),
+            _ => Ok(ch),
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<CharClass, String> {
+        let start = self.pos - 1;
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let Some(ch) = self.peek() else {
+                return Err(format!("unterminated character set at position {}", start));
+            };
+            self.pos += 1;
+            if ch == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = if ch == '\\' {
+                let Some(escaped) = self.peek() else {
+                    return Err(format!("unterminated character set at position {}", start));
+                };
+                self.pos += 1;
+                match escaped {
+                    'd' => items.push(ClassItem::Digit(false)),
+                    'D' => items.push(ClassItem::Digit(true)),
+                    'w' => items.push(ClassItem::Word(false)),
+                    'W' => items.push(ClassItem::Word(true)),
+                    's' => items.push(ClassItem::Space(false)),
+                    'S' => items.push(ClassItem::Space(true)),
+                    'b' => items.push(ClassItem::Range('\x08', '\x08')),
+                    _ => {
+                        let ch = self.escaped_char(escaped)?;
+                        items.push(ClassItem::Range(ch, ch));
+                    }
+                }
+                match items.last() {
+                    Some(ClassItem::Range(ch, _)) => *ch,
+                    _ => continue,
+                }
+            } else {
+                items.push(ClassItem::Range(ch, ch));
+                ch
+            };
+
+            // A `-` between two characters makes a range; elsewhere it is literal
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']') {
+                self.pos += 1;
+                let mut high = self.chars[self.pos];
+                self.pos += 1;
+                if high == '\\' {
+                    let Some(escaped) = self.peek() else {
+                        return Err(format!("unterminated character set at position {}", start));
+                    };
+                    self.pos += 1;
+                    high = self.escaped_char(escaped)?;
+                }
+                if high < low {
+                    return Err(format!("bad character range {}-{}", low, high));
+                }
+                items.pop();
+                items.push(ClassItem::Range(low, high));
+            }
+        }
+        Ok(CharClass { negated, items })
+    }
+
+    /// Returns whether a valid `{m}`, `{m,}`, `{,n}` or `{m,n}` starts at the
+    /// current position (just after its `{`).
+    fn repeat_bounds_ahead(&self) -> bool {
+        let rest = &self.chars[self.pos..];
+        let Some(close) = rest.iter().position(|c| *c == '}') else {
+            return false;
+        };
+        let inside = &rest[..close];
+        let commas = inside.iter().filter(|c| **c == ',').count();
+        !inside.is_empty()
+            && commas <= 1
+            && inside.iter().all(|c| c.is_ascii_digit() || *c == ',')
+            && inside != [',']
+    }
+
+    fn parse_number(&mut self) -> Result<Option<u32>, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Ok(None);
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse::<u32>() {
+            Ok(value) if value <= MAX_REPEAT => Ok(Some(value)),
+            _ => Err("the repetition number is too large".to_string()),
+        }
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                if !self.repeat_bounds_ahead() {
+                    self.pos -= 1;
+                    return Ok(atom);
+                }
+                let min = self.parse_number()?.unwrap_or(0);
+                let max = if self.eat(',') {
+                    self.parse_number()?
+                } else {
+                    Some(min)
+                };
+                if max.is_some_and(|max| max < min) {
+                    return Err("min repeat greater than max repeat".to_string());
+                }
+                // Left on the closing `}`, which the advance below consumes
+                (min, max)
+            }
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+        if matches!(atom, Node::Assert(_) | Node::Empty) {
+            return Err(format!("nothing to repeat at position {}", self.pos - 1));
+        }
+        let greedy = !self.eat('?');
+        if matches!(self.peek(), Some('*' | '+' | '?')) {
+            return Err(format!("multiple repeat at position {}", self.pos));
+        }
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(usize),
+    Assert(Assertion),
+    /// Try the first target, then the second on failure
+    Split(usize, usize),
+    Jump(usize),
+    /// Record the current position in a capture slot
+    Save(usize),
+    Match,
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+    classes: Vec<CharClass>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.program.len() >= MAX_PROGRAM {
+            return Err("pattern is too large".to_string());
+        }
+        self.program.push(inst);
+        Ok(self.program.len() - 1)
+    }
+
+    fn emit(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Empty => {}
+            Node::Literal(ch) => {
+                self.push(Inst::Char(*ch))?;
+            }
+            Node::Any => {
+                self.push(Inst::Any)?;
+            }
+            Node::Class(class) => {
+                self.classes.push(class.clone());
+                self.push(Inst::Class(self.classes.len() - 1))?;
+            }
+            Node::Assert(assertion) => {
+                self.push(Inst::Assert(*assertion))?;
+            }
+            Node::Group(inner, index) => {
+                if let Some(index) = index {
+                    self.push(Inst::Save(index * 2))?;
+                    self.emit(inner)?;
+                    self.push(Inst::Save(index * 2 + 1))?;
+                } else {
+                    self.emit(inner)?;
+                }
+            }
+            Node::Concat(items) => {
+                for item in items {
+                    self.emit(item)?;
+                }
+            }
+            Node::Alternate(branches) => {
+                let mut jumps = Vec::new();
+                for (index, branch) in branches.iter().enumerate() {
+                    if index + 1 < branches.len() {
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.emit(branch)?;
+                        jumps.push(self.push(Inst::Jump(0))?);
+                        let next = self.program.len();
+                        self.program[split] = Inst::Split(split + 1, next);
+                    } else {
+                        self.emit(branch)?;
+                    }
+                }
+                let end = self.program.len();
+                for jump in jumps {
+                    self.program[jump] = Inst::Jump(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.emit(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.emit(node)?;
+                        self.push(Inst::Jump(split))?;
+                        let end = self.program.len();
+                        self.program[split] = self.split(split + 1, end, *greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.push(Inst::Split(0, 0))?);
+                            self.emit(node)?;
+                        }
+                        let end = self.program.len();
+                        for split in splits {
+                            self.program[split] = self.split(split + 1, end, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A split that prefers `body` when greedy and `skip` when lazy.
+    fn split(&self, body: usize, skip: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(body, skip)
+        } else {
+            Inst::Split(skip, body)
+        }
+    }
+}
+
+enum Job {
+    Explore(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+struct Matcher<'r, 't> {
+    regex: &'r Regex,
+    text: &'t [char],
+    /// One bit per (instruction, position) pair already explored
+    visited: Vec<u64>,
+    /// Words of `visited` set since the last search, so clearing them costs
+    /// no more than the search did
+    touched: Vec<usize>,
+}
+
+impl<'r, 't> Matcher<'r, 't> {
+    fn new(regex: &'r Regex, text: &'t [char]) -> Result<Self, String> {
+        let states = regex
+            .program
+            .len()
+            .checked_mul(text.len() + 1)
+            .filter(|states| *states <= MAX_STATES)
+            .ok_or_else(|| "text is too long to match against this pattern".to_string())?;
+        Ok(Matcher {
+            regex,
+            text,
+            visited: vec![0; states.div_ceil(64)],
+            touched: Vec::new(),
+        })
+    }
+
+    /// Finds the first match beginning at `start` or, unless `anchored`,
+    /// anywhere after it. With `allow_empty` false an empty match at `start`
+    /// does not count.
+    fn find(&mut self, start: usize, anchored: bool, allow_empty: bool) -> Option<Captures> {
+        for word in self.touched.drain(..) {
+            self.visited[word] = 0;
+        }
+        let last_begin = if anchored { start } else { self.text.len() };
+        // Explored states stay explored across begin positions: a state that
+        // failed from one start fails from any later one too
+        for begin in start..=last_begin.min(self.text.len()) {
+            let forbid_empty = !allow_empty && begin == start;
+            if let Some(slots) = self.run(begin, forbid_empty) {
+                return Some(
+                    slots
+                        .chunks(2)
+                        .map(|pair| match (pair[0], pair[1]) {
+                            (Some(s), Some(e)) => Some((s, e)),
+                            _ => None,
+                        })
+                        .collect(),
+                );
+            }
+        }
+        None
+    }
+
+    /// Marks a state as explored, returning false if it already was.
+    fn visit(&mut self, pc: usize, pos: usize) -> bool {
+        let index = pc * (self.text.len() + 1) + pos;
+        let (word, bit) = (index / 64, 1u64 << (index % 64));
+        if self.visited[word] & bit != 0 {
+            return false;
+        }
+        if self.visited[word] == 0 {
+            self.touched.push(word);
+        }
+        self.visited[word] |= bit;
+        true
+    }
+
+    fn assertion_holds(&self, assertion: Assertion, pos: usize) -> bool {
+        let len = self.text.len();
+        let word_before = pos > 0 && is_word(self.text[pos - 1]);
+        let word_after = pos < len && is_word(self.text[pos]);
+        match assertion {
+            Assertion::Start | Assertion::TextStart => pos == 0,
+            Assertion::End => pos == len || (pos + 1 == len && self.text[pos] == '\n'),
+            Assertion::TextEnd => pos == len,
+            Assertion::WordBoundary => word_before != word_after,
+            Assertion::NotWordBoundary => word_before == word_after,
+        }
+    }
+
+    /// Runs the program anchored at `start`, returning the capture slots of
+    /// the highest-priority match.
+    fn run(&mut self, start: usize, forbid_empty: bool) -> Option<Vec<Option<usize>>> {
+        let mut slots = vec![None; (self.regex.groups + 1) * 2];
+        slots[0] = Some(start);
+        let mut stack = vec![Job::Explore(0, start)];
+        while let Some(job) = stack.pop() {
+            let (mut pc, mut pos) = match job {
+                Job::Explore(pc, pos) => (pc, pos),
+                Job::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+            loop {
+                if !self.visit(pc, pos) {
+                    break;
+                }
+                match self.regex.program[pc] {
+                    Inst::Char(ch) => {
+                        if self.text.get(pos) != Some(&ch) {
+                            break;
+                        }
+                        pc += 1;
+                        pos += 1;
+                    }
+                    Inst::Any => {
+                        if self.text.get(pos).is_none_or(|ch| *ch == '\n') {
+                            break;
+                        }
+                        pc += 1;
+                        pos += 1;
+                    }
+                    Inst::Class(index) => {
+                        let class = &self.regex.classes[index];
+                        if !self.text.get(pos).is_some_and(|ch| class.matches(*ch)) {
+                            break;
+                        }
+                        pc += 1;
+                        pos += 1;
+                    }
+                    Inst::Assert(assertion) => {
+                        if !self.assertion_holds(assertion, pos) {
+                            break;
+                        }
+                        pc += 1;
+                    }
+                    Inst::Split(first, second) => {
+                        stack.push(Job::Explore(second, pos));
+                        pc = first;
+                    }
+                    Inst::Jump(target) => pc = target,
+                    Inst::Save(slot) => {
+                        stack.push(Job::Restore(slot, slots[slot]));
+                        slots[slot] = Some(pos);
+                        pc += 1;
+                    }
+                    Inst::Match => {
+                        if forbid_empty && pos == start {
+                            break;
+                        }
+                        slots[1] = Some(pos);
+                        return Some(slots);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
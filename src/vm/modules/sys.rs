@@ -0,0 +1,201 @@
+//! The native `sys` module: the recursion limit, the module search path and
+//! counters describing the running VM.
+//!
+//! Its functions read and change VM state, so they are natives that run on
+//! the VM. Likewise `sys.path` is not stored in the module: reading it lists
+//! the VM's search path, and assigning it a list, as
+//! `sys.path.append(directory)` does, replaces the search path.
+
+use crate::object::{ModuleObject, NativeError, NativeResult, Object, ObjectType, VmNativeFn};
+use crate::sync::Rc;
+use crate::vm::call_frame::MAX_RECURSION_LIMIT;
+use crate::vm::{InterpretResult, VM};
+use std::collections::HashMap;
+
+/// The module's functions.
+const FUNCTIONS: [(&str, VmNativeFn); 4] = [
+    ("getrecursionlimit", getrecursionlimit),
+    ("setrecursionlimit", setrecursionlimit),
+    ("getinstructioncount", getinstructioncount),
+    ("getobjectcount", getobjectcount),
+];
+
+/// Builds the `sys` module object.
+pub fn module() -> Object {
+    let mut attributes: HashMap<String, Object> = FUNCTIONS
+        .iter()
+        .map(|(name, func)| {
+            let function = ObjectType::VmNativeFunction(name.to_string(), *func);
+            (name.to_string(), Rc::new(function))
+        })
+        .collect();
+    attributes.insert(
+        "maxsize".to_string(),
+        Rc::new(ObjectType::Integer(i64::MAX)),
+    );
+    Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+        name: "sys".to_string(),
+        attributes,
+    })))
+}
+
+/// `sys.getrecursionlimit()`.
+fn getrecursionlimit(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("getrecursionlimit", keywords)?;
+    let result = no_arguments("getrecursionlimit", args).map(|_| integer(vm.recursion_limit));
+    vm.native_result(result)
+}
+
+/// `sys.setrecursionlimit(limit)`.
+fn setrecursionlimit(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("setrecursionlimit", keywords)?;
+    let result = vm.set_recursion_limit_from(args);
+    vm.native_result(result)
+}
+
+/// `sys.getinstructioncount()`: instructions the VM has run.
+fn getinstructioncount(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("getinstructioncount", keywords)?;
+    let result = no_arguments("getinstructioncount", args).map(|_| integer(vm.instructions_run));
+    vm.native_result(result)
+}
+
+/// `sys.getobjectcount()`: objects reachable from the running program.
+fn getobjectcount(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("getobjectcount", keywords)?;
+    let result = no_arguments("getobjectcount", args).map(|_| integer(vm.count_heap_objects()));
+    vm.native_result(result)
+}
+
+fn integer(value: usize) -> Object {
+    Rc::new(ObjectType::Integer(
+        i64::try_from(value).unwrap_or(i64::MAX),
+    ))
+}
+
+fn no_arguments(name: &str, args: &[Object]) -> Result<(), NativeError> {
+    if args.is_empty() {
+        return Ok(());
+    }
+    Err(NativeError::new(
+        "TypeError",
+        format!("{}() takes no arguments ({} given)", name, args.len()),
+    ))
+}
+
+impl VM {
+    /// Reads `sys.path` when `module` is `sys` and `name` is `path`, and
+    /// returns None for any other attribute.
+    pub(in crate::vm) fn sys_attribute(&self, module: &Object, name: &str) -> Option<Object> {
+        if !is_sys_path(module, name) {
+            return None;
+        }
+        let path = self
+            .search_path
+            .iter()
+            .map(|directory| Rc::new(ObjectType::String(directory.clone())))
+            .collect();
+        Some(Rc::new(ObjectType::List(path)))
+    }
+
+    /// Assigns `sys.path` when `module` is `sys` and `name` is `path`, and
+    /// returns None for any other attribute.
+    pub(in crate::vm) fn set_sys_attribute(
+        &mut self,
+        module: &Object,
+        name: &str,
+        value: Object,
+    ) -> Option<Result<(), NativeError>> {
+        if !is_sys_path(module, name) {
+            return None;
+        }
+        let ObjectType::List(items) = &*value else {
+            return Some(Err(NativeError::type_error(format!(
+                "sys.path must be a list, not '{}'",
+                value.type_name()
+            ))));
+        };
+        let mut path = Vec::with_capacity(items.len());
+        for item in items {
+            let ObjectType::String(directory) = &**item else {
+                return Some(Err(NativeError::type_error(format!(
+                    "sys.path entries must be strings, not '{}'",
+                    item.type_name()
+                ))));
+            };
+            path.push(directory.clone());
+        }
+        self.search_path = path;
+        Some(Ok(()))
+    }
+
+    /// `sys.setrecursionlimit(limit)`.
+    fn set_recursion_limit_from(&mut self, args: &[Object]) -> NativeResult {
+        let [limit] = args else {
+            return Err(NativeError::new(
+                "TypeError",
+                format!(
+                    "setrecursionlimit() takes exactly one argument ({} given)",
+                    args.len()
+                ),
+            ));
+        };
+        let limit = match &**limit {
+            ObjectType::Integer(limit) => *limit,
+            other => {
+                return Err(NativeError::new(
+                    "TypeError",
+                    format!(
+                        "'{}' object cannot be interpreted as an integer",
+                        other.type_name()
+                    ),
+                ))
+            }
+        };
+        if limit < 1 {
+            return Err(NativeError::new(
+                "ValueError",
+                "recursion limit must be greater or equal than 1".to_string(),
+            ));
+        }
+        let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+        if limit > MAX_RECURSION_LIMIT {
+            return Err(NativeError::new(
+                "ValueError",
+                format!("recursion limit must be at most {}", MAX_RECURSION_LIMIT),
+            ));
+        }
+        let depth = self.frames.len();
+        if limit <= depth {
+            return Err(NativeError::new(
+                "RecursionError",
+                format!(
+                    "cannot set the recursion limit to {} at the recursion depth {}: the limit is too low",
+                    limit, depth
+                ),
+            ));
+        }
+        self.recursion_limit = limit;
+        Ok(Rc::new(ObjectType::Nil))
+    }
+}
+
+fn is_sys_path(module: &Object, name: &str) -> bool {
+    name == "path" && matches!(&**module, ObjectType::Module(module) if module.name == "sys")
+}
@@ -0,0 +1,256 @@
+//! The native `traceback` module, and the traceback objects a caught
+//! exception's `__traceback__` holds.
+//!
+//! When a handler catches an exception the VM sets its `__traceback__` to a
+//! chain of `traceback` objects, one for each frame from the handler's down
+//! to the one that raised it. As in CPython, each has `tb_lineno`, `tb_next`
+//! and a `tb_frame` whose `f_code` names the function (`co_name`) and file
+//! (`co_filename`) it was running.
+//!
+//! `format_tb` and `format_exception` lay those out as the lines of a
+//! traceback. `format_exc` and `print_exc` do the same for the exception
+//! being handled, which only the VM knows, so they are natives that run on it,
+//! as the `sys` functions are. `print_exc` writes to the VM's output.
+
+use crate::object::{
+    ClassObject, InstanceObject, ModuleObject, NativeError, NativeFn, NativeResult, Object,
+    ObjectType, VmNativeFn,
+};
+use crate::sync::Rc;
+use crate::vm::exceptions::{exception_str, is_exception_class};
+use crate::vm::runtime_error::TraceFrame;
+use crate::vm::{gc, values, InterpretResult, VM};
+use std::collections::HashMap;
+use std::thread::LocalKey;
+
+/// The first line of a formatted traceback.
+const HEADER: &str = "Traceback (most recent call last):\n";
+
+thread_local! {
+    static TRACEBACK_CLASS: Rc<ClassObject> = empty_class("traceback");
+    static FRAME_CLASS: Rc<ClassObject> = empty_class("frame");
+    static CODE_CLASS: Rc<ClassObject> = empty_class("code");
+}
+
+fn empty_class(name: &str) -> Rc<ClassObject> {
+    Rc::new(ClassObject::new(name.to_string(), HashMap::new()))
+}
+
+/// Builds the `traceback` module object.
+pub fn module() -> Object {
+    let natives: [(&str, NativeFn); 2] = [
+        ("format_tb", traceback_format_tb),
+        ("format_exception", traceback_format_exception),
+    ];
+    // These read the exception being handled, which only the VM knows
+    let vm_natives: [(&str, VmNativeFn); 2] =
+        [("format_exc", format_exc), ("print_exc", print_exc)];
+    let functions = natives
+        .into_iter()
+        .map(|(name, func)| (name, ObjectType::NativeFunction(name.to_string(), func)))
+        .chain(
+            vm_natives
+                .into_iter()
+                .map(|(name, func)| (name, ObjectType::VmNativeFunction(name.to_string(), func))),
+        );
+    Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+        name: "traceback".to_string(),
+        attributes: functions
+            .map(|(name, function)| (name.to_string(), Rc::new(function)))
+            .collect(),
+    })))
+}
+
+/// `format_exc()`: the traceback of the exception being handled.
+fn format_exc(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("format_exc", keywords)?;
+    let result = vm.caught_traceback("format_exc", args).map(string);
+    vm.native_result(result)
+}
+
+/// `print_exc()`: writes the traceback of the exception being handled.
+fn print_exc(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("print_exc", keywords)?;
+    let text = match vm.caught_traceback("print_exc", args) {
+        Ok(text) => text,
+        Err(error) => return Err(vm.raise_error(error.kind, error.message)),
+    };
+    vm.output.write(&text);
+    Ok(Rc::new(ObjectType::Nil))
+}
+
+fn string(value: impl Into<String>) -> Object {
+    Rc::new(ObjectType::String(value.into()))
+}
+
+fn new_instance(class: &'static LocalKey<Rc<ClassObject>>, fields: Vec<(&str, Object)>) -> Object {
+    let mut instance = InstanceObject::new(class.with(Rc::clone));
+    for (name, value) in fields {
+        instance.set_field(name.to_string(), value);
+    }
+    Rc::new(ObjectType::Instance(gc::new_instance(instance)))
+}
+
+/// The chain of `traceback` objects for `frames`, outermost first, or None
+/// when there are no frames.
+pub fn traceback_object(frames: &[TraceFrame]) -> Object {
+    frames
+        .iter()
+        .rev()
+        .fold(Rc::new(ObjectType::Nil), |next, frame| {
+            let line = frame.line.map_or_else(
+                || Rc::new(ObjectType::Nil),
+                |line| values::integer(line as i64),
+            );
+            let code = new_instance(
+                &CODE_CLASS,
+                vec![
+                    ("co_name", string(frame.function.as_str())),
+                    ("co_filename", string(frame.file.as_str())),
+                ],
+            );
+            let frame = new_instance(
+                &FRAME_CLASS,
+                vec![("f_code", code), ("f_lineno", line.clone())],
+            );
+            new_instance(
+                &TRACEBACK_CLASS,
+                vec![("tb_frame", frame), ("tb_lineno", line), ("tb_next", next)],
+            )
+        })
+}
+
+/// Reads the field `name` of `value`, if it is an instance holding one.
+fn field(value: &Object, name: &str) -> Option<Object> {
+    match &**value {
+        ObjectType::Instance(instance) => instance.borrow().get_field(name),
+        _ => None,
+    }
+}
+
+/// One line of `format_tb` for the traceback object `tb`, in CPython's
+/// layout, or None if `tb` is not a traceback object.
+fn format_entry(tb: &Object) -> Option<String> {
+    let code = field(&field(tb, "tb_frame")?, "f_code")?;
+    let text = |name| match field(&code, name).as_deref() {
+        Some(ObjectType::String(text)) => text.clone(),
+        _ => String::new(),
+    };
+    Some(match field(tb, "tb_lineno").as_deref() {
+        Some(ObjectType::Integer(line)) => format!(
+            "  File \"{}\", line {}, in {}\n",
+            text("co_filename"),
+            line,
+            text("co_name")
+        ),
+        _ => format!(
+            "  File \"{}\", in {}\n",
+            text("co_filename"),
+            text("co_name")
+        ),
+    })
+}
+
+/// The lines for the chain of traceback objects starting at `tb`.
+fn format_entries(tb: &Object) -> Result<Vec<String>, NativeError> {
+    let mut lines = Vec::new();
+    let mut current = tb.clone();
+    while !matches!(&*current, ObjectType::Nil) {
+        let Some(line) = format_entry(&current) else {
+            return Err(NativeError::type_error(format!(
+                "expected a traceback object, not '{}'",
+                current.type_name()
+            )));
+        };
+        lines.push(line);
+        current = field(&current, "tb_next").unwrap_or_else(|| Rc::new(ObjectType::Nil));
+    }
+    Ok(lines)
+}
+
+/// The lines CPython prints for an uncaught `exception`: its traceback, if it
+/// has one, then its class and message.
+fn format_exception_lines(exception: &Object) -> Result<Vec<String>, NativeError> {
+    let ObjectType::Instance(instance) = &**exception else {
+        return Err(NativeError::type_error(format!(
+            "expected an exception, not '{}'",
+            exception.type_name()
+        )));
+    };
+    let instance = instance.borrow();
+    if !is_exception_class(&instance.class) {
+        return Err(NativeError::type_error(format!(
+            "expected an exception, not '{}'",
+            instance.class.name
+        )));
+    }
+    let mut lines = Vec::new();
+    if let Some(tb) = instance.get_field("__traceback__") {
+        let entries = format_entries(&tb)?;
+        if !entries.is_empty() {
+            lines.push(HEADER.to_string());
+            lines.extend(entries);
+        }
+    }
+    lines.push(match exception_str(&instance).unwrap_or_default() {
+        message if message.is_empty() => format!("{}\n", instance.class.name),
+        message => format!("{}: {}\n", instance.class.name, message),
+    });
+    Ok(lines)
+}
+
+fn string_list(lines: Vec<String>) -> Object {
+    Rc::new(ObjectType::List(lines.into_iter().map(string).collect()))
+}
+
+/// `traceback.format_tb(tb)`: a line for each entry of a traceback.
+fn traceback_format_tb(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [tb] = args else {
+        return Err(NativeError::type_error(format!(
+            "format_tb() takes 1 argument ({} given)",
+            args.len()
+        )));
+    };
+    Ok(string_list(format_entries(tb)?))
+}
+
+/// `traceback.format_exception(exc)`: the lines printed for an exception.
+fn traceback_format_exception(
+    args: &[Object],
+    _class_context: Option<Rc<ClassObject>>,
+) -> NativeResult {
+    let [exception] = args else {
+        return Err(NativeError::type_error(format!(
+            "format_exception() takes 1 argument ({} given)",
+            args.len()
+        )));
+    };
+    Ok(string_list(format_exception_lines(exception)?))
+}
+
+impl VM {
+    /// The traceback of the exception being handled, as `name` formats it
+    /// when called with `args`.
+    fn caught_traceback(&self, name: &str, args: &[Object]) -> Result<String, NativeError> {
+        if !args.is_empty() {
+            return Err(NativeError::type_error(format!(
+                "{}() takes no arguments ({} given)",
+                name,
+                args.len()
+            )));
+        }
+        // Outside an except clause CPython formats the missing exception as None
+        match self.caught_exception() {
+            Some(exception) => Ok(format_exception_lines(exception)?.concat()),
+            None => Ok("NoneType: None\n".to_string()),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! The native `warnings` module: `warn(message, category=UserWarning)`.
+//!
+//! A warning is written as `file:line: Category: message` to the VM's
+//! warning output, standard error unless the embedder sets another (see
+//! `VM::set_warning_output`). By default each is written once for each place
+//! it is raised from, as CPython's `default` action does; the action set
+//! with `VM::set_warning_action`, or the CLI's `--warnings=` flag, can write
+//! every one, ignore them all or raise them as exceptions instead.
+//!
+//! `warn` needs the caller's frame and the VM's record of warnings already
+//! written, so it is a native that runs on the VM, as the `sys` functions
+//! are.
+
+use crate::object::{ClassObject, ModuleObject, NativeError, Object, ObjectType};
+use crate::platform::Output;
+use crate::sync::Rc;
+use crate::vm::exceptions::{exception_class, exception_str, is_subclass, new_exception};
+use crate::vm::{InterpretResult, VM};
+use std::collections::{HashMap, HashSet};
+
+/// What happens to a warning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WarningAction {
+    /// Write it the first time it is raised from each place
+    #[default]
+    Default,
+    /// Write it every time
+    Always,
+    /// Write nothing
+    Ignore,
+    /// Raise it as an exception
+    Error,
+}
+
+impl WarningAction {
+    /// The action called `name`, as CPython's `-W` option names them.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(WarningAction::Default),
+            "always" => Some(WarningAction::Always),
+            "ignore" => Some(WarningAction::Ignore),
+            "error" => Some(WarningAction::Error),
+            _ => None,
+        }
+    }
+}
+
+/// The VM's warning settings and the warnings it has written.
+#[derive(Debug)]
+pub struct Warnings {
+    pub action: WarningAction,
+    pub output: Output,
+    /// The file, line, category and message of each warning written
+    shown: HashSet<(String, Option<usize>, String, String)>,
+}
+
+impl Default for Warnings {
+    fn default() -> Self {
+        Warnings {
+            action: WarningAction::default(),
+            output: Output::standard_error(),
+            shown: HashSet::new(),
+        }
+    }
+}
+
+/// Builds the `warnings` module object.
+pub fn module() -> Object {
+    Rc::new(ObjectType::Module(Rc::new(ModuleObject {
+        name: "warnings".to_string(),
+        attributes: HashMap::from([(
+            "warn".to_string(),
+            Rc::new(ObjectType::VmNativeFunction("warn".to_string(), warn)),
+        )]),
+    })))
+}
+
+/// `warn(message, category=UserWarning)`.
+fn warn(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("warn", keywords)?;
+    vm.warn(args)?;
+    Ok(Rc::new(ObjectType::Nil))
+}
+
+/// Reads the arguments of `warn`: the warning's category, its message and
+/// the exception raising it would raise. A message that is itself a warning
+/// gives its own category.
+fn warning_arguments(args: &[Object]) -> Result<(Rc<ClassObject>, String, Object), NativeError> {
+    let warning = exception_class("Warning");
+    let (message, category) = match args {
+        [message] => (message, None),
+        [message, category] => (message, Some(category)),
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "warn() takes 1 or 2 arguments ({} given)",
+                args.len()
+            )))
+        }
+    };
+    if let ObjectType::Instance(instance) = &**message {
+        let instance = instance.borrow();
+        if is_subclass(&instance.class, &warning) {
+            let text = exception_str(&instance).unwrap_or_default();
+            return Ok((instance.class.clone(), text, message.clone()));
+        }
+    }
+    let category = match category.map(|category| &**category) {
+        None | Some(ObjectType::Nil) => exception_class("UserWarning"),
+        Some(ObjectType::Class(class)) if is_subclass(class, &warning) => class.clone(),
+        Some(other) => {
+            return Err(NativeError::type_error(format!(
+                "category must be a Warning subclass, not '{}'",
+                other.type_name()
+            )))
+        }
+    };
+    let text = match &**message {
+        ObjectType::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let exception = new_exception(
+        category.clone(),
+        vec![Rc::new(ObjectType::String(text.clone()))],
+    );
+    Ok((category, text, exception))
+}
+
+impl VM {
+    /// Writes, ignores or raises the warning `args` describes, as the
+    /// warning action says.
+    fn warn(&mut self, args: &[Object]) -> Result<(), InterpretResult> {
+        let (category, text, exception) = match warning_arguments(args) {
+            Ok(warning) => warning,
+            Err(error) => return Err(self.raise_error(error.kind, error.message)),
+        };
+        match self.warnings.action {
+            WarningAction::Ignore => {}
+            WarningAction::Error => return Err(self.raise_exception(exception)),
+            action => {
+                // The warning is reported where warn() was called
+                let (file, line) = self.frames.last().map_or_else(
+                    || ("<script>".to_string(), None),
+                    |frame| {
+                        let line = frame.chunk.line_at(frame.ip.saturating_sub(1));
+                        (self.module_file(&frame.function.module), line)
+                    },
+                );
+                let key = (file, line, category.name.clone(), text);
+                if action == WarningAction::Always || !self.warnings.shown.contains(&key) {
+                    let (file, line, category, text) = &key;
+                    let location = match line {
+                        Some(line) => format!("{}:{}", file, line),
+                        None => file.clone(),
+                    };
+                    self.warnings
+                        .output
+                        .write(&format!("{}: {}: {}\n", location, category, text));
+                    self.warnings.shown.insert(key);
+                }
+            }
+        }
+        Ok(())
+    }
+}
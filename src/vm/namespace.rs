@@ -0,0 +1,242 @@
+//! Hash map type backing the VM's global and builtin namespaces.
+//!
+//! Every `OpGetGlobal` hashes the variable name, which made the default
+//! SipHash hasher the single most expensive part of a recursive call such as
+//! `fib(n - 1)`. Namespaces only ever hold identifier keys produced by our own
+//! compiler, so they use the much cheaper (non-DoS-resistant) FxHash instead.
+//!
+//! Even so, a global is looked up far more often than it changes, so
+//! `GlobalCache` remembers what each `OpGetGlobal` name was last found to be.
+//! Every change to a namespace gives it a new version, and a cached value
+//! stands only while the globals and builtins it was found through are still
+//! at the versions they had then.
+
+use crate::object::{Object, ObjectType};
+use crate::sync::{Rc, Weak};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+type Names = HashMap<String, Object, BuildHasherDefault<FxHasher>>;
+
+/// The next version handed out, shared by all namespaces so that no two
+/// states of any of them have the same one (except the empty state, 0).
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+/// A name → value mapping for globals and builtins.
+///
+/// It reads like the map it wraps, but is only changed through its own
+/// methods, each of which moves it to a new version.
+#[derive(Clone, Debug, Default)]
+pub struct Namespace {
+    names: Names,
+    version: u64,
+}
+
+impl Namespace {
+    /// Identifies the namespace's current contents: a namespace has the
+    /// same version as before exactly when nothing has changed in between.
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn changed(&mut self) {
+        self.version = NEXT_VERSION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Binds `name` to `value`, returning the value it replaced.
+    pub fn insert(&mut self, name: String, value: Object) -> Option<Object> {
+        self.changed();
+        self.names.insert(name, value)
+    }
+
+    /// The value bound to `name`, to change in place.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Object> {
+        self.changed();
+        self.names.get_mut(name)
+    }
+
+    /// Unbinds `name`, returning its value if it was bound.
+    pub fn remove<Q>(&mut self, name: &Q) -> Option<Object>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let removed = self.names.remove(name);
+        if removed.is_some() {
+            self.changed();
+        }
+        removed
+    }
+}
+
+/// What a name was found to be by `OpGetGlobal`, and the versions of the
+/// namespaces it was looked up in. The name and value are held weakly, so
+/// the cache neither keeps a value alive (or out of reach of the cycle
+/// collector) nor lets the name's address be reused while it is cached.
+struct CachedGlobal {
+    name: Weak<ObjectType>,
+    globals: u64,
+    builtins: u64,
+    value: Weak<ObjectType>,
+}
+
+/// Number of entries in a `GlobalCache`.
+const GLOBAL_CACHE_SIZE: usize = 256;
+
+/// The values of the globals read last, found by the address of the name
+/// constant reading them.
+pub struct GlobalCache {
+    entries: Vec<Option<CachedGlobal>>,
+}
+
+impl GlobalCache {
+    pub fn new() -> Self {
+        GlobalCache {
+            entries: (0..GLOBAL_CACHE_SIZE).map(|_| None).collect(),
+        }
+    }
+
+    fn index(name: &Object) -> usize {
+        (Rc::as_ptr(name) as *const () as usize >> 4) % GLOBAL_CACHE_SIZE
+    }
+
+    /// The value `name` was last found to have, if neither `globals` nor
+    /// `builtins` has changed since.
+    #[inline(always)]
+    pub fn get(&self, name: &Object, globals: &Namespace, builtins: &Namespace) -> Option<Object> {
+        let entry = self.entries[Self::index(name)].as_ref()?;
+        if entry.name.as_ptr() != Rc::as_ptr(name)
+            || entry.globals != globals.version
+            || entry.builtins != builtins.version
+        {
+            return None;
+        }
+        entry.value.upgrade()
+    }
+
+    /// Remembers that `name` was found to be `value` in `globals` or
+    /// `builtins`, as they are now.
+    pub fn insert(
+        &mut self,
+        name: &Object,
+        globals: &Namespace,
+        builtins: &Namespace,
+        value: &Object,
+    ) {
+        self.entries[Self::index(name)] = Some(CachedGlobal {
+            name: Rc::downgrade(name),
+            globals: globals.version,
+            builtins: builtins.version,
+            value: Rc::downgrade(value),
+        });
+    }
+}
+
+impl Default for GlobalCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Namespace {
+    type Target = Names;
+
+    fn deref(&self) -> &Names {
+        &self.names
+    }
+}
+
+impl FromIterator<(String, Object)> for Namespace {
+    fn from_iter<I: IntoIterator<Item = (String, Object)>>(entries: I) -> Self {
+        let mut namespace = Namespace {
+            names: entries.into_iter().collect(),
+            version: 0,
+        };
+        if !namespace.names.is_empty() {
+            namespace.changed();
+        }
+        namespace
+    }
+}
+
+impl Extend<(String, Object)> for Namespace {
+    fn extend<I: IntoIterator<Item = (String, Object)>>(&mut self, entries: I) {
+        self.changed();
+        self.names.extend(entries);
+    }
+}
+
+impl IntoIterator for Namespace {
+    type Item = (String, Object);
+    type IntoIter = std::collections::hash_map::IntoIter<String, Object>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.names.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Namespace {
+    type Item = (&'a String, &'a Object);
+    type IntoIter = std::collections::hash_map::Iter<'a, String, Object>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.names.iter()
+    }
+}
+
+/// Multiplicative word-at-a-time hasher, as used by rustc (FxHash).
+#[derive(Default, Clone, Copy)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    #[inline]
+    fn add_word(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    /// Hashes eight bytes at a time, then the four, two and one left, so
+    /// short names hash without copying their tail into a buffer.
+    #[inline]
+    fn write(&mut self, mut bytes: &[u8]) {
+        while let Some((word, rest)) = bytes.split_first_chunk::<8>() {
+            self.add_word(u64::from_le_bytes(*word));
+            bytes = rest;
+        }
+        if let Some((word, rest)) = bytes.split_first_chunk::<4>() {
+            self.add_word(u32::from_le_bytes(*word) as u64);
+            bytes = rest;
+        }
+        if let Some((word, rest)) = bytes.split_first_chunk::<2>() {
+            self.add_word(u16::from_le_bytes(*word) as u64);
+            bytes = rest;
+        }
+        if let Some(&byte) = bytes.first() {
+            self.add_word(byte as u64);
+        }
+    }
+
+    #[inline]
+    fn write_u8(&mut self, value: u8) {
+        self.add_word(value as u64);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, value: usize) {
+        self.add_word(value as u64);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
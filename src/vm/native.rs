@@ -1,24 +1,347 @@
-use crate::object::{ClassObject, Object, ObjectType};
-use std::collections::HashMap;
-use std::rc::Rc;
+use crate::object::{
+    ClassObject, DictEntries, IteratorObject, NativeError, NativeResult, Object, ObjectType,
+};
+use crate::sync::Rc;
+use crate::sync::RefCell;
+use crate::vm::collections::{collect_iterable, not_iterable_message};
+use crate::vm::evaluation;
+use crate::vm::formatting;
+use crate::vm::hashing;
+use crate::vm::help;
+use crate::vm::namespace::Namespace;
+use crate::vm::opcodes::arithmetic::{arithmetic_error, op_add, op_divmod};
+use crate::vm::opcodes::attributes::attribute_names;
+use crate::vm::opcodes::builtins::{op_round, op_type, round_error};
+use crate::vm::opcodes::collections::{op_len, range_elements};
+use crate::vm::values::{integer, is_truthy};
+use crate::vm::{protocols, reflection, sorting, InterpretResult, VM};
 
-/// Registers all builtin native functions into the global namespace.
+/// Registers all builtin native functions into the builtins namespace.
 ///
-/// This function populates the globals HashMap with native function implementations
-/// that are available to all Python code. Currently, this includes:
+/// This function populates the builtins namespace with native function implementations
+/// that are available to all Python code. Globals are searched first, so user
+/// code can shadow any of these names. Currently, this includes:
 /// - `super()` - Access parent class methods in inheritance hierarchies
+/// - `classmethod()` - Make a method receive its class rather than an instance
+/// - `zip()` - Iterate over several iterables in lockstep
+/// - `divmod()` - Floored quotient and remainder of two numbers
+/// - `pow()` - Powers, optionally reduced by a modulus
+/// - `abs()` - Absolute value of a number
+/// - `chr()`, `ord()` - Convert between characters and Unicode code points
+/// - `id()`, `hash()` - Object identity, and the hash dict keys are compared by;
+///   `hash()` runs on the VM since an instance's `__hash__` is bytecode
+/// - `format()` - Format a value by a format spec, as f-string fields do; it
+///   runs on the VM since formatting an instance runs its `__str__`
+/// - `str()`, `repr()` - The text of a value as `print()` and the REPL show
+///   it; these run on the VM for instances with `__str__`/`__repr__`
+/// - `getattr()`, `setattr()`, `hasattr()` - Attribute access by name;
+///   `getattr()` and `hasattr()` run on the VM since looking an attribute up
+///   can read its stack
+/// - `callable()`, `vars()`, `dir()` - Introspection of objects and their
+///   attributes, including those inherited from base classes
+/// - `eval()`, `exec()` - Compile and run source text against the globals;
+///   these run on the VM since they run bytecode
+/// - `help()` - Show the signature and docstring of a function, class or
+///   builtin; it runs on the VM since it writes to the VM's output
+/// - `list()`, `tuple()`, `dict()`, `set()` - Build collections from
+///   iterables; `dict()` and `set()` run on the VM, which hashes and compares
+///   keys as `hash()` and `==` do
+/// - `int()` - Convert numbers and strings, in any base, to integers
+/// - `hex()`, `oct()`, `bin()` - Format integers in base 16, 8 and 2
+/// - `iter()`, `next()` - Step through an iterable by hand; these run on the
+///   VM for generators and for instances defining `__iter__`/`__next__`
+/// - `sum()`, `any()`, `all()` - Reduce an iterable to a total or a truth
+///   value
+/// - `sorted()` - A new sorted list; it runs on the VM since a `key=`
+///   function has to run as bytecode
+/// - `min()`, `max()` - The least or greatest of an iterable or of the
+///   arguments; these run on the VM for their `key=`/`default=` keywords
+/// - `len()`, `round()`, `range()`, `type()` - Also reachable through
+///   dedicated opcodes when the compiler can prove the name is not shadowed;
+///   `len()` runs on the VM since an instance's `__len__` is bytecode
 ///
 /// # Arguments
-/// * `globals` - Mutable reference to the VM's global namespace
-pub fn register_builtins(globals: &mut HashMap<String, Object>) {
+/// * `builtins` - Mutable reference to the VM's builtins namespace
+pub fn register_builtins(builtins: &mut Namespace) {
     // Register the super() builtin
-    globals.insert(
+    builtins.insert(
         "super".to_string(),
         Rc::new(ObjectType::NativeFunction(
             "super".to_string(),
             native_super,
         )),
     );
+
+    builtins.insert(
+        "classmethod".to_string(),
+        Rc::new(ObjectType::NativeFunction(
+            "classmethod".to_string(),
+            native_classmethod,
+        )),
+    );
+
+    builtins.insert(
+        "zip".to_string(),
+        Rc::new(ObjectType::NativeFunction("zip".to_string(), native_zip)),
+    );
+
+    builtins.insert(
+        "len".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "len".to_string(),
+            protocols::len,
+        )),
+    );
+
+    builtins.insert(
+        "round".to_string(),
+        Rc::new(ObjectType::NativeFunction(
+            "round".to_string(),
+            native_round,
+        )),
+    );
+
+    builtins.insert(
+        "range".to_string(),
+        Rc::new(ObjectType::NativeFunction(
+            "range".to_string(),
+            native_range,
+        )),
+    );
+
+    builtins.insert(
+        "divmod".to_string(),
+        Rc::new(ObjectType::NativeFunction(
+            "divmod".to_string(),
+            native_divmod,
+        )),
+    );
+
+    builtins.insert(
+        "pow".to_string(),
+        Rc::new(ObjectType::NativeFunction("pow".to_string(), native_pow)),
+    );
+
+    builtins.insert(
+        "abs".to_string(),
+        Rc::new(ObjectType::NativeFunction("abs".to_string(), native_abs)),
+    );
+
+    builtins.insert(
+        "chr".to_string(),
+        Rc::new(ObjectType::NativeFunction("chr".to_string(), native_chr)),
+    );
+
+    builtins.insert(
+        "ord".to_string(),
+        Rc::new(ObjectType::NativeFunction("ord".to_string(), native_ord)),
+    );
+
+    builtins.insert(
+        "id".to_string(),
+        Rc::new(ObjectType::NativeFunction("id".to_string(), native_id)),
+    );
+
+    builtins.insert(
+        "hash".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "hash".to_string(),
+            hashing::hash,
+        )),
+    );
+
+    builtins.insert(
+        "format".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "format".to_string(),
+            formatting::format,
+        )),
+    );
+
+    builtins.insert(
+        "str".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "str".to_string(),
+            formatting::str,
+        )),
+    );
+
+    builtins.insert(
+        "repr".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "repr".to_string(),
+            formatting::repr,
+        )),
+    );
+
+    builtins.insert(
+        "getattr".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "getattr".to_string(),
+            reflection::getattr,
+        )),
+    );
+
+    builtins.insert(
+        "setattr".to_string(),
+        Rc::new(ObjectType::NativeFunction(
+            "setattr".to_string(),
+            reflection::native_setattr,
+        )),
+    );
+
+    builtins.insert(
+        "hasattr".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "hasattr".to_string(),
+            reflection::hasattr,
+        )),
+    );
+
+    builtins.insert(
+        "eval".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "eval".to_string(),
+            evaluation::eval,
+        )),
+    );
+
+    builtins.insert(
+        "exec".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "exec".to_string(),
+            evaluation::exec,
+        )),
+    );
+
+    builtins.insert(
+        "help".to_string(),
+        Rc::new(ObjectType::VmNativeFunction("help".to_string(), help::help)),
+    );
+
+    builtins.insert(
+        "callable".to_string(),
+        Rc::new(ObjectType::NativeFunction(
+            "callable".to_string(),
+            native_callable,
+        )),
+    );
+
+    builtins.insert(
+        "vars".to_string(),
+        Rc::new(ObjectType::NativeFunction("vars".to_string(), native_vars)),
+    );
+
+    builtins.insert(
+        "dir".to_string(),
+        Rc::new(ObjectType::NativeFunction("dir".to_string(), native_dir)),
+    );
+
+    builtins.insert(
+        "list".to_string(),
+        Rc::new(ObjectType::NativeFunction("list".to_string(), native_list)),
+    );
+
+    builtins.insert(
+        "tuple".to_string(),
+        Rc::new(ObjectType::NativeFunction(
+            "tuple".to_string(),
+            native_tuple,
+        )),
+    );
+
+    builtins.insert(
+        "dict".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "dict".to_string(),
+            native_dict,
+        )),
+    );
+
+    builtins.insert(
+        "set".to_string(),
+        Rc::new(ObjectType::VmNativeFunction("set".to_string(), native_set)),
+    );
+
+    builtins.insert(
+        "int".to_string(),
+        Rc::new(ObjectType::NativeFunction("int".to_string(), native_int)),
+    );
+
+    builtins.insert(
+        "hex".to_string(),
+        Rc::new(ObjectType::NativeFunction("hex".to_string(), native_hex)),
+    );
+
+    builtins.insert(
+        "oct".to_string(),
+        Rc::new(ObjectType::NativeFunction("oct".to_string(), native_oct)),
+    );
+
+    builtins.insert(
+        "bin".to_string(),
+        Rc::new(ObjectType::NativeFunction("bin".to_string(), native_bin)),
+    );
+
+    builtins.insert(
+        "iter".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "iter".to_string(),
+            protocols::iter,
+        )),
+    );
+
+    builtins.insert(
+        "next".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "next".to_string(),
+            protocols::next,
+        )),
+    );
+
+    builtins.insert(
+        "sum".to_string(),
+        Rc::new(ObjectType::NativeFunction("sum".to_string(), native_sum)),
+    );
+
+    builtins.insert(
+        "any".to_string(),
+        Rc::new(ObjectType::NativeFunction("any".to_string(), native_any)),
+    );
+
+    builtins.insert(
+        "all".to_string(),
+        Rc::new(ObjectType::NativeFunction("all".to_string(), native_all)),
+    );
+
+    builtins.insert(
+        "sorted".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "sorted".to_string(),
+            sorting::sorted,
+        )),
+    );
+
+    builtins.insert(
+        "min".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "min".to_string(),
+            sorting::min,
+        )),
+    );
+
+    builtins.insert(
+        "max".to_string(),
+        Rc::new(ObjectType::VmNativeFunction(
+            "max".to_string(),
+            sorting::max,
+        )),
+    );
+
+    builtins.insert(
+        "type".to_string(),
+        Rc::new(ObjectType::NativeFunction("type".to_string(), native_type)),
+    );
 }
 
 /// Native implementation of the super() builtin function.
@@ -54,17 +377,16 @@ pub fn register_builtins(globals: &mut HashMap<String, Object>) {
 ///         super().greet()  # Calls Parent.greet()
 ///         print("Hello from Child")
 /// ```
-pub fn native_super(
-    args: &[Object],
-    class_context: Option<Rc<ClassObject>>,
-) -> Result<Object, String> {
+pub fn native_super(args: &[Object], class_context: Option<Rc<ClassObject>>) -> NativeResult {
     // super() should be called with no arguments or with instance explicitly
     if args.is_empty() {
-        return Err("super() requires access to self".to_string());
+        return Err(NativeError::type_error("super() requires access to self"));
     }
 
     if args.len() != 1 {
-        return Err("super() takes at most 1 argument (self)".to_string());
+        return Err(NativeError::type_error(
+            "super() takes at most 1 argument (self)",
+        ));
     }
 
     // Get the current class context
@@ -83,3 +405,1133 @@ pub fn native_super(
     // Return a SuperProxy that will handle attribute lookups in the parent class
     Ok(Rc::new(ObjectType::SuperProxy(instance, parent_class)))
 }
+
+/// Native implementation of the classmethod() builtin, usually applied as
+/// the `@classmethod` decorator.
+///
+/// The wrapped function is bound to the class whenever it is looked up,
+/// through the class or any instance of it, so it receives the class (or a
+/// subclass it was looked up on) as its first argument.
+pub fn native_classmethod(
+    args: &[Object],
+    _class_context: Option<Rc<ClassObject>>,
+) -> NativeResult {
+    let [function] = args else {
+        return Err(NativeError::type_error(format!(
+            "classmethod expected 1 argument, got {}",
+            args.len()
+        )));
+    };
+    Ok(Rc::new(ObjectType::ClassMethod(function.clone())))
+}
+
+/// Native implementation of the zip() builtin function.
+///
+/// Returns a lazy `zip` iterator that yields a tuple containing one item from
+/// each argument per step, stopping as soon as the shortest argument is exhausted.
+/// Any iterable is accepted: lists, tuples, strings, and other iterators.
+///
+/// # Errors
+/// Returns an error if any argument is not iterable.
+///
+/// # Example Python Usage
+/// ```python
+/// pairs = list(zip(["Ann", "Ben"], [88, 93]))  # [('Ann', 88), ('Ben', 93)]
+/// columns = list(zip(*matrix))                # transposes a list of rows
+/// ```
+pub fn native_zip(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let mut iterators = Vec::with_capacity(args.len());
+    for (position, arg) in args.iter().enumerate() {
+        let iterator = IteratorObject::from_iterable(arg)
+            .ok_or_else(|| format!("zip argument #{} must support iteration", position + 1))?;
+        iterators.push(iterator);
+    }
+
+    Ok(Rc::new(ObjectType::Iterator(Rc::new(RefCell::new(
+        IteratorObject::Zip(iterators),
+    )))))
+}
+
+/// Native implementation of the len() builtin function.
+///
+/// Returns the number of items in a list, tuple, dict, or string.
+///
+/// # Errors
+/// Returns an error unless called with exactly one sized argument.
+pub fn native_len(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "len() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+
+    op_len(value.clone())
+        .map(|len| Rc::new(ObjectType::Integer(len)))
+        .map_err(|_| {
+            NativeError::type_error(format!(
+                "object of type '{}' has no len()",
+                value.type_name()
+            ))
+        })
+}
+
+/// Native implementation of the round() builtin function.
+///
+/// With one argument, rounds to the nearest integer (ties go to the even
+/// neighbour, as in Python). With two arguments, rounds to the given number of
+/// decimal places, which may be negative, keeping the value's type.
+///
+/// # Errors
+/// Returns an error for a non-numeric value, a non-integer digit count, a
+/// float that is infinite or NaN with one argument, or a wrong number of
+/// arguments.
+///
+/// # Example Python Usage
+/// ```python
+/// round(2.5)        # 2
+/// round(3.14159, 2) # 3.14
+/// round(2.675, 2)   # 2.67, as 2.675 is stored as 2.67499999...
+/// round(1250, -2)   # 1200
+/// ```
+pub fn native_round(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    match args {
+        [value] => match &**value {
+            ObjectType::Integer(_) => Ok(value.clone()),
+            ObjectType::Float(number) if number.is_nan() => Err(NativeError::value_error(
+                "cannot convert float NaN to integer",
+            )),
+            ObjectType::Float(number) if number.is_infinite() => Err(NativeError::new(
+                "OverflowError",
+                "cannot convert float infinity to integer",
+            )),
+            ObjectType::Float(number) => {
+                let rounded = number.round_ties_even();
+                // i64::MAX as f64 rounds up to 2^63, which is out of range
+                if rounded < -(2f64.powi(63)) || rounded >= 2f64.powi(63) {
+                    return Err(NativeError::new("OverflowError", "integer overflow"));
+                }
+                Ok(Rc::new(ObjectType::Integer(rounded as i64)))
+            }
+            other => Err(NativeError::type_error(format!(
+                "type {} doesn't define __round__ method",
+                other.type_name()
+            ))),
+        },
+        [value, digits] => op_round(value.clone(), digits.clone()).map_err(|_| {
+            let (kind, message) = round_error(value, digits);
+            NativeError::new(kind, message)
+        }),
+        _ => Err(NativeError::type_error(format!(
+            "round() takes 1 or 2 arguments ({} given)",
+            args.len()
+        ))),
+    }
+}
+
+/// Native implementation of the divmod() builtin function.
+///
+/// Returns `(a // b, a % b)` as a tuple. Two integers give integers; a float
+/// operand gives floats.
+///
+/// # Example Python Usage
+/// ```python
+/// divmod(7, 2)    # (3, 1)
+/// divmod(-7, 2)   # (-4, 1)
+/// divmod(7.5, 2)  # (3.0, 1.5)
+/// ```
+pub fn native_divmod(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [a, b] = args else {
+        return Err(NativeError::type_error(format!(
+            "divmod expected 2 arguments, got {}",
+            args.len()
+        )));
+    };
+    op_divmod(a.clone(), b.clone()).map_err(|_| {
+        let (kind, message) = arithmetic_error("divmod()", a, b);
+        NativeError::new(kind, message)
+    })
+}
+
+/// Native implementation of the pow() builtin function.
+///
+/// `pow(base, exp)` raises `base` to `exp`: two integers give an integer
+/// unless the exponent is negative, and a float operand gives a float.
+/// `pow(base, exp, mod)` takes only integers and computes `base ** exp % mod`
+/// by modular exponentiation, so the power itself is never built; a negative
+/// exponent raises the modular inverse of `base` instead.
+///
+/// # Errors
+/// Returns an error for non-numeric arguments, zero raised to a negative
+/// power, a negative float raised to a fractional power, a result out of
+/// range, a zero modulus, a base with no inverse for the modulus, or a wrong
+/// number of arguments.
+///
+/// # Example Python Usage
+/// ```python
+/// pow(2, 10)       # 1024
+/// pow(2, -1)       # 0.5
+/// pow(3, 200, 7)   # 2
+/// pow(3, -1, 7)    # 5, since 3 * 5 % 7 == 1
+/// ```
+pub fn native_pow(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    match args {
+        [base, exponent] => power(base, exponent),
+        [base, exponent, modulus] => match (&**base, &**exponent, &**modulus) {
+            (
+                ObjectType::Integer(base),
+                ObjectType::Integer(exponent),
+                ObjectType::Integer(modulus),
+            ) => modular_power(*base, *exponent, *modulus)
+                .map(|result| Rc::new(ObjectType::Integer(result))),
+            _ if args.iter().all(|arg| is_number(arg)) => Err(NativeError::type_error(
+                "pow() 3rd argument not allowed unless all arguments are integers",
+            )),
+            _ => Err(NativeError::type_error(format!(
+                "unsupported operand type(s) for ** or pow(): '{}', '{}', '{}'",
+                base.type_name(),
+                exponent.type_name(),
+                modulus.type_name()
+            ))),
+        },
+        [] => Err(NativeError::type_error(
+            "pow() missing required argument 'base' (pos 1)",
+        )),
+        [_] => Err(NativeError::type_error(
+            "pow() missing required argument 'exp' (pos 2)",
+        )),
+        _ => Err(NativeError::type_error(format!(
+            "pow() takes at most 3 arguments ({} given)",
+            args.len()
+        ))),
+    }
+}
+
+fn is_number(value: &ObjectType) -> bool {
+    matches!(value, ObjectType::Integer(_) | ObjectType::Float(_))
+}
+
+/// `base ** exponent` for two numbers.
+fn power(base: &Object, exponent: &Object) -> NativeResult {
+    let (x, y) = match (&**base, &**exponent) {
+        (ObjectType::Integer(base), ObjectType::Integer(exponent)) if *exponent >= 0 => {
+            let result = match u32::try_from(*exponent) {
+                Ok(exponent) => base.checked_pow(exponent),
+                // Only these bases stay in range for such large exponents
+                Err(_) => match base {
+                    0 | 1 => Some(*base),
+                    -1 => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+                    _ => None,
+                },
+            };
+            return result
+                .map(|result| Rc::new(ObjectType::Integer(result)))
+                .ok_or_else(|| NativeError::new("OverflowError", "integer overflow"));
+        }
+        (ObjectType::Integer(x), ObjectType::Integer(y)) => (*x as f64, *y as f64),
+        (ObjectType::Integer(x), ObjectType::Float(y)) => (*x as f64, *y),
+        (ObjectType::Float(x), ObjectType::Integer(y)) => (*x, *y as f64),
+        (ObjectType::Float(x), ObjectType::Float(y)) => (*x, *y),
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "unsupported operand type(s) for ** or pow(): '{}' and '{}'",
+                base.type_name(),
+                exponent.type_name()
+            )))
+        }
+    };
+
+    if x == 0.0 && y < 0.0 {
+        return Err(NativeError::new(
+            "ZeroDivisionError",
+            "0.0 cannot be raised to a negative power",
+        ));
+    }
+    if x < 0.0 && y.is_finite() && y.fract() != 0.0 {
+        // CPython returns a complex number here, which has no equivalent
+        return Err(NativeError::value_error(
+            "negative number cannot be raised to a fractional power",
+        ));
+    }
+    let result = x.powf(y);
+    if result.is_infinite() && x.is_finite() && y.is_finite() {
+        return Err(NativeError::new(
+            "OverflowError",
+            "(34, 'Numerical result out of range')",
+        ));
+    }
+    Ok(Rc::new(ObjectType::Float(result)))
+}
+
+/// `base ** exponent % modulus` by square-and-multiply, with the result
+/// taking the sign of the modulus as `%` does.
+fn modular_power(base: i64, exponent: i64, modulus: i64) -> Result<i64, NativeError> {
+    if modulus == 0 {
+        return Err(NativeError::value_error("pow() 3rd argument cannot be 0"));
+    }
+    let size = (modulus as i128).abs();
+    let mut base = (base as i128).rem_euclid(size);
+    if exponent < 0 {
+        base = modular_inverse(base, size).ok_or_else(|| {
+            NativeError::value_error("base is not invertible for the given modulus")
+        })?;
+    }
+
+    let mut exponent = exponent.unsigned_abs();
+    let mut result = 1 % size;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base % size;
+        }
+        base = base * base % size;
+        exponent >>= 1;
+    }
+    if modulus < 0 && result != 0 {
+        result -= size;
+    }
+    Ok(result as i64)
+}
+
+/// The inverse of `value` modulo `size`, found with the extended Euclidean
+/// algorithm, if the two are coprime.
+fn modular_inverse(value: i128, size: i128) -> Option<i128> {
+    let (mut old_r, mut r) = (value, size);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+    }
+    (old_r == 1).then(|| old_s.rem_euclid(size))
+}
+
+/// Native implementation of the abs() builtin function.
+///
+/// # Example Python Usage
+/// ```python
+/// abs(-3)    # 3
+/// abs(-2.5)  # 2.5
+/// ```
+pub fn native_abs(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "abs() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    match &**value {
+        ObjectType::Integer(number) => number
+            .checked_abs()
+            .map(|result| Rc::new(ObjectType::Integer(result)))
+            .ok_or_else(|| NativeError::new("OverflowError", "integer overflow")),
+        ObjectType::Float(number) => Ok(Rc::new(ObjectType::Float(number.abs()))),
+        ObjectType::Boolean(flag) => Ok(Rc::new(ObjectType::Integer(*flag as i64))),
+        other => Err(NativeError::type_error(format!(
+            "bad operand type for abs(): '{}'",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Native implementation of the chr() builtin function.
+///
+/// Returns the one-character string for a Unicode code point. Surrogate code
+/// points have no character of their own, so they are rejected.
+///
+/// # Example Python Usage
+/// ```python
+/// chr(65)                # 'A'
+/// chr(ord('a') + 1)      # 'b'
+/// chr(0x1F600)           # '😀'
+/// ```
+pub fn native_chr(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "chr() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    let ObjectType::Integer(code) = &**value else {
+        return Err(NativeError::type_error(format!(
+            "'{}' object cannot be interpreted as an integer",
+            value.type_name()
+        )));
+    };
+    if !(0..0x110000).contains(code) {
+        return Err(NativeError::value_error("chr() arg not in range(0x110000)"));
+    }
+    char::from_u32(*code as u32)
+        .map(|character| Rc::new(ObjectType::String(character.to_string())))
+        .ok_or_else(|| NativeError::value_error("chr() arg is a surrogate code point"))
+}
+
+/// Native implementation of the ord() builtin function.
+///
+/// Returns the Unicode code point of a one-character string.
+///
+/// # Example Python Usage
+/// ```python
+/// ord('A')   # 65
+/// ord('é')   # 233
+/// ```
+pub fn native_ord(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "ord() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    let ObjectType::String(text) = &**value else {
+        return Err(NativeError::type_error(format!(
+            "ord() expected string of length 1, but {} found",
+            value.type_name()
+        )));
+    };
+    let mut characters = text.chars();
+    match (characters.next(), characters.next()) {
+        (Some(character), None) => Ok(Rc::new(ObjectType::Integer(character as i64))),
+        _ => Err(NativeError::type_error(format!(
+            "ord() expected a character, but string of length {} found",
+            text.chars().count()
+        ))),
+    }
+}
+
+/// Native implementation of the id() builtin function.
+///
+/// Returns an integer unique to the object for as long as it lives: the
+/// address its repr shows, so `hex(id(f))` matches `<function f at ...>`.
+///
+/// # Example Python Usage
+/// ```python
+/// a = []
+/// b = a
+/// id(a) == id(b)   # True
+/// ```
+pub fn native_id(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "id() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    Ok(Rc::new(ObjectType::Integer(hashing::identity(value))))
+}
+
+/// Native implementation of the hash() builtin function.
+///
+/// Returns the hash dict keys are compared by: equal numbers hash alike
+/// whatever their type, and lists, dicts and anything containing them raise
+/// TypeError. The VM calls a user-defined `__hash__` before getting here.
+///
+/// # Example Python Usage
+/// ```python
+/// hash(1) == hash(1.0)   # True
+/// hash((1, "a"))         # combines the items' hashes
+/// hash([1])              # TypeError: unhashable type: 'list'
+/// ```
+pub fn native_hash(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "hash() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    hashing::hash_value(value)
+        .map(|hash| Rc::new(ObjectType::Integer(hash)))
+        .map_err(|type_name| NativeError::type_error(format!("unhashable type: '{}'", type_name)))
+}
+
+/// Native implementation of the callable() builtin function.
+///
+/// Functions, methods, classes and instances of classes defining `__call__`
+/// can be called.
+pub fn native_callable(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "callable() takes exactly one argument ({} given)",
+            args.len()
+        )));
+    };
+    let callable = match &**value {
+        ObjectType::Function(_)
+        | ObjectType::NativeFunction(_, _)
+        | ObjectType::VmNativeFunction(_, _)
+        | ObjectType::Class(_)
+        | ObjectType::BoundMethod(_, _) => true,
+        ObjectType::Instance(instance) => instance.borrow().class.get_method("__call__").is_some(),
+        _ => false,
+    };
+    Ok(Rc::new(ObjectType::Boolean(callable)))
+}
+
+/// Native implementation of the vars() builtin function.
+///
+/// Returns a dict of the attributes stored on an instance, the methods and
+/// attributes a class defines itself (not those it inherits), or a module's
+/// attributes. The dict is a copy, so changing it leaves the object alone.
+pub fn native_vars(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let value = match args {
+        [value] => value,
+        [] => {
+            return Err(NativeError::type_error(
+                "vars() without arguments is not supported",
+            ))
+        }
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "vars expected at most 1 argument, got {}",
+                args.len()
+            )))
+        }
+    };
+    let mut entries: Vec<(String, Object)> = match &**value {
+        ObjectType::Instance(instance) => return Ok(dict(instance.borrow().fields.clone())),
+        ObjectType::Class(class) => class
+            .methods
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+        ObjectType::Module(module) => module
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+        _ => {
+            return Err(NativeError::type_error(
+                "vars() argument must have __dict__ attribute",
+            ))
+        }
+    };
+    // Classes and modules keep their attributes unordered
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(dict(entries))
+}
+
+/// Native implementation of the dir() builtin function.
+///
+/// Returns the sorted names of the attributes `getattr()` finds on the
+/// argument, including methods inherited from base classes.
+///
+/// # Example Python Usage
+/// ```python
+/// class Base:
+///     def greet(self):
+///         return "hi"
+///
+/// class Child(Base):
+///     def __init__(self):
+///         self.name = "c"
+///
+/// dir(Child())   # ['__init__', 'greet', 'name']
+/// ```
+pub fn native_dir(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let value = match args {
+        [value] => value,
+        [] => {
+            return Err(NativeError::type_error(
+                "dir() without arguments is not supported",
+            ))
+        }
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "dir expected at most 1 argument, got {}",
+                args.len()
+            )))
+        }
+    };
+    let names = attribute_names(value)
+        .into_iter()
+        .map(|name| Rc::new(ObjectType::String(name)))
+        .collect();
+    Ok(Rc::new(ObjectType::List(names)))
+}
+
+fn dict(entries: Vec<(String, Object)>) -> Object {
+    Rc::new(ObjectType::dict_of_names(entries))
+}
+
+/// Native implementation of the range() builtin function.
+///
+/// Accepts `range(stop)`, `range(start, stop)` or `range(start, stop, step)` and
+/// returns the resulting integers as a list.
+///
+/// # Errors
+/// Returns an error for non-integer arguments, a zero step, or a wrong number
+/// of arguments.
+///
+/// # Example Python Usage
+/// ```python
+/// range(3)          # [0, 1, 2]
+/// range(10, 0, -3)  # [10, 7, 4, 1]
+/// ```
+pub fn native_range(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let mut bounds = Vec::with_capacity(args.len());
+    for arg in args {
+        match &**arg {
+            ObjectType::Integer(value) => bounds.push(*value),
+            other => {
+                return Err(NativeError::type_error(format!(
+                    "'{}' object cannot be interpreted as an integer",
+                    other.type_name()
+                )))
+            }
+        }
+    }
+
+    let (start, stop, step) = match bounds.as_slice() {
+        [stop] => (0, *stop, 1),
+        [start, stop] => (*start, *stop, 1),
+        [start, stop, step] => (*start, *stop, *step),
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "range expected 1 to 3 arguments, got {}",
+                args.len()
+            )))
+        }
+    };
+
+    if step == 0 {
+        return Err(NativeError::value_error("range() arg 3 must not be zero"));
+    }
+
+    let elements = range_elements(start, stop, step)
+        .ok_or_else(|| NativeError::new("OverflowError", "range too large"))?;
+    Ok(Rc::new(ObjectType::List(elements)))
+}
+
+/// Native implementation of the list() builtin function.
+///
+/// Returns an empty list when called without arguments, otherwise a new list
+/// holding the items of the given iterable.
+///
+/// # Errors
+/// Returns an error for a non-iterable argument or more than one argument.
+pub fn native_list(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    match args {
+        [] => Ok(Rc::new(ObjectType::List(Vec::new()))),
+        [iterable] => collect_iterable(iterable)
+            .map(|items| Rc::new(ObjectType::List(items)))
+            .ok_or_else(|| NativeError::type_error(not_iterable_message(iterable))),
+        _ => Err(NativeError::type_error(format!(
+            "list expected at most 1 argument, got {}",
+            args.len()
+        ))),
+    }
+}
+
+/// Native implementation of the tuple() builtin function.
+///
+/// Returns an empty tuple when called without arguments, otherwise a tuple
+/// holding the items of the given iterable.
+///
+/// # Errors
+/// Returns an error for a non-iterable argument or more than one argument.
+pub fn native_tuple(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    match args {
+        [] => Ok(Rc::new(ObjectType::Tuple(Vec::new()))),
+        [iterable] if matches!(**iterable, ObjectType::Tuple(_)) => Ok(iterable.clone()),
+        [iterable] => collect_iterable(iterable)
+            .map(|items| Rc::new(ObjectType::Tuple(items)))
+            .ok_or_else(|| NativeError::type_error(not_iterable_message(iterable))),
+        _ => Err(NativeError::type_error(format!(
+            "tuple expected at most 1 argument, got {}",
+            args.len()
+        ))),
+    }
+}
+
+/// Native implementation of the set() builtin function.
+///
+/// Returns an empty set when called without arguments, or a set of the
+/// distinct items of the given iterable, kept in the order they first appear.
+///
+/// # Errors
+/// Returns an error for a non-iterable argument, an unhashable item, or more
+/// than one argument.
+pub(in crate::vm) fn native_set(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("set", keywords)?;
+    let items = match args {
+        [] => Vec::new(),
+        [iterable] => match collect_iterable(iterable) {
+            Some(items) => items,
+            None => return Err(vm.raise_error("TypeError", not_iterable_message(iterable))),
+        },
+        _ => {
+            let message = format!("set expected at most 1 argument, got {}", args.len());
+            return Err(vm.raise_error("TypeError", message));
+        }
+    };
+    Ok(Rc::new(ObjectType::Set(vm.distinct_items(items)?)))
+}
+
+/// Native implementation of the dict() builtin function.
+///
+/// Returns an empty dict when called without arguments, a copy of a dict, or
+/// a dict built from an iterable of key/value pairs, followed by the entries
+/// of any `key=value` keywords.
+///
+/// # Example Python Usage
+/// ```python
+/// dict()                          # {}
+/// dict([("a", 1), ("b", 2)])      # {'a': 1, 'b': 2}
+/// dict(zip(["a", "b"], [1, 2]))   # {'a': 1, 'b': 2}
+/// dict({"a": 1}, b=2)             # {'a': 1, 'b': 2}
+/// ```
+///
+/// # Errors
+/// Raises an error for more than one positional argument, a source that is
+/// neither a dict nor an iterable of pairs, or an unhashable key.
+pub(in crate::vm) fn native_dict(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    let mut entries = DictEntries::new();
+    match args {
+        [] => {}
+        [source] => match &**source {
+            ObjectType::Dict(source_entries) => entries = source_entries.clone(),
+            _ => {
+                let Some(items) = collect_iterable(source) else {
+                    return Err(vm.raise_error("TypeError", not_iterable_message(source)));
+                };
+                for (position, item) in items.iter().enumerate() {
+                    let Some(pair) = collect_iterable(item) else {
+                        let message = format!(
+                            "cannot convert dictionary update sequence element #{} to a sequence",
+                            position
+                        );
+                        return Err(vm.raise_error("TypeError", message));
+                    };
+                    let [key, value] = pair.as_slice() else {
+                        let message = format!(
+                            "dictionary update sequence element #{} has length {}; 2 is required",
+                            position,
+                            pair.len()
+                        );
+                        return Err(vm.raise_error("ValueError", message));
+                    };
+                    vm.insert_entry(&mut entries, key.clone(), value.clone())?;
+                }
+            }
+        },
+        _ => {
+            let message = format!("dict expected at most 1 argument, got {}", args.len());
+            return Err(vm.raise_error("TypeError", message));
+        }
+    }
+
+    for (key, value) in keywords {
+        let key = Rc::new(ObjectType::String(key.clone()));
+        vm.insert_entry(&mut entries, key, value.clone())?;
+    }
+    Ok(Rc::new(ObjectType::Dict(entries)))
+}
+
+/// Native implementation of the int() builtin function.
+///
+/// Converts a number, truncating floats towards zero, or parses a string of
+/// digits in `base` (10 by default). Base 0 reads the base from a `0x`,
+/// `0o` or `0b` prefix as a literal in source would; bases 16, 8 and 2 also
+/// accept their prefix. Surrounding whitespace, a sign and underscores
+/// between digits are allowed.
+///
+/// # Example Python Usage
+/// ```python
+/// int()              # 0
+/// int(-2.7)          # -2
+/// int(' 42 ')        # 42
+/// int('ff', 16)      # 255
+/// int('0b1_01', 0)   # 5
+/// ```
+pub fn native_int(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let integer = |value: i64| Rc::new(ObjectType::Integer(value));
+    match args {
+        [] => Ok(integer(0)),
+        [value] => match &**value {
+            ObjectType::Integer(_) => Ok(value.clone()),
+            ObjectType::Boolean(flag) => Ok(integer(*flag as i64)),
+            ObjectType::Float(number) if number.is_nan() => Err(NativeError::value_error(
+                "cannot convert float NaN to integer",
+            )),
+            ObjectType::Float(number) if number.is_infinite() => Err(NativeError::new(
+                "OverflowError",
+                "cannot convert float infinity to integer",
+            )),
+            ObjectType::Float(number) => {
+                let truncated = number.trunc();
+                if truncated < -(2f64.powi(63)) || truncated >= 2f64.powi(63) {
+                    return Err(NativeError::new("OverflowError", "integer overflow"));
+                }
+                Ok(integer(truncated as i64))
+            }
+            ObjectType::String(text) => parse_int(text, 10).map(integer),
+            other => Err(NativeError::type_error(format!(
+                "int() argument must be a string, a bytes-like object or a real number, not '{}'",
+                other.type_name()
+            ))),
+        },
+        [value, base] => {
+            let ObjectType::Integer(base) = &**base else {
+                return Err(NativeError::type_error(format!(
+                    "'{}' object cannot be interpreted as an integer",
+                    base.type_name()
+                )));
+            };
+            if *base != 0 && !(2..=36).contains(base) {
+                return Err(NativeError::value_error(
+                    "int() base must be >= 2 and <= 36, or 0",
+                ));
+            }
+            match &**value {
+                ObjectType::String(text) => parse_int(text, *base as u32).map(integer),
+                _ => Err(NativeError::type_error(
+                    "int() can't convert non-string with explicit base",
+                )),
+            }
+        }
+        _ => Err(NativeError::type_error(format!(
+            "int() takes at most 2 arguments ({} given)",
+            args.len()
+        ))),
+    }
+}
+
+/// Parses `text` as an integer literal in `base`, which is 0 or from 2 to 36.
+fn parse_int(text: &str, base: u32) -> Result<i64, NativeError> {
+    let invalid = || {
+        NativeError::value_error(format!(
+            "invalid literal for int() with base {}: '{}'",
+            base, text
+        ))
+    };
+
+    let trimmed = text.trim();
+    let (negative, unsigned) = match trimmed.as_bytes().first() {
+        Some(b'-') => (true, &trimmed[1..]),
+        Some(b'+') => (false, &trimmed[1..]),
+        _ => (false, trimmed),
+    };
+    let prefix_base = match unsigned.get(..2).map(str::to_ascii_lowercase).as_deref() {
+        Some("0x") => Some(16),
+        Some("0o") => Some(8),
+        Some("0b") => Some(2),
+        _ => None,
+    };
+    let (radix, digits) = match (base, prefix_base) {
+        (0, Some(prefix)) => (prefix, &unsigned[2..]),
+        // Without a prefix, base 0 reads decimal, where only zero may start
+        // with a zero
+        (0, None) => {
+            if unsigned.starts_with('0') && unsigned.chars().any(|c| c != '0' && c != '_') {
+                return Err(invalid());
+            }
+            (10, unsigned)
+        }
+        (base, Some(prefix)) if base == prefix => (base, &unsigned[2..]),
+        (base, _) => (base, unsigned),
+    };
+    // A prefix may be followed by an underscore, as in `0x_ff`
+    let digits = match digits.strip_prefix('_') {
+        Some(rest) if digits.len() < unsigned.len() => rest,
+        _ => digits,
+    };
+
+    if digits.is_empty()
+        || digits.starts_with('_')
+        || digits.ends_with('_')
+        || digits.contains("__")
+    {
+        return Err(invalid());
+    }
+    let mut magnitude: i128 = 0;
+    for character in digits.chars().filter(|c| *c != '_') {
+        let digit = character.to_digit(radix).ok_or_else(invalid)?;
+        magnitude = magnitude
+            .checked_mul(radix as i128)
+            .and_then(|value| value.checked_add(digit as i128))
+            .ok_or_else(|| NativeError::new("OverflowError", "integer overflow"))?;
+    }
+    let value = if negative { -magnitude } else { magnitude };
+    i64::try_from(value).map_err(|_| NativeError::new("OverflowError", "integer overflow"))
+}
+
+/// Native implementation of the hex() builtin function.
+///
+/// # Example Python Usage
+/// ```python
+/// hex(255)   # '0xff'
+/// hex(-16)   # '-0x10'
+/// ```
+pub fn native_hex(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    format_in_base("hex", args, "0x", |value| format!("{:x}", value))
+}
+
+/// Native implementation of the oct() builtin function.
+///
+/// # Example Python Usage
+/// ```python
+/// oct(8)   # '0o10'
+/// ```
+pub fn native_oct(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    format_in_base("oct", args, "0o", |value| format!("{:o}", value))
+}
+
+/// Native implementation of the bin() builtin function.
+///
+/// # Example Python Usage
+/// ```python
+/// bin(5)    # '0b101'
+/// bin(-5)   # '-0b101'
+/// ```
+pub fn native_bin(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    format_in_base("bin", args, "0b", |value| format!("{:b}", value))
+}
+
+/// Formats the single integer argument of `name()` with its sign, `prefix`
+/// and the digits `digits` gives for its magnitude.
+fn format_in_base(
+    name: &str,
+    args: &[Object],
+    prefix: &str,
+    digits: fn(u64) -> String,
+) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "{}() takes exactly one argument ({} given)",
+            name,
+            args.len()
+        )));
+    };
+    let number = match &**value {
+        ObjectType::Integer(number) => *number,
+        ObjectType::Boolean(flag) => *flag as i64,
+        other => {
+            return Err(NativeError::type_error(format!(
+                "'{}' object cannot be interpreted as an integer",
+                other.type_name()
+            )))
+        }
+    };
+    let sign = if number < 0 { "-" } else { "" };
+    Ok(Rc::new(ObjectType::String(format!(
+        "{}{}{}",
+        sign,
+        prefix,
+        digits(number.unsigned_abs())
+    ))))
+}
+
+/// Native implementation of the iter() builtin function.
+///
+/// Returns an iterator over a list, tuple or string; an iterator is returned
+/// as it is.
+///
+/// # Errors
+/// Returns an error for a non-iterable argument or a wrong number of
+/// arguments.
+pub fn native_iter(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [value] = args else {
+        return Err(NativeError::type_error(format!(
+            "iter expected 1 argument, got {}",
+            args.len()
+        )));
+    };
+    if matches!(**value, ObjectType::Iterator(_)) {
+        return Ok(value.clone());
+    }
+    IteratorObject::from_iterable(value)
+        .map(|iterator| Rc::new(ObjectType::Iterator(iterator)))
+        .ok_or_else(|| NativeError::type_error(not_iterable_message(value)))
+}
+
+/// Native implementation of the next() builtin function.
+///
+/// Advances an iterator and returns its next item. Once the iterator is
+/// exhausted it returns the default when one is given, and raises
+/// StopIteration otherwise.
+///
+/// # Example Python Usage
+/// ```python
+/// it = iter([1, 2])
+/// next(it)        # 1
+/// next(it)        # 2
+/// next(it, None)  # None
+/// next(it)        # raises StopIteration
+/// ```
+pub fn native_next(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (iterator, default) = match args {
+        [iterator] => (iterator, None),
+        [iterator, default] => (iterator, Some(default)),
+        [] => {
+            return Err(NativeError::type_error(
+                "next expected at least 1 argument, got 0",
+            ))
+        }
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "next expected at most 2 arguments, got {}",
+                args.len()
+            )))
+        }
+    };
+    let ObjectType::Iterator(iterator) = &**iterator else {
+        return Err(NativeError::type_error(format!(
+            "'{}' object is not an iterator",
+            iterator.type_name()
+        )));
+    };
+    let item = iterator.borrow_mut().next_item();
+    item.or_else(|| default.cloned())
+        .ok_or_else(|| NativeError::new("StopIteration", ""))
+}
+
+/// Native implementation of the sum() builtin function.
+///
+/// Adds the items of the iterable to `start`, which defaults to 0. As in
+/// CPython, strings are refused in favour of `str.join()`.
+///
+/// # Errors
+/// Returns an error for a non-iterable argument, a string start, items that
+/// cannot be added, or a wrong number of arguments.
+pub fn native_sum(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (iterable, start) = match args {
+        [iterable] => (iterable, integer(0)),
+        [iterable, start] => (iterable, start.clone()),
+        _ => {
+            return Err(NativeError::type_error(format!(
+                "sum expected at most 2 arguments, got {}",
+                args.len()
+            )))
+        }
+    };
+    if matches!(*start, ObjectType::String(_)) {
+        return Err(NativeError::type_error(
+            "sum() can't sum strings [use ''.join(seq) instead]",
+        ));
+    }
+    let items = collect_iterable(iterable)
+        .ok_or_else(|| NativeError::type_error(not_iterable_message(iterable)))?;
+    items.into_iter().try_fold(start, |total, item| {
+        op_add(total.clone(), item.clone()).map_err(|_| {
+            let (kind, message) = arithmetic_error("+", &total, &item);
+            NativeError::new(kind, message)
+        })
+    })
+}
+
+/// Native implementation of the any() builtin function.
+///
+/// Returns True if any item of the iterable is true.
+///
+/// # Errors
+/// Returns an error unless called with exactly one iterable argument.
+pub fn native_any(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let items = truth_test_items("any", args)?;
+    Ok(Rc::new(ObjectType::Boolean(
+        items.iter().any(|item| is_truthy(item)),
+    )))
+}
+
+/// Native implementation of the all() builtin function.
+///
+/// Returns True unless some item of the iterable is false.
+///
+/// # Errors
+/// Returns an error unless called with exactly one iterable argument.
+pub fn native_all(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let items = truth_test_items("all", args)?;
+    Ok(Rc::new(ObjectType::Boolean(
+        items.iter().all(|item| is_truthy(item)),
+    )))
+}
+
+/// The items of the single iterable argument of `any()` or `all()`.
+fn truth_test_items(name: &str, args: &[Object]) -> Result<Vec<Object>, NativeError> {
+    let [iterable] = args else {
+        return Err(NativeError::type_error(format!(
+            "{}() takes exactly one argument ({} given)",
+            name,
+            args.len()
+        )));
+    };
+    collect_iterable(iterable)
+        .ok_or_else(|| NativeError::type_error(not_iterable_message(iterable)))
+}
+
+/// The items `min()` or `max()` chooses among: those of the iterable when
+/// given one argument, and the arguments themselves otherwise.
+pub fn extremum_items(name: &str, args: &[Object]) -> Result<Vec<Object>, NativeError> {
+    match args {
+        [] => Err(NativeError::type_error(format!(
+            "{} expected at least 1 argument, got 0",
+            name
+        ))),
+        [iterable] => collect_iterable(iterable)
+            .ok_or_else(|| NativeError::type_error(not_iterable_message(iterable))),
+        _ => Ok(args.to_vec()),
+    }
+}
+
+/// Native implementation of the str() builtin function.
+///
+/// Returns the text `print()` shows for the argument, or an empty string when
+/// called without one. The VM runs `str()` itself so that an instance's
+/// `__str__` can run; this covers every other value.
+///
+/// # Errors
+/// Returns an error when called with more than one argument.
+pub fn native_str(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    match args {
+        [] => Ok(Rc::new(ObjectType::String(String::new()))),
+        [value] => Ok(Rc::new(ObjectType::String(formatting::format_value(
+            value, "",
+        )?))),
+        _ => Err(NativeError::type_error(format!(
+            "str expected at most 1 argument, got {}",
+            args.len()
+        ))),
+    }
+}
+
+/// Native implementation of the repr() builtin function.
+///
+/// Returns the text the REPL shows for the argument: strings quoted, other
+/// values as `str()` gives them. The VM runs `repr()` itself so that an
+/// instance's `__repr__` can run.
+///
+/// # Errors
+/// Returns an error unless called with exactly one argument.
+pub fn native_repr(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    match args {
+        [value] => Ok(Rc::new(ObjectType::String(match &**value {
+            ObjectType::String(text) => crate::object::quote(text),
+            _ => formatting::format_value(value, "")?,
+        }))),
+        _ => Err(NativeError::type_error(format!(
+            "repr() takes exactly one argument ({} given)",
+            args.len()
+        ))),
+    }
+}
+
+/// Native implementation of the type() builtin function.
+///
+/// Returns the name of the argument's type as a string.
+///
+/// # Errors
+/// Returns an error unless called with exactly one argument.
+pub fn native_type(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    match args {
+        [value] => Ok(op_type(value.clone())),
+        _ => Err(NativeError::type_error(format!(
+            "type() takes 1 argument ({} given)",
+            args.len()
+        ))),
+    }
+}
@@ -3,101 +3,267 @@
 //! This module implements the main opcode dispatch logic that executes
 //! individual bytecode instructions.
 
+use super::collections::not_iterable_message;
+use super::exceptions::{self, HandlerKind};
+use super::generators::generator_of;
+use super::hashing::set_entry;
 use super::VM;
-use crate::bytecode::OpCode;
-use crate::object::ObjectType;
-use crate::vm::{opcodes, InterpretResult};
-use std::rc::Rc;
+use crate::bytecode::{OpCode, FORMAT_WITH_SPEC};
+use crate::object::{DictEntries, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::{opcodes, values, InterpretResult};
 
 impl VM {
     /// Dispatch and execute a single opcode instruction.
+    ///
+    /// Always inlined into the `run` loop: keeping the dispatch in one function
+    /// body lets the optimizer hold frame and stack state in registers.
+    #[inline(always)]
     pub(super) fn dispatch_opcode(&mut self, instruction: OpCode) -> InterpretResult {
         match instruction {
-            OpCode::OpConstant => {
-                let const_idx = self.read_byte() as usize;
-                let constant = self.current_chunk().constants[const_idx].clone();
-                self.push(constant);
+            OpCode::OpConstant | OpCode::OpConstantLong => {
+                let index = self.read_slot(instruction);
+                if !self.push_constant(index) {
+                    return self.bad_constant();
+                }
             }
             OpCode::OpAdd => {
                 let b = self.pop();
                 let a = self.pop();
-                match opcodes::arithmetic::op_add(a, b) {
+                if let Some(result) =
+                    opcodes::arithmetic::integer_operation(&a, &b, i64::checked_add)
+                {
+                    let result = self.shared.integer(result);
+                    self.push(result);
+                    return InterpretResult::Ok;
+                }
+                match opcodes::arithmetic::op_add(a.clone(), b.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::arithmetic::arithmetic_error("+", &a, &b);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpDivide => {
                 let b = self.pop();
                 let a = self.pop();
-                match opcodes::arithmetic::op_divide(a, b) {
+                match opcodes::arithmetic::op_divide(a.clone(), b.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::arithmetic::arithmetic_error("/", &a, &b);
+                        return self.raise_error(kind, message);
+                    }
+                }
+            }
+            OpCode::OpFloorDivide => {
+                let b = self.pop();
+                let a = self.pop();
+                match opcodes::arithmetic::op_floor_divide(a.clone(), b.clone()) {
+                    Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) = opcodes::arithmetic::arithmetic_error("//", &a, &b);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpSubtract => {
                 let b = self.pop();
                 let a = self.pop();
-                match opcodes::arithmetic::op_subtract(a, b) {
+                if let Some(result) =
+                    opcodes::arithmetic::integer_operation(&a, &b, i64::checked_sub)
+                {
+                    let result = self.shared.integer(result);
+                    self.push(result);
+                    return InterpretResult::Ok;
+                }
+                match opcodes::arithmetic::op_subtract(a.clone(), b.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::arithmetic::arithmetic_error("-", &a, &b);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpMultiply => {
                 let b = self.pop();
                 let a = self.pop();
-                match opcodes::arithmetic::op_multiply(a, b) {
+                match opcodes::arithmetic::op_multiply(a.clone(), b.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::arithmetic::arithmetic_error("*", &a, &b);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpModulo => {
                 let b = self.pop();
                 let a = self.pop();
-                match opcodes::arithmetic::op_modulo(a, b) {
+                match opcodes::arithmetic::op_modulo(a.clone(), b.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::arithmetic::arithmetic_error("%", &a, &b);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
-            OpCode::OpDefineGlobal => {
-                let name_idx = self.read_byte() as usize;
-                if let ObjectType::String(name) = &*self.current_chunk().constants[name_idx] {
-                    let value = self.peek(0).clone();
-                    opcodes::variables::op_define_global(name.clone(), value, &mut self.globals);
-                    self.pop();
+            OpCode::OpDefineGlobal | OpCode::OpDefineGlobalLong => {
+                self.sync_globals();
+                let constant = self.read_constant(instruction);
+                if let Some(ObjectType::String(name)) = constant.as_deref() {
+                    let value = self.pop();
+                    opcodes::variables::op_define_global(
+                        name.clone(),
+                        value.clone(),
+                        &mut self.globals,
+                    );
+                    self.stack.set_last_popped(value);
                 }
             }
-            OpCode::OpGetGlobal => {
-                let name_idx = self.read_byte() as usize;
-                if let ObjectType::String(name) = &*self.current_chunk().constants[name_idx] {
-                    match opcodes::variables::op_get_global(name, &self.globals) {
-                        Ok(value) => self.push(value),
-                        Err(e) => return e,
+            OpCode::OpGetGlobal | OpCode::OpGetGlobalLong => {
+                self.sync_globals();
+                let index = self.read_slot(instruction);
+                // Borrows the name from the chunk rather than cloning it
+                let Some(constant) = self.current_chunk().constants.get(index) else {
+                    return InterpretResult::Ok;
+                };
+                if let Some(value) = self
+                    .global_cache
+                    .get(constant, &self.globals, &self.builtins)
+                {
+                    self.push(value);
+                    return InterpretResult::Ok;
+                }
+                let ObjectType::String(name) = &**constant else {
+                    return InterpretResult::Ok;
+                };
+                match opcodes::variables::op_get_global(name, &self.globals, &self.builtins) {
+                    Ok(value) => {
+                        let constant = constant.clone();
+                        self.global_cache
+                            .insert(&constant, &self.globals, &self.builtins, &value);
+                        self.push(value);
+                    }
+                    Err(_) => {
+                        let message = format!("name '{}' is not defined", name);
+                        return self.raise_error("NameError", message);
                     }
                 }
             }
-            OpCode::OpSetGlobal => {
-                let name_idx = self.read_byte() as usize;
-                let name = match &*self.current_chunk().constants[name_idx] {
-                    ObjectType::String(name) => name.clone(),
-                    _ => return InterpretResult::RuntimeError,
+            OpCode::OpDeleteGlobal | OpCode::OpDeleteGlobalLong => {
+                self.sync_globals();
+                let constant = self.read_constant(instruction);
+                if let Some(ObjectType::String(name)) = constant.as_deref() {
+                    let name = name.clone();
+                    if opcodes::variables::op_delete_global(&name, &mut self.globals).is_err() {
+                        let message = format!("name '{}' is not defined", name);
+                        return self.raise_error("NameError", message);
+                    }
+                }
+            }
+            OpCode::OpSetGlobal | OpCode::OpSetGlobalLong => {
+                self.sync_globals();
+                let name = match self.read_constant(instruction).as_deref() {
+                    Some(ObjectType::String(name)) => name.clone(),
+                    _ => return self.bad_operand(),
                 };
                 let value = self.peek(0).clone();
-                match opcodes::variables::op_set_global(&name, value, &mut self.globals) {
-                    Ok(()) => {}
-                    Err(e) => return e,
+                if opcodes::variables::op_set_global(&name, value, &mut self.globals).is_err() {
+                    let message = format!("name '{}' is not defined", name);
+                    return self.raise_error("NameError", message);
                 }
             }
             OpCode::OpCall => {
                 let arg_count = self.read_byte() as usize;
+                let depth = self.frames.len();
                 if !self.call_value(arg_count) {
                     return InterpretResult::RuntimeError;
                 }
+                self.reuse_frame_for_tail_call(depth);
+            }
+            OpCode::OpCallKw => {
+                let arg_count = self.read_byte() as usize;
+                let names = match &*self.pop() {
+                    ObjectType::Tuple(names) => names
+                        .iter()
+                        .map(|name| name.to_string())
+                        .collect::<Vec<_>>(),
+                    _ => return self.bad_operand(),
+                };
+                let depth = self.frames.len();
+                if !self.call_value_with_keywords(arg_count, names) {
+                    return InterpretResult::RuntimeError;
+                }
+                self.reuse_frame_for_tail_call(depth);
+            }
+            OpCode::OpListSort => {
+                let reverse = self.pop();
+                let key = self.pop();
+                let list = self.pop();
+                match self.op_list_sort(list, key, reverse) {
+                    Ok(result) => self.push(result),
+                    Err(e) => return e,
+                }
+            }
+            OpCode::OpBuildList => {
+                let count = self.read_byte() as usize;
+                let elements = self.stack.pop_many(count);
+                self.push(opcodes::collections::op_build_list(elements));
+            }
+            OpCode::OpBuildDict => {
+                let count = self.read_byte() as usize;
+                let items = self.stack.pop_many(count * 2);
+                // A repeated key keeps its first position but takes the last value
+                let mut entries = DictEntries::with_capacity(count);
+                let mut items = items.into_iter();
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    if let Err(e) = self.insert_entry(&mut entries, key, value) {
+                        return e;
+                    }
+                }
+                self.push(Rc::new(ObjectType::Dict(entries)));
+            }
+            OpCode::OpUnpack => {
+                let count = self.read_byte() as usize;
+                let starred = (self.read_byte() as usize).checked_sub(1);
+                let sequence = match self.gather_generator(&self.peek(0).clone()) {
+                    Ok(sequence) => sequence,
+                    Err(e) => return e,
+                };
+                self.pop();
+                match opcodes::collections::op_unpack(&sequence, count, starred) {
+                    Ok(values) => {
+                        for value in values.into_iter().rev() {
+                            self.push(value);
+                        }
+                    }
+                    Err((kind, message)) => return self.raise_error(kind, message),
+                }
+            }
+            OpCode::OpCallEx => {
+                let args = match &*self.pop() {
+                    ObjectType::List(values) => values.clone(),
+                    _ => return self.bad_operand(),
+                };
+                let arg_count = args.len();
+                for arg in args {
+                    self.push(arg);
+                }
+                if !self.call_value(arg_count) {
+                    return InterpretResult::RuntimeError;
+                }
+            }
+            OpCode::OpCallExKw => {
+                let mappings = self.pop();
+                let args = self.pop();
+                if !self.call_with_mappings(&args, &mappings) {
+                    return InterpretResult::RuntimeError;
+                }
             }
-            OpCode::OpMakeFunction => {
-                let proto_idx = self.read_byte() as usize;
-                let proto = match &*self.current_chunk().constants[proto_idx] {
-                    ObjectType::FunctionPrototype(proto) => proto.clone(),
-                    _ => return InterpretResult::RuntimeError,
+            OpCode::OpMakeFunction | OpCode::OpMakeFunctionLong => {
+                let proto = match self.read_constant(instruction).as_deref() {
+                    Some(ObjectType::FunctionPrototype(proto)) => proto.clone(),
+                    _ => return self.bad_operand(),
                 };
                 let (frame_slot, parent_upvalues) = if let Some(frame) = self.frames.last() {
                     (frame.slot, frame.function.upvalues.clone())
@@ -108,160 +274,319 @@ impl VM {
                     proto,
                     frame_slot,
                     &parent_upvalues,
-                    self.globals.clone(),
                     &mut self.open_upvalues,
                 ) {
                     Ok(function) => self.push(function),
-                    Err(e) => return e,
+                    Err(_) => return self.bad_operand(),
                 }
             }
-            OpCode::OpGetLocal => {
-                let slot = self.read_byte() as usize;
+            OpCode::OpGetLocal | OpCode::OpGetLocalLong => {
+                let slot = self.read_slot(instruction);
                 match opcodes::variables::op_get_local(slot, &self.stack, &self.frames) {
                     Ok(value) => self.push(value),
-                    Err(e) => return e,
+                    Err(_) => return self.bad_operand(),
                 }
+                self.run_next_constant();
             }
-            OpCode::OpSetLocal => {
-                let slot = self.read_byte() as usize;
+            OpCode::OpSetLocal | OpCode::OpSetLocalLong => {
+                let slot = self.read_slot(instruction);
                 let value = self.peek(0).clone();
                 match opcodes::variables::op_set_local(slot, value, &mut self.stack, &self.frames) {
                     Ok(()) => {}
-                    Err(e) => return e,
+                    Err(_) => return self.bad_operand(),
                 }
             }
-            OpCode::OpGetUpvalue => {
-                let slot = self.read_byte() as usize;
+            OpCode::OpGetUpvalue | OpCode::OpGetUpvalueLong => {
+                let slot = self.read_slot(instruction);
                 match opcodes::variables::op_get_upvalue(slot, &self.stack, &self.frames) {
                     Ok(value) => self.push(value),
-                    Err(e) => return e,
+                    Err(_) => return self.bad_operand(),
                 }
             }
-            OpCode::OpSetUpvalue => {
-                let slot = self.read_byte() as usize;
+            OpCode::OpSetUpvalue | OpCode::OpSetUpvalueLong => {
+                let slot = self.read_slot(instruction);
                 let value = self.peek(0).clone();
                 match opcodes::variables::op_set_upvalue(slot, value, &mut self.stack, &self.frames)
                 {
                     Ok(()) => {}
-                    Err(e) => return e,
+                    Err(_) => return self.bad_operand(),
                 }
             }
             OpCode::OpPrintSpaced => {
-                let value = self.pop();
-                opcodes::io::op_print_spaced(value, &mut self.stack, &mut self.frames);
+                let mut value = self.pop();
+                match self.instance_text(&value, false) {
+                    Some(Ok(text)) => value = text,
+                    Some(Err(error)) => return error,
+                    None => {}
+                }
+                match self.container_repr(&value) {
+                    Some(Ok(text)) => value = Rc::new(ObjectType::String(text)),
+                    Some(Err(error)) => return error,
+                    None => {}
+                }
+                opcodes::io::op_print_spaced(
+                    value,
+                    &mut self.stack,
+                    &mut self.frames,
+                    &mut self.output,
+                );
             }
             OpCode::OpPrint => {
-                let value = self.pop();
-                opcodes::io::op_print(value, &mut self.stack, &mut self.frames);
+                let mut value = self.pop();
+                match self.instance_text(&value, false) {
+                    Some(Ok(text)) => value = text,
+                    Some(Err(error)) => return error,
+                    None => {}
+                }
+                match self.container_repr(&value) {
+                    Some(Ok(text)) => value = Rc::new(ObjectType::String(text)),
+                    Some(Err(error)) => return error,
+                    None => {}
+                }
+                opcodes::io::op_print(value, &mut self.stack, &mut self.frames, &mut self.output);
             }
             OpCode::OpPrintln => {
-                opcodes::io::op_println();
+                opcodes::io::op_println(&mut self.output);
             }
             OpCode::OpIndex => {
                 let index = self.pop();
                 let collection = self.pop();
-                match opcodes::collections::op_index(collection, index) {
+                if let ObjectType::Dict(entries) = &*collection {
+                    match self.find_key(entries, &index) {
+                        Ok(Some(position)) => self.push(entries[position].1.clone()),
+                        Ok(None) => {
+                            let (kind, message) =
+                                opcodes::collections::index_error(&collection, &index);
+                            return self.raise_error(kind, message);
+                        }
+                        Err(e) => return e,
+                    }
+                    return InterpretResult::Ok;
+                }
+                match opcodes::collections::op_index(collection.clone(), index.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) =
+                            opcodes::collections::index_error(&collection, &index);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpLen => {
                 let value = self.pop();
-                match opcodes::collections::op_len(value) {
-                    Ok(len) => self.push(Rc::new(ObjectType::Integer(len))),
+                match self.length(&value) {
+                    Ok(len) => self.push(values::integer(len)),
                     Err(e) => return e,
                 }
             }
             OpCode::OpToList => {
                 let value = self.pop();
-                match opcodes::builtins::op_to_list(value) {
+                match opcodes::builtins::op_to_list(value.clone()) {
                     Ok(result) => self.push(result),
+                    Err(_) => {
+                        return self.raise_error("TypeError", not_iterable_message(&value));
+                    }
+                }
+            }
+            OpCode::OpMakeGenerator => {
+                let function = self.pop();
+                let iterable = self.pop();
+                match self.make_generator(function, iterable) {
+                    Ok(generator) => self.push(generator),
                     Err(e) => return e,
                 }
             }
+            OpCode::OpYield => {
+                let value = self.pop();
+                if let Err(e) = self.suspend_generator(value) {
+                    return e;
+                }
+            }
             OpCode::OpAppend => {
                 let value = self.pop();
                 let collection = self.pop();
-                match opcodes::collections::op_append(collection, value) {
+                match opcodes::collections::op_append(collection.clone(), value) {
                     Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) = opcodes::collections::append_error(&collection);
+                        return self.raise_error(kind, message);
+                    }
+                }
+            }
+            OpCode::OpListExtend => {
+                let iterable = match self.gather_generator(&self.peek(0).clone()) {
+                    Ok(iterable) => iterable,
                     Err(e) => return e,
+                };
+                self.pop();
+                let list = self.pop();
+                match opcodes::collections::op_list_extend(list.clone(), iterable.clone()) {
+                    Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) =
+                            opcodes::collections::list_extend_error(&list, &iterable);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpRange => {
                 let end = self.pop();
                 let start = self.pop();
-                match opcodes::collections::op_range(start, end) {
+                match opcodes::collections::op_range(start.clone(), end.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::collections::range_error(&start, &end);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpLess => {
                 let b = self.pop();
                 let a = self.pop();
-                match opcodes::comparison::op_less(a, b) {
-                    Ok(result) => self.push(Rc::new(ObjectType::Boolean(result))),
-                    Err(e) => return e,
+                match opcodes::comparison::op_less(&a, &b) {
+                    Ok(result) => self.push_condition(result),
+                    Err(_) => {
+                        let message = opcodes::comparison::comparison_error("<", &a, &b);
+                        return self.raise_error("TypeError", message);
+                    }
+                }
+            }
+            OpCode::OpLessEqual => {
+                let b = self.pop();
+                let a = self.pop();
+                match opcodes::comparison::op_less_equal(&a, &b) {
+                    Ok(result) => self.push_condition(result),
+                    Err(_) => {
+                        let message = opcodes::comparison::comparison_error("<=", &a, &b);
+                        return self.raise_error("TypeError", message);
+                    }
+                }
+            }
+            OpCode::OpGreater => {
+                let b = self.pop();
+                let a = self.pop();
+                match opcodes::comparison::op_greater(&a, &b) {
+                    Ok(result) => self.push_condition(result),
+                    Err(_) => {
+                        let message = opcodes::comparison::comparison_error(">", &a, &b);
+                        return self.raise_error("TypeError", message);
+                    }
+                }
+            }
+            OpCode::OpGreaterEqual => {
+                let b = self.pop();
+                let a = self.pop();
+                match opcodes::comparison::op_greater_equal(&a, &b) {
+                    Ok(result) => self.push_condition(result),
+                    Err(_) => {
+                        let message = opcodes::comparison::comparison_error(">=", &a, &b);
+                        return self.raise_error("TypeError", message);
+                    }
                 }
             }
             OpCode::OpEqual => {
                 let b = self.pop();
                 let a = self.pop();
-                let result = opcodes::comparison::op_equal(a, b);
-                self.push(Rc::new(ObjectType::Boolean(result)));
+                match self.equals(&a, &b) {
+                    Ok(result) => self.push_condition(result),
+                    Err(e) => return e,
+                }
+            }
+            OpCode::OpIs => {
+                let b = self.pop();
+                let a = self.pop();
+                let result = opcodes::comparison::op_is(a, b);
+                self.push(values::boolean(result));
+            }
+            OpCode::OpNegate => {
+                let value = self.pop();
+                match opcodes::arithmetic::op_negate(value.clone()) {
+                    Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) = opcodes::arithmetic::negate_error(&value);
+                        return self.raise_error(kind, message);
+                    }
+                }
+            }
+            OpCode::OpNot => {
+                let value = self.pop();
+                match self.truthiness(&value) {
+                    Ok(truth) => self.push(values::boolean(!truth)),
+                    Err(e) => return e,
+                }
             }
             OpCode::OpSlice => {
                 let step = self.pop();
                 let end = self.pop();
                 let start = self.pop();
                 let collection = self.pop();
-                match opcodes::collections::op_slice(collection, start, end, step) {
+                match opcodes::collections::op_slice(
+                    collection.clone(),
+                    start.clone(),
+                    end.clone(),
+                    step.clone(),
+                ) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) =
+                            opcodes::collections::slice_error(&collection, &start, &end, &step);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpStrLower => {
                 let value = self.pop();
-                match opcodes::strings::op_str_lower(value) {
+                match opcodes::strings::op_str_lower(value.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) =
+                            opcodes::strings::string_method_error(&value, "lower");
+                        return self.raise_error(kind, message);
+                    }
+                }
+            }
+            OpCode::OpStrUpper => {
+                let value = self.pop();
+                match opcodes::strings::op_str_upper(value.clone()) {
+                    Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) =
+                            opcodes::strings::string_method_error(&value, "upper");
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpStrIsAlnum => {
                 let value = self.pop();
-                match opcodes::strings::op_str_is_alnum(value) {
-                    Ok(result) => self.push(Rc::new(ObjectType::Boolean(result))),
-                    Err(e) => return e,
+                match opcodes::strings::op_str_is_alnum(value.clone()) {
+                    Ok(result) => self.push(values::boolean(result)),
+                    Err(_) => {
+                        let (kind, message) =
+                            opcodes::strings::string_method_error(&value, "isalnum");
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpStrJoin => {
                 let iterable = self.pop();
                 let separator = self.pop();
-                match opcodes::strings::op_str_join(separator, iterable) {
+                match opcodes::strings::op_str_join(separator.clone(), iterable.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::strings::join_error(&separator, &iterable);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpRound => {
                 let digits = self.pop();
                 let value = self.pop();
-                match opcodes::builtins::op_round(value, digits) {
+                match opcodes::builtins::op_round(value.clone(), digits.clone()) {
                     Ok(result) => self.push(result),
-                    Err(e) => return e,
-                }
-            }
-            OpCode::OpZip => {
-                let arg_count = self.read_byte() as usize;
-                let star_mask = self.read_u16() as u16;
-
-                let mut args = Vec::with_capacity(arg_count);
-                for _ in 0..arg_count {
-                    args.push(self.pop());
-                }
-                args.reverse();
-
-                match opcodes::builtins::op_zip(args, star_mask) {
-                    Ok(result) => self.push(result),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::builtins::round_error(&value, &digits);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpReturn => {
@@ -270,17 +595,31 @@ impl VM {
                 }
             }
             OpCode::OpPop => {
-                self.pop();
+                let value = self.pop();
+                self.stack.set_last_popped(value);
             }
             OpCode::OpIterNext => {
-                let offset = self.read_u16();
+                let offset = self.read_jump();
                 let index = self.pop();
                 let collection = self.pop();
 
+                if let Some(generator) = generator_of(&collection) {
+                    match self.resume_generator(&generator) {
+                        Ok(Some(element)) => {
+                            self.push(collection);
+                            self.push(index);
+                            self.push(element);
+                        }
+                        Ok(None) => opcodes::control_flow::op_jump(offset, &mut self.frames),
+                        Err(e) => return e,
+                    }
+                    return InterpretResult::Ok;
+                }
+
                 match opcodes::control_flow::op_iter_next(
                     offset,
                     index,
-                    collection,
+                    collection.clone(),
                     &mut self.frames,
                 ) {
                     Ok(Some((coll, idx, elem))) => {
@@ -291,29 +630,142 @@ impl VM {
                     Ok(None) => {
                         // Iteration finished, frame IP already updated
                     }
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::control_flow::iter_next_error(&collection);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpLoop => {
-                let offset = self.read_u16();
-                opcodes::control_flow::op_loop(offset, &mut self.frames);
+                let offset = self.read_jump();
+                if opcodes::control_flow::op_loop(offset, &mut self.frames).is_err() {
+                    return self.raise_error("SystemError", "loop target out of range");
+                }
             }
             OpCode::OpJumpIfFalse => {
-                let offset = self.read_u16();
-                let condition = self.peek(0).clone();
-                opcodes::control_flow::op_jump_if_false(offset, &condition, &mut self.frames);
+                let offset = self.read_jump();
+                let condition = self.stack.peek(0);
+                if let ObjectType::Instance(_) = &**condition {
+                    let condition = condition.clone();
+                    match self.truthiness(&condition) {
+                        Ok(true) => {}
+                        Ok(false) => opcodes::control_flow::op_jump(offset, &mut self.frames),
+                        Err(e) => return e,
+                    }
+                } else {
+                    opcodes::control_flow::op_jump_if_false(offset, condition, &mut self.frames);
+                }
+                self.fold_condition_pop();
             }
             OpCode::OpJump => {
-                let offset = self.read_u16();
+                let offset = self.read_jump();
                 opcodes::control_flow::op_jump(offset, &mut self.frames);
             }
             OpCode::OpSetIndex => {
                 let value = self.pop();
                 let index = self.pop();
                 let collection = self.pop();
-                match opcodes::collections::op_set_index(collection, index, value) {
+                if let ObjectType::Dict(entries) = &*collection {
+                    let (position, hash) = match self.entry_position(entries, &index) {
+                        Ok(found) => found,
+                        Err(e) => return e,
+                    };
+                    self.release_store_target(&collection);
+                    let dict = opcodes::collections::update_dict(collection, |entries| {
+                        set_entry(entries, position, index, value, hash)
+                    });
+                    self.push(dict);
+                    return InterpretResult::Ok;
+                }
+                match opcodes::collections::op_set_index(collection.clone(), index.clone(), value) {
                     Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) =
+                            opcodes::collections::set_index_error(&collection, &index);
+                        return self.raise_error(kind, message);
+                    }
+                }
+            }
+            OpCode::OpSetSlice => {
+                let step = self.pop();
+                let end = self.pop();
+                let start = self.pop();
+                let value = self.pop();
+                let value = match self.gather_generator(&value) {
+                    Ok(value) => value,
                     Err(e) => return e,
+                };
+                let collection = self.pop();
+                match opcodes::collections::op_set_slice(
+                    collection.clone(),
+                    start.clone(),
+                    end.clone(),
+                    step.clone(),
+                    value.clone(),
+                ) {
+                    Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) = opcodes::collections::set_slice_error(
+                            &collection,
+                            &start,
+                            &end,
+                            &step,
+                            &value,
+                        );
+                        return self.raise_error(kind, message);
+                    }
+                }
+            }
+            OpCode::OpDeleteIndex => {
+                let index = self.pop();
+                let collection = self.pop();
+                if let ObjectType::Dict(entries) = &*collection {
+                    let position = match self.find_key(entries, &index) {
+                        Ok(Some(position)) => position,
+                        Ok(None) => {
+                            let (kind, message) =
+                                opcodes::collections::delete_index_error(&collection, &index);
+                            return self.raise_error(kind, message);
+                        }
+                        Err(e) => return e,
+                    };
+                    self.release_store_target(&collection);
+                    let dict = opcodes::collections::update_dict(collection, |entries| {
+                        entries.remove(position);
+                    });
+                    self.push(dict);
+                    return InterpretResult::Ok;
+                }
+                match opcodes::collections::op_delete_index(collection.clone(), index.clone()) {
+                    Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) =
+                            opcodes::collections::delete_index_error(&collection, &index);
+                        return self.raise_error(kind, message);
+                    }
+                }
+            }
+            OpCode::OpDeleteSlice => {
+                let step = self.pop();
+                let end = self.pop();
+                let start = self.pop();
+                let collection = self.pop();
+                match opcodes::collections::op_delete_slice(
+                    collection.clone(),
+                    start.clone(),
+                    end.clone(),
+                    step.clone(),
+                ) {
+                    Ok(result) => self.push(result),
+                    Err(_) => {
+                        let (kind, message) = opcodes::collections::delete_slice_error(
+                            &collection,
+                            &start,
+                            &end,
+                            &step,
+                        );
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpDup => {
@@ -323,15 +775,15 @@ impl VM {
             OpCode::OpContains => {
                 let collection = self.pop();
                 let item = self.pop();
-                match opcodes::collections::op_contains(item, collection) {
-                    Ok(result) => self.push(Rc::new(ObjectType::Boolean(result))),
+                match self.contains(&item, &collection) {
+                    Ok(result) => self.push(values::boolean(result)),
                     Err(e) => return e,
                 }
             }
             OpCode::OpSwap => {
                 let base = self.frames.last().map(|frame| frame.slot + 1).unwrap_or(0);
                 if self.stack.top() < base + 2 {
-                    return InterpretResult::RuntimeError;
+                    return self.bad_operand();
                 }
                 self.stack.swap(self.stack.top() - 1, self.stack.top() - 2);
             }
@@ -341,7 +793,7 @@ impl VM {
                 // Pop class name
                 let class_name = match &*self.pop() {
                     ObjectType::String(name) => name.clone(),
-                    _ => return InterpretResult::RuntimeError,
+                    _ => return self.bad_operand(),
                 };
 
                 // Pop method names (in reverse order)
@@ -349,7 +801,7 @@ impl VM {
                 for _ in 0..method_count {
                     let method_name = match &*self.pop() {
                         ObjectType::String(name) => name.clone(),
-                        _ => return InterpretResult::RuntimeError,
+                        _ => return self.bad_operand(),
                     };
                     method_names.push(method_name);
                 }
@@ -368,35 +820,161 @@ impl VM {
 
                 match opcodes::classes::op_make_class(class_name, method_names, method_funcs) {
                     Ok(class) => self.push(class),
-                    Err(e) => return e,
+                    Err(_) => return self.bad_operand(),
                 }
             }
-            OpCode::OpGetAttr => {
-                let attr_idx = self.read_byte() as usize;
-                let attr_name = match &*self.current_chunk().constants[attr_idx] {
-                    ObjectType::String(name) => name.clone(),
-                    _ => return InterpretResult::RuntimeError,
+            OpCode::OpGetAttr | OpCode::OpGetAttrLong => {
+                let attr_name = match self.read_constant(instruction).as_deref() {
+                    Some(ObjectType::String(name)) => name.clone(),
+                    _ => return self.bad_operand(),
                 };
 
                 let object = self.pop();
-                match opcodes::attributes::op_get_attr(object, &attr_name, &self.stack) {
+                let intercepted = self
+                    .sys_attribute(&object, &attr_name)
+                    .or_else(|| self.function_globals(&object, &attr_name));
+                if let Some(value) = intercepted {
+                    self.push(value);
+                    return InterpretResult::Ok;
+                }
+                match opcodes::attributes::op_get_attr(object.clone(), &attr_name, &self.stack) {
                     Ok(value) => self.push(value),
+                    Err(_) => {
+                        let message = opcodes::attributes::attr_error(&object, &attr_name);
+                        return self.raise_error("AttributeError", message);
+                    }
+                }
+            }
+            OpCode::OpImport | OpCode::OpImportLong => {
+                let name = match self.read_constant(instruction).as_deref() {
+                    Some(ObjectType::String(name)) => name.clone(),
+                    _ => return self.bad_operand(),
+                };
+                match self.import_module(&name) {
+                    Ok(module) => self.push(module),
                     Err(e) => return e,
                 }
             }
-            OpCode::OpSetAttr => {
-                let attr_idx = self.read_byte() as usize;
-                let attr_name = match &*self.current_chunk().constants[attr_idx] {
-                    ObjectType::String(name) => name.clone(),
-                    _ => return InterpretResult::RuntimeError,
+            OpCode::OpImportFrom | OpCode::OpImportFromLong => {
+                let name = match self.read_constant(instruction).as_deref() {
+                    Some(ObjectType::String(name)) => name.clone(),
+                    _ => return self.bad_operand(),
+                };
+                let module = self.pop();
+                if let Some(value) = self.sys_attribute(&module, &name) {
+                    self.push(value);
+                    return InterpretResult::Ok;
+                }
+                let ObjectType::Module(module) = &*module else {
+                    return self.bad_operand();
+                };
+                if let Some(value) = module.attributes.get(&name) {
+                    self.push(value.clone());
+                    return InterpretResult::Ok;
+                }
+                match self.import_submodule(module, &name) {
+                    Some(Ok(submodule)) => self.push(submodule),
+                    Some(Err(e)) => return e,
+                    None => {
+                        let message =
+                            format!("cannot import name '{}' from '{}'", name, module.name);
+                        return self.raise_error("ImportError", message);
+                    }
+                }
+            }
+            OpCode::OpEvaluatedNamespace => self.op_evaluated_namespace(),
+            OpCode::OpSetupExcept => {
+                let offset = self.read_jump();
+                self.setup_handler(HandlerKind::Except, offset);
+            }
+            OpCode::OpSetupFinally => {
+                let offset = self.read_jump();
+                self.setup_handler(HandlerKind::Finally, offset);
+            }
+            OpCode::OpPopTry => {
+                self.handlers.pop();
+            }
+            OpCode::OpRaise => {
+                let value = self.pop();
+                let Some(exception) = exceptions::raisable(&value) else {
+                    return self
+                        .raise_error("TypeError", "exceptions must derive from BaseException");
+                };
+                return self.raise_exception(exception);
+            }
+            OpCode::OpReraise => {
+                let Some(raised) = self.caught.last().cloned() else {
+                    return self.raise_error("RuntimeError", "No active exception to reraise");
+                };
+                return self.reraise(raised);
+            }
+            OpCode::OpExceptMatch => {
+                let expected = self.pop();
+                let class = match self.caught_exception().map(|e| &**e) {
+                    Some(ObjectType::Instance(caught)) => caught.borrow().class.clone(),
+                    _ => return self.raise_error("SystemError", "no exception is being handled"),
+                };
+                match exceptions::matches(&class, &expected) {
+                    Some(matched) => self.push(Rc::new(ObjectType::Boolean(matched))),
+                    None => return self.raise_error(
+                        "TypeError",
+                        "catching classes that do not inherit from BaseException is not allowed",
+                    ),
+                }
+            }
+            OpCode::OpGetCaught => {
+                let Some(exception) = self.caught_exception().cloned() else {
+                    return self.raise_error("SystemError", "no exception is being handled");
+                };
+                self.push(exception);
+            }
+            OpCode::OpEndExcept => {
+                self.caught.pop();
+            }
+            OpCode::OpEnterFinally => {
+                self.pending.push(None);
+            }
+            OpCode::OpEndFinally => {
+                if let Some(Some(raised)) = self.pending.pop() {
+                    return self.reraise(raised);
+                }
+            }
+            OpCode::OpPopFinally => {
+                self.pending.pop();
+            }
+            OpCode::OpFormatValue => {
+                let flags = self.read_byte();
+                let spec = if flags & FORMAT_WITH_SPEC != 0 {
+                    self.pop().to_string()
+                } else {
+                    String::new()
+                };
+                let value = self.pop();
+                match self.format_field(value, flags, &spec) {
+                    Ok(text) => self.push(Rc::new(ObjectType::String(text))),
+                    Err(error) => return error,
+                }
+            }
+            OpCode::OpSetAttr | OpCode::OpSetAttrLong => {
+                let attr_name = match self.read_constant(instruction).as_deref() {
+                    Some(ObjectType::String(name)) => name.clone(),
+                    _ => return self.bad_operand(),
                 };
 
                 let value = self.pop();
                 let object = self.pop();
 
-                match opcodes::attributes::op_set_attr(object, attr_name, value) {
-                    Ok(()) => {}
-                    Err(e) => return e,
+                if let Some(result) = self.set_sys_attribute(&object, &attr_name, value.clone()) {
+                    if let Err(error) = result {
+                        return self.raise_error(error.kind, error.message);
+                    }
+                    return InterpretResult::Ok;
+                }
+                if opcodes::attributes::op_set_attr(object.clone(), attr_name.clone(), value)
+                    .is_err()
+                {
+                    let (kind, message) = opcodes::attributes::set_attr_error(&object, &attr_name);
+                    return self.raise_error(kind, message);
                 }
             }
             OpCode::OpInherit => {
@@ -404,9 +982,12 @@ impl VM {
                 let parent = self.pop();
                 let child = self.pop();
 
-                match opcodes::classes::op_inherit(child, parent) {
+                match opcodes::classes::op_inherit(child.clone(), parent.clone()) {
                     Ok(class) => self.push(class),
-                    Err(e) => return e,
+                    Err(_) => {
+                        let (kind, message) = opcodes::classes::inherit_error(&child, &parent);
+                        return self.raise_error(kind, message);
+                    }
                 }
             }
             OpCode::OpType => {
@@ -418,4 +999,41 @@ impl VM {
 
         InterpretResult::Ok
     }
+
+    /// Lets go of the variable's hold on `collection` when the next
+    /// instruction stores the updated collection back to the local or global
+    /// it was read from, as `d[k] = v` and `del d[k]` do, along with the
+    /// stack's record of it as the last value popped. An update that leaves
+    /// nothing else holding it then changes it in place rather than copying
+    /// every entry.
+    fn release_store_target(&mut self, collection: &Object) {
+        if Rc::ptr_eq(&self.stack.last_popped(), collection) {
+            self.stack.set_last_popped(values::none());
+        }
+        let frame = self.frames.last().expect("expected active call frame");
+        let (ip, base) = (frame.ip, frame.slot);
+        match OpCode::from_byte(self.read_byte()) {
+            Some(instruction @ (OpCode::OpSetLocal | OpCode::OpSetLocalLong)) => {
+                let index = base + self.read_slot(instruction);
+                if Rc::ptr_eq(self.stack.get(index), collection) {
+                    self.stack.set(index, values::none());
+                }
+            }
+            Some(instruction @ (OpCode::OpSetGlobal | OpCode::OpSetGlobalLong)) => {
+                self.sync_globals();
+                if let Some(ObjectType::String(name)) = self.read_constant(instruction).as_deref() {
+                    if let Some(value) = self.globals.get_mut(name) {
+                        if Rc::ptr_eq(value, collection) {
+                            *value = values::none();
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.frames
+            .last_mut()
+            .expect("expected active call frame")
+            .ip = ip;
+    }
 }
@@ -1,13 +1,19 @@
 use crate::object::ObjectType;
+use crate::sync::Rc;
+use crate::vm::values::integer;
 use crate::vm::InterpretResult;
-use std::rc::Rc;
 
-/// Handle OpAdd - Add two values (integers, floats, or strings)
+/// Handle OpAdd - Add two numbers, or concatenate two strings, lists or tuples
+///
+/// Integer results that overflow an i64 are reported as a RuntimeError,
+/// which `arithmetic_error` describes like any other failure.
 pub fn op_add(a: Rc<ObjectType>, b: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
+    let (a, b) = (as_int(a), as_int(b));
     match (&*a, &*b) {
-        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => {
-            Ok(Rc::new(ObjectType::Integer(val_a + val_b)))
-        }
+        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => val_a
+            .checked_add(*val_b)
+            .map(integer)
+            .ok_or(InterpretResult::RuntimeError),
         (ObjectType::Float(val_a), ObjectType::Float(val_b)) => {
             Ok(Rc::new(ObjectType::Float(val_a + val_b)))
         }
@@ -22,19 +28,64 @@ pub fn op_add(a: Rc<ObjectType>, b: Rc<ObjectType>) -> Result<Rc<ObjectType>, In
             combined.push_str(val_b);
             Ok(Rc::new(ObjectType::String(combined)))
         }
+        (ObjectType::List(items_a), ObjectType::List(items_b)) => {
+            Ok(Rc::new(ObjectType::List(concatenate(items_a, items_b))))
+        }
+        (ObjectType::Tuple(items_a), ObjectType::Tuple(items_b)) => {
+            Ok(Rc::new(ObjectType::Tuple(concatenate(items_a, items_b))))
+        }
         _ => Err(InterpretResult::RuntimeError),
     }
 }
 
+/// The items of `a` followed by those of `b`.
+fn concatenate(a: &[Rc<ObjectType>], b: &[Rc<ObjectType>]) -> Vec<Rc<ObjectType>> {
+    let mut items = Vec::with_capacity(a.len() + b.len());
+    items.extend_from_slice(a);
+    items.extend_from_slice(b);
+    items
+}
+
+/// Handle OpNegate - Negate a numeric value
+///
+/// Floats flip their sign, so `-0.0` stays a negative zero rather than
+/// becoming `0 - 0.0`.
+pub fn op_negate(value: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
+    match &*as_int(value) {
+        ObjectType::Integer(number) => number
+            .checked_neg()
+            .map(integer)
+            .ok_or(InterpretResult::RuntimeError),
+        ObjectType::Float(number) => Ok(Rc::new(ObjectType::Float(-number))),
+        _ => Err(InterpretResult::RuntimeError),
+    }
+}
+
+/// Describes why `-value` failed, as `arithmetic_error` does for binary
+/// operators.
+pub fn negate_error(value: &ObjectType) -> (&'static str, String) {
+    match value {
+        ObjectType::Integer(_) | ObjectType::Boolean(_) => {
+            ("OverflowError", "integer overflow".to_string())
+        }
+        _ => (
+            "TypeError",
+            format!("bad operand type for unary -: '{}'", value.type_name()),
+        ),
+    }
+}
+
 /// Handle OpSubtract - Subtract two numeric values
 pub fn op_subtract(
     a: Rc<ObjectType>,
     b: Rc<ObjectType>,
 ) -> Result<Rc<ObjectType>, InterpretResult> {
+    let (a, b) = (as_int(a), as_int(b));
     match (&*a, &*b) {
-        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => {
-            Ok(Rc::new(ObjectType::Integer(val_a - val_b)))
-        }
+        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => val_a
+            .checked_sub(*val_b)
+            .map(integer)
+            .ok_or(InterpretResult::RuntimeError),
         (ObjectType::Float(val_a), ObjectType::Float(val_b)) => {
             Ok(Rc::new(ObjectType::Float(val_a - val_b)))
         }
@@ -53,10 +104,12 @@ pub fn op_multiply(
     a: Rc<ObjectType>,
     b: Rc<ObjectType>,
 ) -> Result<Rc<ObjectType>, InterpretResult> {
+    let (a, b) = (as_int(a), as_int(b));
     match (&*a, &*b) {
-        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => {
-            Ok(Rc::new(ObjectType::Integer(val_a * val_b)))
-        }
+        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => val_a
+            .checked_mul(*val_b)
+            .map(integer)
+            .ok_or(InterpretResult::RuntimeError),
         (ObjectType::Float(val_a), ObjectType::Float(val_b)) => {
             Ok(Rc::new(ObjectType::Float(val_a * val_b)))
         }
@@ -72,6 +125,7 @@ pub fn op_multiply(
 
 /// Handle OpDivide - Divide two numeric values
 pub fn op_divide(a: Rc<ObjectType>, b: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
+    let (a, b) = (as_int(a), as_int(b));
     let lhs = match &*a {
         ObjectType::Integer(v) => *v as f64,
         ObjectType::Float(v) => *v,
@@ -88,15 +142,193 @@ pub fn op_divide(a: Rc<ObjectType>, b: Rc<ObjectType>) -> Result<Rc<ObjectType>,
     Ok(Rc::new(ObjectType::Float(lhs / rhs)))
 }
 
-/// Handle OpModulo - Modulo operation on integers
-pub fn op_modulo(a: Rc<ObjectType>, b: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
+/// Handle OpFloorDivide - Divide and round towards negative infinity
+///
+/// Two integers give an integer; any float operand gives a float.
+pub fn op_floor_divide(
+    a: Rc<ObjectType>,
+    b: Rc<ObjectType>,
+) -> Result<Rc<ObjectType>, InterpretResult> {
+    let (a, b) = (as_int(a), as_int(b));
     match (&*a, &*b) {
         (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => {
-            if *val_b == 0 {
-                return Err(InterpretResult::RuntimeError);
-            }
-            Ok(Rc::new(ObjectType::Integer(val_a % val_b)))
+            let (quotient, _) = int_divmod(*val_a, *val_b)?;
+            Ok(integer(quotient))
         }
+        _ => {
+            let (quotient, _) = float_divmod(as_float(&a)?, as_float(&b)?)?;
+            Ok(Rc::new(ObjectType::Float(quotient)))
+        }
+    }
+}
+
+/// Describes why the binary arithmetic `a <operator> b` failed, as an
+/// exception kind and message naming the operand types. `divmod()` stands
+/// in for the operator when the builtin failed.
+pub fn arithmetic_error(operator: &str, a: &ObjectType, b: &ObjectType) -> (&'static str, String) {
+    let is_number = |value: &ObjectType| {
+        matches!(
+            value,
+            ObjectType::Integer(_) | ObjectType::Float(_) | ObjectType::Boolean(_)
+        )
+    };
+    let is_sequence = |value: &ObjectType| {
+        matches!(
+            value,
+            ObjectType::String(_) | ObjectType::List(_) | ObjectType::Tuple(_)
+        )
+    };
+
+    if is_number(a) && is_number(b) {
+        let integers = !matches!(a, ObjectType::Float(_)) && !matches!(b, ObjectType::Float(_));
+        let by_zero = matches!(b, ObjectType::Integer(0) | ObjectType::Boolean(false))
+            || matches!(b, ObjectType::Float(v) if *v == 0.0);
+        let message = match operator {
+            _ if !by_zero => return ("OverflowError", "integer overflow".to_string()),
+            "/" => "division by zero",
+            "//" | "divmod()" if integers => "integer division or modulo by zero",
+            "%" if integers => "integer modulo by zero",
+            "//" => "float floor division by zero",
+            "divmod()" => "float divmod()",
+            _ => "float modulo by zero",
+        };
+        return ("ZeroDivisionError", message.to_string());
+    }
+
+    let message = match operator {
+        "+" if is_sequence(a) => format!(
+            "can only concatenate {} (not \"{}\") to {}",
+            a.type_name(),
+            b.type_name(),
+            a.type_name()
+        ),
+        "*" if is_sequence(a) && !matches!(b, ObjectType::Integer(_)) => format!(
+            "can't multiply sequence by non-int of type '{}'",
+            b.type_name()
+        ),
+        "*" if is_sequence(b) && !matches!(a, ObjectType::Integer(_)) => format!(
+            "can't multiply sequence by non-int of type '{}'",
+            a.type_name()
+        ),
+        _ => format!(
+            "unsupported operand type(s) for {}: '{}' and '{}'",
+            operator,
+            a.type_name(),
+            b.type_name()
+        ),
+    };
+    ("TypeError", message)
+}
+
+/// Computes `divmod(a, b)` as a `(quotient, remainder)` tuple.
+///
+/// The quotient is floored and the remainder takes the sign of the divisor,
+/// so `a == quotient * b + remainder` holds as in Python.
+pub fn op_divmod(a: Rc<ObjectType>, b: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
+    let (a, b) = (as_int(a), as_int(b));
+    let (quotient, remainder) = match (&*a, &*b) {
+        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => {
+            let (quotient, remainder) = int_divmod(*val_a, *val_b)?;
+            (integer(quotient), integer(remainder))
+        }
+        _ => {
+            let (quotient, remainder) = float_divmod(as_float(&a)?, as_float(&b)?)?;
+            (
+                Rc::new(ObjectType::Float(quotient)),
+                Rc::new(ObjectType::Float(remainder)),
+            )
+        }
+    };
+    Ok(Rc::new(ObjectType::Tuple(vec![quotient, remainder])))
+}
+
+/// The result of `operation` on two integers, or None when either value is
+/// not an integer or the result overflows. The dispatcher tries it before
+/// the general operations, which loop counters and recursive calls would
+/// otherwise spend their time matching through.
+#[inline(always)]
+pub fn integer_operation(
+    a: &ObjectType,
+    b: &ObjectType,
+    operation: fn(i64, i64) -> Option<i64>,
+) -> Option<i64> {
+    match (a, b) {
+        (ObjectType::Integer(a), ObjectType::Integer(b)) => operation(*a, *b),
+        _ => None,
+    }
+}
+
+/// Booleans take part in arithmetic as the integers 0 and 1.
+fn as_int(value: Rc<ObjectType>) -> Rc<ObjectType> {
+    match &*value {
+        ObjectType::Boolean(flag) => integer(*flag as i64),
+        _ => value,
+    }
+}
+
+fn as_float(value: &ObjectType) -> Result<f64, InterpretResult> {
+    match value {
+        ObjectType::Integer(v) => Ok(*v as f64),
+        ObjectType::Float(v) => Ok(*v),
         _ => Err(InterpretResult::RuntimeError),
     }
 }
+
+/// Floored integer division and remainder.
+fn int_divmod(a: i64, b: i64) -> Result<(i64, i64), InterpretResult> {
+    if b == 0 {
+        return Err(InterpretResult::RuntimeError);
+    }
+    let quotient = a.checked_div(b).ok_or(InterpretResult::RuntimeError)?;
+    let remainder = a - quotient * b;
+    if remainder != 0 && ((remainder < 0) != (b < 0)) {
+        Ok((quotient - 1, remainder + b))
+    } else {
+        Ok((quotient, remainder))
+    }
+}
+
+/// Floored float division and remainder, following CPython's `float_divmod`.
+fn float_divmod(a: f64, b: f64) -> Result<(f64, f64), InterpretResult> {
+    if b == 0.0 {
+        return Err(InterpretResult::RuntimeError);
+    }
+    let mut remainder = a % b;
+    let mut quotient = (a - remainder) / b;
+    if remainder != 0.0 {
+        if (b < 0.0) != (remainder < 0.0) {
+            remainder += b;
+            quotient -= 1.0;
+        }
+    } else {
+        remainder = 0.0_f64.copysign(b);
+    }
+    let floored = if quotient != 0.0 {
+        let floor = quotient.floor();
+        if quotient - floor > 0.5 {
+            floor + 1.0
+        } else {
+            floor
+        }
+    } else {
+        0.0_f64.copysign(a / b)
+    };
+    Ok((floored, remainder))
+}
+
+/// Handle OpModulo - Remainder with the sign of the divisor, as in Python
+///
+/// Consistent with `op_floor_divide`, so `a == (a // b) * b + a % b`.
+pub fn op_modulo(a: Rc<ObjectType>, b: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
+    let (a, b) = (as_int(a), as_int(b));
+    match (&*a, &*b) {
+        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => {
+            let (_, remainder) = int_divmod(*val_a, *val_b)?;
+            Ok(integer(remainder))
+        }
+        _ => {
+            let (_, remainder) = float_divmod(as_float(&a)?, as_float(&b)?)?;
+            Ok(Rc::new(ObjectType::Float(remainder)))
+        }
+    }
+}
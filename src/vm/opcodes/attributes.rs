@@ -1,11 +1,28 @@
 //! Attribute access opcodes (OpGetAttr, OpSetAttr).
 //!
-//! Handles attribute access for instances, classes, and function introspection.
+//! Handles attribute access for instances, classes, modules, string and
+//! collection methods, and function introspection.
 
-use crate::object::{FunctionObject, FunctionPrototype, Object, ObjectType};
+use crate::object::{DictEntries, FunctionObject, FunctionPrototype, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::collection_methods::{collection_method, COLLECTION_METHODS};
 use crate::vm::stack_ops::Stack;
+use crate::vm::string_methods::{string_method, STRING_METHODS};
 use crate::vm::InterpretResult;
-use std::rc::Rc;
+use std::collections::BTreeSet;
+
+/// Introspection attributes of functions, sorted.
+pub const FUNCTION_ATTRIBUTES: [&str; 9] = [
+    "__annotations__",
+    "__closure__",
+    "__code__",
+    "__defaults__",
+    "__doc__",
+    "__globals__",
+    "__module__",
+    "__name__",
+    "__qualname__",
+];
 
 /// Get an attribute from an object.
 ///
@@ -25,6 +42,22 @@ pub fn op_get_attr(
         }
         ObjectType::Function(func) => get_function_attr(func, attr_name, stack),
         ObjectType::FunctionPrototype(proto) => get_function_prototype_attr(proto, attr_name),
+        ObjectType::Module(module) => match attr_name {
+            "__name__" => Ok(Rc::new(ObjectType::String(module.name.clone()))),
+            _ => module
+                .attributes
+                .get(attr_name)
+                .cloned()
+                .ok_or(InterpretResult::RuntimeError),
+        },
+        ObjectType::String(_) => match string_method(attr_name) {
+            Some(method) => Ok(Rc::new(ObjectType::BoundMethod(object.clone(), method))),
+            None => Err(InterpretResult::RuntimeError),
+        },
+        ObjectType::List(_) | ObjectType::Dict(_) => match collection_method(attr_name) {
+            Some(method) => Ok(Rc::new(ObjectType::BoundMethod(object.clone(), method))),
+            None => Err(InterpretResult::RuntimeError),
+        },
         _ => Err(InterpretResult::RuntimeError),
     }
 }
@@ -32,7 +65,7 @@ pub fn op_get_attr(
 /// Get an attribute from an instance (field or method).
 fn get_instance_attr(
     object: Object,
-    instance_ref: &std::cell::RefCell<crate::object::InstanceObject>,
+    instance_ref: &crate::sync::RefCell<crate::object::InstanceObject>,
     attr_name: &str,
 ) -> Result<Object, InterpretResult> {
     let instance = instance_ref.borrow();
@@ -40,9 +73,21 @@ fn get_instance_attr(
     // First check instance fields
     if let Some(value) = instance.get_field(attr_name) {
         Ok(value)
-    } else if let Some(method) = instance.class.get_method(attr_name) {
-        // Create a bound method (using inheritance chain)
-        Ok(Rc::new(ObjectType::BoundMethod(object, method.clone())))
+    } else if let Some(attribute) = instance.class.get_method(attr_name) {
+        // Functions found on the class (through the inheritance chain) become
+        // bound methods; other class attributes are shared as they are
+        match &*attribute {
+            ObjectType::Function(_)
+            | ObjectType::NativeFunction(_, _)
+            | ObjectType::VmNativeFunction(_, _) => {
+                Ok(Rc::new(ObjectType::BoundMethod(object, attribute)))
+            }
+            ObjectType::ClassMethod(function) => {
+                let class = Rc::new(ObjectType::Class(instance.class.clone()));
+                Ok(Rc::new(ObjectType::BoundMethod(class, function.clone())))
+            }
+            _ => Ok(attribute),
+        }
     } else {
         Err(InterpretResult::RuntimeError)
     }
@@ -53,10 +98,18 @@ fn get_class_attr(
     class: &Rc<crate::object::ClassObject>,
     attr_name: &str,
 ) -> Result<Object, InterpretResult> {
-    // Access method from class directly (using inheritance chain)
-    class
+    // Access method from class directly (using inheritance chain); class
+    // methods are bound to the class they are looked up on
+    let attribute = class
         .get_method(attr_name)
-        .ok_or(InterpretResult::RuntimeError)
+        .ok_or(InterpretResult::RuntimeError)?;
+    match &*attribute {
+        ObjectType::ClassMethod(function) => Ok(Rc::new(ObjectType::BoundMethod(
+            Rc::new(ObjectType::Class(class.clone())),
+            function.clone(),
+        ))),
+        _ => Ok(attribute),
+    }
 }
 
 /// Get an attribute from a super proxy (parent class method).
@@ -65,12 +118,23 @@ fn get_super_proxy_attr(
     parent_class: &Rc<crate::object::ClassObject>,
     attr_name: &str,
 ) -> Result<Object, InterpretResult> {
-    // Look up method in the parent class only (not the full chain)
-    if let Some(method) = parent_class.get_method(attr_name) {
-        // Create a bound method with the instance
-        Ok(Rc::new(ObjectType::BoundMethod(instance, method.clone())))
-    } else {
-        Err(InterpretResult::RuntimeError)
+    // Look up the method from the parent class up its inheritance chain
+    let method = parent_class
+        .get_method(attr_name)
+        .ok_or(InterpretResult::RuntimeError)?;
+    match (&*instance, &*method) {
+        // Class methods bind to the receiver's class, or to the receiver
+        // itself when super() was called in a class method
+        (ObjectType::Instance(instance_ref), ObjectType::ClassMethod(function)) => {
+            let class = Rc::new(ObjectType::Class(instance_ref.borrow().class.clone()));
+            Ok(Rc::new(ObjectType::BoundMethod(class, function.clone())))
+        }
+        (_, ObjectType::ClassMethod(function)) => {
+            Ok(Rc::new(ObjectType::BoundMethod(instance, function.clone())))
+        }
+        // Plain methods found from a class method are not bound
+        (ObjectType::Class(_), _) => Ok(method),
+        _ => Ok(Rc::new(ObjectType::BoundMethod(instance, method))),
     }
 }
 
@@ -104,19 +168,11 @@ fn get_function_attr(
                 annotations.push(("return".to_string(), type_str));
             }
 
-            Ok(Rc::new(ObjectType::Dict(annotations)))
+            Ok(Rc::new(ObjectType::dict_of_names(annotations)))
         }
         "__code__" => Ok(Rc::new(ObjectType::CodeObject(func.chunk.clone()))),
         "__qualname__" => Ok(Rc::new(ObjectType::String(func.qualname.clone()))),
-        "__globals__" => {
-            // Convert HashMap to Dict format (Vec<(String, Object)>)
-            let globals_vec: Vec<(String, Object)> = func
-                .globals
-                .iter()
-                .map(|(k, v)| (k.clone(), v.clone()))
-                .collect();
-            Ok(Rc::new(ObjectType::Dict(globals_vec)))
-        }
+        // `__globals__` is read off the VM (see `VM::function_globals`)
         "__closure__" => {
             // Return a tuple of cell objects (upvalues), or None if no closure
             if func.upvalues.is_empty() {
@@ -185,13 +241,13 @@ fn get_function_prototype_attr(
                 annotations.push(("return".to_string(), type_str));
             }
 
-            Ok(Rc::new(ObjectType::Dict(annotations)))
+            Ok(Rc::new(ObjectType::dict_of_names(annotations)))
         }
         "__code__" => Ok(Rc::new(ObjectType::CodeObject(proto.chunk.clone()))),
         "__qualname__" => Ok(Rc::new(ObjectType::String(proto.qualname.clone()))),
         "__globals__" => {
             // Prototypes don't have globals captured yet - return empty dict
-            Ok(Rc::new(ObjectType::Dict(Vec::new())))
+            Ok(Rc::new(ObjectType::Dict(DictEntries::new())))
         }
         "__closure__" => {
             // Prototypes are templates, not runtime closures
@@ -229,3 +285,78 @@ pub fn op_set_attr(
         _ => Err(InterpretResult::RuntimeError),
     }
 }
+
+/// Describes why `op_get_attr` found no attribute `attr_name` on `object`.
+pub fn attr_error(object: &ObjectType, attr_name: &str) -> String {
+    match object {
+        ObjectType::Class(class) => format!(
+            "type object '{}' has no attribute '{}'",
+            class.name, attr_name
+        ),
+        ObjectType::Module(module) => {
+            format!("module '{}' has no attribute '{}'", module.name, attr_name)
+        }
+        _ => format!(
+            "'{}' object has no attribute '{}'",
+            object.type_name(),
+            attr_name
+        ),
+    }
+}
+
+/// Describes why `op_set_attr` could not set `attr_name` on `object`, as an
+/// exception kind and message. Classes and modules are fixed once created.
+pub fn set_attr_error(object: &ObjectType, attr_name: &str) -> (&'static str, String) {
+    match object {
+        ObjectType::Class(class) => (
+            "TypeError",
+            format!(
+                "cannot set '{}' attribute of immutable type '{}'",
+                attr_name, class.name
+            ),
+        ),
+        ObjectType::Module(_) => (
+            "AttributeError",
+            format!("'module' object attribute '{}' is read-only", attr_name),
+        ),
+        _ => ("AttributeError", attr_error(object, attr_name)),
+    }
+}
+
+/// The names `op_get_attr` finds on `object`, sorted: an instance's fields
+/// together with everything its class and the class's ancestors define.
+pub fn attribute_names(object: &ObjectType) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    let mut class = match object {
+        ObjectType::Instance(instance_ref) => {
+            let instance = instance_ref.borrow();
+            names.extend(instance.fields.iter().map(|(name, _)| name.clone()));
+            Some(instance.class.clone())
+        }
+        ObjectType::Class(class) => Some(class.clone()),
+        ObjectType::SuperProxy(_, parent_class) => Some(parent_class.clone()),
+        ObjectType::Module(module) => {
+            names.extend(module.attributes.keys().cloned());
+            names.insert("__name__".to_string());
+            None
+        }
+        ObjectType::Function(_) | ObjectType::FunctionPrototype(_) => {
+            names.extend(FUNCTION_ATTRIBUTES.iter().map(|name| name.to_string()));
+            None
+        }
+        ObjectType::String(_) => {
+            names.extend(STRING_METHODS.iter().map(|name| name.to_string()));
+            None
+        }
+        ObjectType::List(_) | ObjectType::Dict(_) => {
+            names.extend(COLLECTION_METHODS.iter().map(|name| name.to_string()));
+            None
+        }
+        _ => None,
+    };
+    while let Some(current) = class {
+        names.extend(current.methods.keys().cloned());
+        class = current.parent.clone();
+    }
+    names.into_iter().collect()
+}
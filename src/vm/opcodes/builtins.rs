@@ -1,81 +1,137 @@
 #![allow(dead_code)]
 
-use crate::object::{Object, ObjectType};
+use crate::object::ObjectType;
+use crate::sync::Rc;
 use crate::vm::collections::collect_iterable;
+use crate::vm::values::integer;
 use crate::vm::InterpretResult;
-use std::rc::Rc;
+use std::cmp::Ordering;
 
-/// Handle OpRound - Round a float to specified decimal places
+/// Handle OpRound - Round a number to `digits` decimal places, as
+/// `round(value, digits)` does
+///
+/// Integers stay integers and floats stay floats. Halfway cases go to the
+/// even neighbour, judged on the exact value of the float, so
+/// `round(2.675, 2)` is 2.67: the float nearest 2.675 lies just below it.
 pub fn op_round(
     value: Rc<ObjectType>,
     digits: Rc<ObjectType>,
 ) -> Result<Rc<ObjectType>, InterpretResult> {
-    let digits = match &*digits {
-        ObjectType::Integer(v) => *v as i32,
-        _ => return Err(InterpretResult::RuntimeError),
+    let ObjectType::Integer(digits) = &*digits else {
+        return Err(InterpretResult::RuntimeError);
     };
 
-    let number = match &*value {
-        ObjectType::Integer(v) => *v as f64,
-        ObjectType::Float(v) => *v,
-        _ => return Err(InterpretResult::RuntimeError),
-    };
+    match &*value {
+        ObjectType::Integer(number) => round_integer(*number, *digits)
+            .map(integer)
+            .ok_or(InterpretResult::RuntimeError),
+        ObjectType::Float(number) => Ok(Rc::new(ObjectType::Float(round_float(*number, *digits)))),
+        _ => Err(InterpretResult::RuntimeError),
+    }
+}
 
-    let factor = 10f64.powi(digits.max(0));
-    let rounded = (number * factor).round() / factor;
-    Ok(Rc::new(ObjectType::Float(rounded)))
+/// Describes why `op_round` rejected `round(value, digits)`, as an exception
+/// kind and message.
+pub fn round_error(value: &ObjectType, digits: &ObjectType) -> (&'static str, String) {
+    match (value, digits) {
+        (ObjectType::Integer(_) | ObjectType::Float(_), ObjectType::Integer(_)) => {
+            ("OverflowError", "integer overflow".to_string())
+        }
+        (ObjectType::Integer(_) | ObjectType::Float(_), _) => (
+            "TypeError",
+            format!(
+                "'{}' object cannot be interpreted as an integer",
+                digits.type_name()
+            ),
+        ),
+        _ => (
+            "TypeError",
+            format!("type {} doesn't define __round__ method", value.type_name()),
+        ),
+    }
 }
 
-/// Handle OpToList - Convert an iterable to a list
-pub fn op_to_list(value: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
-    match collect_iterable(&value) {
-        Some(elements) => Ok(Rc::new(ObjectType::List(elements))),
-        None => Err(InterpretResult::RuntimeError),
+/// Rounds an integer to a multiple of `10^-digits`, or returns it unchanged
+/// when `digits` is not negative. `None` means the result overflowed.
+fn round_integer(number: i64, digits: i64) -> Option<i64> {
+    if digits >= 0 {
+        return Some(number);
     }
+    let Some(scale) = 10i128.checked_pow(digits.unsigned_abs().min(39) as u32) else {
+        return Some(0);
+    };
+    let number = number as i128;
+    let (quotient, remainder) = (number.div_euclid(scale), number.rem_euclid(scale));
+    let rounded = match (2 * remainder).cmp(&scale) {
+        Ordering::Less => quotient,
+        Ordering::Greater => quotient + 1,
+        Ordering::Equal => quotient + quotient.rem_euclid(2),
+    };
+    i64::try_from(rounded * scale).ok()
 }
 
-/// Handle OpZip - Zip multiple iterables together
-/// Returns a list of tuples
-pub fn op_zip(args: Vec<Object>, star_mask: u16) -> Result<Rc<ObjectType>, InterpretResult> {
-    if args.is_empty() {
-        return Ok(Rc::new(ObjectType::List(Vec::new())));
+/// Rounds a float to `digits` decimal places, working on the exact decimal
+/// expansion of its value the way CPython does.
+fn round_float(number: f64, digits: i64) -> f64 {
+    // Beyond these, every float is already rounded or rounds to zero
+    if !number.is_finite() || digits > 323 {
+        return number;
+    }
+    if digits < -308 {
+        return 0.0_f64.copysign(number);
     }
 
-    let mut iterables: Vec<Vec<Object>> = Vec::new();
+    let text = if digits >= 0 {
+        // Fixed-precision formatting rounds the exact value, ties to even
+        format!("{:.*}", digits as usize, number)
+    } else {
+        round_integer_digits(number, digits.unsigned_abs() as usize)
+    };
+    let rounded: f64 = text.parse().unwrap_or(number);
+    rounded.copysign(number)
+}
+
+/// Rounds the integer part of `number` to a multiple of `10^places`, ties
+/// to even, returning the result in decimal.
+fn round_integer_digits(number: f64, places: usize) -> String {
+    let magnitude = number.abs();
+    // Formatting an integral float with no fraction digits is exact
+    let integer = format!("{:0>width$.0}", magnitude.trunc(), width = places + 1);
+    let (head, tail) = integer.split_at(integer.len() - places);
+    let half = format!("5{}", "0".repeat(places - 1));
+    let round_up = match tail.cmp(&half) {
+        Ordering::Less => false,
+        Ordering::Greater => true,
+        Ordering::Equal => magnitude.fract() != 0.0 || head.ends_with(['1', '3', '5', '7', '9']),
+    };
 
-    for (index, arg) in args.into_iter().enumerate() {
-        if (star_mask & (1 << index)) != 0 {
-            match &*arg {
-                ObjectType::List(values) => {
-                    for item in values {
-                        if let Some(collected) = collect_iterable(item) {
-                            iterables.push(collected);
-                        } else {
-                            return Err(InterpretResult::RuntimeError);
-                        }
-                    }
-                }
-                _ => return Err(InterpretResult::RuntimeError),
+    let mut head = head.as_bytes().to_vec();
+    if round_up {
+        let mut index = head.len();
+        loop {
+            if index == 0 {
+                head.insert(0, b'1');
+                break;
+            }
+            index -= 1;
+            if head[index] == b'9' {
+                head[index] = b'0';
+            } else {
+                head[index] += 1;
+                break;
             }
-        } else if let Some(collected) = collect_iterable(&arg) {
-            iterables.push(collected);
-        } else {
-            return Err(InterpretResult::RuntimeError);
         }
     }
+    let head = String::from_utf8(head).expect("decimal digits are ASCII");
+    format!("{}{}", head, "0".repeat(places))
+}
 
-    let min_len = iterables.iter().map(|items| items.len()).min().unwrap_or(0);
-
-    let mut zipped = Vec::with_capacity(min_len);
-    for idx in 0..min_len {
-        let mut row = Vec::with_capacity(iterables.len());
-        for iterable in &iterables {
-            row.push(iterable[idx].clone());
-        }
-        zipped.push(Rc::new(ObjectType::Tuple(row)));
+/// Handle OpToList - Convert an iterable to a list
+pub fn op_to_list(value: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
+    match collect_iterable(&value) {
+        Some(elements) => Ok(Rc::new(ObjectType::List(elements))),
+        None => Err(InterpretResult::RuntimeError),
     }
-
-    Ok(Rc::new(ObjectType::List(zipped)))
 }
 
 /// Handle OpType - Get the type of an object as a string
@@ -5,9 +5,9 @@
 // Note: Class operations involve complex object manipulation and method resolution.
 
 use crate::object::{ClassObject, Object, ObjectType};
+use crate::sync::Rc;
 use crate::vm::InterpretResult;
 use std::collections::HashMap;
-use std::rc::Rc;
 
 /// Create a class object from a class name and methods.
 ///
@@ -29,7 +29,7 @@ pub fn op_make_class(
     }
 
     let mut methods = HashMap::new();
-    for (name, func) in method_names.into_iter().zip(method_funcs.into_iter()) {
+    for (name, func) in method_names.into_iter().zip(method_funcs) {
         methods.insert(name, func);
     }
 
@@ -37,6 +37,20 @@ pub fn op_make_class(
     Ok(Rc::new(ObjectType::Class(class)))
 }
 
+/// Describes why `op_inherit` could not derive `child` from `parent`.
+pub fn inherit_error(child: &ObjectType, parent: &ObjectType) -> (&'static str, String) {
+    match (child, parent) {
+        (_, ObjectType::Class(_)) => (
+            "SystemError",
+            format!("cannot inherit into '{}'", child.type_name()),
+        ),
+        _ => (
+            "TypeError",
+            format!("bases must be classes, not '{}'", parent.type_name()),
+        ),
+    }
+}
+
 /// Set up class inheritance by creating a new child class with a parent.
 ///
 /// # Arguments
@@ -1,11 +1,12 @@
 #![allow(dead_code)]
 
-use crate::object::{Object, ObjectType};
-use crate::vm::collections::slice_indices;
+use crate::object::{DictEntries, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::collections::{collect_iterable, not_iterable_message, slice_indices, slice_range};
+use crate::vm::hashing::{hash_value, key_position, static_hash};
 use crate::vm::InterpretResult;
-use std::rc::Rc;
 
-/// Handle OpIndex - Index into a collection (list, tuple, dict)
+/// Handle OpIndex - Index into a collection (list, tuple, string, dict)
 pub fn op_index(
     collection: Rc<ObjectType>,
     index: Rc<ObjectType>,
@@ -24,18 +25,77 @@ pub fn op_index(
             let element = values[idx_isize as usize].clone();
             Ok(element)
         }
-        (ObjectType::Dict(entries), ObjectType::String(key)) => {
-            if let Some((_, value)) = entries.iter().find(|(existing_key, _)| existing_key == key) {
-                Ok(value.clone())
-            } else {
-                Err(InterpretResult::RuntimeError)
+        (ObjectType::String(text), ObjectType::Integer(idx)) => {
+            // Strings index by character, not byte
+            let len = text.chars().count() as i64;
+            let position = if *idx < 0 { *idx + len } else { *idx };
+            if position < 0 || position >= len {
+                return Err(InterpretResult::RuntimeError);
             }
+            let ch = text
+                .chars()
+                .nth(position as usize)
+                .expect("position checked against length");
+            Ok(Rc::new(ObjectType::String(ch.to_string())))
         }
+        (ObjectType::Dict(entries), key) => match key_position(entries, key) {
+            Ok(Some(position)) => Ok(entries[position].1.clone()),
+            _ => Err(InterpretResult::RuntimeError),
+        },
         _ => Err(InterpretResult::RuntimeError),
     }
 }
 
-/// Handle OpSetIndex - Set a value in a collection (list, dict)
+/// Describes why `op_index` rejected `collection[index]`, as an exception
+/// kind and message.
+pub fn index_error(collection: &ObjectType, index: &ObjectType) -> (&'static str, String) {
+    match (collection, index) {
+        (ObjectType::List(_), ObjectType::Integer(_))
+        | (ObjectType::Tuple(_), ObjectType::Integer(_)) => (
+            "IndexError",
+            format!("{} index out of range", collection.type_name()),
+        ),
+        (ObjectType::String(_), ObjectType::Integer(_)) => {
+            ("IndexError", "string index out of range".to_string())
+        }
+        (ObjectType::Dict(_), ObjectType::String(key)) => ("KeyError", format!("'{}'", key)),
+        (ObjectType::Dict(_), key) if hash_value(key).is_err() => {
+            ("TypeError", unhashable_message(key))
+        }
+        (ObjectType::Dict(_), key) => ("KeyError", key.to_string()),
+        (ObjectType::List(_), _) | (ObjectType::Tuple(_), _) | (ObjectType::String(_), _) => (
+            "TypeError",
+            format!(
+                "{} indices must be integers or slices, not {}",
+                collection.type_name(),
+                index.type_name()
+            ),
+        ),
+        _ => (
+            "TypeError",
+            format!("'{}' object is not subscriptable", collection.type_name()),
+        ),
+    }
+}
+
+/// `dict` with its entries changed by `update`: in place when nothing else
+/// holds it, and in a copy otherwise, since dicts are values.
+pub fn update_dict(mut dict: Object, update: impl FnOnce(&mut DictEntries)) -> Object {
+    if let Some(ObjectType::Dict(entries)) = Rc::get_mut(&mut dict) {
+        update(entries);
+        return dict;
+    }
+    let ObjectType::Dict(entries) = &*dict else {
+        return dict;
+    };
+    let mut entries = entries.clone();
+    update(&mut entries);
+    Rc::new(ObjectType::Dict(entries))
+}
+
+/// Handle OpSetIndex - Set a value in a list. A negative index counts from
+/// the end, as it does for OpIndex. The VM stores into dicts itself, since
+/// finding a key may run its `__hash__` and `__eq__`.
 pub fn op_set_index(
     collection: Rc<ObjectType>,
     index: Rc<ObjectType>,
@@ -43,35 +103,54 @@ pub fn op_set_index(
 ) -> Result<Rc<ObjectType>, InterpretResult> {
     match (&*collection, &*index) {
         (ObjectType::List(elements), ObjectType::Integer(idx)) => {
-            let idx_isize = *idx as isize;
-            if idx_isize < 0 || idx_isize as usize >= elements.len() {
+            let len = elements.len() as i64;
+            let position = if *idx < 0 { *idx + len } else { *idx };
+            if position < 0 || position >= len {
                 return Err(InterpretResult::RuntimeError);
             }
             let mut new_elements = elements.clone();
-            new_elements[idx_isize as usize] = value.clone();
+            new_elements[position as usize] = value.clone();
             Ok(Rc::new(ObjectType::List(new_elements)))
         }
-        (ObjectType::Dict(entries), ObjectType::String(key)) => {
-            let mut new_entries = entries.clone();
-            if let Some(position) = new_entries
-                .iter()
-                .position(|(existing_key, _)| existing_key == key)
-            {
-                new_entries[position].1 = value.clone();
-            } else {
-                new_entries.push((key.clone(), value.clone()));
-            }
-            Ok(Rc::new(ObjectType::Dict(new_entries)))
-        }
         _ => Err(InterpretResult::RuntimeError),
     }
 }
 
+/// Describes why `op_set_index` rejected `collection[index] = value`, as an
+/// exception kind and message.
+pub fn set_index_error(collection: &ObjectType, index: &ObjectType) -> (&'static str, String) {
+    match (collection, index) {
+        (ObjectType::List(_), ObjectType::Integer(_)) => (
+            "IndexError",
+            "list assignment index out of range".to_string(),
+        ),
+        (ObjectType::List(_), _) => (
+            "TypeError",
+            format!(
+                "list indices must be integers or slices, not {}",
+                index.type_name()
+            ),
+        ),
+        _ => (
+            "TypeError",
+            format!(
+                "'{}' object does not support item assignment",
+                collection.type_name()
+            ),
+        ),
+    }
+}
+
 /// Handle OpLen - Get length of a collection
+///
+/// Strings are measured in Unicode scalar values, as in CPython, so a
+/// combining accent counts separately from the letter it modifies.
 pub fn op_len(value: Rc<ObjectType>) -> Result<i64, InterpretResult> {
     match &*value {
         ObjectType::List(values) => Ok(values.len() as i64),
         ObjectType::Tuple(values) => Ok(values.len() as i64),
+        ObjectType::Dict(entries) => Ok(entries.len() as i64),
+        ObjectType::Set(items) => Ok(items.len() as i64),
         ObjectType::String(text) => Ok(text.chars().count() as i64),
         _ => Err(InterpretResult::RuntimeError),
     }
@@ -91,6 +170,116 @@ pub fn op_append(
     }
 }
 
+/// Describes why `op_append` failed: only lists have an `append` method.
+pub fn append_error(collection: &ObjectType) -> (&'static str, String) {
+    (
+        "AttributeError",
+        format!(
+            "'{}' object has no attribute 'append'",
+            collection.type_name()
+        ),
+    )
+}
+
+/// Handle OpListExtend - Extend a list with the items of any iterable
+pub fn op_list_extend(
+    list: Rc<ObjectType>,
+    iterable: Rc<ObjectType>,
+) -> Result<Rc<ObjectType>, InterpretResult> {
+    let ObjectType::List(elements) = &*list else {
+        return Err(InterpretResult::RuntimeError);
+    };
+    let items = collect_iterable(&iterable).ok_or(InterpretResult::RuntimeError)?;
+    let mut new_elements = elements.clone();
+    new_elements.extend(items);
+    Ok(Rc::new(ObjectType::List(new_elements)))
+}
+
+/// Handle OpBuildList - Build a list from values popped off the stack
+pub fn op_build_list(elements: Vec<Object>) -> Object {
+    Rc::new(ObjectType::List(elements))
+}
+
+/// Handle OpBuildDict - Build a dict from alternating keys and values
+///
+/// A repeated key keeps its first position but takes the last value, as in
+/// Python. Keys must be hashable; otherwise the offending key is returned.
+pub fn op_build_dict(items: Vec<Object>) -> Result<Object, Object> {
+    let mut entries = DictEntries::with_capacity(items.len() / 2);
+    let mut items = items.into_iter();
+    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+        match key_position(&entries, &key) {
+            Ok(Some(position)) => entries.set_value(position, value),
+            Ok(None) => {
+                let hash = static_hash(&key);
+                entries.push(key, value, hash);
+            }
+            Err(_) => return Err(key),
+        }
+    }
+    Ok(Rc::new(ObjectType::Dict(entries)))
+}
+
+/// Handle OpUnpack - Split an iterable into the values of `count` targets
+///
+/// The target at `starred` takes a list of whatever the others leave over.
+/// On failure the error kind and message are returned.
+pub fn op_unpack(
+    sequence: &Object,
+    count: usize,
+    starred: Option<usize>,
+) -> Result<Vec<Object>, (&'static str, String)> {
+    let Some(mut items) = collect_iterable(sequence) else {
+        let message = format!("cannot unpack non-iterable {} object", sequence.type_name());
+        return Err(("TypeError", message));
+    };
+
+    let Some(starred) = starred else {
+        return match items.len() {
+            len if len < count => Err((
+                "ValueError",
+                format!(
+                    "not enough values to unpack (expected {}, got {})",
+                    count, len
+                ),
+            )),
+            len if len > count => Err((
+                "ValueError",
+                format!("too many values to unpack (expected {})", count),
+            )),
+            _ => Ok(items),
+        };
+    };
+    if starred >= count {
+        return Err(("SystemError", "starred target out of range".to_string()));
+    }
+
+    if items.len() < count - 1 {
+        let message = format!(
+            "not enough values to unpack (expected at least {}, got {})",
+            count - 1,
+            items.len()
+        );
+        return Err(("ValueError", message));
+    }
+    let after = items.split_off(items.len() - (count - 1 - starred));
+    let rest = items.split_off(starred);
+    items.push(Rc::new(ObjectType::List(rest)));
+    items.extend(after);
+    Ok(items)
+}
+
+/// Describes why `op_list_extend` could not extend `list` with `iterable`.
+pub fn list_extend_error(list: &ObjectType, iterable: &ObjectType) -> (&'static str, String) {
+    if !matches!(list, ObjectType::List(_)) {
+        return (
+            "AttributeError",
+            format!("'{}' object has no attribute 'extend'", list.type_name()),
+        );
+    }
+    ("TypeError", not_iterable_message(iterable))
+}
+
 /// Handle OpRange - Create a range of integers
 pub fn op_range(
     start: Rc<ObjectType>,
@@ -101,25 +290,63 @@ pub fn op_range(
         _ => return Err(InterpretResult::RuntimeError),
     };
 
-    let mut elements: Vec<Object> = Vec::new();
-    if start_val < end_val {
-        for value in start_val..end_val {
-            elements.push(Rc::new(ObjectType::Integer(value)));
-        }
+    let elements = range_elements(start_val, end_val, 1).ok_or(InterpretResult::RuntimeError)?;
+    Ok(Rc::new(ObjectType::List(elements)))
+}
+
+/// Describes why `op_range` could not build `range(start, end)`.
+pub fn range_error(start: &ObjectType, end: &ObjectType) -> (&'static str, String) {
+    match [start, end]
+        .into_iter()
+        .find(|bound| !matches!(bound, ObjectType::Integer(_)))
+    {
+        Some(bound) => (
+            "TypeError",
+            format!(
+                "'{}' object cannot be interpreted as an integer",
+                bound.type_name()
+            ),
+        ),
+        None => ("OverflowError", "range too large".to_string()),
     }
+}
 
-    Ok(Rc::new(ObjectType::List(elements)))
+/// Materializes `range(start, stop, step)` as a vector of integers.
+///
+/// Returns `None` instead of aborting when the range is too large to allocate.
+/// `step` must be nonzero.
+pub fn range_elements(start: i64, stop: i64, step: i64) -> Option<Vec<Object>> {
+    let span = if step > 0 {
+        stop as i128 - start as i128
+    } else {
+        start as i128 - stop as i128
+    };
+    let step_size = (step as i128).abs();
+    let len = if span > 0 {
+        (span + step_size - 1) / step_size
+    } else {
+        0
+    };
+    let mut elements: Vec<Object> = Vec::new();
+    elements
+        .try_reserve_exact(usize::try_from(len).ok()?)
+        .ok()?;
+    let mut current = start;
+    for _ in 0..len {
+        elements.push(Rc::new(ObjectType::Integer(current)));
+        current = current.wrapping_add(step);
+    }
+    Some(elements)
 }
 
-/// Handle OpContains - Check if item is in collection
+/// Handle OpContains - Check if item is in a list, tuple or string. The VM
+/// looks in dicts and sets itself, since finding a key may run its
+/// `__hash__` and `__eq__`.
 pub fn op_contains(
     item: Rc<ObjectType>,
     collection: Rc<ObjectType>,
 ) -> Result<bool, InterpretResult> {
     let result = match (&*collection, &*item) {
-        (ObjectType::Dict(entries), ObjectType::String(key)) => {
-            entries.iter().any(|(existing_key, _)| existing_key == key)
-        }
         (ObjectType::List(values), _) | (ObjectType::Tuple(values), _) => {
             values.iter().any(|element| **element == *item)
         }
@@ -130,6 +357,27 @@ pub fn op_contains(
     Ok(result)
 }
 
+/// Describes why `op_contains` rejected `item in collection`.
+pub fn contains_error(item: &ObjectType, collection: &ObjectType) -> String {
+    match collection {
+        ObjectType::String(_) => format!(
+            "'in <string>' requires string as left operand, not {}",
+            item.type_name()
+        ),
+        _ => format!(
+            "argument of type '{}' is not iterable",
+            collection.type_name()
+        ),
+    }
+}
+
+/// Python's TypeError message for using `key`, which has no hash, as a dict
+/// key.
+pub fn unhashable_message(key: &ObjectType) -> String {
+    let type_name = hash_value(key).err().unwrap_or_else(|| key.type_name());
+    format!("unhashable type: '{}'", type_name)
+}
+
 /// Handle OpSlice - Slice a collection (list or string)
 pub fn op_slice(
     collection: Rc<ObjectType>,
@@ -137,36 +385,21 @@ pub fn op_slice(
     end: Rc<ObjectType>,
     step: Rc<ObjectType>,
 ) -> Result<Rc<ObjectType>, InterpretResult> {
-    let start_idx = match &*start {
-        ObjectType::Integer(v) => Some(*v),
-        ObjectType::Nil => None,
-        _ => return Err(InterpretResult::RuntimeError),
-    };
-
-    let end_idx = match &*end {
-        ObjectType::Integer(v) => Some(*v),
-        ObjectType::Nil => None,
-        _ => return Err(InterpretResult::RuntimeError),
-    };
-
-    let step_value = match &*step {
-        ObjectType::Integer(v) => *v,
-        ObjectType::Nil => 1,
-        _ => return Err(InterpretResult::RuntimeError),
-    };
-
-    if step_value == 0 {
+    let Some((start_idx, end_idx, step_value)) = slice_bounds(&start, &end, &step) else {
         return Err(InterpretResult::RuntimeError);
-    }
+    };
 
     match &*collection {
-        ObjectType::List(values) => {
+        ObjectType::List(values) | ObjectType::Tuple(values) => {
             let indices = match slice_indices(values.len(), start_idx, end_idx, step_value) {
                 Some(idxs) => idxs,
                 None => return Err(InterpretResult::RuntimeError),
             };
             let slice: Vec<Object> = indices.into_iter().map(|idx| values[idx].clone()).collect();
-            Ok(Rc::new(ObjectType::List(slice)))
+            Ok(Rc::new(match &*collection {
+                ObjectType::Tuple(_) => ObjectType::Tuple(slice),
+                _ => ObjectType::List(slice),
+            }))
         }
         ObjectType::String(text) => {
             let chars: Vec<char> = text.chars().collect();
@@ -180,3 +413,231 @@ pub fn op_slice(
         _ => Err(InterpretResult::RuntimeError),
     }
 }
+
+/// Reads a slice's start, end and step, which must be integers or None, with
+/// a step of None meaning 1. Returns None for other bounds or a zero step.
+fn slice_bounds(
+    start: &ObjectType,
+    end: &ObjectType,
+    step: &ObjectType,
+) -> Option<(Option<i64>, Option<i64>, i64)> {
+    let bound = |value: &ObjectType| match value {
+        ObjectType::Integer(v) => Some(Some(*v)),
+        ObjectType::Nil => Some(None),
+        _ => None,
+    };
+    let step = bound(step)?.unwrap_or(1);
+    if step == 0 {
+        return None;
+    }
+    Some((bound(start)?, bound(end)?, step))
+}
+
+/// Describes why `op_slice` could not slice `collection`.
+pub fn slice_error(
+    collection: &ObjectType,
+    start: &ObjectType,
+    end: &ObjectType,
+    step: &ObjectType,
+) -> (&'static str, String) {
+    if !matches!(
+        collection,
+        ObjectType::List(_) | ObjectType::Tuple(_) | ObjectType::String(_)
+    ) {
+        return (
+            "TypeError",
+            format!("'{}' object is not subscriptable", collection.type_name()),
+        );
+    }
+    slice_bounds_error(start, end, step)
+        .unwrap_or_else(|| ("MemoryError", "slice is too large".to_string()))
+}
+
+/// Describes why a slice's bounds were rejected, or None if they are valid.
+fn slice_bounds_error(
+    start: &ObjectType,
+    end: &ObjectType,
+    step: &ObjectType,
+) -> Option<(&'static str, String)> {
+    let is_bound = |value: &ObjectType| matches!(value, ObjectType::Integer(_) | ObjectType::Nil);
+    if !(is_bound(start) && is_bound(end) && is_bound(step)) {
+        return Some((
+            "TypeError",
+            "slice indices must be integers or None or have an __index__ method".to_string(),
+        ));
+    }
+    if matches!(step, ObjectType::Integer(0)) {
+        return Some(("ValueError", "slice step cannot be zero".to_string()));
+    }
+    None
+}
+
+/// Handle OpSetSlice - Replace a slice of a list with the items of an
+/// iterable. A slice with a step of 1 may change the list's length; an
+/// extended slice must be given exactly as many items as it covers.
+pub fn op_set_slice(
+    collection: Rc<ObjectType>,
+    start: Rc<ObjectType>,
+    end: Rc<ObjectType>,
+    step: Rc<ObjectType>,
+    value: Rc<ObjectType>,
+) -> Result<Rc<ObjectType>, InterpretResult> {
+    let ObjectType::List(elements) = &*collection else {
+        return Err(InterpretResult::RuntimeError);
+    };
+    let Some((start_idx, end_idx, step_value)) = slice_bounds(&start, &end, &step) else {
+        return Err(InterpretResult::RuntimeError);
+    };
+    let Some(items) = collect_iterable(&value) else {
+        return Err(InterpretResult::RuntimeError);
+    };
+
+    let mut new_elements = elements.clone();
+    if step_value == 1 {
+        let (from, to) = slice_range(elements.len(), start_idx, end_idx);
+        new_elements.splice(from..to, items);
+    } else {
+        let indices = slice_indices(elements.len(), start_idx, end_idx, step_value)
+            .ok_or(InterpretResult::RuntimeError)?;
+        if indices.len() != items.len() {
+            return Err(InterpretResult::RuntimeError);
+        }
+        for (idx, item) in indices.into_iter().zip(items) {
+            new_elements[idx] = item;
+        }
+    }
+    Ok(Rc::new(ObjectType::List(new_elements)))
+}
+
+/// Describes why `op_set_slice` rejected `collection[start:end:step] = value`,
+/// as an exception kind and message.
+pub fn set_slice_error(
+    collection: &ObjectType,
+    start: &ObjectType,
+    end: &ObjectType,
+    step: &ObjectType,
+    value: &Object,
+) -> (&'static str, String) {
+    let ObjectType::List(elements) = collection else {
+        return (
+            "TypeError",
+            format!(
+                "'{}' object does not support item assignment",
+                collection.type_name()
+            ),
+        );
+    };
+    if let Some(error) = slice_bounds_error(start, end, step) {
+        return error;
+    }
+    let extended = !matches!(step, ObjectType::Integer(1) | ObjectType::Nil);
+    let Some(items) = collect_iterable(value) else {
+        let message = if extended {
+            "must assign iterable to extended slice"
+        } else {
+            "can only assign an iterable"
+        };
+        return ("TypeError", message.to_string());
+    };
+    let covered = slice_bounds(start, end, step)
+        .and_then(|(start, end, step)| slice_indices(elements.len(), start, end, step))
+        .map_or(0, |indices| indices.len());
+    (
+        "ValueError",
+        format!(
+            "attempt to assign sequence of size {} to extended slice of size {}",
+            items.len(),
+            covered
+        ),
+    )
+}
+
+/// Handle OpDeleteIndex - Remove an item from a list by index (counting from
+/// the end when negative). The VM removes dict keys itself, since finding a
+/// key may run its `__hash__` and `__eq__`.
+pub fn op_delete_index(
+    collection: Rc<ObjectType>,
+    index: Rc<ObjectType>,
+) -> Result<Rc<ObjectType>, InterpretResult> {
+    match (&*collection, &*index) {
+        (ObjectType::List(elements), ObjectType::Integer(idx)) => {
+            let len = elements.len() as i64;
+            let position = if *idx < 0 { *idx + len } else { *idx };
+            if position < 0 || position >= len {
+                return Err(InterpretResult::RuntimeError);
+            }
+            let mut new_elements = elements.clone();
+            new_elements.remove(position as usize);
+            Ok(Rc::new(ObjectType::List(new_elements)))
+        }
+        _ => Err(InterpretResult::RuntimeError),
+    }
+}
+
+/// Describes why `op_delete_index` rejected `del collection[index]`, as an
+/// exception kind and message.
+pub fn delete_index_error(collection: &ObjectType, index: &ObjectType) -> (&'static str, String) {
+    match (collection, index) {
+        (ObjectType::List(_), ObjectType::Integer(_)) => (
+            "IndexError",
+            "list assignment index out of range".to_string(),
+        ),
+        (ObjectType::List(_), _) | (ObjectType::Dict(_), _) => index_error(collection, index),
+        _ => (
+            "TypeError",
+            format!(
+                "'{}' object doesn't support item deletion",
+                collection.type_name()
+            ),
+        ),
+    }
+}
+
+/// Handle OpDeleteSlice - Remove the items a slice covers from a list.
+pub fn op_delete_slice(
+    collection: Rc<ObjectType>,
+    start: Rc<ObjectType>,
+    end: Rc<ObjectType>,
+    step: Rc<ObjectType>,
+) -> Result<Rc<ObjectType>, InterpretResult> {
+    let ObjectType::List(elements) = &*collection else {
+        return Err(InterpretResult::RuntimeError);
+    };
+    let Some((start_idx, end_idx, step_value)) = slice_bounds(&start, &end, &step) else {
+        return Err(InterpretResult::RuntimeError);
+    };
+    let removed = slice_indices(elements.len(), start_idx, end_idx, step_value)
+        .ok_or(InterpretResult::RuntimeError)?;
+    let new_elements = elements
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !removed.contains(idx))
+        .map(|(_, element)| element.clone())
+        .collect();
+    Ok(Rc::new(ObjectType::List(new_elements)))
+}
+
+/// Describes why `op_delete_slice` rejected `del collection[start:end:step]`,
+/// as an exception kind and message.
+pub fn delete_slice_error(
+    collection: &ObjectType,
+    start: &ObjectType,
+    end: &ObjectType,
+    step: &ObjectType,
+) -> (&'static str, String) {
+    if !matches!(collection, ObjectType::List(_)) {
+        return (
+            "TypeError",
+            format!(
+                "'{}' object doesn't support item deletion",
+                collection.type_name()
+            ),
+        );
+    }
+    slice_bounds_error(start, end, step).unwrap_or_else(|| {
+        (
+            "TypeError",
+            "slice indices must be integers or None or have an __index__ method".to_string(),
+        )
+    })
+}
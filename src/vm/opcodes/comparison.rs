@@ -1,19 +1,112 @@
 use crate::object::ObjectType;
+use crate::sync::Rc;
+use crate::vm::values;
 use crate::vm::InterpretResult;
-use std::rc::Rc;
+use std::cmp::Ordering;
 
 /// Handle OpLess - Less than comparison
-pub fn op_less(a: Rc<ObjectType>, b: Rc<ObjectType>) -> Result<bool, InterpretResult> {
-    match (&*a, &*b) {
-        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => Ok(val_a < val_b),
-        (ObjectType::Float(val_a), ObjectType::Float(val_b)) => Ok(val_a < val_b),
-        (ObjectType::Integer(val_a), ObjectType::Float(val_b)) => Ok((*val_a as f64) < *val_b),
-        (ObjectType::Float(val_a), ObjectType::Integer(val_b)) => Ok(*val_a < (*val_b as f64)),
-        _ => Err(InterpretResult::RuntimeError),
-    }
+#[inline(always)]
+pub fn op_less(a: &ObjectType, b: &ObjectType) -> Result<bool, InterpretResult> {
+    compare(a, b).map(|ordering| ordering == Some(Ordering::Less))
+}
+
+/// Handle OpLessEqual - Less than or equal comparison
+pub fn op_less_equal(a: &ObjectType, b: &ObjectType) -> Result<bool, InterpretResult> {
+    compare(a, b).map(|ordering| matches!(ordering, Some(Ordering::Less | Ordering::Equal)))
+}
+
+/// Handle OpGreater - Greater than comparison
+pub fn op_greater(a: &ObjectType, b: &ObjectType) -> Result<bool, InterpretResult> {
+    compare(a, b).map(|ordering| ordering == Some(Ordering::Greater))
+}
+
+/// Handle OpGreaterEqual - Greater than or equal comparison
+pub fn op_greater_equal(a: &ObjectType, b: &ObjectType) -> Result<bool, InterpretResult> {
+    compare(a, b).map(|ordering| matches!(ordering, Some(Ordering::Greater | Ordering::Equal)))
 }
 
 /// Handle OpEqual - Equality comparison
 pub fn op_equal(a: Rc<ObjectType>, b: Rc<ObjectType>) -> bool {
     *a == *b
 }
+
+/// Handle OpIs - Identity comparison
+///
+/// `None`, booleans and small integers are shared objects in CPython, so
+/// they are identical whenever they are equal, even when the VM holds
+/// separate copies of them.
+pub fn op_is(a: Rc<ObjectType>, b: Rc<ObjectType>) -> bool {
+    if Rc::ptr_eq(&a, &b) {
+        return true;
+    }
+    match (&*a, &*b) {
+        (ObjectType::Nil, ObjectType::Nil) => true,
+        (ObjectType::Boolean(a), ObjectType::Boolean(b)) => a == b,
+        (ObjectType::Integer(a), ObjectType::Integer(b)) => a == b && values::is_small_int(*a),
+        _ => false,
+    }
+}
+
+/// Orders two values the way Python's rich comparisons do.
+///
+/// Numbers compare by value, booleans as the integers 0 and 1, strings by
+/// code point, and lists or tuples lexicographically: the first unequal pair
+/// of elements decides, otherwise the shorter sequence is smaller. `Ok(None)` means the values are unordered
+/// (a NaN was involved); an error means the types cannot be ordered at all.
+///
+/// Two integers, as loop conditions mostly compare, are ordered inline.
+#[inline(always)]
+pub fn compare(a: &ObjectType, b: &ObjectType) -> Result<Option<Ordering>, InterpretResult> {
+    match (a, b) {
+        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => Ok(Some(val_a.cmp(val_b))),
+        _ => compare_values(a, b),
+    }
+}
+
+fn compare_values(a: &ObjectType, b: &ObjectType) -> Result<Option<Ordering>, InterpretResult> {
+    match (a, b) {
+        (ObjectType::Integer(val_a), ObjectType::Integer(val_b)) => Ok(Some(val_a.cmp(val_b))),
+        (ObjectType::Float(val_a), ObjectType::Float(val_b)) => Ok(val_a.partial_cmp(val_b)),
+        (ObjectType::Integer(val_a), ObjectType::Float(val_b)) => {
+            Ok((*val_a as f64).partial_cmp(val_b))
+        }
+        (ObjectType::Float(val_a), ObjectType::Integer(val_b)) => {
+            Ok(val_a.partial_cmp(&(*val_b as f64)))
+        }
+        (ObjectType::Boolean(flag), _) => compare(&ObjectType::Integer(*flag as i64), b),
+        (_, ObjectType::Boolean(flag)) => compare(a, &ObjectType::Integer(*flag as i64)),
+        (ObjectType::String(val_a), ObjectType::String(val_b)) => Ok(Some(val_a.cmp(val_b))),
+        (ObjectType::List(items_a), ObjectType::List(items_b))
+        | (ObjectType::Tuple(items_a), ObjectType::Tuple(items_b)) => {
+            for (item_a, item_b) in items_a.iter().zip(items_b.iter()) {
+                if item_a != item_b {
+                    return compare(item_a, item_b);
+                }
+            }
+            Ok(Some(items_a.len().cmp(&items_b.len())))
+        }
+        _ => Err(InterpretResult::RuntimeError),
+    }
+}
+
+/// Finds the pair of values that made `compare` fail, descending into
+/// sequences, and describes it with Python's TypeError message.
+pub fn comparison_error(operator: &str, a: &ObjectType, b: &ObjectType) -> String {
+    if let (ObjectType::List(items_a), ObjectType::List(items_b))
+    | (ObjectType::Tuple(items_a), ObjectType::Tuple(items_b)) = (a, b)
+    {
+        if let Some((item_a, item_b)) = items_a
+            .iter()
+            .zip(items_b.iter())
+            .find(|(item_a, item_b)| item_a != item_b)
+        {
+            return comparison_error(operator, item_a, item_b);
+        }
+    }
+    format!(
+        "'{}' not supported between instances of '{}' and '{}'",
+        operator,
+        a.type_name(),
+        b.type_name()
+    )
+}
@@ -7,13 +7,16 @@
 //! - Iteration: `OpIterNext`
 
 use crate::object::{Object, ObjectType};
+use crate::sync::Rc;
 use crate::vm::call_frame::CallFrame;
+use crate::vm::collections::not_iterable_message;
+use crate::vm::values::integer;
 use crate::vm::InterpretResult;
-use std::rc::Rc;
 
 /// Handler for OpJump - unconditional forward jump.
 ///
 /// Advances the instruction pointer by the given offset.
+#[inline(always)]
 pub fn op_jump(offset: usize, frames: &mut [CallFrame]) {
     if let Some(frame) = frames.last_mut() {
         frame.ip += offset;
@@ -24,6 +27,7 @@ pub fn op_jump(offset: usize, frames: &mut [CallFrame]) {
 ///
 /// If the value on top of the stack is falsey, advances the instruction pointer
 /// by the given offset. Does not pop the condition value.
+#[inline(always)]
 pub fn op_jump_if_false(offset: usize, condition: &Object, frames: &mut [CallFrame]) {
     if !crate::vm::values::is_truthy(condition) {
         if let Some(frame) = frames.last_mut() {
@@ -35,15 +39,34 @@ pub fn op_jump_if_false(offset: usize, condition: &Object, frames: &mut [CallFra
 /// Handler for OpLoop - backward jump for loops.
 ///
 /// Moves the instruction pointer backward by the given offset.
-pub fn op_loop(offset: usize, frames: &mut [CallFrame]) {
+/// Returns RuntimeError if that would move it before the start of the code.
+pub fn op_loop(offset: usize, frames: &mut [CallFrame]) -> Result<(), InterpretResult> {
     if let Some(frame) = frames.last_mut() {
-        frame.ip -= offset;
+        frame.ip = frame
+            .ip
+            .checked_sub(offset)
+            .ok_or(InterpretResult::RuntimeError)?;
+    }
+    Ok(())
+}
+
+/// Describes why `op_iter_next` could not step through `collection`.
+pub fn iter_next_error(collection: &ObjectType) -> (&'static str, String) {
+    match collection {
+        ObjectType::List(_)
+        | ObjectType::Tuple(_)
+        | ObjectType::Set(_)
+        | ObjectType::Dict(_)
+        | ObjectType::String(_)
+        | ObjectType::Iterator(_) => ("SystemError", "bad iteration index".to_string()),
+        _ => ("TypeError", not_iterable_message(collection)),
     }
 }
 
 /// Handler for OpIterNext - iteration step for for-loops.
 ///
-/// Manages iteration over collections (lists, tuples, strings).
+/// Manages iteration over collections (lists, tuples, sets, dict keys,
+/// strings) and iterator objects.
 /// Stack layout: [collection, index]
 /// Returns: [collection, next_index, element] or jumps past loop body if done
 pub fn op_iter_next(
@@ -54,7 +77,8 @@ pub fn op_iter_next(
 ) -> Result<Option<(Object, Object, Object)>, InterpretResult> {
     match (&*collection, &*index) {
         (ObjectType::List(values), ObjectType::Integer(idx))
-        | (ObjectType::Tuple(values), ObjectType::Integer(idx)) => {
+        | (ObjectType::Tuple(values), ObjectType::Integer(idx))
+        | (ObjectType::Set(values), ObjectType::Integer(idx)) => {
             if *idx < 0 {
                 return Err(InterpretResult::RuntimeError);
             }
@@ -68,32 +92,49 @@ pub fn op_iter_next(
             } else {
                 let element = values[idx_usize].clone();
                 let next_index = (idx_usize + 1) as i64;
-                Ok(Some((
-                    collection,
-                    Rc::new(ObjectType::Integer(next_index)),
-                    element,
-                )))
+                Ok(Some((collection, integer(next_index), element)))
             }
         }
+        (ObjectType::Dict(entries), ObjectType::Integer(idx)) => {
+            let Some((key, _)) = usize::try_from(*idx).ok().and_then(|idx| entries.get(idx)) else {
+                if let Some(frame) = frames.last_mut() {
+                    frame.ip += offset;
+                }
+                return Ok(None);
+            };
+            let key = key.clone();
+            Ok(Some((collection, integer(idx + 1), key)))
+        }
         (ObjectType::String(text), ObjectType::Integer(idx)) => {
             if *idx < 0 {
                 return Err(InterpretResult::RuntimeError);
             }
-            let chars: Vec<char> = text.chars().collect();
+            // The index is a byte offset, so each step decodes one Unicode
+            // scalar where the last one ended instead of counting from the start
             let idx_usize = *idx as usize;
-            if idx_usize >= chars.len() {
+            let Some(ch) = text.get(idx_usize..).and_then(|rest| rest.chars().next()) else {
                 if let Some(frame) = frames.last_mut() {
                     frame.ip += offset;
                 }
-                Ok(None)
-            } else {
-                let ch = chars[idx_usize];
-                let next_index = (idx_usize + 1) as i64;
-                Ok(Some((
-                    collection,
-                    Rc::new(ObjectType::Integer(next_index)),
-                    Rc::new(ObjectType::String(ch.to_string())),
-                )))
+                return Ok(None);
+            };
+            let next_index = (idx_usize + ch.len_utf8()) as i64;
+            Ok(Some((
+                collection,
+                integer(next_index),
+                Rc::new(ObjectType::String(ch.to_string())),
+            )))
+        }
+        (ObjectType::Iterator(iterator), _) => {
+            let next = iterator.borrow_mut().next_item();
+            match next {
+                Some(element) => Ok(Some((collection.clone(), index, element))),
+                None => {
+                    if let Some(frame) = frames.last_mut() {
+                        frame.ip += offset;
+                    }
+                    Ok(None)
+                }
             }
         }
         _ => Err(InterpretResult::RuntimeError),
@@ -7,10 +7,9 @@
 // management and remain in the main VM run() loop.
 
 use crate::object::{FunctionObject, FunctionPrototype, Object, ObjectType, UpvalueRef};
+use crate::sync::Rc;
 use crate::vm::upvalues;
 use crate::vm::InterpretResult;
-use std::collections::HashMap;
-use std::rc::Rc;
 
 /// Create a function object from a function prototype.
 /// Captures upvalues from the current execution context.
@@ -19,7 +18,6 @@ use std::rc::Rc;
 /// * `proto` - The function prototype containing bytecode and metadata
 /// * `frame_slot` - The current call frame's base stack slot
 /// * `parent_upvalues` - Upvalues from the parent function (for nested closures)
-/// * `globals` - The global namespace to capture
 /// * `open_upvalues` - Mutable reference to the VM's open upvalues list
 ///
 /// # Returns
@@ -29,7 +27,6 @@ pub fn op_make_function(
     proto: Rc<FunctionPrototype>,
     frame_slot: usize,
     parent_upvalues: &[UpvalueRef],
-    globals: HashMap<String, Object>,
     open_upvalues: &mut Vec<UpvalueRef>,
 ) -> Result<Object, InterpretResult> {
     let mut captured: Vec<UpvalueRef> = Vec::with_capacity(proto.upvalues.len());
@@ -66,7 +63,7 @@ pub fn op_make_function(
 
     function.doc = proto.doc.clone();
     function.qualname = proto.qualname.clone();
-    function.globals = globals;
+    function.local_count = proto.local_count;
 
     Ok(Rc::new(ObjectType::Function(Rc::new(function))))
 }
@@ -6,6 +6,7 @@
 //! - `OpPrintln`: Print a newline
 
 use crate::object::Object;
+use crate::platform::Output;
 use crate::vm::call_frame::CallFrame;
 use crate::vm::stack_ops::Stack;
 use crate::vm::string_repr;
@@ -14,13 +15,18 @@ use crate::vm::string_repr;
 ///
 /// Pops a value from the stack and prints it with a space after it.
 /// Uses the __str__ method if available on instances.
-pub fn op_print_spaced(value: Object, stack: &mut Stack, frames: &mut Vec<CallFrame>) {
+pub fn op_print_spaced(
+    value: Object,
+    stack: &mut Stack,
+    frames: &mut Vec<CallFrame>,
+    output: &mut Output,
+) {
     let string_repr = string_repr::get_string_representation(value.clone(), stack, frames);
     if let Some(repr) = string_repr {
-        print!("{} ", repr);
+        output.write(&format!("{} ", repr));
     } else {
         // Fallback to default Display implementation
-        print!("{} ", value);
+        output.write(&format!("{} ", value));
     }
 }
 
@@ -28,17 +34,22 @@ pub fn op_print_spaced(value: Object, stack: &mut Stack, frames: &mut Vec<CallFr
 ///
 /// Pops a value from the stack and prints it.
 /// Uses the __str__ method if available on instances.
-pub fn op_print(value: Object, stack: &mut Stack, frames: &mut Vec<CallFrame>) {
+pub fn op_print(
+    value: Object,
+    stack: &mut Stack,
+    frames: &mut Vec<CallFrame>,
+    output: &mut Output,
+) {
     let string_repr = string_repr::get_string_representation(value.clone(), stack, frames);
     if let Some(repr) = string_repr {
-        print!("{}", repr);
+        output.write(&repr);
     } else {
         // Fallback to default Display implementation
-        print!("{}", value);
+        output.write(&value.to_string());
     }
 }
 
 /// Handler for OpPrintln - prints a newline character.
-pub fn op_println() {
-    println!();
+pub fn op_println(output: &mut Output) {
+    output.write("\n");
 }
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 
-use crate::object::ObjectType;
+use crate::object::{Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::collections::collect_iterable;
 use crate::vm::InterpretResult;
-use std::rc::Rc;
 
 /// Handle OpStrLower - Convert string to lowercase
 pub fn op_str_lower(value: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
@@ -12,6 +13,14 @@ pub fn op_str_lower(value: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretRe
     }
 }
 
+/// Handle OpStrUpper - Convert string to uppercase
+pub fn op_str_upper(value: Rc<ObjectType>) -> Result<Rc<ObjectType>, InterpretResult> {
+    match &*value {
+        ObjectType::String(text) => Ok(Rc::new(ObjectType::String(text.to_uppercase()))),
+        _ => Err(InterpretResult::RuntimeError),
+    }
+}
+
 /// Handle OpStrIsAlnum - Check if string is alphanumeric
 pub fn op_str_is_alnum(value: Rc<ObjectType>) -> Result<bool, InterpretResult> {
     match &*value {
@@ -23,28 +32,60 @@ pub fn op_str_is_alnum(value: Rc<ObjectType>) -> Result<bool, InterpretResult> {
     }
 }
 
-/// Handle OpStrJoin - Join strings with separator
+/// Describes why the string method `method` could not run on `value`,
+/// which is not a string.
+pub fn string_method_error(value: &ObjectType, method: &str) -> (&'static str, String) {
+    (
+        "AttributeError",
+        format!(
+            "'{}' object has no attribute '{}'",
+            value.type_name(),
+            method
+        ),
+    )
+}
+
+/// Handle OpStrJoin - Join the strings of any iterable with a separator;
+/// a dict contributes its keys
 pub fn op_str_join(
     separator: Rc<ObjectType>,
     iterable: Rc<ObjectType>,
 ) -> Result<Rc<ObjectType>, InterpretResult> {
-    match (&*separator, &*iterable) {
-        (ObjectType::String(sep), ObjectType::List(values)) => {
-            let mut parts = Vec::with_capacity(values.len());
-            for value in values {
-                match &**value {
-                    ObjectType::String(text) => parts.push(text.clone()),
-                    _ => return Err(InterpretResult::RuntimeError),
-                }
-            }
-            let joined = parts.join(sep);
-            Ok(Rc::new(ObjectType::String(joined)))
+    let ObjectType::String(sep) = &*separator else {
+        return Err(InterpretResult::RuntimeError);
+    };
+    let values = collect_iterable(&iterable).ok_or(InterpretResult::RuntimeError)?;
+    let mut parts = Vec::with_capacity(values.len());
+    for value in &values {
+        match &**value {
+            ObjectType::String(text) => parts.push(text.as_str()),
+            _ => return Err(InterpretResult::RuntimeError),
         }
-        (ObjectType::String(sep), ObjectType::String(text)) => {
-            let chars: Vec<String> = text.chars().map(|ch| ch.to_string()).collect();
-            let joined = chars.join(sep);
-            Ok(Rc::new(ObjectType::String(joined)))
+    }
+    Ok(Rc::new(ObjectType::String(parts.join(sep))))
+}
+
+/// Describes why `op_str_join` could not join `iterable` with `separator`.
+pub fn join_error(separator: &ObjectType, iterable: &Object) -> (&'static str, String) {
+    if !matches!(separator, ObjectType::String(_)) {
+        return string_method_error(separator, "join");
+    }
+    match collect_iterable(iterable) {
+        Some(values) => {
+            let (position, item) = values
+                .iter()
+                .enumerate()
+                .find(|(_, value)| !matches!(***value, ObjectType::String(_)))
+                .map(|(position, value)| (position, value.type_name()))
+                .unwrap_or((0, "str".to_string()));
+            (
+                "TypeError",
+                format!(
+                    "sequence item {}: expected str instance, {} found",
+                    position, item
+                ),
+            )
         }
-        _ => Err(InterpretResult::RuntimeError),
+        None => ("TypeError", "can only join an iterable".to_string()),
     }
 }
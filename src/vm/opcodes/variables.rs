@@ -1,38 +1,53 @@
 //! Variable access operation handlers for the VM.
 //!
 //! This module provides handlers for variable-related opcodes:
-//! - Global variables: `OpDefineGlobal`, `OpGetGlobal`, `OpSetGlobal`
+//! - Global variables: `OpDefineGlobal`, `OpGetGlobal`, `OpSetGlobal`, `OpDeleteGlobal`
 //! - Local variables: `OpGetLocal`, `OpSetLocal`
 //! - Upvalues (closures): `OpGetUpvalue`, `OpSetUpvalue`
 
 use crate::object::Object;
 use crate::vm::call_frame::CallFrame;
+use crate::vm::namespace::Namespace;
 use crate::vm::stack_ops::Stack;
 use crate::vm::InterpretResult;
-use std::collections::HashMap;
 
 /// Handler for OpDefineGlobal - defines a new global variable.
 ///
 /// Reads a constant name from the bytecode and creates a new global variable
 /// with the value on top of the stack.
-pub fn op_define_global(name: String, value: Object, globals: &mut HashMap<String, Object>) {
+pub fn op_define_global(name: String, value: Object, globals: &mut Namespace) {
     globals.insert(name, value);
 }
 
 /// Handler for OpGetGlobal - gets the value of a global variable.
 ///
 /// Reads a constant name from the bytecode and pushes the global variable's value
-/// onto the stack. Returns RuntimeError if the variable doesn't exist.
+/// onto the stack. Globals shadow builtins, so the builtins table is only consulted
+/// when no global of that name exists. Returns RuntimeError if neither has the name.
+#[inline(always)]
 pub fn op_get_global(
     name: &str,
-    globals: &HashMap<String, Object>,
+    globals: &Namespace,
+    builtins: &Namespace,
 ) -> Result<Object, InterpretResult> {
     globals
         .get(name)
+        .or_else(|| builtins.get(name))
         .cloned()
         .ok_or(InterpretResult::RuntimeError)
 }
 
+/// Handler for OpDeleteGlobal - removes a global variable binding.
+///
+/// Returns RuntimeError if the variable doesn't exist. Builtins cannot be deleted,
+/// but deleting a global that shadows one makes the builtin visible again.
+pub fn op_delete_global(name: &str, globals: &mut Namespace) -> Result<(), InterpretResult> {
+    globals
+        .remove(name)
+        .map(|_| ())
+        .ok_or(InterpretResult::RuntimeError)
+}
+
 /// Handler for OpSetGlobal - sets the value of an existing global variable.
 ///
 /// Updates an existing global variable with the value on top of the stack.
@@ -40,7 +55,7 @@ pub fn op_get_global(
 pub fn op_set_global(
     name: &str,
     value: Object,
-    globals: &mut HashMap<String, Object>,
+    globals: &mut Namespace,
 ) -> Result<(), InterpretResult> {
     if globals.contains_key(name) {
         globals.insert(name.to_string(), value);
@@ -54,6 +69,7 @@ pub fn op_set_global(
 ///
 /// Reads a slot index from the bytecode and pushes the local variable's value
 /// from the current frame's stack window.
+#[inline(always)]
 pub fn op_get_local(
     slot: usize,
     stack: &Stack,
@@ -0,0 +1,209 @@
+//! The `__len__`, `__bool__`, `__eq__`, `__contains__`, `__iter__` and
+//! `__next__` protocol methods.
+//!
+//! `len()`, truthiness, `==`, `in`, `iter()` and `next()` answer builtin values
+//! directly, but an instance may define them in bytecode, so they are run
+//! here through `call_callback`. As in CPython, truthiness tries `__bool__`,
+//! then `__len__`, and an instance whose class defines neither is true. A
+//! `__next__` method ends the iteration by raising StopIteration, which
+//! `next()` turns into its default when given one.
+
+use super::generators::generator_of;
+use crate::object::{Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::native::{native_iter, native_len, native_next};
+use crate::vm::opcodes::collections::{contains_error, op_contains, op_len};
+use crate::vm::opcodes::comparison::op_equal;
+use crate::vm::values::{self, is_truthy};
+use crate::vm::{InterpretResult, VM};
+
+impl VM {
+    /// `len(value)`, raising TypeError for a value without a length.
+    pub(super) fn length(&mut self, value: &Object) -> Result<i64, InterpretResult> {
+        let Some(method) = special_method(value, "__len__") else {
+            return op_len(value.clone()).map_err(|_| {
+                self.raise_error(
+                    "TypeError",
+                    format!("object of type '{}' has no len()", value.type_name()),
+                )
+            });
+        };
+        let result = self.call_callback(method, Vec::new())?;
+        match &*result {
+            ObjectType::Integer(len) if *len >= 0 => Ok(*len),
+            ObjectType::Integer(_) => {
+                Err(self.raise_error("ValueError", "__len__() should return >= 0"))
+            }
+            ObjectType::Boolean(flag) => Ok(*flag as i64),
+            other => Err(self.raise_error(
+                "TypeError",
+                format!(
+                    "'{}' object cannot be interpreted as an integer",
+                    other.type_name()
+                ),
+            )),
+        }
+    }
+
+    /// Whether `value` is true in a condition or under `not`.
+    pub(super) fn truthiness(&mut self, value: &Object) -> Result<bool, InterpretResult> {
+        if !matches!(**value, ObjectType::Instance(_)) {
+            return Ok(is_truthy(value));
+        }
+        if let Some(method) = special_method(value, "__bool__") {
+            let result = self.call_callback(method, Vec::new())?;
+            return match &*result {
+                ObjectType::Boolean(flag) => Ok(*flag),
+                other => Err(self.raise_error(
+                    "TypeError",
+                    format!(
+                        "__bool__ should return bool, returned {}",
+                        other.type_name()
+                    ),
+                )),
+            };
+        }
+        if special_method(value, "__len__").is_some() {
+            return Ok(self.length(value)? != 0);
+        }
+        Ok(true)
+    }
+
+    /// `a == b`, running the `__eq__` of an instance `a`.
+    pub(super) fn equals(&mut self, a: &Object, b: &Object) -> Result<bool, InterpretResult> {
+        let Some(method) = special_method(a, "__eq__") else {
+            return Ok(op_equal(a.clone(), b.clone()));
+        };
+        let result = self.call_callback(method, vec![b.clone()])?;
+        Ok(is_truthy(&result))
+    }
+
+    /// `item in collection`, raising TypeError when `collection` cannot
+    /// contain `item`. Dict keys and set items are found by `find_key`.
+    pub(super) fn contains(
+        &mut self,
+        item: &Object,
+        collection: &Object,
+    ) -> Result<bool, InterpretResult> {
+        if let Some(method) = special_method(collection, "__contains__") {
+            let result = self.call_callback(method, vec![item.clone()])?;
+            return self.truthiness(&result);
+        }
+        match &**collection {
+            ObjectType::Dict(entries) => return Ok(self.find_key(entries, item)?.is_some()),
+            ObjectType::Set(items) => return Ok(self.find_item(items, item)?.is_some()),
+            _ => {}
+        }
+        op_contains(item.clone(), collection.clone())
+            .map_err(|_| self.raise_error("TypeError", contains_error(item, collection)))
+    }
+
+    /// `next(iterator[, default])` for a generator or an instance defining
+    /// `__next__`. Without a default, the StopIteration ending the iteration
+    /// reaches the caller.
+    fn next_item(
+        &mut self,
+        iterator: &Object,
+        default: Option<&Object>,
+    ) -> Result<Object, InterpretResult> {
+        if let Some(generator) = generator_of(iterator) {
+            return match self.resume_generator(&generator)? {
+                Some(item) => Ok(item),
+                None => default
+                    .cloned()
+                    .ok_or_else(|| self.raise_error("StopIteration", "")),
+            };
+        }
+        let Some(method) = special_method(iterator, "__next__") else {
+            return Err(self.raise_error(
+                "TypeError",
+                format!("'{}' object is not an iterator", iterator.type_name()),
+            ));
+        };
+        let (frames, stack_depth) = (self.frames.len(), self.stack.top());
+        match self.call_callback(method, Vec::new()) {
+            Ok(item) => Ok(item),
+            Err(error) => match default {
+                Some(default) if self.catch_stop_iteration(frames, stack_depth) => {
+                    Ok(default.clone())
+                }
+                _ => Err(error),
+            },
+        }
+    }
+
+    /// Runs an instance's `__iter__`, which must return an iterator.
+    fn instance_iterator(&mut self, method: Object) -> Result<Object, InterpretResult> {
+        let iterator = self.call_callback(method, Vec::new())?;
+        let is_iterator = matches!(*iterator, ObjectType::Iterator(_))
+            || special_method(&iterator, "__next__").is_some();
+        if !is_iterator {
+            return Err(self.raise_error(
+                "TypeError",
+                format!(
+                    "iter() returned non-iterator of type '{}'",
+                    iterator.type_name()
+                ),
+            ));
+        }
+        Ok(iterator)
+    }
+}
+
+/// `len()`, running an instance's `__len__`.
+pub(in crate::vm) fn len(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("len", keywords)?;
+    let [value] = args else {
+        return vm.native_result(native_len(args, None));
+    };
+    vm.length(value).map(values::integer)
+}
+
+/// `iter()`, running an instance's `__iter__`.
+pub(in crate::vm) fn iter(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("iter", keywords)?;
+    if let [value] = args {
+        if let Some(method) = special_method(value, "__iter__") {
+            return vm.instance_iterator(method);
+        }
+    }
+    vm.native_result(native_iter(args, None))
+}
+
+/// `next()`, resuming a generator or running an instance's `__next__`.
+pub(in crate::vm) fn next(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("next", keywords)?;
+    match args {
+        [iterator, default @ ..] if default.len() <= 1 && runs_bytecode(iterator) => {
+            vm.next_item(iterator, default.first())
+        }
+        _ => vm.native_result(native_next(args, None)),
+    }
+}
+
+/// Whether stepping `value` runs bytecode: it is a generator or an instance.
+fn runs_bytecode(value: &Object) -> bool {
+    generator_of(value).is_some() || matches!(**value, ObjectType::Instance(_))
+}
+
+/// The method `name` bound to `value`, if `value` is an instance whose class
+/// defines it.
+fn special_method(value: &Object, name: &str) -> Option<Object> {
+    let ObjectType::Instance(instance) = &**value else {
+        return None;
+    };
+    let method = instance.borrow().class.get_method(name)?;
+    Some(Rc::new(ObjectType::BoundMethod(value.clone(), method)))
+}
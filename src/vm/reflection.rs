@@ -0,0 +1,123 @@
+//! The `getattr()`, `setattr()` and `hasattr()` builtins.
+//!
+//! They look attributes up exactly as `obj.name` does, which can read open
+//! upvalues off the VM stack (a function's `__closure__`), so `getattr` and
+//! `hasattr` are natives that run on the VM.
+
+use crate::object::{ClassObject, NativeError, NativeResult, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::opcodes::attributes::{attr_error, op_get_attr, op_set_attr, set_attr_error};
+use crate::vm::{values, InterpretResult, VM};
+
+/// `getattr(object, name[, default])`.
+pub(in crate::vm) fn getattr(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("getattr", keywords)?;
+    let result = vm.getattr(args);
+    vm.native_result(result)
+}
+
+/// `hasattr(object, name)`.
+pub(in crate::vm) fn hasattr(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.no_keywords("hasattr", keywords)?;
+    let result = vm.hasattr(args);
+    vm.native_result(result)
+}
+
+impl VM {
+    /// A function's `__globals__`: the globals of the module it was defined
+    /// in as they are now, or None if `object` is not a function or `name`
+    /// is some other attribute. Functions keep only their module's name, so
+    /// making one never copies the namespace.
+    pub(in crate::vm) fn function_globals(&self, object: &Object, name: &str) -> Option<Object> {
+        let ObjectType::Function(function) = &**object else {
+            return None;
+        };
+        if name != "__globals__" {
+            return None;
+        }
+        let globals = self
+            .module_namespace(&function.module)
+            .into_iter()
+            .flat_map(|globals| globals.iter())
+            .map(|(name, value)| (name.clone(), value.clone()));
+        Some(Rc::new(ObjectType::dict_of_names(globals)))
+    }
+
+    /// Looks up the attribute `getattr(object, name[, default])` names.
+    fn getattr(&self, args: &[Object]) -> NativeResult {
+        let (object, name, default) = match args {
+            [object, name] => (object, name, None),
+            [object, name, default] => (object, name, Some(default)),
+            [] | [_] => {
+                return Err(NativeError::type_error(format!(
+                    "getattr expected at least 2 arguments, got {}",
+                    args.len()
+                )))
+            }
+            _ => {
+                return Err(NativeError::type_error(format!(
+                    "getattr expected at most 3 arguments, got {}",
+                    args.len()
+                )))
+            }
+        };
+        let name = attribute_name(name)?;
+        if let Some(globals) = self.function_globals(object, name) {
+            return Ok(globals);
+        }
+        match (op_get_attr(object.clone(), name, &self.stack), default) {
+            (Ok(value), _) => Ok(value),
+            (Err(_), Some(default)) => Ok(default.clone()),
+            (Err(_), None) => Err(NativeError::new("AttributeError", attr_error(object, name))),
+        }
+    }
+
+    /// Whether the attribute `hasattr(object, name)` names is found.
+    fn hasattr(&self, args: &[Object]) -> NativeResult {
+        let [object, name] = args else {
+            return Err(NativeError::type_error(format!(
+                "hasattr expected 2 arguments, got {}",
+                args.len()
+            )));
+        };
+        let name = attribute_name(name)?;
+        let found = self.function_globals(object, name).is_some()
+            || op_get_attr(object.clone(), name, &self.stack).is_ok();
+        Ok(values::boolean(found))
+    }
+}
+
+/// `setattr(object, name, value)`.
+pub fn native_setattr(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let [object, name, value] = args else {
+        return Err(NativeError::type_error(format!(
+            "setattr expected 3 arguments, got {}",
+            args.len()
+        )));
+    };
+    let name = attribute_name(name)?;
+    if op_set_attr(object.clone(), name.to_string(), value.clone()).is_err() {
+        let (kind, message) = set_attr_error(object, name);
+        return Err(NativeError::new(kind, message));
+    }
+    Ok(values::none())
+}
+
+/// The attribute name passed to one of these builtins, which must be a string.
+fn attribute_name(name: &Object) -> Result<&str, NativeError> {
+    match &**name {
+        ObjectType::String(name) => Ok(name),
+        other => Err(NativeError::type_error(format!(
+            "attribute name must be string, not '{}'",
+            other.type_name()
+        ))),
+    }
+}
@@ -0,0 +1,63 @@
+//! Runtime error reporting for the VM.
+//!
+//! When execution fails the VM records a `RuntimeError` describing what went
+//! wrong (a Python-style exception name plus message) and the call stack at the
+//! point of failure, so callers such as the CLI can print a traceback.
+
+use std::fmt;
+
+/// A single entry of a runtime traceback, outermost call first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    /// Module the function was defined in (e.g. `__main__`)
+    pub module: String,
+    /// File the module was read from, or its name if it has none
+    pub file: String,
+    /// Qualified function name, or `<module>` for top-level code
+    pub function: String,
+    /// Source line the frame was running, if the bytecode records one
+    pub line: Option<usize>,
+}
+
+/// A runtime failure captured by the VM.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    /// Python exception class name, e.g. `TypeError`
+    pub kind: String,
+    /// Human readable description of the failure
+    pub message: String,
+    /// Active call frames when the error was raised, outermost first
+    pub traceback: Vec<TraceFrame>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: &str, message: impl Into<String>) -> Self {
+        RuntimeError {
+            kind: kind.to_string(),
+            message: message.into(),
+            traceback: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.traceback.is_empty() {
+            writeln!(f, "Traceback (most recent call last):")?;
+            for frame in &self.traceback {
+                match frame.line {
+                    Some(line) => writeln!(
+                        f,
+                        "  File \"{}\", line {}, in {}",
+                        frame.file, line, frame.function
+                    )?,
+                    None => writeln!(f, "  File \"{}\", in {}", frame.file, frame.function)?,
+                }
+            }
+        }
+        if self.message.is_empty() {
+            return write!(f, "{}", self.kind);
+        }
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
@@ -0,0 +1,158 @@
+//! `sorted()` and `list.sort()` with `key=` and `reverse=`, and `min()` and
+//! `max()` with `key=` and `default=`.
+//!
+//! A key function may be bytecode, so these are natives that run on the VM,
+//! which calls it through `call_callback` before handing the keys to the
+//! shared stable sort.
+
+use crate::object::{Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::collections::{collect_iterable, extremum, not_iterable_message, sort_by_keys};
+use crate::vm::native::extremum_items;
+use crate::vm::values::is_truthy;
+use crate::vm::{InterpretResult, VM};
+
+/// `sorted(iterable, key=None, reverse=False)`.
+pub(in crate::vm) fn sorted(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    let [iterable] = args else {
+        let message = format!("sorted expected 1 argument, got {}", args.len());
+        return Err(vm.raise_error("TypeError", message));
+    };
+
+    let mut key = None;
+    let mut reverse = false;
+    for (name, value) in keywords {
+        match name.as_str() {
+            "key" => key = Some(value.clone()),
+            "reverse" => reverse = is_truthy(value),
+            other => {
+                let message = format!("sort() got an unexpected keyword argument '{}'", other);
+                return Err(vm.raise_error("TypeError", message));
+            }
+        }
+    }
+
+    let Some(items) = collect_iterable(iterable) else {
+        return Err(vm.raise_error("TypeError", not_iterable_message(iterable)));
+    };
+    let sorted = vm.sort_values(items, key, reverse)?;
+    Ok(Rc::new(ObjectType::List(sorted)))
+}
+
+/// `min(iterable, *, key=None, default=...)` or `min(a, b, *args, key=None)`.
+pub(in crate::vm) fn min(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.extremum("min", args, keywords)
+}
+
+/// `max(iterable, *, key=None, default=...)` or `max(a, b, *args, key=None)`.
+pub(in crate::vm) fn max(
+    vm: &mut VM,
+    args: &[Object],
+    keywords: &[(String, Object)],
+) -> Result<Object, InterpretResult> {
+    vm.extremum("max", args, keywords)
+}
+
+impl VM {
+    /// Runs `min()` or `max()`, as `name` says, with the `key=` and
+    /// `default=` among `keywords`.
+    fn extremum(
+        &mut self,
+        name: &str,
+        args: &[Object],
+        keywords: &[(String, Object)],
+    ) -> Result<Object, InterpretResult> {
+        let mut key = None;
+        let mut default = None;
+        for (keyword, value) in keywords {
+            match keyword.as_str() {
+                "key" => key = Some(value.clone()),
+                "default" => default = Some(value.clone()),
+                other => {
+                    let message =
+                        format!("{}() got an unexpected keyword argument '{}'", name, other);
+                    return Err(self.raise_error("TypeError", message));
+                }
+            }
+        }
+        if default.is_some() && args.len() > 1 {
+            let message = format!(
+                "Cannot specify a default for {}() with multiple positional arguments",
+                name
+            );
+            return Err(self.raise_error("TypeError", message));
+        }
+
+        let items = match extremum_items(name, args) {
+            Ok(items) => items,
+            Err(error) => return Err(self.raise_error(error.kind, error.message)),
+        };
+        let keys = self.item_keys(&items, key)?;
+        let chosen = match extremum(items, keys, name == "max") {
+            Ok(chosen) => chosen.or(default),
+            Err(message) => return Err(self.raise_error("TypeError", message)),
+        };
+        chosen.ok_or_else(|| {
+            self.raise_error(
+                "ValueError",
+                format!("{}() iterable argument is empty", name),
+            )
+        })
+    }
+
+    /// Handle OpListSort - sort a list by an optional key function
+    pub(super) fn op_list_sort(
+        &mut self,
+        list: Object,
+        key: Object,
+        reverse: Object,
+    ) -> Result<Object, InterpretResult> {
+        let ObjectType::List(items) = &*list else {
+            return Err(self.raise_error(
+                "AttributeError",
+                format!("'{}' object has no attribute 'sort'", list.type_name()),
+            ));
+        };
+        let sorted = self.sort_values(items.clone(), Some(key), is_truthy(&reverse))?;
+        Ok(Rc::new(ObjectType::List(sorted)))
+    }
+
+    /// Computes each item's key (calling `key` if given) and stable-sorts the
+    /// items by them, raising TypeError for unorderable keys.
+    fn sort_values(
+        &mut self,
+        items: Vec<Object>,
+        key: Option<Object>,
+        reverse: bool,
+    ) -> Result<Vec<Object>, InterpretResult> {
+        let keys = self.item_keys(&items, key)?;
+        sort_by_keys(items, keys, reverse).map_err(|message| self.raise_error("TypeError", message))
+    }
+
+    /// The key of each item: the result of calling `key` on it, or the item
+    /// itself when `key` is missing or None.
+    fn item_keys(
+        &mut self,
+        items: &[Object],
+        key: Option<Object>,
+    ) -> Result<Vec<Object>, InterpretResult> {
+        match key {
+            Some(key) if !matches!(*key, ObjectType::Nil) => {
+                let mut keys = Vec::with_capacity(items.len());
+                for item in items {
+                    keys.push(self.call_callback(key.clone(), vec![item.clone()])?);
+                }
+                Ok(keys)
+            }
+            _ => Ok(items.to_vec()),
+        }
+    }
+}
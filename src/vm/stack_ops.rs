@@ -1,48 +1,143 @@
 use crate::object::Object;
 use crate::object::ObjectType;
-use std::rc::Rc;
+use crate::sync::Rc;
+use std::cell::Cell;
 
-pub const STACK_MAX: usize = 256;
+/// Maximum number of values the operand stack may hold: a full window of
+/// locals for every frame the highest recursion limit allows.
+pub const STACK_MAX: usize = super::call_frame::MAX_RECURSION_LIMIT * 256;
+
+/// A misuse of the stack detected while running, reported by the VM as a
+/// runtime error instead of a panic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StackFault {
+    /// A push went past `STACK_MAX`
+    Overflow,
+    /// A pop or peek went below the bottom, or a slot access past `STACK_MAX`
+    OutOfRange,
+}
 
 /// Stack structure for the VM
 /// Manages the operand stack used during bytecode execution
+///
+/// The slots below `top` hold the values on the stack and those above it are
+/// empty, so pushes and pops move values in and out of their slot without
+/// touching any other reference count, and without a call to grow `data`
+/// once it is big enough. Storage grows on demand up to `STACK_MAX`.
+/// Accesses outside the stack never panic: they yield `None` (or leave the
+/// stack unchanged) and record a `StackFault` that the VM checks after each
+/// instruction.
 pub struct Stack {
-    data: [Object; STACK_MAX],
+    data: Vec<Option<Object>>,
     top: usize,
     last_popped: Object,
+    nil: Object,
+    fault: Cell<Option<StackFault>>,
 }
 
 impl Stack {
-    /// Create a new stack initialized with Nil values
+    /// Create a new, empty stack
     pub fn new() -> Self {
-        let default_obj = Rc::new(ObjectType::Nil);
+        let nil = Rc::new(ObjectType::Nil);
         Stack {
-            data: [(); STACK_MAX].map(|_| default_obj.clone()),
+            data: vec![None; 256],
             top: 0,
-            last_popped: default_obj,
+            last_popped: nil.clone(),
+            nil,
+            fault: Cell::new(None),
+        }
+    }
+
+    fn record_fault(&self, fault: StackFault) {
+        if self.fault.get().is_none() {
+            self.fault.set(Some(fault));
+        }
+    }
+
+    /// Raises the top to `len`, filling the new slots with Nil.
+    /// Returns false (and records an overflow) past `STACK_MAX`.
+    fn grow_to(&mut self, len: usize) -> bool {
+        if len > STACK_MAX {
+            self.record_fault(StackFault::Overflow);
+            return false;
+        }
+        if len > self.data.len() {
+            self.data.resize(len, None);
+        }
+        for slot in &mut self.data[self.top..len] {
+            *slot = Some(self.nil.clone());
         }
+        self.top = len;
+        true
     }
 
     /// Push a value onto the stack
-    #[inline]
+    #[inline(always)]
     pub fn push(&mut self, value: Object) {
-        self.data[self.top] = value;
-        self.top += 1;
+        match self.data.get_mut(self.top) {
+            Some(slot) => {
+                *slot = Some(value);
+                self.top += 1;
+            }
+            None => self.push_growing(value),
+        }
+    }
+
+    /// Pushes `value` into a new slot at the end of `data`.
+    #[cold]
+    fn push_growing(&mut self, value: Object) {
+        if self.top < STACK_MAX {
+            self.data.push(Some(value));
+            self.top += 1;
+        } else {
+            self.record_fault(StackFault::Overflow);
+        }
     }
 
     /// Pop a value from the stack and return it
-    #[inline]
+    #[inline(always)]
     pub fn pop(&mut self) -> Object {
-        self.top -= 1;
-        self.last_popped = self.data[self.top].clone();
-        self.last_popped.clone()
+        let taken = match self.top.checked_sub(1) {
+            Some(index) => self.data[index].take(),
+            None => None,
+        };
+        match taken {
+            Some(value) => {
+                self.top -= 1;
+                value
+            }
+            None => {
+                self.record_fault(StackFault::OutOfRange);
+                self.nil.clone()
+            }
+        }
     }
 
     /// Peek at a value on the stack without removing it
     /// Distance 0 = top of stack, 1 = second from top, etc.
-    #[inline]
+    #[inline(always)]
     pub fn peek(&self, distance: usize) -> &Object {
-        &self.data[self.top - 1 - distance]
+        match self.top.checked_sub(distance + 1) {
+            Some(index) => self.get(index),
+            None => {
+                self.record_fault(StackFault::OutOfRange);
+                &self.nil
+            }
+        }
+    }
+
+    /// Pop the top `count` values and return them, deepest first
+    pub fn pop_many(&mut self, count: usize) -> Vec<Object> {
+        let start = self.top.checked_sub(count).unwrap_or_else(|| {
+            self.record_fault(StackFault::OutOfRange);
+            0
+        });
+        let values = self.data[start..self.top]
+            .iter_mut()
+            .map(|slot| slot.take().unwrap_or_else(|| self.nil.clone()))
+            .collect();
+        self.top = start;
+        values
     }
 
     /// Get the last popped value (used by the VM for tracking expression results)
@@ -54,45 +149,83 @@ impl Stack {
     /// Peek at the top of the stack (returns None if stack is empty)
     /// Helper for testing to inspect the top of the stack without popping
     pub fn peek_top(&self) -> Option<Object> {
-        (self.top > 0).then(|| self.data[self.top - 1].clone())
+        (self.top > 0).then(|| self.get(self.top - 1).clone())
     }
 
     /// Get the current stack top index
-    #[inline]
+    #[inline(always)]
     pub fn top(&self) -> usize {
         self.top
     }
 
-    /// Set the stack top index (used for frame management)
+    /// Set the stack top index (used for frame management). Lowering it
+    /// releases the values above the new top, so a returning frame's locals
+    /// are freed with it; raising it fills the new slots with Nil.
     #[inline]
     pub fn set_top(&mut self, top: usize) {
-        self.top = top;
+        if top <= self.top {
+            for slot in &mut self.data[top..self.top] {
+                *slot = None;
+            }
+            self.top = top;
+        } else {
+            self.grow_to(top);
+        }
     }
 
     /// Get a reference to a specific stack slot
-    #[inline]
+    #[inline(always)]
     pub fn get(&self, index: usize) -> &Object {
-        &self.data[index]
+        match self.data.get(index) {
+            Some(Some(value)) => value,
+            _ => {
+                // Slots above the top hold nothing yet
+                if index >= STACK_MAX {
+                    self.record_fault(StackFault::OutOfRange);
+                }
+                &self.nil
+            }
+        }
     }
 
-    /// Set a specific stack slot to a value
+    /// Set a specific stack slot to a value, raising the top past it if it
+    /// is above the top
     #[inline]
     pub fn set(&mut self, index: usize, value: Object) {
-        self.data[index] = value;
+        if index < self.top || self.grow_to(index + 1) {
+            self.data[index] = Some(value);
+        }
     }
 
     /// Swap two values on the stack
     pub fn swap(&mut self, a: usize, b: usize) {
-        self.data.swap(a, b);
+        if a < self.top && b < self.top {
+            self.data.swap(a, b);
+        } else {
+            self.record_fault(StackFault::OutOfRange);
+        }
     }
 
     /// Reset the stack to initial state
     pub fn reset(&mut self) {
-        self.top = 0;
-        self.last_popped = Rc::new(ObjectType::Nil);
+        self.set_top(0);
+        self.last_popped = self.nil.clone();
+        self.fault.set(None);
     }
 
-    /// Set the last_popped value (used by handle_return)
+    /// Whether a fault has been recorded since the last `take_fault`
+    #[inline]
+    pub fn has_fault(&self) -> bool {
+        self.fault.get().is_some()
+    }
+
+    /// Returns and clears the first fault recorded since the last call
+    #[inline]
+    pub fn take_fault(&mut self) -> Option<StackFault> {
+        self.fault.take()
+    }
+
+    /// Set the last_popped value (used by OpPop and handle_return)
     #[inline]
     pub fn set_last_popped(&mut self, value: Object) {
         self.last_popped = value;
@@ -110,20 +243,20 @@ use super::VM;
 
 impl VM {
     /// Push a value onto the VM's operand stack.
-    #[inline]
+    #[inline(always)]
     pub(super) fn push(&mut self, value: Object) {
         self.stack.push(value);
     }
 
     /// Pop a value from the VM's operand stack.
-    #[inline]
+    #[inline(always)]
     pub(super) fn pop(&mut self) -> Object {
         self.stack.pop()
     }
 
     /// Peek at a value on the stack without removing it.
     /// Distance 0 = top of stack, 1 = second from top, etc.
-    #[inline]
+    #[inline(always)]
     pub(super) fn peek(&self, distance: usize) -> &Object {
         self.stack.peek(distance)
     }
@@ -0,0 +1,347 @@
+//! Native `str` methods: splitting, joining and stripping, plus
+//! `str.format()`, which runs on the VM.
+//!
+//! `op_get_attr` binds these to the receiving string, so each native gets the
+//! string as its first argument followed by the call's own arguments.
+
+use crate::object::{ClassObject, NativeError, NativeFn, NativeResult, Object, ObjectType};
+use crate::sync::Rc;
+use crate::vm::collections::collect_iterable;
+use crate::vm::formatting;
+
+/// Names of the string methods `string_method` provides.
+pub const STRING_METHODS: [&str; 10] = [
+    "format",
+    "join",
+    "lstrip",
+    "partition",
+    "rpartition",
+    "rsplit",
+    "rstrip",
+    "split",
+    "splitlines",
+    "strip",
+];
+
+/// Returns the native implementing the string method `name`, if there is one.
+pub fn string_method(name: &str) -> Option<Object> {
+    let func: NativeFn = match name {
+        "split" => str_split,
+        "rsplit" => str_rsplit,
+        "splitlines" => str_splitlines,
+        "partition" => str_partition,
+        "rpartition" => str_rpartition,
+        "join" => str_join,
+        "strip" => str_strip,
+        "lstrip" => str_lstrip,
+        "rstrip" => str_rstrip,
+        // Runs on the VM, which can call the `__str__` of instances it formats
+        "format" => {
+            let function = ObjectType::VmNativeFunction(name.to_string(), formatting::str_format);
+            return Some(Rc::new(function));
+        }
+        _ => return None,
+    };
+    Some(Rc::new(ObjectType::NativeFunction(name.to_string(), func)))
+}
+
+fn string(value: &str) -> Object {
+    Rc::new(ObjectType::String(value.to_string()))
+}
+
+fn string_list<'a>(parts: impl IntoIterator<Item = &'a str>) -> Object {
+    Rc::new(ObjectType::List(parts.into_iter().map(string).collect()))
+}
+
+/// Splits a method's arguments into the receiving string and the rest,
+/// checking there are at most `max` of the rest.
+fn receiver<'a>(
+    args: &'a [Object],
+    method: &str,
+    max: usize,
+) -> Result<(&'a str, &'a [Object]), String> {
+    let (text, rest) = match args.split_first() {
+        Some((text, rest)) => match &**text {
+            ObjectType::String(text) => (text.as_str(), rest),
+            other => {
+                return Err(format!(
+                    "descriptor '{}' requires a 'str' object but received a '{}'",
+                    method,
+                    other.type_name()
+                ))
+            }
+        },
+        None => return Err(format!("descriptor '{}' needs an argument", method)),
+    };
+    if rest.len() > max {
+        return Err(format!(
+            "{}() takes at most {} argument{} ({} given)",
+            method,
+            max,
+            if max == 1 { "" } else { "s" },
+            rest.len()
+        ));
+    }
+    Ok((text, rest))
+}
+
+/// Reads an optional separator argument: None or absent means whitespace.
+fn separator(arg: Option<&Object>) -> Result<Option<&str>, NativeError> {
+    match arg.map(|arg| &**arg) {
+        None | Some(ObjectType::Nil) => Ok(None),
+        Some(ObjectType::String(sep)) if sep.is_empty() => {
+            Err(NativeError::value_error("empty separator"))
+        }
+        Some(ObjectType::String(sep)) => Ok(Some(sep)),
+        Some(other) => Err(NativeError::type_error(format!(
+            "must be str or None, not {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Reads an optional `maxsplit` argument; a negative count means no limit.
+fn max_splits(arg: Option<&Object>) -> Result<usize, String> {
+    match arg.map(|arg| &**arg) {
+        None => Ok(usize::MAX),
+        Some(ObjectType::Integer(count)) => Ok(usize::try_from(*count).unwrap_or(usize::MAX)),
+        Some(other) => Err(format!(
+            "'{}' object cannot be interpreted as an integer",
+            other.type_name()
+        )),
+    }
+}
+
+/// `str.split(sep=None, maxsplit=-1)`. Without a separator, runs of
+/// whitespace separate the words and leading and trailing whitespace is
+/// dropped, so an empty or blank string gives no words.
+fn str_split(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (text, rest) = receiver(args, "split", 2)?;
+    let limit = max_splits(rest.get(1))?;
+    let Some(sep) = separator(rest.first())? else {
+        return Ok(string_list(split_whitespace(text, limit)));
+    };
+    Ok(string_list(text.splitn(limit.saturating_add(1), sep)))
+}
+
+/// `str.rsplit(sep=None, maxsplit=-1)`: like `split`, but `maxsplit` counts
+/// splits from the right.
+fn str_rsplit(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (text, rest) = receiver(args, "rsplit", 2)?;
+    let limit = max_splits(rest.get(1))?;
+    let mut parts: Vec<&str> = match separator(rest.first())? {
+        Some(sep) => text.rsplitn(limit.saturating_add(1), sep).collect(),
+        None => rsplit_whitespace(text, limit),
+    };
+    parts.reverse();
+    Ok(string_list(parts))
+}
+
+/// Splits on whitespace runs, leaving the rest of the text after `limit`
+/// splits as the last word without its leading whitespace.
+fn split_whitespace(text: &str, limit: usize) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        if words.len() == limit {
+            words.push(rest);
+            break;
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        words.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    words
+}
+
+/// `split_whitespace` from the right, returning the words last first.
+fn rsplit_whitespace(text: &str, limit: usize) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut rest = text.trim_end();
+    while !rest.is_empty() {
+        if words.len() == limit {
+            words.push(rest);
+            break;
+        }
+        let start = rest.rfind(char::is_whitespace).map_or(0, |index| {
+            index + rest[index..].chars().next().map_or(1, char::len_utf8)
+        });
+        words.push(&rest[start..]);
+        rest = rest[..start].trim_end();
+    }
+    words
+}
+
+/// Whether `ch` ends a line for `str.splitlines`.
+fn is_line_break(ch: char) -> bool {
+    matches!(
+        ch,
+        '\n' | '\r'
+            | '\x0b'
+            | '\x0c'
+            | '\x1c'
+            | '\x1d'
+            | '\x1e'
+            | '\u{85}'
+            | '\u{2028}'
+            | '\u{2029}'
+    )
+}
+
+/// `str.splitlines(keepends=False)`: the lines of the text, split at any
+/// line boundary including `\r\n`, with no empty line after a final break.
+fn str_splitlines(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (text, rest) = receiver(args, "splitlines", 1)?;
+    let keep_ends = match rest.first().map(|arg| &**arg) {
+        None => false,
+        Some(ObjectType::Boolean(keep)) => *keep,
+        Some(ObjectType::Integer(keep)) => *keep != 0,
+        Some(other) => {
+            return Err(NativeError::type_error(format!(
+                "'{}' object cannot be interpreted as an integer",
+                other.type_name()
+            )))
+        }
+    };
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, ch)) = chars.next() {
+        if !is_line_break(ch) {
+            continue;
+        }
+        let mut end = index + ch.len_utf8();
+        if ch == '\r' && chars.peek().is_some_and(|(_, next)| *next == '\n') {
+            chars.next();
+            end += 1;
+        }
+        lines.push(&text[start..if keep_ends { end } else { index }]);
+        start = end;
+    }
+    if start < text.len() {
+        lines.push(&text[start..]);
+    }
+    Ok(string_list(lines))
+}
+
+fn partition_separator<'a>(rest: &'a [Object], method: &str) -> Result<&'a str, NativeError> {
+    match rest.first().map(|arg| &**arg) {
+        Some(ObjectType::String(sep)) if sep.is_empty() => {
+            Err(NativeError::value_error("empty separator"))
+        }
+        Some(ObjectType::String(sep)) => Ok(sep),
+        Some(other) => Err(NativeError::type_error(format!(
+            "must be str, not {}",
+            other.type_name()
+        ))),
+        None => Err(NativeError::type_error(format!(
+            "{}() takes exactly one argument (0 given)",
+            method
+        ))),
+    }
+}
+
+fn triple(head: &str, sep: &str, tail: &str) -> Object {
+    Rc::new(ObjectType::Tuple(vec![
+        string(head),
+        string(sep),
+        string(tail),
+    ]))
+}
+
+/// `str.partition(sep)`: the text before the first `sep`, `sep` and the text
+/// after it, or the whole text and two empty strings.
+fn str_partition(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (text, rest) = receiver(args, "partition", 1)?;
+    let sep = partition_separator(rest, "partition")?;
+    Ok(match text.split_once(sep) {
+        Some((head, tail)) => triple(head, sep, tail),
+        None => triple(text, "", ""),
+    })
+}
+
+/// `str.rpartition(sep)`: like `partition` at the last `sep`, or two empty
+/// strings and the whole text.
+fn str_rpartition(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (text, rest) = receiver(args, "rpartition", 1)?;
+    let sep = partition_separator(rest, "rpartition")?;
+    Ok(match text.rsplit_once(sep) {
+        Some((head, tail)) => triple(head, sep, tail),
+        None => triple("", "", text),
+    })
+}
+
+/// `str.join(iterable)`: the strings of the iterable with the receiver
+/// between each pair.
+fn str_join(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    let (sep, rest) = receiver(args, "join", 1)?;
+    let [iterable] = rest else {
+        return Err(NativeError::type_error(
+            "join() takes exactly one argument (0 given)",
+        ));
+    };
+    let items =
+        collect_iterable(iterable).ok_or_else(|| "can only join an iterable".to_string())?;
+    let mut parts = Vec::with_capacity(items.len());
+    for (position, item) in items.iter().enumerate() {
+        match &**item {
+            ObjectType::String(text) => parts.push(text.as_str()),
+            other => {
+                return Err(NativeError::type_error(format!(
+                    "sequence item {}: expected str instance, {} found",
+                    position,
+                    other.type_name()
+                )))
+            }
+        }
+    }
+    Ok(string(&parts.join(sep)))
+}
+
+/// Reads the optional set of characters to strip: None or absent means
+/// whitespace.
+fn strip_chars(rest: &[Object], method: &str) -> Result<Option<Vec<char>>, String> {
+    match rest.first().map(|arg| &**arg) {
+        None | Some(ObjectType::Nil) => Ok(None),
+        Some(ObjectType::String(chars)) => Ok(Some(chars.chars().collect())),
+        Some(other) => Err(format!(
+            "{} arg must be None or str, not {}",
+            method,
+            other.type_name()
+        )),
+    }
+}
+
+fn strip(args: &[Object], method: &str, start: bool, end: bool) -> NativeResult {
+    let (text, rest) = receiver(args, method, 1)?;
+    let chars = strip_chars(rest, method)?;
+    let strip_char = |ch: char| match &chars {
+        Some(chars) => chars.contains(&ch),
+        None => ch.is_whitespace(),
+    };
+    let mut result = text;
+    if start {
+        result = result.trim_start_matches(strip_char);
+    }
+    if end {
+        result = result.trim_end_matches(strip_char);
+    }
+    Ok(string(result))
+}
+
+/// `str.strip(chars=None)`: the text without leading and trailing whitespace,
+/// or without any of the given characters at either end.
+fn str_strip(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    strip(args, "strip", true, true)
+}
+
+/// `str.lstrip(chars=None)`: like `strip`, at the start only.
+fn str_lstrip(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    strip(args, "lstrip", true, false)
+}
+
+/// `str.rstrip(chars=None)`: like `strip`, at the end only.
+fn str_rstrip(args: &[Object], _class_context: Option<Rc<ClassObject>>) -> NativeResult {
+    strip(args, "rstrip", false, true)
+}
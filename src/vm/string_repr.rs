@@ -1,16 +1,118 @@
-use crate::bytecode::OpCode;
+use crate::bytecode::{OpCode, FORMAT_WITH_SPEC};
 use crate::object::{Object, ObjectType};
-use std::rc::Rc;
+use crate::sync::Rc;
 
 use super::call_frame::CallFrame;
+use super::formatting::repr_of;
 use super::stack_ops::Stack;
+use super::{InterpretResult, VM};
+
+/// Deepest nesting of lists, tuples, sets and dicts that printing and
+/// formatting render: CPython's default recursion limit, which its repr of
+/// nested containers runs into.
+pub const MAX_REPR_DEPTH: usize = 1000;
+
+/// The RecursionError message for a value nested past `MAX_REPR_DEPTH`.
+pub const REPR_DEPTH_MESSAGE: &str =
+    "maximum recursion depth exceeded while getting the repr of an object";
+
+impl VM {
+    /// `repr()` of a list, tuple, set or dict, which is also its `str()`, or
+    /// None for any other value. Each instance in it shows its `__repr__`,
+    /// run on the VM.
+    ///
+    /// As in CPython, a container met again while its own repr is being
+    /// rendered, as an item's `__repr__` can lead back to it, shows as
+    /// `[...]`, `(...)`, `{...}` or `set(...)`. Containers are told apart by
+    /// identity, so one list appearing twice side by side renders in full
+    /// both times. Nesting past `MAX_REPR_DEPTH` raises RecursionError
+    /// rather than overflowing the native stack.
+    pub(in crate::vm) fn container_repr(
+        &mut self,
+        value: &Object,
+    ) -> Option<Result<String, InterpretResult>> {
+        match &**value {
+            ObjectType::List(_)
+            | ObjectType::Tuple(_)
+            | ObjectType::Set(_)
+            | ObjectType::Dict(_) => Some(self.element_repr(value)),
+            _ => None,
+        }
+    }
+
+    /// The repr of `value` as an item of a container shows it.
+    fn element_repr(&mut self, value: &Object) -> Result<String, InterpretResult> {
+        let (open, close) = match &**value {
+            ObjectType::List(_) => ("[", "]"),
+            ObjectType::Tuple(_) => ("(", ")"),
+            ObjectType::Set(items) if !items.is_empty() => ("{", "}"),
+            ObjectType::Dict(_) => ("{", "}"),
+            ObjectType::Instance(_) => {
+                return match self.instance_text(value, true) {
+                    Some(text) => text.map(|text| text.to_string()),
+                    None => Ok(repr_of(value.clone(), &mut self.stack, &mut self.frames)),
+                }
+            }
+            _ => return Ok(repr_of(value.clone(), &mut self.stack, &mut self.frames)),
+        };
+
+        let address = Rc::as_ptr(value) as usize;
+        if self.repr_containers.contains(&address) {
+            return Ok(match &**value {
+                ObjectType::Set(_) => "set(...)".to_string(),
+                _ => format!("{}...{}", open, close),
+            });
+        }
+        if self.repr_containers.len() == MAX_REPR_DEPTH {
+            return Err(self.raise_error("RecursionError", REPR_DEPTH_MESSAGE));
+        }
+
+        self.repr_containers.push(address);
+        let mut text = open.to_string();
+        let result = match &**value {
+            ObjectType::Dict(entries) => {
+                entries
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(index, (key, item))| {
+                        if index > 0 {
+                            text.push_str(", ");
+                        }
+                        text.push_str(&self.element_repr(key)?);
+                        text.push_str(": ");
+                        text.push_str(&self.element_repr(item)?);
+                        Ok(())
+                    })
+            }
+            ObjectType::List(items) | ObjectType::Tuple(items) | ObjectType::Set(items) => {
+                items.iter().enumerate().try_for_each(|(index, item)| {
+                    if index > 0 {
+                        text.push_str(", ");
+                    }
+                    text.push_str(&self.element_repr(item)?);
+                    Ok(())
+                })
+            }
+            _ => Ok(()),
+        };
+        self.repr_containers.pop();
+        result?;
+
+        if matches!(&**value, ObjectType::Tuple(items) if items.len() == 1) {
+            text.push(',');
+        }
+        text.push_str(close);
+        Ok(text)
+    }
+}
 
 /// Get string representation of an object, checking for __str__ and __repr__ special methods.
 ///
 /// This function implements Python's string representation protocol:
 /// 1. For instances, check for `__str__` method first
 /// 2. If not found, check for `__repr__` method
-/// 3. If neither exists, use default representation
+/// 3. If neither exists, exceptions show their arguments and other
+///    instances use the default representation
 /// 4. For non-instance objects, use the Display trait
 ///
 /// # Arguments
@@ -57,10 +159,13 @@ pub fn get_string_representation(
                 return None;
             }
 
+            if let Some(text) = super::exceptions::exception_str(&instance) {
+                return Some(text);
+            }
+
             // No __str__ or __repr__, use default representation
-            let class_name = instance.class.name.clone();
             drop(instance);
-            Some(format!("<{} instance>", class_name))
+            Some(format!("{}", value))
         }
         _ => {
             // For non-instance objects, use the Display trait
@@ -69,6 +174,47 @@ pub fn get_string_representation(
     }
 }
 
+/// Get the `repr()` of an object: an instance's `__repr__` if it has one,
+/// and an exception's class and arguments, as in `ValueError('bad')`.
+/// Other objects use the Display trait.
+///
+/// # Returns
+/// Optional string representation, or None if the method call failed
+pub fn get_repr_representation(
+    value: Object,
+    stack: &mut Stack,
+    frames: &mut Vec<CallFrame>,
+) -> Option<String> {
+    let ObjectType::Instance(instance_ref) = &*value else {
+        return Some(format!("{}", value));
+    };
+    let instance = instance_ref.borrow();
+
+    if let Some(repr_method) = instance.class.get_method("__repr__") {
+        drop(instance); // Release borrow before calling method
+        let result = call_str_method(value.clone(), repr_method, stack, frames)?;
+        if let ObjectType::String(s) = &*result {
+            return Some(s.clone());
+        }
+        return None;
+    }
+
+    if super::exceptions::is_exception_class(&instance.class) {
+        let args = match instance.get_field("args").as_deref() {
+            Some(ObjectType::Tuple(args)) if args.len() == 1 => match &*args[0] {
+                ObjectType::String(text) => format!("({})", crate::object::quote(text)),
+                other => format!("({})", other),
+            },
+            Some(args @ ObjectType::Tuple(_)) => args.to_string(),
+            _ => "()".to_string(),
+        };
+        return Some(format!("{}{}", instance.class.name, args));
+    }
+
+    drop(instance);
+    Some(format!("{}", value))
+}
+
 /// Helper to call __str__ or __repr__ method on an instance.
 ///
 /// This function executes a mini-event loop to run the method and capture its result.
@@ -89,6 +235,15 @@ fn call_str_method(
     stack: &mut Stack,
     frames: &mut Vec<CallFrame>,
 ) -> Option<Object> {
+    // Native methods (such as a dataclass __repr__) run directly
+    if let ObjectType::NativeFunction(_, func) = &*method {
+        let class = match &*instance {
+            ObjectType::Instance(instance_ref) => Some(instance_ref.borrow().class.clone()),
+            _ => None,
+        };
+        return func(&[instance], class).ok();
+    }
+
     // Save current stack state
     let saved_stack_top = stack.top();
     let saved_frame_count = frames.len();
@@ -127,19 +282,27 @@ fn call_str_method(
         }
 
         // Read and execute one instruction
-        let instruction = OpCode::from(read_byte(frames));
+        let Some(instruction) = OpCode::from_byte(read_byte(frames)) else {
+            stack.set_top(saved_stack_top);
+            return None;
+        };
 
         // We need to handle all possible opcodes that __str__ might use
         // For simplicity, let's handle the most common ones
         let should_bail = match instruction {
-            OpCode::OpConstant => {
-                let const_idx = read_byte(frames) as usize;
-                let constant = current_chunk(frames).constants[const_idx].clone();
-                stack.push(constant);
+            OpCode::OpConstant | OpCode::OpConstantLong => {
+                let const_idx = (0..instruction.operand_width())
+                    .fold(0, |index, _| index << 8 | read_byte(frames) as usize);
+                let Some(constant) = current_chunk(frames).constants.get(const_idx) else {
+                    stack.set_top(saved_stack_top);
+                    return None;
+                };
+                stack.push(constant.clone());
                 false
             }
-            OpCode::OpGetLocal => {
-                let slot = read_byte(frames) as usize;
+            OpCode::OpGetLocal | OpCode::OpGetLocalLong => {
+                let slot = (0..instruction.operand_width())
+                    .fold(0, |slot, _| slot << 8 | read_byte(frames) as usize);
                 if let Some(frame) = frames.last() {
                     let index = frame.slot + slot;
                     let value = stack.get(index).clone();
@@ -147,15 +310,17 @@ fn call_str_method(
                 }
                 false
             }
-            OpCode::OpGetAttr => {
-                let attr_idx = read_byte(frames) as usize;
-                let attr_name =
-                    if let ObjectType::String(name) = &*current_chunk(frames).constants[attr_idx] {
-                        name.clone()
-                    } else {
-                        stack.set_top(saved_stack_top);
-                        return None;
-                    };
+            OpCode::OpGetAttr | OpCode::OpGetAttrLong => {
+                let attr_idx = (0..instruction.operand_width())
+                    .fold(0, |index, _| index << 8 | read_byte(frames) as usize);
+                let attr_name = if let Some(ObjectType::String(name)) =
+                    current_chunk(frames).constants.get(attr_idx).map(|c| &**c)
+                {
+                    name.clone()
+                } else {
+                    stack.set_top(saved_stack_top);
+                    return None;
+                };
                 let object = stack.pop();
                 if let ObjectType::Instance(instance_ref) = &*object {
                     let instance = instance_ref.borrow();
@@ -184,6 +349,22 @@ fn call_str_method(
                     _ => true,
                 }
             }
+            OpCode::OpFormatValue => {
+                let flags = read_byte(frames);
+                let spec = if flags & FORMAT_WITH_SPEC != 0 {
+                    stack.pop().to_string()
+                } else {
+                    String::new()
+                };
+                let value = stack.pop();
+                match super::formatting::format_field_on_stack(value, flags, &spec, stack, frames) {
+                    Ok(text) => {
+                        stack.push(Rc::new(ObjectType::String(text)));
+                        false
+                    }
+                    Err(_) => true,
+                }
+            }
             OpCode::OpReturn => {
                 if handle_return_for_str(stack, frames) {
                     stack.set_top(saved_stack_top);
@@ -216,7 +397,13 @@ fn current_chunk(frames: &[CallFrame]) -> &crate::bytecode::Chunk {
 /// Helper function to read a byte from the current frame
 fn read_byte(frames: &mut [CallFrame]) -> u8 {
     let frame = frames.last_mut().expect("expected active call frame");
-    let byte = frame.function.chunk.code[frame.ip];
+    let byte = frame
+        .function
+        .chunk
+        .code
+        .get(frame.ip)
+        .copied()
+        .unwrap_or(OpCode::OpReturn as u8);
     frame.ip += 1;
     byte
 }
@@ -247,7 +434,13 @@ fn call_value_for_str(arg_count: usize, stack: &mut Stack, frames: &mut Vec<Call
             match &**method {
                 ObjectType::Function(function) => {
                     // Call with arg_count + 1 (including self)
-                    call_function_for_str(function.clone(), callee_index, arg_count + 1, frames)
+                    call_function_for_str(
+                        function.clone(),
+                        callee_index,
+                        arg_count + 1,
+                        stack,
+                        frames,
+                    )
                 }
                 _ => false,
             }
@@ -261,6 +454,7 @@ fn call_function_for_str(
     function: Rc<crate::object::FunctionObject>,
     callee_index: usize,
     arg_count: usize,
+    stack: &mut Stack,
     frames: &mut Vec<CallFrame>,
 ) -> bool {
     if function.arity != arg_count {
@@ -270,19 +464,25 @@ fn call_function_for_str(
         return false;
     }
 
-    frames.push(CallFrame::new(function, callee_index, None, None));
+    for _ in 0..function.local_count {
+        stack.push(Rc::new(ObjectType::Nil));
+    }
+    frames.push(CallFrame::new(function, callee_index, false, None));
     true
 }
 
 /// Simplified handle_return for __str__ method execution
 fn handle_return_for_str(stack: &mut Stack, frames: &mut Vec<CallFrame>) -> bool {
-    let (frame_slot, frame_arity) = if let Some(frame) = frames.last() {
-        (frame.slot, frame.function.arity)
+    let (frame_slot, frame_size) = if let Some(frame) = frames.last() {
+        (
+            frame.slot,
+            frame.function.arity + frame.function.local_count,
+        )
     } else {
         (0, 0)
     };
 
-    let minimum_stack = frame_slot + frame_arity + 1;
+    let minimum_stack = frame_slot + frame_size + 1;
     let result = if stack.top() > minimum_stack {
         Some(stack.pop())
     } else {
@@ -0,0 +1,60 @@
+//! Opt-in reuse of a frame for a function's call to itself in tail position.
+//!
+//! With `VM::set_tail_calls` on, a call made by `return f(...)` inside `f`
+//! itself replaces the frame making it rather than stacking a new one, so a
+//! recursion written that way runs in constant frame depth, past the
+//! recursion limit, as a loop would. The call is made as usual, binding the
+//! arguments, defaults and locals of the new frame, which is then slid down
+//! over the caller's.
+//!
+//! Only an OpCall or OpCallKw directly followed by OpReturn counts, so a call
+//! inside `try` or `with`, whose return unwinds first, is never replaced, and
+//! neither are calls between different functions, `__init__` frames and
+//! generator frames. The frames replaced leave observable gaps: a traceback
+//! shows a single frame for the whole chain of tail calls, and the profile
+//! and the call hook see only its first call.
+
+use crate::bytecode::OpCode;
+use crate::sync::Rc;
+use crate::vm::upvalues;
+use crate::vm::VM;
+
+impl VM {
+    /// Turns the reuse of frames for self tail calls on or off.
+    pub fn set_tail_calls(&mut self, enabled: bool) {
+        self.tail_calls = enabled;
+    }
+
+    /// Replaces the caller of the frame just pushed by a call made with
+    /// `depth` frames active, when the call is a self tail call.
+    pub(super) fn reuse_frame_for_tail_call(&mut self, depth: usize) {
+        if !self.tail_calls || depth == 0 || self.frames.len() != depth + 1 {
+            return;
+        }
+        let (caller, callee) = (&self.frames[depth - 1], &self.frames[depth]);
+        let in_tail_position = caller.chunk.code.get(caller.ip) == Some(&(OpCode::OpReturn as u8));
+        let reusable = in_tail_position
+            && Rc::ptr_eq(&caller.function, &callee.function)
+            && !caller.is_initializer
+            && !callee.is_initializer
+            && caller.generator.is_none()
+            && !self.has_handler_from(depth);
+        if !reusable {
+            return;
+        }
+
+        // Closures made by the caller keep the values they captured
+        let (caller_slot, callee_slot) = (caller.slot, callee.slot);
+        upvalues::close_upvalues(&mut self.open_upvalues, &self.stack, caller_slot);
+        let top = self.stack.top();
+        for index in callee_slot..top {
+            let value = self.stack.get(index).clone();
+            self.stack.set(caller_slot + index - callee_slot, value);
+        }
+        self.stack.set_top(caller_slot + top - callee_slot);
+
+        let mut frame = self.frames.pop().expect("callee frame checked above");
+        frame.slot = caller_slot;
+        self.frames[depth - 1] = frame;
+    }
+}
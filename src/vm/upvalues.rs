@@ -1,7 +1,8 @@
 use crate::object::{ObjectType, Upvalue, UpvalueRef};
-use std::cell::RefCell;
-use std::rc::Rc;
+use crate::sync::Rc;
+use crate::sync::RefCell;
 
+use super::gc;
 use super::stack_ops::Stack;
 
 /// Captures an upvalue for a given stack index.
@@ -28,6 +29,7 @@ pub fn capture_upvalue(open_upvalues: &mut Vec<UpvalueRef>, index: usize) -> Upv
     }
 
     let upvalue = Rc::new(RefCell::new(Upvalue::new(index, Rc::new(ObjectType::Nil))));
+    gc::track_upvalue(&upvalue);
     open_upvalues.push(upvalue.clone());
     upvalue
 }
@@ -43,6 +45,9 @@ pub fn capture_upvalue(open_upvalues: &mut Vec<UpvalueRef>, index: usize) -> Upv
 /// * `stack` - Reference to the VM stack to read values from
 /// * `from_index` - The stack index from which to close upvalues
 pub fn close_upvalues(open_upvalues: &mut Vec<UpvalueRef>, stack: &Stack, from_index: usize) {
+    if open_upvalues.is_empty() {
+        return;
+    }
     let mut to_remove = Vec::new();
     for (idx, upvalue_ref) in open_upvalues.iter().enumerate() {
         let mut upvalue = upvalue_ref.borrow_mut();
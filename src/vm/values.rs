@@ -1,9 +1,105 @@
-use crate::object::ObjectType;
+use crate::object::{Object, ObjectType};
+use crate::sync::Rc;
 
+/// Smallest integer kept in the shared small-int cache.
+const SMALL_INT_MIN: i64 = -5;
+/// Largest integer kept in the shared small-int cache.
+const SMALL_INT_MAX: i64 = 256;
+
+thread_local! {
+    static SMALL_INTS: Vec<Object> = (SMALL_INT_MIN..=SMALL_INT_MAX)
+        .map(|value| Rc::new(ObjectType::Integer(value)))
+        .collect();
+    static BOOLEANS: [Object; 2] = [
+        Rc::new(ObjectType::Boolean(false)),
+        Rc::new(ObjectType::Boolean(true)),
+    ];
+    static NONE: Object = Rc::new(ObjectType::Nil);
+}
+
+/// Whether a builtin value is true in a condition: as in Python, None,
+/// False, zero and empty strings and collections are false and everything
+/// else is true. Instances defining `__bool__` or `__len__` go through
+/// `VM::truthiness` instead.
 pub fn is_truthy(value: &ObjectType) -> bool {
     match value {
         ObjectType::Nil => false,
         ObjectType::Boolean(b) => *b,
+        ObjectType::Integer(number) => *number != 0,
+        ObjectType::Float(number) => *number != 0.0,
+        ObjectType::String(text) => !text.is_empty(),
+        ObjectType::List(items) | ObjectType::Tuple(items) | ObjectType::Set(items) => {
+            !items.is_empty()
+        }
+        ObjectType::Dict(entries) => !entries.is_empty(),
         _ => true,
     }
 }
+
+/// Returns whether `value` is one of the shared small integers.
+#[inline(always)]
+pub fn is_small_int(value: i64) -> bool {
+    (SMALL_INT_MIN..=SMALL_INT_MAX).contains(&value)
+}
+
+/// Returns an integer object, reusing a shared instance for small values
+/// (as CPython does) so hot arithmetic and loop counters avoid allocating.
+#[inline(always)]
+pub fn integer(value: i64) -> Object {
+    if is_small_int(value) {
+        SMALL_INTS.with(|cache| cache[(value - SMALL_INT_MIN) as usize].clone())
+    } else {
+        Rc::new(ObjectType::Integer(value))
+    }
+}
+
+/// Returns the shared `True` or `False` object.
+#[inline(always)]
+pub fn boolean(value: bool) -> Object {
+    BOOLEANS.with(|cache| cache[value as usize].clone())
+}
+
+/// The shared small integers and booleans, held by the VM so its hot paths
+/// hand them out without a thread-local lookup each time. They are the same
+/// objects `integer` and `boolean` return.
+pub struct SharedValues {
+    small_ints: Vec<Object>,
+    booleans: [Object; 2],
+}
+
+impl SharedValues {
+    pub fn new() -> Self {
+        Self {
+            small_ints: SMALL_INTS.with(Vec::clone),
+            booleans: BOOLEANS.with(<[Object; 2]>::clone),
+        }
+    }
+
+    /// Returns an integer object, as `integer` does.
+    #[inline(always)]
+    pub fn integer(&self, value: i64) -> Object {
+        if is_small_int(value) {
+            self.small_ints[(value - SMALL_INT_MIN) as usize].clone()
+        } else {
+            Rc::new(ObjectType::Integer(value))
+        }
+    }
+
+    /// Returns the shared `True` or `False` object.
+    #[inline(always)]
+    pub fn boolean(&self, value: bool) -> Object {
+        self.booleans[value as usize].clone()
+    }
+}
+
+impl Default for SharedValues {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the shared `None` object.
+#[inline]
+pub fn none() -> Object {
+    NONE.with(Rc::clone)
+}
@@ -1,4 +1,9 @@
-use oxython::bytecode::{Chunk, OpCode};
+use oxython::bytecode::{
+    jump_distance, slot_index, Chunk, OpCode, JUMP_WIDTH, MAX_CONSTANT, MAX_SLOT,
+};
+use oxython::compiler::Compiler;
+use oxython::object::ObjectType;
+use oxython::sync::Rc;
 
 #[test]
 fn test_opcode_from_u8() {
@@ -33,16 +38,40 @@ fn test_opcode_from_u8() {
     assert_eq!(OpCode::from(28), OpCode::OpModulo);
     assert_eq!(OpCode::from(29), OpCode::OpEqual);
     assert_eq!(OpCode::from(30), OpCode::OpToList);
-    assert_eq!(OpCode::from(31), OpCode::OpZip);
-    assert_eq!(OpCode::from(32), OpCode::OpStrLower);
-    assert_eq!(OpCode::from(33), OpCode::OpStrIsAlnum);
-    assert_eq!(OpCode::from(34), OpCode::OpStrJoin);
-    assert_eq!(OpCode::from(35), OpCode::OpCall);
-    assert_eq!(OpCode::from(36), OpCode::OpGetLocal);
-    assert_eq!(OpCode::from(37), OpCode::OpSetLocal);
-    assert_eq!(OpCode::from(38), OpCode::OpGetUpvalue);
-    assert_eq!(OpCode::from(39), OpCode::OpSetUpvalue);
-    assert_eq!(OpCode::from(40), OpCode::OpMakeFunction);
+    assert_eq!(OpCode::from(31), OpCode::OpStrLower);
+    assert_eq!(OpCode::from(32), OpCode::OpStrIsAlnum);
+    assert_eq!(OpCode::from(33), OpCode::OpStrJoin);
+    assert_eq!(OpCode::from(34), OpCode::OpCall);
+    assert_eq!(OpCode::from(35), OpCode::OpGetLocal);
+    assert_eq!(OpCode::from(36), OpCode::OpSetLocal);
+    assert_eq!(OpCode::from(37), OpCode::OpGetUpvalue);
+    assert_eq!(OpCode::from(38), OpCode::OpSetUpvalue);
+    assert_eq!(OpCode::from(39), OpCode::OpMakeFunction);
+    assert_eq!(OpCode::from(45), OpCode::OpListExtend);
+    assert_eq!(OpCode::from(46), OpCode::OpCallEx);
+    assert_eq!(OpCode::from(47), OpCode::OpDeleteGlobal);
+    assert_eq!(OpCode::from(48), OpCode::OpFloorDivide);
+    assert_eq!(OpCode::from(49), OpCode::OpStrUpper);
+    assert_eq!(OpCode::from(50), OpCode::OpLessEqual);
+    assert_eq!(OpCode::from(51), OpCode::OpGreater);
+    assert_eq!(OpCode::from(52), OpCode::OpGreaterEqual);
+    assert_eq!(OpCode::from(53), OpCode::OpListSort);
+    assert_eq!(OpCode::from(54), OpCode::OpCallKw);
+    assert_eq!(OpCode::from(55), OpCode::OpBuildList);
+    assert_eq!(OpCode::from(56), OpCode::OpBuildDict);
+    assert_eq!(OpCode::from(57), OpCode::OpUnpack);
+    assert_eq!(OpCode::from(58), OpCode::OpNot);
+    assert_eq!(OpCode::from(59), OpCode::OpIs);
+    assert_eq!(OpCode::from(60), OpCode::OpImport);
+    assert_eq!(OpCode::from(61), OpCode::OpImportFrom);
+    assert_eq!(OpCode::from(62), OpCode::OpSetupExcept);
+    assert_eq!(OpCode::from(65), OpCode::OpRaise);
+    assert_eq!(OpCode::from(72), OpCode::OpPopFinally);
+    assert_eq!(OpCode::from(73), OpCode::OpFormatValue);
+    assert_eq!(OpCode::from(74), OpCode::OpMakeGenerator);
+    assert_eq!(OpCode::from(75), OpCode::OpYield);
+    assert_eq!(OpCode::from(76), OpCode::OpSetSlice);
+    assert_eq!(OpCode::from(78), OpCode::OpDeleteSlice);
 }
 
 #[test]
@@ -51,9 +80,294 @@ fn test_opcode_from_u8_invalid_panics() {
     let _ = OpCode::from(255);
 }
 
+#[test]
+fn test_opcode_from_byte_rejects_invalid_bytes() {
+    assert_eq!(OpCode::from_byte(0), Some(OpCode::OpConstant));
+    assert_eq!(OpCode::from_byte(56), Some(OpCode::OpBuildDict));
+    assert_eq!(OpCode::from_byte(57), Some(OpCode::OpUnpack));
+    assert_eq!(OpCode::from_byte(59), Some(OpCode::OpIs));
+    assert_eq!(OpCode::from_byte(61), Some(OpCode::OpImportFrom));
+    assert_eq!(OpCode::from_byte(72), Some(OpCode::OpPopFinally));
+    assert_eq!(OpCode::from_byte(73), Some(OpCode::OpFormatValue));
+    assert_eq!(OpCode::from_byte(74), Some(OpCode::OpMakeGenerator));
+    assert_eq!(OpCode::from_byte(75), Some(OpCode::OpYield));
+    assert_eq!(OpCode::from_byte(77), Some(OpCode::OpDeleteIndex));
+    assert_eq!(OpCode::from_byte(79), Some(OpCode::OpGetLocalLong));
+    assert_eq!(OpCode::from_byte(82), Some(OpCode::OpSetUpvalueLong));
+    assert_eq!(OpCode::from_byte(83), Some(OpCode::OpCallExKw));
+    assert_eq!(OpCode::from_byte(84), Some(OpCode::OpConstantLong));
+    assert_eq!(OpCode::from_byte(93), Some(OpCode::OpImportFromLong));
+    assert_eq!(OpCode::from_byte(94), Some(OpCode::OpEvaluatedNamespace));
+    assert_eq!(OpCode::from_byte(95), Some(OpCode::OpNegate));
+    assert_eq!(OpCode::from_byte(96), None);
+    assert_eq!(OpCode::from_byte(255), None);
+}
+
+#[test]
+fn test_opcode_operand_width() {
+    assert_eq!(OpCode::OpAdd.operand_width(), 0);
+    assert_eq!(OpCode::OpConstant.operand_width(), 1);
+    assert_eq!(OpCode::OpGetAttr.operand_width(), 1);
+    assert_eq!(OpCode::OpJump.operand_width(), JUMP_WIDTH);
+    assert_eq!(OpCode::OpUnpack.operand_width(), 2);
+    assert_eq!(OpCode::OpImport.operand_width(), 1);
+    assert_eq!(OpCode::OpSetupFinally.operand_width(), JUMP_WIDTH);
+    assert_eq!(OpCode::OpPopTry.operand_width(), 0);
+    assert_eq!(OpCode::OpFormatValue.operand_width(), 1);
+    assert!(OpCode::OpLoop.is_jump());
+    assert!(!OpCode::OpUnpack.is_jump());
+    assert_eq!(OpCode::OpGetLocal.operand_width(), 1);
+    assert_eq!(OpCode::OpGetLocalLong.operand_width(), 2);
+    assert_eq!(OpCode::OpSetUpvalueLong.operand_width(), 2);
+}
+
+#[test]
+fn test_long_forms_of_slot_opcodes() {
+    assert_eq!(OpCode::OpGetLocal.long_form(), OpCode::OpGetLocalLong);
+    assert_eq!(OpCode::OpSetLocal.long_form(), OpCode::OpSetLocalLong);
+    assert_eq!(OpCode::OpGetUpvalue.long_form(), OpCode::OpGetUpvalueLong);
+    assert_eq!(OpCode::OpSetUpvalue.long_form(), OpCode::OpSetUpvalueLong);
+    assert_eq!(OpCode::OpAdd.long_form(), OpCode::OpAdd);
+    assert_eq!(slot_index(&[7]), 7);
+    assert_eq!(slot_index(&[1, 44]), 300);
+    assert_eq!(MAX_SLOT, 65535);
+}
+
+#[test]
+fn test_long_forms_of_constant_opcodes() {
+    assert_eq!(OpCode::OpConstant.long_form(), OpCode::OpConstantLong);
+    assert_eq!(OpCode::OpGetGlobal.long_form(), OpCode::OpGetGlobalLong);
+    assert_eq!(
+        OpCode::OpMakeFunction.long_form(),
+        OpCode::OpMakeFunctionLong
+    );
+    assert_eq!(OpCode::OpImportFrom.long_form(), OpCode::OpImportFromLong);
+    assert!(OpCode::OpConstantLong.takes_constant());
+    assert!(OpCode::OpSetAttrLong.takes_long_constant());
+    assert!(!OpCode::OpSetAttr.takes_long_constant());
+    assert_eq!(OpCode::OpConstantLong.operand_width(), 2);
+    assert_eq!(OpCode::OpGetAttrLong.operand_width(), 2);
+    assert_eq!(MAX_CONSTANT, 65535);
+}
+
+#[test]
+fn test_jump_distance_is_big_endian() {
+    assert_eq!(jump_distance(&[0, 0, 0, 10]), 10);
+    assert_eq!(jump_distance(&[0, 1, 0, 0]), 65536);
+    assert_eq!(jump_distance(&[1, 2, 3, 4]), 0x0102_0304);
+}
+
 #[test]
 fn test_chunk_default_is_empty() {
     let chunk = Chunk::default();
     assert!(chunk.code.is_empty());
     assert!(chunk.constants.is_empty());
+    assert!(chunk.lines.is_empty());
+}
+
+#[test]
+fn test_chunk_line_table() {
+    let mut chunk = Chunk::new();
+    chunk.add_line(1);
+    chunk
+        .code
+        .extend([OpCode::OpAdd as u8, OpCode::OpPop as u8]);
+    chunk.add_line(1);
+    chunk.code.push(OpCode::OpAdd as u8);
+    chunk.add_line(3);
+    // A line starting where nothing has been emitted replaces it
+    chunk.add_line(4);
+    chunk.code.push(OpCode::OpPop as u8);
+    chunk.restart_line(4);
+    chunk.code.push(OpCode::OpReturn as u8);
+
+    assert_eq!(chunk.lines, vec![(0, 1), (3, 4), (4, 4)]);
+    assert_eq!(chunk.line_at(2), Some(1));
+    assert_eq!(chunk.line_at(3), Some(4));
+    assert_eq!(chunk.line_at(99), Some(4));
+    assert_eq!(chunk.line_starting_at(3), Some(4));
+    assert_eq!(chunk.line_starting_at(1), None);
+    assert_eq!(Chunk::new().line_at(0), None);
+}
+
+#[test]
+fn test_chunk_instructions_decode_operands() {
+    let chunk = Compiler::compile("x = 1\nwhile x:\n    x = 0").expect("Expected chunk");
+    let instructions: Vec<(usize, OpCode, Vec<u8>)> = chunk
+        .instructions()
+        .map(|(offset, opcode, operands)| (offset, opcode, operands.to_vec()))
+        .collect();
+    assert_eq!(
+        instructions,
+        vec![
+            (0, OpCode::OpConstant, vec![1]),
+            (2, OpCode::OpDefineGlobal, vec![0]),
+            (4, OpCode::OpGetGlobal, vec![0]),
+            (6, OpCode::OpJumpIfFalse, vec![0, 0, 0, 10]),
+            (11, OpCode::OpPop, vec![]),
+            (12, OpCode::OpConstant, vec![2]),
+            (14, OpCode::OpDefineGlobal, vec![0]),
+            (16, OpCode::OpLoop, vec![0, 0, 0, 17]),
+            (21, OpCode::OpPop, vec![]),
+            (22, OpCode::OpReturn, vec![]),
+        ]
+    );
+}
+
+#[test]
+fn test_chunk_instructions_stop_at_malformed_code() {
+    let mut chunk = Chunk::new();
+    chunk
+        .code
+        .extend([OpCode::OpPop as u8, 0xff, OpCode::OpPop as u8]);
+    assert_eq!(chunk.instructions().count(), 1);
+
+    chunk.code = vec![OpCode::OpPop as u8, OpCode::OpJump as u8, 0];
+    assert_eq!(chunk.instructions().count(), 1);
+}
+
+#[test]
+fn test_chunk_display_disassembles_code_and_functions() {
+    let chunk = Compiler::compile("def f(a):\n    return a\n\nif f(1):\n    f('b')")
+        .expect("Expected chunk");
+    assert_eq!(
+        chunk.to_string(),
+        "\
+0000    1 OpMakeFunction     0 <fn f>
+0002    | OpDefineGlobal     1 'f'
+0004    4 OpGetGlobal        1 'f'
+0006    | OpConstant         2 1
+0008    | OpCall             1
+0010    | OpJumpIfFalse     13 -> 0028
+0015    | OpPop
+0016    5 OpGetGlobal        1 'f'
+0018    | OpConstant         3 'b'
+0020    | OpCall             1
+0022    | OpPop
+0023    | OpJump             1 -> 0029
+0028    | OpPop
+0029    | OpReturn
+
+== f ==
+0000    2 OpGetLocal         1
+0002    | OpReturn
+"
+    );
+}
+
+#[test]
+fn test_chunk_records_statement_depths() {
+    let chunk = Compiler::compile("for i in [1]:\n    for j in [2]:\n        x = j\ny = 2")
+        .expect("Expected chunk");
+    let depths: Vec<usize> = chunk.statements.iter().map(|&(_, depth)| depth).collect();
+    assert_eq!(depths, vec![0, 2, 4, 0]);
+    assert_eq!(chunk.statement_depth_at(0), Some(0));
+    assert_eq!(chunk.statement_depth_at(1), None);
+}
+
+#[test]
+fn test_chunk_constant_references() {
+    let chunk =
+        Compiler::compile("x = 'a'\nimport math\nprint(x.y, math.pi)").expect("Expected chunk");
+    for (offset, index) in chunk.constant_references() {
+        assert!(OpCode::from(chunk.code[offset]).takes_constant());
+        assert!(index < chunk.constants.len());
+    }
+    assert!(chunk.referenced_constants().iter().all(|&used| used));
+    assert!(!OpCode::OpGetLocal.takes_constant());
+}
+
+#[test]
+fn test_chunk_compacts_duplicate_and_unused_constants() {
+    let mut chunk = Chunk::new();
+    for value in [1, 2, 1, 3] {
+        chunk.constants.push(Rc::new(ObjectType::Integer(value)));
+    }
+    chunk.constants.push(Rc::new(ObjectType::Float(0.0)));
+    chunk.constants.push(Rc::new(ObjectType::Float(-0.0)));
+    chunk.code = vec![
+        OpCode::OpConstant as u8,
+        2,
+        OpCode::OpConstant as u8,
+        3,
+        OpCode::OpConstant as u8,
+        0,
+        OpCode::OpConstant as u8,
+        4,
+        OpCode::OpConstant as u8,
+        5,
+        OpCode::OpReturn as u8,
+    ];
+    assert_eq!(
+        chunk.referenced_constants(),
+        vec![true, false, true, true, true, true]
+    );
+
+    assert_eq!(chunk.deduplicate_constants(), 1);
+    assert_eq!(chunk.code[1], 0);
+    assert_eq!(chunk.constants.len(), 6);
+
+    assert_eq!(chunk.remove_unused_constants(), 2);
+    let values: Vec<String> = chunk
+        .constants
+        .iter()
+        .map(|value| value.to_string())
+        .collect();
+    assert_eq!(values, vec!["1", "3", "0.0", "-0.0"]);
+    let operands: Vec<u8> = chunk
+        .constant_references()
+        .map(|(_, index)| index as u8)
+        .collect();
+    assert_eq!(operands, vec![0, 1, 0, 2, 3]);
+    assert_eq!(chunk.compact_constants(), 0);
+}
+
+#[test]
+fn test_chunk_compacts_constants_behind_long_operands() {
+    let mut chunk = Chunk::new();
+    for value in [1, 2, 1] {
+        chunk.constants.push(Rc::new(ObjectType::Integer(value)));
+    }
+    chunk.code = vec![
+        OpCode::OpConstantLong as u8,
+        0,
+        2,
+        OpCode::OpConstant as u8,
+        1,
+        OpCode::OpReturn as u8,
+    ];
+    assert_eq!(chunk.referenced_constants(), vec![false, true, true]);
+    assert_eq!(chunk.compact_constants(), 1);
+    assert_eq!(chunk.code[..3], [OpCode::OpConstantLong as u8, 0, 0]);
+    assert_eq!(chunk.code[4], 1);
+    assert_eq!(chunk.constants.len(), 2);
+}
+
+#[test]
+fn test_chunk_keeps_constants_of_malformed_code() {
+    let mut chunk = Chunk::new();
+    chunk.constants.push(Rc::new(ObjectType::Nil));
+    chunk.code = vec![OpCode::OpPop as u8, 0xff, OpCode::OpConstant as u8, 0];
+    assert_eq!(chunk.remove_unused_constants(), 0);
+    assert_eq!(chunk.constants.len(), 1);
+}
+
+#[test]
+fn test_compiler_compacts_comprehension_constants() {
+    let chunk = Compiler::compile("def f(xs):\n    return [x + 1 for x in xs if x]\n")
+        .expect("Expected chunk");
+    let function = chunk
+        .constants
+        .iter()
+        .find_map(|constant| match &**constant {
+            ObjectType::FunctionPrototype(prototype) => Some(prototype.chunk.clone()),
+            _ => None,
+        })
+        .expect("Expected function");
+    assert!(function.referenced_constants().iter().all(|&used| used));
+    // The loop variable became a local, so its name is no longer kept
+    assert!(!function
+        .constants
+        .iter()
+        .any(|value| value.to_string() == "x"));
 }
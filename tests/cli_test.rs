@@ -1,7 +1,9 @@
 use oxython::cli::{
-    handle_args, handle_args_with_prompt, run_file, run_main_with_args, run_prompt,
-    run_prompt_with_io, run_prompt_with_streams,
+    handle_args, handle_args_with_prompt, run_file, run_file_with_profile, run_main_with_args,
+    run_prompt, run_prompt_with_io, run_prompt_with_streams,
 };
+use oxython::compiler::Compiler;
+use oxython::marshal;
 use std::cell::Cell;
 use std::env;
 use std::fs;
@@ -128,6 +130,22 @@ fn run_prompt_reports_compile_error() {
     assert!(output.contains("Compilation failed."));
 }
 
+#[test]
+fn run_prompt_reports_syntax_error_positions() {
+    let input = b"x = ) ; y = ]\n";
+    let mut reader = Cursor::new(&input[..]);
+    let mut buffer = Vec::new();
+
+    run_prompt_with_io(&mut reader, &mut buffer).unwrap();
+
+    let mut output = String::new();
+    Cursor::new(buffer).read_to_string(&mut output).unwrap();
+
+    assert!(output.contains("SyntaxError: invalid syntax (line 1, column 5)"));
+    assert!(output.contains("SyntaxError: invalid syntax (line 1, column 13)"));
+    assert!(output.contains("Compilation failed."));
+}
+
 #[test]
 fn run_prompt_reports_runtime_error() {
     let input = b"a\n";
@@ -157,6 +175,35 @@ fn run_file_reports_compile_error() {
     let _ = fs::remove_file(&path);
 }
 
+#[test]
+fn run_file_reports_runtime_error() {
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "oxythonlang_test_{}_runtime_err.py",
+        std::process::id()
+    ));
+    fs::write(&path, "def f(a):\n    return a\n\nf(1, 2)\n").unwrap();
+
+    let result = run_file(path.to_str().unwrap());
+    assert_eq!(result.unwrap_err(), 70);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn run_prompt_reports_runtime_error_message() {
+    let input = b"len(1, 2)\n";
+    let mut reader = Cursor::new(&input[..]);
+    let mut buffer = Vec::new();
+
+    run_prompt_with_io(&mut reader, &mut buffer).unwrap();
+
+    let mut output = String::new();
+    Cursor::new(buffer).read_to_string(&mut output).unwrap();
+
+    assert!(output.contains("TypeError: len() takes exactly one argument (2 given)"));
+}
+
 #[test]
 fn run_main_with_args_propagates_compile_error() {
     let mut path = env::temp_dir();
@@ -223,14 +270,31 @@ fn run_prompt_suppresses_nil_values() {
                 && !l.contains("Welcome")
                 && !l.contains("REPL")
                 && !l.contains("oxython")
+                && !l.contains("Error")
+                && !l.contains("error")
         })
         .copied()
         .collect();
 
-    // Nil values should not be printed
+    // None values should not be printed
     assert!(!after_prompts
         .iter()
-        .any(|l| l.contains("nil") || l.contains("Nil")));
+        .any(|l| l.contains("None") || l.contains("nil")));
+}
+
+#[test]
+fn run_prompt_pretty_prints_values_wider_than_the_line() {
+    let input = b"'text'\n{'b': 1, 'a': 2}\nlist(range(30))\n";
+    let mut reader = Cursor::new(&input[..]);
+    let mut buffer = Vec::new();
+
+    run_prompt_with_io(&mut reader, &mut buffer).unwrap();
+
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(output.contains("> 'text'\n"));
+    assert!(output.contains("> {'b': 1, 'a': 2}\n"));
+    let numbers: Vec<String> = (0..30).map(|n| n.to_string()).collect();
+    assert!(output.contains(&format!("> [{}]\n", numbers.join(",\n "))));
 }
 
 #[test]
@@ -246,6 +310,261 @@ fn run_file_executes_and_interprets() {
     let _ = fs::remove_file(&path);
 }
 
+#[test]
+fn run_file_with_profile_runs_the_script() {
+    let mut path = env::temp_dir();
+    path.push(format!("oxython_test_{}_profile.py", std::process::id()));
+    fs::write(&path, "def f(n):\n    return n + 1\nf(1)").unwrap();
+
+    assert!(run_file_with_profile(path.to_str().unwrap()).is_ok());
+    let args = vec![
+        String::from("--profile"),
+        path.to_str().unwrap().to_string(),
+    ];
+    assert!(handle_args_with_prompt(&args, || {}).is_ok());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn coverage_flag_writes_an_lcov_report_beside_the_script() {
+    let mut dir = env::temp_dir();
+    dir.push(format!("oxython_test_{}_coverage", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("script.py");
+    fs::write(&script, "x = 1\nif x > 1:\n    print(x)").unwrap();
+
+    let args = vec![
+        String::from("--coverage"),
+        script.to_str().unwrap().to_string(),
+    ];
+    assert!(handle_args_with_prompt(&args, || {}).is_ok());
+    let report = fs::read_to_string(dir.join("lcov.info")).unwrap();
+    let source = fs::canonicalize(&script).unwrap();
+    assert_eq!(
+        report,
+        format!(
+            "TN:\nSF:{}\nDA:1,1\nDA:2,1\nDA:3,0\nLF:3\nLH:2\nend_of_record\n",
+            source.display()
+        )
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn tail_calls_flag_runs_deep_self_tail_recursion() {
+    let mut path = env::temp_dir();
+    path.push(format!("oxython_test_{}_tail_calls.py", std::process::id()));
+    let source =
+        "def down(n):\n    if n == 0:\n        return 0\n    return down(n - 1)\ndown(500)";
+    fs::write(&path, source).unwrap();
+
+    let script = path.to_str().unwrap().to_string();
+    assert_eq!(
+        handle_args_with_prompt(std::slice::from_ref(&script), || {}).unwrap_err(),
+        70
+    );
+    let args = vec![String::from("--tail-calls"), script];
+    assert!(handle_args_with_prompt(&args, || {}).is_ok());
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn warnings_flag_ignores_warnings_or_raises_them() {
+    let mut path = env::temp_dir();
+    path.push(format!("oxython_test_{}_warnings.py", std::process::id()));
+    fs::write(&path, "import warnings\nwarnings.warn('careful')").unwrap();
+
+    let script = path.to_str().unwrap().to_string();
+    for (flag, status) in [
+        ("--warnings=ignore", Ok(())),
+        ("--warnings=default", Ok(())),
+        ("--warnings=error", Err(70)),
+        ("--warnings=loud", Err(64)),
+    ] {
+        let args = vec![flag.to_string(), script.clone()];
+        assert_eq!(handle_args_with_prompt(&args, || {}), status, "{}", flag);
+    }
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn uncaught_keyboard_interrupt_exits_as_ctrl_c_does() {
+    let mut path = env::temp_dir();
+    path.push(format!("oxython_test_{}_interrupt.py", std::process::id()));
+    fs::write(&path, "raise KeyboardInterrupt").unwrap();
+
+    let script = path.to_str().unwrap().to_string();
+    assert_eq!(run_file(&script), Err(130));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn timeout_flag_stops_scripts_that_run_too_long() {
+    let mut path = env::temp_dir();
+    path.push(format!("oxython_test_{}_timeout.py", std::process::id()));
+    fs::write(&path, "x = 0\nwhile True:\n    x = x + 1").unwrap();
+
+    let script = path.to_str().unwrap().to_string();
+    let args = vec![
+        String::from("--timeout"),
+        String::from("100ms"),
+        script.clone(),
+    ];
+    assert_eq!(handle_args_with_prompt(&args, || {}), Err(124));
+    for timeout in ["", "5x", "-1s"] {
+        let args = vec![
+            String::from("--timeout"),
+            timeout.to_string(),
+            script.clone(),
+        ];
+        assert_eq!(
+            handle_args_with_prompt(&args, || {}),
+            Err(64),
+            "{}",
+            timeout
+        );
+    }
+    let args = vec![String::from("--timeout")];
+    assert_eq!(handle_args_with_prompt(&args, || {}), Err(64));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn timeout_flag_stops_scripts_spinning_inside_callbacks() {
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "oxython_test_{}_timeout_key.py",
+        std::process::id()
+    ));
+    fs::write(
+        &path,
+        "def key(x):\n    while True:\n        x = x + 1\nsorted([3, 1, 2], key=key)",
+    )
+    .unwrap();
+
+    let args = vec![
+        String::from("--timeout"),
+        String::from("100ms"),
+        path.to_str().unwrap().to_string(),
+    ];
+    assert_eq!(handle_args_with_prompt(&args, || {}), Err(124));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn cache_flag_reuses_saved_bytecode_for_unchanged_source() {
+    let mut dir = env::temp_dir();
+    dir.push(format!("oxython_test_{}_cache", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    env::set_var("OXYTHON_CACHE_DIR", &dir);
+    let script = dir.with_extension("py");
+    fs::write(&script, "x = 1").unwrap();
+    let args = vec![
+        String::from("--cache"),
+        script.to_str().unwrap().to_string(),
+    ];
+
+    assert!(handle_args_with_prompt(&args, || {}).is_ok());
+    let entries: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(entries.len(), 1);
+    let cached = &entries[0];
+    assert_eq!(cached.extension().unwrap(), "oxyc");
+
+    // A later run loads the saved bytecode instead of compiling the source
+    let failing = Compiler::compile("x = 1 + 'a'").unwrap();
+    fs::write(cached, marshal::dump(&failing).unwrap()).unwrap();
+    assert_eq!(handle_args_with_prompt(&args, || {}).unwrap_err(), 70);
+
+    // Bytecode that does not load is replaced
+    fs::write(cached, b"not bytecode").unwrap();
+    assert!(handle_args_with_prompt(&args, || {}).is_ok());
+    assert!(marshal::load(&fs::read(cached).unwrap()).is_some());
+
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_file(&script);
+}
+
+#[test]
+fn fmt_rewrites_files_and_check_reports_unformatted_ones() {
+    let mut path = env::temp_dir();
+    path.push(format!("oxython_test_{}_fmt.py", std::process::id()));
+    fs::write(&path, "def f(a,b = 2) :\n  return a+b\n").unwrap();
+    let file = path.to_str().unwrap().to_string();
+    let check = vec![String::from("fmt"), String::from("--check"), file.clone()];
+
+    assert_eq!(handle_args_with_prompt(&check, || {}).unwrap_err(), 1);
+    assert!(handle_args_with_prompt(&[String::from("fmt"), file.clone()], || {}).is_ok());
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "def f(a, b=2):\n    return a + b\n"
+    );
+    assert!(handle_args_with_prompt(&check, || {}).is_ok());
+
+    // Source that does not compile is left alone
+    fs::write(&path, "def f(:\n").unwrap();
+    assert_eq!(handle_args_with_prompt(&check, || {}).unwrap_err(), 65);
+    assert_eq!(
+        handle_args_with_prompt(&[String::from("fmt")], || {}).unwrap_err(),
+        64
+    );
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn check_reports_syntax_errors_and_lints_when_asked() {
+    let mut path = env::temp_dir();
+    path.push(format!("oxython_test_{}_check.py", std::process::id()));
+    fs::write(&path, "def f():\n    unused = 1\n").unwrap();
+    let file = path.to_str().unwrap().to_string();
+
+    assert!(handle_args_with_prompt(&[String::from("check"), file.clone()], || {}).is_ok());
+    let lint = vec![String::from("check"), String::from("--lint"), file.clone()];
+    assert_eq!(handle_args_with_prompt(&lint, || {}).unwrap_err(), 1);
+
+    let json = vec![
+        String::from("check"),
+        String::from("--error-format=json"),
+        String::from("--lint"),
+        file.clone(),
+    ];
+    assert_eq!(handle_args_with_prompt(&json, || {}).unwrap_err(), 1);
+
+    fs::write(&path, "def f(:\n").unwrap();
+    assert_eq!(handle_args_with_prompt(&lint, || {}).unwrap_err(), 65);
+    assert_eq!(handle_args_with_prompt(&json, || {}).unwrap_err(), 65);
+    let run = vec![String::from("--error-format=json"), file.clone()];
+    assert_eq!(handle_args_with_prompt(&run, || {}).unwrap_err(), 65);
+    let unknown = vec![String::from("--error-format=xml"), file.clone()];
+    assert_eq!(handle_args_with_prompt(&unknown, || {}).unwrap_err(), 64);
+    assert_eq!(
+        handle_args_with_prompt(&[String::from("check")], || {}).unwrap_err(),
+        64
+    );
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn handle_args_rejects_unknown_flags() {
+    for args in [
+        vec![String::from("--profile")],
+        vec![String::from("--trace"), String::from("script.py")],
+    ] {
+        assert_eq!(handle_args_with_prompt(&args, || {}).unwrap_err(), 64);
+    }
+}
+
 #[cfg(unix)]
 #[test]
 fn run_prompt_uses_stdin_stdout() {
@@ -319,3 +638,32 @@ where
         output
     }
 }
+
+#[test]
+fn path_flag_adds_module_directories_after_the_scripts_own() {
+    let mut root = env::temp_dir();
+    root.push(format!("oxython_test_{}_path_flag", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let (scripts, library) = (root.join("scripts"), root.join("library"));
+    fs::create_dir_all(&scripts).unwrap();
+    fs::create_dir_all(&library).unwrap();
+    fs::write(scripts.join("shadow.py"), "found_in = 'scripts'").unwrap();
+    fs::write(library.join("shadow.py"), "found_in = 'library'").unwrap();
+    fs::write(library.join("tools.py"), "answer = 42").unwrap();
+    let script = scripts.join("main.py");
+    fs::write(
+        &script,
+        "import shadow\nimport tools\nif shadow.found_in == 'library':\n    raise ImportError('wrong shadow')\n",
+    )
+    .unwrap();
+
+    let script = script.to_str().unwrap().to_string();
+    let library = library.to_str().unwrap().to_string();
+    let args = vec![String::from("--path"), library, script.clone()];
+    assert!(handle_args_with_prompt(&args, || {}).is_ok());
+    assert_eq!(run_file(&script), Err(70), "tools is only on the --path");
+    let args = vec![String::from("--path")];
+    assert_eq!(handle_args_with_prompt(&args, || {}), Err(64));
+
+    let _ = fs::remove_dir_all(&root);
+}
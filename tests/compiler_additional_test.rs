@@ -1,37 +1,18 @@
-use oxython::bytecode::{Chunk, OpCode};
+use oxython::bytecode::{slot_index, Chunk, OpCode, MAX_SLOT};
 use oxython::compiler::Compiler;
-use oxython::object::ObjectType;
+use oxython::object::{Object, ObjectType};
 
 fn opcodes(chunk: &Chunk) -> Vec<OpCode> {
-    let mut result = Vec::new();
-    let mut ip = 0;
-    while ip < chunk.code.len() {
-        let opcode = OpCode::from(chunk.code[ip]);
-        result.push(opcode);
-        ip += 1;
-        match opcode {
-            OpCode::OpConstant
-            | OpCode::OpDefineGlobal
-            | OpCode::OpGetGlobal
-            | OpCode::OpSetGlobal
-            | OpCode::OpCall
-            | OpCode::OpGetLocal
-            | OpCode::OpSetLocal
-            | OpCode::OpMakeFunction
-            | OpCode::OpGetAttr
-            | OpCode::OpSetAttr => {
-                ip += 1;
-            }
-            OpCode::OpIterNext | OpCode::OpLoop | OpCode::OpJumpIfFalse | OpCode::OpJump => {
-                ip += 2;
-            }
-            OpCode::OpZip => {
-                ip += 3;
-            }
-            _ => {}
-        }
-    }
-    result
+    chunk.instructions().map(|(_, opcode, _)| opcode).collect()
+}
+
+/// The constant loaded by the chunk's first instruction.
+fn first_constant(chunk: &Chunk) -> &Object {
+    let (_, _, operands) = chunk
+        .instructions()
+        .next()
+        .expect("Expected an instruction");
+    &chunk.constants[operands[0] as usize]
 }
 
 #[test]
@@ -47,18 +28,17 @@ fn compile_skips_unknown_tokens() {
 
 #[test]
 fn compile_emits_add_and_pop_for_expression_statement() {
-    let chunk = Compiler::compile("1 + 2").expect("Expected chunk");
+    let chunk = Compiler::compile("a + 2").expect("Expected chunk");
     assert_eq!(
         opcodes(&chunk),
         vec![
-            OpCode::OpConstant,
+            OpCode::OpGetGlobal,
             OpCode::OpConstant,
             OpCode::OpAdd,
             OpCode::OpPop,
             OpCode::OpReturn
         ]
     );
-    assert!(matches!(&*chunk.constants[0], ObjectType::Integer(1)));
     assert!(matches!(&*chunk.constants[1], ObjectType::Integer(2)));
 }
 
@@ -74,6 +54,17 @@ fn compile_function_definition_emits_prototype() {
     assert!(ops.contains(&OpCode::OpDefineGlobal));
 }
 
+#[test]
+fn compile_function_prototype_counts_local_slots() {
+    let source =
+        "def f(a):\n    if a:\n        b = 1\n    for c in a:\n        d = c\n    return a";
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    assert!(chunk.constants.iter().any(|value| matches!(
+        &**value,
+        ObjectType::FunctionPrototype(proto) if proto.local_count == 3
+    )));
+}
+
 #[test]
 fn compile_function_call_emits_call_opcode() {
     let source = "def add(a, b): return a + b\nprint(add(1, 2))";
@@ -81,13 +72,24 @@ fn compile_function_call_emits_call_opcode() {
     assert!(opcodes(&chunk).contains(&OpCode::OpCall));
 }
 
+#[test]
+fn compile_exec_stores_back_the_dict_it_binds_names_in() {
+    let chunk = Compiler::compile("g = {}\nexec('a = 1', g)").expect("Expected chunk");
+    assert!(opcodes(&chunk).contains(&OpCode::OpEvaluatedNamespace));
+
+    // A rebound exec is an ordinary call
+    let source = "def exec(source, names):\n    return names\ng = {}\nexec('a = 1', g)";
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    assert!(!opcodes(&chunk).contains(&OpCode::OpEvaluatedNamespace));
+}
+
 #[test]
 fn compile_handles_multiple_additions() {
-    let chunk = Compiler::compile("1 + 2 + 3").expect("Expected chunk");
+    let chunk = Compiler::compile("a + 2 + 3").expect("Expected chunk");
     assert_eq!(
         opcodes(&chunk),
         vec![
-            OpCode::OpConstant,
+            OpCode::OpGetGlobal,
             OpCode::OpConstant,
             OpCode::OpAdd,
             OpCode::OpConstant,
@@ -96,7 +98,6 @@ fn compile_handles_multiple_additions() {
             OpCode::OpReturn
         ]
     );
-    assert!(matches!(&*chunk.constants[0], ObjectType::Integer(1)));
     assert!(matches!(&*chunk.constants[1], ObjectType::Integer(2)));
     assert!(matches!(&*chunk.constants[2], ObjectType::Integer(3)));
 }
@@ -118,7 +119,8 @@ fn compile_handles_assignment_and_lookup() {
         .constants
         .iter()
         .any(|c| matches!(&**c, ObjectType::Integer(10))));
-    assert_eq!(chunk.constants.len(), 3);
+    // The name is stored once for both instructions
+    assert_eq!(chunk.constants.len(), 2);
 }
 
 #[test]
@@ -212,11 +214,11 @@ fn compile_handles_len_builtin() {
 
 #[test]
 fn compile_string_concatenation() {
-    let chunk = Compiler::compile("print('Ada' + ' Lovelace')").expect("Expected chunk");
+    let chunk = Compiler::compile("print(first + ' Lovelace')").expect("Expected chunk");
     assert_eq!(
         opcodes(&chunk),
         vec![
-            OpCode::OpConstant,
+            OpCode::OpGetGlobal,
             OpCode::OpConstant,
             OpCode::OpAdd,
             OpCode::OpPrint,
@@ -294,9 +296,9 @@ fn compile_handles_slice_expression() {
 #[test]
 fn compile_handles_negative_index_and_less() {
     let chunk =
-        Compiler::compile("values = [1, 2]; print(values[-1] < 3)").expect("Expected chunk");
+        Compiler::compile("i = 1; values = [1, 2]; print(values[-i] < 3)").expect("Expected chunk");
     let ops = opcodes(&chunk);
-    assert!(ops.iter().any(|op| matches!(op, OpCode::OpSubtract)));
+    assert!(ops.iter().any(|op| matches!(op, OpCode::OpNegate)));
     assert!(ops.iter().any(|op| matches!(op, OpCode::OpLess)));
 }
 
@@ -356,8 +358,9 @@ fn compile_errors_on_invalid_slice_syntax() {
 }
 
 #[test]
-fn compile_errors_on_list_literal_with_expression() {
-    assert!(Compiler::compile("items = [foo]").is_none());
+fn compile_builds_list_literal_with_expression() {
+    let chunk = Compiler::compile("items = [foo]").expect("Expected chunk");
+    assert!(opcodes(&chunk).contains(&OpCode::OpBuildList));
 }
 
 #[test]
@@ -415,9 +418,58 @@ fn compile_errors_on_malformed_range_call_missing_rparen() {
 }
 
 #[test]
-fn compile_errors_on_invalid_append_on_indexed_value() {
-    // Hardcoded append only works on variables, not indexed values
-    assert!(Compiler::compile("items = [[1, 2]]; items[0].append(3)").is_none());
+fn compile_append_to_an_indexed_value_stores_the_list_back() {
+    let chunk = Compiler::compile("items = [[1, 2]]; items[0].append(3)").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    let append = ops.iter().position(|op| *op == OpCode::OpAppend).unwrap();
+    assert_eq!(
+        ops[append..append + 8],
+        [
+            OpCode::OpAppend,
+            OpCode::OpDup,
+            OpCode::OpGetGlobal,
+            OpCode::OpSwap,
+            OpCode::OpConstant,
+            OpCode::OpSwap,
+            OpCode::OpSetIndex,
+            OpCode::OpDefineGlobal,
+        ]
+    );
+}
+
+#[test]
+fn compile_append_to_an_attribute_stores_the_list_back() {
+    let chunk = Compiler::compile("c = 1\nc.items.append(3)").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    let append = ops.iter().position(|op| *op == OpCode::OpAppend).unwrap();
+    assert_eq!(
+        ops[append..append + 5],
+        [
+            OpCode::OpAppend,
+            OpCode::OpDup,
+            OpCode::OpGetGlobal,
+            OpCode::OpSwap,
+            OpCode::OpSetAttr,
+        ]
+    );
+}
+
+#[test]
+fn compile_keyword_unpacking_calls_with_mappings() {
+    let chunk = Compiler::compile("f(1, **d)").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(ops.contains(&OpCode::OpCallExKw));
+    assert!(!ops.contains(&OpCode::OpCallEx));
+    let chunk = Compiler::compile("f(*a)").expect("Expected chunk");
+    assert!(!opcodes(&chunk).contains(&OpCode::OpCallExKw));
+}
+
+#[test]
+fn compile_errors_on_positional_argument_after_keywords() {
+    assert!(Compiler::compile("f(**d, 1)").is_none());
+    assert!(Compiler::compile("f(a=1, 2)").is_none());
+    assert!(Compiler::compile("f(**d, *b)").is_none());
+    assert!(Compiler::compile("f(a=1, *b)").is_some());
 }
 
 #[test]
@@ -442,13 +494,15 @@ fn compile_errors_on_dict_literal_missing_colon() {
 }
 
 #[test]
-fn compile_errors_on_dict_literal_invalid_value() {
-    assert!(Compiler::compile("d = {'key': foo}").is_none());
+fn compile_builds_dict_literal_with_expression_value() {
+    let chunk = Compiler::compile("d = {'key': foo}").expect("Expected chunk");
+    assert!(opcodes(&chunk).contains(&OpCode::OpBuildDict));
 }
 
 #[test]
-fn compile_errors_on_dict_literal_invalid_key() {
-    assert!(Compiler::compile("d = {123: 'value'}").is_none());
+fn compile_leaves_unhashable_dict_literal_key_to_runtime() {
+    let chunk = Compiler::compile("d = {[1]: 'value'}").expect("Expected chunk");
+    assert!(opcodes(&chunk).contains(&OpCode::OpBuildDict));
 }
 
 #[test]
@@ -480,11 +534,11 @@ fn compile_handles_if_without_else() {
 
 #[test]
 fn compile_handles_subtract_operator() {
-    let chunk = Compiler::compile("print(10 - 3)").expect("Expected chunk");
+    let chunk = Compiler::compile("print(n - 3)").expect("Expected chunk");
     assert_eq!(
         opcodes(&chunk),
         vec![
-            OpCode::OpConstant,
+            OpCode::OpGetGlobal,
             OpCode::OpConstant,
             OpCode::OpSubtract,
             OpCode::OpPrint,
@@ -496,11 +550,11 @@ fn compile_handles_subtract_operator() {
 
 #[test]
 fn compile_handles_multiply_operator() {
-    let chunk = Compiler::compile("print(3 * 4)").expect("Expected chunk");
+    let chunk = Compiler::compile("print(n * 4)").expect("Expected chunk");
     assert_eq!(
         opcodes(&chunk),
         vec![
-            OpCode::OpConstant,
+            OpCode::OpGetGlobal,
             OpCode::OpConstant,
             OpCode::OpMultiply,
             OpCode::OpPrint,
@@ -512,11 +566,11 @@ fn compile_handles_multiply_operator() {
 
 #[test]
 fn compile_handles_divide_operator() {
-    let chunk = Compiler::compile("print(10 / 2)").expect("Expected chunk");
+    let chunk = Compiler::compile("print(n / 2)").expect("Expected chunk");
     assert_eq!(
         opcodes(&chunk),
         vec![
-            OpCode::OpConstant,
+            OpCode::OpGetGlobal,
             OpCode::OpConstant,
             OpCode::OpDivide,
             OpCode::OpPrint,
@@ -570,9 +624,9 @@ fn compile_handles_dict_literal_with_duplicate_keys() {
 
 #[test]
 fn compile_handles_unary_minus() {
-    let chunk = Compiler::compile("print(-5)").expect("Expected chunk");
+    let chunk = Compiler::compile("print(-n)").expect("Expected chunk");
     let ops = opcodes(&chunk);
-    assert!(ops.iter().any(|op| matches!(op, OpCode::OpSubtract)));
+    assert!(ops.iter().any(|op| matches!(op, OpCode::OpNegate)));
 }
 
 #[test]
@@ -662,18 +716,27 @@ fn compile_errors_on_for_loop_missing_identifier() {
 }
 
 #[test]
-fn compile_errors_on_len_missing_expression() {
-    assert!(Compiler::compile("len()").is_none());
+fn compile_len_missing_expression_fails_at_runtime() {
+    use oxython::vm::{InterpretResult, VM};
+    let chunk = Compiler::compile("len()").expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
 }
 
 #[test]
-fn compile_errors_on_round_missing_expression() {
-    assert!(Compiler::compile("round()").is_none());
+fn compile_round_missing_expression_fails_at_runtime() {
+    use oxython::vm::{InterpretResult, VM};
+    let chunk = Compiler::compile("round()").expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
 }
 
 #[test]
-fn compile_errors_on_range_missing_expression() {
-    assert!(Compiler::compile("range()").is_none());
+fn compile_range_missing_expression_fails_at_runtime() {
+    use oxython::vm::{InterpretResult, VM};
+    let chunk = Compiler::compile("range()").expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
 }
 
 #[test]
@@ -725,12 +788,12 @@ fn compile_errors_on_unary_minus_with_invalid_token() {
 
 #[test]
 fn compile_handles_nested_unary_minus() {
-    let chunk = Compiler::compile("print(--5)").expect("Expected chunk");
+    let chunk = Compiler::compile("print(--n)").expect("Expected chunk");
     let ops = opcodes(&chunk);
-    // Double negation results in subtraction operations
+    // Each minus emits its own negation
     assert!(
         ops.iter()
-            .filter(|&op| matches!(op, OpCode::OpSubtract))
+            .filter(|&op| matches!(op, OpCode::OpNegate))
             .count()
             >= 2
     );
@@ -794,7 +857,7 @@ fn compile_handles_nested_for_loops() {
 
 #[test]
 fn compile_handles_complex_expression() {
-    let chunk = Compiler::compile("result = 1 + 2 * 3 - 4 / 2").expect("Expected chunk");
+    let chunk = Compiler::compile("result = n + 2 * 3 - 4 / 2").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpAdd));
     assert!(ops.contains(&OpCode::OpMultiply));
@@ -863,7 +926,7 @@ fn compile_errors_on_mismatched_brackets() {
 
 #[test]
 fn compile_handles_comparison_in_expression() {
-    let chunk = Compiler::compile("result = 5 < 10").expect("Expected chunk");
+    let chunk = Compiler::compile("result = n < 10").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpLess));
 }
@@ -892,8 +955,8 @@ fn compile_errors_on_identifier_with_dot_no_assignment() {
 
 #[test]
 fn compile_errors_on_identifier_with_comma() {
-    // Line 106: Comma after identifier at bracket_depth 0
-    assert!(Compiler::compile("x, y = 1").is_none());
+    // Unpacking assignment is now supported with OpUnpack
+    assert!(Compiler::compile("x, y = 1").is_some());
 }
 
 #[test]
@@ -1090,9 +1153,10 @@ fn compile_errors_on_list_literal_no_rbracket() {
 }
 
 #[test]
-fn compile_errors_on_dict_literal_key_not_string() {
-    // Dict key is not a string
-    assert!(Compiler::compile("{123: 'value'}").is_none());
+fn compile_accepts_dict_literal_key_not_string() {
+    // Any hashable constant key folds into a constant dict
+    let chunk = Compiler::compile("{123: 'value'}").expect("Expected chunk");
+    assert!(!opcodes(&chunk).contains(&OpCode::OpBuildDict));
 }
 
 #[test]
@@ -1266,7 +1330,7 @@ fn compile_errors_on_parse_term_invalid_token() {
 #[test]
 fn compile_handles_less_than_comparison() {
     // Exercise less than operation
-    let chunk = Compiler::compile("x = 1 < 2").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n < 2").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpLess));
 }
@@ -1282,7 +1346,7 @@ fn compile_handles_in_operator_with_dict() {
 #[test]
 fn compile_handles_subtraction() {
     // Exercise subtraction
-    let chunk = Compiler::compile("x = 5 - 3").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n - 3").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpSubtract));
 }
@@ -1290,7 +1354,7 @@ fn compile_handles_subtraction() {
 #[test]
 fn compile_handles_division() {
     // Exercise division
-    let chunk = Compiler::compile("x = 10 / 2").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n / 2").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpDivide));
 }
@@ -1536,7 +1600,7 @@ fn compile_parse_expression_no_token_returns_false() {
 #[test]
 fn compile_binary_plus_operator() {
     // Lines 287, 288: Plus operator in expression
-    let chunk = Compiler::compile("x = 1 + 2").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n + 2").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpAdd));
 }
@@ -1544,7 +1608,7 @@ fn compile_binary_plus_operator() {
 #[test]
 fn compile_binary_slash_operator() {
     // Lines 287, 289: Slash operator
-    let chunk = Compiler::compile("x = 6 / 3").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n / 3").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpDivide));
 }
@@ -1552,7 +1616,7 @@ fn compile_binary_slash_operator() {
 #[test]
 fn compile_binary_star_operator() {
     // Lines 287, 290: Star operator
-    let chunk = Compiler::compile("x = 3 * 4").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n * 4").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpMultiply));
 }
@@ -1560,7 +1624,7 @@ fn compile_binary_star_operator() {
 #[test]
 fn compile_binary_minus_operator() {
     // Lines 287, 291: Minus operator
-    let chunk = Compiler::compile("x = 7 - 2").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n - 2").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpSubtract));
 }
@@ -1568,7 +1632,7 @@ fn compile_binary_minus_operator() {
 #[test]
 fn compile_less_operator_in_expression() {
     // Lines 287, 292: Less operator
-    let chunk = Compiler::compile("x = 2 < 5").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n < 5").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpLess));
 }
@@ -1584,19 +1648,19 @@ fn compile_in_operator_in_expression() {
 #[test]
 fn compile_unary_minus_line_325() {
     // Line 325: Unary minus in parse_term
-    let chunk = Compiler::compile("x = -5").expect("Expected chunk");
+    let chunk = Compiler::compile("x = -n").expect("Expected chunk");
     let ops = opcodes(&chunk);
-    assert!(ops.contains(&OpCode::OpSubtract));
+    assert!(ops.contains(&OpCode::OpNegate));
 }
 
 #[test]
 fn compile_unary_minus_recursive() {
     // Line 331: Recursive parse_term for unary minus
-    let chunk = Compiler::compile("x = --10").expect("Expected chunk");
+    let chunk = Compiler::compile("x = --n").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(
         ops.iter()
-            .filter(|op| matches!(op, OpCode::OpSubtract))
+            .filter(|op| matches!(op, OpCode::OpNegate))
             .count()
             >= 2
     );
@@ -1882,7 +1946,7 @@ fn compile_line_280_parse_term_fail() {
 #[test]
 fn compile_line_287_expression_operator() {
     // Line 287: Operator in expression loop
-    let chunk = Compiler::compile("x = 1 + 2").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n + 2").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpAdd));
 }
@@ -1890,7 +1954,7 @@ fn compile_line_287_expression_operator() {
 #[test]
 fn compile_line_299_300_consume_operator() {
     // Lines 299-300: Consume operator and parse term
-    let chunk = Compiler::compile("x = 3 * 4").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n * 4").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpMultiply));
 }
@@ -1898,7 +1962,7 @@ fn compile_line_299_300_consume_operator() {
 #[test]
 fn compile_line_302_parse_term_in_expression() {
     // Line 302: parse_term in expression
-    let chunk = Compiler::compile("x = 5 - 1").expect("Expected chunk");
+    let chunk = Compiler::compile("x = n - 1").expect("Expected chunk");
     let ops = opcodes(&chunk);
     assert!(ops.contains(&OpCode::OpSubtract));
 }
@@ -1918,9 +1982,9 @@ fn compile_line_317_operands_zero() {
 #[test]
 fn compile_line_325_unary_minus() {
     // Line 325: Unary minus in parse_term
-    let chunk = Compiler::compile("x = -10").expect("Expected chunk");
+    let chunk = Compiler::compile("x = -n").expect("Expected chunk");
     let ops = opcodes(&chunk);
-    assert!(ops.contains(&OpCode::OpSubtract));
+    assert!(ops.contains(&OpCode::OpNegate));
 }
 
 #[test]
@@ -2160,9 +2224,9 @@ fn compile_line_558_559_list_literal_float() {
 }
 
 #[test]
-fn compile_line_565_568_list_literal_identifier_error() {
-    // Lines 565, 568: Identifier in list literal (not allowed)
-    assert!(Compiler::compile("[x]").is_none());
+fn compile_list_literal_with_identifier_builds_at_runtime() {
+    let chunk = Compiler::compile("[x]").expect("Expected chunk");
+    assert!(opcodes(&chunk).contains(&OpCode::OpBuildList));
 }
 
 #[test]
@@ -2187,7 +2251,9 @@ fn compile_line_593_595_dict_literal_string_key() {
     let chunk = Compiler::compile("x = {\"name\": \"value\"}").expect("Expected chunk");
     assert!(chunk.constants.iter().any(|c| {
         if let ObjectType::Dict(entries) = &**c {
-            entries.iter().any(|(k, _)| k == "name")
+            entries
+                .iter()
+                .any(|(k, _)| **k == ObjectType::String("name".to_string()))
         } else {
             false
         }
@@ -2206,7 +2272,7 @@ fn compile_line_614_dict_literal_duplicate_key() {
     let chunk = Compiler::compile("x = {\"k\": 1, \"k\": 2}").expect("Expected chunk");
     assert!(chunk.constants.iter().any(|c| {
         if let ObjectType::Dict(entries) = &**c {
-            entries.len() == 1 && entries[0].0 == "k"
+            entries.len() == 1 && *entries[0].0 == ObjectType::String("k".to_string())
         } else {
             false
         }
@@ -2214,9 +2280,18 @@ fn compile_line_614_dict_literal_duplicate_key() {
 }
 
 #[test]
-fn compile_line_619_dict_literal_invalid_key() {
-    // Line 619: Invalid key type in dict literal
-    assert!(Compiler::compile("{123: \"value\"}").is_none());
+fn compile_dict_literal_merges_equal_number_keys() {
+    let chunk =
+        Compiler::compile("x = {1: \"a\", 1.0: \"b\", True: \"c\"}").expect("Expected chunk");
+    assert!(chunk.constants.iter().any(|c| {
+        if let ObjectType::Dict(entries) = &**c {
+            entries.len() == 1
+                && *entries[0].0 == ObjectType::Integer(1)
+                && *entries[0].1 == ObjectType::String("c".to_string())
+        } else {
+            false
+        }
+    }));
 }
 
 #[test]
@@ -2376,7 +2451,7 @@ fn integration_dict_string_lookup() {
 #[test]
 fn integration_int_int_addition() {
     use oxython::vm::VM;
-    let chunk = Compiler::compile("x = 5 + 10").expect("Expected chunk");
+    let chunk = Compiler::compile("n = 5; x = n + 10").expect("Expected chunk");
     let mut vm = VM::new();
     vm.interpret(chunk);
 }
@@ -2384,7 +2459,7 @@ fn integration_int_int_addition() {
 #[test]
 fn integration_int_int_subtraction() {
     use oxython::vm::VM;
-    let chunk = Compiler::compile("x = 10 - 5").expect("Expected chunk");
+    let chunk = Compiler::compile("n = 10; x = n - 5").expect("Expected chunk");
     let mut vm = VM::new();
     vm.interpret(chunk);
 }
@@ -2392,7 +2467,601 @@ fn integration_int_int_subtraction() {
 #[test]
 fn integration_division_result_push() {
     use oxython::vm::VM;
-    let chunk = Compiler::compile("x = 10 / 2").expect("Expected chunk");
+    let chunk = Compiler::compile("n = 10; x = n / 2").expect("Expected chunk");
     let mut vm = VM::new();
     vm.interpret(chunk);
 }
+
+#[test]
+fn compile_aliased_len_uses_generic_call() {
+    let chunk = Compiler::compile("f = len; x = len([1, 2])").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(!ops.contains(&OpCode::OpLen));
+    assert!(ops.contains(&OpCode::OpCall));
+}
+
+#[test]
+fn compile_list_constructor_is_a_builtin_call() {
+    let chunk = Compiler::compile("x = list('ab')").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(!ops.contains(&OpCode::OpToList));
+    assert!(ops.contains(&OpCode::OpCall));
+}
+
+#[test]
+fn compile_generator_expression_argument_is_a_single_argument() {
+    let chunk = Compiler::compile("s = ', '.join(str(x) for x in [1, 2])").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    let make = ops
+        .iter()
+        .position(|op| *op == OpCode::OpMakeGenerator)
+        .expect("Expected OpMakeGenerator");
+    assert_eq!(ops[make - 1], OpCode::OpMakeFunction);
+    assert_eq!(ops[make + 1], OpCode::OpCall);
+    assert!(!ops.contains(&OpCode::OpStrJoin));
+}
+
+#[test]
+fn compile_builtin_with_other_arity_uses_generic_call() {
+    let chunk = Compiler::compile("x = range(5); y = round(2.5)").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(!ops.contains(&OpCode::OpRange));
+    assert!(!ops.contains(&OpCode::OpRound));
+    assert_eq!(ops.iter().filter(|op| **op == OpCode::OpCall).count(), 2);
+}
+
+#[test]
+fn compile_del_statement_emits_delete_global() {
+    let chunk = Compiler::compile("x = 1; y = 2; del x, y").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert_eq!(
+        ops.iter()
+            .filter(|op| **op == OpCode::OpDeleteGlobal)
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn compile_slice_targets_use_slice_opcodes() {
+    let chunk = Compiler::compile("x = [1, 2, 3]\nx[1:] = [4]\ndel x[::2]\ndel x[0]")
+        .expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(ops.contains(&OpCode::OpSetSlice));
+    assert!(ops.contains(&OpCode::OpDeleteSlice));
+    assert!(ops.contains(&OpCode::OpDeleteIndex));
+    assert!(!ops.contains(&OpCode::OpSetIndex));
+    assert!(Compiler::compile("x = [1]\nx[0:1] += [2]").is_none());
+}
+
+#[test]
+fn compile_del_of_local_is_rejected() {
+    assert!(Compiler::compile("def f():\n    x = 1\n    del x\n").is_none());
+}
+
+#[test]
+fn compile_folds_constant_arithmetic() {
+    let chunk = Compiler::compile("x = 2 * 3600 + 30").expect("Expected chunk");
+    assert_eq!(
+        opcodes(&chunk),
+        vec![OpCode::OpConstant, OpCode::OpDefineGlobal, OpCode::OpReturn]
+    );
+    let value = first_constant(&chunk);
+    assert!(matches!(&**value, ObjectType::Integer(7230)));
+    // Only the folded value and the global's name remain in the pool
+    assert_eq!(chunk.constants.len(), 2);
+}
+
+#[test]
+fn compile_folds_string_concatenation_and_unary_minus() {
+    let chunk = Compiler::compile("print('Ada' + ' Lovelace')").expect("Expected chunk");
+    assert_eq!(
+        opcodes(&chunk),
+        vec![
+            OpCode::OpConstant,
+            OpCode::OpPrint,
+            OpCode::OpPrintln,
+            OpCode::OpReturn
+        ]
+    );
+    let value = first_constant(&chunk);
+    assert!(matches!(&**value, ObjectType::String(s) if s == "Ada Lovelace"));
+
+    let chunk = Compiler::compile("x = -2.5 * 2").expect("Expected chunk");
+    assert!(opcodes(&chunk).starts_with(&[OpCode::OpConstant, OpCode::OpDefineGlobal]));
+    let value = first_constant(&chunk);
+    assert!(matches!(&**value, ObjectType::Float(f) if *f == -5.0));
+}
+
+#[test]
+fn compile_leaves_failing_constant_operations_for_runtime() {
+    let chunk = Compiler::compile("x = 1 / 0").expect("Expected chunk");
+    assert!(opcodes(&chunk).contains(&OpCode::OpDivide));
+
+    let chunk = Compiler::compile("x = 'a' - 1").expect("Expected chunk");
+    assert!(opcodes(&chunk).contains(&OpCode::OpSubtract));
+}
+
+#[test]
+fn compile_folds_constant_container_literals() {
+    let chunk = Compiler::compile("x = [-1, [2, 'a'], {'k': -3}]").expect("Expected chunk");
+    assert_eq!(
+        opcodes(&chunk),
+        vec![OpCode::OpConstant, OpCode::OpDefineGlobal, OpCode::OpReturn]
+    );
+    let value = first_constant(&chunk);
+    let ObjectType::List(elements) = &**value else {
+        panic!("Expected list constant");
+    };
+    assert_eq!(*elements[0], ObjectType::Integer(-1));
+    assert!(matches!(&*elements[1], ObjectType::List(items) if items.len() == 2));
+    assert!(
+        matches!(&*elements[2], ObjectType::Dict(entries) if *entries[0].1 == ObjectType::Integer(-3))
+    );
+}
+
+#[test]
+fn compile_builds_containers_from_expressions() {
+    let chunk = Compiler::compile("x = [a, b + 1]").expect("Expected chunk");
+    assert_eq!(
+        opcodes(&chunk),
+        vec![
+            OpCode::OpGetGlobal,
+            OpCode::OpGetGlobal,
+            OpCode::OpConstant,
+            OpCode::OpAdd,
+            OpCode::OpBuildList,
+            OpCode::OpDefineGlobal,
+            OpCode::OpReturn
+        ]
+    );
+
+    let chunk = Compiler::compile("d = {'a': compute(), 'b': 2}").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(ops.contains(&OpCode::OpCall));
+    assert!(ops.contains(&OpCode::OpBuildDict));
+}
+
+#[test]
+fn compile_reports_multiple_syntax_errors() {
+    let source = "x = )\ny = 1\ndef f(a):\n    b = ]\n    return a\nz = ]; w = 2\nv = @\n";
+    let errors = Compiler::compile_with_errors(source, "<script>").unwrap_err();
+    let positions: Vec<(usize, usize)> = errors.iter().map(|e| (e.line, e.column)).collect();
+    assert_eq!(positions, vec![(1, 5), (4, 9), (6, 5), (7, 5)]);
+    assert_eq!(
+        errors[0].to_string(),
+        "SyntaxError: invalid syntax (line 1, column 5)"
+    );
+}
+
+#[test]
+fn compile_recovers_at_dedent_after_failed_block_header() {
+    let source = "def f(:\n    return 1\nprint(1)\nif x ==:\n    y = 1\nz = )\n";
+    let errors = Compiler::compile_with_errors(source, "<script>").unwrap_err();
+    let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, vec![1, 4, 6]);
+
+    let errors = Compiler::compile_with_errors("a = 1 $ 2", "<script>").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(Compiler::compile_with_errors("a = 1\nb = a", "<script>").is_ok());
+}
+
+#[test]
+fn compile_reports_too_many_constants() {
+    let source: String = (0..300).map(|i| format!("v{} = {}\n", i, i)).collect();
+    let chunk = Compiler::compile(&source).expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(ops.contains(&OpCode::OpConstantLong));
+    assert!(ops.contains(&OpCode::OpDefineGlobalLong));
+
+    // Past the reach of two-byte operands the error is reported once, not
+    // again for every statement after it
+    let items: Vec<String> = (0..70_000).map(|i| i.to_string()).collect();
+    let source = format!("x = [{}]\ny = 1.5\nz = 'more'\n", items.join(", "));
+    let errors = Compiler::compile_with_errors(&source, "<script>").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, 1);
+    assert_eq!(errors[0].message, "too many constants in one chunk");
+}
+
+#[test]
+fn compile_does_not_fold_overflowing_integer_arithmetic() {
+    let chunk = Compiler::compile("x = 9223372036854775807 + 1").expect("Expected chunk");
+    assert!(opcodes(&chunk).contains(&OpCode::OpAdd));
+}
+
+#[test]
+fn compile_limits_nesting_depth() {
+    let deep = format!("x = {}", "[".repeat(50_000));
+    let errors = Compiler::compile_with_errors(&deep, "<script>").unwrap_err();
+    assert_eq!(errors[0].message, "too many nested expressions or blocks");
+
+    let nested_lists = format!("x = {}1{}", "[".repeat(150), "]".repeat(150));
+    assert!(Compiler::compile(&nested_lists).is_some());
+
+    let nested_blocks: String = (0..150)
+        .map(|depth| format!("{}if True:\n", " ".repeat(depth)))
+        .chain(std::iter::once(format!("{}x = 1\n", " ".repeat(150))))
+        .collect();
+    assert!(Compiler::compile(&nested_blocks).is_some());
+}
+
+#[test]
+fn compile_unpacking_emits_unpack_with_starred_position() {
+    let chunk = Compiler::compile("a, *b, c = [1, 2, 3]").expect("Expected chunk");
+    let (_, _, operands) = chunk
+        .instructions()
+        .find(|(_, opcode, _)| *opcode == OpCode::OpUnpack)
+        .expect("Expected OpUnpack");
+    assert_eq!(operands, &[3, 2]);
+
+    for (source, message) in [
+        ("*a, *b = [1]", "multiple starred expressions in assignment"),
+        (
+            "*a = [1]",
+            "starred assignment target must be in a list or tuple",
+        ),
+    ] {
+        let errors = Compiler::compile_with_errors(source, "<script>").unwrap_err();
+        assert_eq!(errors[0].message, message);
+    }
+}
+
+#[test]
+fn compile_reports_malformed_number_literals() {
+    for (source, message) in [
+        ("x = 0b102", "invalid binary literal"),
+        ("x = 0o8", "invalid octal literal"),
+        ("x = 0xG", "invalid hexadecimal literal"),
+        ("x = 1 + 1__0", "invalid decimal literal"),
+        ("x = -1e", "invalid decimal literal"),
+        (
+            "x = 007",
+            "leading zeros in decimal integer literals are not permitted",
+        ),
+        ("x = 99999999999999999999", "integer literal too large"),
+    ] {
+        let errors = Compiler::compile_with_errors(source, "<script>").unwrap_err();
+        assert_eq!(errors[0].message, message, "{}", source);
+    }
+}
+
+#[test]
+fn compile_number_literal_constants() {
+    let chunk = Compiler::compile("x = 0xFF + 1_000\ny = 1e-3").expect("Expected chunk");
+    assert!(chunk
+        .constants
+        .iter()
+        .any(|c| matches!(&**c, ObjectType::Integer(1255))));
+    assert!(chunk
+        .constants
+        .iter()
+        .any(|c| matches!(&**c, ObjectType::Float(f) if *f == 0.001)));
+}
+
+#[test]
+fn compile_adjacent_string_literals_into_one_constant() {
+    let chunk = Compiler::compile("x = ('a' \"b\"\n     'c')\n'd'").expect("Expected chunk");
+    let strings: Vec<String> = chunk
+        .constants
+        .iter()
+        .filter_map(|c| match &**c {
+            ObjectType::String(text) if text != "x" => Some(text.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(strings, vec!["abc", "d"]);
+    assert!(!opcodes(&chunk).contains(&OpCode::OpAdd));
+
+    let chunk = Compiler::compile("name = 'n'\ny = 'a' f'{name}' 'b' 'c'").expect("Expected chunk");
+    assert!(chunk
+        .constants
+        .iter()
+        .any(|c| matches!(&**c, ObjectType::String(text) if text == "bc")));
+}
+
+#[test]
+fn compile_f_string_expressions_track_nested_brackets_and_quotes() {
+    let chunk =
+        Compiler::compile("d = {'}': 1}\nx = f\"{d['}']} {[1, 2][0]}\"").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert_eq!(
+        ops.iter()
+            .filter(|op| **op == OpCode::OpFormatValue)
+            .count(),
+        2
+    );
+
+    for source in ["f'{}'", "f'{x'", "f'{x y}'", "f'a}'", "f'{[1}'"] {
+        assert!(Compiler::compile(source).is_none(), "{source} compiled");
+    }
+}
+
+#[test]
+fn compile_conditional_expressions_stop_at_the_end_of_the_line() {
+    let chunk = Compiler::compile("x = 1 if y else 2").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(ops.contains(&OpCode::OpJumpIfFalse));
+    assert!(ops.contains(&OpCode::OpJump));
+
+    assert!(Compiler::compile("x = 1\nif x:\n    x = 2").is_some());
+    assert!(Compiler::compile("x = 1 if y").is_none());
+}
+
+#[test]
+fn compile_backslash_continues_a_logical_line() {
+    let source = "x = 'a'\ny = x \\\n    .upper() \\\n    .lower()\nif y:\n    y = 1";
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert!(ops.contains(&OpCode::OpStrUpper));
+    assert!(ops.contains(&OpCode::OpStrLower));
+
+    assert!(Compiler::compile("x = 1 + \\\n2").is_some());
+    assert!(Compiler::compile("x = 1 + \\ # comment\n2").is_none());
+    assert!(Compiler::compile("x = 1\n    y = 2").is_none());
+}
+
+#[test]
+fn compile_augmented_attribute_assignment_evaluates_the_receiver_once() {
+    let chunk = Compiler::compile("c = 1\nc.count += 1").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    let dup = ops.iter().position(|op| *op == OpCode::OpDup).unwrap();
+    assert_eq!(
+        ops[dup..dup + 5],
+        [
+            OpCode::OpDup,
+            OpCode::OpGetAttr,
+            OpCode::OpConstant,
+            OpCode::OpAdd,
+            OpCode::OpSetAttr,
+        ]
+    );
+}
+
+#[test]
+fn compile_negated_comparisons_emit_not_after_the_comparison() {
+    let chunk = Compiler::compile("x = 1\ny = [x]\nx not in y").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    let contains = ops.iter().position(|op| *op == OpCode::OpContains).unwrap();
+    assert_eq!(ops[contains + 1], OpCode::OpNot);
+
+    let chunk = Compiler::compile("x = 1\nx is not None").expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    let is = ops.iter().position(|op| *op == OpCode::OpIs).unwrap();
+    assert_eq!(ops[is + 1], OpCode::OpNot);
+
+    assert!(Compiler::compile("x = 1\nx is").is_none());
+}
+
+#[test]
+fn compile_import_statements() {
+    let chunk = Compiler::compile("from re import match as m, sub").expect("Expected chunk");
+    assert_eq!(
+        opcodes(&chunk),
+        vec![
+            OpCode::OpImport,
+            OpCode::OpImportFrom,
+            OpCode::OpDefineGlobal,
+            OpCode::OpImport,
+            OpCode::OpImportFrom,
+            OpCode::OpDefineGlobal,
+            OpCode::OpReturn,
+        ]
+    );
+
+    // `import package.module` imports the module, then binds the package
+    let chunk = Compiler::compile("import os.path").expect("Expected chunk");
+    assert_eq!(
+        opcodes(&chunk),
+        vec![
+            OpCode::OpImport,
+            OpCode::OpPop,
+            OpCode::OpImport,
+            OpCode::OpDefineGlobal,
+            OpCode::OpReturn,
+        ]
+    );
+    let names: Vec<String> = chunk.constants.iter().map(|c| c.to_string()).collect();
+    assert_eq!(names, ["os.path", "os"]);
+
+    let chunk = Compiler::compile("from ..pkg.sub import name").expect("Expected chunk");
+    assert!(chunk.constants.iter().any(|c| c.to_string() == "..pkg.sub"));
+    let chunk = Compiler::compile("from . import name").expect("Expected chunk");
+    assert!(chunk.constants.iter().any(|c| c.to_string() == "."));
+
+    for (source, message) in [
+        ("import", "expected a module name after 'import'"),
+        ("import re as", "expected a name after 'as'"),
+        ("from re match", "expected 'import'"),
+        ("import os.", "expected a module name after '.'"),
+        ("from import name", "expected a module name after 'from'"),
+    ] {
+        let errors = Compiler::compile_with_errors(source, "<script>").unwrap_err();
+        assert_eq!(errors[0].message, message, "{}", source);
+    }
+}
+
+#[test]
+fn compile_try_statements() {
+    let source = "try:\n    x = 1\nexcept ValueError:\n    x = 2\nfinally:\n    x = 3";
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert_eq!(ops[0], OpCode::OpSetupFinally);
+    assert_eq!(ops[1], OpCode::OpSetupExcept);
+    for op in [
+        OpCode::OpPopTry,
+        OpCode::OpExceptMatch,
+        OpCode::OpEndExcept,
+        OpCode::OpReraise,
+        OpCode::OpEnterFinally,
+        OpCode::OpEndFinally,
+    ] {
+        assert!(ops.contains(&op), "missing {:?}", op);
+    }
+
+    // break leaves the handler and runs its own copy of the finally suite
+    let chunk =
+        Compiler::compile("for i in []:\n    try:\n        break\n    finally:\n        print(i)")
+            .expect("Expected chunk");
+    let ops = opcodes(&chunk);
+    assert_eq!(ops.iter().filter(|op| **op == OpCode::OpPrint).count(), 2);
+
+    for (source, message) in [
+        (
+            "try:\n    x = 1\nx = 2",
+            "expected 'except' or 'finally' block",
+        ),
+        (
+            "try:\n    x = 1\nexcept:\n    x = 2\nexcept ValueError:\n    x = 3",
+            "default 'except:' must be last",
+        ),
+        ("continue", "'continue' not properly in loop"),
+    ] {
+        let errors = Compiler::compile_with_errors(source, "<script>").unwrap_err();
+        assert_eq!(errors[0].message, message, "{}", source);
+    }
+}
+
+#[test]
+fn compile_reports_indentation_errors() {
+    for (source, error) in [
+        (
+            "x = 1\n    y = 2\n    z = 3\nw = 4",
+            "IndentationError: unexpected indent (line 2, column 5)",
+        ),
+        (
+            "if x:\n    y = 1\n  z = 2",
+            "IndentationError: unindent does not match any outer indentation level (line 3, column 3)",
+        ),
+        (
+            "if x:\n\ty = 1\n        z = 2",
+            "TabError: inconsistent use of tabs and spaces in indentation (line 3, column 9)",
+        ),
+        (
+            "while x:\ny = 1",
+            "IndentationError: expected an indented block (line 1, column 8)",
+        ),
+        (
+            "if x: y = 1\n    z = 2",
+            "IndentationError: unexpected indent (line 2, column 5)",
+        ),
+    ] {
+        let errors = Compiler::compile_with_errors(source, "<script>").unwrap_err();
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec![error], "{}", source);
+    }
+
+    // Tabs advance to the next multiple of eight columns, and bracketed
+    // continuation lines are not indentation
+    for source in [
+        "if x:\n\ty = 1\n\tif y:\n\t\tz = 2\n\tw = 3",
+        "if x:\n\tif y:\n\t    z = 1\n\tw = 2",
+        "x = [1,\n      2,\n  3]\ny = 4",
+    ] {
+        assert!(Compiler::compile(source).is_some(), "{}", source);
+    }
+}
+
+#[test]
+fn compile_modules_returns_each_result_in_order() {
+    let sources: Vec<String> = (0..8).map(|i| format!("x = {}\nprint(x)", i)).collect();
+    let mut modules: Vec<(&str, &str)> = sources.iter().map(|s| ("mod", s.as_str())).collect();
+    modules.insert(3, ("broken", "x = ("));
+
+    let compiled = Compiler::compile_modules(&modules).unwrap();
+    assert_eq!(compiled.len(), 9);
+    for ((name, source), result) in modules.iter().zip(&compiled) {
+        match Compiler::compile_with_errors(source, name) {
+            Ok(chunk) => assert_eq!(result.as_ref().unwrap().code, chunk.code),
+            Err(errors) => assert_eq!(result.as_ref().unwrap_err(), &errors),
+        }
+    }
+    assert!(compiled[3].is_err());
+    assert!(Compiler::compile_modules(&[]).unwrap().is_empty());
+}
+
+/// Source defining `f` with `count` locals, all bound to `f` so that they
+/// add no constants.
+fn function_with_locals(count: usize) -> String {
+    let mut source = String::from("def f():\n");
+    for index in 0..count {
+        source.push_str(&format!("    x{} = f\n", index));
+    }
+    source
+}
+
+#[test]
+fn compile_uses_long_local_opcodes_past_one_byte() {
+    let chunk = Compiler::compile(&function_with_locals(300)).expect("Expected chunk");
+    let prototype = chunk
+        .constants
+        .iter()
+        .find_map(|value| match &**value {
+            ObjectType::FunctionPrototype(proto) => Some(proto.clone()),
+            _ => None,
+        })
+        .expect("Expected a function prototype");
+    let slots: Vec<_> = prototype
+        .chunk
+        .instructions()
+        .filter(|&(_, opcode, _)| opcode == OpCode::OpSetLocalLong)
+        .map(|(_, _, operands)| slot_index(operands))
+        .collect();
+    assert_eq!(slots.first(), Some(&256));
+    assert_eq!(slots.last(), Some(&300));
+}
+
+#[test]
+fn compile_limits_locals_per_function() {
+    // Slot 0 holds the function itself, leaving `MAX_SLOT` for locals
+    assert!(Compiler::compile(&function_with_locals(MAX_SLOT)).is_some());
+
+    let errors =
+        Compiler::compile_with_errors(&function_with_locals(MAX_SLOT + 1), "<script>").unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "too many local variables in one function"
+    );
+    assert_eq!(errors[0].line, MAX_SLOT + 2);
+}
+
+#[test]
+fn compile_limits_parameters() {
+    let parameters = |count: usize| {
+        let names: Vec<_> = (0..count).map(|index| format!("p{}", index)).collect();
+        format!("def f({}):\n    return p0", names.join(", "))
+    };
+    assert!(Compiler::compile(&parameters(MAX_SLOT)).is_some());
+
+    let errors = Compiler::compile_with_errors(&parameters(MAX_SLOT + 1), "<script>").unwrap_err();
+    assert_eq!(errors[0].message, "too many parameters");
+}
+
+#[test]
+fn compile_limits_captured_variables() {
+    // `h` captures the `MAX_SLOT - 1` locals of `f` besides `g`, and the
+    // `extra` locals of `g` besides `h`
+    let captures = |extra: usize| {
+        let mut source = function_with_locals(MAX_SLOT - 1);
+        source.push_str("    def g():\n");
+        for index in 0..extra {
+            source.push_str(&format!("        y{} = g\n", index));
+        }
+        source.push_str("        def h():\n");
+        for index in 0..MAX_SLOT - 1 {
+            source.push_str(&format!("            u = x{}\n", index));
+        }
+        for index in 0..extra {
+            source.push_str(&format!("            u = y{}\n", index));
+        }
+        source.push_str("        return h\n    return g\n");
+        source
+    };
+    assert!(Compiler::compile(&captures(2)).is_some());
+
+    let errors = Compiler::compile_with_errors(&captures(3), "<script>").unwrap_err();
+    assert_eq!(
+        errors[0].message,
+        "too many variables captured by one function"
+    );
+}
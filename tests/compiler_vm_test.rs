@@ -1,7 +1,12 @@
+use oxython::bytecode::jump_distance;
 use oxython::compiler::Compiler;
 use oxython::object::ObjectType;
+use oxython::platform::Output;
+use oxython::sync::Rc;
+use oxython::vm::modules::warnings::WarningAction;
 use oxython::vm::{InterpretResult, VM};
-use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 fn run_code(source: &str) -> (InterpretResult, Rc<ObjectType>) {
     let chunk = Compiler::compile(source).expect("Compilation failed");
@@ -16,6 +21,14 @@ fn run_code(source: &str) -> (InterpretResult, Rc<ObjectType>) {
     (result, last_popped)
 }
 
+fn run_error(source: &str) -> String {
+    let chunk = Compiler::compile(source).expect("Compilation failed");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+    let error = vm.last_error().expect("expected a recorded runtime error");
+    format!("{}: {}", error.kind, error.message)
+}
+
 #[test]
 fn test_integer_arithmetic() {
     let source = "1 + 2";
@@ -110,6 +123,62 @@ compute(4)
     assert_eq!(*last_popped, ObjectType::Integer(5));
 }
 
+#[test]
+fn test_suites_skip_blank_and_comment_lines() {
+    let source = "
+# Sums with a loop
+def total(items):
+    result = 0
+
+    # comments may sit at any indentation inside a block
+        # even deeper than the block
+# or at the left margin
+    for item in items:  # trailing comment
+
+        result = result + item
+    \t
+    return result  # done
+
+total([1, 2, 3])
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(6));
+
+    // The suite ends at the end of the source, wherever that falls
+    for source in [
+        "def f():\n    return 1\n\n# the end\nf()",
+        "x = 0\nif True:\n    x = 2\n    # trailing comment",
+    ] {
+        let (result, _) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+    }
+    // Windows line endings
+    let (_, last_popped) = run_code("x = 0\r\nif True:\r\n    x = 2\r\n\r\n    \r\nx");
+    assert_eq!(*last_popped, ObjectType::Integer(2));
+}
+
+#[test]
+fn test_inline_suites_take_semicolon_separated_statements() {
+    let source = "
+a = 0
+b = 0
+skip = False
+if skip: a = 1; b = 2
+items = []
+for i in [1, 2]: items.append(i); items.append(i * 10);
+def f(): y = 3; return y + b
+[a, b, items, f()]
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(last_popped.to_string(), "[0, 0, [1, 10, 2, 20], 3]");
+
+    // A statement on the next line is back outside the suite
+    let (_, last_popped) = run_code("b = 0\nif False: b = 1;\nb = b + 5\nb");
+    assert_eq!(*last_popped, ObjectType::Integer(5));
+}
+
 #[test]
 fn test_function_uses_globals_without_leaking_locals() {
     let source = "
@@ -377,3 +446,3916 @@ fn test_type_builtin_expression() {
     assert_eq!(result, InterpretResult::Ok);
     assert_eq!(*last_popped, ObjectType::String("int".to_string()));
 }
+
+#[test]
+fn test_zip_builtin_can_be_aliased() {
+    let source = "
+pair_up = zip
+list(pair_up([1, 2], 'ab'))
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(format!("{}", last_popped), "[(1, 'a'), (2, 'b')]");
+}
+
+#[test]
+fn test_zip_iterator_is_consumed_lazily_by_for_loop() {
+    let source = "
+total = 0
+for pair in zip([1, 2, 3], [10, 20]):
+    product = pair[0] * pair[1]
+    total = total + product
+total
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(50));
+}
+
+#[test]
+fn test_zip_iterator_is_exhausted_after_one_pass() {
+    let source = "
+z = zip([1, 2], [3, 4])
+first = list(z)
+list(z)
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::List(Vec::new()));
+}
+
+#[test]
+fn test_star_arguments_spread_into_user_function() {
+    let source = "
+def add3(a, b, c):
+    return a + b + c
+
+add3(1, *[2, 3])
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(6));
+}
+
+#[test]
+fn test_len_builtin_can_be_aliased() {
+    let source = "
+f = len
+f([1, 2])
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(2));
+}
+
+#[test]
+fn test_len_builtin_can_be_shadowed_by_function() {
+    let source = "
+def len(value):
+    return 42
+
+len([1, 2])
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(42));
+}
+
+#[test]
+fn test_round_and_range_accept_python_argument_forms() {
+    let (result, last_popped) = run_code("round(2.5)");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(2));
+
+    let (result, last_popped) = run_code("range(0, 10, 4)");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(format!("{}", last_popped), "[0, 4, 8]");
+}
+
+#[test]
+fn test_round_matches_cpython() {
+    let cases = [
+        ("round(2.675, 2)", "2.67"),
+        ("round(0.125, 2)", "0.12"),
+        ("round(0.375, 2)", "0.38"),
+        ("round(0.5)", "0"),
+        ("round(1.5)", "2"),
+        ("round(-2.5)", "-2"),
+        ("round(2.5, 0)", "2.0"),
+        ("round(1234.5, -2)", "1200.0"),
+        ("round(1250.0, -2)", "1200.0"),
+        ("round(1250.5, -2)", "1300.0"),
+        ("round(-1350.0, -2)", "-1400.0"),
+        ("round(5, 2)", "5"),
+        ("round(1250, -2)", "1200"),
+        ("round(-1350, -2)", "-1400"),
+        ("round(3.14159, 400)", "3.14159"),
+        ("round(3.14159, -400)", "0.0"),
+        ("x = 10.0 / 4; [x, round(x, 1)]", "[2.5, 2.5]"),
+        ("round(-0.4, 0)", "-0.0"),
+        ("round(-0.001, 2)", "-0.0"),
+        ("round(-0.0)", "0"),
+        (
+            "x = 0.0; [-x, x, -x * 2, repr(-x)]",
+            "[-0.0, 0.0, -0.0, '-0.0']",
+        ),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+
+    let errors = [
+        (
+            "round('a', 1)",
+            "TypeError: type str doesn't define __round__ method",
+        ),
+        (
+            "round(1.5, 1.5)",
+            "TypeError: 'float' object cannot be interpreted as an integer",
+        ),
+        (
+            "x = 1e308 * 10; round(x)",
+            "OverflowError: cannot convert float infinity to integer",
+        ),
+    ];
+    for (source, expected) in errors {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_numeric_builtins() {
+    let cases = [
+        (
+            "[divmod(7, 2), divmod(-7, 2), divmod(7.5, 2)]",
+            "[(3, 1), (-4, 1), (3.0, 1.5)]",
+        ),
+        (
+            "[pow(2, 10), pow(2, -1), pow(2.0, 0.5) > 1.41]",
+            "[1024, 0.5, True]",
+        ),
+        ("[pow(3, 200, 7), pow(3, -1, 7)]", "[2, 5]"),
+        ("[abs(-3), abs(4), abs(-2.5)]", "[3, 4, 2.5]"),
+        ("def pow(a, b):\n    return a\npow(2, 3)", "2"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+
+    let errors = [
+        (
+            "pow('a', 2)",
+            "TypeError: unsupported operand type(s) for ** or pow(): 'str' and 'int'",
+        ),
+        ("pow(2, 3, 0)", "ValueError: pow() 3rd argument cannot be 0"),
+        (
+            "pow(0, -1)",
+            "ZeroDivisionError: 0.0 cannot be raised to a negative power",
+        ),
+        (
+            "divmod(1, 0)",
+            "ZeroDivisionError: integer division or modulo by zero",
+        ),
+        (
+            "divmod('a', 1)",
+            "TypeError: unsupported operand type(s) for divmod(): 'str' and 'int'",
+        ),
+        (
+            "abs()",
+            "TypeError: abs() takes exactly one argument (0 given)",
+        ),
+    ];
+    for (source, expected) in errors {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_chr_and_ord() {
+    let cases = [
+        ("[chr(65), chr(ord('a') + 1), ord('A')]", "['A', 'b', 65]"),
+        (
+            "[ord('é'), chr(233), ord(chr(128512))]",
+            "[233, 'é', 128512]",
+        ),
+        (
+            "out = ''\nfor c in 'abc':\n    out = out + chr(ord(c) - 32)\nout",
+            "ABC",
+        ),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+
+    let errors = [
+        ("chr(-1)", "ValueError: chr() arg not in range(0x110000)"),
+        (
+            "chr(1114112)",
+            "ValueError: chr() arg not in range(0x110000)",
+        ),
+        (
+            "chr(55296)",
+            "ValueError: chr() arg is a surrogate code point",
+        ),
+        (
+            "chr('a')",
+            "TypeError: 'str' object cannot be interpreted as an integer",
+        ),
+        (
+            "ord('ab')",
+            "TypeError: ord() expected a character, but string of length 2 found",
+        ),
+        (
+            "ord('')",
+            "TypeError: ord() expected a character, but string of length 0 found",
+        ),
+        (
+            "ord(1)",
+            "TypeError: ord() expected string of length 1, but int found",
+        ),
+        (
+            "ord()",
+            "TypeError: ord() takes exactly one argument (0 given)",
+        ),
+    ];
+    for (source, expected) in errors {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_int_conversion_errors() {
+    let errors = [
+        (
+            "int([1])",
+            "TypeError: int() argument must be a string, a bytes-like object or a real number, not 'list'",
+        ),
+        (
+            "int(5, 10)",
+            "TypeError: int() can't convert non-string with explicit base",
+        ),
+        (
+            "int('5', 1)",
+            "ValueError: int() base must be >= 2 and <= 36, or 0",
+        ),
+        (
+            "int('9', 8)",
+            "ValueError: invalid literal for int() with base 8: '9'",
+        ),
+        (
+            "int('99999999999999999999')",
+            "OverflowError: integer overflow",
+        ),
+        (
+            "x = 1e308 * 10; int(x)",
+            "OverflowError: cannot convert float infinity to integer",
+        ),
+        (
+            "hex(1.5)",
+            "TypeError: 'float' object cannot be interpreted as an integer",
+        ),
+    ];
+    for (source, expected) in errors {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_hash_follows_equality_and_rejects_unhashable_values() {
+    let (result, value) = run_code(
+        "class Point:\n    def __hash__(self):\n        return 42\n[hash(3) == hash(3.0), hash(True), hash(-1), hash(Point()), hash(divmod(7, 2)) == hash(divmod(7.0, 2.0))]",
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(value.to_string(), "[True, 1, -2, 42, True]");
+
+    let errors = [
+        ("hash([1])", "TypeError: unhashable type: 'list'"),
+        (
+            "hash(list(zip([1], [{}]))[0])",
+            "TypeError: unhashable type: 'dict'",
+        ),
+        (
+            "class P:\n    def __eq__(self, other):\n        return True\nhash(P())",
+            "TypeError: unhashable type: 'P'",
+        ),
+        (
+            "class P:\n    def __hash__(self):\n        return 'x'\nhash(P())",
+            "TypeError: __hash__ method should return an integer",
+        ),
+        ("{'a': 1}[[1]]", "TypeError: unhashable type: 'list'"),
+        ("d = {}\nd[[1]] = 2", "TypeError: unhashable type: 'list'"),
+        (
+            "hash()",
+            "TypeError: hash() takes exactly one argument (0 given)",
+        ),
+        (
+            "id(1, 2)",
+            "TypeError: id() takes exactly one argument (2 given)",
+        ),
+    ];
+    for (source, expected) in errors {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_dict_keys_and_set_items_use_hash_and_eq_methods() {
+    let class = "class K:\n    def __init__(self, n):\n        self.n = n\n    def __hash__(self):\n        return hash(self.n)\n    def __eq__(self, other):\n        return self.n == other.n\n";
+    let cases = [
+        ("{K(1): 'x'}[K(1)]", "x"),
+        ("len(set([K(1), K(1)]))", "1"),
+        ("[K(1) in {K(1): 0}, K(2) in set([K(1)])]", "[True, False]"),
+        (
+            "d = {K(1): 'x'}\nd[K(1)] = 'y'\n[len(d), d[K(1)]]",
+            "[1, 'y']",
+        ),
+        ("d = {K(1): 'x'}\ndel d[K(1)]\nlen(d)", "0"),
+        ("dict([[K(2), 'z']])[K(2)]", "z"),
+        ("{tuple([K(3), 1]): 5}[tuple([K(3), 1])]", "5"),
+        ("hash(tuple([K(3), 1])) == hash(tuple([3, 1]))", "True"),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", class, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert!(run_error(&format!("{}{{K(1): 0}}[K(2)]", class)).starts_with("KeyError"));
+}
+
+#[test]
+fn test_storing_into_a_dict_leaves_its_copies_alone() {
+    let source = "d = {1: 'a'}\ne = d\nd[2] = 'b'\ndel d[1]\ndef f():\n    x = {1: 2}\n    y = x\n    x[3] = 4\n    del x[1]\n    z = {}\n    i = 0\n    while i < 500:\n        z['k' + str(i)] = i\n        i = i + 1\n    return [x, y, len(z), z['k499']]\n[d, e, f()]";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        value.to_string(),
+        "[{2: 'b'}, {1: 'a'}, [{3: 4}, {1: 2}, 500, 499]]"
+    );
+}
+
+#[test]
+fn test_attribute_builtins() {
+    let (result, value) = run_code(
+        "class A:\n    def f(self):\n        return 1\na = A()\nsetattr(a, 'n', 2)\ndef outer():\n    x = 5\n    def inner():\n        return x\n    return getattr(inner, '__closure__')\n[getattr(a, 'n'), getattr(a, 'f')(), hasattr(A, 'f'), hasattr(a, 'm'), getattr(a, 'm', None), outer()]",
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(value.to_string(), "[2, 1, True, False, None, (5,)]");
+
+    let errors = [
+        (
+            "class A:\n    def f(self):\n        return 1\nsetattr(A, 'x', 1)",
+            "TypeError: cannot set 'x' attribute of immutable type 'A'",
+        ),
+        (
+            "import sys\nsys.x = 1",
+            "AttributeError: 'module' object attribute 'x' is read-only",
+        ),
+        (
+            "x = 5\nx.y = 1",
+            "AttributeError: 'int' object has no attribute 'y'",
+        ),
+        (
+            "[1].y",
+            "AttributeError: 'list' object has no attribute 'y'",
+        ),
+        (
+            "getattr(1, 'a', 2, 3)",
+            "TypeError: getattr expected at most 3 arguments, got 4",
+        ),
+        (
+            "hasattr(1)",
+            "TypeError: hasattr expected 2 arguments, got 1",
+        ),
+    ];
+    for (source, expected) in errors {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_dir_lists_inherited_attributes() {
+    let source = "class Base:\n    def greet(self):\n        return 1\nclass Child(Base):\n    def __init__(self):\n        self.name = 2\n    def wave(self):\n        return 3\nc = Child()\nimport sys\n";
+    let cases = [
+        ("dir(c)", "['__init__', 'greet', 'name', 'wave']"),
+        ("dir(Child)", "['__init__', 'greet', 'wave']"),
+        ("vars(Child)", "{'__init__': <function Child.__init__ at 0x"),
+        (
+            "dir(sys)[0:3]",
+            "['__name__', 'getinstructioncount', 'getobjectcount']",
+        ),
+        ("dir(' ')[0:2]", "['format', 'join']"),
+        ("dir(1)", "[]"),
+        ("'__qualname__' in dir(Base.greet)", "True"),
+    ];
+    for (expression, expected) in cases {
+        let (result, value) = run_code(&format!("{}{}", source, expression));
+        assert_eq!(result, InterpretResult::Ok, "{}", expression);
+        assert!(
+            value.to_string().starts_with(expected),
+            "{}: {}",
+            expression,
+            value
+        );
+    }
+    assert_eq!(
+        run_error("vars()"),
+        "TypeError: vars() without arguments is not supported"
+    );
+}
+
+#[test]
+fn test_format_specs_shared_by_f_strings_and_format_functions() {
+    let cases = [
+        ("n = 1234567\nf'{n:>12,}'", "   1,234,567"),
+        ("x = 2.5\nw = 7\nf'{x:{w}.2f}|'", "   2.50|"),
+        ("f'{\"a\"!r:^5}'", " 'a' "),
+        ("format(255, '#06x')", "0x00ff"),
+        ("'{1}{0:03}'.format(7, 'n')", "n007"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("x = 'a'\nf'{x:+}'"),
+        "ValueError: Sign not allowed in string format specifier"
+    );
+    assert_eq!(
+        run_error("'{}{}'.format(1)"),
+        "IndexError: Replacement index 1 out of range for positional args tuple"
+    );
+}
+
+#[test]
+fn test_format_fills_named_fields_from_keywords() {
+    let cases = [
+        ("'{n}'.format(n=1)", "1"),
+        ("'{a}-{0}-{b!r}'.format(5, a='x', b='y')", "x-5-'y'"),
+        ("kw = {'who': 'me'}\n'hi {who}'.format(**kw)", "hi me"),
+        ("'{p[0]}'.format(p=[7])", "7"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(run_error("'{m}'.format(n=1)"), "KeyError: 'm'");
+}
+
+#[test]
+fn test_natives_running_on_the_vm_take_keywords_however_they_are_reached() {
+    let cases = [
+        (
+            "order = sorted\norder([1, 3, 2], reverse=True)",
+            "[3, 2, 1]",
+        ),
+        (
+            "import pprint\nlayout = pprint.pformat\nlayout([1, 2], width=3)",
+            "[1,\n 2]",
+        ),
+        ("pick = [max]\npick[0](['a', 'bbb'], key=len)", "bbb"),
+        ("template = '{x}'.format\ntemplate(x=4)", "4"),
+        ("dict([['a', 1]], b=2)", "{'a': 1, 'b': 2}"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("len([1], x=1)"),
+        "TypeError: len() takes no keyword arguments"
+    );
+    assert_eq!(
+        run_error("import sys\nsys.getrecursionlimit(depth=1)"),
+        "TypeError: getrecursionlimit() takes no keyword arguments"
+    );
+}
+
+#[test]
+fn test_containers_show_their_elements_repr() {
+    let cases = [
+        ("repr({'a': 'x'})", "{'a': 'x'}"),
+        ("'{!r}'.format({'a': 'x'})", "{'a': 'x'}"),
+        ("str([{'a': 'x'}])", "[{'a': 'x'}]"),
+        ("str([\"it's\"])", "[\"it's\"]"),
+        ("str(tuple(['a\"b', 1]))", "('a\"b', 1)"),
+        ("str(set([\"it's\"]))", "{\"it's\"}"),
+        ("str({'k': [None, 'q']})", "{'k': [None, 'q']}"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_booleans_are_integers_in_arithmetic_and_comparisons() {
+    let cases = [
+        ("True + True", "2"),
+        ("x = 0\nx += True\nx", "1"),
+        ("True * 2.5", "2.5"),
+        ("-True", "-1"),
+        ("False < True", "True"),
+        ("True == 1", "True"),
+        ("[True, 0] == [1, False]", "True"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("1 % False"),
+        "ZeroDivisionError: integer modulo by zero"
+    );
+}
+
+#[test]
+fn test_augmented_assignment_to_attributes() {
+    let class = "class Counter:\n    def __init__(self):\n        self.count = 0\n        self.label = 'c'\n    def bump(self, by):\n        self.count += by\n        self.label += '!'\n        return self\n";
+    let cases = [
+        ("c = Counter()\nc.bump(2).bump(3)\nc.count", "5"),
+        ("c = Counter()\nc.bump(1).bump(1)\nc.label", "c!!"),
+        ("c = Counter()\nc.count += 4\nc.count *= 3\nc.count", "12"),
+        (
+            "c = Counter()\ndef grow():\n    c.count += 7\ngrow()\nc.count",
+            "7",
+        ),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", class, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error(&format!("{}c = Counter()\nc.missing += 1", class)),
+        "AttributeError: 'Counter' object has no attribute 'missing'"
+    );
+}
+
+#[test]
+fn test_methods_mutating_self_are_seen_by_every_reference() {
+    let class = "class Bag:\n    def __init__(self):\n        self.items = []\n    def add(self, item):\n        self.items.append(item)\n        self.items.sort()\n        return self\n";
+    let cases = [
+        ("b = Bag()\nb.add(2).add(1)\nb.items", "[1, 2]"),
+        ("bags = [Bag()]\nbags[0].add(3)\nbags[0].items", "[3]"),
+        (
+            "bags = {'a': Bag()}\nbags['a'].add(4)\nbags['a'].items",
+            "[4]",
+        ),
+        (
+            "b = Bag()\ndef fill():\n    b.add(5)\nfill()\nb.items",
+            "[5]",
+        ),
+        ("b = Bag()\nc = b\nc.items.append(6)\nb.items", "[6]"),
+        (
+            "bags = [Bag(), Bag()]\nbags[0].items.append(4)\n[bags[0].items, bags[1].items]",
+            "[[4], []]",
+        ),
+        (
+            "b = Bag()\nb.nested = [[1], [2]]\nb.nested[0].append(3)\nb.nested",
+            "[[1, 3], [2]]",
+        ),
+        ("n = [[5], [6]]\nx = n[0].append(9)\nn", "[[5, 9], [6]]"),
+        (
+            "def f():\n    n = {'k': [[1]]}\n    n['k'][0].append(2)\n    return n\nf()",
+            "{'k': [[1, 2]]}",
+        ),
+        (
+            "n = [[2, 1]]\nn[0][1:].append(7)\nn[0].sort()\nn",
+            "[[1, 2]]",
+        ),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", class, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_super_resolves_from_the_class_defining_the_method() {
+    let classes = "class A:\n    def __init__(self):\n        self.trail = 'A'\n    def name(self):\n        return 'A'\n    @classmethod\n    def make(cls):\n        return cls()\nclass B(A):\n    def __init__(self):\n        super().__init__()\n        self.trail = self.trail + 'B'\n    def name(self):\n        return 'B' + super().name()\n    @classmethod\n    def make(cls):\n        return super().make()\nclass C(B):\n    def name(self):\n        return 'C' + super().name()\n";
+    let cases = [
+        ("C().trail", "AB"),
+        ("C().name()", "CBA"),
+        ("C.make().name()", "CBA"),
+        ("C().make().trail", "AB"),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", classes, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_class_bodies_compute_attributes_from_enclosing_names() {
+    let cases = [
+        ("BASE = 2\nclass A:\n    X = BASE * 3\n    Y = X + 1\nA.Y", "7"),
+        (
+            "class A:\n    total = 0\n    for n in [1, 2, 3]:\n        total += n\nA.total",
+            "6",
+        ),
+        (
+            "def make(base):\n    class A:\n        X = base + 1\n    return A\nmake(4).X",
+            "5",
+        ),
+        (
+            "X = 'global'\nclass A:\n    X = 'class'\n    def get(self):\n        return X\nA().get()",
+            "global",
+        ),
+        ("class A:\n    x: int\n    y: int = 3\nA.y", "3"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_instances_with_call_method_are_callable() {
+    let class = "class Adder:\n    def __init__(self, amount):\n        self.amount = amount\n    def __call__(self, value, scale=1):\n        return (value + self.amount) * scale\n";
+    let cases = [
+        ("Adder(2)(3)", "5"),
+        ("Adder(2)(3, scale=10)", "50"),
+        (
+            "add = Adder(1)\ndef apply(f, x):\n    return f(x)\napply(add, 4)",
+            "5",
+        ),
+        ("sorted([1, 3, 2], key=Adder(0))", "[1, 2, 3]"),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", class, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("class Plain:\n    x = 1\nPlain()()"),
+        "TypeError: 'Plain' object is not callable"
+    );
+}
+
+#[test]
+fn test_len_truthiness_and_in_use_protocol_methods() {
+    let class = "class Box:\n    def __init__(self, items):\n        self.items = items\n    def __len__(self):\n        return len(self.items)\n    def __contains__(self, item):\n        return item in self.items\nclass Flag:\n    def __init__(self, on):\n        self.on = on\n    def __bool__(self):\n        return self.on\n";
+    let cases = [
+        ("len(Box([1, 2]))", "2"),
+        ("2 in Box([1, 2])", "True"),
+        ("3 not in Box([1, 2])", "True"),
+        ("not Box([])", "True"),
+        ("'yes' if Box([1]) else 'no'", "yes"),
+        ("'yes' if Flag(False) else 'no'", "no"),
+        ("not Flag(True)", "False"),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", class, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("class Bad:\n    def __bool__(self):\n        return 1\nnot Bad()"),
+        "TypeError: __bool__ should return bool, returned int"
+    );
+}
+
+#[test]
+fn test_qualnames_put_locals_after_each_enclosing_function() {
+    let source = "def outer():\n    def inner():\n        return 1\n    class Local:\n        def method(self):\n            return 2\n    return [inner.__qualname__, Local.method.__qualname__]\nclass Top:\n    def method(self):\n        return 3\nouter() + [Top.method.__qualname__, str(None)]";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        value.to_string(),
+        "['outer.<locals>.inner', 'outer.<locals>.Local.method', 'Top.method', 'None']"
+    );
+}
+
+#[test]
+fn test_unary_minus_negates_its_operand() {
+    let cases = [
+        ("-5", "-5"),
+        ("n = 3\n-n", "-3"),
+        ("n = 3\n--n", "3"),
+        ("-True", "-1"),
+        ("-0.0", "-0.0"),
+        ("str(-0.0)", "-0.0"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_unary_minus_on_a_string_names_its_type() {
+    assert_eq!(
+        run_error("-'a'"),
+        "TypeError: bad operand type for unary -: 'str'"
+    );
+}
+
+#[test]
+fn test_operand_errors_name_the_offending_type() {
+    let cases = [
+        (
+            "for k in 5:\n    print(k)",
+            "TypeError: 'int' object is not iterable",
+        ),
+        ("[x for x in 5]", "TypeError: 'int' object is not iterable"),
+        ("list(5)", "TypeError: 'int' object is not iterable"),
+        (
+            "(5).append(1)",
+            "AttributeError: 'int' object has no attribute 'append'",
+        ),
+        (
+            "d = {}\nd.append(1)",
+            "AttributeError: 'dict' object has no attribute 'append'",
+        ),
+        (
+            "x = 5\nx.lower()",
+            "AttributeError: 'int' object has no attribute 'lower'",
+        ),
+        (
+            "range('a', 2)",
+            "TypeError: 'str' object cannot be interpreted as an integer",
+        ),
+        (
+            "x = 5\nx[1:2]",
+            "TypeError: 'int' object is not subscriptable",
+        ),
+        ("', '.join(5)", "TypeError: can only join an iterable"),
+        (
+            "', '.join(['a', 1])",
+            "TypeError: sequence item 1: expected str instance, int found",
+        ),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_slicing_a_tuple_gives_a_tuple() {
+    let (result, value) = run_code("tuple([1, 2, 3])[1:]");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(value.to_string(), "(2, 3)");
+}
+
+#[test]
+fn test_zero_and_empty_values_are_false() {
+    let cases = [
+        ("x = 0\nx if x else 'none'", "none"),
+        ("x = 7\nx if x else 'none'", "7"),
+        ("'yes' if 0.0 else 'no'", "no"),
+        ("'yes' if '' else 'no'", "no"),
+        ("'yes' if [] else 'no'", "no"),
+        ("'yes' if {} else 'no'", "no"),
+        ("'yes' if tuple([]) else 'no'", "no"),
+        ("'yes' if set() else 'no'", "no"),
+        ("'yes' if [0] else 'no'", "yes"),
+        ("not 0", "True"),
+        ("any([0, '', []])", "False"),
+        (
+            "n = 3\ncount = 0\nwhile n:\n    n = n - 1\n    count = count + 1\ncount",
+            "3",
+        ),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_collection_constructors_accept_iterables_and_keywords() {
+    let cases = [
+        ("dict(a=1, b=2)", "{'a': 1, 'b': 2}"),
+        ("dict([['a', 1], ['b', 2]], b=3)", "{'a': 1, 'b': 3}"),
+        ("dict(zip(['x'], [1]))", "{'x': 1}"),
+        (
+            "base = {'a': 1}\ncopy = dict(base, c=2)\nlen(base) + len(copy)",
+            "3",
+        ),
+        ("tuple([1, 2])", "(1, 2)"),
+        ("tuple()", "()"),
+        ("list(tuple('ab'))", "['a', 'b']"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("dict([['a', 1, 2]])"),
+        "ValueError: dictionary update sequence element #0 has length 3; 2 is required"
+    );
+    assert_eq!(
+        run_error("dict([[[1], 2]])"),
+        "TypeError: unhashable type: 'list'"
+    );
+}
+
+#[test]
+fn test_calls_unpack_keyword_mappings() {
+    let f = "def f(a, b=2, c=3):\n    return [a, b, c]\n";
+    let cases = [
+        ("f(1, **{'b': 20})", "[1, 20, 3]"),
+        ("f(**{'a': 5, 'c': 7})", "[5, 2, 7]"),
+        ("args = [1]\nf(*args, c=9, **{'b': 0})", "[1, 0, 9]"),
+        (
+            "kw = {'b': 2}\n[dict(**kw), dict(a=1, **kw)]",
+            "[{'b': 2}, {'a': 1, 'b': 2}]",
+        ),
+        ("sorted([3, 1, 2], **{'reverse': True})", "[3, 2, 1]"),
+        ("f(c=0, *[7], **{'b': 8})", "[7, 8, 0]"),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", f, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    let errors = [
+        (
+            "f(1, **{'b': 1}, b=2)",
+            "TypeError: f() got multiple values for keyword argument 'b'",
+        ),
+        (
+            "f(**[1])",
+            "TypeError: f() argument after ** must be a mapping, not list",
+        ),
+        ("f(**{1: 2})", "TypeError: keywords must be strings"),
+    ];
+    for (source, expected) in errors {
+        assert_eq!(
+            run_error(&format!("{}{}", f, source)),
+            expected,
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_set_constructor_keeps_distinct_items() {
+    let cases = [
+        (
+            "s = set([3, 1, 3, 2, 1])\n[len(s), sorted(s), 3 in s, 5 in s]",
+            "[3, [1, 2, 3], True, False]",
+        ),
+        ("set()", "set()"),
+        ("set('aba') == set(['b', 'a'])", "True"),
+        (
+            "total = 0\nfor n in set([2, 2, 5]):\n    total = total + n\ntotal",
+            "7",
+        ),
+        ("type(set())", "set"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("set([[1]])"),
+        "TypeError: unhashable type: 'list'"
+    );
+    assert_eq!(
+        run_error("hash(set())"),
+        "TypeError: unhashable type: 'set'"
+    );
+}
+
+#[test]
+fn test_generator_expressions_are_iterables() {
+    let cases = [
+        ("', '.join(str(x) for x in [1, 2, 3])", "1, 2, 3"),
+        ("','.join(w.upper() for w in ['a', 'b'] if w == 'b')", "B"),
+        ("sorted(n * -1 for n in range(3))", "[-2, -1, 0]"),
+        (
+            "gen = (n for n in 'ab')\nfirst = list(gen)\n[first, list(gen)]",
+            "[['a', 'b'], []]",
+        ),
+        (
+            "def f(xs):\n    k = 2\n    return list(x * k for x in xs)\nf([1, 2])",
+            "[2, 4]",
+        ),
+        (
+            "log = []\ndef f(n):\n    log.append(n)\n    return n\ngen = (f(n) for n in [1, 2])\nbefore = len(log)\nfor v in gen:\n    break\n[before, v, log]",
+            "[0, 1, [1]]",
+        ),
+        ("sum(n * n for n in range(4))", "14"),
+        ("sum((n for n in [1, 2]), 10)", "13"),
+        ("[any(n > 2 for n in [1, 3]), all(n > 2 for n in [1, 3])]", "[True, False]"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_sum_rejects_strings_and_unaddable_items() {
+    assert_eq!(
+        run_error("sum(['a'], '')"),
+        "TypeError: sum() can't sum strings [use ''.join(seq) instead]"
+    );
+    assert_eq!(
+        run_error("sum(n for n in [1, 'a'])"),
+        "TypeError: unsupported operand type(s) for +: 'int' and 'str'"
+    );
+}
+
+#[test]
+fn test_str_and_repr_builtins() {
+    let point = "class Point:\n    def __init__(self, x):\n        self.x = x\n    def __str__(self):\n        return 'P' + str(self.x)\n    def __repr__(self):\n        return 'Point(' + repr(self.x) + ')'\n";
+    let cases = [
+        (
+            "[str(1), str(2.5), str(None), str(True), str()]",
+            "['1', '2.5', 'None', 'True', '']",
+        ),
+        (
+            "[str('a'), repr('a') == \"'a'\", repr(1), str([1, 'b']) == \"[1, 'b']\"]",
+            "['a', True, '1', True]",
+        ),
+        (
+            &format!(
+                "{}[str(Point(1)), repr(Point('a')) == \"Point('a')\"]",
+                point
+            ),
+            "['P1', True]",
+        ),
+        ("s = str\ns(3) + s(4)", "34"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("str(1, 2)"),
+        "TypeError: str expected at most 1 argument, got 2"
+    );
+}
+
+#[test]
+fn test_min_and_max_builtins() {
+    let cases = [
+        ("[min([3, 1, 2]), max([3, 1, 2]), min(4, 2, 3), max('b', 'c', 'a')]", "[1, 3, 2, 'c']"),
+        ("[min(n * -1 for n in range(3)), max('hello')]", "[-2, 'o']"),
+        ("def length(w):\n    return len(w)\n[max(['a', 'bb', 'cc'], key=length), min('ab', 'c', key=length)]", "['bb', 'c']"),
+        ("[max([], default=0), min([5], default=0), max([1, 2], key=None)]", "[0, 5, 2]"),
+        ("[max(1, 1.0), min(1.0, 1)]", "[1, 1.0]"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("max([])"),
+        "ValueError: max() iterable argument is empty"
+    );
+    assert_eq!(
+        run_error("min()"),
+        "TypeError: min expected at least 1 argument, got 0"
+    );
+    assert_eq!(
+        run_error("max([1, 'a'])"),
+        "TypeError: '>' not supported between instances of 'str' and 'int'"
+    );
+    assert_eq!(
+        run_error("min(1, 2, default=0)"),
+        "TypeError: Cannot specify a default for min() with multiple positional arguments"
+    );
+}
+
+#[test]
+fn test_next_steps_iterators_and_absorbs_stop_iteration() {
+    let countdown = "class Countdown:\n    def __init__(self, n):\n        self.n = n\n    def __iter__(self):\n        return self\n    def __next__(self):\n        if self.n == 0:\n            raise StopIteration\n        self.n = self.n - 1\n        return self.n + 1\n";
+    let cases = [
+        ("it = iter([1, 2])\n[next(it), next(it), next(it, 'end')]", "[1, 2, 'end']"),
+        ("gen = (n * 2 for n in [1, 2])\n[next(gen), list(gen), next(gen, 0)]", "[2, [4], 0]"),
+        ("it = iter(Countdown(2))\n[next(it), next(it), next(it, 'end')]", "[2, 1, 'end']"),
+        ("z = zip('ab', [1, 2])\n[next(z), iter(z) is z]", "[('a', 1), True]"),
+        (
+            "it = iter([])\ntry:\n    next(it)\n    caught = False\nexcept StopIteration as e:\n    caught = e.args\ncaught",
+            "()",
+        ),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", countdown, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(
+        run_error("next(iter('a'))\nnext(iter(''))"),
+        "StopIteration: "
+    );
+    assert_eq!(
+        run_error("next([1])"),
+        "TypeError: 'list' object is not an iterator"
+    );
+    assert_eq!(
+        run_error("class A:\n    def __iter__(self):\n        return 1\niter(A())"),
+        "TypeError: iter() returned non-iterator of type 'int'"
+    );
+}
+
+#[test]
+fn test_slice_assignment_and_deletion() {
+    let cases = [
+        ("x = [0, 1, 2, 3]\nx[1:3] = ['a', 'b', 'c']\nx", "[0, 'a', 'b', 'c', 3]"),
+        ("x = [0, 1, 2]\nx[:2] = []\nx", "[2]"),
+        ("x = [0, 1]\nx[1:1] = 'ab'\nx", "[0, 'a', 'b', 1]"),
+        ("x = [0, 1, 2, 3]\nx[::2] = [8, 9]\nx", "[8, 1, 9, 3]"),
+        ("x = [0, 1]\nx[-1:] = (n * 2 for n in [3, 4])\nx", "[0, 6, 8]"),
+        ("x = [0, 1, 2, 3, 4]\ndel x[1:3]\nx", "[0, 3, 4]"),
+        ("x = [0, 1, 2, 3, 4]\ndel x[::2]\nx", "[1, 3]"),
+        ("x = [0, 1, 2]\ndel x[-1], x[0]\nx", "[1]"),
+        ("d = {'a': 1, 'b': 2}\ndel d['a']\nd", "{'b': 2}"),
+        ("a = b = [1, 2]\na[:1] = [5, 6]\na", "[5, 6, 2]"),
+        (
+            "def f():\n    xs = [1, 2, 3, 4]\n    xs[1:3] = [0]\n    del xs[:1]\n    return xs\nf()",
+            "[0, 4]",
+        ),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+
+    let errors = [
+        (
+            "x = [1, 2, 3]\nx[::2] = [1]",
+            "ValueError: attempt to assign sequence of size 1 to extended slice of size 2",
+        ),
+        (
+            "x = [1]\nx[1:2] = 5",
+            "TypeError: can only assign an iterable",
+        ),
+        (
+            "x = [1]\nx[::0] = []",
+            "ValueError: slice step cannot be zero",
+        ),
+        (
+            "s = 'abc'\ns[0:1] = 'x'",
+            "TypeError: 'str' object does not support item assignment",
+        ),
+        (
+            "x = [1]\ndel x[3]",
+            "IndexError: list assignment index out of range",
+        ),
+        ("d = {'a': 1}\ndel d['b']", "KeyError: 'b'"),
+        (
+            "s = 'abc'\ndel s[0]",
+            "TypeError: 'str' object doesn't support item deletion",
+        ),
+    ];
+    for (source, expected) in errors {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_copy_module_and_collection_copy_methods() {
+    let source = r#"
+import copy
+class Box:
+    def __init__(self, items):
+        self.items = items
+a = [1, [2]]
+b = a.copy()
+d = {"k": [1]}
+box = Box([1])
+box.me = box
+shallow = copy.copy(box)
+deep = copy.deepcopy(box)
+[b is a, b == a, d.copy() == d, shallow.items is box.items, deep.items is box.items, deep.items == box.items, deep.me is deep]
+"#;
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        value.to_string(),
+        "[False, True, True, True, False, True, True]"
+    );
+    assert_eq!(
+        run_error("import copy\nx = copy.deepcopy(iter([1]))"),
+        "TypeError: cannot pickle 'list_iterator' object"
+    );
+}
+
+#[test]
+fn test_iterating_a_dict_yields_its_keys_in_insertion_order() {
+    let cases = [
+        (
+            "d = {'b': 1, 'a': 2}\nd['c'] = 3\nkeys = []\nfor k in d:\n    keys.append(k)\nkeys",
+            "['b', 'a', 'c']",
+        ),
+        ("[k for k in {'b': 1, 'a': 2}]", "['b', 'a']"),
+        ("next(iter({'b': 1, 'a': 2}))", "b"),
+        ("list({'b': 1, 'a': 2})", "['b', 'a']"),
+        ("tuple({'b': 1, 'a': 2})", "('b', 'a')"),
+        ("sorted({'b': 1, 'a': 2})", "['a', 'b']"),
+        ("list({})", "[]"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_equal_numbers_are_the_same_dict_key() {
+    let cases = [
+        ("d = {}\nd[1] = 'a'\nd[1.0] = 'b'\nd[True]", "b"),
+        ("d = {True: 1, 1: 2, 1.0: 3}\nd", "{True: 3}"),
+        (
+            "d = {0: 'zero'}\n[d[False], d[0.0], 0.0 in d, 0.5 in d]",
+            "['zero', 'zero', True, False]",
+        ),
+        ("d = {tuple([1, 2]): 'pair'}\nd[tuple([1.0, 2])]", "pair"),
+        ("d = dict([[2, 'x'], [2.0, 'y']])\ndel d[2.0]\nlen(d)", "0"),
+        (
+            "[1 == 1.0, True == 1.0, 0.5 == 0, [1] == [1.0]]",
+            "[True, True, False, True]",
+        ),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+    assert_eq!(run_error("d = {1: 2}\nd[2.0]"), "KeyError: 2.0");
+    assert_eq!(
+        run_error("d = {}\nd[[1]] = 2"),
+        "TypeError: unhashable type: 'list'"
+    );
+}
+
+#[test]
+fn test_printing_deeply_nested_containers_raises_recursion_error() {
+    let nest = "x = []\nfor i in range(1100):\n    x = [x]\n";
+    let message =
+        "RecursionError: maximum recursion depth exceeded while getting the repr of an object";
+    assert_eq!(run_error(&format!("{}print(x)", nest)), message);
+    assert_eq!(run_error(&format!("{}y = f'{{x}}'", nest)), message);
+    let (result, value) = run_code("x = []\nfor i in range(100):\n    x = [x]\nlen(f'{x}')");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Integer(202));
+}
+
+#[test]
+fn test_a_container_reached_again_through_repr_shows_as_an_ellipsis() {
+    let class = "class N:\n    def __repr__(self):\n        return 'N' + repr(self.c)\nn = N()\n";
+    let cases = [
+        ("n.c = [n, 1]\nrepr(n)", "N[N[...], 1]"),
+        ("n.c = {'k': n}\nf'{n.c}'", "{'k': N{...}}"),
+        ("n.c = tuple([n])\nstr([n])", "[N(N(...),)]"),
+        ("n.c = set([n])\n'{}'.format(n.c)", "{Nset(...)}"),
+        ("l = [1]\nn.c = [l, l]\nrepr(n)", "N[[1], [1]]"),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", class, source);
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(
+            *value,
+            ObjectType::String(expected.to_string()),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_tail_calls_reuse_frames_when_enabled() {
+    let source = "
+def count(n, acc):
+    if n == 0:
+        return acc
+    return count(n - 1, acc=acc + n)
+
+def chain(n, f):
+    if n == 0:
+        return f()
+    def g():
+        return n
+    return chain(n - 1, g)
+
+def fail(n):
+    if n == 0:
+        return len(5)
+    return fail(n - 1)
+
+[count(1000, 0), chain(100, None)]
+";
+    let run = |source: &str, tail_calls: bool| {
+        let chunk = Compiler::compile(source).expect("Compilation failed");
+        let mut vm = VM::new();
+        vm.set_tail_calls(tail_calls);
+        let result = vm.interpret(chunk);
+        (result, vm)
+    };
+
+    let (result, vm) = run(source, true);
+    assert_eq!(result, InterpretResult::Ok);
+    let value = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(value.to_string(), "[500500, 1]");
+    let (result, vm) = run(source, false);
+    assert_eq!(result, InterpretResult::RuntimeError);
+    assert_eq!(vm.last_error().unwrap().kind, "RecursionError");
+
+    // The frames replaced are missing from the traceback
+    let (result, vm) = run(&format!("{}fail(3)", source), true);
+    assert_eq!(result, InterpretResult::RuntimeError);
+    let functions: Vec<&str> = vm
+        .last_error()
+        .unwrap()
+        .traceback
+        .iter()
+        .map(|frame| frame.function.as_str())
+        .collect();
+    assert_eq!(functions, vec!["<module>", "fail"]);
+
+    // A call whose result is used is not in tail position
+    let (result, vm) = run(
+        "def depth(n):\n    if n == 0:\n        return 0\n    return 1 + depth(n - 1)\ndepth(1000)",
+        true,
+    );
+    assert_eq!(result, InterpretResult::RuntimeError);
+    assert_eq!(vm.last_error().unwrap().kind, "RecursionError");
+}
+
+#[test]
+fn test_docstrings_are_stored_on_functions_and_classes() {
+    let source = r#"
+def documented(x):
+    """Says what it does.
+
+    In more detail."""
+    return x
+
+def undocumented(x):
+    "not a docstring".upper()
+    return x
+
+class Shape:
+    'A shape.'
+    def area(self):
+        return 0
+
+result = [documented.__doc__, undocumented.__doc__, Shape.__doc__, Shape().__doc__]
+result
+"#;
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        value.to_string(),
+        "['Says what it does.\\n\\n    In more detail.', None, 'A shape.', 'A shape.']"
+    );
+}
+
+#[test]
+fn test_help_shows_signatures_and_docstrings() {
+    let source = r#"
+def greet(name: str, greeting="Hello") -> str:
+    """Return a greeting.
+
+    The greeting comes first."""
+    return greeting + name
+
+class Animal:
+    """An animal."""
+    sound = "..."
+    def __init__(self, name):
+        self.name = name
+    def speak(self, loud=False):
+        """Make a sound."""
+        return self.sound
+
+class Dog(Animal):
+    def fetch(self):
+        return 1
+
+help(greet)
+help(Dog)
+help(Dog("rex").speak)
+help(len)
+help("missing")
+"#;
+    let chunk = Compiler::compile(source).expect("Compilation failed");
+    let mut vm = VM::new();
+    vm.capture_output();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    let expected = "\
+Help on function greet in module <script>:
+
+greet(name: str, greeting='Hello') -> str
+    Return a greeting.
+    
+    The greeting comes first.
+
+Help on class Dog in module <script>:
+
+class Dog(Animal)
+ |  Dog(name)
+ |  
+ |  Method resolution order:
+ |      Dog
+ |      Animal
+ |      builtins.object
+ |  
+ |  Methods defined here:
+ |  
+ |  fetch(self)
+ |  
+ |  ----------------------------------------------------------------------
+ |  Methods inherited from Animal:
+ |  
+ |  __init__(self, name)
+ |  
+ |  speak(self, loud=False)
+ |      Make a sound.
+ |  
+ |  ----------------------------------------------------------------------
+ |  Data and other attributes inherited from Animal:
+ |  
+ |  sound = '...'
+
+Help on method speak in module <script>:
+
+speak(loud=False) method of <script>.Dog instance
+    Make a sound.
+
+Help on built-in function len in module builtins:
+
+len(obj, /)
+    Return the number of items in a container.
+
+No Python documentation found for 'missing'.
+Use help() to get the interactive help utility.
+Use help(str) for help on the str class.
+
+";
+    assert_eq!(vm.take_output(), expected);
+}
+
+#[test]
+fn test_too_many_arguments_reports_function_name() {
+    let source = "
+def add(a, b):
+    return a + b
+
+add(1, 2, 3)
+";
+    assert_eq!(
+        run_error(source),
+        "TypeError: add() takes 2 positional arguments but 3 were given"
+    );
+}
+
+#[test]
+fn test_missing_arguments_are_named() {
+    let source = "
+def greet(greeting, name, punctuation):
+    return greeting
+
+greet('hi')
+";
+    assert_eq!(
+        run_error(source),
+        "TypeError: greet() missing 2 required positional arguments: 'name' and 'punctuation'"
+    );
+}
+
+#[test]
+fn test_default_arguments_report_accepted_range() {
+    let source = "
+def scale(value, factor=2):
+    return value * factor
+
+scale(1, 2, 3)
+";
+    assert_eq!(
+        run_error(source),
+        "TypeError: scale() takes from 1 to 2 positional arguments but 3 were given"
+    );
+}
+
+#[test]
+fn test_method_arity_error_counts_self() {
+    let source = "
+class Dog:
+    def __init__(self, name):
+        self.name = name
+
+Dog()
+";
+    assert_eq!(
+        run_error(source),
+        "TypeError: Dog.__init__() missing 1 required positional argument: 'name'"
+    );
+}
+
+#[test]
+fn test_calling_non_callable_reports_type() {
+    assert_eq!(
+        run_error("x = 5; x()"),
+        "TypeError: 'int' object is not callable"
+    );
+}
+
+#[test]
+fn test_runtime_error_traceback_lists_active_frames() {
+    let source = "
+def inner():
+    return len(5)
+
+def outer():
+    return inner()
+
+outer()
+";
+    let chunk = Compiler::compile(source).expect("Compilation failed");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+    let error = vm.last_error().expect("expected a recorded runtime error");
+    let functions: Vec<&str> = error
+        .traceback
+        .iter()
+        .map(|frame| frame.function.as_str())
+        .collect();
+    assert_eq!(functions, vec!["<module>", "outer", "inner"]);
+    assert_eq!(
+        error.to_string(),
+        "Traceback (most recent call last):
+  File \"<script>\", line 8, in <module>
+  File \"<script>\", line 6, in outer
+  File \"<script>\", line 3, in inner
+TypeError: object of type 'int' has no len()"
+    );
+}
+
+#[test]
+fn test_global_assignment_shadows_builtin() {
+    let source = "
+list = [1]
+list
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(format!("{}", last_popped), "[1]");
+}
+
+#[test]
+fn test_del_unshadows_builtin() {
+    let source = "
+list = [1]
+del list
+list('ab')
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(format!("{}", last_popped), "['a', 'b']");
+}
+
+#[test]
+fn test_local_shadows_builtin_inside_function_only() {
+    let source = "
+def count(items):
+    len = 99
+    return len
+
+count([1, 2]) + len([1, 2])
+";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(101));
+}
+
+#[test]
+fn test_del_of_undefined_name_is_name_error() {
+    assert_eq!(
+        run_error("del missing"),
+        "NameError: name 'missing' is not defined"
+    );
+}
+
+#[test]
+fn test_builtins_cannot_be_deleted() {
+    assert_eq!(run_error("del len"), "NameError: name 'len' is not defined");
+}
+
+#[test]
+fn test_function_and_method_reprs() {
+    let (result, value) = run_code(
+        r#"
+def add(a, b):
+    return a + b
+
+add
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert!(format!("{}", value).starts_with("<function add at 0x"));
+
+    let (result, value) = run_code(
+        r#"
+class Greeter:
+    def greet(self):
+        return "hi"
+
+g = Greeter()
+g.greet
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    let text = format!("{}", value);
+    assert!(
+        text.starts_with("<bound method Greeter.greet of <Greeter object at 0x"),
+        "{}",
+        text
+    );
+
+    let (result, value) = run_code("len");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(format!("{}", value), "<built-in function len>");
+}
+
+#[test]
+fn test_class_without_init_constructs_instance() {
+    let (result, value) = run_code(
+        r#"
+class Point:
+    def norm(self):
+        return self.x + self.y
+
+p = Point()
+p.x = 3
+p.y = 4
+p.norm()
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Integer(7));
+}
+
+#[test]
+fn test_init_result_is_instance_even_with_bare_return() {
+    let (result, value) = run_code(
+        r#"
+class Counter:
+    def __init__(self, start):
+        self.count = start + 1
+        return
+
+Counter(20).count
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Integer(21));
+}
+
+#[test]
+fn test_inherited_init_is_used_for_construction() {
+    let (result, value) = run_code(
+        r#"
+class Base:
+    def __init__(self, name):
+        self.name = name
+
+class Child(Base):
+    def greet(self):
+        return "hi " + self.name
+
+Child("sam").greet()
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::String("hi sam".to_string()));
+}
+
+#[test]
+fn test_init_arity_is_validated() {
+    let message = run_error(
+        r#"
+class Pair:
+    def __init__(self, a, b):
+        self.a = a
+
+Pair(1)
+"#,
+    );
+    assert_eq!(
+        message,
+        "TypeError: Pair.__init__() missing 1 required positional argument: 'b'"
+    );
+}
+
+#[test]
+fn test_true_division_always_returns_float() {
+    let (result, value) = run_code("4 / 2");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Float(2.0));
+}
+
+#[test]
+fn test_floor_division() {
+    let cases = [
+        ("7 // 2", ObjectType::Integer(3)),
+        ("-7 // 2", ObjectType::Integer(-4)),
+        ("7 // -2", ObjectType::Integer(-4)),
+        ("7.0 // 2", ObjectType::Float(3.0)),
+        ("-7 // 2.0", ObjectType::Float(-4.0)),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(*value, expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_floor_division_by_zero_is_runtime_error() {
+    let chunk = Compiler::compile("1 // 0").expect("Compilation failed");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+}
+
+#[test]
+fn test_divmod_builtin() {
+    let int_pair = |q, r| {
+        ObjectType::Tuple(vec![
+            Rc::new(ObjectType::Integer(q)),
+            Rc::new(ObjectType::Integer(r)),
+        ])
+    };
+    let (result, value) = run_code("divmod(7, 2)");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, int_pair(3, 1));
+
+    let (_, value) = run_code("divmod(-7, 2)");
+    assert_eq!(*value, int_pair(-4, 1));
+
+    let (_, value) = run_code("divmod(7.5, 2)");
+    assert_eq!(
+        *value,
+        ObjectType::Tuple(vec![
+            Rc::new(ObjectType::Float(3.0)),
+            Rc::new(ObjectType::Float(1.5))
+        ])
+    );
+
+    assert_eq!(
+        run_error("divmod(1, 0)"),
+        "ZeroDivisionError: integer division or modulo by zero"
+    );
+    assert_eq!(
+        run_error("divmod(\"a\", 2)"),
+        "TypeError: unsupported operand type(s) for divmod(): 'str' and 'int'"
+    );
+}
+
+#[test]
+fn test_modulo_takes_sign_of_divisor() {
+    let cases = [
+        ("7 % 3", ObjectType::Integer(1)),
+        ("-7 % 3", ObjectType::Integer(2)),
+        ("7 % -3", ObjectType::Integer(-2)),
+        ("-7 % -3", ObjectType::Integer(-1)),
+        ("7.5 % 2", ObjectType::Float(1.5)),
+        ("-7.5 % 2", ObjectType::Float(0.5)),
+        ("7 % -2.5", ObjectType::Float(-0.5)),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(*value, expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_floor_division_and_modulo_are_consistent() {
+    for (a, b) in [(7, 3), (-7, 3), (7, -3), (-7, -3), (0, 5), (6, -3)] {
+        let source = format!("q = {a} // {b}; r = {a} % {b}; q * {b} + r");
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(*value, ObjectType::Integer(a), "{}", source);
+    }
+}
+
+#[test]
+fn test_modulo_by_zero_is_runtime_error() {
+    for source in ["1 % 0", "1.5 % 0"] {
+        let chunk = Compiler::compile(source).expect("Compilation failed");
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+    }
+}
+
+#[test]
+fn test_string_indexing_by_character() {
+    let cases = [
+        ("'hello'[1]", "e"),
+        ("'hello'[-1]", "o"),
+        ("s = 'héllo'; s[1]", "é"),
+        ("s = 'naïve'; s[-3]", "ï"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(
+            *value,
+            ObjectType::String(expected.to_string()),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_index_errors_are_reported() {
+    assert_eq!(
+        run_error("s = 'abc'; s[3]"),
+        "IndexError: string index out of range"
+    );
+    assert_eq!(
+        run_error("s = 'abc'; s[-4]"),
+        "IndexError: string index out of range"
+    );
+    assert_eq!(
+        run_error("items = [1, 2]; items[5]"),
+        "IndexError: list index out of range"
+    );
+    assert_eq!(run_error("d = {'a': 1}; d['b']"), "KeyError: 'b'");
+    assert_eq!(
+        run_error("s = 'abc'; s['x']"),
+        "TypeError: str indices must be integers or slices, not str"
+    );
+    assert_eq!(
+        run_error("n = 5; n[0]"),
+        "TypeError: 'int' object is not subscriptable"
+    );
+}
+
+#[test]
+fn test_string_operations_use_unicode_scalar_values() {
+    let int_cases = [
+        ("len('héllo')", 5),
+        ("len('👍🏽')", 2),
+        ("len('e\u{301}')", 2),
+        ("len('日本語')", 3),
+    ];
+    for (source, expected) in int_cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(*value, ObjectType::Integer(expected), "{}", source);
+    }
+
+    let string_cases = [
+        ("s = 'a👍b'; s[1]", "👍"),
+        ("s = 'a👍b😀c'; s[1:4]", "👍b😀"),
+        ("s = 'a👍b😀c'; s[::-1]", "c😀b👍a"),
+        ("s = 'e\u{301}x'; s[1]", "\u{301}"),
+        ("'straße'.upper()", "STRASSE"),
+        ("'ÉCOLE'.lower()", "école"),
+        ("'ñandú'.upper()", "ÑANDÚ"),
+    ];
+    for (source, expected) in string_cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(
+            *value,
+            ObjectType::String(expected.to_string()),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_string_iteration_yields_characters() {
+    let (result, value) = run_code(
+        r#"
+count = 0
+last = ""
+for ch in "a😀é":
+    count = count + 1
+    last = ch
+last
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::String("é".to_string()));
+}
+
+#[test]
+fn test_ordering_comparisons() {
+    let cases = [
+        ("3 > 2", true),
+        ("2 >= 2", true),
+        ("2 <= 1", false),
+        ("1.5 > 1", true),
+        ("'apple' < 'banana'", true),
+        ("'apple' < 'app'", false),
+        ("'Zebra' < 'apple'", true),
+        ("'b' >= 'b'", true),
+        ("r = [1, 2, 3] < [1, 3]; r", true),
+        ("r = [1, 2] < [1, 2, 0]; r", true),
+        ("r = [1, 2] <= [1, 2]; r", true),
+        ("r = [2] > [1, 9, 9]; r", true),
+        ("divmod(7, 2) > divmod(7, 3)", true),
+        ("divmod(7, 2) >= divmod(9, 2)", false),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(*value, ObjectType::Boolean(expected), "{}", source);
+    }
+}
+
+#[test]
+fn test_lists_and_tuples_concatenate() {
+    let cases = [
+        ("[1] + [9]", "[1, 9]"),
+        ("a = [1]\na += [2]\na", "[1, 2]"),
+        ("tuple([1, 2]) + tuple([3])", "(1, 2, 3)"),
+        ("x = [[0]]\nx[0] += [5]\nx", "[[0, 5]]"),
+        ("a = [1]\nb = a + [2]\n[a, b]", "[[1], [1, 2]]"),
+        ("sum([[1], [2]], [])", "[1, 2]"),
+    ];
+    for (source, expected) in cases {
+        let (result, value) = run_code(source);
+        assert_eq!(result, InterpretResult::Ok, "{}", source);
+        assert_eq!(value.to_string(), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_operand_type_errors_name_the_types() {
+    let cases = [
+        (
+            "x = 1 + [1]",
+            "TypeError: unsupported operand type(s) for +: 'int' and 'list'",
+        ),
+        (
+            "x = 2.5 - 'a'",
+            "TypeError: unsupported operand type(s) for -: 'float' and 'str'",
+        ),
+        (
+            "x = [1] / 2",
+            "TypeError: unsupported operand type(s) for /: 'list' and 'int'",
+        ),
+        (
+            "x = 7 // {}",
+            "TypeError: unsupported operand type(s) for //: 'int' and 'dict'",
+        ),
+        (
+            "x = None % 2",
+            "TypeError: unsupported operand type(s) for %: 'NoneType' and 'int'",
+        ),
+        (
+            "x = 'a' + 1",
+            "TypeError: can only concatenate str (not \"int\") to str",
+        ),
+        (
+            "x = [1]; x += 2",
+            "TypeError: can only concatenate list (not \"int\") to list",
+        ),
+        (
+            "x = [1] + tuple([2])",
+            "TypeError: can only concatenate list (not \"tuple\") to list",
+        ),
+        (
+            "x = 'a' * 1.5",
+            "TypeError: can't multiply sequence by non-int of type 'float'",
+        ),
+        (
+            "x = [] * [1]",
+            "TypeError: can't multiply sequence by non-int of type 'list'",
+        ),
+        (
+            "class Point:\n    def __init__(self):\n        self.x = 1\nx = Point() + 1",
+            "TypeError: unsupported operand type(s) for +: 'Point' and 'int'",
+        ),
+        (
+            "x = 5; x[0] = 1",
+            "TypeError: 'int' object does not support item assignment",
+        ),
+        (
+            "x = [1]; x['a'] = 1",
+            "TypeError: list indices must be integers or slices, not str",
+        ),
+        (
+            "x = [1]; x[3] = 1",
+            "IndexError: list assignment index out of range",
+        ),
+        (
+            "x = 1 in 3",
+            "TypeError: argument of type 'int' is not iterable",
+        ),
+        (
+            "x = 1 in 'abc'",
+            "TypeError: 'in <string>' requires string as left operand, not int",
+        ),
+        ("x = [1] in {}", "TypeError: unhashable type: 'list'"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+
+    let (_, value) = run_code("1 in {'a': 1}");
+    assert_eq!(*value, ObjectType::Boolean(false));
+}
+
+#[test]
+fn test_division_by_zero_and_overflow_errors() {
+    let cases = [
+        ("x = 0; 1 / x", "ZeroDivisionError: division by zero"),
+        (
+            "x = 0; 1 // x",
+            "ZeroDivisionError: integer division or modulo by zero",
+        ),
+        ("x = 0; 1 % x", "ZeroDivisionError: integer modulo by zero"),
+        (
+            "x = 0.0; 1 // x",
+            "ZeroDivisionError: float floor division by zero",
+        ),
+        ("x = 0; 1.5 % x", "ZeroDivisionError: float modulo by zero"),
+        (
+            "x = 9223372036854775807; x + 1",
+            "OverflowError: integer overflow",
+        ),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(run_error(source), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_ordering_unorderable_types_is_type_error() {
+    assert_eq!(
+        run_error("1 < 'a'"),
+        "TypeError: '<' not supported between instances of 'int' and 'str'"
+    );
+    assert_eq!(
+        run_error("r = [1, 'x'] >= [1, 2]"),
+        "TypeError: '>=' not supported between instances of 'str' and 'int'"
+    );
+}
+
+fn list_of_ints(values: &[i64]) -> ObjectType {
+    ObjectType::List(
+        values
+            .iter()
+            .map(|v| Rc::new(ObjectType::Integer(*v)))
+            .collect(),
+    )
+}
+
+fn list_of_strs(values: &[&str]) -> ObjectType {
+    ObjectType::List(
+        values
+            .iter()
+            .map(|v| Rc::new(ObjectType::String(v.to_string())))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_sorted_builtin() {
+    let (result, value) = run_code("sorted([3, 1, 2])");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, list_of_ints(&[1, 2, 3]));
+
+    let (_, value) = run_code("sorted([3, 1, 2], reverse=True)");
+    assert_eq!(*value, list_of_ints(&[3, 2, 1]));
+
+    let (_, value) = run_code("sorted('cab')");
+    assert_eq!(*value, list_of_strs(&["a", "b", "c"]));
+}
+
+#[test]
+fn test_sorted_and_list_take_a_dicts_keys() {
+    let (result, value) = run_code("sorted({'b': 1, 'c': 2, 'a': 3})");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, list_of_strs(&["a", "b", "c"]));
+
+    let (_, value) = run_code("sorted({'b': 1, 'c': 2, 'a': 3}, reverse=True)");
+    assert_eq!(*value, list_of_strs(&["c", "b", "a"]));
+
+    let (_, value) = run_code(
+        "scores = {'b': 1, 'c': 2, 'a': 3}\ndef score(k):\n    return scores[k]\nsorted(scores, key=score)",
+    );
+    assert_eq!(*value, list_of_strs(&["b", "c", "a"]));
+
+    let (_, value) = run_code("list({'b': 1, 'c': 2, 'a': 3})");
+    assert_eq!(*value, list_of_strs(&["b", "c", "a"]));
+
+    assert_eq!(
+        run_error("sorted(5)"),
+        "TypeError: 'int' object is not iterable"
+    );
+}
+
+#[test]
+fn test_sorted_with_key_function_is_stable() {
+    let (result, value) = run_code(
+        r#"
+def length(word):
+    return len(word)
+
+words = ["bb", "a", "dd", "ccc", "e"]
+sorted(words, key=length)
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, list_of_strs(&["a", "e", "bb", "dd", "ccc"]));
+
+    let (_, value) = run_code(
+        r#"
+def length(word):
+    return len(word)
+
+words = ["bb", "a", "dd", "ccc", "e"]
+sorted(words, key=length, reverse=True)
+"#,
+    );
+    assert_eq!(*value, list_of_strs(&["ccc", "bb", "dd", "a", "e"]));
+}
+
+#[test]
+fn test_sorted_key_can_be_closure_or_bound_method() {
+    let (result, value) = run_code(
+        r#"
+def distance_from(origin):
+    def distance(x):
+        d = x - origin
+        if d < 0:
+            d = 0 - d
+        return d
+    return distance
+
+sorted([1, 9, 4, 6], key=distance_from(5))
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, list_of_ints(&[4, 6, 1, 9]));
+
+    let (result, value) = run_code(
+        r#"
+class Negate:
+    def apply(self, x):
+        return 0 - x
+
+n = Negate()
+sorted([2, 3, 1], key=n.apply)
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, list_of_ints(&[3, 2, 1]));
+}
+
+#[test]
+fn test_list_sort_rebinds_variable() {
+    let (result, value) = run_code("xs = [3, 1, 2]; xs.sort(); xs");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, list_of_ints(&[1, 2, 3]));
+
+    let (result, value) = run_code(
+        r#"
+def negate(x):
+    return 0 - x
+
+def ordered():
+    ys = [1, 3, 2]
+    ys.sort(key=negate)
+    return ys
+
+ordered()
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, list_of_ints(&[3, 2, 1]));
+
+    let (_, value) = run_code("xs = ['b', 'c', 'a']; xs.sort(reverse=True); xs");
+    assert_eq!(*value, list_of_strs(&["c", "b", "a"]));
+}
+
+#[test]
+fn test_sort_errors() {
+    assert_eq!(
+        run_error("sorted([1, 'a'])"),
+        "TypeError: '<' not supported between instances of 'str' and 'int'"
+    );
+    assert_eq!(
+        run_error("sorted([1], cmp=2)"),
+        "TypeError: sort() got an unexpected keyword argument 'cmp'"
+    );
+    assert!(Compiler::compile("xs = [1]; xs.sort(1)").is_none());
+}
+
+#[test]
+fn test_keyword_arguments_for_user_functions() {
+    let (result, value) = run_code(
+        r#"
+def greet(name, greeting="Hello", punctuation="!"):
+    return greeting + " " + name + punctuation
+
+greet("sam", punctuation="?")
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::String("Hello sam?".to_string()));
+
+    let (_, value) = run_code(
+        r#"
+class Point:
+    def __init__(self, x, y=0):
+        self.x = x
+        self.y = y
+
+Point(y=5, x=2).y
+"#,
+    );
+    assert_eq!(*value, ObjectType::Integer(5));
+
+    let source = r#"
+def f(a, b):
+    return a
+"#;
+    assert_eq!(
+        run_error(&format!("{}f(1, c=2)", source)),
+        "TypeError: f() got an unexpected keyword argument 'c'"
+    );
+    assert_eq!(
+        run_error(&format!("{}f(1, a=2)", source)),
+        "TypeError: f() got multiple values for argument 'a'"
+    );
+    assert_eq!(
+        run_error(&format!("{}f(b=2)", source)),
+        "TypeError: f() missing 1 required positional argument: 'a'"
+    );
+    assert_eq!(
+        run_error("len(obj=[])"),
+        "TypeError: len() takes no keyword arguments"
+    );
+    assert!(Compiler::compile(&format!("{}f(a=1, 2)", source)).is_none());
+}
+
+#[test]
+fn test_container_literals_accept_expressions() {
+    let (result, value) = run_code("r = [-1, 2, -3.5]; r");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Integer(-1)),
+            Rc::new(ObjectType::Integer(2)),
+            Rc::new(ObjectType::Float(-3.5)),
+        ])
+    );
+
+    let (result, value) = run_code(
+        r#"
+def pair(a, b):
+    return [a, [b, a + b]]
+
+pair(1, 2)[1]
+"#,
+    );
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Integer(2)),
+            Rc::new(ObjectType::Integer(3)),
+        ])
+    );
+
+    let (result, value) = run_code("k = 'b'; d = {'a': [1, -2], k: {'c': k}}; d['b']['c']");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::String("b".to_string()));
+
+    let (_, value) = run_code("k = 'a'; d = {k: 1, 'a': 2}; len(d)");
+    assert_eq!(*value, ObjectType::Integer(1));
+
+    assert_eq!(
+        run_error("k = [1]; d = {k: 'one'}"),
+        "TypeError: unhashable type: 'list'"
+    );
+}
+
+#[test]
+fn test_container_literals_evaluate_elements_at_runtime() {
+    let source = r#"
+def compute():
+    return 42
+
+x = 1
+y = 2
+"#;
+    let (result, value) = run_code(&format!("{}r = [x, y + 1]; r", source));
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Integer(1)),
+            Rc::new(ObjectType::Integer(3)),
+        ])
+    );
+
+    let (_, value) = run_code(&format!("{}{{'a': compute()}}['a']", source));
+    assert_eq!(*value, ObjectType::Integer(42));
+
+    let (_, value) = run_code(&format!("{}[x, y][1]", source));
+    assert_eq!(*value, ObjectType::Integer(2));
+
+    let (_, value) = run_code(&format!("{}[n * 10 for n in [x, y]][-1]", source));
+    assert_eq!(*value, ObjectType::Integer(20));
+}
+
+#[test]
+fn test_trailing_commas_and_blank_lines_in_brackets() {
+    let source = r#"
+def add(
+    a,
+
+    b,
+):
+    return a + b
+
+xs = [
+    3,
+
+    1,
+]
+d = {'total': add(len(xs,), xs[0],),}
+d['total']
+"#;
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Integer(5));
+
+    let (_, value) = run_code("xs = [2, 1, 3]; xs.sort(reverse=True,); xs[0]");
+    assert_eq!(*value, ObjectType::Integer(3));
+
+    assert!(Compiler::compile("len([1],,)").is_none());
+    assert!(Compiler::compile("x = [1,,]").is_none());
+    assert!(Compiler::compile("def f(a,,):\n    return a").is_none());
+}
+
+#[test]
+fn test_integer_overflow_is_a_runtime_error() {
+    for source in [
+        "n = 9223372036854775807\nn + 1",
+        "n = -9223372036854775807 - 1\nn - 1",
+        "n = 4611686018427387904\nn * 2",
+        "m = -9223372036854775807 - 1\n-m",
+    ] {
+        let (result, _) = run_code(source);
+        assert_eq!(result, InterpretResult::RuntimeError, "{}", source);
+    }
+}
+
+#[test]
+fn test_range_too_large_is_a_runtime_error() {
+    let (result, _) = run_code("m = -9223372036854775807 - 1\nrange(m, 9223372036854775807)");
+    assert_eq!(result, InterpretResult::RuntimeError);
+    assert_eq!(
+        run_error("range(-9223372036854775807 - 1, 9223372036854775807, 1)"),
+        "OverflowError: range too large"
+    );
+    let (result, value) = run_code("list(range(9223372036854775806, 9223372036854775807, 2))");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![Rc::new(ObjectType::Integer(i64::MAX - 1))])
+    );
+}
+
+#[test]
+fn test_deep_recursion_with_many_arguments_is_a_recursion_error() {
+    let source = "def f(n, a, b, c, d):\n    return f(n + 1, a, b, c, d)\nf(0, 1, 2, 3, 4)";
+    assert_eq!(
+        run_error(source),
+        "RecursionError: maximum recursion depth exceeded"
+    );
+}
+
+fn integers(values: &[i64]) -> ObjectType {
+    ObjectType::List(
+        values
+            .iter()
+            .map(|value| Rc::new(ObjectType::Integer(*value)))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_chained_assignment_to_globals_and_locals() {
+    let (result, value) = run_code("a = b = c = 5\n[a, b, c]");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, integers(&[5, 5, 5]));
+
+    let source = "def f():\n    x = y = 2\n    y = y + 1\n    return [x, y]\nf()";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, integers(&[2, 3]));
+}
+
+#[test]
+fn test_chained_assignment_evaluates_value_once() {
+    let source = "calls = []\ndef f():\n    calls.append(1)\n    return len(calls)\na = b = f()\nresult = [a, b, len(calls)]";
+    let (_, value) = run_code(source);
+    assert_eq!(*value, integers(&[1, 1, 1]));
+}
+
+#[test]
+fn test_chained_assignment_to_attributes_and_subscripts() {
+    let source = "class P:\n    def __init__(self):\n        self.x = 0\np = P()\nxs = [0, 0]\np.x = xs[1] = q = 7\n[p.x, xs[0], xs[1], q]";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, integers(&[7, 0, 7, 7]));
+
+    let source = "def f():\n    ys = [1, 2]\n    ys[0] = z = 4\n    return [ys[0], ys[1], z]\nf()";
+    let (_, value) = run_code(source);
+    assert_eq!(*value, integers(&[4, 2, 4]));
+}
+
+#[test]
+fn test_unpacking_assignment() {
+    let (result, value) = run_code("a, b, c = [1, 2, 3]\nresult = [c, b, a]");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, integers(&[3, 2, 1]));
+
+    let (_, value) = run_code("a, b = 1, 2\na, b = b, a\nresult = [a, b]");
+    assert_eq!(*value, integers(&[2, 1]));
+
+    let source = "def f(pair):\n    x, y = pair\n    return [y, x]\nf([4, 5])";
+    let (_, value) = run_code(source);
+    assert_eq!(*value, integers(&[5, 4]));
+}
+
+#[test]
+fn test_starred_unpacking_binds_leftover_items() {
+    let (_, value) = run_code("first, *rest = [1, 2, 3, 4]\nrest");
+    assert_eq!(*value, integers(&[2, 3, 4]));
+
+    let (_, value) = run_code("*init, last = [1, 2, 3]\ninit");
+    assert_eq!(*value, integers(&[1, 2]));
+
+    let (_, value) = run_code("a, *middle, z = [1, 2]\nresult = [a, z, len(middle)]");
+    assert_eq!(*value, integers(&[1, 2, 0]));
+}
+
+#[test]
+fn test_unpacking_length_mismatch_is_a_value_error() {
+    assert_eq!(
+        run_error("a, b = [1, 2, 3]"),
+        "ValueError: too many values to unpack (expected 2)"
+    );
+    assert_eq!(
+        run_error("a, b, c = [1, 2]"),
+        "ValueError: not enough values to unpack (expected 3, got 2)"
+    );
+    assert_eq!(
+        run_error("a, *b, c = [1]"),
+        "ValueError: not enough values to unpack (expected at least 2, got 1)"
+    );
+    assert_eq!(
+        run_error("a, b = 1"),
+        "TypeError: cannot unpack non-iterable int object"
+    );
+}
+
+#[test]
+fn test_postfix_operations_on_expression_results() {
+    let (_, value) = run_code("a = \"ab\"\nb = \"cd\"\n(a + b)[2]");
+    assert_eq!(*value, ObjectType::String("c".to_string()));
+
+    let (_, value) = run_code("def f():\n    return \"hello\"\nf()[1:3]");
+    assert_eq!(*value, ObjectType::String("el".to_string()));
+
+    let (_, value) = run_code("(1 + 2) * 3");
+    assert_eq!(*value, ObjectType::Integer(9));
+
+    let (_, value) = run_code("def f():\n    return [4, 5]\n(f)()[1]");
+    assert_eq!(*value, ObjectType::Integer(5));
+}
+
+#[test]
+fn test_bracket_at_line_start_begins_new_statement() {
+    let (result, value) = run_code("def f():\n    return [1]\nx = f()\n[x, 2]");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::List(vec![Rc::new(ObjectType::Integer(1))])),
+            Rc::new(ObjectType::Integer(2)),
+        ])
+    );
+}
+
+#[test]
+fn test_not_in_and_is_not_operators() {
+    let (_, value) = run_code("xs = [1, 2, 3]\nresult = [4 not in xs, 2 not in xs]");
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Boolean(true)),
+            Rc::new(ObjectType::Boolean(false)),
+        ])
+    );
+
+    let (_, value) = run_code("x = None\nresult = [x is None, x is not None, 5 is not None]");
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Boolean(true)),
+            Rc::new(ObjectType::Boolean(false)),
+            Rc::new(ObjectType::Boolean(true)),
+        ])
+    );
+
+    let (_, value) = run_code("a = [1]\nb = a\nresult = [a is b, a is [1], a is not b]");
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Boolean(true)),
+            Rc::new(ObjectType::Boolean(false)),
+            Rc::new(ObjectType::Boolean(false)),
+        ])
+    );
+}
+
+#[test]
+fn test_comparison_and_not_precedence() {
+    // Comparisons bind more loosely than arithmetic
+    let (_, value) = run_code("3 == 1 + 2");
+    assert_eq!(*value, ObjectType::Boolean(true));
+    let (_, value) = run_code("xs = [3]\n1 + 2 not in xs");
+    assert_eq!(*value, ObjectType::Boolean(false));
+
+    // `not` binds more loosely than comparisons
+    let (_, value) = run_code("xs = [1]\nnot 1 in xs");
+    assert_eq!(*value, ObjectType::Boolean(false));
+    let (_, value) = run_code("not 1 == 2");
+    assert_eq!(*value, ObjectType::Boolean(true));
+    let (_, value) = run_code("x = None\nnot x is not None");
+    assert_eq!(*value, ObjectType::Boolean(true));
+}
+
+fn strings(values: &[&str]) -> ObjectType {
+    ObjectType::List(
+        values
+            .iter()
+            .map(|value| Rc::new(ObjectType::String(value.to_string())))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_import_binds_one_shared_module() {
+    let (result, value) = run_code("import re\nimport re as r\nre is r");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Boolean(true));
+
+    let (_, value) =
+        run_code("def f():\n    import re as r\n    return r.sub(\"o\", \"0\", \"foo\")\nf()");
+    assert_eq!(*value, ObjectType::String("f00".to_string()));
+
+    let (_, value) = run_code(
+        "from re import findall as fa, sub\n[fa(\"b\", \"abcb\"), sub(\"b\", \"\", \"abcb\")]",
+    );
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(strings(&["b", "b"])),
+            Rc::new(ObjectType::String("ac".to_string())),
+        ])
+    );
+}
+
+#[test]
+fn test_import_errors() {
+    assert_eq!(
+        run_error("import nosuch"),
+        "ModuleNotFoundError: No module named 'nosuch'"
+    );
+    assert_eq!(
+        run_error("from re import nope"),
+        "ImportError: cannot import name 'nope' from 're'"
+    );
+}
+
+#[test]
+fn test_re_match_object_methods() {
+    let source = "import re\nm = re.match(\"(\\w+) (x)?(\\w+)\", \"hello world!\")\n\
+                  [m.group(), m.group(3, 1), m.groups(), m.span(3), m.start(2), m.end()]";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    let text = |s: &str| Rc::new(ObjectType::String(s.to_string()));
+    let pair = |a: i64, b: i64| {
+        Rc::new(ObjectType::Tuple(vec![
+            Rc::new(ObjectType::Integer(a)),
+            Rc::new(ObjectType::Integer(b)),
+        ]))
+    };
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            text("hello world"),
+            Rc::new(ObjectType::Tuple(vec![text("world"), text("hello")])),
+            Rc::new(ObjectType::Tuple(vec![
+                text("hello"),
+                Rc::new(ObjectType::Nil),
+                text("world"),
+            ])),
+            pair(6, 11),
+            Rc::new(ObjectType::Integer(-1)),
+            Rc::new(ObjectType::Integer(11)),
+        ])
+    );
+
+    let (_, value) = run_code("import re\nre.match(\"b\", \"abc\")");
+    assert_eq!(*value, ObjectType::Nil);
+    let (_, value) = run_code("import re\nre.search(\"b+\", \"abbc\").span()");
+    assert_eq!(*value, *pair(1, 3));
+}
+
+#[test]
+fn test_re_findall_and_sub() {
+    let (_, value) = run_code("import re\nre.findall(\"\\d+\", \"a1 b22 c333\")");
+    assert_eq!(*value, strings(&["1", "22", "333"]));
+    // Empty matches are allowed right after a non-empty one, as in CPython
+    let (_, value) = run_code("import re\nre.findall(\"a*\", \"baaa\")");
+    assert_eq!(*value, strings(&["", "aaa", ""]));
+
+    let (_, value) = run_code("import re\nre.sub(\"(\\w)(\\d)\", \"\\2\\g<1>\", \"a1 b2 c3\", 2)");
+    assert_eq!(*value, ObjectType::String("1a 2b c3".to_string()));
+    let (_, value) = run_code("import re\nre.sub(\"x*\", \"-\", \"abc\")");
+    assert_eq!(*value, ObjectType::String("-a-b-c-".to_string()));
+}
+
+#[test]
+fn test_sys_recursion_limit() {
+    let source = "import sys\ndef depth(n):\n    if n == 0:\n        return 0\n    return 1 + depth(n - 1)\n\
+                  before = sys.getrecursionlimit()\nsys.setrecursionlimit(500)\n\
+                  [before, sys.getrecursionlimit(), depth(400)]";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, integers(&[64, 500, 400]));
+
+    let source = "import sys\nsys.setrecursionlimit(10)\ndef f(n):\n    return f(n + 1)\nf(0)";
+    assert_eq!(
+        run_error(source),
+        "RecursionError: maximum recursion depth exceeded"
+    );
+    let source = "import sys\ndef f(n):\n    if n == 0:\n        sys.setrecursionlimit(3)\n    return f(n - 1)\nf(5)";
+    assert_eq!(
+        run_error(source),
+        "RecursionError: cannot set the recursion limit to 3 at the recursion depth 7: the limit is too low"
+    );
+}
+
+#[test]
+fn test_sys_setrecursionlimit_errors() {
+    assert_eq!(
+        run_error("import sys\nsys.setrecursionlimit(0)"),
+        "ValueError: recursion limit must be greater or equal than 1"
+    );
+    assert_eq!(
+        run_error("import sys\nsys.setrecursionlimit(100000)"),
+        "ValueError: recursion limit must be at most 4096"
+    );
+    assert_eq!(
+        run_error("import sys\nsys.setrecursionlimit(\"10\")"),
+        "TypeError: 'str' object cannot be interpreted as an integer"
+    );
+    assert_eq!(
+        run_error("import sys\nsys.setrecursionlimit()"),
+        "TypeError: setrecursionlimit() takes exactly one argument (0 given)"
+    );
+    assert_eq!(
+        run_error("import sys\nsys.getrecursionlimit(1)"),
+        "TypeError: getrecursionlimit() takes no arguments (1 given)"
+    );
+}
+
+#[test]
+fn test_sys_vm_counters() {
+    // The counter is read before the call's own instruction finishes, so
+    // consecutive reads differ by the instructions between them
+    let source = "import sys\nstart = sys.getinstructioncount()\nend = sys.getinstructioncount()\n\
+                  [start > 0, end - start < 10]";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    let booleans = |values: &[bool]| {
+        ObjectType::List(
+            values
+                .iter()
+                .map(|value| Rc::new(ObjectType::Boolean(*value)))
+                .collect(),
+        )
+    };
+    assert_eq!(*value, booleans(&[true, true]));
+
+    let source = "import sys\nbefore = sys.getobjectcount()\n\
+                  items = [[1], [2], [3]]\n[before > 0, sys.getobjectcount() - before >= 7]";
+    let (_, value) = run_code(source);
+    assert_eq!(*value, booleans(&[true, true]));
+}
+
+const NODE_CLASS: &str = "class Node:\n    def __init__(self, name):\n        self.name = name\n";
+
+#[test]
+fn test_gc_collect_frees_unreachable_cycles() {
+    let source = format!(
+        "import gc\n{}def make():\n    a = Node(\"a\")\n    b = Node(\"b\")\n    a.other = b\n    b.other = a\n    return 0\n\
+         keep = Node(\"keep\")\nkeep.me = keep\nbefore = gc.collect()\nmake()\nmake()\n\
+         [before, gc.collect(), gc.collect(), keep.me.name == \"keep\"]",
+        NODE_CLASS
+    );
+    let (result, value) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Integer(0)),
+            Rc::new(ObjectType::Integer(4)),
+            Rc::new(ObjectType::Integer(0)),
+            Rc::new(ObjectType::Boolean(true)),
+        ])
+    );
+}
+
+#[test]
+fn test_gc_collects_automatically_unless_disabled() {
+    let source = format!(
+        "import gc\n{}def churn(count):\n    i = 0\n    node = 0\n    while i < count:\n        \
+         node = Node(i)\n        node.me = node\n        i = i + 1\n    return gc.collect()\n\
+         automatic = churn(5000)\ngc.disable()\n[automatic < 1000, churn(3000), gc.isenabled()]",
+        NODE_CLASS
+    );
+    let (result, value) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Boolean(true)),
+            Rc::new(ObjectType::Integer(3000)),
+            Rc::new(ObjectType::Boolean(false)),
+        ])
+    );
+    oxython::vm::gc::set_enabled(true);
+}
+
+#[test]
+fn test_cycles_outliving_their_vm_are_freed_by_collect() {
+    for cycle in ["a.me = a", "def get():\n        return a\n    a.get = get"] {
+        let source = format!(
+            "{}def make():\n    a = Node(\"a\")\n    {}\n    return a\nmake()",
+            NODE_CLASS, cycle
+        );
+        let (result, value) = run_code(&source);
+        assert_eq!(result, InterpretResult::Ok);
+        let ObjectType::Instance(instance) = &*value else {
+            panic!("expected an instance, got {:?}", value);
+        };
+        let instance = Rc::downgrade(instance);
+        drop(value);
+        assert!(instance.upgrade().is_some(), "{} should leak", cycle);
+        assert!(oxython::vm::gc::collect() > 0);
+        assert!(instance.upgrade().is_none(), "{} was not freed", cycle);
+    }
+}
+
+#[test]
+fn test_re_nested_quantifiers_do_not_backtrack_exponentially() {
+    let source = format!("import re\nre.match(\"(a*)*b\", \"{}\")", "a".repeat(5000));
+    let (result, value) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Nil);
+}
+
+#[test]
+fn test_re_pattern_errors() {
+    assert_eq!(
+        run_error("import re\nre.match(\"a**\", \"a\")"),
+        "re.error: multiple repeat at position 2"
+    );
+    assert_eq!(
+        run_error("import re\nre.search(\"(a\", \"a\")"),
+        "re.error: missing ), unterminated subpattern"
+    );
+    assert_eq!(
+        run_error("import re\nre.sub(\"a\", \"\\3\", \"a\")"),
+        "re.error: invalid group reference 3"
+    );
+    assert_eq!(
+        run_error("import re\nre.findall(\"a\", 5)"),
+        "TypeError: expected string or bytes-like object, got 'int'"
+    );
+}
+
+#[test]
+fn test_string_splitlines() {
+    // String literals may span lines, which puts real line breaks in the text
+    let (result, value) = run_code("\"x\ny\r\nz\r\n\nw\r\".splitlines()");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, strings(&["x", "y", "z", "", "w"]));
+
+    let (_, value) = run_code("\"x\ny\r\n\".splitlines(True)");
+    assert_eq!(*value, strings(&["x\n", "y\r\n"]));
+    let (_, value) = run_code("\"\".splitlines()");
+    assert_eq!(*value, strings(&[]));
+}
+
+#[test]
+fn test_string_split_and_join_round_trip() {
+    let (_, value) = run_code("line = \"a, b,,c\"\n\",\".join(line.split(\",\")) == line");
+    assert_eq!(*value, ObjectType::Boolean(true));
+
+    let (_, value) = run_code("\" a\tb\n c \".split()");
+    assert_eq!(*value, strings(&["a", "b", "c"]));
+    let (_, value) = run_code("\"a b c\".rsplit(\" \", 1)");
+    assert_eq!(*value, strings(&["a b", "c"]));
+    let (_, value) = run_code("\"a b c\".split(\" \", 0)");
+    assert_eq!(*value, strings(&["a b c"]));
+}
+
+#[test]
+fn test_string_join_takes_a_dicts_keys() {
+    let (result, value) = run_code("\", \".join({\"b\": 1, \"a\": 2})");
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::String("b, a".to_string()));
+}
+
+#[test]
+fn test_string_method_errors() {
+    assert_eq!(
+        run_error("\"a\".split(\"\")"),
+        "ValueError: empty separator"
+    );
+    assert_eq!(
+        run_error("\"a\".partition(\"\")"),
+        "ValueError: empty separator"
+    );
+    assert_eq!(
+        run_error("\",\".join([\"a\", 1])"),
+        "TypeError: sequence item 1: expected str instance, int found"
+    );
+    assert_eq!(
+        run_error("\"a\".split(\",\", 1, 2)"),
+        "TypeError: split() takes at most 2 arguments (3 given)"
+    );
+}
+
+#[test]
+fn test_finally_runs_on_break_and_continue_in_loops() {
+    let source = "\
+log = []
+for i in [1, 2, 3, 4]:
+    try:
+        if i == 2:
+            continue
+        if i == 4:
+            break
+        log.append(i)
+    finally:
+        log.append(10 * i)
+log";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, integers(&[1, 10, 20, 3, 30, 40]));
+
+    let source = "\
+log = []
+n = 0
+while n < 5:
+    n = n + 1
+    try:
+        try:
+            if n % 2 == 0:
+                continue
+            if n == 5:
+                break
+        finally:
+            log.append(n)
+    finally:
+        log.append(-n)
+log";
+    let (_, value) = run_code(source);
+    assert_eq!(*value, integers(&[1, -1, 2, -2, 3, -3, 4, -4, 5, -5]));
+}
+
+#[test]
+fn test_finally_runs_innermost_first_on_return() {
+    let source = "\
+log = []
+def find(items, target):
+    for item in items:
+        try:
+            for other in items:
+                try:
+                    if item + other == target:
+                        return [item, other]
+                finally:
+                    log.append(other)
+        finally:
+            log.append(-item)
+    return []
+[find([1, 2, 3], 4), log]";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(integers(&[1, 3])),
+            Rc::new(integers(&[1, 2, 3, -1])),
+        ])
+    );
+
+    let source = "\
+def f():
+    try:
+        return \"body\"
+    finally:
+        note = \"finally\"
+        log.append(note)
+log = []
+[f(), log]";
+    let (_, value) = run_code(source);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::String("body".to_string())),
+            Rc::new(strings(&["finally"])),
+        ])
+    );
+}
+
+#[test]
+fn test_break_in_finally_discards_the_exception() {
+    let source = "\
+def f():
+    for i in [1, 2]:
+        try:
+            raise KeyError(i)
+        finally:
+            break
+    return \"swallowed\"
+f()";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::String("swallowed".to_string()));
+}
+
+#[test]
+fn test_except_clauses_match_by_class() {
+    let source = "\
+log = []
+for kind in [ValueError, IndexError, KeyError, TypeError]:
+    try:
+        try:
+            raise kind(\"message\")
+        except ValueError as e:
+            log.append(\"value \" + e.args[0])
+        except (KeyError, IndexError):
+            log.append(\"lookup\")
+    except Exception:
+        log.append(\"outer\")
+    else:
+        log.append(\"no error\")
+log";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        strings(&[
+            "value message",
+            "no error",
+            "lookup",
+            "no error",
+            "lookup",
+            "no error",
+            "outer"
+        ])
+    );
+
+    let source = "\
+class AppError(Exception):
+    def __init__(self, code):
+        super().__init__(\"failed\", code)
+        self.code = code
+try:
+    [1][3]
+except LookupError as e:
+    first = e.args[0]
+try:
+    raise AppError(7)
+except Exception as e:
+    second = [e.code, e.args]
+[first, second]";
+    let (_, value) = run_code(source);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::String("list index out of range".to_string())),
+            Rc::new(ObjectType::List(vec![
+                Rc::new(ObjectType::Integer(7)),
+                Rc::new(ObjectType::Tuple(vec![
+                    Rc::new(ObjectType::String("failed".to_string())),
+                    Rc::new(ObjectType::Integer(7)),
+                ])),
+            ])),
+        ])
+    );
+}
+
+#[test]
+fn test_bare_raise_reraises_the_handled_exception() {
+    let source = "\
+def f():
+    try:
+        raise TypeError(\"first\")
+    except TypeError:
+        try:
+            raise ValueError(\"second\")
+        except ValueError:
+            log.append(\"inner\")
+        raise
+log = []
+try:
+    f()
+except TypeError as e:
+    log.append(e.args[0])
+log";
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, strings(&["inner", "first"]));
+}
+
+#[test]
+fn test_uncaught_exceptions_run_finally_and_propagate() {
+    let source = "\
+def f():
+    try:
+        raise ValueError(\"boom\")
+    finally:
+        print(\"cleanup\")
+f()";
+    assert_eq!(run_error(source), "ValueError: boom");
+    assert_eq!(
+        run_error("try:\n    [][0]\nexcept KeyError:\n    1"),
+        "IndexError: list index out of range"
+    );
+}
+
+#[test]
+fn test_raise_errors() {
+    assert_eq!(
+        run_error("raise 5"),
+        "TypeError: exceptions must derive from BaseException"
+    );
+    assert_eq!(
+        run_error("raise"),
+        "RuntimeError: No active exception to reraise"
+    );
+    assert_eq!(
+        run_error("try:\n    raise ValueError\nexcept 3:\n    1"),
+        "TypeError: catching classes that do not inherit from BaseException is not allowed"
+    );
+}
+
+#[test]
+fn test_decorators_apply_bottom_up() {
+    let source = r#"
+def twice(func):
+    def wrapper(x):
+        return func(func(x))
+    return wrapper
+
+def add_one(func):
+    def wrapper(x):
+        return func(x) + 1
+    return wrapper
+
+@twice
+@add_one
+def double(x):
+    return x * 2
+
+double(3)
+"#;
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Integer(15));
+}
+
+#[test]
+fn test_class_body_annotations_and_defaults() {
+    let source = r#"
+class Config:
+    name: str
+    retries: int = 3
+
+c = Config()
+[Config.retries, c.retries, Config.__annotations__["name"]]
+"#;
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Integer(3)),
+            Rc::new(ObjectType::Integer(3)),
+            Rc::new(ObjectType::String("str".to_string())),
+        ])
+    );
+}
+
+#[test]
+fn test_dataclass_generates_init_repr_and_eq() {
+    let source = r#"
+from dataclasses import dataclass
+
+@dataclass
+class Point:
+    x: int
+    y: int = 0
+
+p = Point(1, y=2)
+[p.__repr__(), p == Point(1, 2), p == Point(1), Point(4).y]
+"#;
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        ObjectType::List(vec![
+            Rc::new(ObjectType::String("Point(x=1, y=2)".to_string())),
+            Rc::new(ObjectType::Boolean(true)),
+            Rc::new(ObjectType::Boolean(false)),
+            Rc::new(ObjectType::Integer(0)),
+        ])
+    );
+}
+
+#[test]
+fn test_dataclass_keeps_methods_the_class_defines() {
+    let source = r#"
+from dataclasses import dataclass
+
+@dataclass
+class Pair:
+    a: int
+    b: int
+
+    def __eq__(self, other):
+        return self.a == other.b
+
+Pair(1, 2) == Pair(5, 1)
+"#;
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*value, ObjectType::Boolean(true));
+}
+
+#[test]
+fn test_dataclass_errors() {
+    let source = r#"
+from dataclasses import dataclass
+
+@dataclass
+class Bad:
+    a: int = 1
+    b: int
+"#;
+    assert_eq!(
+        run_error(source),
+        "TypeError: non-default argument 'b' follows default argument"
+    );
+    assert_eq!(
+        run_error("from dataclasses import dataclass\ndataclass(3)"),
+        "TypeError: dataclass() argument must be a class, not 'int'"
+    );
+    assert_eq!(
+        run_error("from dataclasses import dataclass\n@dataclass\nclass P:\n    x: int\nP()"),
+        "TypeError: P.__init__() missing 1 required positional argument: 'x'"
+    );
+}
+
+#[test]
+fn test_native_errors_raise_their_exception_class() {
+    let source = r#"
+import re
+caught = []
+try:
+    divmod(1, 0)
+except ZeroDivisionError as e:
+    caught.append(e.args[0])
+try:
+    "a,b".split("")
+except ValueError as e:
+    caught.append(e.args[0])
+try:
+    re.search("(a", "a")
+except re.error as e:
+    caught.append(e.args[0])
+try:
+    re.match("(a)", "a").group(2)
+except IndexError as e:
+    caught.append(e.args[0])
+caught
+"#;
+    let (result, value) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        *value,
+        strings(&[
+            "integer division or modulo by zero",
+            "empty separator",
+            "missing ), unterminated subpattern",
+            "no such group",
+        ])
+    );
+}
+
+#[test]
+fn test_jumps_cross_more_than_64k_of_bytecode() {
+    // Each statement in the `if` body compiles to 8 bytes, so the jumps
+    // over it and back to the loop's start cross far more than 64KB
+    let mut source = String::from(
+        "def f(n):\n    y = 0\n    while n > 0:\n        n = n - 1\n        if n > 0:\n",
+    );
+    for _ in 0..10_000 {
+        source.push_str("            y = y + n\n");
+    }
+    source.push_str("    return y\n\nf(3)");
+    let (result, last_popped) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(30_000));
+
+    // The same at module level, where the `else` is jumped to and over too
+    let mut source = String::from("n = 3\ny = 0\nwhile n > 0:\n    n = n - 1\n    if n > 1:\n");
+    for _ in 0..10_000 {
+        source.push_str("        y = y + n\n");
+    }
+    source.push_str("    else:\n        y = y - 1\ny");
+    let chunk = Compiler::compile(&source).expect("Compilation failed");
+    let longest = chunk
+        .instructions()
+        .filter(|(_, opcode, _)| opcode.is_jump())
+        .map(|(_, _, operands)| jump_distance(operands))
+        .max();
+    assert!(longest.is_some_and(|distance| distance > usize::from(u16::MAX)));
+    let (result, last_popped) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(19_998));
+}
+
+#[test]
+fn test_chunks_hold_more_than_256_constants() {
+    // Every line adds a name and a number to the module's constant pool
+    let mut source: String = (0..300).map(|i| format!("v{} = {}.5\n", i, i)).collect();
+    source.push_str("def f():\n    return v299\nimport copy\n[f() + v0 + 0.25, copy.copy(v1)]");
+    let (result, last_popped) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(last_popped.to_string(), "[300.25, 1.5]");
+}
+
+#[test]
+fn test_functions_use_more_than_256_locals() {
+    let mut source = String::from("def f(n):\n");
+    for index in 0..300 {
+        source.push_str(&format!("    x{} = n\n", index));
+    }
+    // Comprehension variables and the exception binding land past slot 255
+    for line in [
+        "    s = [v * x299 for v in [1, 2, 3] if v > x299]",
+        "    c = [v if v > 1 else x299 for v in [1, 2, 3]]",
+        "    t = 0",
+        "    for i in range(4):",
+        "        t = t + i",
+        "    try:",
+        "        raise ValueError('e')",
+        "    except ValueError as err:",
+        "        t = t + x299",
+        "    return x0 + x299 + s[0] + c[0] + c[2] + t",
+        "",
+        "f(2)",
+    ] {
+        source.push_str(line);
+        source.push('\n');
+    }
+    let (result, last_popped) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(2 + 2 + 6 + 2 + 3 + 8));
+}
+
+#[test]
+fn test_functions_take_more_than_256_parameters() {
+    let names: Vec<_> = (0..300).map(|index| format!("p{}", index)).collect();
+    let source = format!(
+        "def f({}):\n    return p0 + p299\n\nf(*list(range(300)))",
+        names.join(", ")
+    );
+    let (result, last_popped) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(299));
+}
+
+#[test]
+fn test_closures_capture_more_than_256_variables() {
+    let mut source = String::from("def f(n):\n");
+    for index in 0..300 {
+        source.push_str(&format!("    x{} = n\n", index));
+    }
+    source.push_str("    def g():\n        nonlocal x299\n        t = 0\n");
+    for index in 0..300 {
+        source.push_str(&format!("        t = t + x{}\n", index));
+    }
+    source.push_str("        x299 = t\n        return x299\n    return g\n\nf(2)()");
+    let (result, last_popped) = run_code(&source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*last_popped, ObjectType::Integer(600));
+}
+
+#[test]
+fn test_entry_script_is_named_main() {
+    let source = "ran = False\nif __name__ == '__main__':\n    ran = True\n[__name__, ran]";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(last_popped.to_string(), "['__main__', True]");
+}
+
+#[test]
+fn test_module_runs_under_its_own_name() {
+    let source = "\
+ran = False
+if __name__ == '__main__':
+    ran = True
+def f():
+    return __name__
+[f(), f.__module__, ran]";
+    let chunk = Compiler::compile_with_module(source, "helpers").expect("Compilation failed");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret_module(chunk, "helpers"), InterpretResult::Ok);
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(last_popped.to_string(), "['helpers', 'helpers', False]");
+}
+
+#[test]
+fn test_eval_and_exec_run_against_globals() {
+    let source = "\
+x = 4
+exec('y = x + 2')
+exec('def g(n):' + chr(10) + '    return n + y')
+def f():
+    return eval('g(x)')
+[eval('x + 1'), y, f(), exec('z = 1')]";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(last_popped.to_string(), "[5, 6, 10, None]");
+}
+
+#[test]
+fn test_eval_with_globals_and_locals_dicts() {
+    let source = "\
+a = 100
+exec('c = a', {'a': 1})
+try:
+    leaked = c
+except NameError:
+    leaked = False
+[eval('a + b', {'a': 1, 'b': 5}, {'b': 2}), a, leaked]";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(last_popped.to_string(), "[3, 100, False]");
+}
+
+#[test]
+fn test_exec_binds_names_in_the_dicts_passed() {
+    let source = "\
+g = {'a': 1}
+exec('b = a + 1', g)
+l = {'z': 1}
+exec('y = a + z', g, l)
+exec('del z', g, l)
+def f():
+    ns = {}
+    exec('q = 4', ns)
+    return ns['q']
+[g, l, f()]";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(last_popped.to_string(), "[{'a': 1, 'b': 2}, {'y': 2}, 4]");
+}
+
+#[test]
+fn test_eval_and_exec_see_the_locals_of_the_function_calling_them() {
+    let source = "\
+x = 1
+def f(n):
+    m = 10
+    exec('m = 20')
+    return [eval('n + m + x'), m]
+f(2)";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(last_popped.to_string(), "[13, 10]");
+}
+
+#[test]
+fn test_eval_called_through_an_alias() {
+    let source = "\
+e = eval
+def apply(fn, items):
+    return [fn(item) for item in items]
+class K:
+    ev = eval
+[e('1 + 2'), apply(eval, ['3', '4']), K().ev('5')]";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(last_popped.to_string(), "[3, [3, 4], 5]");
+}
+
+#[test]
+fn test_eval_and_exec_errors() {
+    let source = "\
+caught = []
+try:
+    eval('1 +')
+except SyntaxError as e:
+    caught.append(e.args[0])
+try:
+    exec('n = 1 / 0')
+except ZeroDivisionError:
+    caught.append('ZeroDivisionError')
+caught";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        last_popped.to_string(),
+        "['invalid syntax (<string>, line 1)', 'ZeroDivisionError']"
+    );
+    assert_eq!(
+        run_error("eval('x = 1')"),
+        "SyntaxError: invalid syntax (<string>, line 1)"
+    );
+    assert_eq!(
+        run_error("eval(1)"),
+        "TypeError: eval() arg 1 must be a string, not 'int'"
+    );
+    assert_eq!(
+        run_error("exec('x = 1', [])"),
+        "TypeError: globals must be a dict, not list"
+    );
+}
+
+#[test]
+fn test_caught_exception_has_traceback() {
+    let source = "\
+def inner():
+    return 1 / 0
+def outer():
+    return inner()
+def main():
+    try:
+        outer()
+    except ZeroDivisionError as e:
+        return e.__traceback__
+tb = main()
+names = []
+while tb:
+    names.append([tb.tb_frame.f_code.co_name, tb.tb_lineno, tb.tb_frame.f_code.co_filename])
+    tb = tb.tb_next
+names";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        last_popped.to_string(),
+        "[['main', 7, '<script>'], ['outer', 4, '<script>'], ['inner', 2, '<script>']]"
+    );
+}
+
+#[test]
+fn test_traceback_module_formats_exceptions() {
+    let source = "\
+import traceback
+def fail():
+    raise ValueError('bad')
+try:
+    fail()
+except ValueError as e:
+    text = traceback.format_exc()
+    lines = traceback.format_exception(e)
+    entries = traceback.format_tb(e.__traceback__)
+[text, len(lines), len(entries), traceback.format_exc()]";
+    let (result, last_popped) = run_code(source);
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(
+        last_popped.to_string(),
+        "['Traceback (most recent call last):\\n  File \"<script>\", line 5, in <module>\\n  File \"<script>\", line 3, in fail\\nValueError: bad\\n', 4, 2, 'NoneType: None\\n']"
+    );
+}
+
+#[test]
+fn test_warn_writes_each_warning_once_per_place() {
+    let source = "\
+import warnings
+def old():
+    warnings.warn('old() is deprecated', DeprecationWarning)
+for i in range(3):
+    old()
+warnings.warn('plain')
+warnings.warn(RuntimeWarning('given as an instance'))";
+    let chunk = Compiler::compile(source).expect("Compilation failed");
+    let mut vm = VM::new();
+    vm.set_warning_output(Output::Captured(String::new()));
+    assert_eq!(vm.interpret(chunk.clone()), InterpretResult::Ok);
+    assert_eq!(
+        vm.take_warnings(),
+        "<script>:3: DeprecationWarning: old() is deprecated\n\
+<script>:6: UserWarning: plain\n\
+<script>:7: RuntimeWarning: given as an instance\n"
+    );
+
+    vm.set_warning_action(WarningAction::Always);
+    assert_eq!(vm.interpret(chunk.clone()), InterpretResult::Ok);
+    assert_eq!(vm.take_warnings().lines().count(), 5);
+
+    vm.set_warning_action(WarningAction::Ignore);
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(vm.take_warnings(), "");
+}
+
+#[test]
+fn test_warn_raises_warnings_as_errors() {
+    let source = "\
+import warnings
+caught = []
+try:
+    warnings.warn('careful', FutureWarning)
+except Warning as e:
+    caught.append(e.args[0])
+caught";
+    let chunk = Compiler::compile(source).expect("Compilation failed");
+    let mut vm = VM::new();
+    vm.set_warning_action(WarningAction::Error);
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(last_popped.to_string(), "['careful']");
+    assert_eq!(
+        run_error("import warnings\nwarnings.warn('x', ValueError)"),
+        "TypeError: category must be a Warning subclass, not 'type'"
+    );
+}
+
+#[test]
+fn test_interrupt_flag_raises_keyboard_interrupt() {
+    let source = "\
+log = []
+n = 0
+try:
+    try:
+        while True:
+            n = n + 1
+    except Exception:
+        log.append('Exception')
+    finally:
+        log.append('finally')
+except KeyboardInterrupt as e:
+    log.append(e.args)
+log";
+    let chunk = Compiler::compile(source).expect("Compilation failed");
+    let flag = Arc::new(AtomicBool::new(true));
+    let mut vm = VM::new();
+    vm.set_interrupt_flag(flag.clone());
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(last_popped.to_string(), "['finally', ()]");
+    assert!(!flag.load(Ordering::Relaxed));
+
+    flag.store(true, Ordering::Relaxed);
+    let chunk = Compiler::compile("while True:\n    x = 1").expect("Compilation failed");
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+    assert_eq!(vm.last_error().unwrap().kind, "KeyboardInterrupt");
+
+    // Loops inside callbacks and generators are interrupted too
+    for source in [
+        "def key(x):\n    while True:\n        x = 1\nsorted([1, 2], key=key)",
+        "def gen():\n    while True:\n        x = 1\n    yield x\nfor v in gen():\n    v",
+    ] {
+        flag.store(true, Ordering::Relaxed);
+        let chunk = Compiler::compile(source).expect("Compilation failed");
+        assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+        assert_eq!(vm.last_error().unwrap().kind, "KeyboardInterrupt");
+    }
+}
+
+/// Writes each `(file, source)` into a fresh directory named for `test`,
+/// making the directories files are in, and returns the directory.
+fn module_directory(test: &str, files: &[(&str, &str)]) -> String {
+    let mut dir = std::env::temp_dir();
+    dir.push(format!("oxython_test_{}_{}", std::process::id(), test));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, source) in files {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+    dir.to_str().unwrap().to_string()
+}
+
+fn run_with_search_path(source: &str, path: Vec<String>) -> (InterpretResult, VM) {
+    let chunk = Compiler::compile(source).expect("Compilation failed");
+    let mut vm = VM::new();
+    vm.capture_output();
+    vm.set_search_path(path);
+    let result = vm.interpret(chunk);
+    (result, vm)
+}
+
+#[test]
+fn test_tracebacks_and_warnings_name_the_files_run() {
+    let dir = module_directory(
+        "script_files",
+        &[("helper.py", "def boom():\n    return 1 / 0\n")],
+    );
+    let source = "\
+import traceback
+import warnings
+import helper
+warnings.warn('careful')
+try:
+    helper.boom()
+except ZeroDivisionError:
+    text = traceback.format_exc()
+[__file__, text]";
+    let chunk = Compiler::compile_with_module(source, "__main__").expect("Compilation failed");
+    let mut vm = VM::new();
+    vm.set_search_path(vec![dir.clone()]);
+    vm.set_script_file("app.py");
+    vm.set_warning_output(Output::Captured(String::new()));
+    assert_eq!(vm.interpret_module(chunk, "__main__"), InterpretResult::Ok);
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    let helper = std::path::Path::new(&dir).join("helper.py");
+    assert_eq!(
+        last_popped.to_string(),
+        format!(
+            "['app.py', 'Traceback (most recent call last):\\n  File \"app.py\", line 6, in <module>\\n  File \"{}\", line 2, in boom\\nZeroDivisionError: division by zero\\n']",
+            helper.display()
+        )
+    );
+    assert_eq!(vm.take_warnings(), "app.py:4: UserWarning: careful\n");
+
+    let chunk =
+        Compiler::compile_with_module("helper.boom()", "__main__").expect("Compilation failed");
+    assert_eq!(
+        vm.interpret_module(chunk, "__main__"),
+        InterpretResult::RuntimeError
+    );
+    let files: Vec<&str> = vm
+        .last_error()
+        .unwrap()
+        .traceback
+        .iter()
+        .map(|frame| frame.file.as_str())
+        .collect();
+    assert_eq!(files, ["app.py", helper.to_str().unwrap()]);
+}
+
+#[test]
+fn test_import_loads_source_modules_from_the_search_path() {
+    let first = module_directory(
+        "search_first",
+        &[(
+            "shapes.py",
+            "sides = 'four'\ndef describe(name):\n    return name + ' has ' + sides\n",
+        )],
+    );
+    let second = module_directory(
+        "search_second",
+        &[
+            ("shapes.py", "sides = 'three'\n"),
+            ("colors.py", "print('loading colors')\nred = 'ff0000'\n"),
+        ],
+    );
+    let source = "\
+sides = 'none'
+import shapes
+import colors
+import colors as again
+from shapes import describe
+[describe('square'), sides, shapes.sides, colors.red, again is colors, shapes.__name__]";
+    let (result, mut vm) = run_with_search_path(source, vec![first.clone(), second.clone()]);
+    assert_eq!(result, InterpretResult::Ok, "{:?}", vm.last_error());
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(
+        last_popped.to_string(),
+        "['square has four', 'none', 'four', 'ff0000', True, 'shapes']"
+    );
+    assert_eq!(vm.search_path(), [first.clone(), second.clone()]);
+    assert_eq!(vm.take_output(), "loading colors\n");
+
+    let chunk = Compiler::compile("import colors").expect("Compilation failed");
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(
+        vm.take_output(),
+        "",
+        "a module runs on its first import only"
+    );
+
+    let _ = std::fs::remove_dir_all(first);
+    let _ = std::fs::remove_dir_all(second);
+}
+
+#[test]
+fn test_sys_path_lists_and_changes_the_search_path() {
+    let dir = module_directory("sys_path", &[("late.py", "value = 'found'\n")]);
+    let source = format!(
+        "\
+import sys
+before = sys.path
+try:
+    import late
+except ModuleNotFoundError:
+    before.append('missing')
+sys.path.append('{}')
+import late
+from sys import path
+[before, len(path), late.value]",
+        dir
+    );
+    let (result, vm) = run_with_search_path(&source, vec!["first".to_string()]);
+    assert_eq!(result, InterpretResult::Ok, "{:?}", vm.last_error());
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(
+        last_popped.to_string(),
+        "[['first', 'missing'], 2, 'found']"
+    );
+    assert_eq!(vm.search_path(), ["first".to_string(), dir.clone()]);
+
+    assert_eq!(
+        run_error("import sys\nsys.path = 'here'"),
+        "TypeError: sys.path must be a list, not 'str'"
+    );
+    assert_eq!(
+        run_error("import sys\nsys.path = [1]"),
+        "TypeError: sys.path entries must be strings, not 'int'"
+    );
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_import_reports_errors_in_source_modules() {
+    let dir = module_directory(
+        "module_errors",
+        &[
+            ("broken.py", "x = 1\ny = = 2\n"),
+            ("failing.py", "def fail():\n    return 1 / 0\nfail()\n"),
+        ],
+    );
+    let (result, vm) = run_with_search_path("import broken", vec![dir.clone()]);
+    assert_eq!(result, InterpretResult::RuntimeError);
+    let error = vm.last_error().unwrap();
+    assert_eq!(error.kind, "SyntaxError");
+    assert!(
+        error.message.ends_with("broken.py, line 2)"),
+        "{}",
+        error.message
+    );
+
+    let source = "\
+caught = []
+for attempt in range(2):
+    try:
+        import failing
+    except ZeroDivisionError:
+        caught.append(attempt)
+caught";
+    let (result, vm) = run_with_search_path(source, vec![dir.clone()]);
+    assert_eq!(result, InterpretResult::Ok, "{:?}", vm.last_error());
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(last_popped.to_string(), "[0, 1]");
+
+    assert_eq!(
+        run_error("import shapes"),
+        "ModuleNotFoundError: No module named 'shapes'"
+    );
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_modules_compiled_ahead_of_their_import_run_as_read_then() {
+    let first = module_directory(
+        "precompiled_first",
+        &[
+            ("broken.py", "y = = 2\n"),
+            (
+                "lazy.py",
+                "def load():\n    import inner\n    return inner.value\n",
+            ),
+            ("inner.py", "value = 'inner'\n"),
+            ("moved.py", "where = 'first'\n"),
+        ],
+    );
+    let second = module_directory("precompiled_second", &[("moved.py", "where = 'second'\n")]);
+    let source = format!(
+        "\
+import sys
+if False:
+    import broken
+import lazy
+loaded = lazy.load()
+sys.path = ['{}']
+import moved
+[loaded, moved.where]",
+        second
+    );
+    let (result, vm) = run_with_search_path(&source, vec![first.clone()]);
+    assert_eq!(result, InterpretResult::Ok, "{:?}", vm.last_error());
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(last_popped.to_string(), "['inner', 'second']");
+
+    let _ = std::fs::remove_dir_all(first);
+    let _ = std::fs::remove_dir_all(second);
+}
+
+#[test]
+fn test_import_loads_packages_and_relative_imports() {
+    let dir = module_directory(
+        "packages",
+        &[
+            (
+                "app/__init__.py",
+                "from . import tools\nversion = '1.0'\nseen = __package__\n",
+            ),
+            (
+                "app/tools.py",
+                "from .core.utils import helper\ndef use():\n    return helper() + ' via tools'\n",
+            ),
+            ("app/core/__init__.py", ""),
+            (
+                "app/core/utils.py",
+                "from .. import core as parent\ndef helper():\n    return 'helper in ' + __name__\n",
+            ),
+            ("app/too_far.py", "from ... import tools\n"),
+            ("app/shadowed.py", "kind = 'module'\n"),
+            ("app/shadowed/__init__.py", "kind = 'package'\n"),
+        ],
+    );
+    let source = "\
+import app.core.utils
+from app import tools, shadowed
+import app.tools as aliased
+from app.core import utils
+[app.core.utils.helper(), app.version, app.seen, tools.use(), aliased.use(), utils.__package__, shadowed.kind]";
+    let (result, vm) = run_with_search_path(source, vec![dir.clone()]);
+    assert_eq!(result, InterpretResult::Ok, "{:?}", vm.last_error());
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(
+        last_popped.to_string(),
+        "['helper in app.core.utils', '1.0', 'app', 'helper in app.core.utils via tools', \
+'helper in app.core.utils via tools', 'app.core', 'package']"
+    );
+
+    for (source, expected) in [
+        (
+            "from . import tools",
+            "ImportError: attempted relative import with no known parent package",
+        ),
+        (
+            "import app.too_far",
+            "ImportError: attempted relative import beyond top-level package",
+        ),
+        (
+            "import app.missing",
+            "ModuleNotFoundError: No module named 'app.missing'",
+        ),
+        (
+            "import app.version.x",
+            "ModuleNotFoundError: No module named 'app.version'",
+        ),
+        (
+            "import app.core.utils.x",
+            "ModuleNotFoundError: No module named 'app.core.utils.x'; 'app.core.utils' is not a package",
+        ),
+        (
+            "from app import missing",
+            "ImportError: cannot import name 'missing' from 'app'",
+        ),
+    ] {
+        let (result, vm) = run_with_search_path(source, vec![dir.clone()]);
+        assert_eq!(result, InterpretResult::RuntimeError, "{}", source);
+        let error = vm.last_error().unwrap();
+        assert_eq!(format!("{}: {}", error.kind, error.message), expected);
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_circular_imports_fail_naming_the_cycle() {
+    let dir = module_directory(
+        "circular",
+        &[
+            ("first.py", "import second\n"),
+            ("second.py", "import third\n"),
+            ("third.py", "from first import name\n"),
+            ("itself.py", "import itself\n"),
+            ("pkg/__init__.py", "from . import a\n"),
+            ("pkg/a.py", "from . import b\n"),
+            ("pkg/b.py", "from .a import name\n"),
+        ],
+    );
+    for (source, expected) in [
+        (
+            "import first",
+            "ImportError: circular import: first -> second -> third -> first",
+        ),
+        (
+            "import third",
+            "ImportError: circular import: third -> first -> second -> third",
+        ),
+        (
+            "import itself",
+            "ImportError: circular import: itself -> itself",
+        ),
+        (
+            "import pkg",
+            "ImportError: circular import: pkg.a -> pkg.b -> pkg.a",
+        ),
+    ] {
+        let (result, vm) = run_with_search_path(source, vec![dir.clone()]);
+        assert_eq!(result, InterpretResult::RuntimeError, "{}", source);
+        let error = vm.last_error().unwrap();
+        assert_eq!(format!("{}: {}", error.kind, error.message), expected);
+    }
+
+    // A failed import leaves nothing behind, so it fails the same way again
+    let source = "\
+caught = []
+for attempt in range(2):
+    try:
+        import first
+    except ImportError as e:
+        caught.append(e.args[0])
+caught";
+    let (result, vm) = run_with_search_path(source, vec![dir.clone()]);
+    assert_eq!(result, InterpretResult::Ok, "{:?}", vm.last_error());
+    let last_popped = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(
+        last_popped.to_string(),
+        "['circular import: first -> second -> third -> first', \
+'circular import: first -> second -> third -> first']"
+    );
+    let _ = std::fs::remove_dir_all(dir);
+}
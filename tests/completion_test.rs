@@ -0,0 +1,80 @@
+use oxython::compiler::Compiler;
+use oxython::vm::completion::Completions;
+use oxython::vm::{InterpretResult, VM};
+
+fn vm_after(source: &str) -> VM {
+    let chunk = Compiler::compile(source).expect("Compilation failed");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    vm
+}
+
+fn candidates(vm: &VM, line: &str) -> Vec<String> {
+    vm.complete(line).candidates
+}
+
+#[test]
+fn bare_names_complete_from_globals_builtins_and_keywords() {
+    let vm = vm_after("printer = 1\nprice = 2\n_private = 3\n");
+
+    assert_eq!(candidates(&vm, "pri"), vec!["price", "print", "printer"]);
+    assert_eq!(candidates(&vm, "x = le"), vec!["len"]);
+    assert_eq!(candidates(&vm, "_pr"), vec!["_private"]);
+    assert!(!candidates(&vm, "").contains(&"_private".to_string()));
+    assert_eq!(vm.complete("x = le").start, 4);
+}
+
+#[test]
+fn attributes_complete_after_a_dotted_path() {
+    let vm = vm_after(
+        r#"
+import sys
+
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+    def norm(self):
+        return self.x + self.y
+
+class Segment:
+    def __init__(self, start):
+        self.start = start
+
+origin = Point(0, 0)
+segment = Segment(origin)
+name = "text"
+"#,
+    );
+
+    assert_eq!(candidates(&vm, "origin."), vec!["norm", "x", "y"]);
+    assert_eq!(candidates(&vm, "print(segment.start.n"), vec!["norm"]);
+    assert_eq!(candidates(&vm, "origin.__in"), vec!["__init__"]);
+    assert_eq!(
+        candidates(&vm, "sys.get"),
+        vec!["getinstructioncount", "getobjectcount", "getrecursionlimit"]
+    );
+    assert_eq!(candidates(&vm, "name.spl"), vec!["split", "splitlines"]);
+    assert_eq!(vm.complete("print(origin.no").start, 13);
+
+    // Paths that do not name a value have nothing to offer
+    assert!(candidates(&vm, "missing.x").is_empty());
+    assert!(candidates(&vm, "origin.z.").is_empty());
+    assert!(candidates(&vm, "1.re").is_empty());
+}
+
+#[test]
+fn common_prefix_is_what_every_candidate_shares() {
+    let completions = |candidates: &[&str]| Completions {
+        start: 0,
+        candidates: candidates.iter().map(|name| name.to_string()).collect(),
+    };
+
+    assert_eq!(
+        completions(&["getobjectcount", "getrecursionlimit"]).common_prefix(),
+        "get"
+    );
+    assert_eq!(completions(&["print", "printer"]).common_prefix(), "print");
+    assert_eq!(completions(&["norm"]).common_prefix(), "norm");
+    assert_eq!(completions(&[]).common_prefix(), "");
+}
@@ -0,0 +1,60 @@
+use oxython::compiler::Compiler;
+use oxython::diagnostic::{json_string, Diagnostic, Position, Range, Severity};
+use oxython::vm::{InterpretResult, VM};
+
+#[test]
+fn syntax_errors_and_lints_become_point_diagnostics() {
+    let errors = Compiler::compile_with_errors("x = 1\ny = (\n", "<script>").unwrap_err();
+    let diagnostic = Diagnostic::from_syntax_error("a.py", &errors[0]);
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(
+        diagnostic.to_json(),
+        format!(
+            "{{\"severity\":\"error\",\"code\":\"SyntaxError\",\"message\":{},\"file\":\"a.py\",\"range\":{{\"start\":{{\"line\":{line},\"column\":{column}}},\"end\":{{\"line\":{line},\"column\":{column}}}}}}}",
+            json_string(&errors[0].message),
+            line = errors[0].line,
+            column = errors[0].column
+        )
+    );
+
+    let lints = Compiler::lint("def f():\n    unused = 1\n", "<script>").unwrap();
+    let diagnostic = Diagnostic::from_lint("b.py", &lints[0]);
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(diagnostic.code, "unused-variable");
+    assert_eq!(
+        diagnostic.range.map(|range| range.start),
+        Some(Position { line: 2, column: 5 })
+    );
+}
+
+#[test]
+fn runtime_errors_cover_the_line_the_innermost_frame_ran() {
+    let source = "def f(x):\n    return x + \"a\"\n\nprint(f(1))\n";
+    let mut vm = VM::new();
+    let chunk = Compiler::compile(source).unwrap();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+    let error = vm.last_error().unwrap();
+    let lines: Vec<_> = error.traceback.iter().map(|frame| frame.line).collect();
+    assert_eq!(lines, vec![Some(4), Some(2)]);
+
+    let diagnostic = Diagnostic::from_runtime_error("c.py", error);
+    assert_eq!(diagnostic.code, "TypeError");
+    assert_eq!(
+        diagnostic.range,
+        Some(Range {
+            start: Position { line: 2, column: 1 },
+            end: Position { line: 3, column: 1 },
+        })
+    );
+    assert!(diagnostic.to_json().starts_with(
+        "{\"severity\":\"error\",\"code\":\"TypeError\",\"message\":\"unsupported operand type(s) for +: 'int' and 'str'\""
+    ));
+}
+
+#[test]
+fn json_strings_escape_quotes_backslashes_and_control_characters() {
+    assert_eq!(
+        json_string("say \"hi\"\\\n\t\u{1}é"),
+        "\"say \\\"hi\\\"\\\\\\n\\t\\u0001é\""
+    );
+}
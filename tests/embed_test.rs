@@ -0,0 +1,60 @@
+use oxython::embed::{self, RunOutput};
+use oxython::platform::Output;
+use oxython::sync::{Rc, RefCell};
+use oxython::vm::{InterpretResult, VM};
+
+#[test]
+fn run_captures_what_the_script_prints() {
+    assert_eq!(
+        embed::run("print('a', 1)\nfor i in range(2):\n    print(i)"),
+        RunOutput {
+            stdout: "a 1\n0\n1\n".to_string(),
+            error: None,
+        }
+    );
+}
+
+#[test]
+fn run_reports_runtime_errors_after_the_output_so_far() {
+    let output = embed::run("print('before')\nx = 1 + 'a'");
+    assert_eq!(output.stdout, "before\n");
+    assert_eq!(
+        output.error.as_deref(),
+        Some(
+            "Traceback (most recent call last):\n  File \"<script>\", line 2, in <module>\nTypeError: unsupported operand type(s) for +: 'int' and 'str'"
+        )
+    );
+}
+
+#[test]
+fn run_reports_syntax_errors_without_running() {
+    let output = embed::run("print('never')\nx = (");
+    assert_eq!(output.stdout, "");
+    assert!(output.error.unwrap().starts_with("SyntaxError"));
+    assert!(embed::compile("x = 1").is_ok());
+}
+
+#[test]
+fn captured_output_is_taken_once() {
+    let mut vm = VM::new();
+    assert_eq!(vm.take_output(), "");
+    vm.capture_output();
+    let chunk = embed::compile("print(1)").unwrap();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(vm.take_output(), "1\n");
+    assert_eq!(vm.take_output(), "");
+}
+
+#[test]
+fn output_can_be_sent_to_a_writer() {
+    let printed = Rc::new(RefCell::new(String::new()));
+    let sink = printed.clone();
+    let mut vm = VM::new();
+    vm.set_output(Output::Writer(Box::new(move |text| {
+        sink.borrow_mut().push_str(text)
+    })));
+    let chunk = embed::compile("print('a', 1)\nprint(2)").unwrap();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(*printed.borrow(), "a 1\n2\n");
+    assert_eq!(vm.take_output(), "");
+}
@@ -74,7 +74,7 @@ fn run_list_append() {
 #[test]
 fn run_average_temperature() {
     let output = run_example("examples/algorithms/average_temperature.py");
-    assert_eq!(output, "69.9\n");
+    assert_eq!(output, "69.8\n");
 }
 
 #[test]
@@ -187,6 +187,12 @@ fn run_super() {
     );
 }
 
+#[test]
+fn run_super_chain() {
+    let output = run_example("examples/oop/super_chain.py");
+    assert_eq!(output, "GrandParent\nParent\nChild\n");
+}
+
 #[test]
 fn run_test_str() {
     let output = run_example("examples/oop/test_str.py");
@@ -230,7 +236,7 @@ fn run_basic_variables() {
 #[test]
 fn run_function_annotations() {
     let output = run_example("examples/type-annotations/function_annotations.py");
-    assert_eq!(output, "Hello, Bob\n10 + 20 = 30\n3.5 * 2.0 = 7\n");
+    assert_eq!(output, "Hello, Bob\n10 + 20 = 30\n3.5 * 2.0 = 7.0\n");
 }
 
 #[test]
@@ -254,7 +260,7 @@ fn run_mixed_annotated() {
 #[test]
 fn run_class_annotations() {
     let output = run_example("examples/type-annotations/class_annotations.py");
-    assert_eq!(output, "Alice\n5 + 10 = 15\n2.5 * 4.0 = 10\n");
+    assert_eq!(output, "Alice\n5 + 10 = 15\n2.5 * 4.0 = 10.0\n");
 }
 
 #[test]
@@ -275,19 +281,19 @@ fn test_introspection_function_name() {
 #[test]
 fn test_introspection_function_module() {
     let output = run_example("examples/introspection/function_module.py");
-    assert_eq!(output, "<script>\n<script>\n");
+    assert_eq!(output, "__main__\n__main__\n");
 }
 
 #[test]
 fn test_introspection_basic_attributes() {
     let output = run_example("examples/introspection/basic_attributes.py");
-    assert_eq!(output, "greet.__name__ = greet\ncalculate.__name__ = calculate\ngreet.__doc__ = nil\ncalculate.__doc__ = nil\n");
+    assert_eq!(output, "greet.__name__ = greet\ncalculate.__name__ = calculate\ngreet.__doc__ = None\ncalculate.__doc__ = None\n");
 }
 
 #[test]
 fn test_introspection_annotations() {
     let output = run_example("examples/introspection/annotations.py");
-    assert_eq!(output, "add.__annotations__ = {'x': int, 'y': int, 'return': int}\ngreet.__annotations__ = {'name': str, 'return': str}\nprocess.__annotations__ = {'data': str, 'count': int, 'flag': bool}\nno_annotations.__annotations__ = {}\n");
+    assert_eq!(output, "add.__annotations__ = {'x': 'int', 'y': 'int', 'return': 'int'}\ngreet.__annotations__ = {'name': 'str', 'return': 'str'}\nprocess.__annotations__ = {'data': 'str', 'count': 'int', 'flag': 'bool'}\nno_annotations.__annotations__ = {}\n");
 }
 
 #[test]
@@ -303,7 +309,7 @@ fn test_introspection_closure_namespace() {
     let output = run_example("examples/introspection/closure_namespace.py");
     assert_eq!(
         output,
-        "simple_func\nsimple_func\nnil\ninner\nouter.inner\n(3, 10)\n18\n"
+        "simple_func\nsimple_func\nNone\ninner\nouter.<locals>.inner\n2\n18\n"
     );
 }
 
@@ -312,7 +318,7 @@ fn test_introspection_default_parameters() {
     let output = run_example("examples/introspection/default_parameters.py");
     assert_eq!(
         output,
-        "no_defaults\nnil\nwith_defaults\n(10, 20)\nall_defaults\n(1, 2, 3)\ngreet\n('Hello', '!')\n"
+        "no_defaults\nNone\nwith_defaults\n(10, 20)\nall_defaults\n(1, 2, 3)\ngreet\n('Hello', '!')\n"
     );
 }
 
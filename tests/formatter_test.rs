@@ -0,0 +1,101 @@
+use oxython::formatter::format_source;
+
+fn format(source: &str) -> String {
+    format_source(source).expect("source should compile")
+}
+
+#[test]
+fn indentation_and_spacing_are_normalized() {
+    let source = "\
+class Point :
+\tdef __init__(self,x:int=0,y = 5):
+\t\tself.x=x;self.y = y
+\tdef scaled(self , k)->int:
+\t\treturn Point(self.x*k,-self.y)
+if not(Point(1).x==1) :
+  print( -Point().y , 'done' )
+";
+    assert_eq!(
+        format(source),
+        "\
+class Point:
+    def __init__(self, x: int = 0, y=5):
+        self.x = x; self.y = y
+    def scaled(self, k) -> int:
+        return Point(self.x * k, -self.y)
+if not (Point(1).x == 1):
+    print(-Point().y, \"done\")
+"
+    );
+}
+
+#[test]
+fn comments_blank_lines_and_line_breaks_are_kept() {
+    let source = "
+
+# heading
+x = [1,2 ,  # first two
+     3,
+]
+
+
+
+def f():
+        # explains
+        return x[ -1 ]   # last
+# done
+";
+    assert_eq!(
+        format(source),
+        "\
+# heading
+x = [1, 2,  # first two
+    3,
+]
+
+
+def f():
+    # explains
+    return x[-1]  # last
+# done
+"
+    );
+}
+
+#[test]
+fn strings_keep_their_quotes_where_changing_them_matters() {
+    let source = "a = 'it''s'\nb = f'{d[\"k\"]}'\nc = '''doc'''\nd = {'k': 'v'}\n";
+    assert_eq!(
+        format(source),
+        "a = 'it''s'\nb = f'{d[\"k\"]}'\nc = '''doc'''\nd = {\"k\": \"v\"}\n"
+    );
+}
+
+#[test]
+fn formatting_is_stable_and_refuses_invalid_source() {
+    let source = "def f(a, b=2):\n    return a + b\n\n\nprint(f(1))\n";
+    assert_eq!(format(source), source);
+    assert_eq!(format(&format(source)), source);
+
+    let errors = format_source("def f(:\n").unwrap_err();
+    assert_eq!(errors[0].kind, "SyntaxError");
+}
+
+#[test]
+fn unpacking_stars_stay_attached() {
+    let source = "first ,*rest = [1, 2, 3]\nprint(f( *rest )*2)\n";
+    assert_eq!(
+        format(source),
+        "first, *rest = [1, 2, 3]\nprint(f(*rest) * 2)\n"
+    );
+}
+
+#[test]
+fn import_dots_stay_attached_to_module_names() {
+    let source =
+        "import  pkg . sub as s\nfrom ..core . utils import helper\nfrom  .  import  sibling\n";
+    assert_eq!(
+        format(source),
+        "import pkg.sub as s\nfrom ..core.utils import helper\nfrom . import sibling\n"
+    );
+}
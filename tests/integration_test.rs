@@ -26,3 +26,38 @@ fn test_usage_error_exit_code() {
     assert_eq!(output.status.code(), Some(64));
     assert!(String::from_utf8_lossy(&output.stderr).contains("Usage: oxython"));
 }
+
+#[test]
+fn test_oxythonpath_directories_are_searched_for_modules() {
+    let mut root = std::env::temp_dir();
+    root.push(format!("oxython_test_{}_oxythonpath", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let (first, second) = (root.join("first"), root.join("second"));
+    std::fs::create_dir_all(&first).unwrap();
+    std::fs::create_dir_all(&second).unwrap();
+    std::fs::write(first.join("greeting.py"), "text = 'from first'").unwrap();
+    std::fs::write(second.join("greeting.py"), "text = 'from second'").unwrap();
+    std::fs::write(second.join("other.py"), "text = 'other'").unwrap();
+    let script = root.join("main.py");
+    std::fs::write(
+        &script,
+        "import greeting\nimport other\nprint(greeting.text)\nprint(other.text)",
+    )
+    .unwrap();
+
+    let output = Command::new("target/debug/oxython")
+        .arg(&script)
+        .env(
+            "OXYTHONPATH",
+            std::env::join_paths([&first, &second]).unwrap(),
+        )
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "from first\nother\n"
+    );
+    let _ = std::fs::remove_dir_all(&root);
+}
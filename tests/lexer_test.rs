@@ -45,7 +45,7 @@ fn test_lexer() {
 fn test_type_annotations() {
     let input = r#"
         x: int = 5
-        y: float = 3.14
+        y: float = 2.5
         name: str = "test"
         flag: bool = True
         items: list
@@ -63,12 +63,12 @@ fn test_type_annotations() {
         Token::Identifier("int".to_string()),
         Token::Assign,
         Token::Integer(5),
-        // y: float = 3.14
+        // y: float = 2.5
         Token::Identifier("y".to_string()),
         Token::Colon,
         Token::Identifier("float".to_string()),
         Token::Assign,
-        Token::Float(3.14),
+        Token::Float(2.5),
         // name: str = "test"
         Token::Identifier("name".to_string()),
         Token::Colon,
@@ -124,3 +124,166 @@ fn test_function_type_annotations() {
 
     assert_eq!(tokens, expected_tokens);
 }
+
+#[test]
+fn test_lexer_distinguishes_slash_and_floor_division() {
+    let tokens: Vec<Token> = Token::lexer("7 / 2 // 3").filter_map(Result::ok).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Integer(7),
+            Token::Slash,
+            Token::Integer(2),
+            Token::SlashSlash,
+            Token::Integer(3),
+        ]
+    );
+}
+
+#[test]
+fn test_lexer_comparison_operators() {
+    let tokens: Vec<Token> = Token::lexer("< <= > >= == ->")
+        .filter_map(Result::ok)
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Less,
+            Token::LessEqual,
+            Token::Greater,
+            Token::GreaterEqual,
+            Token::EqualEqual,
+            Token::Arrow,
+        ]
+    );
+}
+
+#[test]
+fn test_lexer_number_literal_forms() {
+    let tokens: Vec<Token> =
+        Token::lexer("1_000_000 1e-3 2.5E2 .5 3. 0xFF 0o777 0b1010 0 00 0x_1f")
+            .filter_map(Result::ok)
+            .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Integer(1_000_000),
+            Token::Float(0.001),
+            Token::Float(250.0),
+            Token::Float(0.5),
+            Token::Float(3.0),
+            Token::Integer(255),
+            Token::Integer(511),
+            Token::Integer(10),
+            Token::Integer(0),
+            Token::Integer(0),
+            Token::Integer(31),
+        ]
+    );
+}
+
+#[test]
+fn test_lexer_rejects_malformed_number_literals() {
+    for source in [
+        "0b102",
+        "1__0",
+        "1_",
+        "0x",
+        "012",
+        "1e",
+        "9223372036854775808",
+    ] {
+        let tokens: Vec<_> = Token::lexer(source).collect();
+        assert_eq!(tokens, vec![Err(())], "{}", source);
+    }
+    let tokens: Vec<_> = Token::lexer("9223372036854775807").collect();
+    assert_eq!(tokens, vec![Ok(Token::Integer(i64::MAX))]);
+}
+
+#[test]
+fn test_lexer_skips_comments() {
+    let tokens: Vec<_> = Token::lexer("# leading\r\nx = '#' # trailing\n  # indented\n")
+        .filter_map(Result::ok)
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Identifier("x".to_string()),
+            Token::Assign,
+            Token::String("#".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_lex_with_spans_categorizes_tokens() {
+    use oxython::highlight::{lex_with_spans, Category};
+
+    let source = "def f(x):  # note\n    return None if x >= 0b1 else 'no'";
+    let tokens: Vec<(&str, Category)> = lex_with_spans(source)
+        .into_iter()
+        .map(|(_, span, category)| (&source[span], category))
+        .collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            ("def", Category::Keyword),
+            ("f", Category::Name),
+            ("(", Category::Punctuation),
+            ("x", Category::Name),
+            (")", Category::Punctuation),
+            (":", Category::Punctuation),
+            ("return", Category::Keyword),
+            ("None", Category::Keyword),
+            ("if", Category::Keyword),
+            ("x", Category::Name),
+            (">=", Category::Operator),
+            ("0b1", Category::Number),
+            ("else", Category::Keyword),
+            ("'no'", Category::String),
+        ]
+    );
+}
+
+#[test]
+fn test_lex_with_spans_splits_f_strings_and_reports_errors() {
+    use oxython::highlight::{lex_with_spans, Category};
+
+    let source = "f\"a {x + 1:>4} b {{c}}\" $ 007";
+    let tokens: Vec<(Result<Token, ()>, &str, Category)> = lex_with_spans(source)
+        .into_iter()
+        .map(|(token, span, category)| (token, &source[span], category))
+        .collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            (
+                Ok(Token::Identifier("f".to_string())),
+                "f",
+                Category::FString
+            ),
+            (
+                Ok(Token::String("\"a {".to_string())),
+                "\"a {",
+                Category::FString
+            ),
+            (Ok(Token::Identifier("x".to_string())), "x", Category::Name),
+            (Ok(Token::Plus), "+", Category::Operator),
+            (Ok(Token::Integer(1)), "1", Category::Number),
+            (
+                Ok(Token::String(":>4} b {{c}}\"".to_string())),
+                ":>4} b {{c}}\"",
+                Category::FString
+            ),
+            (Err(()), "$", Category::Error),
+            (Err(()), "007", Category::Error),
+        ]
+    );
+
+    // A malformed f-string is one error token
+    let tokens = lex_with_spans("f'{'");
+    assert_eq!(tokens.last().unwrap().1, 1..4);
+    assert_eq!(tokens.last().unwrap().2, Category::Error);
+}
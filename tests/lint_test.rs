@@ -0,0 +1,95 @@
+use oxython::compiler::lint::{Lint, LintKind};
+use oxython::compiler::Compiler;
+
+fn lint(source: &str) -> Vec<Lint> {
+    Compiler::lint(source, "<script>").expect("source should compile")
+}
+
+fn kinds_and_lines(source: &str) -> Vec<(LintKind, usize)> {
+    lint(source)
+        .into_iter()
+        .map(|lint| (lint.kind, lint.line))
+        .collect()
+}
+
+#[test]
+fn unused_locals_are_reported_unless_read_captured_or_underscored() {
+    let source = "\
+def f(a):
+    unused = 1
+    _ignored = 2
+    total = 0
+    for i in range(a):
+        total += i
+    def inner():
+        return total
+    return inner
+";
+    let lints = lint(source);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].kind, LintKind::UnusedVariable);
+    assert_eq!((lints[0].line, lints[0].column), (2, 5));
+    assert_eq!(
+        lints[0].to_string(),
+        "unused-variable: local variable 'unused' is assigned to but never used (line 2, column 5)"
+    );
+}
+
+#[test]
+fn shadowed_builtins_and_undefined_names_are_reported() {
+    let source = "\
+list = [1]
+def f(len):
+    return len + later + missing
+later = 2
+";
+    assert_eq!(
+        kinds_and_lines(source),
+        vec![
+            (LintKind::ShadowedBuiltin, 1),
+            (LintKind::ShadowedBuiltin, 2),
+            (LintKind::UndefinedName, 3),
+        ]
+    );
+    assert_eq!(lint(source)[2].message, "name 'missing' is not defined");
+}
+
+#[test]
+fn module_name_is_defined() {
+    let source = "\
+def f():
+    return __name__
+";
+    assert!(lint(source).is_empty());
+}
+
+#[test]
+fn statements_after_leaving_a_block_are_unreachable() {
+    let source = "\
+def f(x):
+    if x:
+        raise ValueError(\"x\")
+        print(1)
+        print(2)
+    return x
+    print(3)
+for i in range(2):
+    continue
+    print(i)
+";
+    assert_eq!(
+        kinds_and_lines(source),
+        vec![
+            (LintKind::UnreachableCode, 4),
+            (LintKind::UnreachableCode, 7),
+            (LintKind::UnreachableCode, 10),
+        ]
+    );
+}
+
+#[test]
+fn clean_source_has_no_lints_and_invalid_source_has_errors() {
+    assert!(lint("def f(x):\n    y = x * 2\n    return y\nprint(f(2))\n").is_empty());
+    let errors = Compiler::lint("def f(:\n", "<script>").unwrap_err();
+    assert_eq!(errors[0].kind, "SyntaxError");
+}
@@ -0,0 +1,213 @@
+use oxython::lsp::json::Value;
+use oxython::lsp::{serve, Server};
+
+const URI: &str = "file:///shapes.py";
+
+const SOURCE: &str = "\
+class Shape:
+    \"\"\"A thing with an area.\"\"\"
+    def area(self) -> int:
+        \"\"\"Zero unless overridden.\"\"\"
+        return 0
+
+def make(size: int) -> Shape:
+    shape = Shape()
+    count = 3
+    return shape
+
+box = make(2)
+print(box.area())
+";
+
+fn request(id: usize, method: &str, params: Value) -> Value {
+    Value::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id.into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    Value::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn open(server: &mut Server, text: &str) -> Vec<Value> {
+    server.handle(&notification(
+        "textDocument/didOpen",
+        Value::object([(
+            "textDocument",
+            Value::object([
+                ("uri", URI.into()),
+                ("languageId", "python".into()),
+                ("version", 1usize.into()),
+                ("text", text.into()),
+            ]),
+        )]),
+    ))
+}
+
+fn at(line: usize, character: usize) -> Value {
+    Value::object([
+        ("textDocument", Value::object([("uri", URI.into())])),
+        (
+            "position",
+            Value::object([("line", line.into()), ("character", character.into())]),
+        ),
+    ])
+}
+
+fn result(server: &mut Server, method: &str, params: Value) -> Value {
+    let replies = server.handle(&request(1, method, params));
+    replies[0].get("result").cloned().expect("a result")
+}
+
+fn start(value: &Value) -> (usize, usize) {
+    let start = value.get("range").unwrap().get("start").unwrap();
+    (
+        start.get("line").unwrap().as_usize().unwrap(),
+        start.get("character").unwrap().as_usize().unwrap(),
+    )
+}
+
+#[test]
+fn opening_a_file_publishes_its_lints_and_syntax_errors() {
+    let mut server = Server::new();
+    let published = open(&mut server, SOURCE);
+    let params = published[0].get("params").unwrap();
+    let Some(Value::Array(diagnostics)) = params.get("diagnostics") else {
+        panic!("no diagnostics in {}", published[0]);
+    };
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].get("code").and_then(Value::as_str),
+        Some("unused-variable")
+    );
+    assert_eq!(start(&diagnostics[0]), (8, 4));
+    let end = diagnostics[0].get("range").unwrap().get("end").unwrap();
+    assert_eq!(end.get("character").unwrap().as_usize(), Some(9));
+
+    let published = open(&mut server, "def f(:\n");
+    let diagnostics = published[0].get("params").unwrap().get("diagnostics");
+    let Some(Value::Array(diagnostics)) = diagnostics else {
+        panic!("no diagnostics");
+    };
+    assert_eq!(diagnostics[0].get("severity").unwrap().as_usize(), Some(1));
+}
+
+#[test]
+fn hover_shows_headers_docstrings_and_inferred_types() {
+    let mut server = Server::new();
+    open(&mut server, SOURCE);
+    let hover = |server: &mut Server, line, character| {
+        let hover = result(server, "textDocument/hover", at(line, character));
+        hover
+            .get("contents")
+            .and_then(|contents| contents.get("value"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    assert_eq!(
+        hover(&mut server, 12, 11).as_deref(),
+        Some("```python\ndef area(self) -> int\n```\n\nZero unless overridden.")
+    );
+    assert_eq!(
+        hover(&mut server, 7, 13).as_deref(),
+        Some("```python\nclass Shape\n```\n\nA thing with an area.")
+    );
+    assert_eq!(
+        hover(&mut server, 9, 12).as_deref(),
+        Some("```python\nshape: Shape\n```")
+    );
+    assert_eq!(
+        hover(&mut server, 12, 7).as_deref(),
+        Some("```python\nbox: Shape\n```")
+    );
+    assert_eq!(
+        hover(&mut server, 6, 10).as_deref(),
+        Some("```python\nsize: int\n```")
+    );
+    assert_eq!(hover(&mut server, 12, 0).as_deref(), None);
+}
+
+#[test]
+fn definition_jumps_to_functions_and_classes_in_scope() {
+    let mut server = Server::new();
+    open(&mut server, SOURCE);
+
+    let location = result(&mut server, "textDocument/definition", at(11, 7));
+    assert_eq!(location.get("uri").and_then(Value::as_str), Some(URI));
+    assert_eq!(start(&location), (6, 4));
+
+    let location = result(&mut server, "textDocument/definition", at(12, 11));
+    assert_eq!(start(&location), (2, 8));
+
+    let source = "\
+def helper():
+    return 1
+
+def outer():
+    def helper():
+        return 2
+    return helper()
+";
+    open(&mut server, source);
+    let location = result(&mut server, "textDocument/definition", at(6, 12));
+    assert_eq!(start(&location), (4, 8));
+    assert_eq!(
+        result(&mut server, "textDocument/definition", at(6, 4)),
+        Value::Null
+    );
+}
+
+#[test]
+fn serve_answers_framed_messages_until_exit() {
+    let messages = [
+        request(1, "initialize", Value::object([])),
+        request(2, "textDocument/formatting", Value::object([])),
+        request(3, "shutdown", Value::Null),
+        notification("exit", Value::Null),
+    ];
+    let mut input = String::new();
+    for message in &messages {
+        let body = message.to_string();
+        input.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+    }
+    let mut output = Vec::new();
+    let code = serve(&mut input.as_bytes(), &mut output).unwrap();
+    assert_eq!(code, 0);
+
+    let output = String::from_utf8(output).unwrap();
+    let replies: Vec<Value> = output
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|framed| Value::parse(framed.split_once("\r\n\r\n").unwrap().1).unwrap())
+        .collect();
+    assert_eq!(replies.len(), 3);
+    let capabilities = replies[0]
+        .get("result")
+        .unwrap()
+        .get("capabilities")
+        .unwrap();
+    assert_eq!(capabilities.get("hoverProvider"), Some(&Value::Bool(true)));
+    assert_eq!(
+        replies[1].get("error").unwrap().get("code"),
+        Some(&Value::Number(-32601.0))
+    );
+    assert_eq!(replies[2].get("result"), Some(&Value::Null));
+}
+
+#[test]
+fn json_values_round_trip() {
+    let text = r#"{"a":[1,-2.5,true,null],"b":"q\"\\\né😀","c":{}}"#;
+    let value = Value::parse(text).unwrap();
+    assert_eq!(value.get("b").and_then(Value::as_str), Some("q\"\\\né😀"));
+    assert_eq!(Value::parse(&value.to_string()), Some(value));
+    assert_eq!(Value::parse("[1,]"), None);
+    assert_eq!(Value::parse(&"[".repeat(1000)), None);
+}
@@ -0,0 +1,72 @@
+use oxython::compiler::Compiler;
+use oxython::embed;
+use oxython::marshal;
+use oxython::vm::VM;
+
+const SOURCE: &str = r#"
+def greet(name: str, punctuation: str = "!") -> str:
+    """Says hello."""
+    return "Hello, " + name + punctuation
+
+def counter():
+    count = 0
+    def step():
+        nonlocal count
+        count += 1
+        return count
+    return step
+
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+step = counter()
+step()
+print(greet("you"), greet.__doc__, step(), 2.5, -7, None, True)
+print([1, 2.0, "three"], {"a": 1, 2: [3]}, Point(1, 2).y)
+print([n * n for n in range(4)])
+"#;
+
+fn output_of(chunk: oxython::bytecode::Chunk) -> String {
+    let mut vm = VM::new();
+    vm.capture_output();
+    vm.interpret(chunk);
+    vm.take_output()
+}
+
+#[test]
+fn loaded_chunks_run_like_the_compiled_ones() {
+    let chunk = Compiler::compile(SOURCE).unwrap();
+    let bytes = marshal::dump(&chunk).unwrap();
+    let loaded = marshal::load(&bytes).unwrap();
+
+    assert_eq!(loaded.code, chunk.code);
+    assert_eq!(loaded.lines, chunk.lines);
+    assert_eq!(loaded.statements, chunk.statements);
+    assert_eq!(output_of(loaded), embed::run(SOURCE).stdout);
+    assert_eq!(
+        marshal::dump(&marshal::load(&bytes).unwrap()).unwrap(),
+        bytes
+    );
+}
+
+#[test]
+fn malformed_bytes_do_not_load() {
+    let bytes = marshal::dump(&Compiler::compile(SOURCE).unwrap()).unwrap();
+    for length in 0..bytes.len() {
+        assert!(marshal::load(&bytes[..length]).is_none(), "{}", length);
+    }
+    let mut extended = bytes.clone();
+    extended.push(0);
+    assert!(marshal::load(&extended).is_none());
+    let mut other_version = bytes.clone();
+    other_version[4] += 1;
+    assert!(marshal::load(&other_version).is_none());
+
+    // Constants nested past any the compiler emits
+    let mut nested = b"OXYC\x02\x00\x00\x00\x01".to_vec();
+    nested.extend(std::iter::repeat_n([6u8, 1], 300).flatten());
+    nested.push(0);
+    assert!(marshal::load(&nested).is_none());
+}
@@ -1,13 +1,14 @@
-use oxython::object::{ClassObject, InstanceObject, ObjectType, Type};
-use std::cell::RefCell;
+use oxython::object::{
+    ClassObject, DictEntries, InstanceObject, NativeResult, Object, ObjectType, Type,
+};
+use oxython::sync::{Rc, RefCell};
 use std::collections::HashMap;
-use std::rc::Rc;
 
 #[test]
 fn display_formats_boolean_and_nil() {
     assert_eq!(format!("{}", ObjectType::Boolean(true)), "True");
     assert_eq!(format!("{}", ObjectType::Boolean(false)), "False");
-    assert_eq!(format!("{}", ObjectType::Nil), "nil");
+    assert_eq!(format!("{}", ObjectType::Nil), "None");
 }
 
 #[test]
@@ -15,6 +16,30 @@ fn display_formats_float() {
     assert_eq!(format!("{}", ObjectType::Float(3.15)), "3.15");
 }
 
+#[test]
+fn display_formats_floats_like_python_repr() {
+    let cases = [
+        (0.1, "0.1"),
+        (0.1 + 0.2, "0.30000000000000004"),
+        (1.0, "1.0"),
+        (-2.0, "-2.0"),
+        (-0.0, "-0.0"),
+        (1e15, "1000000000000000.0"),
+        (1e16, "1e+16"),
+        (1.5e20, "1.5e+20"),
+        (0.0001, "0.0001"),
+        (0.00001, "1e-05"),
+        (-1.25e-7, "-1.25e-07"),
+        (1.7976931348623157e308, "1.7976931348623157e+308"),
+        (f64::INFINITY, "inf"),
+        (f64::NEG_INFINITY, "-inf"),
+        (f64::NAN, "nan"),
+    ];
+    for (value, expected) in cases {
+        assert_eq!(format!("{}", ObjectType::Float(value)), expected);
+    }
+}
+
 #[test]
 fn type_name_method_returns_correct_names() {
     assert_eq!(Type::Int.name(), "int");
@@ -59,7 +84,7 @@ fn type_equality_works() {
 #[test]
 fn object_type_get_type_returns_correct_type() {
     assert_eq!(ObjectType::Integer(42).get_type(), Type::Int);
-    assert_eq!(ObjectType::Float(3.14).get_type(), Type::Float);
+    assert_eq!(ObjectType::Float(2.5).get_type(), Type::Float);
     assert_eq!(
         ObjectType::String("hello".to_string()).get_type(),
         Type::Str
@@ -67,7 +92,7 @@ fn object_type_get_type_returns_correct_type() {
     assert_eq!(ObjectType::Boolean(true).get_type(), Type::Bool);
     assert_eq!(ObjectType::List(vec![]).get_type(), Type::List);
     assert_eq!(ObjectType::Tuple(vec![]).get_type(), Type::Tuple);
-    assert_eq!(ObjectType::Dict(vec![]).get_type(), Type::Dict);
+    assert_eq!(ObjectType::Dict(DictEntries::new()).get_type(), Type::Dict);
     assert_eq!(ObjectType::Nil.get_type(), Type::None);
 }
 
@@ -89,12 +114,12 @@ fn object_type_get_type_for_instance() {
 #[test]
 fn object_type_type_name_returns_correct_names() {
     assert_eq!(ObjectType::Integer(42).type_name(), "int");
-    assert_eq!(ObjectType::Float(3.14).type_name(), "float");
+    assert_eq!(ObjectType::Float(2.5).type_name(), "float");
     assert_eq!(ObjectType::String("hello".to_string()).type_name(), "str");
     assert_eq!(ObjectType::Boolean(true).type_name(), "bool");
     assert_eq!(ObjectType::List(vec![]).type_name(), "list");
     assert_eq!(ObjectType::Tuple(vec![]).type_name(), "tuple");
-    assert_eq!(ObjectType::Dict(vec![]).type_name(), "dict");
+    assert_eq!(ObjectType::Dict(DictEntries::new()).type_name(), "dict");
     assert_eq!(ObjectType::Nil.type_name(), "NoneType");
 }
 
@@ -113,6 +138,31 @@ fn object_type_type_name_for_instance() {
     assert_eq!(obj.type_name(), "Person");
 }
 
+#[test]
+fn class_object_defining_class_finds_the_ancestor_defining_a_method() {
+    let method: Object = Rc::new(ObjectType::Integer(1));
+    let wrapped: Object = Rc::new(ObjectType::Integer(2));
+    let mut methods = HashMap::new();
+    methods.insert("method".to_string(), method.clone());
+    methods.insert(
+        "wrapped".to_string(),
+        Rc::new(ObjectType::ClassMethod(wrapped.clone())),
+    );
+    let base = Rc::new(ClassObject::new("Base".to_string(), methods));
+    let child = Rc::new(ClassObject::new_with_parent(
+        "Child".to_string(),
+        HashMap::new(),
+        base,
+    ));
+
+    let found = ClassObject::defining_class(&child, &method).expect("Expected a class");
+    assert_eq!(found.name, "Base");
+    let found = ClassObject::defining_class(&child, &wrapped).expect("Expected a class");
+    assert_eq!(found.name, "Base");
+    let other: Object = Rc::new(ObjectType::Integer(1));
+    assert!(ClassObject::defining_class(&child, &other).is_none());
+}
+
 #[test]
 fn type_clone_works() {
     let t1 = Type::Int;
@@ -135,3 +185,56 @@ fn type_debug_format() {
     assert!(debug_str.contains("Class"));
     assert!(debug_str.contains("MyClass"));
 }
+
+#[test]
+fn display_formats_callables_with_names() {
+    use oxython::bytecode::Chunk;
+    use oxython::object::{FunctionObject, Object};
+
+    let mut function = FunctionObject::new(
+        "greet".to_string(),
+        1,
+        Chunk::new(),
+        Vec::new(),
+        "<script>".to_string(),
+    );
+    function.qualname = "Greeter.greet".to_string();
+    let function = Rc::new(ObjectType::Function(Rc::new(function)));
+    let text = format!("{}", function);
+    assert!(
+        text.starts_with("<function Greeter.greet at 0x"),
+        "{}",
+        text
+    );
+    assert!(text.ends_with('>'));
+
+    let class = Rc::new(ClassObject::new("Greeter".to_string(), HashMap::new()));
+    assert_eq!(
+        format!("{}", ObjectType::Class(class.clone())),
+        "<class 'Greeter'>"
+    );
+
+    let instance = Rc::new(ObjectType::Instance(Rc::new(RefCell::new(
+        InstanceObject::new(class),
+    ))));
+    let instance_text = format!("{}", instance);
+    assert!(
+        instance_text.starts_with("<Greeter object at 0x"),
+        "{}",
+        instance_text
+    );
+
+    let bound = ObjectType::BoundMethod(instance, function);
+    assert_eq!(
+        format!("{}", bound),
+        format!("<bound method Greeter.greet of {}>", instance_text)
+    );
+
+    fn native(_: &[Object], _: Option<Rc<ClassObject>>) -> NativeResult {
+        Ok(Rc::new(ObjectType::Nil))
+    }
+    assert_eq!(
+        format!("{}", ObjectType::NativeFunction("len".to_string(), native)),
+        "<built-in function len>"
+    );
+}
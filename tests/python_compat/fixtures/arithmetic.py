@@ -0,0 +1,7 @@
+print(7 // 2, -7 // 2, 7 // -2)
+print(7 % 3, -7 % 3, 7 % -3)
+print(divmod(17, 5), divmod(-17, 5))
+print(2 * 3600 + 30)
+print(1 < 2, 2 <= 2, 3 > 4, 5 >= 5)
+print(10 - 4 - 3)
+print([1] + [9], tuple([1, 2]) + tuple([3]), sum([[1], [2]], []))
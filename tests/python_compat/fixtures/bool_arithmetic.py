@@ -0,0 +1,26 @@
+# Booleans are the integers 0 and 1 in arithmetic and comparisons
+
+print(True + True, True * 3, 1 + False, 3 - True)
+print(True / 2, True // 1, 7 % True, 1.5 + True)
+print(-True, abs(True), divmod(True, 1))
+print(True == 1, False == 0, True == 2, 1 == True)
+print(False < True, True < 2, True > 0.5, True >= 1)
+print(sorted([True, 0, False, 2]), sorted([2.5, True, -1]))
+print(1 in [True], [True, False] == [1, 0])
+
+flags = [True, False, True, True]
+count = 0
+for flag in flags:
+    count += flag
+print(count)
+
+matches = [word == "a" for word in ["a", "b", "a"]]
+total = 0
+for match in matches:
+    total = total + match
+print(total, total * 10)
+
+try:
+    print(True // False)
+except ZeroDivisionError as error:
+    print("ZeroDivisionError:", error)
@@ -0,0 +1,44 @@
+# Instances whose class defines __call__ used as functions and callbacks
+class Counter:
+    def __init__(self, start):
+        self.count = start
+
+    def __call__(self, step=1):
+        self.count += step
+        return self.count
+
+
+class Scale:
+    def __init__(self, factor):
+        self.factor = factor
+
+    def __call__(self, value):
+        return value * self.factor
+
+
+class Loud(Scale):
+    """No methods of its own."""
+
+
+c = Counter(10)
+print(c(), c(5), c(step=2), c.count)
+double = Scale(2)
+print(double(21), [double(n) for n in [1, 2, 3]], sorted([3, 1, 2], key=Loud(-1)))
+print(callable(c), callable(Loud(3)), Loud(3)(4))
+
+
+def apply(callback, value):
+    return callback(value)
+
+
+print(apply(Scale(10), 4))
+
+
+class Plain:
+    """No methods of its own."""
+
+
+try:
+    Plain()()
+except TypeError as e:
+    print(e)
@@ -0,0 +1,80 @@
+# Class bodies computing attributes from enclosing names and earlier attributes
+DEFAULT = 21
+PREFIX = "item"
+
+
+class Config:
+    """Settings computed from module constants."""
+
+    X = DEFAULT * 2
+    Y = X + 1
+    NAME = PREFIX + "-" + str(Y) if False else PREFIX
+    ITEMS = [X, Y]
+    TABLE = {"x": X}
+    total = 0
+    for n in ITEMS:
+        total += n
+    if X > 40:
+        SIZE = "big"
+    else:
+        SIZE = "small"
+
+    def describe(self):
+        return f"{self.NAME} {self.X} {self.Y} {self.SIZE}"
+
+    @classmethod
+    def doubled(cls):
+        return cls.X * 2
+
+
+print(Config.X, Config.Y, Config.NAME, Config.ITEMS, Config.TABLE, Config.total, Config.n)
+print(Config().describe(), Config.doubled())
+
+
+def make(base):
+    scale = 3
+
+    class Scaled:
+        VALUE = base * scale
+
+        def get(self):
+            return self.VALUE + scale
+
+    return Scaled
+
+
+S = make(5)
+print(S.VALUE, S().get())
+
+
+class Point:
+    x: int = 0
+    y: int
+    label: str = "p"
+
+    def __init__(self, x):
+        self.x = x
+
+
+print(Point.x, Point.label, len(Point.__annotations__), Point(4).x)
+
+
+class Child(Config):
+    X = 1
+
+    def describe(self):
+        return "child " + super().describe()
+
+
+print(Child.X, Child.Y, Child().describe())
+X = "global"
+
+
+class Shadow:
+    X = "class"
+
+    def get(self):
+        return X
+
+
+print(Shadow().get(), Shadow.X)
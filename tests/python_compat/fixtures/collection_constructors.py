@@ -0,0 +1,27 @@
+# dict(), list() and tuple() built from iterables and keywords
+print(dict(), list(), tuple())
+print(dict(a=1, b=2.5))
+print(dict([["x", 1], ["y", 2]]), dict(zip(["p", "q"], [3, 4])))
+base = {"a": 1, "b": 2}
+copy = dict(base, b=20, c=30)
+print(copy, base)
+print(dict([["k", 1], ["k", 2]]), dict(["a1", "b2"])["b"] == "2")
+print(tuple([1, 2, 3]), tuple("hi"), list(tuple([4, 5])), list("ok"))
+print(list(range(3)), tuple(range(2)))
+names = ["a", "bb"]
+print(dict([[n, len(n)] for n in names]), dict(zip(names, range(2)), z=0))
+for bad in [[1], [["a", 1, 2]]]:
+    try:
+        dict(bad)
+    except TypeError as e:
+        print("TypeError", e)
+    except ValueError as e:
+        print("ValueError", e)
+try:
+    tuple(5)
+except TypeError as e:
+    print(e)
+try:
+    dict(1, 2)
+except TypeError as e:
+    print(e)
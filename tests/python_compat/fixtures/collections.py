@@ -0,0 +1,9 @@
+numbers = [3, 1, 2]
+numbers.sort()
+print(numbers)
+print(sorted([3, 1, 2], reverse=True))
+print(sorted(["bb", "a", "ccc"], key=len))
+scores = {"alice": 90, "bob": 85}
+print(scores["bob"])
+print([n * n for n in range(5)])
+print(list(zip([1, 2], ["a", "b"])))
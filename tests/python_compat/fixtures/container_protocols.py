@@ -0,0 +1,64 @@
+# len(), truthiness and 'in' running __len__, __bool__ and __contains__
+class Bag:
+    def __init__(self, items):
+        self.items = items
+
+    def __len__(self):
+        return len(self.items)
+
+    def __contains__(self, item):
+        return item in self.items
+
+
+class Switch:
+    def __init__(self, on):
+        self.on = on
+
+    def __bool__(self):
+        return self.on
+
+
+class Plain:
+    """No protocol methods."""
+
+
+full = Bag([1, 2, 3])
+empty = Bag([])
+print(len(full), len(empty), 2 in full, 5 in full, 5 not in full)
+if full:
+    print("full is true")
+if not empty:
+    print("empty is false")
+print(not Switch(True), not Switch(False), not Plain())
+s = Switch(False)
+while s:
+    print("never")
+print("yes" if Switch(True) else "no", [b.on for b in [Switch(True), Switch(False)] if b])
+print(sorted([Bag([1, 2]), Bag([1]), Bag([])], key=len)[0].items)
+
+
+class Broken:
+    def __len__(self):
+        return -1
+
+    def __bool__(self):
+        return 1
+
+
+try:
+    len(Broken())
+except ValueError as e:
+    print("ValueError", e)
+try:
+    if Broken():
+        print("unreachable")
+except TypeError as e:
+    print("TypeError", e)
+try:
+    len(Plain())
+except TypeError as e:
+    print("TypeError", e)
+try:
+    print(1 in Plain())
+except TypeError as e:
+    print("TypeError", e)
@@ -0,0 +1,12 @@
+print(int(), int(-2.7), int(3.9), int("  12  "), int(True))
+print(int("ff", 16), int("0xff", 16), int("Z", 36), int("-0b101", 2))
+print(int("0xff", 0), int("0o17", 0), int("0b1_01", 0), int("0x_ff", 0), int("0_0", 0))
+print(int(" -42 "), int("+7"), int("1_000"))
+print(hex(255), hex(-16), hex(0), oct(8), oct(-8), bin(5), bin(-5), bin(0))
+print(int(hex(3054), 16), int(bin(77), 0))
+
+for case in [["010", 0], ["1__0", 10], ["_1", 10], ["1_", 10], ["0x", 16], ["12", 2], ["", 10], ["0b101", 16]]:
+    try:
+        int(case[0], case[1])
+    except ValueError as error:
+        print(error)
@@ -0,0 +1,35 @@
+# copy module and list/dict copy()
+import copy
+
+class Node:
+    def __init__(self, value):
+        self.value = value
+        self.items = [value]
+
+a = [1, [2, 3]]
+b = a.copy()
+print(b)
+print(b is a)
+print(b == a)
+d = {"x": [1]}
+e = d.copy()
+print(e)
+print(e is d)
+
+n = Node(1)
+s = copy.copy(n)
+print(s is n)
+print(s.items is n.items)
+t = copy.deepcopy(n)
+print(t.items is n.items)
+print(t.items == n.items)
+n.me = n
+u = copy.deepcopy(n)
+print(u.me is u)
+print(u.me is n)
+nested = [[1], [2]]
+deep = copy.deepcopy(nested)
+print(deep == nested)
+print(deep[0] is nested[0])
+print(copy.copy(nested)[0] is nested[0])
+print(copy.deepcopy("abc"))
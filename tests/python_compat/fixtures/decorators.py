@@ -0,0 +1,50 @@
+from dataclasses import dataclass
+
+def shout(func):
+    def wrapper(name):
+        return func(name).upper()
+    return wrapper
+
+@shout
+def greet(name):
+    return "hello " + name
+
+print(greet("ada"))
+
+@dataclass
+class Point:
+    x: int
+    y: int = 0
+    label: str = "origin"
+
+p = Point(1, 2)
+print(p)
+print(Point(3))
+print(Point(1, 2) == p)
+print(Point(1, 3) == p)
+print(Point(x=5, label="five"))
+print(p.x + p.y)
+
+@dataclass
+class Point3(Point):
+    z: int = 9
+
+print(Point3(1, 2, "a", 3))
+print(Point3(1) == Point(1))
+
+@dataclass
+class Named:
+    name: str
+
+    def __repr__(self):
+        return "Named " + self.name
+
+print(Named("n"))
+
+try:
+    @dataclass
+    class Bad:
+        a: int = 1
+        b: int
+except TypeError as e:
+    print(e)
@@ -0,0 +1,23 @@
+# Deeply nested containers: rendering them raises RecursionError instead of overflowing
+x = []
+for i in range(500):
+    x = [x]
+print(len(f"{x}"))
+for i in range(700):
+    x = [x, i]
+try:
+    print(x)
+except RecursionError as err:
+    print("print:", err)
+try:
+    text = f"{x!r}"
+except RecursionError as err:
+    print("f-string:", err)
+d = {"k": 1}
+for i in range(1100):
+    d = {"k": d}
+try:
+    text = "{}".format(d)
+except RecursionError as err:
+    print("format:", err)
+print(len(x), len(d))
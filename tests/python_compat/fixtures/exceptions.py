@@ -0,0 +1,77 @@
+def walk():
+    for i in [1, 2, 3, 4]:
+        try:
+            if i == 2:
+                continue
+            if i == 4:
+                break
+            print("body", i)
+        finally:
+            print("finally", i)
+    print("done")
+
+walk()
+
+def nested_return():
+    try:
+        try:
+            return "value"
+        finally:
+            print("inner")
+    finally:
+        print("outer")
+
+print(nested_return())
+
+def lookup(items, index):
+    try:
+        return items[index]
+    except IndexError as e:
+        print("caught", e)
+        return -1
+    finally:
+        print("cleanup")
+
+print(lookup([10, 20], 1))
+print(lookup([10, 20], 5))
+
+try:
+    raise ValueError("bad")
+except (TypeError, ValueError) as err:
+    print("got", err, err.args)
+
+class AppError(Exception):
+    def __init__(self, code):
+        super().__init__("failed", code)
+        self.code = code
+
+try:
+    raise AppError(7)
+except Exception as e:
+    print(e.code, e)
+
+i = 0
+while i < 5:
+    i = i + 1
+    try:
+        if i % 2 == 0:
+            continue
+        print("odd", i)
+    except ValueError:
+        print("never")
+    else:
+        print("else", i)
+    finally:
+        print("f", i)
+
+def reraise():
+    try:
+        raise TypeError("t")
+    except TypeError:
+        print("handling")
+        raise
+
+try:
+    reraise()
+except TypeError as e:
+    print("outer got", e)
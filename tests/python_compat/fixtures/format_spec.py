@@ -0,0 +1,20 @@
+# Conversions and format specs in f-string replacement fields
+
+pi = 3.14159265
+n = 1234567
+name = "Ada"
+print(f"{name!r} {name!s} {name!a}")
+print(f"[{name:>8}] [{name:<8}] [{name:^9}] [{name:*^9}] [{name:.2}]")
+print(f"[{n:,}] [{n:_}] [{n:>12,}] [{n:012,}] [{-n:+,}] [{n: d}]")
+print(f"[{255:x}] [{255:#X}] [{255:#010b}] [{255:o}] [{65:c}] [{1234567:_x}]")
+print(f"[{pi:.2f}] [{pi:10.3f}] [{pi:<10.1f}|] [{pi:+.3e}] [{pi:g}] [{pi:.3}] [{0.25:%}] [{0.125:.1%}]")
+print(f"[{1e20:,.0f}] [{12345.678:,.2f}] [{1.5:08.2f}] [{100.0:e}] [{1e-7:g}] [{123456789.0:g}]")
+print(f"[{True}] [{True:>5}] [{None}] [{[1, 'a']}] [{1.0}] [{1e16}]")
+width = 10
+precision = 3
+print(f"[{pi:{width}.{precision}f}] [{name:>{width}}]")
+print(f"{'nested' if n > 5 else 'no':>10}|")
+
+print(f"{1.25:.1f} {2.5:.0f} {0.125:.2f} {0.375:.2f} {1.5:.0f} {2.675:.2f} {1e100:.3g} {0.0001234:.2g} {123.0:.2g} {0.5:.0e} {9.9999:.3g} {9.9999:.3} {0.0:g} {0.0:.3}")
+print(f"{12.0:#g} {1.0:#.0f} {3:.2%} {5:e} {7:g}")
+print(f"{'x':05} {3.5:=+8.2f} {-42:=8} {42:^+7}")
@@ -0,0 +1,57 @@
+# Conditional expressions and general expressions inside f-strings
+
+name = "Ada"
+missing = None
+print(f"{name if name else 'none'}")
+print(f"{missing if missing else 'none'}")
+print(f"{'yes' if 3 > 2 else 'no'} and {'yes' if 2 > 3 else 'no'}")
+
+scores = {"ada": 3, "bob": 5}
+print(f"ada scored {scores['ada']}, bob scored {scores['bob']}")
+print(f"{len(scores)} players, {{literal braces}}")
+print(f"{ {'a': 1}['a'] } and {[1, 2, 3][1]}")
+print(f"{'}'} {'{'}")
+
+items = [1, 2, 3]
+print(f"count={len(items)} first={items[0]} last={items[-1]}")
+print(f"{'many' if len(items) > 2 else 'few'} items")
+
+
+def describe(count):
+    label = "item" if count == 1 else "items"
+    return f"{count} {label}"
+
+
+print(describe(1))
+print(describe(2))
+
+
+def outer():
+    prefix = ">"
+
+    def inner(text):
+        return f"{prefix}{text.strip()}"
+
+    return inner
+
+
+print(outer()("  hi  "))
+
+size = 7
+print("big" if size > 5 else "small")
+print("a" if size > 10 else "b" if size > 5 else "c")
+print([x if x > 1 else 0 for x in items])
+print([x for x in items if x > 1])
+print(f"{not True}")
+
+
+class Point:
+    def __init__(self, x):
+        self.x = x
+
+    def __str__(self):
+        return f"Point({self.x})"
+
+
+point = Point(3)
+print(f"at {point}, next {point.x + 1}")
@@ -0,0 +1,30 @@
+def make_counter():
+    count = 0
+    def increment():
+        nonlocal count
+        count = count + 1
+        return count
+    return increment
+
+counter = make_counter()
+counter()
+print(counter())
+
+def greet(name, greeting="Hello"):
+    return greeting + ", " + name
+
+print(greet("Ada"))
+print(greet("Ada", greeting="Hi"))
+
+def make_parity():
+    def is_even(n):
+        if n == 0:
+            return True
+        return is_odd(n - 1)
+    def is_odd(n):
+        if n == 0:
+            return False
+        return is_even(n - 1)
+    return is_even
+
+print(make_parity()(10))
@@ -0,0 +1,48 @@
+# Generator expressions: lazy iterables passed to calls and iterated directly
+items = [1, 2, 3, 4]
+print(", ".join(str(x) for x in items))
+print("-".join(str(x * 10) for x in items if x > 2))
+print(sorted(x % 3 for x in items), list(c.upper() for c in "abc"), tuple(n for n in range(3)))
+words = ["b", "a"]
+print(" ".join(w for w in sorted(words)))
+gen = (x + 1 for x in items)
+print(list(gen), list(gen))
+total = 0
+for n in (y * y for y in items):
+    total += n
+print(total)
+
+
+def describe(values):
+    prefix = "v"
+    return "|".join(prefix + str(v) for v in values)
+
+
+print(describe([7, 8]))
+print(dict(zip(words, (len(w) for w in words))))
+
+
+def noisy(n):
+    print("produce", n)
+    return n
+
+
+lazy = (noisy(n) for n in [1, 2, 3])
+print("created")
+for n in lazy:
+    print("got", n)
+    if n == 2:
+        break
+print(list(lazy))
+print(sum(n * n for n in range(5)), sum((n for n in items), 100))
+print(any(n > 3 for n in items), all(n > 3 for n in items))
+
+
+def late():
+    base = 1
+    gen = (base + n for n in range(2))
+    base = 10
+    return list(gen)
+
+
+print(late())
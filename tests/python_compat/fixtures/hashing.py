@@ -0,0 +1,39 @@
+print(hash(1), hash(-1), hash(1.0), hash(True), hash(1.5), hash(-2.75), hash(0.1), hash(1e300), hash(4611686018427387904))
+t = divmod(7, 2)
+u = divmod(7.5, 2)
+z = list(zip([1], [None]))
+e = z[0]
+print(hash(t), hash(u), hash(""), hash(e) == hash(e))
+print(hash(-1.0), hash(1e-310), hash(123456789.125))
+a = [1]
+b = a
+print(id(a) == id(b), id(a) == id([1]), id(None) == id(None), id(5) == id(5))
+class P:
+    def __eq__(self, other):
+        return True
+class Q:
+    def __hash__(self):
+        return -1
+class R:
+    def m(self):
+        return 1
+print(hash(Q()))
+r = R()
+print(hash(r) == hash(r), hash(len) == hash(len), hash(r.m) == hash(r.m))
+def attempt(f, x):
+    try:
+        print(f(x))
+    except TypeError as e:
+        print(e)
+def has(x):
+    return x in {"a": 1}
+def get(x):
+    return {"a": 1}[x]
+bad = list(zip([1], [[2]]))[0]
+attempt(hash, [1])
+attempt(hash, {})
+attempt(hash, bad)
+attempt(hash, P())
+attempt(has, bad)
+attempt(has, t)
+attempt(get, [1])
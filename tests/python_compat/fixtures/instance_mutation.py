@@ -0,0 +1,55 @@
+# Methods mutating self, seen through every reference to the instance
+class Stack:
+    def __init__(self):
+        self.items = []
+        self.evens = []
+
+    def push(self, value):
+        self.items.append(value)
+        if value % 2 == 0:
+            self.evens.append(value)
+        return self
+
+    def ordered(self):
+        self.items.sort(reverse=True)
+        return self.items
+
+
+s = Stack()
+s.push(3).push(1).push(2)
+print(s.items, s.evens)
+print(s.ordered(), s.items)
+
+stacks = [Stack(), Stack()]
+stacks[1].push(5)
+for stack in stacks:
+    stack.push(9)
+print([stack.items for stack in stacks])
+
+registry = {"main": Stack()}
+registry["main"].push(4)
+print(registry["main"].items)
+
+shared = Stack()
+alias = shared
+alias.push(7)
+print(shared.items, shared is alias)
+
+
+def fill(n):
+    for i in range(n):
+        shared.push(i)
+
+
+fill(3)
+print(shared.items, alias.items)
+
+rows = [Stack(), Stack()]
+rows[0].items.append(8)
+grid = Stack()
+grid.cells = [[1], [2]]
+grid.cells[1].append(3)
+nested = [[5], [6]]
+nested[0].append(9)
+nested[1][:].append(0)
+print(rows[0].items, rows[1].items, grid.cells, nested)
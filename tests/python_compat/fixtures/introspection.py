@@ -0,0 +1,40 @@
+class Base:
+    def greet(self):
+        return "hi"
+
+
+class Child(Base):
+    def __init__(self, name):
+        self.name = name
+        self.count = 0
+
+
+class Counter:
+    def __call__(self):
+        return 1
+
+
+def helper():
+    return 2
+
+
+c = Child(7)
+print(callable(helper), callable(len), callable(Child), callable(c.greet))
+print(callable(c), callable(Counter()), callable(1), callable("s"), callable(None))
+print(vars(c))
+c.count = 3
+setattr(c, "extra", [1, 2])
+print(vars(c))
+
+
+def attempt(f, args):
+    try:
+        print(f(*args))
+    except TypeError as e:
+        print("TypeError:", e)
+
+
+attempt(vars, [1])
+attempt(vars, [c, c])
+attempt(callable, [])
+attempt(dir, [c, c])
@@ -0,0 +1,34 @@
+# ** unpacking mappings into calls, and set() keeping distinct items
+def f(a, b=2, c=3):
+    return [a, b, c]
+
+
+kw = {"b": 20}
+print(f(1, **kw), f(**{"a": 5, "c": 7}))
+args = [1]
+print(f(*args, c=9, **kw))
+print(dict(**kw), dict(a=1, **kw), dict({"x": 0}, **kw))
+
+
+class P:
+    def __init__(self, x, y=0):
+        self.x = x
+        self.y = y
+
+
+p = P(**{"x": 4, "y": 5})
+print(p.x, p.y)
+print(sorted([3, 1, 2], **{"reverse": True}))
+try:
+    f(**{1: 2})
+except TypeError as e:
+    print(e)
+s = set([3, 1, 3, 2, 1])
+print(len(s), sorted(s), 3 in s, 5 in s, set(), set("aba") == set(["b", "a"]))
+for x in set([2, 2]):
+    print(x)
+try:
+    set([[1]])
+except TypeError as e:
+    print(e)
+print(sorted(set(n % 2 for n in range(5))))
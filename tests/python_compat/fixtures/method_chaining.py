@@ -0,0 +1,61 @@
+# Method calls, subscripts and calls chained one after another
+text = "  Alpha, Beta ,Gamma  "
+words = text.strip().lower().split(",")[1].strip().upper().split("E")
+print(words)
+
+cleaned = text \
+    .strip() \
+    .lower() \
+    .lstrip("a") \
+    .split(",")
+print(cleaned)
+
+joined = (
+    "-".join(["x", "y", "z"])
+    .upper()
+    .split("-")[::-1][0]
+    .lower()
+    .strip()
+)
+print(joined)
+
+print("abc".upper().lower().upper().lower().upper().lower())
+print([" a ", " b "][0].strip().upper())
+print({"k": " v "}["k"].strip().upper().lower())
+
+nested = [[" a,b "]]
+print(nested[0][0].strip().split(",")[1].upper())
+
+
+def adder(n):
+    def add(m):
+        def total(k):
+            return n + m + k
+
+        return total
+
+    return add
+
+
+print(adder(1)(2)(3))
+
+
+class Builder:
+    def __init__(self):
+        self.text = ""
+
+    def add(self, part):
+        self.text = (self.text + " " + part).strip()
+        return self
+
+    def build(self):
+        return self.text
+
+
+sentence = Builder().add("one").add("two").add("three").add("four").add("five").build()
+print(sentence)
+print(Builder() \
+    .add("a") \
+    .add("b") \
+    .build() \
+    .upper())
@@ -0,0 +1,22 @@
+# Negative indices as assignment and del targets count from the end
+values = [1, 2, 3, 4]
+values[-1] = 40
+values[-4] += 10
+print(values)
+first, values[-2] = "a", 30
+print(first, values)
+del values[-3]
+print(values)
+counts = {"x": 1}
+counts["x"] += 1
+counts["y"] = 5
+del counts["x"]
+print(counts)
+try:
+    values[-4] = 0
+except IndexError as e:
+    print(e)
+try:
+    del values[-9]
+except IndexError as e:
+    print(e)
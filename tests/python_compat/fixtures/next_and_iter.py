@@ -0,0 +1,48 @@
+# next() and iter(): stepping iterators by hand and ending them with StopIteration
+it = iter([1, 2])
+print(next(it), next(it), next(it, "done"))
+try:
+    next(it)
+except StopIteration as e:
+    print("stopped", e.args)
+
+gen = (c.upper() for c in "abc")
+print(next(gen), list(gen), next(gen, "empty"))
+
+
+class Countdown:
+    def __init__(self, start):
+        self.n = start
+
+    def __iter__(self):
+        return self
+
+    def __next__(self):
+        if self.n == 0:
+            raise StopIteration
+        self.n = self.n - 1
+        return self.n + 1
+
+
+c = iter(Countdown(3))
+print(next(c), next(c), next(c), next(c, "liftoff"))
+
+
+def first_even(values):
+    it = iter(values)
+    while True:
+        v = next(it, "none")
+        if v == "none":
+            return v
+        if v % 2 == 0:
+            return v
+
+
+print(first_even([1, 3, 4, 6]), first_even([1]))
+pairs = zip("xy", [1, 2])
+print(next(pairs), list(pairs))
+for value in [5, [1]]:
+    try:
+        next(value)
+    except TypeError as e:
+        print(e)
@@ -0,0 +1,37 @@
+# Numeric dict keys: equal ints, floats and bools share one entry
+d = {}
+d[1] = 1
+d[1.0] = 2.0
+print(d)
+print(d[1.0])
+print(d[True])
+print(len(d))
+d[True] = 3
+print(d)
+e = {True: 4, 1: 5, 1.0: 6}
+print(e)
+f = {0: 7, False: 8}
+print(f)
+print(0.0 in f)
+print(2 in f)
+g = {tuple([1, 2]): 9, 2.5: 10, "k": 1}
+print(g[tuple([1.0, 2])])
+print(g[2.5])
+print(g)
+del g[tuple([1, 2.0])]
+print(g)
+print(dict([[1, 11], [1.0, 12]]))
+print(1 == 1.0)
+print(True == 1.0)
+print([1, 2] == [1.0, 2.0])
+print(0.5 == 0)
+h = {-1: 13}
+print(h[-1.0])
+try:
+    x = {}[[1]]
+except TypeError as err:
+    print(err)
+try:
+    x = {1: 2}[2.0]
+except KeyError as err:
+    print("missing", err)
@@ -0,0 +1,37 @@
+# pprint lays out nested containers over indented lines once they are too wide
+import pprint
+
+data = {"zeta": [1, 2, 3], "alpha": {"nested": [10, 20, 30], "more": tuple([1])}, "mid": "text"}
+pprint.pprint(data)
+pprint.pprint(data, width=30)
+pprint.pprint(data, width=20, sort_dicts=False)
+pprint.pp(data, width=30)
+pprint.pprint(data, indent=4, width=30)
+pprint.pprint(data, depth=1)
+pprint.pprint([[1, [2, [3, [4]]]]], depth=2)
+rows = []
+for i in range(12):
+    rows.append({"id": i, "name": "row", "tags": ["a", "b"]})
+pprint.pprint(rows)
+text = pprint.pformat(list(range(40)), width=40)
+print(text)
+words = "the quick brown fox jumps over the lazy dog the quick brown fox jumps over the lazy dog the quick brown fox jumps over the lazy dog "
+pprint.pprint(words)
+pprint.pprint([words, words], width=50)
+pprint.pprint("short")
+pprint.pprint({1: "one", 2.5: "two", True: "t"})
+pprint.pprint(tuple([list(range(30))]))
+pprint.pprint([])
+pprint.pprint({})
+class P:
+    def __repr__(self):
+        return "P()"
+pprint.pprint([P(), P(), P(), P(), P(), P(), P(), P(), P(), P(), P(), P(), P(), P(), P(), P(), P()])
+try:
+    pprint.pprint(1, width=0)
+except ValueError as e:
+    print(e)
+try:
+    pprint.pformat(1, bogus=1)
+except TypeError as e:
+    print(e)
@@ -0,0 +1,12 @@
+# Recursive reprs: a container reached again through an item's __repr__ prints as [...]
+class Node:
+    def __repr__(self):
+        return "Node" + repr(self.children)
+
+
+node = Node()
+for children in [[node], tuple([node]), set([node]), {1: node}, {node: 1}]:
+    node.children = children
+    print(repr(children), str(node), f"{children}", "{}".format(children), f"{children!a}")
+shared = [1]
+print([shared, shared], [set(), tuple([])])
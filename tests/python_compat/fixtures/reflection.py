@@ -0,0 +1,53 @@
+import sys
+
+
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+    def norm(self):
+        return self.x * self.y
+
+
+class Point3(Point):
+    def depth(self):
+        return 3
+
+
+p = Point3(3, 4)
+print(getattr(p, "x"), getattr(p, "missing", "fallback"))
+print(getattr(p, "norm")())
+print(hasattr(p, "y"), hasattr(p, "depth"), hasattr(p, "z"))
+setattr(p, "z", 12)
+print(p.z, hasattr(p, "z"))
+for name in ["x", "y", "z"]:
+    setattr(p, name, getattr(p, name) * 2)
+print(p.x, p.y, p.z)
+print(getattr(Point3, "depth")(p))
+print(getattr(sys, "maxsize"), hasattr(sys, "nope"))
+print(getattr(" abc ", "strip")())
+
+
+def attempt(f, args):
+    try:
+        print(f(*args))
+    except AttributeError as e:
+        print("AttributeError:", e)
+    except TypeError as e:
+        print("TypeError:", e)
+
+
+attempt(getattr, [p, "w"])
+attempt(getattr, [Point, "w"])
+attempt(getattr, [sys, "w"])
+attempt(getattr, [None, "w"])
+attempt(getattr, [p, 1])
+attempt(getattr, [p])
+attempt(hasattr, [p, 1])
+attempt(setattr, [5, "w", 1])
+attempt(setattr, [p, "w"])
+try:
+    p.missing
+except AttributeError as e:
+    print("AttributeError:", e)
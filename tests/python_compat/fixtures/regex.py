@@ -0,0 +1,24 @@
+import re
+from re import findall, sub as replace
+
+m = re.match("(\w+) (\w+)", "hello world again")
+print(m.group(), m.group(1), m.group(2, 1))
+print(m.groups(), m.span(2), m.start(), m.end())
+print(m.string)
+
+s = re.search("[0-9]+", "abc 123 def")
+print(s.group(), s.span())
+print(re.match("[0-9]+", "abc 123") == None)
+
+print(findall("[a-z]+", "ab cd1ef"))
+print(findall("(a)(b)?", "a ab"))
+print(findall("a*", "baaa"))
+print(findall("(\d+)-", "1-22-333"))
+
+print(replace("a+", "-", "caaat aa"))
+print(re.sub("x*", "-", "abc"))
+print(re.sub("(?:ab)+", "<\g<0>>", "ababc"))
+print(re.sub("o", "0", "foo boo", 2))
+print(re.search("colou?r", "my colour").group())
+print(re.search("a{2,3}?", "aaaa").group())
+print(re.search("^[^@ ]+@[a-z.]+$", "ann@example.com").group())
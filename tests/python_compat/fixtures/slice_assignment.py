@@ -0,0 +1,37 @@
+# Slice assignment and del of items and slices
+items = [0, 1, 2, 3, 4, 5]
+items[1:3] = ["a", "b", "c"]
+print(items)
+items[:2] = []
+items[len(items):] = [9, 10]
+print(items)
+items[::2] = [7, 7, 7, 7]
+items[2:2] = "xy"
+print(items)
+items[-2:] = (n * 3 for n in range(3))
+print(items)
+del items[1:3]
+print(items)
+del items[::2], items[0]
+print(items)
+scores = {"ann": 1, "ben": 2}
+del scores["ann"]
+print(scores)
+
+
+def trim(values):
+    values[1:-1] = [sum(values[1:-1])]
+    del values[-1]
+    return values
+
+
+print(trim([1, 2, 3, 4, 5]))
+grid = [1, 2, 3]
+try:
+    grid[::2] = [1]
+except ValueError as e:
+    print(e)
+try:
+    del grid[5]
+except IndexError as e:
+    print(e)
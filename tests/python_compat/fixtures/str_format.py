@@ -0,0 +1,54 @@
+# The format() builtin and str.format()
+
+pi = 3.14159265
+n = 1234567
+name = "Ada"
+print(format(pi, ".2f"), format(42), format(42, "05d"), format("x", "^5"))
+print("{} and {}".format("a", 1))
+print("{1}-{0}-{1}".format("a", "b"))
+print("{0:>6.2f}|{1!r:^8}|".format(pi, "q"))
+print("{:{}}|".format("w", 5))
+print("{0[1]} {0[0]}".format(["x", "y"]))
+print("{{literal}} {}".format(3))
+d = {"k": "v"}
+print("{0[k]}".format(d))
+
+
+class Point:
+    def __init__(self, x):
+        self.x = x
+
+    def __repr__(self):
+        return "Point(" + "x" + ")"
+
+
+p = Point(2)
+print(f"{p} {p!r} {p!s}", "{0.x}".format(p), format(p))
+try:
+    print(f"{name:d}")
+except ValueError as e:
+    print("ValueError:", e)
+try:
+    print(f"{n:.2d}")
+except ValueError as e:
+    print("ValueError:", e)
+try:
+    print("{} {}".format(1))
+except IndexError as e:
+    print("IndexError:", e)
+try:
+    print("{0} {}".format(1, 2))
+except ValueError as e:
+    print("ValueError:", e)
+try:
+    print(format([1], ">5"))
+except TypeError as e:
+    print("TypeError:", e)
+try:
+    print("{".format(1))
+except ValueError as e:
+    print("ValueError:", e)
+try:
+    print(f"{n:,_}")
+except ValueError as e:
+    print("ValueError:", e)
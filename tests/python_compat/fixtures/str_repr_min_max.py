@@ -0,0 +1,22 @@
+# str(), repr(), min() and max(), and printing instances whose __str__ calls them
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+    def __str__(self):
+        return "(" + str(self.x) + ", " + str(self.y) + ")"
+
+    def __repr__(self):
+        return "Point(" + repr(self.x) + ", " + repr(self.y) + ")"
+
+
+p = Point(1, "a")
+print(p)
+print(str(p), repr(p))
+print(str(1) + str(2.5) + str(None) + str(True), repr("hi"), str())
+items = [3, 1, 2]
+print(", ".join(str(x) for x in items))
+print(min(items), max(items), min(4, 2, 3), max("hello"))
+print(max(["a", "bbb", "cc"], key=len), min([], default="none"))
+print(max(n * -1 for n in items))
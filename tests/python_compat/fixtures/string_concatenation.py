@@ -0,0 +1,21 @@
+name = "world"
+greeting = "hello, " "there"
+print(greeting)
+print("a" 'b' "c", len("ab" "cd"))
+message = ("first line, "
+           "second line, "
+           f"{name}!")
+print(message)
+print(f"{name}" " and " f"{name}")
+print("x" f"" "y")
+items = ["a" "b",
+         "c"]
+print(items)
+x = "one"
+"a standalone string"
+print(x)
+def show(a, b):
+    return a + "|" + b
+print(show("p" "q",
+           "r"
+           "s"))
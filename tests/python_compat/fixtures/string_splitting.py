@@ -0,0 +1,21 @@
+print("  a b  c  ".split())
+print("  a b  c  ".split(None, 1))
+print("  a b  c  ".rsplit(None, 1))
+print("a,b,,c".split(","), "a,b,,c".split(",", 2), "a,b,,c".rsplit(",", 2))
+print("".split(), "".split(","), "a::b::c".split("::"))
+
+print("key=value=x".partition("="), "key=value=x".rpartition("="))
+print("novalue".partition("="), "novalue".rpartition("="))
+
+print("-".join(["a", "b", "c"]), ",".join("xyz"), "[" + "".join([]) + "]")
+print("[" + "  hi  ".strip() + "]", "xxhixx".strip("x"), "[" + "  hi ".lstrip() + "]")
+
+line = "name, age ,city"
+print(",".join(line.split(",")) == line)
+
+data = "id;name;score|1;ann; 88 |2;ben;93"
+rows = data.split("|")
+header = rows[0].split(";")
+for row in rows[1:]:
+    fields = row.split(";")
+    print(header[1], fields[1], header[2], "[" + fields[2].strip() + "]")
@@ -0,0 +1,7 @@
+word = "héllo"
+print(word[0], word[1], word[-1])
+print(len(word))
+for ch in "abc":
+    print(ch)
+print("Py" + "thon")
+print("ell" in "hello")
@@ -0,0 +1,81 @@
+# super() without arguments at every depth of a class hierarchy, and in class methods
+class A:
+    def __init__(self):
+        self.log = "A"
+
+    def name(self):
+        return "A"
+
+
+class B(A):
+    def __init__(self):
+        super().__init__()
+        self.log = self.log + "B"
+
+    def name(self):
+        return "B" + super().name()
+
+
+class C(B):
+    def __init__(self):
+        super().__init__()
+        self.log = self.log + "C"
+
+    def name(self):
+        return "C" + super().name()
+
+
+class D(C):
+    def extra(self):
+        return 1
+
+
+c = C()
+print(c.log, c.name())
+d = D()
+print(d.log, d.name())
+
+
+class Shape:
+
+    @classmethod
+    def create(cls, size):
+        return cls(size)
+
+    @classmethod
+    def describe(cls):
+        return f"shape of area {cls(1).area()}"
+
+    def __init__(self, size):
+        self.size = size
+
+    def area(self):
+        return 0
+
+
+class Square(Shape):
+    @classmethod
+    def describe(cls):
+        return "square < " + super().describe()
+
+    def __init__(self, size):
+        super().__init__(size)
+        self.kind = "square"
+
+    def area(self):
+        return self.size * self.size + super().area()
+
+
+class Cube(Square):
+    @classmethod
+    def describe(cls):
+        return "cube < " + super().describe()
+
+    def area(self):
+        return 6 * super().area()
+
+
+c = Cube.create(2)
+print(c.kind, c.size, c.area())
+print(Cube.describe(), c.describe(), Square.describe())
+print(Square.create(3).area(), Shape.create(1).area())
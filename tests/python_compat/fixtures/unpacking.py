@@ -0,0 +1,9 @@
+first, *rest = [1, 2, 3, 4]
+print(first, rest)
+*init, last = "abc"
+print(init, last)
+a, *middle, z = [1, 2]
+print(a, middle, z)
+a, b = 1, 2
+a, b = b, a
+print(a, b)
@@ -0,0 +1,126 @@
+//! Golden tests against CPython.
+//!
+//! Runs every `.py` file under `examples/` and `tests/python_compat/fixtures/`
+//! with both oxython and `python3` and compares their stdout. Fixtures listed in
+//! `known_divergences.txt` are expected to differ. The test is skipped when no
+//! CPython is available; set `OXYTHON_COMPAT_PYTHON` to use another interpreter.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const FIXTURE_DIRS: [&str; 2] = ["examples", "tests/python_compat/fixtures"];
+const ALLOWLIST: &str = "tests/python_compat/known_divergences.txt";
+
+fn collect_fixtures(dir: &Path, fixtures: &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir).unwrap_or_else(|e| panic!("cannot read {:?}: {}", dir, e));
+    for entry in entries {
+        let path = entry.expect("unreadable directory entry").path();
+        if path.is_dir() {
+            collect_fixtures(&path, fixtures);
+        } else if path.extension().is_some_and(|ext| ext == "py") {
+            fixtures.push(path);
+        }
+    }
+}
+
+/// Maps each allowlisted fixture path to the reason it diverges.
+fn known_divergences(root: &Path) -> BTreeMap<String, String> {
+    let text = fs::read_to_string(root.join(ALLOWLIST)).expect("cannot read allowlist");
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (path, reason) = line.split_once('#').unwrap_or((line, ""));
+            (path.trim().to_string(), reason.trim().to_string())
+        })
+        .collect()
+}
+
+fn stdout_of(program: &str, root: &Path, fixture: &str) -> Option<String> {
+    let output = Command::new(program)
+        .current_dir(root)
+        .arg(fixture)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Describes the first line where the two outputs differ.
+fn first_difference(ours: &str, theirs: &str) -> String {
+    let mut ours_lines = ours.lines();
+    let mut theirs_lines = theirs.lines();
+    for line in 1.. {
+        match (ours_lines.next(), theirs_lines.next()) {
+            (Some(a), Some(b)) if a == b => continue,
+            (None, None) => break,
+            (a, b) => {
+                return format!(
+                    "line {}:\n    oxython: {}\n    python:  {}",
+                    line,
+                    a.unwrap_or("<end of output>"),
+                    b.unwrap_or("<end of output>")
+                )
+            }
+        }
+    }
+    "outputs differ only in trailing newlines".to_string()
+}
+
+#[test]
+fn fixtures_match_cpython() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let python = std::env::var("OXYTHON_COMPAT_PYTHON").unwrap_or_else(|_| "python3".into());
+    let has_python = Command::new(&python)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !has_python {
+        eprintln!("skipping CPython comparison: `{}` is not available", python);
+        return;
+    }
+
+    let mut fixtures = Vec::new();
+    for dir in FIXTURE_DIRS {
+        collect_fixtures(&root.join(dir), &mut fixtures);
+    }
+    fixtures.sort();
+
+    let allowlist = known_divergences(root);
+    let mut failures = Vec::new();
+    for path in &fixtures {
+        let fixture = path
+            .strip_prefix(root)
+            .expect("fixture outside the repository")
+            .to_string_lossy()
+            .replace('\\', "/");
+        let ours = stdout_of(env!("CARGO_BIN_EXE_oxython"), root, &fixture)
+            .expect("failed to run oxython");
+        let theirs = stdout_of(&python, root, &fixture).expect("failed to run python");
+        match (ours == theirs, allowlist.contains_key(&fixture)) {
+            (false, false) => failures.push(format!(
+                "{} differs from CPython at {}",
+                fixture,
+                first_difference(&ours, &theirs)
+            )),
+            (true, true) => failures.push(format!(
+                "{} now matches CPython; remove it from {}",
+                fixture, ALLOWLIST
+            )),
+            _ => {}
+        }
+    }
+
+    for listed in allowlist.keys() {
+        if !root.join(listed).is_file() {
+            failures.push(format!("{} lists missing fixture {}", ALLOWLIST, listed));
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}\n", failures.join("\n"));
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "sync")]
+
+use oxython::embed;
+use oxython::platform::Output;
+use oxython::sync::{Rc, RefCell};
+use oxython::vm::{InterpretResult, VM};
+use std::thread;
+
+fn assert_send<T: Send>() {}
+
+#[test]
+fn vms_and_chunks_are_send() {
+    assert_send::<VM>();
+    assert_send::<oxython::bytecode::Chunk>();
+    assert_send::<oxython::object::Object>();
+}
+
+#[test]
+fn a_vm_keeps_its_globals_when_moved_to_another_thread() {
+    let printed = Rc::new(RefCell::new(String::new()));
+    let sink = printed.clone();
+    let mut vm = VM::new();
+    vm.set_output(Output::Writer(Box::new(move |text| {
+        sink.borrow_mut().push_str(text)
+    })));
+    let chunk =
+        embed::compile("class C:\n    def __init__(self):\n        self.n = 1\nc = C()").unwrap();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+
+    let chunk = embed::compile("c.n = c.n + 1\nprint(c.n)").unwrap();
+    let result = thread::spawn(move || vm.interpret(chunk)).join().unwrap();
+    assert_eq!(result, InterpretResult::Ok);
+    assert_eq!(*printed.borrow(), "2\n");
+}
@@ -1,8 +1,10 @@
 use oxython::bytecode::{Chunk, OpCode};
 use oxython::compiler::Compiler;
-use oxython::object::{FunctionObject, ObjectType};
+use oxython::object::{DictEntries, FunctionObject, ObjectType};
+use oxython::sync::Rc;
+use oxython::vm::limits::Limit;
 use oxython::vm::{InterpretResult, VM};
-use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 // Import Stack to test Default trait
 mod stack_default_test {
@@ -522,7 +524,7 @@ fn vm_sets_dict_entry() {
     let mut chunk = Chunk::new();
     let dict_idx = push_constant(
         &mut chunk,
-        ObjectType::Dict(vec![("a".into(), Rc::new(ObjectType::Integer(1)))]),
+        ObjectType::dict_of_names(vec![("a".into(), Rc::new(ObjectType::Integer(1)))]),
     );
     chunk.code.push(OpCode::OpConstant as u8);
     chunk.code.push(dict_idx as u8);
@@ -539,7 +541,7 @@ fn vm_sets_dict_entry() {
     assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
     let top = vm.peek_stack().expect("expected value on stack");
     assert!(matches!(&*top, ObjectType::Dict(ref entries)
-            if entries.iter().any(|(k, v)| k == "a" && matches!(&**v, ObjectType::Integer(5)))));
+            if entries.iter().any(|(k, v)| **k == ObjectType::String("a".into()) && matches!(&**v, ObjectType::Integer(5)))));
 }
 
 #[test]
@@ -563,7 +565,7 @@ fn vm_contains_supports_list_and_dict_and_string() {
     // dict contains
     let dict_idx = push_constant(
         &mut chunk,
-        ObjectType::Dict(vec![("k".into(), Rc::new(ObjectType::Integer(1)))]),
+        ObjectType::dict_of_names(vec![("k".into(), Rc::new(ObjectType::Integer(1)))]),
     );
     let key_idx = push_constant(&mut chunk, ObjectType::String("k".into()));
     chunk.code.push(OpCode::OpConstant as u8);
@@ -719,7 +721,7 @@ fn vm_index_errors_on_negative_out_of_bounds() {
 #[test]
 fn vm_index_errors_on_missing_dict_key() {
     let mut chunk = Chunk::new();
-    let dict_idx = push_constant(&mut chunk, ObjectType::Dict(vec![]));
+    let dict_idx = push_constant(&mut chunk, ObjectType::Dict(DictEntries::new()));
     chunk.code.push(OpCode::OpConstant as u8);
     chunk.code.push(dict_idx as u8);
     let key_idx = push_constant(&mut chunk, ObjectType::String("missing".into()));
@@ -1044,7 +1046,7 @@ fn vm_round_handles_integer_value() {
     let mut vm = VM::new();
     assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
     let top = vm.peek_stack().expect("expected value on stack");
-    assert!(matches!(&*top, ObjectType::Float(ref v) if (*v - 5.0).abs() < f64::EPSILON));
+    assert!(matches!(&*top, ObjectType::Integer(5)));
 }
 
 #[test]
@@ -1060,8 +1062,7 @@ fn vm_iter_next_errors_on_negative_index() {
     chunk.code.push(OpCode::OpConstant as u8);
     chunk.code.push(neg_idx as u8);
     chunk.code.push(OpCode::OpIterNext as u8);
-    chunk.code.push(0);
-    chunk.code.push(0);
+    chunk.code.extend([0, 0, 0, 0]);
     chunk.code.push(OpCode::OpReturn as u8);
 
     let mut vm = VM::new();
@@ -1078,8 +1079,7 @@ fn vm_iter_next_errors_on_invalid_type() {
     chunk.code.push(OpCode::OpConstant as u8);
     chunk.code.push(idx_idx as u8);
     chunk.code.push(OpCode::OpIterNext as u8);
-    chunk.code.push(0);
-    chunk.code.push(0);
+    chunk.code.extend([0, 0, 0, 0]);
     chunk.code.push(OpCode::OpReturn as u8);
 
     let mut vm = VM::new();
@@ -1087,11 +1087,14 @@ fn vm_iter_next_errors_on_invalid_type() {
 }
 
 #[test]
-fn vm_set_index_errors_on_negative_list_index() {
+fn vm_set_index_counts_negative_list_index_from_end() {
     let mut chunk = Chunk::new();
     let list_idx = push_constant(
         &mut chunk,
-        ObjectType::List(vec![Rc::new(ObjectType::Integer(1))]),
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Integer(1)),
+            Rc::new(ObjectType::Integer(2)),
+        ]),
     );
     chunk.code.push(OpCode::OpConstant as u8);
     chunk.code.push(list_idx as u8);
@@ -1104,8 +1107,35 @@ fn vm_set_index_errors_on_negative_list_index() {
     chunk.code.push(OpCode::OpSetIndex as u8);
     chunk.code.push(OpCode::OpReturn as u8);
 
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    let top = vm.peek_stack().expect("expected value on stack");
+    assert_eq!(top.to_string(), "[1, 42]");
+}
+
+#[test]
+fn vm_set_index_errors_on_negative_list_index_past_start() {
+    let mut chunk = Chunk::new();
+    let list_idx = push_constant(
+        &mut chunk,
+        ObjectType::List(vec![Rc::new(ObjectType::Integer(1))]),
+    );
+    chunk.code.push(OpCode::OpConstant as u8);
+    chunk.code.push(list_idx as u8);
+    let neg_idx = push_constant(&mut chunk, ObjectType::Integer(-2));
+    chunk.code.push(OpCode::OpConstant as u8);
+    chunk.code.push(neg_idx as u8);
+    let value_idx = push_constant(&mut chunk, ObjectType::Integer(42));
+    chunk.code.push(OpCode::OpConstant as u8);
+    chunk.code.push(value_idx as u8);
+    chunk.code.push(OpCode::OpSetIndex as u8);
+    chunk.code.push(OpCode::OpReturn as u8);
+
     let mut vm = VM::new();
     assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+    let error = vm.last_error().expect("expected a recorded runtime error");
+    assert_eq!(error.kind, "IndexError");
+    assert_eq!(error.message, "list assignment index out of range");
 }
 
 #[test]
@@ -1152,7 +1182,7 @@ fn vm_set_index_errors_on_invalid_type() {
 #[test]
 fn vm_set_index_adds_new_dict_entry() {
     let mut chunk = Chunk::new();
-    let dict_idx = push_constant(&mut chunk, ObjectType::Dict(vec![]));
+    let dict_idx = push_constant(&mut chunk, ObjectType::Dict(DictEntries::new()));
     chunk.code.push(OpCode::OpConstant as u8);
     chunk.code.push(dict_idx as u8);
     let key_idx = push_constant(&mut chunk, ObjectType::String("new".into()));
@@ -1459,7 +1489,7 @@ fn vm_index_dict_with_string_key() {
     let mut chunk = Chunk::new();
     let dict_idx = push_constant(
         &mut chunk,
-        ObjectType::Dict(vec![
+        ObjectType::dict_of_names(vec![
             ("name".into(), Rc::new(ObjectType::String("Alice".into()))),
             ("age".into(), Rc::new(ObjectType::Integer(30))),
         ]),
@@ -1547,7 +1577,7 @@ fn vm_contains_checks_dict_for_key() {
     chunk.code.push(key_idx as u8);
     let dict_idx = push_constant(
         &mut chunk,
-        ObjectType::Dict(vec![
+        ObjectType::dict_of_names(vec![
             ("x".into(), Rc::new(ObjectType::Integer(10))),
             ("y".into(), Rc::new(ObjectType::Integer(20))),
         ]),
@@ -1606,8 +1636,7 @@ fn vm_iter_next_iterates_over_list() {
     chunk.code.push(OpCode::OpConstant as u8);
     chunk.code.push(zero_idx as u8);
     chunk.code.push(OpCode::OpIterNext as u8);
-    chunk.code.push(0);
-    chunk.code.push(3); // Jump 3 bytes if done
+    chunk.code.extend([0, 0, 0, 3]); // Jump 3 bytes if done
     chunk.code.push(OpCode::OpPop as u8); // Pop the element
     chunk.code.push(OpCode::OpReturn as u8);
 
@@ -1634,8 +1663,7 @@ fn vm_iter_next_iterates_over_string() {
     chunk.code.push(OpCode::OpConstant as u8);
     chunk.code.push(zero_idx as u8);
     chunk.code.push(OpCode::OpIterNext as u8);
-    chunk.code.push(0);
-    chunk.code.push(3);
+    chunk.code.extend([0, 0, 0, 3]);
     chunk.code.push(OpCode::OpPop as u8);
     chunk.code.push(OpCode::OpReturn as u8);
 
@@ -2305,6 +2333,44 @@ print(my_func.__globals__)
     assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
 }
 
+#[test]
+fn test_function_globals_are_the_live_module_globals() {
+    let source = r#"
+def my_func():
+    return 1
+
+later = 42
+print(my_func.__globals__["later"], getattr(my_func, "__globals__")["my_func"] is my_func)
+print(hasattr(my_func, "__globals__"))
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    vm.capture_output();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(vm.take_output(), "42 True\nTrue\n");
+}
+
+#[test]
+fn test_dropping_many_functions_made_in_turn_does_not_recurse() {
+    // Each function used to hold a copy of the globals, so every one held
+    // the one made before it, and dropping the last dropped them all in turn
+    let source = r#"
+def mk():
+    def inner():
+        return 1
+    return inner
+
+i = 0
+while i < 100000:
+    h = mk()
+    i = i + 1
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    drop(vm);
+}
+
 #[test]
 fn test_function_closure_nil_for_non_closure() {
     let source = r#"
@@ -2387,6 +2453,381 @@ print(counter())
     assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
 }
 
+#[test]
+fn test_closure_calls_sibling_defined_later() {
+    let source = r#"
+def outer():
+    def inner1():
+        return inner2() + 1
+    def inner2():
+        return 10
+    return inner1()
+
+outer()
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(*vm.last_popped_stack_elem(), ObjectType::Integer(11));
+}
+
+#[test]
+fn test_sibling_closures_recurse_mutually_after_outer_returns() {
+    let source = r#"
+def make_parity():
+    def is_even(n):
+        if n == 0:
+            return True
+        return is_odd(n - 1)
+    def is_odd(n):
+        if n == 0:
+            return False
+        return is_even(n - 1)
+    return is_even
+
+is_even = make_parity()
+[is_even(10), is_even(7)]
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(
+        *vm.last_popped_stack_elem(),
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Boolean(true)),
+            Rc::new(ObjectType::Boolean(false)),
+        ])
+    );
+}
+
+#[test]
+fn test_sibling_closure_sees_redefinition() {
+    let source = r#"
+def outer():
+    def call():
+        return target()
+    def target():
+        return 1
+    first = call()
+    def target():
+        return 2
+    return [first, call()]
+
+outer()
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(
+        *vm.last_popped_stack_elem(),
+        ObjectType::List(vec![
+            Rc::new(ObjectType::Integer(1)),
+            Rc::new(ObjectType::Integer(2)),
+        ])
+    );
+}
+
+#[test]
+fn test_return_from_nested_blocks_leaves_caller_stack_intact() {
+    let source = r#"
+def find(items, target):
+    for i in range(len(items)):
+        j = 0
+        while j < 3:
+            if items[i] == target:
+                if j == 1:
+                    found = i
+                    return found
+            j = j + 1
+    return -1
+
+results = []
+for k in [1, 2]:
+    results.append(100 * k + find([5, 6, 7], 7) + find([5], 9))
+[results, 1 + find([4, 5], 5) + 2]
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(vm.last_popped_stack_elem().to_string(), "[[101, 201], 4]");
+}
+
+#[test]
+fn test_break_and_continue_leave_enclosing_loops_intact() {
+    let cases = [
+        (
+            r#"
+out = []
+for i in range(10):
+    if i % 2 == 0:
+        if i == 6:
+            break
+        else:
+            continue
+    out.append(i)
+out
+"#,
+            "[1, 3, 5]",
+        ),
+        (
+            r#"
+def f():
+    res = []
+    for i in range(5):
+        for j in range(5):
+            if j > i:
+                break
+            if (i + j) % 2 == 1:
+                continue
+            res.append([i, j])
+        if i == 3:
+            break
+    return res
+[1, f(), 2]
+"#,
+            "[1, [[0, 0], [1, 1], [2, 0], [2, 2], [3, 1], [3, 3]], 2]",
+        ),
+        (
+            r#"
+out = []
+n = 0
+while n < 10:
+    n = n + 1
+    for k in range(3):
+        if k == 1:
+            continue
+        if n > 2:
+            break
+        out.append([n, k])
+    if n == 5:
+        break
+[out, n]
+"#,
+            "[[[1, 0], [1, 2], [2, 0], [2, 2]], 5]",
+        ),
+        (
+            r#"
+def g():
+    out = []
+    for i in range(6):
+        try:
+            if i == 1:
+                continue
+            if i == 4:
+                break
+            out.append(i)
+        except ValueError:
+            out.append(-1)
+        finally:
+            out.append("f")
+    return out
+g()
+"#,
+            "[0, 'f', 'f', 2, 'f', 3, 'f', 'f']",
+        ),
+        (
+            r#"
+out = []
+for i in range(4):
+    try:
+        if i == 2:
+            raise ValueError("x")
+        out.append(i)
+    except ValueError:
+        out.append("e")
+        continue
+    if i == 3:
+        break
+out
+"#,
+            "[0, 1, 'e', 3]",
+        ),
+        (
+            r#"
+def h():
+    total = 0
+    for i in range(100):
+        for j in range(100):
+            if j == 2:
+                break
+            total = total + 1
+    return total
+1 + h() + 2
+"#,
+            "203",
+        ),
+        (
+            r#"
+out = []
+for x in [[1, 2], [3, 4]]:
+    for y in x:
+        if y == 2:
+            continue
+        out.append(y)
+out
+"#,
+            "[1, 3, 4]",
+        ),
+        (
+            r#"
+out = []
+for i in range(3):
+    for j in range(3):
+        try:
+            if j == 1:
+                break
+        finally:
+            out.append([i, j])
+out
+"#,
+            "[[0, 0], [0, 1], [1, 0], [1, 1], [2, 0], [2, 1]]",
+        ),
+    ];
+
+    for (source, expected) in cases {
+        let chunk = Compiler::compile(source).expect("Expected chunk");
+        let mut vm = VM::new();
+        assert_eq!(vm.interpret(chunk), InterpretResult::Ok, "{source}");
+        assert_eq!(
+            vm.last_popped_stack_elem().to_string(),
+            expected,
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn test_call_statements_leave_nothing_on_the_stack() {
+    // Debug builds check the stack height as each statement starts, so a
+    // statement leaving a value behind fails the run
+    let source = r#"
+class Box:
+    def __init__(self, items):
+        self.items = items
+    def get(self):
+        return self
+
+def pair():
+    return [1, 2]
+
+def show(value):
+    return value
+
+pair()
+pair()[0]
+box = Box([3, 4])
+box.get().get()
+box.get().items[1]
+out = []
+for i in range(3):
+    pair()[1]
+    show(i)
+    [i, pair()][1][0]
+    out.append(show(box.get().items[0]) + i)
+
+def run():
+    total = 0
+    for item in box.items:
+        pair()
+        show([item])[0]
+        total += show(item)
+    return total
+[out, run(), len([1, 2])]
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(vm.last_popped_stack_elem().to_string(), "[[3, 4, 5], 7, 2]");
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "stack height at the statement")]
+fn vm_checks_stack_height_as_statements_start() {
+    let mut chunk = Chunk::new();
+    let value_idx = push_constant(&mut chunk, ObjectType::Integer(1));
+    chunk.add_statement(0);
+    chunk.code.push(OpCode::OpConstant as u8);
+    chunk.code.push(value_idx as u8);
+    // The first statement leaves its value on the stack
+    chunk.add_statement(0);
+    chunk.code.push(OpCode::OpConstant as u8);
+    chunk.code.push(value_idx as u8);
+    chunk.code.push(OpCode::OpPop as u8);
+    chunk.code.push(OpCode::OpReturn as u8);
+
+    VM::new().interpret(chunk);
+}
+
+#[test]
+fn test_locals_first_assigned_in_nested_blocks_keep_their_slots() {
+    let source = r#"
+def f(flag):
+    if flag:
+        a = 1
+    for i in [1, 2]:
+        b = i
+    try:
+        c = 3
+        raise ValueError("x")
+    except ValueError as error:
+        d = [b, c]
+    e = len([n for n in [1, 2, 3]])
+    return [d, e]
+
+[f(False), f(True)]
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(
+        vm.last_popped_stack_elem().to_string(),
+        "[[[2, 3], 3], [[2, 3], 3]]"
+    );
+}
+
+#[test]
+fn test_return_inside_loop_closes_captured_upvalues() {
+    let source = r#"
+def make():
+    for i in [1, 2, 3]:
+        total = i * 10
+        def get():
+            return total + i
+        if i == 2:
+            try:
+                return get
+            finally:
+                extra = 1
+    return None
+
+get = make()
+get()
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(*vm.last_popped_stack_elem(), ObjectType::Integer(22));
+}
+
+#[test]
+fn test_nested_functions_stay_local_to_their_function() {
+    let source = r#"
+def outer():
+    def helper():
+        return 1
+    return helper()
+
+outer()
+helper
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+    let error = vm.last_error().expect("expected an error");
+    assert_eq!(error.kind, "NameError");
+}
+
 #[test]
 fn test_function_defaults_no_defaults() {
     let source = r#"
@@ -2451,3 +2892,432 @@ print(mixed.__defaults__)
     let mut vm = VM::new();
     assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
 }
+
+#[test]
+fn test_small_integers_are_cached() {
+    use oxython::vm::values;
+
+    assert!(Rc::ptr_eq(&values::integer(7), &values::integer(7)));
+    assert!(Rc::ptr_eq(&values::boolean(true), &values::boolean(true)));
+    assert!(!Rc::ptr_eq(
+        &values::integer(100_000),
+        &values::integer(100_000)
+    ));
+    assert_eq!(*values::integer(100_000), ObjectType::Integer(100_000));
+}
+
+#[test]
+fn test_closures_share_prototype_chunk() {
+    let source = r#"
+def make_adder(n):
+    def add(x):
+        return x + n
+    return add
+
+first = make_adder(1)
+second = make_adder(2)
+first
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    let first = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+
+    let chunk = Compiler::compile("second").expect("Expected chunk");
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    let second = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+
+    match (&*first, &*second) {
+        (ObjectType::Function(a), ObjectType::Function(b)) => {
+            assert!(!Rc::ptr_eq(a, b));
+            assert!(Rc::ptr_eq(&a.chunk, &b.chunk));
+        }
+        other => panic!("expected two functions, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_recursive_calls_return_correct_result() {
+    let source = r#"
+def fib(n):
+    if n < 2:
+        return n
+    return fib(n - 1) + fib(n - 2)
+
+fib(20)
+"#;
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    let value = vm
+        .peek_stack()
+        .unwrap_or_else(|| vm.last_popped_stack_elem());
+    assert_eq!(*value, ObjectType::Integer(6765));
+}
+
+fn run_corrupt_chunk(code: Vec<u8>, constants: Vec<ObjectType>) -> String {
+    let mut chunk = Chunk::new();
+    chunk.code = code;
+    chunk.constants = constants.into_iter().map(Rc::new).collect();
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+    let error = vm.last_error().expect("expected a recorded runtime error");
+    format!("{}: {}", error.kind, error.message)
+}
+
+#[test]
+fn test_corrupt_bytecode_raises_instead_of_panicking() {
+    assert_eq!(
+        run_corrupt_chunk(vec![200], vec![]),
+        "SystemError: invalid opcode 200"
+    );
+    assert_eq!(
+        run_corrupt_chunk(vec![OpCode::OpConstant as u8, 3], vec![ObjectType::Nil]),
+        "SystemError: constant index out of range"
+    );
+    assert_eq!(
+        run_corrupt_chunk(vec![OpCode::OpPop as u8, OpCode::OpPop as u8], vec![]),
+        "SystemError: stack access out of range"
+    );
+    assert_eq!(
+        run_corrupt_chunk(vec![OpCode::OpLoop as u8, 0, 0, 0, 9], vec![]),
+        "SystemError: loop target out of range"
+    );
+}
+
+#[test]
+fn test_code_without_return_stops_at_its_end() {
+    let mut chunk = Chunk::new();
+    let idx = push_constant(&mut chunk, ObjectType::Integer(7));
+    chunk.code.push(OpCode::OpConstant as u8);
+    chunk.code.push(idx as u8);
+    // The truncated jump operand reads past the end, which acts as a return
+    chunk.code.push(OpCode::OpJump as u8);
+
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+}
+
+#[test]
+fn test_with_limits_stops_runaway_loops() {
+    let chunk = Compiler::compile("x = 0\nwhile True:\n    x = x + 1").expect("Expected chunk");
+    let mut vm = VM::with_limits(10_000, usize::MAX, usize::MAX);
+    assert_eq!(vm.interpret(chunk), InterpretResult::LimitExceeded);
+    assert_eq!(vm.exceeded_limit(), Some(Limit::Instructions));
+    assert_eq!(
+        vm.exceeded_limit().unwrap().to_string(),
+        "instruction limit exceeded"
+    );
+}
+
+#[test]
+fn test_with_limits_stop_loops_inside_callbacks_and_generators() {
+    let spin_in_key = "\
+def key(x):
+    while True:
+        x = x + 1
+try:
+    sorted([1, 2], key=key)
+except Exception:
+    caught = True";
+    let spin_in_generator = "\
+def forever():
+    n = 0
+    while True:
+        n = n + 1
+    yield n
+for value in forever():
+    print(value)";
+    for source in [spin_in_key, spin_in_generator] {
+        let chunk = Compiler::compile(source).expect("Expected chunk");
+        let mut vm = VM::with_limits(100_000, usize::MAX, usize::MAX);
+        assert_eq!(vm.interpret(chunk.clone()), InterpretResult::LimitExceeded);
+        assert_eq!(vm.exceeded_limit(), Some(Limit::Instructions));
+
+        let mut vm = VM::new();
+        vm.set_timeout(Some(Duration::from_millis(50)));
+        assert_eq!(vm.interpret(chunk), InterpretResult::LimitExceeded);
+        assert_eq!(vm.exceeded_limit(), Some(Limit::Time));
+    }
+
+    let source = "def f(n):\n    return f(n + 1)\nsorted([1], key=f)";
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::with_limits(usize::MAX, usize::MAX, 10);
+    assert_eq!(vm.interpret(chunk), InterpretResult::LimitExceeded);
+    assert_eq!(vm.exceeded_limit(), Some(Limit::CallDepth));
+}
+
+#[test]
+fn test_with_limits_stops_unbounded_allocation() {
+    let source = "n = 0\nxs = []\nwhile True:\n    n = n + 1\n    xs.append(n)";
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::with_limits(10_000_000, 5_000, usize::MAX);
+    assert_eq!(vm.interpret(chunk), InterpretResult::LimitExceeded);
+    assert_eq!(vm.exceeded_limit(), Some(Limit::HeapObjects));
+}
+
+#[test]
+fn test_with_limits_stops_deep_calls() {
+    let source = "def f(n):\n    return f(n + 1)\nf(0)";
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::with_limits(usize::MAX, usize::MAX, 10);
+    assert_eq!(vm.interpret(chunk), InterpretResult::LimitExceeded);
+    assert_eq!(vm.exceeded_limit(), Some(Limit::CallDepth));
+}
+
+#[test]
+fn test_with_limits_allows_scripts_within_budget() {
+    let source =
+        "def fib(n):\n    if n < 2:\n        return n\n    return fib(n - 1) + fib(n - 2)\nfib(15)";
+    let mut vm = VM::with_limits(1_000_000, 1_000, 20);
+    for _ in 0..2 {
+        let chunk = Compiler::compile(source).expect("Expected chunk");
+        assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+        assert_eq!(vm.exceeded_limit(), None);
+        let value = vm
+            .peek_stack()
+            .unwrap_or_else(|| vm.last_popped_stack_elem());
+        assert_eq!(*value, ObjectType::Integer(610));
+    }
+}
+
+#[test]
+fn test_timeout_stops_long_runs() {
+    let chunk = Compiler::compile("x = 0\nwhile True:\n    x = x + 1").expect("Expected chunk");
+    let mut vm = VM::new();
+    vm.set_timeout(Some(Duration::from_millis(50)));
+    let started = Instant::now();
+    assert_eq!(vm.interpret(chunk), InterpretResult::LimitExceeded);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert_eq!(vm.exceeded_limit(), Some(Limit::Time));
+    assert_eq!(
+        vm.exceeded_limit().unwrap().to_string(),
+        "time limit exceeded"
+    );
+
+    // The budget applies to each run afresh
+    let chunk = Compiler::compile("1 + 2").expect("Expected chunk");
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(vm.exceeded_limit(), None);
+}
+
+#[test]
+fn test_deterministic_mode_numbers_object_addresses() {
+    let source =
+        "class A:\n    def f(self):\n        return 1\na = A()\nb = A()\nf = A.f\nxs = [a, b, a, f]";
+    let run = || {
+        let mut vm = VM::new();
+        vm.set_deterministic(true);
+        let chunk = Compiler::compile(source).expect("Expected chunk");
+        assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+        vm.last_popped_stack_elem().to_string()
+    };
+    let first = run();
+    assert_eq!(
+        first,
+        "[<A object at 0x10>, <A object at 0x20>, <A object at 0x10>, <function A.f at 0x30>]"
+    );
+    assert_eq!(run(), first);
+}
+
+#[test]
+fn test_id_is_the_address_reprs_show() {
+    let source = "class A:\n    def f(self):\n        return 1\na = A()\nb = a\n[a, hex(id(b)), id(a) == id(A()), id(None) == id(None), id(7) - id(3 + 4)]";
+    let mut vm = VM::new();
+    vm.set_deterministic(true);
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(
+        vm.last_popped_stack_elem().to_string(),
+        "[<A object at 0x10>, '0x10', False, True, 0]"
+    );
+}
+
+#[test]
+fn test_profile_counts_instructions_per_opcode_and_function() {
+    let source =
+        "def fib(n):\n    if n < 2:\n        return n\n    return fib(n - 1) + fib(n - 2)\nfib(10)";
+    let mut vm = VM::new();
+    assert!(vm.profile().is_none());
+    vm.set_profiling(true);
+    for _ in 0..2 {
+        let chunk = Compiler::compile(source).expect("Expected chunk");
+        assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+        let profile = vm.profile().expect("profiling is on");
+
+        let calls: Vec<(String, u64)> = profile
+            .functions()
+            .into_iter()
+            .map(|function| (function.name, function.calls))
+            .collect();
+        assert_eq!(calls.len(), 2);
+        assert!(calls.contains(&("fib".to_string(), 177)));
+        assert!(calls.contains(&("<module>".to_string(), 1)));
+
+        let opcodes = profile.opcodes();
+        let count = |opcode: OpCode| {
+            opcodes
+                .iter()
+                .find(|(op, _)| *op == opcode)
+                .map_or(0, |(_, stats)| stats.instructions)
+        };
+        assert_eq!(count(OpCode::OpCall), 177);
+        assert_eq!(count(OpCode::OpAdd), 88);
+        let total: u64 = opcodes.iter().map(|(_, stats)| stats.instructions).sum();
+        assert_eq!(profile.total().instructions, total);
+
+        let table = profile.to_string();
+        assert!(table.starts_with(&format!("{} instructions in ", total)));
+        assert!(table.contains("\nOpCall "));
+        assert!(table.contains("\nfib "));
+    }
+
+    vm.set_profiling(false);
+    assert!(vm.profile().is_none());
+}
+
+#[test]
+fn test_hooks_report_lines_calls_and_returns() {
+    use oxython::sync::RefCell;
+
+    let source = "def add(a, b):\n    return a + b\ni = 0\nwhile i < 2:\n    i = add(i, 1)\nx = i";
+    let events: Rc<RefCell<Vec<String>>> = Rc::default();
+    let instructions = Rc::new(RefCell::new(0));
+    let mut vm = VM::new();
+    let log = events.clone();
+    vm.on_line(move |frame| {
+        log.borrow_mut()
+            .push(format!("line {} {}", frame.line.unwrap(), frame.function))
+    });
+    let log = events.clone();
+    vm.on_call(move |frame| {
+        log.borrow_mut()
+            .push(format!("call {} {}", frame.function, frame.depth))
+    });
+    let log = events.clone();
+    vm.on_return(move |frame, value| {
+        log.borrow_mut()
+            .push(format!("return {} {}", frame.function, value))
+    });
+    let count = instructions.clone();
+    vm.on_instruction(move |_, _| *count.borrow_mut() += 1);
+
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(
+        *events.borrow(),
+        [
+            "call <module> 1",
+            "line 1 <module>",
+            "line 3 <module>",
+            "line 4 <module>",
+            "line 5 <module>",
+            "call add 2",
+            "line 2 add",
+            "return add 1",
+            "line 4 <module>",
+            "line 5 <module>",
+            "call add 2",
+            "line 2 add",
+            "return add 2",
+            "line 4 <module>",
+            "line 6 <module>",
+            "return <module> None",
+        ]
+    );
+    assert!(*instructions.borrow() > events.borrow().len());
+
+    vm.clear_hooks();
+    events.borrow_mut().clear();
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert!(events.borrow().is_empty());
+}
+
+#[test]
+fn test_line_hook_reports_for_loop_header_each_iteration() {
+    use oxython::sync::RefCell;
+
+    let lines: Rc<RefCell<Vec<usize>>> = Rc::default();
+    let mut vm = VM::new();
+    let log = lines.clone();
+    vm.on_line(move |frame| log.borrow_mut().push(frame.line.unwrap()));
+    let chunk = Compiler::compile("total = 0\nfor x in [1, 2]:\n    total = total + x\ntotal")
+        .expect("Expected chunk");
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(*lines.borrow(), [1, 2, 2, 3, 2, 3, 2, 4]);
+}
+
+#[test]
+fn test_coverage_counts_executable_lines() {
+    use oxython::vm::coverage::Coverage;
+
+    let source = "def f(n):\n    if n > 5:\n        return 1\n    return 0\n\
+                  class A:\n    def m(self):\n        return 2\nf(1)\nf(2)";
+    let chunk = Compiler::compile(source).expect("Expected chunk");
+    let mut vm = VM::new();
+    let coverage = Coverage::attach(&mut vm, &chunk);
+    assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+    assert_eq!(
+        coverage.lines(),
+        [
+            (1, 1),
+            (2, 2),
+            (3, 0),
+            (4, 2),
+            (5, 1),
+            (6, 1),
+            (7, 0),
+            (8, 1),
+            (9, 1)
+        ]
+    );
+    assert_eq!(
+        coverage.lcov("/src/script.py"),
+        "TN:\nSF:/src/script.py\nDA:1,1\nDA:2,2\nDA:3,0\nDA:4,2\nDA:5,1\nDA:6,1\nDA:7,0\n\
+         DA:8,1\nDA:9,1\nLF:9\nLH:7\nend_of_record\n"
+    );
+}
+
+#[test]
+fn test_folded_constants_match_unfolded_results() {
+    // Profiling steps through every instruction, so the second run computes
+    // each `local op constant` without folding it
+    let source = r#"
+def check(n):
+    results = [n - 1, n + 2, n < 2, n <= 2, n > 2, n >= 2, n == 2]
+    if n < 2:
+        results.append("small")
+    while n > 0:
+        n = n - 1
+    results.append(n)
+    return results
+
+flag = True
+check(2) + check(1) + [flag + 1, 9223372036854775806 + 1]
+"#;
+    let mut results = Vec::new();
+    for profiling in [false, true] {
+        let chunk = Compiler::compile(source).expect("Expected chunk");
+        let mut vm = VM::new();
+        vm.set_profiling(profiling);
+        assert_eq!(vm.interpret(chunk), InterpretResult::Ok);
+        let value = vm
+            .peek_stack()
+            .unwrap_or_else(|| vm.last_popped_stack_elem());
+        results.push(value.to_string());
+    }
+    assert_eq!(results[0], results[1]);
+    assert_eq!(
+        results[0],
+        "[1, 4, False, True, False, True, True, 0, 0, 3, True, True, False, False, False, 'small', 0, 2, 9223372036854775807]"
+    );
+
+    let chunk = Compiler::compile("big = 9223372036854775807\nbig + 1").expect("Expected chunk");
+    let mut vm = VM::new();
+    assert_eq!(vm.interpret(chunk), InterpretResult::RuntimeError);
+}
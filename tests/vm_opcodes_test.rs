@@ -1,12 +1,15 @@
-use oxython::object::{ClassObject, ObjectType};
+use oxython::object::{ClassObject, DictEntries, NativeError, ObjectType};
+use oxython::sync::Rc;
 use oxython::vm::collections::{collect_iterable, slice_indices};
-use oxython::vm::native::native_super;
-use oxython::vm::opcodes::builtins::{op_round, op_to_list, op_zip};
+use oxython::vm::native::{
+    native_abs, native_divmod, native_len, native_pow, native_range, native_round, native_super,
+    native_zip,
+};
+use oxython::vm::opcodes::builtins::{op_round, op_to_list};
 use oxython::vm::opcodes::strings::{op_str_is_alnum, op_str_join, op_str_lower};
 use oxython::vm::values::is_truthy;
 use oxython::vm::InterpretResult;
 use std::collections::HashMap;
-use std::rc::Rc;
 
 // ============================================================================
 // String Operations Tests
@@ -129,6 +132,29 @@ fn test_op_str_join_handles_empty_string() {
     }
 }
 
+#[test]
+fn test_op_str_join_joins_dict_keys() {
+    let sep = Rc::new(ObjectType::String(", ".to_string()));
+    let dict = Rc::new(ObjectType::Dict(
+        vec![
+            (
+                Rc::new(ObjectType::String("b".to_string())),
+                Rc::new(ObjectType::Integer(1)),
+            ),
+            (
+                Rc::new(ObjectType::String("a".to_string())),
+                Rc::new(ObjectType::Integer(2)),
+            ),
+        ]
+        .into(),
+    ));
+    let result = op_str_join(sep, dict).unwrap();
+    match &*result {
+        ObjectType::String(joined) => assert_eq!(joined, "b, a"),
+        _ => panic!("Expected string"),
+    }
+}
+
 #[test]
 fn test_op_str_join_errors_on_list_with_non_string() {
     let sep = Rc::new(ObjectType::String(", ".to_string()));
@@ -181,37 +207,34 @@ fn test_is_truthy_true_is_truthy() {
 }
 
 #[test]
-fn test_is_truthy_integer_is_truthy() {
-    let zero = ObjectType::Integer(0);
-    assert!(is_truthy(&zero));
-    let positive = ObjectType::Integer(42);
-    assert!(is_truthy(&positive));
-    let negative = ObjectType::Integer(-1);
-    assert!(is_truthy(&negative));
-}
-
-#[test]
-fn test_is_truthy_float_is_truthy() {
-    let zero = ObjectType::Float(0.0);
-    assert!(is_truthy(&zero));
-    let positive = ObjectType::Float(3.14);
-    assert!(is_truthy(&positive));
+fn test_is_truthy_zero_is_falsy() {
+    assert!(!is_truthy(&ObjectType::Integer(0)));
+    assert!(is_truthy(&ObjectType::Integer(42)));
+    assert!(is_truthy(&ObjectType::Integer(-1)));
+    assert!(!is_truthy(&ObjectType::Float(0.0)));
+    assert!(!is_truthy(&ObjectType::Float(-0.0)));
+    assert!(is_truthy(&ObjectType::Float(2.5)));
 }
 
 #[test]
-fn test_is_truthy_string_is_truthy() {
+fn test_is_truthy_empty_string_is_falsy() {
     let empty = ObjectType::String("".to_string());
-    assert!(is_truthy(&empty));
+    assert!(!is_truthy(&empty));
     let non_empty = ObjectType::String("hello".to_string());
     assert!(is_truthy(&non_empty));
 }
 
 #[test]
-fn test_is_truthy_list_is_truthy() {
-    let empty = ObjectType::List(vec![]);
-    assert!(is_truthy(&empty));
-    let non_empty = ObjectType::List(vec![Rc::new(ObjectType::Integer(1))]);
-    assert!(is_truthy(&non_empty));
+fn test_is_truthy_empty_collections_are_falsy() {
+    let one = || Rc::new(ObjectType::Integer(1));
+    assert!(!is_truthy(&ObjectType::List(vec![])));
+    assert!(is_truthy(&ObjectType::List(vec![one()])));
+    assert!(!is_truthy(&ObjectType::Tuple(vec![])));
+    assert!(is_truthy(&ObjectType::Tuple(vec![one()])));
+    assert!(!is_truthy(&ObjectType::Dict(DictEntries::new())));
+    assert!(is_truthy(&ObjectType::Dict(vec![(one(), one())].into())));
+    assert!(!is_truthy(&ObjectType::Set(vec![])));
+    assert!(is_truthy(&ObjectType::Set(vec![one()])));
 }
 
 // ============================================================================
@@ -222,7 +245,10 @@ fn test_is_truthy_list_is_truthy() {
 fn test_native_super_errors_with_no_args() {
     let result = native_super(&[], None);
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), "super() requires access to self");
+    assert_eq!(
+        result.unwrap_err(),
+        NativeError::type_error("super() requires access to self")
+    );
 }
 
 #[test]
@@ -233,7 +259,7 @@ fn test_native_super_errors_with_too_many_args() {
     assert!(result.is_err());
     assert_eq!(
         result.unwrap_err(),
-        "super() takes at most 1 argument (self)"
+        NativeError::type_error("super() takes at most 1 argument (self)")
     );
 }
 
@@ -244,7 +270,7 @@ fn test_native_super_errors_without_class_context() {
     assert!(result.is_err());
     assert_eq!(
         result.unwrap_err(),
-        "super() can only be called inside a method"
+        NativeError::type_error("super() can only be called inside a method")
     );
 }
 
@@ -256,7 +282,7 @@ fn test_native_super_errors_with_no_parent_class() {
     assert!(result.is_err());
     assert_eq!(
         result.unwrap_err(),
-        "super() called in class with no parent"
+        NativeError::type_error("super() called in class with no parent")
     );
 }
 
@@ -269,7 +295,7 @@ fn test_native_super_returns_super_proxy_with_parent() {
         parent.clone(),
     ));
     let instance = Rc::new(ObjectType::Integer(42));
-    let result = native_super(&[instance.clone()], Some(child));
+    let result = native_super(std::slice::from_ref(&instance), Some(child));
     assert!(result.is_ok());
     match &*result.unwrap() {
         ObjectType::SuperProxy(inst, parent_class) => {
@@ -286,24 +312,26 @@ fn test_native_super_returns_super_proxy_with_parent() {
 
 #[test]
 fn test_op_round_rounds_float_to_decimal_places() {
-    let value = Rc::new(ObjectType::Float(3.14159));
+    let value = Rc::new(ObjectType::Float(1.23456));
     let digits = Rc::new(ObjectType::Integer(2));
     let result = op_round(value, digits).unwrap();
     match &*result {
-        ObjectType::Float(v) => assert!((v - 3.14).abs() < 1e-6),
+        ObjectType::Float(v) => assert!((v - 1.23).abs() < 1e-6),
         _ => panic!("Expected float"),
     }
 }
 
 #[test]
-fn test_op_round_rounds_integer_to_float() {
+fn test_op_round_keeps_integers_integral() {
     let value = Rc::new(ObjectType::Integer(42));
     let digits = Rc::new(ObjectType::Integer(2));
     let result = op_round(value, digits).unwrap();
-    match &*result {
-        ObjectType::Float(v) => assert!((v - 42.0).abs() < 1e-6),
-        _ => panic!("Expected float"),
-    }
+    assert_eq!(*result, ObjectType::Integer(42));
+
+    let value = Rc::new(ObjectType::Integer(1250));
+    let digits = Rc::new(ObjectType::Integer(-2));
+    let result = op_round(value, digits).unwrap();
+    assert_eq!(*result, ObjectType::Integer(1200));
 }
 
 #[test]
@@ -318,16 +346,11 @@ fn test_op_round_with_zero_digits() {
 }
 
 #[test]
-fn test_op_round_with_negative_digits_uses_zero() {
-    let value = Rc::new(ObjectType::Float(123.456));
+fn test_op_round_with_negative_digits_rounds_to_tens() {
+    let value = Rc::new(ObjectType::Float(153.456));
     let digits = Rc::new(ObjectType::Integer(-2));
     let result = op_round(value, digits).unwrap();
-    match &*result {
-        // negative digits are clamped to 0 via max(0), so factor = 10^0 = 1
-        // This rounds to nearest integer
-        ObjectType::Float(v) => assert!((v - 123.0).abs() < 1e-6),
-        _ => panic!("Expected float"),
-    }
+    assert_eq!(*result, ObjectType::Float(200.0));
 }
 
 #[test]
@@ -340,7 +363,7 @@ fn test_op_round_errors_on_non_numeric_value() {
 
 #[test]
 fn test_op_round_errors_on_non_integer_digits() {
-    let value = Rc::new(ObjectType::Float(3.14));
+    let value = Rc::new(ObjectType::Float(2.5));
     let digits = Rc::new(ObjectType::String("two".to_string()));
     let result = op_round(value, digits);
     assert_eq!(result, Err(InterpretResult::RuntimeError));
@@ -396,16 +419,14 @@ fn test_op_to_list_errors_on_non_iterable() {
 }
 
 #[test]
-fn test_op_zip_with_no_args_returns_empty_list() {
-    let result = op_zip(vec![], 0).unwrap();
-    match &*result {
-        ObjectType::List(values) => assert_eq!(values.len(), 0),
-        _ => panic!("Expected list"),
-    }
+fn test_native_zip_with_no_args_yields_nothing() {
+    let result = native_zip(&[], None).unwrap();
+    let values = collect_iterable(&result).expect("zip should be iterable");
+    assert!(values.is_empty());
 }
 
 #[test]
-fn test_op_zip_zips_two_lists() {
+fn test_native_zip_zips_two_lists() {
     let list1 = Rc::new(ObjectType::List(vec![
         Rc::new(ObjectType::Integer(1)),
         Rc::new(ObjectType::Integer(2)),
@@ -414,21 +435,17 @@ fn test_op_zip_zips_two_lists() {
         Rc::new(ObjectType::String("a".to_string())),
         Rc::new(ObjectType::String("b".to_string())),
     ]));
-    let result = op_zip(vec![list1, list2], 0).unwrap();
-    match &*result {
-        ObjectType::List(values) => {
-            assert_eq!(values.len(), 2);
-            match &*values[0] {
-                ObjectType::Tuple(items) => assert_eq!(items.len(), 2),
-                _ => panic!("Expected tuple"),
-            }
-        }
-        _ => panic!("Expected list"),
+    let result = native_zip(&[list1, list2], None).unwrap();
+    let values = collect_iterable(&result).expect("zip should be iterable");
+    assert_eq!(values.len(), 2);
+    match &*values[0] {
+        ObjectType::Tuple(items) => assert_eq!(items.len(), 2),
+        _ => panic!("Expected tuple"),
     }
 }
 
 #[test]
-fn test_op_zip_stops_at_shortest_iterable() {
+fn test_native_zip_stops_at_shortest_iterable() {
     let list1 = Rc::new(ObjectType::List(vec![
         Rc::new(ObjectType::Integer(1)),
         Rc::new(ObjectType::Integer(2)),
@@ -437,61 +454,185 @@ fn test_op_zip_stops_at_shortest_iterable() {
     let list2 = Rc::new(ObjectType::List(vec![Rc::new(ObjectType::String(
         "a".to_string(),
     ))]));
-    let result = op_zip(vec![list1, list2], 0).unwrap();
-    match &*result {
-        ObjectType::List(values) => assert_eq!(values.len(), 1),
-        _ => panic!("Expected list"),
-    }
+    let result = native_zip(&[list1, list2], None).unwrap();
+    let values = collect_iterable(&result).expect("zip should be iterable");
+    assert_eq!(values.len(), 1);
 }
 
 #[test]
-fn test_op_zip_with_star_unpacking() {
-    // Create a list of lists to be unpacked
-    let inner1 = Rc::new(ObjectType::List(vec![
+fn test_native_zip_accepts_strings_and_tuples() {
+    let text = Rc::new(ObjectType::String("ab".to_string()));
+    let tuple = Rc::new(ObjectType::Tuple(vec![
         Rc::new(ObjectType::Integer(1)),
         Rc::new(ObjectType::Integer(2)),
     ]));
-    let inner2 = Rc::new(ObjectType::List(vec![
-        Rc::new(ObjectType::Integer(3)),
-        Rc::new(ObjectType::Integer(4)),
-    ]));
-    let outer = Rc::new(ObjectType::List(vec![inner1, inner2]));
+    let result = native_zip(&[text, tuple], None).unwrap();
+    let values = collect_iterable(&result).expect("zip should be iterable");
+    assert_eq!(
+        *values[1],
+        ObjectType::Tuple(vec![
+            Rc::new(ObjectType::String("b".to_string())),
+            Rc::new(ObjectType::Integer(2)),
+        ])
+    );
+}
 
-    // star_mask = 1 means unpack the first argument
-    let result = op_zip(vec![outer], 1).unwrap();
-    match &*result {
-        ObjectType::List(values) => {
-            assert_eq!(values.len(), 2); // Shortest of the two inner lists
-            match &*values[0] {
-                ObjectType::Tuple(items) => assert_eq!(items.len(), 2), // Two lists unpacked
-                _ => panic!("Expected tuple"),
-            }
-        }
-        _ => panic!("Expected list"),
-    }
+#[test]
+fn test_native_zip_is_lazy_and_exhausts_once() {
+    let list = Rc::new(ObjectType::List(vec![Rc::new(ObjectType::Integer(1))]));
+    let result = native_zip(&[list], None).unwrap();
+    assert_eq!(result.type_name(), "zip");
+    assert_eq!(collect_iterable(&result).unwrap().len(), 1);
+    assert!(collect_iterable(&result).unwrap().is_empty());
 }
 
 #[test]
-fn test_op_zip_errors_on_non_iterable() {
+fn test_native_zip_errors_on_non_iterable() {
     let integer = Rc::new(ObjectType::Integer(42));
-    let result = op_zip(vec![integer], 0);
-    assert_eq!(result, Err(InterpretResult::RuntimeError));
+    let result = native_zip(&[integer], None);
+    assert_eq!(
+        result.unwrap_err(),
+        NativeError::type_error("zip argument #1 must support iteration")
+    );
+}
+
+#[test]
+fn test_native_len_counts_dict_entries() {
+    let dict = Rc::new(ObjectType::Dict(
+        vec![(
+            Rc::new(ObjectType::String("a".to_string())),
+            Rc::new(ObjectType::Integer(1)),
+        )]
+        .into(),
+    ));
+    let result = native_len(&[dict], None).unwrap();
+    assert_eq!(*result, ObjectType::Integer(1));
 }
 
 #[test]
-fn test_op_zip_errors_on_star_with_non_list() {
+fn test_native_len_errors_on_unsized_value() {
     let integer = Rc::new(ObjectType::Integer(42));
-    // star_mask = 1 means try to unpack the first argument
-    let result = op_zip(vec![integer], 1);
-    assert_eq!(result, Err(InterpretResult::RuntimeError));
+    assert_eq!(
+        native_len(&[integer], None).unwrap_err(),
+        NativeError::type_error("object of type 'int' has no len()")
+    );
+    assert!(native_len(&[], None).is_err());
 }
 
 #[test]
-fn test_op_zip_errors_on_star_with_non_iterable_items() {
-    let bad_list = Rc::new(ObjectType::List(vec![Rc::new(ObjectType::Integer(42))]));
-    // star_mask = 1 means unpack the first argument, but it contains non-iterables
-    let result = op_zip(vec![bad_list], 1);
-    assert_eq!(result, Err(InterpretResult::RuntimeError));
+fn test_native_round_single_argument_rounds_half_to_even() {
+    let result = native_round(&[Rc::new(ObjectType::Float(2.5))], None).unwrap();
+    assert_eq!(*result, ObjectType::Integer(2));
+    let result = native_round(&[Rc::new(ObjectType::Float(3.5))], None).unwrap();
+    assert_eq!(*result, ObjectType::Integer(4));
+}
+
+#[test]
+fn test_native_round_with_digits_matches_opcode() {
+    let args = [
+        Rc::new(ObjectType::Float(1.256)),
+        Rc::new(ObjectType::Integer(2)),
+    ];
+    let result = native_round(&args, None).unwrap();
+    assert_eq!(*result, ObjectType::Float(1.26));
+}
+
+#[test]
+fn test_native_pow_supports_two_and_three_arguments() {
+    let int = |value: i64| Rc::new(ObjectType::Integer(value));
+    let cases = [
+        (vec![int(2), int(10)], ObjectType::Integer(1024)),
+        (vec![int(2), int(-2)], ObjectType::Float(0.25)),
+        (
+            vec![Rc::new(ObjectType::Float(2.0)), int(3)],
+            ObjectType::Float(8.0),
+        ),
+        (vec![int(3), int(200), int(7)], ObjectType::Integer(2)),
+        (vec![int(3), int(-1), int(7)], ObjectType::Integer(5)),
+        (vec![int(-3), int(2), int(-7)], ObjectType::Integer(-5)),
+        (vec![int(2), int(10), int(-1000)], ObjectType::Integer(-976)),
+        (vec![int(5), int(3), int(1)], ObjectType::Integer(0)),
+        (
+            vec![int(i64::MAX), int(i64::MAX), int(i64::MAX - 1)],
+            ObjectType::Integer(1),
+        ),
+    ];
+    for (args, expected) in cases {
+        let result = native_pow(&args, None).unwrap();
+        assert_eq!(*result, expected, "{:?}", args);
+    }
+
+    let errors = [
+        (vec![int(0), int(-1)], "ZeroDivisionError"),
+        (vec![int(10), int(100)], "OverflowError"),
+        (vec![int(2), int(3), int(0)], "ValueError"),
+        (vec![int(2), int(-1), int(4)], "ValueError"),
+        (
+            vec![Rc::new(ObjectType::Float(2.0)), int(3), int(5)],
+            "TypeError",
+        ),
+        (
+            vec![
+                Rc::new(ObjectType::Float(-8.0)),
+                Rc::new(ObjectType::Float(0.5)),
+            ],
+            "ValueError",
+        ),
+        (vec![int(1)], "TypeError"),
+    ];
+    for (args, kind) in errors {
+        let error = native_pow(&args, None).unwrap_err();
+        assert_eq!(error.kind, kind, "{:?}", args);
+    }
+}
+
+#[test]
+fn test_native_abs_and_divmod() {
+    let result = native_abs(&[Rc::new(ObjectType::Integer(-3))], None).unwrap();
+    assert_eq!(*result, ObjectType::Integer(3));
+    let result = native_abs(&[Rc::new(ObjectType::Float(-2.5))], None).unwrap();
+    assert_eq!(*result, ObjectType::Float(2.5));
+    let error = native_abs(&[Rc::new(ObjectType::Integer(i64::MIN))], None).unwrap_err();
+    assert_eq!(error.kind, "OverflowError");
+    let error = native_abs(&[Rc::new(ObjectType::String("a".to_string()))], None).unwrap_err();
+    assert_eq!(error.message, "bad operand type for abs(): 'str'");
+
+    let args = [
+        Rc::new(ObjectType::Float(1.0)),
+        Rc::new(ObjectType::Integer(0)),
+    ];
+    let error = native_divmod(&args, None).unwrap_err();
+    assert_eq!(
+        error,
+        NativeError::new("ZeroDivisionError", "float divmod()")
+    );
+}
+
+#[test]
+fn test_native_range_supports_stop_and_step_forms() {
+    let result = native_range(&[Rc::new(ObjectType::Integer(3))], None).unwrap();
+    assert_eq!(format!("{}", result), "[0, 1, 2]");
+
+    let args = [
+        Rc::new(ObjectType::Integer(10)),
+        Rc::new(ObjectType::Integer(0)),
+        Rc::new(ObjectType::Integer(-3)),
+    ];
+    let result = native_range(&args, None).unwrap();
+    assert_eq!(format!("{}", result), "[10, 7, 4, 1]");
+}
+
+#[test]
+fn test_native_range_errors_on_zero_step() {
+    let args = [
+        Rc::new(ObjectType::Integer(0)),
+        Rc::new(ObjectType::Integer(5)),
+        Rc::new(ObjectType::Integer(0)),
+    ];
+    assert_eq!(
+        native_range(&args, None).unwrap_err(),
+        NativeError::value_error("range() arg 3 must not be zero")
+    );
 }
 
 // ============================================================================